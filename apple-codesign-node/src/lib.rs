@@ -0,0 +1,245 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Node.js bindings to App Store Connect workflows of `apple-codesign`.
+//!
+//! Functions mirror the corresponding `rcodesign` commands and are exported
+//! with camel case names. e.g. `uploadIpa()`. Long running operations
+//! (notarization and uploads) run on the libuv thread pool and return
+//! promises, so they don't block the event loop.
+
+use {
+    apple_codesign::{
+        app_store_connect::{
+            build_upload_api::Platform,
+            certs_api::{CertificateType, ProfileType, DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS},
+            AppStoreConnectClient, UnifiedApiKey,
+        },
+        redact::redact,
+        stapling::Stapler,
+        AppleCodesignError, NotarizationUpload, Notarizer,
+    },
+    napi::{bindgen_prelude::AsyncTask, Env, Error, Result, Task},
+    napi_derive::napi,
+    serde::{de::DeserializeOwned, Serialize},
+    std::{path::Path, time::Duration},
+};
+
+/// Convert an error, redacting secrets in its message.
+fn to_napi_err(e: AppleCodesignError) -> Error {
+    Error::from_reason(redact(&e.to_string()).to_string())
+}
+
+/// Parse an API enum value from its wire name. e.g. `IOS_APP_STORE`.
+fn parse_enum<T: DeserializeOwned + Serialize>(kind: &str, value: &str) -> Result<T> {
+    serde_json::from_value::<T>(serde_json::Value::String(value.to_string()))
+        .ok()
+        // Enums with a catch-all variant accept anything. Reject what doesn't
+        // serialize back to the input.
+        .filter(|v| {
+            serde_json::to_value(v)
+                .ok()
+                .as_ref()
+                .and_then(|v| v.as_str())
+                == Some(value)
+        })
+        .ok_or_else(|| Error::from_reason(format!("invalid {}: {}", kind, value)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+fn client(api_key_path: &str) -> std::result::Result<AppStoreConnectClient, AppleCodesignError> {
+    AppStoreConnectClient::new(UnifiedApiKey::from_json_path(api_key_path)?.try_into()?)
+}
+
+/// Notarizes a path on the libuv thread pool. See [notarize()].
+pub struct NotarizeTask {
+    api_key_path: String,
+    path: String,
+    wait_seconds: Option<u32>,
+    staple: bool,
+}
+
+impl Task for NotarizeTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let path = Path::new(&self.path);
+
+        let mut notarizer = Notarizer::new().map_err(to_napi_err)?;
+        notarizer.set_token_encoder(
+            UnifiedApiKey::from_json_path(&self.api_key_path)
+                .and_then(|key| key.try_into())
+                .map_err(to_napi_err)?,
+        );
+
+        let wait_limit = self
+            .wait_seconds
+            .or(if self.staple { Some(600) } else { None });
+
+        let id = match notarizer
+            .notarize_path(path, wait_limit.map(|v| Duration::from_secs(v as u64)))
+            .map_err(to_napi_err)?
+        {
+            NotarizationUpload::UploadId(id) => id,
+            NotarizationUpload::NotaryResponse(response) => response.data.id,
+        };
+
+        if self.staple {
+            Stapler::new()
+                .and_then(|stapler| stapler.staple_path(path))
+                .map_err(to_napi_err)?;
+        }
+
+        Ok(id)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Notarize a bundle, `.dmg`, or `.pkg`.
+///
+/// Resolves to the submission ID. If `wait_seconds` is given, waits for
+/// notarization to finish and rejects if it failed. If `staple` is true, the
+/// notarization ticket is stapled to `path` afterwards. Stapling requires
+/// waiting, for up to 600 seconds if `wait_seconds` isn't given.
+#[napi(ts_return_type = "Promise<string>")]
+pub fn notarize(
+    api_key_path: String,
+    path: String,
+    wait_seconds: Option<u32>,
+    staple: Option<bool>,
+) -> AsyncTask<NotarizeTask> {
+    AsyncTask::new(NotarizeTask {
+        api_key_path,
+        path,
+        wait_seconds,
+        staple: staple.unwrap_or_default(),
+    })
+}
+
+/// Uploads an `.ipa` on the libuv thread pool. See [upload_ipa()].
+pub struct UploadIpaTask {
+    api_key_path: String,
+    app_id: String,
+    path: String,
+    platform: String,
+}
+
+impl Task for UploadIpaTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let platform = parse_enum::<Platform>("platform", &self.platform)?;
+
+        client(&self.api_key_path)
+            .and_then(|client| client.upload_ipa(&self.app_id, Path::new(&self.path), platform))
+            .map(|upload| upload.id)
+            .map_err(to_napi_err)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Upload an `.ipa` file for an app.
+///
+/// `platform` is one of `IOS`, `MAC_OS`, `TV_OS`, or `VISION_OS`. Resolves to
+/// the ID of the build upload.
+#[napi(ts_return_type = "Promise<string>")]
+pub fn upload_ipa(
+    api_key_path: String,
+    app_id: String,
+    path: String,
+    platform: String,
+) -> AsyncTask<UploadIpaTask> {
+    AsyncTask::new(UploadIpaTask {
+        api_key_path,
+        app_id,
+        path,
+        platform,
+    })
+}
+
+/// List signing certificates. Returns a JSON array.
+#[napi]
+pub fn list_certificates(api_key_path: String) -> Result<String> {
+    to_json(
+        &client(&api_key_path)
+            .and_then(|client| client.list_certificates())
+            .map_err(to_napi_err)?,
+    )
+}
+
+/// Create a signing certificate from a PEM encoded certificate signing request.
+///
+/// `certificate_type` is an API certificate type, e.g. `DISTRIBUTION`.
+/// Returns the certificate as JSON.
+#[napi]
+pub fn create_certificate(
+    api_key_path: String,
+    csr_pem: String,
+    certificate_type: String,
+) -> Result<String> {
+    let certificate_type = parse_enum::<CertificateType>("certificate type", &certificate_type)?;
+
+    to_json(
+        &client(&api_key_path)
+            .and_then(|client| client.create_certificate(&csr_pem, certificate_type))
+            .map_err(to_napi_err)?,
+    )
+}
+
+/// Create a provisioning profile. Returns the profile as JSON.
+///
+/// `profile_type` is an API profile type, e.g. `IOS_APP_STORE`. `bundle_id_id`
+/// is the resource ID of the bundle ID.
+#[napi]
+pub fn create_profile(
+    api_key_path: String,
+    name: String,
+    profile_type: String,
+    bundle_id_id: String,
+    certificate_ids: Vec<String>,
+    device_ids: Option<Vec<String>>,
+) -> Result<String> {
+    let profile_type = parse_enum::<ProfileType>("profile type", &profile_type)?;
+
+    to_json(
+        &client(&api_key_path)
+            .and_then(|client| {
+                client.create_profile(
+                    &name,
+                    profile_type,
+                    &bundle_id_id,
+                    &certificate_ids,
+                    &device_ids.unwrap_or_default(),
+                )
+            })
+            .map_err(to_napi_err)?,
+    )
+}
+
+/// Write the `.mobileprovision` file of a provisioning profile to `output_path`.
+#[napi]
+pub fn download_profile(
+    api_key_path: String,
+    profile_id: String,
+    output_path: String,
+) -> Result<()> {
+    let profile = client(&api_key_path)
+        .and_then(|client| {
+            client.download_profile(&profile_id, DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS)
+        })
+        .map_err(to_napi_err)?;
+
+    std::fs::write(&output_path, profile.data).map_err(|e| to_napi_err(e.into()))
+}
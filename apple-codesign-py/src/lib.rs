@@ -0,0 +1,203 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Python bindings to App Store Connect workflows of `apple-codesign`.
+//!
+//! The Python module is named `apple_codesign`. Functions mirror the
+//! corresponding `rcodesign` commands. Long running functions release the GIL.
+
+use {
+    apple_codesign::{
+        app_store_connect::{
+            build_upload_api::Platform,
+            certs_api::{CertificateType, ProfileType, DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS},
+            AppStoreConnectClient, UnifiedApiKey,
+        },
+        redact::redact,
+        stapling::Stapler,
+        AppleCodesignError, NotarizationUpload, Notarizer,
+    },
+    pyo3::{exceptions::PyRuntimeError, prelude::*},
+    serde::{de::DeserializeOwned, Serialize},
+    std::{path::PathBuf, time::Duration},
+};
+
+/// Run a function without holding the GIL, converting its error.
+///
+/// Secrets in error messages are redacted.
+fn run<T: Send>(
+    py: Python,
+    f: impl FnOnce() -> Result<T, AppleCodesignError> + Send,
+) -> PyResult<T> {
+    py.allow_threads(|| f().map_err(|e| redact(&e.to_string()).to_string()))
+        .map_err(PyRuntimeError::new_err)
+}
+
+/// Parse an API enum value from its wire name. e.g. `IOS_APP_STORE`.
+fn parse_enum<T: DeserializeOwned + Serialize>(kind: &str, value: &str) -> PyResult<T> {
+    serde_json::from_value::<T>(serde_json::Value::String(value.to_string()))
+        .ok()
+        // Enums with a catch-all variant accept anything. Reject what doesn't
+        // serialize back to the input.
+        .filter(|v| {
+            serde_json::to_value(v)
+                .ok()
+                .as_ref()
+                .and_then(|v| v.as_str())
+                == Some(value)
+        })
+        .ok_or_else(|| PyRuntimeError::new_err(format!("invalid {}: {}", kind, value)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> PyResult<String> {
+    serde_json::to_string(value).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+fn client(api_key_path: &str) -> Result<AppStoreConnectClient, AppleCodesignError> {
+    AppStoreConnectClient::new(UnifiedApiKey::from_json_path(api_key_path)?.try_into()?)
+}
+
+/// Notarize a bundle, `.dmg`, or `.pkg`.
+///
+/// Returns the submission ID. If `wait_seconds` is given, waits for
+/// notarization to finish and raises if it failed. If `staple` is true, the
+/// notarization ticket is stapled to `path` afterwards. Stapling requires
+/// waiting, for up to 600 seconds if `wait_seconds` isn't given.
+#[pyfunction]
+#[pyo3(signature = (api_key_path, path, wait_seconds = None, staple = false))]
+fn notarize(
+    py: Python,
+    api_key_path: &str,
+    path: PathBuf,
+    wait_seconds: Option<u64>,
+    staple: bool,
+) -> PyResult<String> {
+    run(py, || {
+        let mut notarizer = Notarizer::new()?;
+        notarizer.set_token_encoder(UnifiedApiKey::from_json_path(api_key_path)?.try_into()?);
+
+        let wait_limit = wait_seconds.or(if staple { Some(600) } else { None });
+
+        let id = match notarizer.notarize_path(&path, wait_limit.map(Duration::from_secs))? {
+            NotarizationUpload::UploadId(id) => id,
+            NotarizationUpload::NotaryResponse(response) => response.data.id,
+        };
+
+        if staple {
+            Stapler::new()?.staple_path(&path)?;
+        }
+
+        Ok(id)
+    })
+}
+
+/// Upload an `.ipa` file for an app.
+///
+/// `platform` is one of `IOS`, `MAC_OS`, `TV_OS`, or `VISION_OS`. Returns the
+/// ID of the build upload.
+#[pyfunction]
+fn upload_ipa(
+    py: Python,
+    api_key_path: &str,
+    app_id: &str,
+    path: PathBuf,
+    platform: &str,
+) -> PyResult<String> {
+    let platform = parse_enum::<Platform>("platform", platform)?;
+
+    run(py, || {
+        Ok(client(api_key_path)?
+            .upload_ipa(app_id, &path, platform)?
+            .id)
+    })
+}
+
+/// List signing certificates. Returns a JSON array.
+#[pyfunction]
+fn list_certificates(py: Python, api_key_path: &str) -> PyResult<String> {
+    let certificates = run(py, || client(api_key_path)?.list_certificates())?;
+
+    to_json(&certificates)
+}
+
+/// Create a signing certificate from a PEM encoded certificate signing request.
+///
+/// `certificate_type` is an API certificate type, e.g. `DISTRIBUTION`.
+/// Returns the certificate as JSON.
+#[pyfunction]
+fn create_certificate(
+    py: Python,
+    api_key_path: &str,
+    csr_pem: &str,
+    certificate_type: &str,
+) -> PyResult<String> {
+    let certificate_type = parse_enum::<CertificateType>("certificate type", certificate_type)?;
+
+    let certificate = run(py, || {
+        client(api_key_path)?.create_certificate(csr_pem, certificate_type)
+    })?;
+
+    to_json(&certificate)
+}
+
+/// Create a provisioning profile. Returns the profile as JSON.
+///
+/// `profile_type` is an API profile type, e.g. `IOS_APP_STORE`. `bundle_id_id`
+/// is the resource ID of the bundle ID.
+#[pyfunction]
+#[pyo3(signature = (api_key_path, name, profile_type, bundle_id_id, certificate_ids, device_ids = vec![]))]
+fn create_profile(
+    py: Python,
+    api_key_path: &str,
+    name: &str,
+    profile_type: &str,
+    bundle_id_id: &str,
+    certificate_ids: Vec<String>,
+    device_ids: Vec<String>,
+) -> PyResult<String> {
+    let profile_type = parse_enum::<ProfileType>("profile type", profile_type)?;
+
+    let profile = run(py, || {
+        client(api_key_path)?.create_profile(
+            name,
+            profile_type,
+            bundle_id_id,
+            &certificate_ids,
+            &device_ids,
+        )
+    })?;
+
+    to_json(&profile)
+}
+
+/// Write the `.mobileprovision` file of a provisioning profile to `output_path`.
+#[pyfunction]
+fn download_profile(
+    py: Python,
+    api_key_path: &str,
+    profile_id: &str,
+    output_path: PathBuf,
+) -> PyResult<()> {
+    run(py, || {
+        let profile = client(api_key_path)?
+            .download_profile(profile_id, DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS)?;
+
+        std::fs::write(&output_path, profile.data)?;
+
+        Ok(())
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "apple_codesign")]
+fn apple_codesign_py(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(notarize, m)?)?;
+    m.add_function(wrap_pyfunction!(upload_ipa, m)?)?;
+    m.add_function(wrap_pyfunction!(list_certificates, m)?)?;
+    m.add_function(wrap_pyfunction!(create_certificate, m)?)?;
+    m.add_function(wrap_pyfunction!(create_profile, m)?)?;
+    m.add_function(wrap_pyfunction!(download_profile, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#ifndef APPLE_CODESIGN_H
+#define APPLE_CODESIGN_H
+
+/* Generated by cbindgen. Do not edit. */
+
+#include <stdint.h>
+
+/**
+ * Result of a C API call.
+ */
+typedef enum AppleCodesignStatus {
+  /**
+   * The call succeeded.
+   */
+  APPLE_CODESIGN_STATUS_OK = 0,
+  /**
+   * An argument was NULL or not valid UTF-8.
+   */
+  APPLE_CODESIGN_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The operation failed.
+   */
+  APPLE_CODESIGN_STATUS_ERROR = 2,
+  /**
+   * The operation panicked.
+   */
+  APPLE_CODESIGN_STATUS_PANIC = 3,
+} AppleCodesignStatus;
+
+/**
+ * An App Store Connect client created by [apple_codesign_client_new()].
+ */
+typedef struct AppleCodesignClient AppleCodesignClient;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Obtain a description of the last error on the calling thread.
+ *
+ * Returns NULL if the last call on the calling thread succeeded. The string is
+ * owned by the library and valid until the next call on the same thread.
+ */
+const char *apple_codesign_last_error(void);
+
+/**
+ * Release a string returned by this library.
+ *
+ * # Safety
+ *
+ * `s` must be NULL or a string returned through an out parameter of this
+ * library that hasn't been released yet.
+ */
+void apple_codesign_string_free(char *s);
+
+/**
+ * Create a client from the content of a unified API key JSON file.
+ *
+ * Such files are produced by `rcodesign encode-app-store-connect-api-key`.
+ * On success, the client is stored in `client_out` and must be released with
+ * [apple_codesign_client_free()].
+ *
+ * # Safety
+ *
+ * `api_key_json` must be a NUL terminated string. `client_out` must point to
+ * writable memory for a pointer.
+ */
+AppleCodesignStatus apple_codesign_client_new(const char *api_key_json,
+                                              AppleCodesignClient **client_out);
+
+/**
+ * Release a client created by [apple_codesign_client_new()].
+ *
+ * # Safety
+ *
+ * `client` must be NULL or a client that hasn't been released yet.
+ */
+void apple_codesign_client_free(AppleCodesignClient *client);
+
+/**
+ * Upload an `.ipa` file for an app.
+ *
+ * `platform` is one of `IOS`, `MAC_OS`, `TV_OS`, or `VISION_OS`. On success,
+ * the ID of the build upload is stored in `upload_id_out`, if not NULL.
+ *
+ * # Safety
+ *
+ * String arguments must be NUL terminated strings. `upload_id_out` must be
+ * NULL or point to writable memory for a pointer.
+ */
+AppleCodesignStatus apple_codesign_upload_ipa(const AppleCodesignClient *client,
+                                              const char *app_id,
+                                              const char *ipa_path,
+                                              const char *platform,
+                                              char **upload_id_out);
+
+/**
+ * Notarize a bundle, `.dmg`, or `.pkg`.
+ *
+ * If `wait_seconds` is non-zero, waits up to that long for notarization to
+ * finish and fails if notarization failed. On success, the ID of the
+ * submission is stored in `submission_id_out`, if not NULL.
+ *
+ * # Safety
+ *
+ * `path` must be a NUL terminated string. `submission_id_out` must be NULL or
+ * point to writable memory for a pointer.
+ */
+AppleCodesignStatus apple_codesign_notarize_path(const AppleCodesignClient *client,
+                                                 const char *path,
+                                                 uint64_t wait_seconds,
+                                                 char **submission_id_out);
+
+/**
+ * Write the `.mobileprovision` file of a provisioning profile to `output_path`.
+ *
+ * # Safety
+ *
+ * String arguments must be NUL terminated strings.
+ */
+AppleCodesignStatus apple_codesign_fetch_profile(const AppleCodesignClient *client,
+                                                 const char *profile_id,
+                                                 const char *output_path);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* APPLE_CODESIGN_H */
@@ -0,0 +1,27 @@
+cli-error = Fehler: { $message }
+cli-hint = Hinweis: { $hint }
+
+batch-summary = { $succeeded } erfolgreich, { $failed } fehlgeschlagen
+
+prompt-api-key-password = Bitte das Passwort des API-Schlüssels eingeben
+prompt-p12-password = Bitte das Passwort der p12-Datei eingeben
+prompt-p12-password-confirm = Bitte das Passwort bestätigen
+prompt-p12-password-mismatch = Die Passwörter stimmen nicht überein
+prompt-device-pin = Bitte die PIN des Geräts eingeben
+prompt-confirm = Fortfahren?
+confirm-declined = abgebrochen
+confirm-unavailable = Bestätigung erforderlich, aber kein Terminal für die Abfrage vorhanden; zum Fortfahren --yes angeben
+
+## Hinweise zu Fehlern der App Store Connect API.
+
+hint-duplicate-build-number = ein Build mit dieser Build-Nummer wurde bereits hochgeladen; CFBundleVersion erhöhen und erneut hochladen
+hint-device-limit = das Team hat das jährliche Gerätelimit für diese Plattform erreicht; deaktivierte Geräte geben ihren Platz erst bei der Verlängerung der Mitgliedschaft frei
+hint-submit-for-review = die Version kann noch nicht eingereicht werden; prüfen, ob die erforderlichen Metadaten, Bildschirmfotos und ein verarbeiteter Build zugeordnet sind
+hint-duplicate-attribute = eine Ressource mit diesem Wert existiert bereits; die vorhandene Ressource verwenden, statt eine neue anzulegen
+hint-invalid-attribute = ein Attributwert wurde abgelehnt; das in der Fehlerquelle genannte Attribut prüfen
+hint-invalid-relationship = eine referenzierte Ressource existiert nicht oder hat den falschen Typ; die übergebenen IDs prüfen
+hint-not-found = die Ressource existiert nicht oder ist für diesen API-Schlüssel nicht sichtbar
+hint-not-authorized = der API-Schlüssel wurde abgelehnt; Issuer-ID, Schlüssel-ID und privaten Schlüssel prüfen und sicherstellen, dass der Schlüssel nicht widerrufen wurde
+hint-forbidden = die Rolle des API-Schlüssels erlaubt diesen Vorgang nicht; einen Schlüssel mit der Rolle „Admin“ oder „App Manager“ verwenden
+hint-rate-limit = das stündliche Anfragelimit des API-Schlüssels wurde überschritten; warten und erneut versuchen
+hint-state-error = die Ressource befindet sich in einem Zustand, der diesen Vorgang nicht erlaubt; ihren Zustand in App Store Connect prüfen
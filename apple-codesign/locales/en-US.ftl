@@ -0,0 +1,33 @@
+# Messages of the rcodesign command line interface.
+#
+# Every message must be defined here. Other locales may omit messages, which
+# then fall back to the English message.
+
+cli-error = Error: { $message }
+cli-hint = hint: { $hint }
+
+batch-summary = { $succeeded } succeeded, { $failed } failed
+
+prompt-api-key-password = Please enter password for the API Key
+prompt-p12-password = Please enter password for p12 file
+prompt-p12-password-confirm = Please confirm the password
+prompt-p12-password-mismatch = Passwords do not match
+prompt-device-pin = Please enter device PIN
+prompt-confirm = Proceed?
+confirm-declined = aborted
+confirm-unavailable = confirmation required but no terminal to ask on; pass --yes to proceed
+
+## Remediation hints for App Store Connect API errors. These must match the
+## English text in src/app_store_connect/api_error.rs.
+
+hint-duplicate-build-number = a build with this build number was already uploaded; increment CFBundleVersion and upload again
+hint-device-limit = the team reached the yearly device limit for this platform; disabled devices only free their slot when the membership renews
+hint-submit-for-review = the version can't be submitted yet; check that required metadata, screenshots, and a processed build are attached
+hint-duplicate-attribute = a resource with this value already exists; use the existing resource instead of creating a new one
+hint-invalid-attribute = an attribute value was rejected; check the attribute named in the error source
+hint-invalid-relationship = a referenced resource doesn't exist or has the wrong type; check the IDs passed
+hint-not-found = the resource doesn't exist or isn't visible to this API key
+hint-not-authorized = the API key was rejected; check the issuer ID, key ID, and private key, and that the key wasn't revoked
+hint-forbidden = the role of the API key doesn't allow this operation; use a key with the Admin or App Manager role
+hint-rate-limit = the hourly request limit of the API key was exceeded; wait and try again
+hint-state-error = the resource is in a state that doesn't allow this operation; check its state in App Store Connect
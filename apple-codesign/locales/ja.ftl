@@ -0,0 +1,27 @@
+cli-error = エラー: { $message }
+cli-hint = ヒント: { $hint }
+
+batch-summary = 成功 { $succeeded } 件、失敗 { $failed } 件
+
+prompt-api-key-password = API キーのパスワードを入力してください
+prompt-p12-password = p12 ファイルのパスワードを入力してください
+prompt-p12-password-confirm = パスワードを再入力してください
+prompt-p12-password-mismatch = パスワードが一致しません
+prompt-device-pin = デバイスの PIN を入力してください
+prompt-confirm = 続行しますか?
+confirm-declined = 中止しました
+confirm-unavailable = 確認が必要ですが、確認できる端末がありません。続行するには --yes を指定してください
+
+## App Store Connect API エラーの対処方法。
+
+hint-duplicate-build-number = このビルド番号のビルドは既にアップロードされています。CFBundleVersion を増やして再度アップロードしてください
+hint-device-limit = チームはこのプラットフォームの年間デバイス登録上限に達しています。無効にしたデバイスの枠はメンバーシップの更新時にのみ解放されます
+hint-submit-for-review = このバージョンはまだ提出できません。必要なメタデータ、スクリーンショット、処理済みのビルドが設定されているか確認してください
+hint-duplicate-attribute = この値のリソースは既に存在します。新しく作成せずに既存のリソースを使用してください
+hint-invalid-attribute = 属性の値が拒否されました。エラーの source に示された属性を確認してください
+hint-invalid-relationship = 参照先のリソースが存在しないか、種類が正しくありません。指定した ID を確認してください
+hint-not-found = リソースが存在しないか、この API キーからは参照できません
+hint-not-authorized = API キーが拒否されました。発行者 ID、キー ID、秘密鍵を確認し、キーが失効していないことを確認してください
+hint-forbidden = API キーのロールではこの操作は許可されていません。Admin または App Manager ロールのキーを使用してください
+hint-rate-limit = API キーの 1 時間あたりのリクエスト上限を超えました。しばらく待ってから再試行してください
+hint-state-error = リソースがこの操作を許可しない状態です。App Store Connect で状態を確認してください
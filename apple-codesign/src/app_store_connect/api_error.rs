@@ -0,0 +1,463 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Errors returned by the App Store Connect API.
+//!
+//! Failed requests are answered with an [ErrorDocument] holding one or more
+//! errors with machine-readable codes. Codes are often terse, so common codes
+//! are mapped to hints on how to resolve them.
+//!
+//! Codes are hierarchical, e.g. `ENTITY_ERROR.ATTRIBUTE.INVALID`. Callers
+//! handling errors programmatically match on the [ErrorKind] of an
+//! [ApiError] or test for a code with [ApiError::has_code()] rather than
+//! comparing code strings.
+
+use {
+    crate::app_store_connect::json_api::{ErrorDocument, ErrorObject},
+    std::fmt::{Display, Formatter},
+};
+
+/// A hint on how to resolve an error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Hint {
+    /// Stable identifier of the hint, used to look up translations.
+    pub id: &'static str,
+
+    /// The hint in English.
+    pub text: &'static str,
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+/// A remediation hint for errors matching a code and detail.
+pub(crate) struct Remediation {
+    /// Code the error code must equal or be a more specific form of.
+    ///
+    /// Empty matches every code.
+    pub(crate) code: &'static str,
+
+    /// Text the error detail must contain, ignoring case.
+    pub(crate) detail: Option<&'static str>,
+
+    pub(crate) hint: Hint,
+}
+
+/// Known remediations. The first matching entry is used, so more specific
+/// entries come first.
+pub(crate) const REMEDIATIONS: &[Remediation] = &[
+    Remediation {
+        code: "",
+        detail: Some("bundle version must be higher"),
+        hint: Hint {
+            id: "hint-duplicate-build-number",
+            text: "a build with this build number was already uploaded; increment CFBundleVersion and upload again",
+        },
+    },
+    Remediation {
+        code: "",
+        detail: Some("maximum number of"),
+        hint: Hint {
+            id: "hint-device-limit",
+            text: "the team reached the yearly device limit for this platform; disabled devices only free their slot when the membership renews",
+        },
+    },
+    Remediation {
+        code: "STATE_ERROR.SUBMIT_FOR_REVIEW",
+        detail: None,
+        hint: Hint {
+            id: "hint-submit-for-review",
+            text: "the version can't be submitted yet; check that required metadata, screenshots, and a processed build are attached",
+        },
+    },
+    Remediation {
+        code: "ENTITY_ERROR.ATTRIBUTE.INVALID.DUPLICATE",
+        detail: None,
+        hint: Hint {
+            id: "hint-duplicate-attribute",
+            text: "a resource with this value already exists; use the existing resource instead of creating a new one",
+        },
+    },
+    Remediation {
+        code: "ENTITY_ERROR.ATTRIBUTE.INVALID",
+        detail: None,
+        hint: Hint {
+            id: "hint-invalid-attribute",
+            text: "an attribute value was rejected; check the attribute named in the error source",
+        },
+    },
+    Remediation {
+        code: "ENTITY_ERROR.RELATIONSHIP.INVALID",
+        detail: None,
+        hint: Hint {
+            id: "hint-invalid-relationship",
+            text: "a referenced resource doesn't exist or has the wrong type; check the IDs passed",
+        },
+    },
+    Remediation {
+        code: "ENTITY_ERROR.NOT_FOUND",
+        detail: None,
+        hint: Hint {
+            id: "hint-not-found",
+            text: "the resource doesn't exist or isn't visible to this API key",
+        },
+    },
+    Remediation {
+        code: "NOT_AUTHORIZED",
+        detail: None,
+        hint: Hint {
+            id: "hint-not-authorized",
+            text: "the API key was rejected; check the issuer ID, key ID, and private key, and that the key wasn't revoked",
+        },
+    },
+    Remediation {
+        code: "FORBIDDEN_ERROR",
+        detail: None,
+        hint: Hint {
+            id: "hint-forbidden",
+            text: "the role of the API key doesn't allow this operation; use a key with the Admin or App Manager role",
+        },
+    },
+    Remediation {
+        code: "RATE_LIMIT_EXCEEDED",
+        detail: None,
+        hint: Hint {
+            id: "hint-rate-limit",
+            text: "the hourly request limit of the API key was exceeded; wait and try again",
+        },
+    },
+    Remediation {
+        code: "STATE_ERROR",
+        detail: None,
+        hint: Hint {
+            id: "hint-state-error",
+            text: "the resource is in a state that doesn't allow this operation; check its state in App Store Connect",
+        },
+    },
+];
+
+/// Whether an error code equals `prefix` or is a more specific form of it.
+///
+/// `ENTITY_ERROR.ATTRIBUTE.INVALID` matches `ENTITY_ERROR` but
+/// `STATE_ERROR_OTHER` doesn't match `STATE_ERROR`. An empty prefix matches
+/// every code.
+pub fn code_matches(code: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || code == prefix
+        || code
+            .strip_prefix(prefix)
+            .map(|rest| rest.starts_with('.'))
+            .unwrap_or_default()
+}
+
+/// Resolve the remediation hint for an error code and detail.
+pub fn remediation_hint(code: &str, detail: &str) -> Option<Hint> {
+    let detail = detail.to_lowercase();
+
+    REMEDIATIONS
+        .iter()
+        .find(|r| {
+            code_matches(code, r.code) && r.detail.map(|d| detail.contains(d)).unwrap_or(true)
+        })
+        .map(|r| r.hint)
+}
+
+/// The category of an error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// A query parameter was invalid (`PARAMETER_ERROR`).
+    ParameterError,
+
+    /// The request document was invalid (`ENTITY_ERROR`).
+    EntityError,
+
+    /// The API Key was rejected (`NOT_AUTHORIZED`).
+    NotAuthorized,
+
+    /// The API Key may not perform the operation (`FORBIDDEN_ERROR`,
+    /// `FORBIDDEN.*`).
+    Forbidden,
+
+    /// The resource doesn't exist (`NOT_FOUND`, `ENTITY_ERROR.NOT_FOUND`,
+    /// `PATH_ERROR`).
+    NotFound,
+
+    /// The request conflicts with the state of a resource (`STATE_ERROR`).
+    StateError,
+
+    /// Too many requests were made with the API Key (`RATE_LIMIT_EXCEEDED`).
+    RateLimitExceeded,
+
+    /// The server failed (`UNEXPECTED_ERROR`).
+    ServerError,
+
+    /// A code or status not covered by another kind.
+    Other,
+}
+
+impl ErrorKind {
+    /// The kind of an error code.
+    ///
+    /// `ENTITY_ERROR.NOT_FOUND` is [Self::NotFound], not [Self::EntityError].
+    pub fn from_code(code: &str) -> Self {
+        if code_matches(code, "ENTITY_ERROR.NOT_FOUND")
+            || code_matches(code, "NOT_FOUND")
+            || code_matches(code, "PATH_ERROR")
+        {
+            Self::NotFound
+        } else if code_matches(code, "ENTITY_ERROR") {
+            Self::EntityError
+        } else if code_matches(code, "PARAMETER_ERROR") {
+            Self::ParameterError
+        } else if code_matches(code, "NOT_AUTHORIZED") {
+            Self::NotAuthorized
+        } else if code_matches(code, "FORBIDDEN_ERROR") || code_matches(code, "FORBIDDEN") {
+            Self::Forbidden
+        } else if code_matches(code, "STATE_ERROR") {
+            Self::StateError
+        } else if code_matches(code, "RATE_LIMIT_EXCEEDED") {
+            Self::RateLimitExceeded
+        } else if code_matches(code, "UNEXPECTED_ERROR") {
+            Self::ServerError
+        } else {
+            Self::Other
+        }
+    }
+
+    /// The kind of an HTTP status of a response without error codes.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            400 => Self::ParameterError,
+            401 => Self::NotAuthorized,
+            403 => Self::Forbidden,
+            404 => Self::NotFound,
+            409 => Self::StateError,
+            422 => Self::EntityError,
+            429 => Self::RateLimitExceeded,
+            500..=599 => Self::ServerError,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// An unsuccessful response from the App Store Connect API.
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    /// The HTTP status code of the response.
+    pub status: u16,
+
+    /// The errors of the response.
+    pub errors: Vec<ErrorObject>,
+}
+
+impl ApiError {
+    pub fn new(status: u16, document: ErrorDocument) -> Self {
+        Self {
+            status,
+            errors: document.errors,
+        }
+    }
+
+    /// Construct an instance for a response whose body isn't an [ErrorDocument].
+    pub fn from_status(status: u16) -> Self {
+        Self {
+            status,
+            errors: vec![],
+        }
+    }
+
+    /// The kind of the first error, or of the HTTP status if there are no errors.
+    pub fn kind(&self) -> ErrorKind {
+        match self.errors.first() {
+            Some(error) => ErrorKind::from_code(&error.code),
+            None => ErrorKind::from_status(self.status),
+        }
+    }
+
+    /// Whether any error is of a kind.
+    ///
+    /// Without errors, the kind of the HTTP status is tested.
+    pub fn is(&self, kind: ErrorKind) -> bool {
+        if self.errors.is_empty() {
+            ErrorKind::from_status(self.status) == kind
+        } else {
+            self.errors
+                .iter()
+                .any(|e| ErrorKind::from_code(&e.code) == kind)
+        }
+    }
+
+    /// Whether any error has a code equal to or more specific than `code`.
+    ///
+    /// See [code_matches()].
+    pub fn has_code(&self, code: &str) -> bool {
+        self.errors.iter().any(|e| code_matches(&e.code, code))
+    }
+
+    /// Remediation hints for the errors, without duplicates.
+    pub fn hints(&self) -> Vec<Hint> {
+        let mut hints = vec![];
+
+        for hint in self
+            .errors
+            .iter()
+            .filter_map(|e| remediation_hint(&e.code, &e.detail))
+        {
+            if !hints.contains(&hint) {
+                hints.push(hint);
+            }
+        }
+
+        hints
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {}", self.status)?;
+
+        if self.errors.is_empty() {
+            if let Some(reason) = reqwest::StatusCode::from_u16(self.status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+            {
+                write!(f, " {}", reason)?;
+            }
+        }
+
+        for error in &self.errors {
+            write!(f, "; {}: {}", error.code, error.detail)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            app_store_connect::testing::{fixtures, MockResponse, MockServerBuilder},
+            AppleCodesignError,
+        },
+        serde_json::json,
+    };
+
+    #[test]
+    fn hint_lookup() {
+        assert_eq!(
+            remediation_hint("ENTITY_ERROR.ATTRIBUTE.INVALID.DUPLICATE", ""),
+            Some(REMEDIATIONS[3].hint)
+        );
+        assert_eq!(
+            remediation_hint("ENTITY_ERROR.ATTRIBUTE.INVALID.TOO_LONG", ""),
+            Some(REMEDIATIONS[4].hint)
+        );
+        assert_eq!(
+            remediation_hint(
+                "ENTITY_ERROR.ATTRIBUTE.INVALID",
+                "The bundle version must be higher than the previously uploaded version."
+            ),
+            Some(REMEDIATIONS[0].hint)
+        );
+        assert_eq!(
+            remediation_hint("STATE_ERROR_OTHER", ""),
+            None,
+            "prefixes only match whole components"
+        );
+        assert_eq!(remediation_hint("UNKNOWN", ""), None);
+    }
+
+    #[test]
+    fn typed_error() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                403,
+                fixtures::error(403, "FORBIDDEN_ERROR", "not allowed"),
+            )
+            .start()?;
+
+        match server.client()?.list_apps() {
+            Err(AppleCodesignError::AppStoreConnectApi(e)) => {
+                assert_eq!(e.status, 403);
+                assert_eq!(e.errors[0].code, "FORBIDDEN_ERROR");
+                assert_eq!(e.hints(), vec![REMEDIATIONS[8].hint]);
+                assert_eq!(e.kind(), ErrorKind::Forbidden);
+                assert!(e.has_code("FORBIDDEN_ERROR"));
+            }
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_kinds() -> Result<(), AppleCodesignError> {
+        assert_eq!(
+            ErrorKind::from_code("ENTITY_ERROR.ATTRIBUTE.INVALID"),
+            ErrorKind::EntityError
+        );
+        assert_eq!(
+            ErrorKind::from_code("ENTITY_ERROR.NOT_FOUND"),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            ErrorKind::from_code("FORBIDDEN.REQUIRED_AGREEMENTS_MISSING_OR_EXPIRED"),
+            ErrorKind::Forbidden
+        );
+        assert_eq!(
+            ErrorKind::from_code("PARAMETER_ERROR.INVALID"),
+            ErrorKind::ParameterError
+        );
+        assert_eq!(ErrorKind::from_code("STATE_ERROR_OTHER"), ErrorKind::Other);
+
+        let server = MockServerBuilder::default()
+            .json(
+                "POST",
+                "/v1/devices",
+                409,
+                json!({"errors": [
+                    {"status": "409", "code": "ENTITY_ERROR.ATTRIBUTE.INVALID", "title": "", "detail": "bad udid",
+                     "source": {"pointer": "/data/attributes/udid"}},
+                    {"status": "409", "code": "STATE_ERROR", "title": "", "detail": "busy"},
+                ]}),
+            )
+            .route("GET", "/v1/apps", MockResponse::empty(502))
+            .start()?;
+        let client = server.client()?;
+
+        match client.api_post::<_, serde_json::Value>("/v1/devices", &json!({})) {
+            Err(AppleCodesignError::AppStoreConnectApi(e)) => {
+                assert_eq!(e.kind(), ErrorKind::EntityError);
+                assert!(e.is(ErrorKind::StateError));
+                assert!(!e.is(ErrorKind::Forbidden));
+                assert!(e.has_code("ENTITY_ERROR.ATTRIBUTE"));
+                assert!(!e.has_code("ENTITY_ERROR.RELATIONSHIP"));
+                assert_eq!(
+                    e.errors[0].source,
+                    Some(json!({"pointer": "/data/attributes/udid"}))
+                );
+            }
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+
+        // Bodies without error documents are typed by their status.
+        match client.list_apps() {
+            Err(AppleCodesignError::AppStoreConnectApi(e)) => {
+                assert!(e.errors.is_empty());
+                assert_eq!(e.kind(), ErrorKind::ServerError);
+                assert_eq!(e.to_string(), "HTTP 502 Bad Gateway");
+            }
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+
+        Ok(())
+    }
+}
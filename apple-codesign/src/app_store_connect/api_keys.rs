@@ -0,0 +1,481 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Validation of API keys.
+//!
+//! The App Store Connect API doesn't expose the API keys of a team nor the
+//! roles of the key making requests. Keys are created and revoked at
+//! <https://appstoreconnect.apple.com/access/api>.
+//! [AppStoreConnectClient::probe_permissions()] makes cheap read-only requests
+//! across endpoint families to find which the key may access.
+//! [AppStoreConnectClient::probe_api_key()] additionally infers the roles the
+//! key appears to have.
+//!
+//! [AppStoreConnectClient::validate_key()] is cheaper: a single request tells
+//! whether the key is accepted, and the team of the key is looked up.
+
+use {
+    crate::{
+        app_store_connect::{team::TeamInfo, users_api::UserRole, AppStoreConnectClient},
+        AppleCodesignError,
+    },
+    serde::Serialize,
+    serde_json::Value,
+};
+
+/// Roles an API key can be created with.
+pub const API_KEY_ROLES: &[UserRole] = &[
+    UserRole::Admin,
+    UserRole::AppManager,
+    UserRole::Developer,
+    UserRole::Marketing,
+    UserRole::Sales,
+    UserRole::Finance,
+    UserRole::AccessToReports,
+    UserRole::CustomerSupport,
+];
+
+/// A read-only request whose permission depends on the role of the key.
+pub struct PermissionProbe {
+    /// Name of the endpoint family.
+    pub name: &'static str,
+
+    /// Human readable description of what the endpoint family gives access to.
+    pub description: &'static str,
+
+    /// Endpoint requested.
+    pub path: &'static str,
+
+    /// Whether the endpoint is a report download rather than a collection.
+    ///
+    /// Report requests require the vendor number of the team, which isn't
+    /// known. A permitted key is answered with a parameter error or a missing
+    /// report instead of a report, which still tells it is permitted.
+    pub report: bool,
+
+    /// Roles permitted to make the request.
+    pub roles: &'static [UserRole],
+}
+
+/// Requests made to probe the permissions of a key.
+///
+/// The first request is permitted for every role, so its failure means the key
+/// is invalid.
+pub const PERMISSION_PROBES: &[PermissionProbe] = &[
+    PermissionProbe {
+        name: "apps",
+        description: "apps",
+        path: "/v1/apps",
+        report: false,
+        roles: API_KEY_ROLES,
+    },
+    PermissionProbe {
+        name: "builds",
+        description: "builds",
+        path: "/v1/builds",
+        report: false,
+        roles: &[
+            UserRole::Admin,
+            UserRole::AppManager,
+            UserRole::Developer,
+            UserRole::Marketing,
+        ],
+    },
+    PermissionProbe {
+        name: "betaGroups",
+        description: "TestFlight beta groups",
+        path: "/v1/betaGroups",
+        report: false,
+        roles: &[
+            UserRole::Admin,
+            UserRole::AppManager,
+            UserRole::Developer,
+            UserRole::Marketing,
+        ],
+    },
+    PermissionProbe {
+        name: "certificates",
+        description: "signing certificates",
+        path: "/v1/certificates",
+        report: false,
+        roles: &[UserRole::Admin, UserRole::AppManager, UserRole::Developer],
+    },
+    PermissionProbe {
+        name: "profiles",
+        description: "provisioning profiles",
+        path: "/v1/profiles",
+        report: false,
+        roles: &[UserRole::Admin, UserRole::AppManager, UserRole::Developer],
+    },
+    PermissionProbe {
+        name: "devices",
+        description: "registered devices",
+        path: "/v1/devices",
+        report: false,
+        roles: &[UserRole::Admin, UserRole::AppManager, UserRole::Developer],
+    },
+    PermissionProbe {
+        name: "users",
+        description: "users",
+        path: "/v1/users",
+        report: false,
+        roles: &[UserRole::Admin],
+    },
+    PermissionProbe {
+        name: "salesReports",
+        description: "sales reports",
+        path: "/v1/salesReports",
+        report: true,
+        roles: &[
+            UserRole::Admin,
+            UserRole::Sales,
+            UserRole::Finance,
+            UserRole::AccessToReports,
+        ],
+    },
+    PermissionProbe {
+        name: "financeReports",
+        description: "finance reports",
+        path: "/v1/financeReports",
+        report: true,
+        roles: &[UserRole::Admin, UserRole::Finance],
+    },
+];
+
+/// Whether a key may access an endpoint family.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Access {
+    Permitted,
+    Forbidden,
+}
+
+/// Access of a key to an endpoint family.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capability {
+    /// Name of the endpoint family. See [PermissionProbe::name].
+    pub name: &'static str,
+
+    /// See [PermissionProbe::description].
+    pub description: &'static str,
+
+    pub access: Access,
+}
+
+impl Capability {
+    /// Whether access is permitted.
+    pub fn is_permitted(&self) -> bool {
+        self.access == Access::Permitted
+    }
+}
+
+/// Permissions of an API key found by [AppStoreConnectClient::probe_api_key()].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ApiKeyReport {
+    /// Names of the permitted probes.
+    pub permitted: Vec<&'static str>,
+
+    /// Names of the forbidden probes.
+    pub forbidden: Vec<&'static str>,
+
+    /// Roles that permit exactly the permitted probes.
+    ///
+    /// Empty if no single role explains the observed permissions.
+    pub roles: Vec<UserRole>,
+}
+
+/// Whether an API key works, found by [AppStoreConnectClient::validate_key()].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyValidation {
+    /// ID of the key.
+    pub key_id: String,
+
+    /// Issuer ID of a team key. [None] for individual keys.
+    pub issuer_id: Option<String>,
+
+    /// Whether the key was accepted.
+    pub valid: bool,
+
+    /// Why the key was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// The team of the key, if it could be discovered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<TeamInfo>,
+}
+
+impl AppStoreConnectClient {
+    /// Check whether the API key of this client is accepted.
+    ///
+    /// A single read-only request permitted for every role is made. If the key
+    /// is accepted, its team is discovered with [Self::team_info()], which
+    /// needs access to certificates or bundle IDs.
+    ///
+    /// A rejected key isn't an error. Errors are returned for failures to
+    /// reach the server.
+    pub fn validate_key(&self) -> Result<KeyValidation, AppleCodesignError> {
+        let mut validation = KeyValidation {
+            key_id: self.connect_token.key_id().to_string(),
+            issuer_id: self.connect_token.issuer_id().map(|s| s.to_string()),
+            valid: false,
+            error: None,
+            team: None,
+        };
+
+        match self.api_get::<Value>(PERMISSION_PROBES[0].path, &[("limit", "1".into())]) {
+            Ok(_) => {
+                validation.valid = true;
+            }
+            Err(AppleCodesignError::AppStoreConnectApi(e))
+                if e.status == 401 || e.status == 403 =>
+            {
+                validation.error = Some(e.to_string());
+                return Ok(validation);
+            }
+            Err(e) => return Err(e),
+        }
+
+        validation.team = match self.team_info() {
+            Ok(team) => Some(team),
+            Err(AppleCodesignError::TeamIdUnknown(_))
+            | Err(AppleCodesignError::AppStoreConnectApi(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(validation)
+    }
+
+    /// Probe which endpoint families the API key of this client may access.
+    ///
+    /// A cheap read-only request is made for every entry of [PERMISSION_PROBES].
+    /// Errors if the key is rejected.
+    pub fn probe_permissions(&self) -> Result<Vec<Capability>, AppleCodesignError> {
+        let mut capabilities: Vec<Capability> = vec![];
+
+        for probe in PERMISSION_PROBES {
+            let query = if probe.report {
+                vec![]
+            } else {
+                vec![("limit", "1".to_string())]
+            };
+
+            let access = match self.api_get::<Value>(probe.path, &query) {
+                Ok(_) => Access::Permitted,
+                Err(AppleCodesignError::AppStoreConnectApi(e))
+                    if probe.report && (e.status == 400 || e.status == 404) =>
+                {
+                    Access::Permitted
+                }
+                Err(AppleCodesignError::AppStoreConnectApi(e))
+                    if e.status == 403 && !capabilities.is_empty() =>
+                {
+                    Access::Forbidden
+                }
+                Err(e) => return Err(e),
+            };
+
+            capabilities.push(Capability {
+                name: probe.name,
+                description: probe.description,
+                access,
+            });
+        }
+
+        Ok(capabilities)
+    }
+
+    /// Validate the API key of this client and probe its permissions.
+    ///
+    /// This is [Self::probe_permissions()] with the roles of the key inferred.
+    pub fn probe_api_key(&self) -> Result<ApiKeyReport, AppleCodesignError> {
+        let capabilities = self.probe_permissions()?;
+
+        let (permitted, forbidden): (Vec<_>, Vec<_>) =
+            capabilities.iter().partition(|c| c.is_permitted());
+        let permitted = permitted.into_iter().map(|c| c.name).collect::<Vec<_>>();
+        let forbidden = forbidden.into_iter().map(|c| c.name).collect::<Vec<_>>();
+
+        let roles = API_KEY_ROLES
+            .iter()
+            .copied()
+            .filter(|role| {
+                PERMISSION_PROBES
+                    .iter()
+                    .all(|probe| probe.roles.contains(role) == permitted.contains(&probe.name))
+            })
+            .collect();
+
+        Ok(ApiKeyReport {
+            permitted,
+            forbidden,
+            roles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn probe() -> Result<(), AppleCodesignError> {
+        let forbidden = fixtures::error(403, "FORBIDDEN_ERROR", "not allowed");
+
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/builds", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/betaGroups", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/certificates", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/profiles", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/devices", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/users", 403, forbidden.clone())
+            .json("GET", "/v1/salesReports", 403, forbidden.clone())
+            .json("GET", "/v1/financeReports", 403, forbidden)
+            .start()?;
+        let client = server.client()?;
+
+        let report = client.probe_api_key()?;
+        assert_eq!(
+            report.permitted,
+            vec![
+                "apps",
+                "builds",
+                "betaGroups",
+                "certificates",
+                "profiles",
+                "devices"
+            ]
+        );
+        assert_eq!(
+            report.forbidden,
+            vec!["users", "salesReports", "financeReports"]
+        );
+        assert_eq!(
+            report.roles,
+            vec![UserRole::AppManager, UserRole::Developer]
+        );
+
+        let requests = server.requests();
+        assert!(requests.iter().all(|r| r.method == "GET"));
+        assert_eq!(requests[0].query.as_deref(), Some("limit=1"));
+        assert!(!requests[8]
+            .query
+            .as_deref()
+            .unwrap_or_default()
+            .contains("limit"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports() -> Result<(), AppleCodesignError> {
+        let forbidden = fixtures::error(403, "FORBIDDEN_ERROR", "not allowed");
+
+        let mut builder =
+            MockServerBuilder::default().json("GET", "/v1/apps", 200, fixtures::collection(vec![]));
+        for path in [
+            "/v1/builds",
+            "/v1/betaGroups",
+            "/v1/certificates",
+            "/v1/profiles",
+            "/v1/devices",
+            "/v1/users",
+        ] {
+            builder = builder.json("GET", path, 403, forbidden.clone());
+        }
+        let server = builder
+            .json(
+                "GET",
+                "/v1/salesReports",
+                400,
+                fixtures::error(400, "PARAMETER_ERROR.REQUIRED", "vendor number required"),
+            )
+            .json(
+                "GET",
+                "/v1/financeReports",
+                400,
+                fixtures::error(400, "PARAMETER_ERROR.REQUIRED", "vendor number required"),
+            )
+            .start()?;
+
+        let capabilities = server.client()?.probe_permissions()?;
+        let finance = capabilities
+            .iter()
+            .find(|c| c.name == "financeReports")
+            .unwrap();
+        assert_eq!(finance.access, Access::Permitted);
+        assert_eq!(finance.description, "finance reports");
+
+        let report = server.client()?.probe_api_key()?;
+        assert_eq!(report.roles, vec![UserRole::Finance]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/certificates", 200, fixtures::collection(vec![]))
+            .json(
+                "GET",
+                "/v1/bundleIds",
+                200,
+                fixtures::collection(vec![fixtures::resource(
+                    "bundleIds",
+                    "B1",
+                    serde_json::json!({"identifier": "com.example.app", "seedId": "ABCDE12345"}),
+                )]),
+            )
+            .start()?;
+
+        let validation = server.client()?.validate_key()?;
+        assert!(validation.valid);
+        assert!(validation.error.is_none());
+        assert_eq!(validation.team.unwrap().team_id, "ABCDE12345");
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                401,
+                fixtures::error(401, "NOT_AUTHORIZED", "invalid token"),
+            )
+            .start()?;
+
+        let validation = server.client()?.validate_key()?;
+        assert!(!validation.valid);
+        assert!(validation.error.unwrap().contains("401"));
+        assert!(validation.team.is_none());
+        assert_eq!(server.requests().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_key() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                401,
+                fixtures::error(401, "NOT_AUTHORIZED", "invalid token"),
+            )
+            .start()?;
+
+        assert!(matches!(
+            server.client()?.probe_api_key(),
+            Err(AppleCodesignError::AppStoreConnectApi(e)) if e.status == 401
+        ));
+        assert_eq!(server.requests().len(), 1);
+
+        Ok(())
+    }
+}
@@ -3,25 +3,149 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 //! App Store Connect API tokens.
+//!
+//! Tokens are JWTs signed with ES256. The signature is produced by a
+//! [JwtSigner]. [EcdsaDerSigner] signs with a private key held in memory and is
+//! what the constructors of [ConnectTokenEncoder] taking key material use.
+//! Keys that can't leave an HSM, a cloud KMS, or a smart card are used by
+//! implementing [JwtSigner] and constructing the encoder with
+//! [ConnectTokenEncoder::from_signer()].
 
 use {
-    crate::AppleCodesignError,
+    crate::{app_store_connect::cache_scope::CacheScope, AppleCodesignError},
     jsonwebtoken::{Algorithm, EncodingKey, Header},
     serde::{Deserialize, Serialize},
-    std::{path::Path, time::SystemTime},
+    std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::SystemTime,
+    },
+    zeroize::Zeroizing,
 };
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct ConnectTokenRequest {
-    iss: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
     iat: u64,
     exp: u64,
     aud: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<Vec<String>>,
 }
 
+/// The `sub` claim of tokens of individual keys.
+const INDIVIDUAL_KEY_SUBJECT: &str = "user";
+
+/// Default lifetime in seconds of tokens minted by [AppStoreConnectClient](super::AppStoreConnectClient).
+pub const DEFAULT_TOKEN_DURATION_SECONDS: u64 = 300;
+
+/// Longest lifetime in seconds Apple accepts for tokens.
+///
+/// Tokens living longer than 20 minutes are rejected.
+pub const MAX_TOKEN_DURATION_SECONDS: u64 = 1200;
+
 /// A JWT Token for use with App Store Connect API.
 pub type AppStoreConnectToken = String;
 
+/// Produces the ES256 signatures of tokens.
+pub trait JwtSigner: Send + Sync {
+    /// Sign a JWT signing input (`<header>.<claims>`) with ECDSA P-256 and SHA-256.
+    ///
+    /// The message is not hashed; implementations hash it with SHA-256. The
+    /// signature may be returned as the 64 byte concatenation of `r` and `s`
+    /// (as PKCS#11 produces) or ASN.1 DER encoded (as AWS KMS produces).
+    fn sign_es256(&self, message: &[u8]) -> Result<Vec<u8>, AppleCodesignError>;
+}
+
+/// A [JwtSigner] holding a PKCS#8 DER encoded ECDSA private key in memory.
+///
+/// The key is zeroed on drop.
+#[derive(Clone)]
+pub struct EcdsaDerSigner {
+    der: Zeroizing<Vec<u8>>,
+}
+
+impl EcdsaDerSigner {
+    /// Construct an instance from a PKCS#8 DER encoded ECDSA P-256 private key.
+    pub fn new(der_data: &[u8]) -> Self {
+        Self {
+            der: Zeroizing::new(der_data.to_vec()),
+        }
+    }
+}
+
+impl JwtSigner for EcdsaDerSigner {
+    fn sign_es256(&self, message: &[u8]) -> Result<Vec<u8>, AppleCodesignError> {
+        let rng = ring::rand::SystemRandom::new();
+        let key = ring::signature::EcdsaKeyPair::from_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &self.der,
+        )
+        .map_err(|e| {
+            AppleCodesignError::AppStoreConnectApiKey(format!("invalid ECDSA private key: {}", e))
+        })?;
+
+        let signature = key.sign(&rng, message).map_err(|_| {
+            AppleCodesignError::AppStoreConnectApiKey("ECDSA signing failed".into())
+        })?;
+
+        Ok(signature.as_ref().to_vec())
+    }
+}
+
+/// Convert an ECDSA P-256 signature to the 64 byte `r || s` form JWS requires.
+///
+/// Signatures already in that form are returned as is. Otherwise the signature
+/// is parsed as an ASN.1 DER `SEQUENCE` of the `INTEGER`s `r` and `s`.
+fn es256_signature_to_fixed(signature: &[u8]) -> Result<Vec<u8>, AppleCodesignError> {
+    if signature.len() == 64 {
+        return Ok(signature.to_vec());
+    }
+
+    let invalid = || {
+        AppleCodesignError::AppStoreConnectApiKey(format!(
+            "signer produced an invalid ES256 signature ({} bytes)",
+            signature.len()
+        ))
+    };
+
+    // Signatures are under 128 bytes, so all lengths are in short form.
+    let body = match signature {
+        [0x30, len, body @ ..] if *len as usize == body.len() => body,
+        _ => return Err(invalid()),
+    };
+
+    let mut fixed = vec![];
+    let mut rest = body;
+    for _ in 0..2 {
+        let (integer, remaining) = match rest {
+            [0x02, len, tail @ ..] if tail.len() >= *len as usize => tail.split_at(*len as usize),
+            _ => return Err(invalid()),
+        };
+
+        let mut integer = integer;
+        while let [0, tail @ ..] = integer {
+            integer = tail;
+        }
+        if integer.len() > 32 {
+            return Err(invalid());
+        }
+
+        fixed.extend(std::iter::repeat(0).take(32 - integer.len()));
+        fixed.extend_from_slice(integer);
+        rest = remaining;
+    }
+
+    if !rest.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(fixed)
+}
+
 /// Represents a private key used to create JWT tokens for use with App Store Connect.
 ///
 /// See https://developer.apple.com/documentation/appstoreconnectapi/creating_api_keys_for_app_store_connect_api
@@ -36,19 +160,58 @@ pub type AppStoreConnectToken = String;
 /// * An issuer ID. This is likely a UUID.
 /// * A private key. Likely ECDSA.
 ///
+/// Individual keys belong to a user rather than the team and have no issuer
+/// ID. Their tokens identify the user with a `sub` claim instead of an `iss`
+/// claim. Construct encoders for them with the `individual_*` constructors.
+///
 /// All these are issued by Apple. You can log in to App Store Connect and see/manage your keys
 /// at https://appstoreconnect.apple.com/access/api.
+///
+/// Private key material given as DER or PEM is held in memory that is zeroed on drop.
+/// Keys that can't be exported are used through a [JwtSigner].
+///
+/// Tokens minted by clients live [DEFAULT_TOKEN_DURATION_SECONDS] and may be
+/// used against any endpoint the key has access to. [Self::with_token_duration()]
+/// and [Self::with_scope()] change that, e.g. so a CI job carries a token only
+/// good for the requests it makes.
 #[derive(Clone)]
 pub struct ConnectTokenEncoder {
     key_id: String,
-    issuer_id: String,
-    encoding_key: EncodingKey,
+    /// The issuer of a team key. `None` for an individual key.
+    issuer_id: Option<String>,
+    signing_key: SigningKey,
+    token_duration: u64,
+    /// The `scope` claim of tokens. Empty for unscoped tokens.
+    scope: Vec<String>,
+}
+
+/// Private key material of a [ConnectTokenEncoder].
+#[derive(Clone)]
+enum SigningKey {
+    /// A signer producing signatures, such as an [EcdsaDerSigner].
+    Signer(Arc<dyn JwtSigner>),
+
+    /// An [EncodingKey] given by the caller.
+    Jwt(EncodingKey),
+}
+
+impl std::fmt::Debug for ConnectTokenEncoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectTokenEncoder")
+            .field("key_id", &self.key_id)
+            .field("issuer_id", &self.issuer_id)
+            .field("signing_key", &crate::redact::REDACTED)
+            .field("token_duration", &self.token_duration)
+            .field("scope", &self.scope)
+            .finish()
+    }
 }
 
 impl ConnectTokenEncoder {
     /// Construct an instance from an [EncodingKey] instance.
     ///
-    /// This is the lowest level API and ultimately what all constructors use.
+    /// The memory of [EncodingKey] isn't zeroed on drop. Prefer the other
+    /// constructors.
     pub fn from_jwt_encoding_key(
         key_id: String,
         issuer_id: String,
@@ -56,8 +219,10 @@ impl ConnectTokenEncoder {
     ) -> Self {
         Self {
             key_id,
-            issuer_id,
-            encoding_key,
+            issuer_id: Some(issuer_id),
+            signing_key: SigningKey::Jwt(encoding_key),
+            token_duration: DEFAULT_TOKEN_DURATION_SECONDS,
+            scope: vec![],
         }
     }
 
@@ -67,9 +232,55 @@ impl ConnectTokenEncoder {
         issuer_id: String,
         der_data: &[u8],
     ) -> Result<Self, AppleCodesignError> {
-        let encoding_key = EncodingKey::from_ec_der(der_data);
+        Ok(Self {
+            key_id,
+            issuer_id: Some(issuer_id),
+            signing_key: SigningKey::Signer(Arc::new(EcdsaDerSigner::new(der_data))),
+            token_duration: DEFAULT_TOKEN_DURATION_SECONDS,
+            scope: vec![],
+        })
+    }
+
+    /// Construct an instance of an individual key from a DER encoded ECDSA private key.
+    pub fn individual_from_ecdsa_der(
+        key_id: String,
+        der_data: &[u8],
+    ) -> Result<Self, AppleCodesignError> {
+        Ok(Self {
+            key_id,
+            issuer_id: None,
+            signing_key: SigningKey::Signer(Arc::new(EcdsaDerSigner::new(der_data))),
+            token_duration: DEFAULT_TOKEN_DURATION_SECONDS,
+            scope: vec![],
+        })
+    }
+
+    /// Construct an instance whose tokens are signed by a [JwtSigner].
+    ///
+    /// This is how keys held in an HSM, a cloud KMS, or on a smart card are used.
+    pub fn from_signer(
+        key_id: String,
+        issuer_id: String,
+        signer: impl JwtSigner + 'static,
+    ) -> Self {
+        Self {
+            key_id,
+            issuer_id: Some(issuer_id),
+            signing_key: SigningKey::Signer(Arc::new(signer)),
+            token_duration: DEFAULT_TOKEN_DURATION_SECONDS,
+            scope: vec![],
+        }
+    }
 
-        Ok(Self::from_jwt_encoding_key(key_id, issuer_id, encoding_key))
+    /// Construct an instance of an individual key whose tokens are signed by a [JwtSigner].
+    pub fn individual_from_signer(key_id: String, signer: impl JwtSigner + 'static) -> Self {
+        Self {
+            key_id,
+            issuer_id: None,
+            signing_key: SigningKey::Signer(Arc::new(signer)),
+            token_duration: DEFAULT_TOKEN_DURATION_SECONDS,
+            scope: vec![],
+        }
     }
 
     /// Create a token from a PEM encoded ECDSA private key.
@@ -78,9 +289,15 @@ impl ConnectTokenEncoder {
         issuer_id: String,
         pem_data: &[u8],
     ) -> Result<Self, AppleCodesignError> {
-        let encoding_key = EncodingKey::from_ec_pem(pem_data)?;
+        Self::from_ecdsa_der(key_id, issuer_id, &parse_private_key_pem(pem_data)?)
+    }
 
-        Ok(Self::from_jwt_encoding_key(key_id, issuer_id, encoding_key))
+    /// Create a token for an individual key from a PEM encoded ECDSA private key.
+    pub fn individual_from_ecdsa_pem(
+        key_id: String,
+        pem_data: &[u8],
+    ) -> Result<Self, AppleCodesignError> {
+        Self::individual_from_ecdsa_der(key_id, &parse_private_key_pem(pem_data)?)
     }
 
     /// Create a token from a PEM encoded ECDSA private key in a filesystem path.
@@ -89,38 +306,93 @@ impl ConnectTokenEncoder {
         issuer_id: String,
         path: impl AsRef<Path>,
     ) -> Result<Self, AppleCodesignError> {
-        let data = std::fs::read(path.as_ref())?;
+        let data = Zeroizing::new(std::fs::read(path.as_ref())?);
 
         Self::from_ecdsa_pem(key_id, issuer_id, &data)
     }
 
+    /// Create a token for an individual key from a PEM encoded ECDSA private key in a path.
+    pub fn individual_from_ecdsa_pem_path(
+        key_id: String,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, AppleCodesignError> {
+        let data = Zeroizing::new(std::fs::read(path.as_ref())?);
+
+        Self::individual_from_ecdsa_pem(key_id, &data)
+    }
+
     /// Attempt to construct in instance from an API Key ID.
     ///
     /// e.g. `DEADBEEF42`. This looks for an `AuthKey_<id>.p8` file in default search
     /// locations like `~/.appstoreconnect/private_keys`.
     pub fn from_api_key_id(key_id: String, issuer_id: String) -> Result<Self, AppleCodesignError> {
-        let mut search_paths = vec![std::env::current_dir()?.join("private_keys")];
-
-        if let Some(home) = dirs::home_dir() {
-            search_paths.extend([
-                home.join("private_keys"),
-                home.join(".private_keys"),
-                home.join(".appstoreconnect").join("private_keys"),
-            ]);
-        }
+        let path = find_api_key_path(&key_id)?;
 
-        // AuthKey_<apiKey>.p8
-        let filename = format!("AuthKey_{}.p8", key_id);
+        Self::from_ecdsa_pem_path(key_id, issuer_id, path)
+    }
 
-        for path in search_paths {
-            let candidate = path.join(&filename);
+    /// Attempt to construct an instance of an individual key from an API Key ID.
+    ///
+    /// The private key is searched for like [Self::from_api_key_id()].
+    pub fn individual_from_api_key_id(key_id: String) -> Result<Self, AppleCodesignError> {
+        let path = find_api_key_path(&key_id)?;
 
-            if candidate.exists() {
-                return Self::from_ecdsa_pem_path(key_id, issuer_id, candidate);
-            }
+        Self::individual_from_ecdsa_pem_path(key_id, path)
+    }
+
+    /// Set the lifetime in seconds of tokens minted by clients.
+    ///
+    /// Errors if the duration is zero or exceeds [MAX_TOKEN_DURATION_SECONDS].
+    pub fn with_token_duration(mut self, seconds: u64) -> Result<Self, AppleCodesignError> {
+        if seconds == 0 || seconds > MAX_TOKEN_DURATION_SECONDS {
+            return Err(AppleCodesignError::AppStoreConnectTokenDuration(
+                seconds,
+                MAX_TOKEN_DURATION_SECONDS,
+            ));
         }
 
-        Err(AppleCodesignError::AppStoreConnectApiKeyNotFound)
+        self.token_duration = seconds;
+
+        Ok(self)
+    }
+
+    /// Restrict tokens to the given requests with the `scope` claim.
+    ///
+    /// Each entry is a method and a path with an optional query, e.g.
+    /// `GET /v1/apps?filter[platform]=IOS`. Requests outside the scope are
+    /// rejected by the server. An empty list mints unscoped tokens.
+    pub fn with_scope(mut self, scope: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.scope = scope.into_iter().map(|s| s.to_string()).collect();
+
+        self
+    }
+
+    /// The lifetime in seconds of tokens minted by clients.
+    pub fn token_duration(&self) -> u64 {
+        self.token_duration
+    }
+
+    /// The `scope` claim of tokens. Empty for unscoped tokens.
+    pub fn scope(&self) -> &[String] {
+        &self.scope
+    }
+
+    /// The ID of this key.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The issuer ID of a team key. `None` for an individual key.
+    pub fn issuer_id(&self) -> Option<&str> {
+        self.issuer_id.as_deref()
+    }
+
+    /// The scope persisted state of this key is partitioned by.
+    pub fn cache_scope(&self) -> CacheScope {
+        CacheScope::new(
+            self.issuer_id.as_deref().unwrap_or(INDIVIDUAL_KEY_SUBJECT),
+            &self.key_id,
+        )
     }
 
     /// Mint a new JWT token.
@@ -128,6 +400,19 @@ impl ConnectTokenEncoder {
     /// Using the private key and key metadata bound to this instance, we issue a new JWT
     /// for the requested duration.
     pub fn new_token(&self, duration: u64) -> Result<AppStoreConnectToken, AppleCodesignError> {
+        self.new_token_with_clock_offset(duration, 0)
+    }
+
+    /// Mint a new JWT token with timestamps adjusted by a clock offset.
+    ///
+    /// `clock_offset` is the number of seconds the server's clock is ahead of
+    /// the local clock (negative if it is behind). It is added to the local time
+    /// so the token's `iat` and `exp` are correct from the server's perspective.
+    pub fn new_token_with_clock_offset(
+        &self,
+        duration: u64,
+        clock_offset: i64,
+    ) -> Result<AppStoreConnectToken, AppleCodesignError> {
         let header = Header {
             kid: Some(self.key_id.clone()),
             alg: Algorithm::ES256,
@@ -137,17 +422,237 @@ impl ConnectTokenEncoder {
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .expect("calculating UNIX time should never fail")
-            .as_secs();
+            .as_secs() as i64;
+        let now = (now + clock_offset).max(0) as u64;
 
         let claims = ConnectTokenRequest {
             iss: self.issuer_id.clone(),
+            sub: match self.issuer_id {
+                Some(_) => None,
+                None => Some(INDIVIDUAL_KEY_SUBJECT.to_string()),
+            },
             iat: now,
             exp: now + duration,
             aud: "appstoreconnect-v1".to_string(),
+            scope: if self.scope.is_empty() {
+                None
+            } else {
+                Some(self.scope.clone())
+            },
         };
 
-        let token = jsonwebtoken::encode(&header, &claims, &self.encoding_key)?;
+        let token = match &self.signing_key {
+            SigningKey::Signer(signer) => {
+                let encode_part =
+                    |value: &[u8]| base64::encode_config(value, base64::URL_SAFE_NO_PAD);
+
+                let message = format!(
+                    "{}.{}",
+                    encode_part(&serde_json::to_vec(&header)?),
+                    encode_part(&serde_json::to_vec(&claims)?)
+                );
+                let signature = es256_signature_to_fixed(&signer.sign_es256(message.as_bytes())?)?;
+
+                format!("{}.{}", message, encode_part(&signature))
+            }
+            SigningKey::Jwt(encoding_key) => jsonwebtoken::encode(&header, &claims, encoding_key)?,
+        };
 
         Ok(token)
     }
 }
+
+/// Decode a PEM encoded `PRIVATE KEY` to DER.
+pub(crate) fn parse_private_key_pem(
+    pem_data: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, AppleCodesignError> {
+    let parsed = pem::parse(pem_data).map_err(|e| {
+        AppleCodesignError::AppStoreConnectApiKey(format!("error parsing PEM: {}", e))
+    })?;
+    let der = Zeroizing::new(parsed.contents);
+
+    if parsed.tag != "PRIVATE KEY" {
+        return Err(AppleCodesignError::AppStoreConnectApiKey(
+            "does not look like a PRIVATE KEY".to_string(),
+        ));
+    }
+
+    Ok(der)
+}
+
+/// Environment variable naming a directory searched first by [find_api_key_path()].
+///
+/// Also honored by `altool`.
+pub const API_PRIVATE_KEYS_DIR_ENV: &str = "API_PRIVATE_KEYS_DIR";
+
+/// Find the `AuthKey_<id>.p8` file of an API Key in default search locations.
+///
+/// These are the locations searched by Apple's `altool` and `notarytool`:
+/// the directory in [API_PRIVATE_KEYS_DIR_ENV], `./private_keys`,
+/// `~/private_keys`, `~/.private_keys`, and `~/.appstoreconnect/private_keys`.
+pub(crate) fn find_api_key_path(key_id: &str) -> Result<PathBuf, AppleCodesignError> {
+    let mut search_paths = std::env::var_os(API_PRIVATE_KEYS_DIR_ENV)
+        .map(PathBuf::from)
+        .into_iter()
+        .collect::<Vec<_>>();
+    search_paths.push(std::env::current_dir()?.join("private_keys"));
+
+    if let Some(home) = dirs::home_dir() {
+        search_paths.extend([
+            home.join("private_keys"),
+            home.join(".private_keys"),
+            home.join(".appstoreconnect").join("private_keys"),
+        ]);
+    }
+
+    // AuthKey_<apiKey>.p8
+    let filename = format!("AuthKey_{}.p8", key_id);
+
+    search_paths
+        .into_iter()
+        .map(|path| path.join(&filename))
+        .find(|candidate| candidate.exists())
+        .ok_or(AppleCodesignError::AppStoreConnectApiKeyNotFound)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn claims(token: &str) -> serde_json::Value {
+        let payload = token.split('.').nth(1).unwrap();
+        serde_json::from_slice(&base64::decode_config(payload, base64::URL_SAFE_NO_PAD).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn individual_key_claims() -> Result<(), AppleCodesignError> {
+        let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .unwrap();
+
+        let team =
+            ConnectTokenEncoder::from_ecdsa_der("KEY".into(), "ISSUER".into(), key.as_ref())?;
+        let claims_team = claims(&team.new_token(60)?);
+        assert_eq!(claims_team["iss"], "ISSUER");
+        assert!(claims_team.get("sub").is_none());
+
+        let individual =
+            ConnectTokenEncoder::individual_from_ecdsa_der("KEY".into(), key.as_ref())?;
+        assert_eq!(individual.issuer_id(), None);
+        let claims_individual = claims(&individual.new_token(60)?);
+        assert_eq!(claims_individual["sub"], "user");
+        assert_eq!(claims_individual["aud"], "appstoreconnect-v1");
+        assert!(claims_individual.get("iss").is_none());
+
+        assert_ne!(team.cache_scope(), individual.cache_scope());
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_options() -> Result<(), AppleCodesignError> {
+        let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .unwrap();
+
+        let encoder =
+            ConnectTokenEncoder::from_ecdsa_der("KEY".into(), "ISSUER".into(), key.as_ref())?;
+        assert_eq!(encoder.token_duration(), DEFAULT_TOKEN_DURATION_SECONDS);
+        assert!(claims(&encoder.new_token(60)?).get("scope").is_none());
+
+        let encoder = encoder
+            .with_token_duration(MAX_TOKEN_DURATION_SECONDS)?
+            .with_scope(["GET /v1/apps", "GET /v1/builds?filter[app]=123"]);
+        assert_eq!(encoder.token_duration(), MAX_TOKEN_DURATION_SECONDS);
+        assert_eq!(
+            claims(&encoder.new_token(60)?)["scope"],
+            serde_json::json!(["GET /v1/apps", "GET /v1/builds?filter[app]=123"])
+        );
+
+        assert!(matches!(
+            encoder
+                .clone()
+                .with_token_duration(MAX_TOKEN_DURATION_SECONDS + 1),
+            Err(AppleCodesignError::AppStoreConnectTokenDuration(1201, 1200))
+        ));
+        assert!(encoder.with_token_duration(0).is_err());
+
+        Ok(())
+    }
+
+    /// A signer returning DER encoded signatures, like AWS KMS.
+    struct DerSignatureSigner(EcdsaDerSigner);
+
+    impl JwtSigner for DerSignatureSigner {
+        fn sign_es256(&self, message: &[u8]) -> Result<Vec<u8>, AppleCodesignError> {
+            let fixed = self.0.sign_es256(message)?;
+
+            let integer = |bytes: &[u8]| {
+                let mut bytes = bytes.to_vec();
+                while bytes.len() > 1 && bytes[0] == 0 && bytes[1] < 0x80 {
+                    bytes.remove(0);
+                }
+                if bytes[0] >= 0x80 {
+                    bytes.insert(0, 0);
+                }
+                let mut res = vec![0x02, bytes.len() as u8];
+                res.extend(bytes);
+                res
+            };
+
+            let mut body = integer(&fixed[0..32]);
+            body.extend(integer(&fixed[32..64]));
+
+            let mut res = vec![0x30, body.len() as u8];
+            res.extend(body);
+            Ok(res)
+        }
+    }
+
+    #[test]
+    fn external_signer() -> Result<(), AppleCodesignError> {
+        use ring::signature::KeyPair;
+
+        let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .unwrap();
+        let public_key = ring::signature::EcdsaKeyPair::from_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            key.as_ref(),
+        )
+        .unwrap()
+        .public_key()
+        .as_ref()
+        .to_vec();
+
+        let encoder = ConnectTokenEncoder::from_signer(
+            "KEY".into(),
+            "ISSUER".into(),
+            DerSignatureSigner(EcdsaDerSigner::new(key.as_ref())),
+        );
+        let token = encoder.new_token(60)?;
+        assert_eq!(claims(&token)["iss"], "ISSUER");
+
+        let (message, signature) = token.rsplit_once('.').unwrap();
+        let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).unwrap();
+        assert_eq!(signature.len(), 64);
+
+        ring::signature::UnparsedPublicKey::new(
+            &ring::signature::ECDSA_P256_SHA256_FIXED,
+            &public_key,
+        )
+        .verify(message.as_bytes(), &signature)
+        .expect("signature should verify");
+
+        assert!(es256_signature_to_fixed(&[0x30, 0x00]).is_err());
+
+        Ok(())
+    }
+}
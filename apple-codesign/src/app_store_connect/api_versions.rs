@@ -0,0 +1,172 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Versions of App Store Connect API endpoints.
+//!
+//! Most endpoints are at `/v1`, but Apple moves individual resource types to
+//! newer versions over time. e.g. sandbox testers are at `/v2/sandboxTesters`
+//! and app price points at `/v3/appPricePoints`.
+//!
+//! Request paths are written with the version they were implemented against
+//! (`/v1/...`). [ApiVersions] maps the resource type in the first segment after
+//! the version to the version to actually request. A version bump of a
+//! resource type is a change to [DEFAULT_API_VERSIONS] (or an override in the
+//! client configuration) rather than to every path mentioning it.
+//!
+//! Relationship paths are routed by their parent resource type, so
+//! `/v1/apps/{id}/appPricePoints` follows the version of `apps`.
+
+use {
+    crate::AppleCodesignError,
+    std::{borrow::Cow, collections::BTreeMap},
+};
+
+/// The resource type of a versioned request path.
+///
+/// e.g. `apps` for `/v1/apps/{id}/appPricePoints`.
+pub fn resource_type(path: &str) -> Option<&str> {
+    let (version, rest) = path.strip_prefix("/v")?.split_once('/')?;
+    version.parse::<u8>().ok()?;
+
+    rest.split(['/', '?']).next()
+}
+
+/// Resource types served at a version other than `/v1`.
+pub const DEFAULT_API_VERSIONS: &[(&str, u8)] = &[
+    ("appAvailabilities", 2),
+    ("appPricePoints", 3),
+    ("sandboxTesters", 2),
+    ("sandboxTestersClearPurchaseHistoryRequest", 2),
+];
+
+/// Parse a `RESOURCE=VERSION` override. e.g. `sandboxTesters=2` or `appPricePoints=v3`.
+pub fn parse_api_version_override(s: &str) -> Result<(String, u8), AppleCodesignError> {
+    let invalid = || {
+        AppleCodesignError::CliGeneralError(format!(
+            "invalid API version override {}; expected RESOURCE=VERSION (e.g. sandboxTesters=2)",
+            s
+        ))
+    };
+
+    let (resource, version) = s.split_once('=').ok_or_else(invalid)?;
+    let resource = resource.trim();
+    let version = version.trim();
+    let version = version
+        .strip_prefix('v')
+        .unwrap_or(version)
+        .parse::<u8>()
+        .map_err(|_| invalid())?;
+
+    if resource.is_empty() || resource.contains('/') || version == 0 {
+        return Err(invalid());
+    }
+
+    Ok((resource.to_string(), version))
+}
+
+/// The version requested for each resource type.
+#[derive(Clone, Debug)]
+pub struct ApiVersions {
+    versions: BTreeMap<String, u8>,
+}
+
+impl Default for ApiVersions {
+    fn default() -> Self {
+        Self {
+            versions: DEFAULT_API_VERSIONS
+                .iter()
+                .map(|(resource, version)| (resource.to_string(), *version))
+                .collect(),
+        }
+    }
+}
+
+impl ApiVersions {
+    /// Request `resource` at `version`, replacing any default.
+    pub fn set(&mut self, resource: impl ToString, version: u8) {
+        self.versions.insert(resource.to_string(), version);
+    }
+
+    /// The version requested for a resource type, if it has an entry.
+    pub fn get(&self, resource: &str) -> Option<u8> {
+        self.versions.get(resource).copied()
+    }
+
+    /// Rewrite the version of a request path.
+    ///
+    /// Paths not beginning with a version, and resource types without an
+    /// entry, are returned unchanged.
+    pub fn resolve<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let rest = match path.strip_prefix("/v") {
+            Some(rest) => rest,
+            None => return Cow::Borrowed(path),
+        };
+
+        let (current, rest) = match rest.split_once('/') {
+            Some((version, rest)) if version.parse::<u8>().is_ok() => (version, rest),
+            _ => return Cow::Borrowed(path),
+        };
+
+        match self.get(resource_type(path).unwrap_or_default()) {
+            Some(version) if version.to_string() != current => {
+                Cow::Owned(format!("/v{}/{}", version, rest))
+            }
+            _ => Cow::Borrowed(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn resolve() {
+        let mut versions = ApiVersions::default();
+
+        assert_eq!(versions.resolve("/v1/apps/A1"), "/v1/apps/A1");
+        assert_eq!(versions.resolve("/v1/sandboxTesters"), "/v2/sandboxTesters");
+        assert_eq!(
+            versions.resolve("/v1/appPricePoints/P1/equalizations"),
+            "/v3/appPricePoints/P1/equalizations"
+        );
+        assert_eq!(
+            versions.resolve("/v1/apps/A1/appPricePoints"),
+            "/v1/apps/A1/appPricePoints"
+        );
+        assert_eq!(
+            versions.resolve("/v2/appAvailabilities"),
+            "/v2/appAvailabilities"
+        );
+        assert_eq!(versions.resolve("/notary"), "/notary");
+
+        versions.set("apps", 2);
+        assert_eq!(versions.resolve("/v1/apps/A1"), "/v2/apps/A1");
+
+        assert_eq!(
+            parse_api_version_override("sandboxTesters=v3").unwrap(),
+            ("sandboxTesters".to_string(), 3)
+        );
+        assert!(parse_api_version_override("sandboxTesters").is_err());
+        assert!(parse_api_version_override("=2").is_err());
+        assert!(parse_api_version_override("apps=0").is_err());
+    }
+
+    #[test]
+    fn client_override() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v2/apps", 200, fixtures::collection(vec![]))
+            .start()?;
+        let mut client = server.client()?;
+        client.set_api_version("apps", 2);
+
+        assert!(client.list_apps()?.is_empty());
+        assert_eq!(server.requests()[0].path, "/v2/apps");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App Store Connect apps and their App Store versions.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/apps>
+//! and <https://developer.apple.com/documentation/appstoreconnectapi/app_store_versions>.
+
+pub use asconnect_types::apps::*;
+
+use crate::{
+    app_store_connect::{
+        json_api::{AppQuery, AppStoreVersionQuery, Document, RequestDocument},
+        AppStoreConnectClient,
+    },
+    AppleCodesignError,
+};
+
+impl AppStoreConnectClient {
+    /// List all apps in the account.
+    pub fn list_apps(&self) -> Result<Vec<App>, AppleCodesignError> {
+        self.api_get_all("/v1/apps", &[])
+    }
+
+    /// Fetch an app by its ID.
+    ///
+    /// Apps are memoized in the [lookup cache](Self::lookup_cache()).
+    pub fn get_app(&self, app_id: &str) -> Result<App, AppleCodesignError> {
+        self.lookup_cache().app_by_id(app_id, || {
+            let doc = self.api_get::<Document<App>>(&format!("/v1/apps/{}", app_id), &[])?;
+
+            Ok(doc.data)
+        })
+    }
+
+    /// Find the app having a bundle identifier.
+    ///
+    /// Apps are memoized in the [lookup cache](Self::lookup_cache()).
+    pub fn find_app_by_bundle_id(
+        &self,
+        bundle_id: &str,
+    ) -> Result<Option<App>, AppleCodesignError> {
+        self.lookup_cache().app_by_bundle_identifier(bundle_id, || {
+            Ok(self
+                .api_get_all::<AppAttributes>(
+                    "/v1/apps",
+                    &AppQuery::new().bundle_id(bundle_id).to_query(),
+                )?
+                .into_iter()
+                .find(|app| app.attributes.bundle_id.as_deref() == Some(bundle_id)))
+        })
+    }
+
+    /// List the App Store versions of an app.
+    pub fn list_app_store_versions(
+        &self,
+        app_id: &str,
+        query: &AppStoreVersionQuery,
+    ) -> Result<Vec<AppStoreVersion>, AppleCodesignError> {
+        self.api_get_all(
+            &format!("/v1/apps/{}/appStoreVersions", app_id),
+            &query.to_query(),
+        )
+    }
+
+    /// Create an App Store version of an app.
+    ///
+    /// `attributes` must have the platform and version string set.
+    pub fn create_app_store_version(
+        &self,
+        app_id: &str,
+        attributes: AppStoreVersionAttributes,
+    ) -> Result<AppStoreVersion, AppleCodesignError> {
+        let body = RequestDocument::create("appStoreVersions", attributes)
+            .with_relationship("app", "apps", app_id);
+
+        let doc = self.api_post::<_, Document<AppStoreVersion>>("/v1/appStoreVersions", &body)?;
+
+        Ok(doc.data)
+    }
+}
@@ -0,0 +1,274 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Signatures of artifacts produced by release pipelines.
+//!
+//! Exports, provenance files, and logs are consumed by other systems, which
+//! may want to know that a file came from the release pipeline and wasn't
+//! altered. An [ArtifactSigner] signs files with an ECDSA P-256 key, such as
+//! the team's App Store Connect API key, and writes an [ArtifactSignature]
+//! next to each file as `<file>.sig` (see [ArtifactSignature::path_for()]).
+//!
+//! Consumers verify a file with [ArtifactSignature::problems()] given the
+//! public key of the signer, which [ArtifactSigner::public_key_pem()] renders
+//! as a PEM encoded `PUBLIC KEY`. The signature is a DER encoded ECDSA
+//! signature over the SHA-256 of the file content, so it can also be checked
+//! with standard tools.
+
+use {
+    crate::{cryptography::InMemoryPrivateKey, AppleCodesignError},
+    chrono::Utc,
+    elliptic_curve::sec1::ToEncodedPoint,
+    p256::pkcs8::{DecodePublicKey, EncodePublicKey},
+    ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1},
+    serde::{Deserialize, Serialize},
+    sha2::Digest,
+    std::path::{Path, PathBuf},
+    x509_certificate::Sign,
+};
+
+/// JOSE name of the only supported signature algorithm: ECDSA P-256 with SHA-256.
+pub const ARTIFACT_SIGNATURE_ALGORITHM: &str = "ES256";
+
+/// Identify a public key by the hex encoded SHA-256 of its SEC1 encoding.
+fn key_id(public_key_data: &[u8]) -> String {
+    hex::encode(sha2::Sha256::digest(public_key_data))
+}
+
+/// Signs artifacts with an ECDSA P-256 private key.
+#[derive(Clone, Debug)]
+pub struct ArtifactSigner {
+    key: InMemoryPrivateKey,
+    key_id: String,
+}
+
+impl ArtifactSigner {
+    /// Sign with a private key, which must be an ECDSA P-256 key.
+    pub fn new(key: InMemoryPrivateKey) -> Result<Self, AppleCodesignError> {
+        if !matches!(key, InMemoryPrivateKey::EcdsaP256(_)) {
+            return Err(AppleCodesignError::ArtifactSignature(
+                "artifacts must be signed with an ECDSA P-256 key".into(),
+            ));
+        }
+
+        let key_id = key_id(key.public_key_data().as_ref());
+
+        Ok(Self { key, key_id })
+    }
+
+    /// Sign with the PEM encoded PKCS#8 private key in a file.
+    ///
+    /// App Store Connect API key files (`AuthKey_<id>.p8`) are such files.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        let pem = pem::parse(std::fs::read(path)?).map_err(AppleCodesignError::CertificatePem)?;
+
+        if pem.tag != "PRIVATE KEY" {
+            return Err(AppleCodesignError::ArtifactSignature(format!(
+                "{} does not contain a PRIVATE KEY",
+                path.display()
+            )));
+        }
+
+        Self::new(InMemoryPrivateKey::from_pkcs8_der(&pem.contents)?)
+    }
+
+    /// Hex encoded SHA-256 of the public key.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The public key as PEM encoded SubjectPublicKeyInfo.
+    pub fn public_key_pem(&self) -> Result<String, AppleCodesignError> {
+        let public_key = p256::PublicKey::from_sec1_bytes(self.key.public_key_data().as_ref())
+            .map_err(|e| AppleCodesignError::ArtifactSignature(e.to_string()))?;
+        let der = public_key
+            .to_public_key_der()
+            .map_err(|e| AppleCodesignError::ArtifactSignature(e.to_string()))?;
+
+        Ok(pem::encode(&pem::Pem {
+            tag: "PUBLIC KEY".to_string(),
+            contents: der.as_ref().to_vec(),
+        }))
+    }
+
+    /// Sign content.
+    pub fn sign(
+        &self,
+        artifact: &str,
+        data: &[u8],
+    ) -> Result<ArtifactSignature, AppleCodesignError> {
+        let (signature, _) = self.key.sign(data)?;
+
+        Ok(ArtifactSignature {
+            artifact: artifact.to_string(),
+            algorithm: ARTIFACT_SIGNATURE_ALGORITHM.to_string(),
+            key_id: self.key_id.clone(),
+            sha256: hex::encode(sha2::Sha256::digest(data)),
+            signature: base64::encode(signature),
+            signed_at: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Sign a file, writing the signature to `<path>.sig`.
+    ///
+    /// Returns the path of the signature.
+    pub fn sign_file(&self, path: &Path) -> Result<PathBuf, AppleCodesignError> {
+        let signature = self.sign(
+            &path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            &std::fs::read(path)?,
+        )?;
+
+        let signature_path = ArtifactSignature::path_for(path);
+        signature.write(&signature_path)?;
+
+        Ok(signature_path)
+    }
+}
+
+/// A detached signature of an artifact.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactSignature {
+    /// File name of the signed artifact.
+    pub artifact: String,
+
+    /// Always [ARTIFACT_SIGNATURE_ALGORITHM].
+    pub algorithm: String,
+
+    /// Hex encoded SHA-256 of the SEC1 encoded public key of the signer.
+    pub key_id: String,
+
+    /// Hex encoded SHA-256 of the artifact.
+    pub sha256: String,
+
+    /// Base64 encoded DER ECDSA signature of the artifact.
+    pub signature: String,
+
+    /// RFC 3339 time the artifact was signed.
+    pub signed_at: String,
+}
+
+impl ArtifactSignature {
+    /// The path of the signature of a file: `<path>.sig`.
+    pub fn path_for(path: &Path) -> PathBuf {
+        let mut path = path.as_os_str().to_owned();
+        path.push(".sig");
+
+        PathBuf::from(path)
+    }
+
+    /// Read a signature from a JSON file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// Write this signature to a JSON file.
+    pub fn write(&self, path: &Path) -> Result<(), AppleCodesignError> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Describe why this isn't a valid signature of content by a key.
+    ///
+    /// `public_key_pem` is the PEM encoded public key of the trusted signer.
+    /// Empty if the signature is valid.
+    pub fn problems(
+        &self,
+        data: &[u8],
+        public_key_pem: &str,
+    ) -> Result<Vec<String>, AppleCodesignError> {
+        let pem = pem::parse(public_key_pem).map_err(AppleCodesignError::CertificatePem)?;
+        let public_key = p256::PublicKey::from_public_key_der(&pem.contents)
+            .map_err(|e| AppleCodesignError::ArtifactSignature(e.to_string()))?;
+        let public_key_data = public_key.to_encoded_point(false);
+
+        let mut problems = vec![];
+
+        if self.algorithm != ARTIFACT_SIGNATURE_ALGORITHM {
+            problems.push(format!(
+                "unsupported signature algorithm {}",
+                self.algorithm
+            ));
+            return Ok(problems);
+        }
+
+        let expected_key_id = key_id(public_key_data.as_bytes());
+        if self.key_id != expected_key_id {
+            problems.push(format!(
+                "signed by key {}; the trusted key is {}",
+                self.key_id, expected_key_id
+            ));
+        }
+
+        let sha256 = hex::encode(sha2::Sha256::digest(data));
+        if sha256 != self.sha256 {
+            problems.push(format!("SHA-256 is {}; {} was signed", sha256, self.sha256));
+        }
+
+        let signature = base64::decode(&self.signature)?;
+        if UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, public_key_data.as_bytes())
+            .verify(data, &signature)
+            .is_err()
+        {
+            problems.push("signature does not match the content and trusted key".to_string());
+        }
+
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let artifact = td.path().join("export.ndjson");
+        std::fs::write(&artifact, b"{\"id\":\"A1\"}\n")?;
+
+        let key_path = td.path().join("key.p8");
+        std::fs::write(
+            &key_path,
+            pem::encode(&pem::Pem {
+                tag: "PRIVATE KEY".to_string(),
+                contents: include_bytes!("../testdata/secp256r1.pk8").to_vec(),
+            }),
+        )?;
+
+        let signer = ArtifactSigner::from_path(&key_path)?;
+        let signature_path = signer.sign_file(&artifact)?;
+        assert!(signature_path.ends_with("export.ndjson.sig"));
+
+        let signature = ArtifactSignature::from_path(&signature_path)?;
+        assert_eq!(signature.artifact, "export.ndjson");
+        assert_eq!(signature.key_id, signer.key_id());
+
+        let public_key = signer.public_key_pem()?;
+        assert!(public_key.starts_with("-----BEGIN PUBLIC KEY-----"));
+        assert!(signature
+            .problems(&std::fs::read(&artifact)?, &public_key)?
+            .is_empty());
+
+        // Altered content.
+        assert_eq!(
+            signature.problems(b"{\"id\":\"A2\"}\n", &public_key)?.len(),
+            2
+        );
+
+        // RSA keys aren't supported.
+        assert!(
+            ArtifactSigner::new(InMemoryPrivateKey::from_pkcs8_der(include_bytes!(
+                "../testdata/rsa-2048.pk8"
+            ))?)
+            .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Asset uploads to App Store Connect.
+//!
+//! Files (screenshots, review attachments, etc) are uploaded to App Store Connect
+//! through *asset reservations*. The client creates a resource describing the file
+//! it wants to upload. The server responds with a list of *upload operations*,
+//! each describing a byte range of the file and the URL and headers it should be
+//! sent to. Once all operations are performed, the client commits the reservation
+//! by sending the MD5 checksum of the file.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/uploading_assets_to_app_store_connect>.
+
+pub use asconnect_types::assets::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            bandwidth::ThrottledReader, progress::ProgressEvent, AppStoreConnectClient,
+        },
+        redact::redact,
+        AppleCodesignError,
+    },
+    log::{debug, info},
+    md5::Digest,
+    reqwest::{blocking::Body, Method},
+    std::{io::Cursor, str::FromStr},
+};
+
+/// Compute the hex encoded MD5 of asset data, as required to commit a reservation.
+pub fn asset_checksum(data: &[u8]) -> String {
+    hex::encode(md5::Md5::digest(data))
+}
+
+impl AppStoreConnectClient {
+    /// Perform the upload operations of an asset reservation.
+    ///
+    /// `data` is the full content of the asset being uploaded. Each operation
+    /// uploads the byte range it describes. `name` identifies the asset in
+    /// progress events.
+    ///
+    /// Upload URLs are pre-authorized, so no App Store Connect token is sent.
+    pub fn perform_upload_operations(
+        &self,
+        name: &str,
+        operations: &[UploadOperation],
+        data: &[u8],
+    ) -> Result<(), AppleCodesignError> {
+        info!(
+            "uploading {} bytes in {} operations",
+            data.len(),
+            operations.len()
+        );
+
+        let total = operations
+            .iter()
+            .fold(0u64, |total, op| total.saturating_add(op.length));
+        let mut uploaded = 0;
+        self.report_progress(
+            ProgressEvent::new("asset-upload", name, "uploading").with_bytes(uploaded, total),
+        );
+
+        for op in operations {
+            // Offsets and lengths come from the server, so guard against overflow.
+            let chunk = usize::try_from(op.offset)
+                .ok()
+                .zip(usize::try_from(op.length).ok())
+                .and_then(|(start, length)| Some(start..start.checked_add(length)?))
+                .and_then(|range| data.get(range))
+                .ok_or_else(|| {
+                    AppleCodesignError::AppStoreConnectUpload(format!(
+                        "upload operation of {} bytes at offset {} exceeds asset size {}",
+                        op.length,
+                        op.offset,
+                        data.len()
+                    ))
+                })?;
+
+            let method = Method::from_str(&op.method).map_err(|_| {
+                AppleCodesignError::AppStoreConnectUpload(format!(
+                    "unsupported upload method: {}",
+                    op.method
+                ))
+            })?;
+
+            debug!("{} {} ({} bytes)", op.method, redact(&op.url), chunk.len());
+
+            let body = match &self.upload_bucket {
+                Some(bucket) => Body::sized(
+                    ThrottledReader::new(Cursor::new(chunk.to_vec()), bucket.clone()),
+                    chunk.len() as u64,
+                ),
+                None => Body::from(chunk.to_vec()),
+            };
+
+            let mut req = self
+                .client
+                .request(method, &op.url)
+                .timeout(self.upload_timeout()?)
+                .body(body);
+            for header in &op.request_headers {
+                req = req.header(&header.name, &header.value);
+            }
+
+            self.send_request_raw(req)?;
+
+            uploaded += op.length;
+            self.report_progress(
+                ProgressEvent::new(
+                    "asset-upload",
+                    name,
+                    if uploaded == total {
+                        "uploaded"
+                    } else {
+                        "uploading"
+                    },
+                )
+                .with_bytes(uploaded, total),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::app_store_connect::testing::MockServerBuilder};
+
+    #[test]
+    fn operation_ranges() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default().start()?;
+        let client = server.client()?;
+
+        let op = |offset, length| UploadOperation {
+            method: "PUT".into(),
+            url: format!("{}/upload", server.url()),
+            length,
+            offset,
+            request_headers: vec![],
+        };
+
+        for (offset, length) in [(2, 4), (u64::MAX, 2), (1, u64::MAX)] {
+            assert!(matches!(
+                client.perform_upload_operations("asset", &[op(offset, length)], b"abcd"),
+                Err(AppleCodesignError::AppStoreConnectUpload(_))
+            ));
+        }
+        assert!(server.requests().is_empty());
+
+        Ok(())
+    }
+}
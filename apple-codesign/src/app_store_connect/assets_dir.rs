@@ -0,0 +1,723 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A managed directory of signing assets for hosts without a keychain.
+//!
+//! On macOS, certificates live in the keychain and Xcode installs provisioning
+//! profiles into well-known directories. Other hosts signing with `rcodesign`
+//! have no such convention, so downloaded certificates, profiles, and keys end
+//! up wherever a script put them. A [SigningAssetsDir] gives them a
+//! predictable home other tools can point at:
+//!
+//! * `certs/<SHA-256 fingerprint>.cer`: DER encoded certificates.
+//! * `profiles/<UUID>.mobileprovision`: provisioning profiles.
+//! * `keys/<SHA-256 of the public key>.key`: PEM encoded PKCS#8 private keys.
+//! * `manifests/*.toml`: [AssetsManifest]s of the assets tools need.
+//!
+//! The default root is `asconnect` in the user data directory (e.g.
+//! `~/.local/share/asconnect` on Linux and `%APPDATA%\asconnect` on Windows).
+//! Installing an asset that is already present replaces it, so installing is
+//! idempotent.
+//!
+//! [SigningAssetsDir::gc()] removes certificates and profiles that expired,
+//! were revoked in the account, or aren't referenced by any [AssetsManifest],
+//! keeping CI images lean. Private keys are only removed on request.
+
+use {
+    crate::{
+        app_store_connect::{
+            certs_api::ProfileState, ipa_preflight::ProvisioningProfile, set_permissions_private,
+            AppStoreConnectClient,
+        },
+        cryptography::InMemoryPrivateKey,
+        AppleCodesignError,
+    },
+    chrono::{DateTime, Utc},
+    pkcs8::EncodePrivateKey,
+    serde::{Deserialize, Serialize},
+    sha2::Digest,
+    std::{
+        collections::BTreeSet,
+        io::Write,
+        path::{Path, PathBuf},
+    },
+    x509_certificate::{CapturedX509Certificate, Sign},
+};
+
+/// The kind of an asset in a [SigningAssetsDir].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetKind {
+    Certificate,
+    Profile,
+    Key,
+}
+
+impl AssetKind {
+    /// Name of the subdirectory holding assets of this kind.
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            Self::Certificate => "certs",
+            Self::Profile => "profiles",
+            Self::Key => "keys",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Certificate => "cer",
+            Self::Profile => "mobileprovision",
+            Self::Key => "key",
+        }
+    }
+}
+
+/// An asset in a [SigningAssetsDir].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetEntry {
+    pub kind: AssetKind,
+    pub path: PathBuf,
+
+    /// Common name of a certificate or name of a profile.
+    pub name: Option<String>,
+
+    /// RFC 3339 time a certificate or profile expires.
+    pub expiration_date: Option<String>,
+
+    /// Fingerprint of a certificate, lowercase UUID of a profile, or SHA-256
+    /// of the public key of a key.
+    #[serde(skip)]
+    id: String,
+
+    /// Hex encoded SHA-256 of the public key of a certificate or key.
+    #[serde(skip)]
+    public_key_digest: Option<String>,
+
+    /// Fingerprints of the certificates embedded in a profile.
+    #[serde(skip)]
+    certificate_fingerprints: Vec<String>,
+
+    #[serde(skip)]
+    expires: Option<DateTime<Utc>>,
+}
+
+impl AssetEntry {
+    /// Whether the asset expired before `now`. Keys don't expire.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.map(|t| t < now).unwrap_or_default()
+    }
+}
+
+/// Assets a project needs, which [SigningAssetsDir::gc()] keeps.
+///
+/// Manifests are TOML files:
+///
+/// ```toml
+/// profiles = ["9d8a3c9e-0000-4000-8000-000000000000", "MyApp App Store"]
+/// certificates = ["Apple Distribution: Example (ABCDE12345)"]
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetsManifest {
+    /// UUIDs or names of provisioning profiles.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+
+    /// Hex encoded SHA-256 fingerprints or common names of certificates.
+    #[serde(default)]
+    pub certificates: Vec<String>,
+}
+
+impl AssetsManifest {
+    /// Parse a manifest from TOML.
+    pub fn from_toml(data: &str) -> Result<Self, AppleCodesignError> {
+        toml::from_str(data).map_err(|e| AppleCodesignError::AssetsManifest(e.to_string()))
+    }
+
+    /// Read a manifest from a TOML file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| AppleCodesignError::AssetsManifest(format!("{}: {}", path.display(), e)))
+    }
+
+    fn references_profile(&self, entry: &AssetEntry) -> bool {
+        self.profiles
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(&entry.id) || Some(p) == entry.name.as_ref())
+    }
+
+    fn references_certificate(&self, entry: &AssetEntry) -> bool {
+        self.certificates
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(&entry.id) || Some(c) == entry.name.as_ref())
+    }
+}
+
+/// Certificates and active profiles of an account.
+#[derive(Clone, Debug, Default)]
+pub struct AccountAssets {
+    /// Hex encoded SHA-256 fingerprints of certificates.
+    pub certificate_fingerprints: BTreeSet<String>,
+
+    /// Lowercase UUIDs of active profiles.
+    pub profile_uuids: BTreeSet<String>,
+}
+
+impl AccountAssets {
+    fn contains(&self, entry: &AssetEntry) -> bool {
+        match entry.kind {
+            AssetKind::Certificate => self.certificate_fingerprints.contains(&entry.id),
+            AssetKind::Profile => self.profile_uuids.contains(&entry.id),
+            AssetKind::Key => true,
+        }
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Fetch the certificates and active profiles of the account.
+    pub fn account_assets(&self) -> Result<AccountAssets, AppleCodesignError> {
+        let mut assets = AccountAssets::default();
+
+        for cert in self.list_certificates()? {
+            let der = base64::decode(
+                cert.attributes
+                    .certificate_content
+                    .as_deref()
+                    .unwrap_or_default(),
+            )?;
+            assets.certificate_fingerprints.insert(sha256_fingerprint(
+                &CapturedX509Certificate::from_der(der)?,
+            )?);
+        }
+
+        for profile in self.list_profiles()? {
+            if profile.attributes.profile_state == Some(ProfileState::Active) {
+                if let Some(uuid) = profile.attributes.uuid {
+                    assets.profile_uuids.insert(uuid.to_lowercase());
+                }
+            }
+        }
+
+        Ok(assets)
+    }
+}
+
+/// Controls what [SigningAssetsDir::gc()] removes.
+#[derive(Clone, Debug)]
+pub struct GcOptions {
+    /// Certificates and profiles expiring before this time are removed.
+    pub now: DateTime<Utc>,
+
+    /// Assets of the account. If set, certificates and profiles not in the
+    /// account are removed.
+    pub account: Option<AccountAssets>,
+
+    /// If not empty, certificates and profiles referenced by none of these
+    /// are removed.
+    pub manifests: Vec<AssetsManifest>,
+
+    /// Whether to remove private keys of removed certificates.
+    pub include_keys: bool,
+}
+
+impl GcOptions {
+    /// Only remove expired certificates and profiles.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now,
+            account: None,
+            manifests: vec![],
+            include_keys: false,
+        }
+    }
+}
+
+/// Why [SigningAssetsDir::gc()] removes an asset.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GcReason {
+    Expired,
+    /// Revoked or deleted in the account.
+    Revoked,
+    Unreferenced,
+    /// A key all of whose certificates are removed.
+    CertificateRemoved,
+}
+
+impl std::fmt::Display for GcReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Expired => "expired",
+            Self::Revoked => "revoked",
+            Self::Unreferenced => "unreferenced",
+            Self::CertificateRemoved => "certificate removed",
+        })
+    }
+}
+
+/// An asset [SigningAssetsDir::gc()] removes.
+#[derive(Clone, Debug, Serialize)]
+pub struct GcCandidate {
+    #[serde(flatten)]
+    pub entry: AssetEntry,
+    pub reason: GcReason,
+}
+
+fn sha256_fingerprint(cert: &CapturedX509Certificate) -> Result<String, AppleCodesignError> {
+    Ok(hex::encode(cert.sha256_fingerprint()?.as_ref()))
+}
+
+fn public_key_digest(public_key_data: &[u8]) -> String {
+    hex::encode(sha2::Sha256::digest(public_key_data))
+}
+
+/// A directory of certificates, provisioning profiles, and private keys.
+///
+/// See the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct SigningAssetsDir {
+    root: PathBuf,
+}
+
+impl SigningAssetsDir {
+    /// Use a directory as the root.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The default root, `asconnect` in the user data directory.
+    pub fn default_root() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("asconnect"))
+    }
+
+    /// Use the default root.
+    pub fn from_default_root() -> Result<Self, AppleCodesignError> {
+        Self::default_root().map(Self::new).ok_or_else(|| {
+            AppleCodesignError::CliGeneralError(
+                "unable to determine the user data directory".into(),
+            )
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory holding assets of a kind.
+    pub fn dir(&self, kind: AssetKind) -> PathBuf {
+        self.root.join(kind.dir_name())
+    }
+
+    fn write(
+        &self,
+        kind: AssetKind,
+        stem: &str,
+        data: &[u8],
+    ) -> Result<PathBuf, AppleCodesignError> {
+        let dir = self.dir(kind);
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!("{}.{}", stem, kind.extension()));
+
+        let mut fh = std::fs::File::create(&path)?;
+        if kind == AssetKind::Key {
+            let mut permissions = fh.metadata()?.permissions();
+            set_permissions_private(&mut permissions);
+            fh.set_permissions(permissions)?;
+        }
+        fh.write_all(data)?;
+
+        Ok(path)
+    }
+
+    /// Install a certificate.
+    pub fn install_certificate(
+        &self,
+        cert: &CapturedX509Certificate,
+    ) -> Result<PathBuf, AppleCodesignError> {
+        self.write(
+            AssetKind::Certificate,
+            &sha256_fingerprint(cert)?,
+            cert.constructed_data(),
+        )
+    }
+
+    /// Install the content of a `.mobileprovision` or `.provisionprofile` file.
+    pub fn install_profile(&self, data: &[u8]) -> Result<PathBuf, AppleCodesignError> {
+        let profile = ProvisioningProfile::from_der(data)?;
+
+        self.write(AssetKind::Profile, &profile.uuid.to_lowercase(), data)
+    }
+
+    /// Install a private key.
+    pub fn install_key(&self, key: &InMemoryPrivateKey) -> Result<PathBuf, AppleCodesignError> {
+        let der = key.to_pkcs8_der().map_err(|e| {
+            AppleCodesignError::CertificateGeneric(format!("when encoding PKCS#8 data: {}", e))
+        })?;
+
+        let pem = pem::encode(&pem::Pem {
+            tag: "PRIVATE KEY".to_string(),
+            contents: der.as_bytes().to_vec(),
+        });
+
+        self.write(
+            AssetKind::Key,
+            &public_key_digest(key.public_key_data().as_ref()),
+            pem.as_bytes(),
+        )
+    }
+
+    /// Install the assets in a file.
+    ///
+    /// `.mobileprovision` and `.provisionprofile` files are installed as
+    /// profiles. PEM files may hold any number of certificates and private
+    /// keys. Other files must be a DER encoded certificate or PKCS#8 private
+    /// key.
+    pub fn install_file(&self, path: &Path) -> Result<Vec<PathBuf>, AppleCodesignError> {
+        let data = std::fs::read(path)?;
+
+        if matches!(
+            path.extension().and_then(|x| x.to_str()),
+            Some("mobileprovision" | "provisionprofile")
+        ) {
+            return Ok(vec![self.install_profile(&data)?]);
+        }
+
+        if data.starts_with(b"-----BEGIN") {
+            let mut installed = vec![];
+
+            for pem in pem::parse_many(&data).map_err(AppleCodesignError::CertificatePem)? {
+                installed.push(match pem.tag.as_str() {
+                    "CERTIFICATE" => {
+                        self.install_certificate(&CapturedX509Certificate::from_der(pem.contents)?)?
+                    }
+                    "PRIVATE KEY" => {
+                        self.install_key(&InMemoryPrivateKey::from_pkcs8_der(&pem.contents)?)?
+                    }
+                    "RSA PRIVATE KEY" => {
+                        self.install_key(&InMemoryPrivateKey::from_pkcs1_der(&pem.contents)?)?
+                    }
+                    tag => {
+                        return Err(AppleCodesignError::CliGeneralError(format!(
+                            "{}: unhandled PEM tag {}",
+                            path.display(),
+                            tag
+                        )))
+                    }
+                });
+            }
+
+            return Ok(installed);
+        }
+
+        if let Ok(cert) = CapturedX509Certificate::from_der(data.clone()) {
+            Ok(vec![self.install_certificate(&cert)?])
+        } else {
+            Ok(vec![self.install_key(
+                &InMemoryPrivateKey::from_pkcs8_der(&data)?,
+            )?])
+        }
+    }
+
+    fn read_entry(kind: AssetKind, path: PathBuf) -> Result<AssetEntry, AppleCodesignError> {
+        let data = std::fs::read(&path)?;
+
+        let mut entry = AssetEntry {
+            kind,
+            path,
+            name: None,
+            expiration_date: None,
+            id: String::new(),
+            public_key_digest: None,
+            certificate_fingerprints: vec![],
+            expires: None,
+        };
+
+        match kind {
+            AssetKind::Certificate => {
+                let cert = CapturedX509Certificate::from_der(data)?;
+                entry.id = sha256_fingerprint(&cert)?;
+                entry.name = cert.subject_common_name();
+                entry.expires = Some(cert.validity_not_after());
+                entry.public_key_digest = Some(public_key_digest(cert.public_key_data().as_ref()));
+            }
+            AssetKind::Profile => {
+                let profile = ProvisioningProfile::from_der(&data)?;
+                entry.id = profile.uuid.to_lowercase();
+                entry.certificate_fingerprints = profile
+                    .developer_certificates
+                    .iter()
+                    .map(sha256_fingerprint)
+                    .collect::<Result<_, _>>()?;
+                entry.name = Some(profile.name);
+                entry.expires = Some(profile.expiration_date);
+            }
+            AssetKind::Key => {
+                let pem = pem::parse(&data).map_err(AppleCodesignError::CertificatePem)?;
+                let key = InMemoryPrivateKey::from_pkcs8_der(&pem.contents)?;
+                entry.id = public_key_digest(key.public_key_data().as_ref());
+                entry.public_key_digest = Some(entry.id.clone());
+            }
+        }
+
+        entry.expiration_date = entry.expires.map(|t| t.to_rfc3339());
+
+        Ok(entry)
+    }
+
+    /// List installed assets, ordered by kind and path.
+    ///
+    /// Files that can't be parsed are skipped with a warning.
+    pub fn list(&self) -> Result<Vec<AssetEntry>, AppleCodesignError> {
+        let mut entries = vec![];
+
+        for kind in [AssetKind::Certificate, AssetKind::Profile, AssetKind::Key] {
+            let dir = self.dir(kind);
+            if !dir.is_dir() {
+                continue;
+            }
+
+            let mut paths = std::fs::read_dir(&dir)?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<Vec<_>, std::io::Error>>()?;
+            paths.sort();
+
+            for path in paths {
+                if path.extension().and_then(|x| x.to_str()) != Some(kind.extension()) {
+                    continue;
+                }
+
+                match Self::read_entry(kind, path.clone()) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => log::warn!("unable to read {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Read the manifests in the `manifests` directory.
+    pub fn manifests(&self) -> Result<Vec<AssetsManifest>, AppleCodesignError> {
+        let dir = self.root.join("manifests");
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+
+        let mut paths = std::fs::read_dir(&dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        paths.sort();
+
+        paths
+            .iter()
+            .filter(|path| path.extension().and_then(|x| x.to_str()) == Some("toml"))
+            .map(|path| AssetsManifest::from_path(path))
+            .collect()
+    }
+
+    /// Find assets to remove and, unless `dry_run`, remove them.
+    ///
+    /// Certificates and profiles are removed if they expired, if they aren't
+    /// in [GcOptions::account], or if they aren't referenced by any of
+    /// [GcOptions::manifests]. Certificates embedded in a referenced profile
+    /// are referenced.
+    ///
+    /// Private keys are only removed with [GcOptions::include_keys], and only
+    /// if all certificates using them are removed. Keys without any
+    /// certificate are kept, as they may belong to a certificate that hasn't
+    /// been issued yet.
+    pub fn gc(
+        &self,
+        options: &GcOptions,
+        dry_run: bool,
+    ) -> Result<Vec<GcCandidate>, AppleCodesignError> {
+        let entries = self.list()?;
+
+        let referenced_profiles = entries
+            .iter()
+            .filter(|e| {
+                e.kind == AssetKind::Profile
+                    && options.manifests.iter().any(|m| m.references_profile(e))
+            })
+            .collect::<Vec<_>>();
+
+        let is_referenced = |entry: &AssetEntry| match entry.kind {
+            AssetKind::Certificate => {
+                options
+                    .manifests
+                    .iter()
+                    .any(|m| m.references_certificate(entry))
+                    || referenced_profiles
+                        .iter()
+                        .any(|p| p.certificate_fingerprints.contains(&entry.id))
+            }
+            AssetKind::Profile => referenced_profiles.iter().any(|p| p.path == entry.path),
+            AssetKind::Key => true,
+        };
+
+        let mut removed = vec![];
+        let mut kept = vec![];
+
+        for entry in entries {
+            let reason = if entry.kind == AssetKind::Key {
+                None
+            } else if entry.is_expired(options.now) {
+                Some(GcReason::Expired)
+            } else if !options
+                .account
+                .as_ref()
+                .map(|account| account.contains(&entry))
+                .unwrap_or(true)
+            {
+                Some(GcReason::Revoked)
+            } else if !options.manifests.is_empty() && !is_referenced(&entry) {
+                Some(GcReason::Unreferenced)
+            } else {
+                None
+            };
+
+            match reason {
+                Some(reason) => removed.push(GcCandidate { entry, reason }),
+                None => kept.push(entry),
+            }
+        }
+
+        if options.include_keys {
+            let uses_key = |cert: &AssetEntry, key: &AssetEntry| {
+                cert.kind == AssetKind::Certificate
+                    && cert.public_key_digest == key.public_key_digest
+            };
+
+            let keys = kept
+                .iter()
+                .filter(|key| {
+                    key.kind == AssetKind::Key
+                        && removed.iter().any(|c| uses_key(&c.entry, key))
+                        && !kept.iter().any(|c| uses_key(c, key))
+                })
+                .map(|key| GcCandidate {
+                    entry: key.clone(),
+                    reason: GcReason::CertificateRemoved,
+                })
+                .collect::<Vec<_>>();
+
+            removed.extend(keys);
+        }
+
+        if !dry_run {
+            for candidate in &removed {
+                std::fs::remove_file(&candidate.entry.path)?;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::certificate::{create_self_signed_code_signing_certificate, CertificateProfile},
+        std::collections::BTreeMap,
+        x509_certificate::{EcdsaCurve, KeyAlgorithm},
+    };
+
+    #[test]
+    fn install_and_gc() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let assets = SigningAssetsDir::new(td.path());
+        assert!(assets.list()?.is_empty());
+
+        let mut installed_paths = vec![];
+        for (name, days) in [("Expiring", 10), ("Current", 100), ("Unused", 100)] {
+            let (cert, _, key_document) = create_self_signed_code_signing_certificate(
+                KeyAlgorithm::Ecdsa(EcdsaCurve::Secp256r1),
+                CertificateProfile::AppleDistribution,
+                "ABCDE12345",
+                name,
+                "US",
+                chrono::Duration::days(days),
+            )?;
+
+            let pem_path = td.path().join(format!("{}.pem", name));
+            std::fs::write(
+                &pem_path,
+                format!(
+                    "{}{}",
+                    cert.encode_pem(),
+                    pem::encode(&pem::Pem {
+                        tag: "PRIVATE KEY".to_string(),
+                        contents: key_document.as_ref().to_vec(),
+                    })
+                ),
+            )?;
+
+            let installed = assets.install_file(&pem_path)?;
+            assert_eq!(installed.len(), 2);
+            assert!(installed[0].starts_with(td.path().join("certs")));
+            assert!(installed[1].starts_with(td.path().join("keys")));
+
+            // Installing again replaces the same files.
+            assert_eq!(assets.install_file(&pem_path)?, installed);
+
+            installed_paths.push(installed);
+        }
+
+        let entries = assets.list()?;
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0].kind, AssetKind::Certificate);
+        assert_eq!(entries[5].kind, AssetKind::Key);
+
+        let mut options = GcOptions::new(Utc::now() + chrono::Duration::days(30));
+
+        // Only expired certificates are removed by default.
+        let removed = assets.gc(&options, true)?;
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].entry.name.as_deref(), Some("Expiring"));
+        assert_eq!(removed[0].reason, GcReason::Expired);
+
+        std::fs::create_dir(td.path().join("manifests"))?;
+        std::fs::write(
+            td.path().join("manifests").join("app.toml"),
+            "certificates = [\"Current\"]\n",
+        )?;
+        options.manifests = assets.manifests()?;
+        options.include_keys = true;
+
+        let removed = assets
+            .gc(&options, false)?
+            .into_iter()
+            .map(|c| (c.entry.path, c.reason))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            removed,
+            BTreeMap::from([
+                (installed_paths[0][0].clone(), GcReason::Expired),
+                (installed_paths[2][0].clone(), GcReason::Unreferenced),
+                (installed_paths[0][1].clone(), GcReason::CertificateRemoved),
+                (installed_paths[2][1].clone(), GcReason::CertificateRemoved),
+            ])
+        );
+
+        let entries = assets.list()?;
+        assert_eq!(
+            entries.iter().map(|e| &e.path).collect::<Vec<_>>(),
+            vec![&installed_paths[1][0], &installed_paths[1][1]]
+        );
+
+        // Certificates not in the account are revoked.
+        options.manifests.clear();
+        options.account = Some(AccountAssets::default());
+        let removed = assets.gc(&options, true)?;
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0].reason, GcReason::Revoked);
+
+        assert!(AssetsManifest::from_toml("profile = []").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,806 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An async client to the App Store Connect API.
+//!
+//! The [blocking client](super::AppStoreConnectClient) is built on
+//! `reqwest::blocking`, which panics when used from within a tokio runtime.
+//! [AppStoreConnectClient] offers the certificate, device, profile, notary,
+//! and build upload methods of the blocking client as `async` functions using
+//! the async [reqwest::Client], for build orchestration services running on
+//! tokio. Requests and responses use the same models as the blocking client.
+//!
+//! Tokens are minted from a [ConnectTokenEncoder], honoring its lifetime and
+//! scope. Other facilities of the blocking client, such as token caches,
+//! read-only mode, HTTP captures, and progress sinks, aren't available.
+//!
+//! This module is available with the `async` feature.
+
+use {
+    crate::{
+        app_store_connect::{
+            api_error::ApiError,
+            api_token::{AppStoreConnectToken, ConnectTokenEncoder},
+            asset_upload::UploadOperation,
+            build_api::Build,
+            build_upload_api::{
+                build_upload_file_commit_request, build_upload_file_request, build_upload_request,
+                validate_ipa_for_upload, BuildUpload, BuildUploadFile, BuildUploadState, IpaInfo,
+                Platform,
+            },
+            certs_api::{
+                certificate_request, device_request, normalize_udid, profile_request, AccountType,
+                Certificate, CertificateType, Device, DeviceAttributes, DownloadedProfile, Profile,
+                ProfileType,
+            },
+            json_api::{CertificateQuery, DeviceQuery, Document, ErrorDocument, Resource},
+            notary_api::{
+                NewSubmissionRequest, NewSubmissionResponse, SubmissionListResponse,
+                SubmissionLogResponse, SubmissionResponse, SubmissionResponseStatus,
+                APPLE_NOTARY_SUBMIT_SOFTWARE_URL,
+            },
+            APP_STORE_CONNECT_API_URL, TOKEN_REFRESH_FRACTION,
+        },
+        notarization::s3_config_loader,
+        redact::redact,
+        AppleCodesignError,
+    },
+    aws_smithy_http::byte_stream::ByteStream,
+    log::{debug, error, info, warn},
+    reqwest::{Method, RequestBuilder, Response},
+    serde::{de::DeserializeOwned, Serialize},
+    serde_json::Value,
+    sha2::Digest,
+    std::{
+        str::FromStr,
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Send a request with `client` and return the successful [Response].
+///
+/// Error responses are converted to [AppleCodesignError::AppStoreConnectApi].
+pub(crate) async fn execute(
+    client: &reqwest::Client,
+    request: RequestBuilder,
+) -> Result<Response, AppleCodesignError> {
+    let request = request.build()?;
+    let url = request.url().to_string();
+
+    debug!("{} {}", request.method(), redact(&url));
+
+    let response = client.execute(request).await?;
+
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        error!("HTTP error from {}", redact(&url));
+
+        let status = response.status().as_u16();
+        let body = response.bytes().await?;
+
+        if let Ok(document) = serde_json::from_slice::<ErrorDocument>(body.as_ref()) {
+            if !document.errors.is_empty() {
+                return Err(AppleCodesignError::AppStoreConnectApi(ApiError::new(
+                    status, document,
+                )));
+            }
+        }
+
+        if let Ok(value) = serde_json::from_slice::<Value>(body.as_ref()) {
+            for line in redact(&serde_json::to_string_pretty(&value)?).lines() {
+                error!("{}", line);
+            }
+        } else {
+            error!("{}", redact(&String::from_utf8_lossy(body.as_ref())));
+        }
+
+        Err(AppleCodesignError::AppStoreConnectApi(
+            ApiError::from_status(status),
+        ))
+    }
+}
+
+/// An async client for App Store Connect API.
+pub struct AppStoreConnectClient {
+    client: reqwest::Client,
+    api_url: String,
+    notary_url: String,
+    connect_token: ConnectTokenEncoder,
+    token: Mutex<Option<(AppStoreConnectToken, Instant)>>,
+    account_type: AccountType,
+}
+
+impl AppStoreConnectClient {
+    /// Create a new client to the App Store Connect API.
+    pub fn new(connect_token: ConnectTokenEncoder) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: APP_STORE_CONNECT_API_URL.to_string(),
+            notary_url: APPLE_NOTARY_SUBMIT_SOFTWARE_URL.to_string(),
+            connect_token,
+            token: Mutex::new(None),
+            account_type: AccountType::default(),
+        }
+    }
+
+    /// Set the base URL of the App Store Connect API.
+    pub fn set_api_url(&mut self, url: impl ToString) {
+        self.api_url = url.to_string().trim_end_matches('/').to_string();
+    }
+
+    /// Set the URL of the submissions of the Notary API.
+    ///
+    /// Defaults to [APPLE_NOTARY_SUBMIT_SOFTWARE_URL].
+    pub fn set_notary_url(&mut self, url: impl ToString) {
+        self.notary_url = url.to_string().trim_end_matches('/').to_string();
+    }
+
+    /// Set the type of account the client is authenticated against.
+    ///
+    /// This controls which certificate and profile types may be created.
+    pub fn set_account_type(&mut self, account_type: AccountType) {
+        self.account_type = account_type;
+    }
+
+    /// The ID of the API key the client is authenticated with.
+    pub fn api_key_id(&self) -> &str {
+        self.connect_token.key_id()
+    }
+
+    fn get_token(&self) -> Result<AppStoreConnectToken, AppleCodesignError> {
+        let lifetime = Duration::from_secs(self.connect_token.token_duration());
+        let mut token = self.token.lock().unwrap();
+
+        match token.as_ref() {
+            Some((value, minted))
+                if minted.elapsed() < lifetime.mul_f64(TOKEN_REFRESH_FRACTION) =>
+            {
+                Ok(value.clone())
+            }
+            _ => {
+                let value = self.connect_token.new_token(lifetime.as_secs())?;
+                token.replace((value.clone(), Instant::now()));
+
+                Ok(value)
+            }
+        }
+    }
+
+    /// Construct an authenticated request to an absolute URL.
+    fn request(&self, method: Method, url: &str) -> Result<RequestBuilder, AppleCodesignError> {
+        Ok(self
+            .client
+            .request(method, url)
+            .bearer_auth(self.get_token()?)
+            .header("Accept", "application/json"))
+    }
+
+    /// Send a request and return the successful [Response].
+    async fn send_request_raw(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<Response, AppleCodesignError> {
+        execute(&self.client, request).await
+    }
+
+    async fn send_request<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, AppleCodesignError> {
+        Ok(self.send_request_raw(request).await?.json::<T>().await?)
+    }
+
+    async fn api_get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, AppleCodesignError> {
+        let req = self
+            .request(Method::GET, &format!("{}{}", self.api_url, path))?
+            .query(query);
+
+        self.send_request(req).await
+    }
+
+    /// Fetch all resources of a collection, following pagination links.
+    async fn api_get_all<A: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<Resource<A>>, AppleCodesignError> {
+        let mut page = self
+            .api_get::<Document<Vec<Resource<A>>>>(path, query)
+            .await?;
+        let mut res = vec![];
+
+        loop {
+            res.extend(page.data);
+
+            // Pagination links include the query string.
+            match page.links.and_then(|links| links.next) {
+                Some(next) => {
+                    page = self.send_request(self.request(Method::GET, &next)?).await?;
+                }
+                None => break,
+            }
+        }
+
+        Ok(res)
+    }
+
+    async fn api_send_json<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &B,
+    ) -> Result<T, AppleCodesignError> {
+        let req = self
+            .request(method, &format!("{}{}", self.api_url, path))?
+            .header("Content-Type", "application/json")
+            .json(body);
+
+        self.send_request(req).await
+    }
+
+    async fn api_delete(&self, path: &str) -> Result<(), AppleCodesignError> {
+        let req = self.request(Method::DELETE, &format!("{}{}", self.api_url, path))?;
+
+        self.send_request_raw(req).await?;
+
+        Ok(())
+    }
+
+    /// List signing certificates in the account.
+    pub async fn list_certificates(&self) -> Result<Vec<Certificate>, AppleCodesignError> {
+        self.api_get_all("/v1/certificates", &[]).await
+    }
+
+    /// List signing certificates of a type.
+    pub async fn list_certificates_of_type(
+        &self,
+        certificate_type: CertificateType,
+    ) -> Result<Vec<Certificate>, AppleCodesignError> {
+        self.api_get_all(
+            "/v1/certificates",
+            &CertificateQuery::new()
+                .certificate_type(certificate_type)
+                .to_query(),
+        )
+        .await
+    }
+
+    /// Fetch a signing certificate by its ID.
+    pub async fn get_certificate(
+        &self,
+        certificate_id: &str,
+    ) -> Result<Certificate, AppleCodesignError> {
+        let doc = self
+            .api_get::<Document<Certificate>>(&format!("/v1/certificates/{}", certificate_id), &[])
+            .await?;
+
+        Ok(doc.data)
+    }
+
+    /// Create a signing certificate from a certificate signing request.
+    ///
+    /// The certificate type is validated against the client's [AccountType].
+    pub async fn create_certificate(
+        &self,
+        csr_pem: &str,
+        certificate_type: CertificateType,
+    ) -> Result<Certificate, AppleCodesignError> {
+        let body = certificate_request(self.account_type, csr_pem, certificate_type)?;
+
+        let doc = self
+            .api_send_json::<_, Document<Certificate>>(Method::POST, "/v1/certificates", &body)
+            .await?;
+
+        Ok(doc.data)
+    }
+
+    /// Revoke a signing certificate.
+    pub async fn revoke_certificate(&self, certificate_id: &str) -> Result<(), AppleCodesignError> {
+        self.api_delete(&format!("/v1/certificates/{}", certificate_id))
+            .await
+    }
+
+    /// List provisioning profiles in the account.
+    pub async fn list_profiles(&self) -> Result<Vec<Profile>, AppleCodesignError> {
+        self.api_get_all("/v1/profiles", &[]).await
+    }
+
+    /// Fetch a provisioning profile by its ID.
+    pub async fn get_profile(&self, profile_id: &str) -> Result<Profile, AppleCodesignError> {
+        let doc = self
+            .api_get::<Document<Profile>>(&format!("/v1/profiles/{}", profile_id), &[])
+            .await?;
+
+        Ok(doc.data)
+    }
+
+    /// Download the `.mobileprovision` content of a provisioning profile.
+    ///
+    /// See [DownloadedProfile::from_profile()] for the certificate expiry warnings.
+    pub async fn download_profile(
+        &self,
+        profile_id: &str,
+        certificate_expiry_days: i64,
+    ) -> Result<DownloadedProfile, AppleCodesignError> {
+        DownloadedProfile::from_profile(
+            &self.get_profile(profile_id).await?,
+            certificate_expiry_days,
+        )
+    }
+
+    /// Create a provisioning profile.
+    ///
+    /// See [super::AppStoreConnectClient::create_profile()].
+    pub async fn create_profile(
+        &self,
+        name: &str,
+        profile_type: ProfileType,
+        bundle_id_id: &str,
+        certificate_ids: &[String],
+        device_ids: &[String],
+    ) -> Result<Profile, AppleCodesignError> {
+        let body = profile_request(
+            self.account_type,
+            name,
+            profile_type,
+            bundle_id_id,
+            certificate_ids,
+            device_ids,
+        )?;
+
+        let doc = self
+            .api_send_json::<_, Document<Profile>>(Method::POST, "/v1/profiles", &body)
+            .await?;
+
+        Ok(doc.data)
+    }
+
+    /// Delete a provisioning profile.
+    pub async fn delete_profile(&self, profile_id: &str) -> Result<(), AppleCodesignError> {
+        self.api_delete(&format!("/v1/profiles/{}", profile_id))
+            .await
+    }
+
+    /// List devices registered to the account.
+    pub async fn list_devices(&self) -> Result<Vec<Device>, AppleCodesignError> {
+        self.api_get_all("/v1/devices", &[]).await
+    }
+
+    /// Find a registered device by its UDID.
+    ///
+    /// The UDID is normalized with [normalize_udid()].
+    pub async fn find_device_by_udid(
+        &self,
+        udid: &str,
+    ) -> Result<Option<Device>, AppleCodesignError> {
+        let udid = normalize_udid(udid)?;
+
+        Ok(self
+            .api_get_all::<DeviceAttributes>(
+                "/v1/devices",
+                &DeviceQuery::new().udid(&udid).to_query(),
+            )
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    /// Register a device to the account.
+    ///
+    /// See [super::AppStoreConnectClient::register_device()].
+    pub async fn register_device(
+        &self,
+        name: &str,
+        udid: &str,
+        platform: &str,
+    ) -> Result<Device, AppleCodesignError> {
+        let body = device_request(name, udid, platform)?;
+
+        let doc = self
+            .api_send_json::<_, Document<Device>>(Method::POST, "/v1/devices", &body)
+            .await?;
+
+        Ok(doc.data)
+    }
+
+    /// Create a submission to the Notary API.
+    pub async fn create_submission(
+        &self,
+        sha256: &str,
+        submission_name: &str,
+    ) -> Result<NewSubmissionResponse, AppleCodesignError> {
+        let body = NewSubmissionRequest {
+            notifications: Vec::new(),
+            sha256: sha256.to_string(),
+            submission_name: submission_name.to_string(),
+        };
+
+        let req = self
+            .request(Method::POST, &self.notary_url)?
+            .header("Content-Type", "application/json")
+            .json(&body);
+
+        self.send_request(req).await
+    }
+
+    /// Fetch the status of a Notary API submission.
+    pub async fn get_submission(
+        &self,
+        submission_id: &str,
+    ) -> Result<SubmissionResponse, AppleCodesignError> {
+        let req = self.request(
+            Method::GET,
+            &format!("{}/{}", self.notary_url, submission_id),
+        )?;
+
+        self.send_request(req).await
+    }
+
+    /// Fetch the most recent submissions made by the team.
+    pub async fn list_submissions(&self) -> Result<SubmissionListResponse, AppleCodesignError> {
+        let req = self.request(Method::GET, &self.notary_url)?;
+
+        self.send_request(req).await
+    }
+
+    /// Fetch details about a single completed notarization.
+    pub async fn get_submission_log(
+        &self,
+        submission_id: &str,
+    ) -> Result<Value, AppleCodesignError> {
+        let req = self.request(
+            Method::GET,
+            &format!("{}/{}/logs", self.notary_url, submission_id),
+        )?;
+
+        let res = self.send_request::<SubmissionLogResponse>(req).await?;
+
+        Ok(self
+            .client
+            .get(res.data.attributes.developer_log_url)
+            .send()
+            .await?
+            .json::<Value>()
+            .await?)
+    }
+
+    /// Submit content for notarization.
+    ///
+    /// A submission is created and `data` is uploaded to the S3 object Apple
+    /// provides for it. Returns the created submission. Use
+    /// [Self::wait_on_submission()] to wait for the result.
+    pub async fn notarize_data(
+        &self,
+        submission_name: &str,
+        data: Vec<u8>,
+    ) -> Result<NewSubmissionResponse, AppleCodesignError> {
+        let digest = hex::encode(sha2::Sha256::digest(&data));
+        warn!(
+            "creating Notary API submission for {} (sha256: {})",
+            submission_name, digest
+        );
+
+        let submission = self.create_submission(&digest, submission_name).await?;
+        warn!("created submission ID: {}", submission.data.id);
+
+        let config = s3_config_loader(&submission).load().await;
+        let s3_client = aws_sdk_s3::Client::new(&config);
+
+        warn!(
+            "uploading asset to s3://{}/{}",
+            submission.data.attributes.bucket, submission.data.attributes.object
+        );
+
+        s3_client
+            .put_object()
+            .bucket(submission.data.attributes.bucket.clone())
+            .key(submission.data.attributes.object.clone())
+            .content_length(data.len() as i64)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(aws_sdk_s3::Error::from)?;
+
+        warn!("S3 upload completed successfully");
+
+        Ok(submission)
+    }
+
+    /// Wait for a Notary API submission to finish processing.
+    ///
+    /// Returns the final status, which may be a rejection. Use
+    /// [SubmissionResponse::into_result()] to turn rejections into errors.
+    pub async fn wait_on_submission(
+        &self,
+        submission_id: &str,
+        wait_limit: Duration,
+        poll_interval: Duration,
+    ) -> Result<SubmissionResponse, AppleCodesignError> {
+        warn!(
+            "waiting up to {}s for package upload {} to finish processing",
+            wait_limit.as_secs(),
+            submission_id
+        );
+
+        let start_time = Instant::now();
+
+        loop {
+            let status = self.get_submission(submission_id).await?;
+            let elapsed = start_time.elapsed();
+
+            info!(
+                "poll state after {}s: {:?}",
+                elapsed.as_secs(),
+                status.data.attributes.status
+            );
+
+            if status.data.attributes.status != SubmissionResponseStatus::InProgress {
+                return Ok(status);
+            }
+
+            if elapsed >= wait_limit {
+                warn!("reached wait limit after {}s", elapsed.as_secs());
+                return Err(AppleCodesignError::NotarizeWaitLimitReached);
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Fetch a build upload by its ID.
+    pub async fn get_build_upload(
+        &self,
+        upload_id: &str,
+    ) -> Result<BuildUpload, AppleCodesignError> {
+        let doc = self
+            .api_get::<Document<BuildUpload>>(&format!("/v1/buildUploads/{}", upload_id), &[])
+            .await?;
+
+        Ok(doc.data)
+    }
+
+    /// Upload the content of an `.ipa` file for an app.
+    ///
+    /// The archive is validated like [super::AppStoreConnectClient::upload_ipa()]
+    /// does before anything is uploaded. Use [Self::wait_on_build_upload()] to
+    /// wait for the resulting build.
+    pub async fn upload_ipa_data(
+        &self,
+        app_id: &str,
+        file_name: &str,
+        data: &[u8],
+        platform: Platform,
+    ) -> Result<BuildUpload, AppleCodesignError> {
+        validate_ipa_for_upload(data, platform)?;
+        let info = IpaInfo::from_ipa_data(data)?;
+
+        warn!(
+            "creating build upload for {} {} ({})",
+            info.bundle_identifier, info.short_version, info.version
+        );
+
+        let upload = self
+            .api_send_json::<_, Document<BuildUpload>>(
+                Method::POST,
+                "/v1/buildUploads",
+                &build_upload_request(app_id, &info, platform),
+            )
+            .await?
+            .data;
+
+        let file = self
+            .api_send_json::<_, Document<BuildUploadFile>>(
+                Method::POST,
+                "/v1/buildUploadFiles",
+                &build_upload_file_request(&upload.id, file_name, data.len()),
+            )
+            .await?
+            .data;
+
+        self.perform_upload_operations(
+            file.attributes
+                .upload_operations
+                .as_deref()
+                .unwrap_or_default(),
+            data,
+        )
+        .await?;
+
+        warn!("committing build upload file {}", file.id);
+
+        self.api_send_json::<_, Document<BuildUploadFile>>(
+            Method::PATCH,
+            &format!("/v1/buildUploadFiles/{}", file.id),
+            &build_upload_file_commit_request(&file.id, data),
+        )
+        .await?;
+
+        Ok(upload)
+    }
+
+    /// Perform the upload operations of an asset reservation.
+    ///
+    /// Upload URLs are pre-authorized, so no App Store Connect token is sent.
+    async fn perform_upload_operations(
+        &self,
+        operations: &[UploadOperation],
+        data: &[u8],
+    ) -> Result<(), AppleCodesignError> {
+        warn!(
+            "uploading {} bytes in {} operations",
+            data.len(),
+            operations.len()
+        );
+
+        for op in operations {
+            let start = op.offset as usize;
+            let end = start + op.length as usize;
+
+            let chunk = data.get(start..end).ok_or_else(|| {
+                AppleCodesignError::AppStoreConnectUpload(format!(
+                    "upload operation range {}..{} exceeds asset size {}",
+                    start,
+                    end,
+                    data.len()
+                ))
+            })?;
+
+            let method = Method::from_str(&op.method).map_err(|_| {
+                AppleCodesignError::AppStoreConnectUpload(format!(
+                    "unsupported upload method: {}",
+                    op.method
+                ))
+            })?;
+
+            let mut req = self.client.request(method, &op.url).body(chunk.to_vec());
+            for header in &op.request_headers {
+                req = req.header(&header.name, &header.value);
+            }
+
+            self.send_request_raw(req).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for a build upload to be processed into a build.
+    ///
+    /// See [super::AppStoreConnectClient::wait_on_build_upload()].
+    pub async fn wait_on_build_upload(
+        &self,
+        upload_id: &str,
+        wait_limit: Duration,
+        poll_interval: Duration,
+    ) -> Result<Build, AppleCodesignError> {
+        warn!(
+            "waiting up to {}s for build upload {} to be processed",
+            wait_limit.as_secs(),
+            upload_id
+        );
+
+        let start_time = Instant::now();
+
+        loop {
+            let doc = self
+                .api_get::<Document<BuildUpload>>(
+                    &format!("/v1/buildUploads/{}", upload_id),
+                    &[("include", "build".to_string())],
+                )
+                .await?;
+            let elapsed = start_time.elapsed();
+
+            info!(
+                "poll state after {}s: {:?}",
+                elapsed.as_secs(),
+                doc.data.attributes.state
+            );
+
+            if doc.data.attributes.state == Some(BuildUploadState::Failed) {
+                return Err(AppleCodesignError::BuildProcessingFailed(
+                    upload_id.to_string(),
+                    "build upload failed".into(),
+                ));
+            }
+
+            if let Some(build) = doc
+                .included
+                .into_iter()
+                .filter_map(|v| serde_json::from_value::<Build>(v).ok())
+                .find(|r| r.r#type == "builds")
+            {
+                warn!("build upload {} produced build {}", upload_id, build.id);
+                return Ok(build);
+            }
+
+            if elapsed >= wait_limit {
+                warn!("reached wait limit after {}s", elapsed.as_secs());
+                return Err(AppleCodesignError::BuildProcessingWaitLimitReached);
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::json,
+    };
+
+    #[test]
+    fn certs_devices_profiles() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/certificates",
+                200,
+                fixtures::collection(vec![fixtures::certificate("C1", "DEVELOPMENT", "")]),
+            )
+            .json(
+                "POST",
+                "/v1/devices",
+                201,
+                json!({"data": fixtures::device("D1", "00008030-001A2B3C4D5E6F70")}),
+            )
+            .start()?;
+
+        let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .unwrap();
+        let mut client = AppStoreConnectClient::new(ConnectTokenEncoder::from_ecdsa_der(
+            "MOCKKEY123".into(),
+            "00000000-0000-0000-0000-000000000000".into(),
+            key.as_ref(),
+        )?);
+        client.set_api_url(server.url());
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let certificates = rt.block_on(client.list_certificates())?;
+        assert_eq!(certificates[0].id, "C1");
+
+        let device =
+            rt.block_on(client.register_device("iPhone", "00008030-001a2b3c4d5e6f70", "IOS"))?;
+        assert_eq!(device.id, "D1");
+
+        // Validation happens before any request.
+        assert!(matches!(
+            rt.block_on(client.create_profile(
+                "App Store",
+                ProfileType::IosAppStore,
+                "B1",
+                &["C1".to_string()],
+                &["D1".to_string()],
+            )),
+            Err(AppleCodesignError::AccountTypeMismatch(_))
+        ));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]
+            .header("authorization")
+            .unwrap()
+            .starts_with("Bearer "));
+        let body = serde_json::from_slice::<Value>(&requests[1].body)?;
+        assert_eq!(
+            body["data"]["attributes"]["udid"],
+            "00008030-001A2B3C4D5E6F70"
+        );
+
+        Ok(())
+    }
+}
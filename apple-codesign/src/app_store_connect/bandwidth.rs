@@ -0,0 +1,337 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Limiting the bandwidth of uploads.
+//!
+//! Uploading a multi-gigabyte IPA saturates the uplink of small offices. A
+//! [BandwidthLimit] caps the rate at which asset data (e.g. builds uploaded
+//! through upload operations) and notarization submissions to S3 are sent.
+//!
+//! Bodies are sent in [THROTTLE_CHUNK_SIZE] chunks drawn from a [TokenBucket]
+//! shared by all uploads of a client, so concurrent uploads share the limit.
+//! The bucket holds at most one second worth of bytes, so bursts are short.
+
+use {
+    crate::AppleCodesignError,
+    aws_smithy_http::{body::SdkBody, byte_stream::ByteStream},
+    bytes::Bytes,
+    std::{
+        fmt::{Display, Formatter},
+        future::Future,
+        io::Read,
+        pin::Pin,
+        str::FromStr,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+        time::{Duration, Instant},
+    },
+};
+
+/// Size of the chunks throttled bodies are sent in.
+pub const THROTTLE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A maximum transfer rate, in bytes per second.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BandwidthLimit(u64);
+
+impl BandwidthLimit {
+    /// Construct an instance from a rate in bytes per second.
+    pub fn new(bytes_per_second: u64) -> Result<Self, AppleCodesignError> {
+        if bytes_per_second == 0 {
+            Err(AppleCodesignError::CliGeneralError(
+                "bandwidth limit must be greater than 0".into(),
+            ))
+        } else {
+            Ok(Self(bytes_per_second))
+        }
+    }
+
+    /// The rate in bytes per second.
+    pub fn bytes_per_second(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for BandwidthLimit {
+    type Err = AppleCodesignError;
+
+    /// Parse a rate such as `10MB/s`, `512KiB/s`, or `1.5M`.
+    ///
+    /// Units are decimal (`K`, `M`, `G`) or binary (`Ki`, `Mi`, `Gi`),
+    /// optionally followed by `B` and `/s`. Without a unit, the rate is in
+    /// bytes per second.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            AppleCodesignError::CliGeneralError(format!(
+                "invalid bandwidth limit {}; expected a rate like 10MB/s",
+                s
+            ))
+        };
+
+        let value = s.trim();
+        let value = value.strip_suffix("/s").unwrap_or(value);
+        let split = value
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(split);
+
+        let number = number.parse::<f64>().map_err(|_| invalid())?;
+        let unit = unit.trim();
+        let unit = unit
+            .strip_suffix('B')
+            .or_else(|| unit.strip_suffix('b'))
+            .unwrap_or(unit);
+
+        let multiplier = match unit {
+            "" => 1,
+            "k" | "K" => 1000,
+            "M" => 1000 * 1000,
+            "G" => 1000 * 1000 * 1000,
+            "Ki" => 1024,
+            "Mi" => 1024 * 1024,
+            "Gi" => 1024 * 1024 * 1024,
+            _ => return Err(invalid()),
+        };
+
+        Self::new((number * multiplier as f64) as u64).map_err(|_| invalid())
+    }
+}
+
+impl Display for BandwidthLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}B/s", self.0)
+    }
+}
+
+/// A token bucket metering bytes.
+#[derive(Debug)]
+pub struct TokenBucket {
+    limit: BandwidthLimit,
+    rate: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// Construct a full bucket refilling at `limit`.
+    pub fn new(limit: BandwidthLimit) -> Self {
+        let rate = limit.bytes_per_second() as f64;
+
+        Self {
+            limit,
+            rate,
+            state: Mutex::new((rate, Instant::now())),
+        }
+    }
+
+    /// The rate the bucket refills at.
+    pub fn limit(&self) -> BandwidthLimit {
+        self.limit
+    }
+
+    /// Take `bytes` from the bucket.
+    ///
+    /// Returns how long to wait before sending them. The bucket goes into
+    /// debt, so later callers wait for earlier reservations.
+    pub fn reserve(&self, bytes: usize) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (available, updated) = &mut *state;
+
+        let now = Instant::now();
+        *available =
+            (*available + now.duration_since(*updated).as_secs_f64() * self.rate).min(self.rate);
+        *updated = now;
+        *available -= bytes as f64;
+
+        if *available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*available / self.rate)
+        }
+    }
+}
+
+/// A reader blocking to stay within the rate of a [TokenBucket].
+pub struct ThrottledReader<R> {
+    inner: R,
+    bucket: Arc<TokenBucket>,
+}
+
+impl<R: Read> ThrottledReader<R> {
+    /// Construct an instance reading from `inner`.
+    pub fn new(inner: R, bucket: Arc<TokenBucket>) -> Self {
+        Self { inner, bucket }
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(THROTTLE_CHUNK_SIZE);
+        let count = self.inner.read(&mut buf[..len])?;
+
+        let delay = self.bucket.reserve(count);
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+
+        Ok(count)
+    }
+}
+
+/// An HTTP body for async clients sent within the rate of a [TokenBucket].
+///
+/// Used for notarization uploads to S3.
+pub struct ThrottledBody {
+    reader: Result<Box<dyn Read + Send + Sync>, Option<std::io::Error>>,
+    remaining: u64,
+    bucket: Arc<TokenBucket>,
+    pending: Option<(Bytes, Pin<Box<tokio::time::Sleep>>)>,
+}
+
+impl ThrottledBody {
+    /// Construct an instance sending `len` bytes from `reader`.
+    ///
+    /// An error opening the reader is returned when the body is polled.
+    pub fn new(
+        reader: std::io::Result<Box<dyn Read + Send + Sync>>,
+        len: u64,
+        bucket: Arc<TokenBucket>,
+    ) -> Self {
+        Self {
+            reader: reader.map_err(Some),
+            remaining: len,
+            bucket,
+            pending: None,
+        }
+    }
+}
+
+impl http_body::Body for ThrottledBody {
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        if let Some((_, sleep)) = self.pending.as_mut() {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            let (chunk, _) = self
+                .pending
+                .take()
+                .expect("pending chunk should be present");
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+
+        if self.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        let mut chunk = vec![0; self.remaining.min(THROTTLE_CHUNK_SIZE as u64) as usize];
+        let reader = match self.reader.as_mut() {
+            Ok(reader) => reader,
+            Err(e) => {
+                let e = e
+                    .take()
+                    .unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::Other));
+                return Poll::Ready(Some(Err(e.into())));
+            }
+        };
+
+        let count = match reader.read(&mut chunk) {
+            Ok(0) => {
+                return Poll::Ready(Some(Err(
+                    "upload source ended before its declared length".into()
+                )))
+            }
+            Ok(count) => count,
+            Err(e) => return Poll::Ready(Some(Err(e.into()))),
+        };
+        chunk.truncate(count);
+        self.remaining -= count as u64;
+
+        let delay = self.bucket.reserve(count);
+        if delay.is_zero() {
+            return Poll::Ready(Some(Ok(chunk.into())));
+        }
+
+        self.pending = Some((chunk.into(), Box::pin(tokio::time::sleep(delay))));
+        self.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<reqwest::header::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0 && self.pending.is_none()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let pending = self
+            .pending
+            .as_ref()
+            .map(|(chunk, _)| chunk.len() as u64)
+            .unwrap_or_default();
+
+        http_body::SizeHint::with_exact(self.remaining + pending)
+    }
+}
+
+/// A [ByteStream] of `len` bytes read from `open` within the rate of `bucket`.
+///
+/// `open` is called again if the request is retried.
+pub fn throttled_byte_stream(
+    open: impl Fn() -> std::io::Result<Box<dyn Read + Send + Sync>> + Send + Sync + 'static,
+    len: u64,
+    bucket: Arc<TokenBucket>,
+) -> ByteStream {
+    ByteStream::new(SdkBody::retryable(move || {
+        SdkBody::from_dyn(http_body::combinators::BoxBody::new(ThrottledBody::new(
+            open(),
+            len,
+            bucket.clone(),
+        )))
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let parse = |s: &str| BandwidthLimit::from_str(s).map(|x| x.bytes_per_second());
+
+        assert_eq!(parse("10MB/s").unwrap(), 10_000_000);
+        assert_eq!(parse("512KiB/s").unwrap(), 524_288);
+        assert_eq!(parse("1.5M").unwrap(), 1_500_000);
+        assert_eq!(parse("2000").unwrap(), 2000);
+        assert!(parse("0MB/s").is_err());
+        assert!(parse("10XB/s").is_err());
+        assert!(parse("fast").is_err());
+    }
+
+    #[test]
+    fn throttled_reader() -> Result<(), AppleCodesignError> {
+        // A full bucket lets one second worth through, then the rest waits.
+        let bucket = Arc::new(TokenBucket::new(BandwidthLimit::new(100_000)?));
+        let data = vec![42u8; 120_000];
+
+        let started = Instant::now();
+        let mut reader = ThrottledReader::new(std::io::Cursor::new(&data), bucket);
+        let mut read = vec![];
+        reader.read_to_end(&mut read)?;
+
+        assert_eq!(read, data);
+        assert!(started.elapsed() >= Duration::from_millis(150));
+
+        Ok(())
+    }
+}
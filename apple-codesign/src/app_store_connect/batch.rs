@@ -0,0 +1,270 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Per-item outcomes of composite operations.
+//!
+//! Composite operations (pushing metadata to many apps, registering a list of
+//! devices, installing many signing assets) consist of independent items. A
+//! failure for one item shouldn't discard the work done for the others, so
+//! these operations record the outcome of every item in a [BatchReport]
+//! instead of aborting on the first error.
+//!
+//! Some errors doom every remaining item, e.g. a rejected API Key. Once
+//! [BatchReport::run()] sees such an error (see [is_batch_fatal()]), the
+//! remaining items are recorded as skipped rather than attempted.
+//!
+//! [BatchReport::result()] distinguishes partial failure, where some items
+//! succeeded, from complete failure. The CLI exits with
+//! [PARTIAL_FAILURE_EXIT_CODE] on partial failure, so scripts can tell
+//! "nothing happened" from "retry the failed items".
+
+use {
+    crate::{app_store_connect::orchestrate::AppResult, AppleCodesignError},
+    log::error,
+};
+
+/// Process exit code of the CLI when some, but not all, items failed.
+pub const PARTIAL_FAILURE_EXIT_CODE: i32 = 2;
+
+/// Whether an error means the remaining items of a batch would fail too.
+pub fn is_batch_fatal(e: &AppleCodesignError) -> bool {
+    match e {
+        AppleCodesignError::AppStoreConnectApi(e) => e.status == 401,
+        AppleCodesignError::AppStoreConnectOffline(_)
+        | AppleCodesignError::AppStoreConnectReadOnly(_, _)
+        | AppleCodesignError::AppStoreConnectAppleIdSession => true,
+        _ => false,
+    }
+}
+
+/// The outcome of a composite operation for a single item.
+#[derive(Debug)]
+pub struct ItemResult<T> {
+    /// Human readable name of the item, e.g. an app ID or a file path.
+    pub item: String,
+
+    /// Result of the operation.
+    ///
+    /// Items not attempted because of an earlier error hold
+    /// [AppleCodesignError::BatchItemSkipped].
+    pub result: Result<T, AppleCodesignError>,
+}
+
+impl<T> ItemResult<T> {
+    /// Whether the item wasn't attempted.
+    pub fn is_skipped(&self) -> bool {
+        matches!(self.result, Err(AppleCodesignError::BatchItemSkipped(_)))
+    }
+}
+
+/// Overall outcome of a [BatchReport].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BatchOutcome {
+    /// Every item succeeded. Batches without items succeed.
+    Succeeded,
+
+    /// Some items succeeded and some failed.
+    PartiallyFailed,
+
+    /// Every item failed.
+    Failed,
+}
+
+/// Outcomes of the items of a composite operation.
+#[derive(Debug)]
+pub struct BatchReport<T> {
+    results: Vec<ItemResult<T>>,
+}
+
+impl<T> Default for BatchReport<T> {
+    fn default() -> Self {
+        Self { results: vec![] }
+    }
+}
+
+impl<T> From<Vec<AppResult<T>>> for BatchReport<T> {
+    fn from(results: Vec<AppResult<T>>) -> Self {
+        Self {
+            results: results
+                .into_iter()
+                .map(|r| ItemResult {
+                    item: r.app_id,
+                    result: r.result,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T> BatchReport<T> {
+    /// Run an operation on items in order, continuing past failures.
+    ///
+    /// Items are `(name, value)` pairs. After an error for which
+    /// [is_batch_fatal()] is true, remaining items are skipped.
+    pub fn run<I>(
+        items: impl IntoIterator<Item = (String, I)>,
+        mut op: impl FnMut(&I) -> Result<T, AppleCodesignError>,
+    ) -> Self {
+        let mut report = Self::default();
+        let mut fatal = None;
+
+        for (item, value) in items {
+            let result = match &fatal {
+                Some(cause) => Err(AppleCodesignError::BatchItemSkipped(cause.clone())),
+                None => op(&value),
+            };
+
+            if let Err(e) = &result {
+                if !matches!(e, AppleCodesignError::BatchItemSkipped(_)) {
+                    error!("{}: {}", item, e);
+                }
+                if fatal.is_none() && is_batch_fatal(e) {
+                    fatal = Some(e.to_string());
+                }
+            }
+
+            report.push(item, result);
+        }
+
+        report
+    }
+
+    /// Record the outcome of an item.
+    pub fn push(&mut self, item: impl ToString, result: Result<T, AppleCodesignError>) {
+        self.results.push(ItemResult {
+            item: item.to_string(),
+            result,
+        });
+    }
+
+    /// The outcomes of items, in the order they were recorded.
+    pub fn results(&self) -> &[ItemResult<T>] {
+        &self.results
+    }
+
+    /// The successful items and their values.
+    pub fn successes(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.results
+            .iter()
+            .filter_map(|r| r.result.as_ref().ok().map(|v| (r.item.as_str(), v)))
+    }
+
+    /// Number of items.
+    pub fn total(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Number of failed or skipped items.
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|r| r.result.is_err()).count()
+    }
+
+    /// Number of successful items.
+    pub fn succeeded(&self) -> usize {
+        self.total() - self.failed()
+    }
+
+    /// The overall outcome.
+    pub fn outcome(&self) -> BatchOutcome {
+        match self.failed() {
+            0 => BatchOutcome::Succeeded,
+            failed if failed == self.total() => BatchOutcome::Failed,
+            _ => BatchOutcome::PartiallyFailed,
+        }
+    }
+
+    /// Convert the outcome to a result.
+    ///
+    /// Returns [AppleCodesignError::BatchPartiallyFailed] or
+    /// [AppleCodesignError::BatchFailed] if any item failed.
+    pub fn result(&self) -> Result<(), AppleCodesignError> {
+        match self.outcome() {
+            BatchOutcome::Succeeded => Ok(()),
+            BatchOutcome::PartiallyFailed => Err(AppleCodesignError::BatchPartiallyFailed(
+                self.failed(),
+                self.total(),
+            )),
+            BatchOutcome::Failed => Err(AppleCodesignError::BatchFailed(self.total())),
+        }
+    }
+
+    /// Render a plain text table of items, their status, and failure causes.
+    pub fn summary_table(&self) -> String {
+        let width = self
+            .results
+            .iter()
+            .map(|r| r.item.chars().count())
+            .chain(std::iter::once("ITEM".len()))
+            .max()
+            .unwrap_or_default();
+
+        let mut table = format!("{:width$}  {:7}  CAUSE\n", "ITEM", "STATUS");
+
+        for r in &self.results {
+            let (status, cause) = match &r.result {
+                Ok(_) => ("ok", String::new()),
+                Err(AppleCodesignError::BatchItemSkipped(cause)) => ("skipped", cause.clone()),
+                Err(e) => ("failed", e.to_string()),
+            };
+
+            table.push_str(format!("{:width$}  {:7}  {}", r.item, status, cause).trim_end());
+            table.push('\n');
+        }
+
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::{api_error::ApiError, json_api::ErrorDocument},
+    };
+
+    #[test]
+    fn partial_failure() {
+        let report = BatchReport::run(
+            ["a", "b", "c"].map(|item| (item.to_string(), item)),
+            |item| match *item {
+                "b" => Err(AppleCodesignError::CliGeneralError("bad item".into())),
+                _ => Ok(item.len()),
+            },
+        );
+
+        assert_eq!(report.outcome(), BatchOutcome::PartiallyFailed);
+        assert_eq!((report.succeeded(), report.failed()), (2, 1));
+        assert_eq!(
+            report.successes().map(|(item, _)| item).collect::<Vec<_>>(),
+            vec!["a", "c"]
+        );
+        assert!(matches!(
+            report.result(),
+            Err(AppleCodesignError::BatchPartiallyFailed(1, 3))
+        ));
+        assert_eq!(
+            report.summary_table(),
+            "ITEM  STATUS   CAUSE\na     ok\nb     failed   bad item\nc     ok\n"
+        );
+
+        // Rejected credentials skip the remaining items.
+        let mut attempts = 0;
+        let report = BatchReport::<()>::run(["a", "b"].map(|item| (item.to_string(), ())), |_| {
+            attempts += 1;
+            Err(AppleCodesignError::AppStoreConnectApi(ApiError::new(
+                401,
+                ErrorDocument { errors: vec![] },
+            )))
+        });
+        assert_eq!(attempts, 1);
+        assert!(report.results()[1].is_skipped());
+        assert_eq!(report.outcome(), BatchOutcome::Failed);
+        assert!(matches!(
+            report.result(),
+            Err(AppleCodesignError::BatchFailed(2))
+        ));
+
+        assert!(BatchReport::<()>::default().result().is_ok());
+    }
+}
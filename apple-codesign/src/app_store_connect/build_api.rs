@@ -0,0 +1,174 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App Store Connect builds.
+//!
+//! A build is an uploaded binary of an app. Builds are processed by Apple after
+//! upload and can then be distributed via TestFlight or submitted for App Store
+//! review.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/builds>.
+
+pub use asconnect_types::builds::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            app_api::{App, AppAttributes},
+            json_api::{BuildQuery, Document, RequestDocument},
+            notify::{NotificationEvent, NotificationKind},
+            progress::{state_name, ProgressEvent},
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    log::{info, warn},
+    std::time::Duration,
+};
+
+impl AppStoreConnectClient {
+    /// Fetch a build by its ID.
+    pub fn get_build(&self, build_id: &str) -> Result<Build, AppleCodesignError> {
+        let doc = self.api_get::<Document<Build>>(&format!("/v1/builds/{}", build_id), &[])?;
+
+        Ok(doc.data)
+    }
+
+    /// List builds of an app.
+    ///
+    /// If `version` is given, only builds having that build number are returned.
+    pub fn list_builds(
+        &self,
+        app_id: &str,
+        version: Option<&str>,
+    ) -> Result<Vec<Build>, AppleCodesignError> {
+        let mut query = BuildQuery::new().app(app_id);
+        if let Some(version) = version {
+            query = query.version(version);
+        }
+
+        self.api_get_all("/v1/builds", &query.to_query())
+    }
+
+    /// List builds across all apps having the given processing state, with their app.
+    pub fn list_builds_in_state(
+        &self,
+        state: BuildProcessingState,
+    ) -> Result<Vec<(Build, Option<App>)>, AppleCodesignError> {
+        let mut query = BuildQuery::new().processing_state(state).to_query();
+        query.push(("include", "app".to_string()));
+
+        let (builds, included) =
+            self.api_get_all_with_included::<BuildAttributes>("/v1/builds", &query)?;
+        let apps = self.fetch_related_all::<AppAttributes, _>(&builds, "app", &included)?;
+
+        Ok(builds
+            .into_iter()
+            .map(|build| {
+                let app = build
+                    .related("app")
+                    .first()
+                    .and_then(|id| apps.get(&id.id))
+                    .cloned();
+
+                (build, app)
+            })
+            .collect())
+    }
+
+    /// Declare whether a build uses encryption that isn't exempt from export compliance.
+    ///
+    /// Builds without this declaration can't be tested via TestFlight.
+    pub fn set_build_uses_non_exempt_encryption(
+        &self,
+        build_id: &str,
+        value: bool,
+    ) -> Result<Build, AppleCodesignError> {
+        let body = RequestDocument::update(
+            "builds",
+            build_id,
+            BuildAttributes {
+                uses_non_exempt_encryption: Some(value),
+                ..Default::default()
+            },
+        );
+
+        let doc =
+            self.api_patch::<_, Document<Build>>(&format!("/v1/builds/{}", build_id), &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// Wait for a build to finish processing.
+    ///
+    /// Returns the processed build if processing succeeded. Errors if processing
+    /// failed or didn't finish within `wait_limit`.
+    pub fn wait_on_build_processing(
+        &self,
+        build_id: &str,
+        wait_limit: Duration,
+        poll_interval: Duration,
+    ) -> Result<Build, AppleCodesignError> {
+        let wait_limit = self.constrain_wait_limit(wait_limit);
+
+        warn!(
+            "waiting up to {}s for build {} to finish processing",
+            wait_limit.as_secs(),
+            build_id
+        );
+
+        let start_time = std::time::Instant::now();
+
+        loop {
+            let build = self.get_build(build_id)?;
+            let elapsed = start_time.elapsed();
+
+            info!(
+                "poll state after {}s: {:?}",
+                elapsed.as_secs(),
+                build.attributes.processing_state
+            );
+            self.report_progress(
+                ProgressEvent::new(
+                    "build-processing",
+                    build_id,
+                    state_name(build.attributes.processing_state.as_ref()),
+                )
+                .with_elapsed(elapsed),
+            );
+
+            match build.attributes.processing_state {
+                Some(BuildProcessingState::Valid) => {
+                    warn!("build {} finished processing", build_id);
+                    self.notify(NotificationEvent::new(
+                        NotificationKind::BuildProcessed,
+                        build_id,
+                        format!("build {} finished processing", build_id),
+                    ));
+                    return Ok(build);
+                }
+                Some(BuildProcessingState::Processing) | None => {}
+                Some(state) => {
+                    let e = AppleCodesignError::BuildProcessingFailed(
+                        build_id.to_string(),
+                        format!("{:?}", state),
+                    );
+                    self.notify(NotificationEvent::new(
+                        NotificationKind::BuildProcessingFailed,
+                        build_id,
+                        &e,
+                    ));
+                    return Err(e);
+                }
+            }
+
+            if elapsed >= wait_limit {
+                warn!("reached wait limit after {}s", elapsed.as_secs());
+                return Err(AppleCodesignError::BuildProcessingWaitLimitReached);
+            }
+
+            std::thread::sleep(poll_interval);
+        }
+    }
+}
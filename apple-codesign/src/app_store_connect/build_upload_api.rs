@@ -0,0 +1,424 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Uploading app builds to App Store Connect.
+//!
+//! Builds are uploaded by creating a *build upload* describing the version being
+//! uploaded, then a *build upload file* for the archive, which is an asset
+//! reservation (see [crate::app_store_connect::asset_upload]). After the file is
+//! uploaded and committed, Apple processes the upload and eventually creates a
+//! [crate::app_store_connect::build_api::Build].
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/build_uploads>.
+
+pub use asconnect_types::builds::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            asset_upload::asset_checksum,
+            ipa_preflight::{BinaryInspection, IpaSigningInfo},
+            json_api::{Document, RequestDocument},
+            progress::{state_name, ProgressEvent},
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    log::{info, warn},
+    std::{
+        io::{Cursor, Read},
+        path::Path,
+        time::Duration,
+    },
+};
+
+/// Information about an app extracted from an `.ipa` file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IpaInfo {
+    /// The `CFBundleIdentifier` of the main app.
+    pub bundle_identifier: String,
+
+    /// The `CFBundleShortVersionString` of the main app. e.g. `1.2.0`.
+    pub short_version: String,
+
+    /// The `CFBundleVersion` of the main app. i.e. the build number.
+    pub version: String,
+
+    /// The `CFBundleSupportedPlatforms` of the main app. e.g. `iPhoneOS` or `XROS`.
+    pub supported_platforms: Vec<String>,
+
+    /// The `UIDeviceFamily` of the main app. e.g. `1` (iPhone) or `7` (Apple Vision).
+    pub device_families: Vec<u64>,
+}
+
+impl IpaInfo {
+    /// Resolve information about an app from the content of an `.ipa` file.
+    ///
+    /// The `Info.plist` of the app bundle in the `Payload` directory is read.
+    pub fn from_ipa_data(data: &[u8]) -> Result<Self, AppleCodesignError> {
+        let mut za = zip::ZipArchive::new(Cursor::new(data))?;
+
+        let name = za
+            .file_names()
+            .find(|name| {
+                let parts = name.split('/').collect::<Vec<_>>();
+
+                parts.len() == 3
+                    && parts[0] == "Payload"
+                    && parts[1].ends_with(".app")
+                    && parts[2] == "Info.plist"
+            })
+            .map(|name| name.to_string())
+            .ok_or_else(|| {
+                AppleCodesignError::IpaMalformed("no Payload/*.app/Info.plist".into())
+            })?;
+
+        let mut plist_data = vec![];
+        za.by_name(&name)?.read_to_end(&mut plist_data)?;
+
+        let value = plist::Value::from_reader(Cursor::new(plist_data))
+            .map_err(|e| AppleCodesignError::IpaMalformed(format!("{}: {}", name, e)))?;
+
+        let dict = value.as_dictionary().ok_or_else(|| {
+            AppleCodesignError::IpaMalformed(format!("{} is not a dictionary", name))
+        })?;
+
+        let get_string = |key: &str| -> Result<String, AppleCodesignError> {
+            dict.get(key)
+                .and_then(|v| v.as_string())
+                .map(|v| v.to_string())
+                .ok_or_else(|| AppleCodesignError::IpaMalformed(format!("{} lacks {}", name, key)))
+        };
+
+        let get_array = |key: &str| {
+            dict.get(key)
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        Ok(Self {
+            bundle_identifier: get_string("CFBundleIdentifier")?,
+            short_version: get_string("CFBundleShortVersionString")?,
+            version: get_string("CFBundleVersion")?,
+            supported_platforms: get_array("CFBundleSupportedPlatforms")
+                .iter()
+                .filter_map(|v| v.as_string().map(|v| v.to_string()))
+                .collect(),
+            device_families: get_array("UIDeviceFamily")
+                .iter()
+                .filter_map(|v| v.as_unsigned_integer())
+                .collect(),
+        })
+    }
+
+    /// The platform the app is built for, according to `CFBundleSupportedPlatforms`.
+    pub fn platform(&self) -> Option<Platform> {
+        self.supported_platforms
+            .iter()
+            .find_map(|platform| match platform.as_str() {
+                "iPhoneOS" => Some(Platform::Ios),
+                "MacOSX" => Some(Platform::MacOs),
+                "AppleTVOS" => Some(Platform::TvOs),
+                "XROS" => Some(Platform::VisionOs),
+                _ => None,
+            })
+    }
+
+    /// Find problems with uploading the app as a build of a platform.
+    pub fn platform_problems(&self, platform: Platform) -> Vec<String> {
+        let mut problems = vec![];
+
+        match self.platform() {
+            Some(built) if built != platform => {
+                problems.push(format!(
+                    "{} is built for {:?} but is uploaded as a {:?} build",
+                    self.bundle_identifier, built, platform
+                ));
+            }
+            None if platform == Platform::VisionOs => {
+                problems.push(format!(
+                    "{} lacks XROS in CFBundleSupportedPlatforms, which visionOS apps require",
+                    self.bundle_identifier
+                ));
+            }
+            _ => {}
+        }
+
+        if platform == Platform::VisionOs && !self.device_families.contains(&7) {
+            problems.push(format!(
+                "{} lacks 7 (Apple Vision) in UIDeviceFamily, which visionOS apps require",
+                self.bundle_identifier
+            ));
+        }
+
+        problems
+    }
+
+    /// Error if the app can't be uploaded as a build of a platform.
+    pub fn validate_platform(&self, platform: Platform) -> Result<(), AppleCodesignError> {
+        let problems = self.platform_problems(platform);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(AppleCodesignError::IpaPreflightFailed(problems.join("; ")))
+        }
+    }
+}
+
+/// Validate an `.ipa` before it is uploaded.
+///
+/// See [AppStoreConnectClient::upload_ipa()].
+pub(crate) fn validate_ipa_for_upload(
+    data: &[u8],
+    platform: Platform,
+) -> Result<(), AppleCodesignError> {
+    IpaInfo::from_ipa_data(data)?.validate_platform(platform)?;
+    IpaSigningInfo::from_ipa_data(data)?.validate_for_upload(chrono::Utc::now())?;
+    BinaryInspection::from_ipa_data(data)?.validate_for_upload()?;
+
+    Ok(())
+}
+
+/// The request document creating a build upload of an app version.
+pub(crate) fn build_upload_request(
+    app_id: &str,
+    info: &IpaInfo,
+    platform: Platform,
+) -> RequestDocument<BuildUploadAttributes> {
+    RequestDocument::create(
+        "buildUploads",
+        BuildUploadAttributes {
+            cf_bundle_short_version_string: Some(info.short_version.clone()),
+            cf_bundle_version: Some(info.version.clone()),
+            platform: Some(platform),
+            state: None,
+        },
+    )
+    .with_relationship("app", "apps", app_id)
+}
+
+/// The request document reserving the `.ipa` file of a build upload.
+pub(crate) fn build_upload_file_request(
+    upload_id: &str,
+    file_name: &str,
+    size: usize,
+) -> RequestDocument<BuildUploadFileAttributes> {
+    RequestDocument::create(
+        "buildUploadFiles",
+        BuildUploadFileAttributes {
+            asset_type: Some("ASSET".into()),
+            file_name: Some(file_name.to_string()),
+            file_size: Some(size as u64),
+            uti: Some("com.apple.ipa".into()),
+            ..Default::default()
+        },
+    )
+    .with_relationship("buildUpload", "buildUploads", upload_id)
+}
+
+/// The request document committing the uploaded file of a build upload.
+pub(crate) fn build_upload_file_commit_request(
+    file_id: &str,
+    data: &[u8],
+) -> RequestDocument<BuildUploadFileAttributes> {
+    RequestDocument::update(
+        "buildUploadFiles",
+        file_id,
+        BuildUploadFileAttributes {
+            source_file_checksum: Some(asset_checksum(data)),
+            uploaded: Some(true),
+            ..Default::default()
+        },
+    )
+}
+
+impl AppStoreConnectClient {
+    /// Fetch a build upload by its ID.
+    pub fn get_build_upload(&self, upload_id: &str) -> Result<BuildUpload, AppleCodesignError> {
+        let doc =
+            self.api_get::<Document<BuildUpload>>(&format!("/v1/buildUploads/{}", upload_id), &[])?;
+
+        Ok(doc.data)
+    }
+
+    /// Upload an `.ipa` file for an app.
+    ///
+    /// The entitlements of the app are validated against its embedded provisioning
+    /// profile and its binaries are inspected before anything is uploaded (see
+    /// [IpaSigningInfo::validate_for_upload()] and
+    /// [BinaryInspection::validate_for_upload()]).
+    ///
+    /// Returns the build upload once the file has been transferred. Processing of
+    /// the upload happens asynchronously on Apple's servers. Use
+    /// [Self::wait_on_build_upload()] to wait for the resulting build.
+    pub fn upload_ipa(
+        &self,
+        app_id: &str,
+        path: &Path,
+        platform: Platform,
+    ) -> Result<BuildUpload, AppleCodesignError> {
+        let data = std::fs::read(path)?;
+        validate_ipa_for_upload(&data, platform)?;
+
+        let file_name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_else(|| "app.ipa".to_string());
+
+        self.upload_ipa_data_unchecked(app_id, &file_name, &data, platform)
+    }
+
+    /// Upload the content of an `.ipa` file without pre-flight validation.
+    ///
+    /// Only the `Info.plist` of the app is read. Prefer [Self::upload_ipa()],
+    /// which rejects archives App Store Connect would reject after the upload.
+    pub fn upload_ipa_data_unchecked(
+        &self,
+        app_id: &str,
+        file_name: &str,
+        data: &[u8],
+        platform: Platform,
+    ) -> Result<BuildUpload, AppleCodesignError> {
+        let info = IpaInfo::from_ipa_data(data)?;
+
+        warn!(
+            "creating build upload for {} {} ({})",
+            info.bundle_identifier, info.short_version, info.version
+        );
+
+        let body = build_upload_request(app_id, &info, platform);
+
+        let upload = self
+            .api_post::<_, Document<BuildUpload>>("/v1/buildUploads", &body)?
+            .data;
+
+        let body = build_upload_file_request(&upload.id, file_name, data.len());
+
+        let file = self
+            .api_post::<_, Document<BuildUploadFile>>("/v1/buildUploadFiles", &body)?
+            .data;
+
+        self.perform_upload_operations(
+            file_name,
+            file.attributes
+                .upload_operations
+                .as_deref()
+                .unwrap_or_default(),
+            data,
+        )?;
+
+        warn!("committing build upload file {}", file.id);
+
+        let body = build_upload_file_commit_request(&file.id, data);
+
+        self.api_patch::<_, Document<BuildUploadFile>>(
+            &format!("/v1/buildUploadFiles/{}", file.id),
+            &body,
+        )?;
+
+        Ok(upload)
+    }
+
+    /// Wait for a build upload to be processed into a build.
+    ///
+    /// Returns the [Build] created from the upload. The build itself may still be
+    /// processing. Use [Self::wait_on_build_processing()] to wait for that.
+    pub fn wait_on_build_upload(
+        &self,
+        upload_id: &str,
+        wait_limit: Duration,
+        poll_interval: Duration,
+    ) -> Result<Build, AppleCodesignError> {
+        let wait_limit = self.constrain_wait_limit(wait_limit);
+
+        warn!(
+            "waiting up to {}s for build upload {} to be processed",
+            wait_limit.as_secs(),
+            upload_id
+        );
+
+        let start_time = std::time::Instant::now();
+
+        loop {
+            let doc = self.api_get::<Document<BuildUpload>>(
+                &format!("/v1/buildUploads/{}", upload_id),
+                &[("include", "build".to_string())],
+            )?;
+            let elapsed = start_time.elapsed();
+
+            info!(
+                "poll state after {}s: {:?}",
+                elapsed.as_secs(),
+                doc.data.attributes.state
+            );
+            self.report_progress(
+                ProgressEvent::new(
+                    "build-upload-processing",
+                    upload_id,
+                    state_name(doc.data.attributes.state.as_ref()),
+                )
+                .with_elapsed(elapsed),
+            );
+
+            if doc.data.attributes.state == Some(BuildUploadState::Failed) {
+                return Err(AppleCodesignError::BuildProcessingFailed(
+                    upload_id.to_string(),
+                    "build upload failed".into(),
+                ));
+            }
+
+            if let Some(build) = doc
+                .included
+                .into_iter()
+                .filter_map(|v| serde_json::from_value::<Build>(v).ok())
+                .find(|r| r.r#type == "builds")
+            {
+                warn!("build upload {} produced build {}", upload_id, build.id);
+                return Ok(build);
+            }
+
+            if elapsed >= wait_limit {
+                warn!("reached wait limit after {}s", elapsed.as_secs());
+                return Err(AppleCodesignError::BuildProcessingWaitLimitReached);
+            }
+
+            std::thread::sleep(poll_interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn info(supported_platforms: &[&str], device_families: &[u64]) -> IpaInfo {
+        IpaInfo {
+            bundle_identifier: "com.example.app".into(),
+            short_version: "1.0.0".into(),
+            version: "1".into(),
+            supported_platforms: supported_platforms.iter().map(|x| x.to_string()).collect(),
+            device_families: device_families.to_vec(),
+        }
+    }
+
+    #[test]
+    fn platform_validation() {
+        let vision = info(&["XROS"], &[7]);
+        assert_eq!(vision.platform(), Some(Platform::VisionOs));
+        assert!(vision.validate_platform(Platform::VisionOs).is_ok());
+        assert_eq!(vision.platform_problems(Platform::Ios).len(), 1);
+
+        let iphone = info(&["iPhoneOS"], &[1, 2]);
+        assert!(iphone.validate_platform(Platform::Ios).is_ok());
+        assert_eq!(iphone.platform_problems(Platform::VisionOs).len(), 2);
+
+        // Archives lacking CFBundleSupportedPlatforms can't be checked, except
+        // that visionOS requires it.
+        let unknown = info(&[], &[]);
+        assert!(unknown.validate_platform(Platform::Ios).is_ok());
+        assert_eq!(unknown.platform_problems(Platform::VisionOs).len(), 2);
+    }
+}
@@ -0,0 +1,717 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App Store Connect bundle IDs and their capabilities.
+//!
+//! A bundle ID (also known as an App ID) registers a bundle identifier with the
+//! team and records which capabilities (push notifications, app groups, etc)
+//! are enabled for it. Entitlements of a signed app must agree with the
+//! capabilities of its bundle ID and with its provisioning profile.
+//! [render_entitlements()] derives a minimal entitlements plist from a bundle
+//! ID so signing pipelines not using Xcode don't need to author one by hand.
+//!
+//! Bundle IDs are either explicit (`com.example.app`) or wildcards
+//! (`com.example.*`) matching every bundle identifier with their prefix.
+//! Wildcard bundle IDs can't have many capabilities (see
+//! [WILDCARD_UNSUPPORTED_CAPABILITIES]) and apps using them can't be
+//! distributed on the App Store. [AppStoreConnectClient::find_matching_bundle_id()]
+//! resolves an explicit bundle identifier to a wildcard registration when no
+//! explicit one exists.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/bundle_ids>
+//! and <https://developer.apple.com/documentation/appstoreconnectapi/bundle_id_capabilities>.
+
+pub use asconnect_types::bundles::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            certs_api::ProfileType,
+            json_api::{BundleIdQuery, Document},
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    log::warn,
+    plist::{Dictionary, Value},
+};
+
+/// Capabilities which can't be enabled for wildcard bundle IDs.
+pub const WILDCARD_UNSUPPORTED_CAPABILITIES: &[&str] = &[
+    "APP_GROUPS",
+    "APPLE_ID_AUTH",
+    "APPLE_PAY",
+    "ASSOCIATED_DOMAINS",
+    "GAME_CENTER",
+    "HEALTHKIT",
+    "HOMEKIT",
+    "HOT_SPOT",
+    "ICLOUD",
+    "IN_APP_PURCHASE",
+    "INTER_APP_AUDIO",
+    "NETWORK_EXTENSIONS",
+    "NFC_TAG_READING",
+    "PERSONAL_VPN",
+    "PUSH_NOTIFICATIONS",
+    "SIGN_IN_WITH_APPLE",
+    "SIRIKIT",
+    "WALLET",
+    "WIRELESS_ACCESSORY_CONFIGURATION",
+];
+
+/// Validate a reverse-DNS bundle identifier.
+///
+/// Identifiers consist of at least two period separated components of ASCII
+/// letters, digits, and hyphens. Wildcard identifiers end with a `*`
+/// component (`com.example.*`); `*` alone matches every identifier.
+pub fn validate_bundle_identifier(identifier: &str) -> Result<(), AppleCodesignError> {
+    let invalid = |reason: &str| {
+        AppleCodesignError::InvalidBundleIdentifier(identifier.to_string(), reason.into())
+    };
+
+    if identifier.is_empty() {
+        return Err(invalid("is empty"));
+    }
+    if identifier == "*" {
+        return Ok(());
+    }
+
+    let components = identifier.split('.').collect::<Vec<_>>();
+    if components.len() < 2 {
+        return Err(invalid("is not a reverse-DNS name (e.g. com.example.app)"));
+    }
+
+    for (i, component) in components.iter().enumerate() {
+        if component.is_empty() {
+            return Err(invalid("has an empty component"));
+        }
+        if *component == "*" {
+            if i + 1 != components.len() {
+                return Err(invalid("* is only allowed as the last component"));
+            }
+            continue;
+        }
+        if let Some(c) = component
+            .chars()
+            .find(|c| !c.is_ascii_alphanumeric() && *c != '-')
+        {
+            return Err(invalid(&format!(
+                "contains {:?}; only letters, digits, hyphens, and periods are allowed",
+                c
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a bundle identifier matches a bundle ID identifier.
+///
+/// Explicit identifiers only match themselves. Wildcard identifiers match
+/// identifiers with their prefix, e.g. `com.example.*` matches
+/// `com.example.app` but not `com.example`.
+pub fn bundle_identifier_matches(pattern: &str, identifier: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => identifier.len() > prefix.len() && identifier.starts_with(prefix),
+        None => pattern == identifier,
+    }
+}
+
+/// Describe the limitations of a profile for a wildcard bundle ID.
+///
+/// Empty if the bundle ID is explicit.
+pub fn wildcard_profile_warnings(
+    bundle_id: &BundleIdAttributes,
+    profile_type: ProfileType,
+) -> Vec<String> {
+    if !bundle_id.is_wildcard() {
+        return vec![];
+    }
+
+    let identifier = bundle_id.identifier.as_deref().unwrap_or_default();
+
+    let mut warnings = vec![format!(
+        "bundle ID {} is a wildcard; apps signed with the profile cannot use {}",
+        identifier,
+        WILDCARD_UNSUPPORTED_CAPABILITIES.join(", ")
+    )];
+    if profile_type.is_app_store() {
+        warnings.push(format!(
+            "apps using wildcard bundle ID {} cannot be distributed on the App Store",
+            identifier
+        ));
+    }
+
+    warnings
+}
+
+fn strings(values: &[String]) -> Value {
+    Value::Array(values.iter().map(|v| Value::String(v.clone())).collect())
+}
+
+/// Render the entitlements implied by a bundle ID and its capabilities.
+///
+/// The returned dictionary contains the application identifier and team
+/// identifier, `get-task-allow` for development profiles, and an entitlement
+/// for each enabled capability having one. Capabilities whose entitlements
+/// hold identifiers not exposed by the API (such as app group or iCloud
+/// container names) are rendered with empty arrays that need to be filled in.
+///
+/// Driver extensions are always entitled to DriverKit when rendered for a
+/// DriverKit profile type. Their USB and PCI transport entitlements list the
+/// matched hardware, which also needs to be filled in.
+pub fn render_entitlements(
+    bundle_id: &BundleIdAttributes,
+    capabilities: &[BundleIdCapabilityAttributes],
+    profile_type: ProfileType,
+) -> Result<Dictionary, AppleCodesignError> {
+    let identifier = bundle_id.identifier.as_deref().ok_or_else(|| {
+        AppleCodesignError::AppStoreConnectUpload("bundle ID lacks an identifier".into())
+    })?;
+    let team_id = bundle_id.seed_id.as_deref().ok_or_else(|| {
+        AppleCodesignError::AppStoreConnectUpload("bundle ID lacks a seed ID".into())
+    })?;
+
+    let macos = profile_type.is_macos();
+    let development = profile_type.is_development();
+    let app_id = format!("{}.{}", team_id, identifier);
+    let environment = if development {
+        "development"
+    } else {
+        "production"
+    };
+
+    let mut d = Dictionary::new();
+
+    if macos {
+        d.insert(
+            "com.apple.application-identifier".into(),
+            app_id.clone().into(),
+        );
+    } else {
+        d.insert("application-identifier".into(), app_id.clone().into());
+        d.insert(
+            "keychain-access-groups".into(),
+            Value::Array(vec![app_id.clone().into()]),
+        );
+    }
+    d.insert("com.apple.developer.team-identifier".into(), team_id.into());
+
+    if development {
+        let key = if macos {
+            "com.apple.security.get-task-allow"
+        } else {
+            "get-task-allow"
+        };
+        d.insert(key.into(), true.into());
+    }
+
+    if profile_type.is_driverkit() {
+        d.insert("com.apple.developer.driverkit".into(), true.into());
+    }
+
+    for capability in capabilities {
+        let capability_type = match capability.capability_type.as_deref() {
+            Some(v) => v,
+            None => continue,
+        };
+
+        match capability_type {
+            "PUSH_NOTIFICATIONS" => {
+                let key = if macos {
+                    "com.apple.developer.aps-environment"
+                } else {
+                    "aps-environment"
+                };
+                d.insert(key.into(), environment.into());
+            }
+            "APP_GROUPS" => {
+                d.insert("com.apple.security.application-groups".into(), strings(&[]));
+            }
+            "ICLOUD" => {
+                d.insert(
+                    "com.apple.developer.icloud-container-identifiers".into(),
+                    strings(&[]),
+                );
+                d.insert(
+                    "com.apple.developer.icloud-services".into(),
+                    strings(&["CloudKit".into()]),
+                );
+            }
+            "ASSOCIATED_DOMAINS" => {
+                d.insert(
+                    "com.apple.developer.associated-domains".into(),
+                    strings(&[]),
+                );
+            }
+            "APPLE_PAY" => {
+                d.insert("com.apple.developer.in-app-payments".into(), strings(&[]));
+            }
+            "WALLET" => {
+                d.insert(
+                    "com.apple.developer.pass-type-identifiers".into(),
+                    strings(&[format!("{}.*", team_id)]),
+                );
+            }
+            "HEALTHKIT" => {
+                d.insert("com.apple.developer.healthkit".into(), true.into());
+            }
+            "HOMEKIT" => {
+                d.insert("com.apple.developer.homekit".into(), true.into());
+            }
+            "SIRIKIT" => {
+                d.insert("com.apple.developer.siri".into(), true.into());
+            }
+            "GAME_CENTER" => {
+                if macos {
+                    d.insert("com.apple.developer.game-center".into(), true.into());
+                }
+            }
+            "NETWORK_EXTENSIONS" => {
+                d.insert(
+                    "com.apple.developer.networking.networkextension".into(),
+                    strings(&[]),
+                );
+            }
+            "PERSONAL_VPN" => {
+                d.insert(
+                    "com.apple.developer.networking.vpn.api".into(),
+                    strings(&["allow-vpn".into()]),
+                );
+            }
+            "DATA_PROTECTION" => {
+                d.insert(
+                    "com.apple.developer.default-data-protection".into(),
+                    "NSFileProtectionComplete".into(),
+                );
+            }
+            "SIGN_IN_WITH_APPLE" => {
+                d.insert(
+                    "com.apple.developer.applesignin".into(),
+                    strings(&["Default".into()]),
+                );
+            }
+            "ACCESS_WIFI_INFORMATION" => {
+                d.insert(
+                    "com.apple.developer.networking.wifi-info".into(),
+                    true.into(),
+                );
+            }
+            "NFC_TAG_READING" => {
+                d.insert(
+                    "com.apple.developer.nfc.readersession.formats".into(),
+                    strings(&["NDEF".into(), "TAG".into()]),
+                );
+            }
+            "CLASSKIT" => {
+                d.insert(
+                    "com.apple.developer.ClassKit-environment".into(),
+                    environment.into(),
+                );
+            }
+            "HOT_SPOT" => {
+                d.insert(
+                    "com.apple.developer.networking.HotspotConfiguration".into(),
+                    true.into(),
+                );
+            }
+            "MULTIPATH" => {
+                d.insert(
+                    "com.apple.developer.networking.multipath".into(),
+                    true.into(),
+                );
+            }
+            "INTER_APP_AUDIO" => {
+                d.insert("inter-app-audio".into(), true.into());
+            }
+            "AUTOFILL_CREDENTIAL_PROVIDER" => {
+                d.insert(
+                    "com.apple.developer.authentication-services.autofill-credential-provider"
+                        .into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT" => {
+                d.insert("com.apple.developer.driverkit".into(), true.into());
+            }
+            "DRIVERKIT_ALLOW_THIRD_PARTY_USER_CLIENTS" => {
+                d.insert(
+                    "com.apple.developer.driverkit.allow-third-party-userclients".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_COMMUNICATES_WITH_DRIVERS" => {
+                d.insert(
+                    "com.apple.developer.driverkit.communicates-with-drivers".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_FAMILY_AUDIO" => {
+                d.insert(
+                    "com.apple.developer.driverkit.family.audio".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_FAMILY_HID_DEVICE" => {
+                d.insert(
+                    "com.apple.developer.driverkit.family.hid.device".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_FAMILY_HID_EVENT_SERVICE" => {
+                d.insert(
+                    "com.apple.developer.driverkit.family.hid.eventservice".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_FAMILY_NETWORKING" => {
+                d.insert(
+                    "com.apple.developer.driverkit.family.networking".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_FAMILY_SCSICONTROLLER" => {
+                d.insert(
+                    "com.apple.developer.driverkit.family.scsicontroller".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_FAMILY_SERIAL" => {
+                d.insert(
+                    "com.apple.developer.driverkit.family.serial".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_TRANSPORT_HID" => {
+                d.insert(
+                    "com.apple.developer.driverkit.transport.hid".into(),
+                    true.into(),
+                );
+            }
+            "DRIVERKIT_USB_TRANSPORT" => {
+                d.insert(
+                    "com.apple.developer.driverkit.transport.usb".into(),
+                    Value::Array(vec![]),
+                );
+            }
+            "DRIVERKIT_PCI_TRANSPORT" => {
+                d.insert(
+                    "com.apple.developer.driverkit.transport.pci".into(),
+                    Value::Array(vec![]),
+                );
+            }
+            "SYSTEM_EXTENSION_INSTALL" => {
+                d.insert(
+                    "com.apple.developer.system-extension.install".into(),
+                    true.into(),
+                );
+            }
+            // These capabilities don't have entitlements.
+            "IN_APP_PURCHASE" | "MAPS" => {}
+            other => {
+                warn!("capability {} has no known entitlement; ignoring", other);
+            }
+        }
+    }
+
+    Ok(d)
+}
+
+impl AppStoreConnectClient {
+    /// List bundle IDs registered to the account.
+    pub fn list_bundle_ids(&self) -> Result<Vec<BundleId>, AppleCodesignError> {
+        self.api_get_all("/v1/bundleIds", &[])
+    }
+
+    /// Fetch a bundle ID by its resource ID.
+    ///
+    /// Bundle IDs are memoized in the [lookup cache](Self::lookup_cache()).
+    pub fn get_bundle_id(&self, bundle_id_id: &str) -> Result<BundleId, AppleCodesignError> {
+        self.lookup_cache().bundle_id_by_id(bundle_id_id, || {
+            let doc = self
+                .api_get::<Document<BundleId>>(&format!("/v1/bundleIds/{}", bundle_id_id), &[])?;
+
+            Ok(doc.data)
+        })
+    }
+
+    /// Find the bundle ID registered for a bundle identifier.
+    ///
+    /// Bundle IDs are memoized in the [lookup cache](Self::lookup_cache()).
+    pub fn find_bundle_id(&self, identifier: &str) -> Result<Option<BundleId>, AppleCodesignError> {
+        self.lookup_cache().bundle_id_by_identifier(identifier, || {
+            Ok(self
+                .api_get_all::<BundleIdAttributes>(
+                    "/v1/bundleIds",
+                    &BundleIdQuery::new().identifier(identifier).to_query(),
+                )?
+                .into_iter()
+                // The filter matches prefixes, so ensure an exact match.
+                .find(|b| b.attributes.identifier.as_deref() == Some(identifier)))
+        })
+    }
+
+    /// Find the bundle ID a bundle identifier is registered under.
+    ///
+    /// The explicit bundle ID of the identifier is preferred. Otherwise the
+    /// most specific wildcard bundle ID matching it is returned.
+    pub fn find_matching_bundle_id(
+        &self,
+        identifier: &str,
+    ) -> Result<Option<BundleId>, AppleCodesignError> {
+        validate_bundle_identifier(identifier)?;
+
+        if let Some(bundle_id) = self.find_bundle_id(identifier)? {
+            return Ok(Some(bundle_id));
+        }
+
+        Ok(self
+            .list_bundle_ids()?
+            .into_iter()
+            .filter(|b| {
+                b.attributes.is_wildcard()
+                    && bundle_identifier_matches(
+                        b.attributes.identifier.as_deref().unwrap_or_default(),
+                        identifier,
+                    )
+            })
+            .max_by_key(|b| b.attributes.identifier.as_ref().map(|x| x.len())))
+    }
+
+    /// List the capabilities enabled for a bundle ID.
+    pub fn list_bundle_id_capabilities(
+        &self,
+        bundle_id_id: &str,
+    ) -> Result<Vec<BundleIdCapability>, AppleCodesignError> {
+        let doc = self.api_get::<Document<Vec<BundleIdCapability>>>(
+            &format!("/v1/bundleIds/{}/bundleIdCapabilities", bundle_id_id),
+            &[],
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// Render the entitlements for a bundle identifier and profile type.
+    ///
+    /// See [render_entitlements()].
+    pub fn entitlements_for_bundle_id(
+        &self,
+        identifier: &str,
+        profile_type: ProfileType,
+    ) -> Result<Dictionary, AppleCodesignError> {
+        let bundle_id = self.find_bundle_id(identifier)?.ok_or_else(|| {
+            AppleCodesignError::AppStoreConnectUpload(format!(
+                "bundle ID {} is not registered",
+                identifier
+            ))
+        })?;
+
+        let capabilities = self
+            .list_bundle_id_capabilities(&bundle_id.id)?
+            .into_iter()
+            .map(|c| c.attributes)
+            .collect::<Vec<_>>();
+
+        render_entitlements(&bundle_id.attributes, &capabilities, profile_type)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    fn capability(name: &str) -> BundleIdCapabilityAttributes {
+        BundleIdCapabilityAttributes {
+            capability_type: Some(name.to_string()),
+            settings: vec![],
+        }
+    }
+
+    #[test]
+    fn render_ios() -> Result<(), AppleCodesignError> {
+        let bundle_id = BundleIdAttributes {
+            identifier: Some("com.example.app".into()),
+            seed_id: Some("ABCDE12345".into()),
+            ..Default::default()
+        };
+        let capabilities = vec![capability("PUSH_NOTIFICATIONS"), capability("APP_GROUPS")];
+
+        let d = render_entitlements(&bundle_id, &capabilities, ProfileType::IosAppDevelopment)?;
+
+        assert_eq!(
+            d.get("application-identifier").and_then(|v| v.as_string()),
+            Some("ABCDE12345.com.example.app")
+        );
+        assert_eq!(
+            d.get("aps-environment").and_then(|v| v.as_string()),
+            Some("development")
+        );
+        assert_eq!(
+            d.get("get-task-allow").and_then(|v| v.as_boolean()),
+            Some(true)
+        );
+        assert!(d.contains_key("com.apple.security.application-groups"));
+
+        let d = render_entitlements(&bundle_id, &capabilities, ProfileType::IosAppStore)?;
+        assert_eq!(
+            d.get("aps-environment").and_then(|v| v.as_string()),
+            Some("production")
+        );
+        assert!(!d.contains_key("get-task-allow"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_macos() -> Result<(), AppleCodesignError> {
+        let bundle_id = BundleIdAttributes {
+            identifier: Some("com.example.app".into()),
+            seed_id: Some("ABCDE12345".into()),
+            ..Default::default()
+        };
+
+        let d = render_entitlements(
+            &bundle_id,
+            &[capability("PUSH_NOTIFICATIONS")],
+            ProfileType::MacAppStore,
+        )?;
+
+        assert_eq!(
+            d.get("com.apple.application-identifier")
+                .and_then(|v| v.as_string()),
+            Some("ABCDE12345.com.example.app")
+        );
+        assert_eq!(
+            d.get("com.apple.developer.aps-environment")
+                .and_then(|v| v.as_string()),
+            Some("production")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_driverkit() -> Result<(), AppleCodesignError> {
+        let bundle_id = BundleIdAttributes {
+            identifier: Some("com.example.app.driver".into()),
+            seed_id: Some("ABCDE12345".into()),
+            ..Default::default()
+        };
+        let capabilities = vec![
+            capability("DRIVERKIT_FAMILY_SERIAL"),
+            capability("DRIVERKIT_USB_TRANSPORT"),
+        ];
+
+        let d = render_entitlements(
+            &bundle_id,
+            &capabilities,
+            ProfileType::DriverkitAppDevelopment,
+        )?;
+
+        assert_eq!(
+            d.get("com.apple.application-identifier")
+                .and_then(|v| v.as_string()),
+            Some("ABCDE12345.com.example.app.driver")
+        );
+        assert!(!d.contains_key("keychain-access-groups"));
+        assert_eq!(
+            d.get("com.apple.developer.driverkit")
+                .and_then(|v| v.as_boolean()),
+            Some(true)
+        );
+        assert_eq!(
+            d.get("com.apple.developer.driverkit.family.serial")
+                .and_then(|v| v.as_boolean()),
+            Some(true)
+        );
+        assert!(d
+            .get("com.apple.developer.driverkit.transport.usb")
+            .and_then(|v| v.as_array())
+            .is_some());
+        assert_eq!(
+            d.get("com.apple.security.get-task-allow")
+                .and_then(|v| v.as_boolean()),
+            Some(true)
+        );
+
+        let d = render_entitlements(
+            &bundle_id,
+            &[capability("SYSTEM_EXTENSION_INSTALL")],
+            ProfileType::MacAppDirect,
+        )?;
+        assert!(!d.contains_key("com.apple.developer.driverkit"));
+        assert!(d.contains_key("com.apple.developer.system-extension.install"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bundle_identifier_validation() {
+        assert!(validate_bundle_identifier("com.example.app").is_ok());
+        assert!(validate_bundle_identifier("com.example-corp.app2").is_ok());
+        assert!(validate_bundle_identifier("com.example.*").is_ok());
+        assert!(validate_bundle_identifier("*").is_ok());
+
+        assert!(validate_bundle_identifier("").is_err());
+        assert!(validate_bundle_identifier("app").is_err());
+        assert!(validate_bundle_identifier("com..app").is_err());
+        assert!(validate_bundle_identifier("com.example_corp.app").is_err());
+        assert!(validate_bundle_identifier("com.*.app").is_err());
+    }
+
+    #[test]
+    fn wildcard_matching() -> Result<(), AppleCodesignError> {
+        assert!(bundle_identifier_matches(
+            "com.example.*",
+            "com.example.app"
+        ));
+        assert!(!bundle_identifier_matches("com.example.*", "com.example."));
+        assert!(!bundle_identifier_matches("com.example.*", "com.other.app"));
+        assert!(bundle_identifier_matches("*", "com.example.app"));
+        assert!(!bundle_identifier_matches(
+            "com.example.app",
+            "com.example.app2"
+        ));
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/bundleIds",
+                200,
+                fixtures::collection(vec![
+                    fixtures::bundle_id("B1", "*"),
+                    fixtures::bundle_id("B2", "com.example.*"),
+                    fixtures::bundle_id("B3", "com.example.app"),
+                ]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let found = |identifier| -> Result<Option<String>, AppleCodesignError> {
+            Ok(client.find_matching_bundle_id(identifier)?.map(|b| b.id))
+        };
+        assert_eq!(found("com.example.app")?, Some("B3".to_string()));
+        assert_eq!(found("com.example.other")?, Some("B2".to_string()));
+        assert_eq!(found("org.example.app")?, Some("B1".to_string()));
+        assert!(found("not valid").is_err());
+
+        let wildcard = BundleIdAttributes {
+            identifier: Some("com.example.*".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            wildcard_profile_warnings(&wildcard, ProfileType::IosAppDevelopment).len(),
+            1
+        );
+        assert_eq!(
+            wildcard_profile_warnings(&wildcard, ProfileType::IosAppStore).len(),
+            2
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Partitioning of persisted state by API key.
+//!
+//! State written to disk (pipeline checkpoints, export cursors, operation
+//! queues) refers to resources of the team owning the API key it was written
+//! with. A machine switching between API keys of different teams must not
+//! resume one team's state with another team's key. Every persistent store
+//! records or partitions its content by a [CacheScope] and ignores content of
+//! other scopes.
+
+use {
+    sha2::Digest,
+    std::{
+        fmt::{Display, Formatter},
+        path::{Path, PathBuf},
+    },
+};
+
+/// Identifies the API key state was persisted with.
+///
+/// The scope is derived from the issuer ID and key ID. Neither is secret, but
+/// the scope is a digest of them so paths and files don't reveal them.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CacheScope(String);
+
+impl CacheScope {
+    /// Derive the scope of an API key.
+    pub fn new(issuer_id: &str, key_id: &str) -> Self {
+        let mut h = sha2::Sha256::new();
+        h.update(issuer_id.as_bytes());
+        h.update(b"\n");
+        h.update(key_id.as_bytes());
+
+        Self(hex::encode(&h.finalize()[0..16]))
+    }
+
+    /// The string form of the scope. 32 hex characters.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Resolve a directory for state of this scope within `base`.
+    pub fn dir(&self, base: impl AsRef<Path>) -> PathBuf {
+        base.as_ref().join(&self.0)
+    }
+}
+
+impl Display for CacheScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distinct() {
+        let a = CacheScope::new("issuer-a", "KEY1");
+
+        assert_eq!(a, CacheScope::new("issuer-a", "KEY1"));
+        assert_ne!(a, CacheScope::new("issuer-b", "KEY1"));
+        assert_ne!(a, CacheScope::new("issuer-a", "KEY2"));
+        assert_eq!(a.as_str().len(), 32);
+        assert!(!a.as_str().contains("issuer"));
+    }
+}
@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Calendars of expiration dates.
+//!
+//! Expired certificates and profiles break builds, and an expired membership
+//! takes apps off the App Store. An [ExpirationCalendar] collects these dates
+//! and renders them as an iCalendar (RFC 5545) document, which calendar
+//! applications can import or subscribe to.
+//!
+//! Each expiration is an all-day event. Event UIDs are derived from resource
+//! IDs, so importing a newer calendar updates events rather than duplicating
+//! them.
+//!
+//! The App Store Connect API doesn't expose the renewal date of the
+//! membership. It is added with [ExpirationCalendar::add_membership_renewal()].
+
+use {
+    crate::{
+        app_store_connect::{
+            certs_api::{Certificate, Profile, ProfileState},
+            json_api::parse_api_date,
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::{DateTime, Duration, NaiveDate, Utc},
+};
+
+/// What expires.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExpirationKind {
+    Certificate,
+    Profile,
+    Membership,
+}
+
+impl ExpirationKind {
+    /// The name of the kind as used in event UIDs and JSON.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Certificate => "certificate",
+            Self::Profile => "profile",
+            Self::Membership => "membership",
+        }
+    }
+}
+
+/// An expiration date.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Expiration {
+    pub kind: ExpirationKind,
+
+    /// ID of the expiring resource.
+    pub id: String,
+
+    /// Name of the expiring resource.
+    pub name: String,
+
+    /// Day of the expiration, in UTC.
+    pub date: NaiveDate,
+}
+
+impl Expiration {
+    fn summary(&self) -> String {
+        match self.kind {
+            ExpirationKind::Certificate => format!("Certificate {} expires", self.name),
+            ExpirationKind::Profile => format!("Provisioning profile {} expires", self.name),
+            ExpirationKind::Membership => "Apple Developer Program membership expires".into(),
+        }
+    }
+}
+
+/// Escape text for use in an iCalendar property value.
+fn escape_text(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                res.push('\\');
+                res.push(c);
+            }
+            '\n' => res.push_str("\\n"),
+            '\r' => {}
+            _ => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// Append a content line, folding it at 75 octets as RFC 5545 requires.
+fn push_line(out: &mut String, line: &str) {
+    let mut len = 0;
+
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+
+    out.push_str("\r\n");
+}
+
+/// Expiration dates of an account.
+#[derive(Clone, Debug, Default)]
+pub struct ExpirationCalendar {
+    expirations: Vec<Expiration>,
+
+    /// Days before an expiration to remind attendees.
+    reminder_days: Option<u32>,
+}
+
+impl ExpirationCalendar {
+    /// Remind of expirations this many days in advance.
+    pub fn set_reminder_days(&mut self, days: Option<u32>) {
+        self.reminder_days = days;
+    }
+
+    /// The expirations in the calendar, in order of date.
+    pub fn expirations(&self) -> &[Expiration] {
+        &self.expirations
+    }
+
+    fn add(&mut self, expiration: Expiration) {
+        let index = self
+            .expirations
+            .partition_point(|e| (e.date, &e.name) <= (expiration.date, &expiration.name));
+        self.expirations.insert(index, expiration);
+    }
+
+    /// Add the expiration of a certificate.
+    ///
+    /// Certificates without an expiration date are ignored.
+    pub fn add_certificate(&mut self, certificate: &Certificate) {
+        let a = &certificate.attributes;
+
+        if let Some(date) = a.expiration_date.as_deref().and_then(parse_api_date) {
+            self.add(Expiration {
+                kind: ExpirationKind::Certificate,
+                id: certificate.id.clone(),
+                name: a
+                    .display_name
+                    .clone()
+                    .or_else(|| a.name.clone())
+                    .unwrap_or_else(|| certificate.id.clone()),
+                date: date.naive_utc().date(),
+            });
+        }
+    }
+
+    /// Add the expiration of a provisioning profile.
+    ///
+    /// Invalid profiles and profiles without an expiration date are ignored.
+    pub fn add_profile(&mut self, profile: &Profile) {
+        let a = &profile.attributes;
+
+        if a.profile_state == Some(ProfileState::Invalid) {
+            return;
+        }
+
+        if let Some(date) = a.expiration_date.as_deref().and_then(parse_api_date) {
+            self.add(Expiration {
+                kind: ExpirationKind::Profile,
+                id: profile.id.clone(),
+                name: a.name.clone().unwrap_or_else(|| profile.id.clone()),
+                date: date.naive_utc().date(),
+            });
+        }
+    }
+
+    /// Add the renewal date of the developer program membership.
+    pub fn add_membership_renewal(&mut self, date: NaiveDate) {
+        self.add(Expiration {
+            kind: ExpirationKind::Membership,
+            id: "membership".into(),
+            name: "Apple Developer Program".into(),
+            date,
+        });
+    }
+
+    /// Render the calendar as an iCalendar document.
+    ///
+    /// `now` is the creation time recorded in events.
+    pub fn to_ics(&self, now: DateTime<Utc>) -> String {
+        let mut out = String::new();
+        let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        push_line(&mut out, "BEGIN:VCALENDAR");
+        push_line(&mut out, "VERSION:2.0");
+        push_line(&mut out, "PRODID:-//apple-codesign//Expirations//EN");
+        push_line(&mut out, "CALSCALE:GREGORIAN");
+        push_line(&mut out, "X-WR-CALNAME:Apple Developer expirations");
+
+        for expiration in &self.expirations {
+            let kind = expiration.kind.as_str();
+
+            push_line(&mut out, "BEGIN:VEVENT");
+            push_line(
+                &mut out,
+                &format!("UID:{}-{}@apple-codesign", kind, expiration.id),
+            );
+            push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+            push_line(
+                &mut out,
+                &format!("DTSTART;VALUE=DATE:{}", expiration.date.format("%Y%m%d")),
+            );
+            push_line(
+                &mut out,
+                &format!(
+                    "DTEND;VALUE=DATE:{}",
+                    (expiration.date + Duration::days(1)).format("%Y%m%d")
+                ),
+            );
+            push_line(
+                &mut out,
+                &format!("SUMMARY:{}", escape_text(&expiration.summary())),
+            );
+            push_line(
+                &mut out,
+                &format!(
+                    "DESCRIPTION:{}",
+                    escape_text(&format!("{} {} ({})", kind, expiration.name, expiration.id))
+                ),
+            );
+            push_line(&mut out, "TRANSP:TRANSPARENT");
+
+            if let Some(days) = self.reminder_days {
+                push_line(&mut out, "BEGIN:VALARM");
+                push_line(&mut out, "ACTION:DISPLAY");
+                push_line(
+                    &mut out,
+                    &format!("DESCRIPTION:{}", escape_text(&expiration.summary())),
+                );
+                push_line(&mut out, &format!("TRIGGER:-P{}D", days));
+                push_line(&mut out, "END:VALARM");
+            }
+
+            push_line(&mut out, "END:VEVENT");
+        }
+
+        push_line(&mut out, "END:VCALENDAR");
+
+        out
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Collect the expiration dates of certificates and profiles.
+    pub fn expiration_calendar(&self) -> Result<ExpirationCalendar, AppleCodesignError> {
+        let mut calendar = ExpirationCalendar::default();
+
+        for certificate in self.list_certificates()? {
+            calendar.add_certificate(&certificate);
+        }
+        for profile in self.list_profiles()? {
+            calendar.add_profile(&profile);
+        }
+
+        Ok(calendar)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        chrono::TimeZone,
+    };
+
+    #[test]
+    fn ics() -> Result<(), AppleCodesignError> {
+        let mut certificate = fixtures::certificate("C1", "DISTRIBUTION", "");
+        certificate["attributes"]["displayName"] = "Example, Inc.".into();
+        certificate["attributes"]["expirationDate"] = "2026-03-01T12:00:00.000+0000".into();
+
+        let mut profile = fixtures::profile("P1", "Example App Store", "IOS_APP_STORE", "");
+        profile["attributes"]["expirationDate"] = "2026-02-01T00:00:00.000+0000".into();
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/certificates",
+                200,
+                fixtures::collection(vec![certificate]),
+            )
+            .json(
+                "GET",
+                "/v1/profiles",
+                200,
+                fixtures::collection(vec![profile]),
+            )
+            .start()?;
+
+        let mut calendar = server.client()?.expiration_calendar()?;
+        calendar
+            .add_membership_renewal(NaiveDate::parse_from_str("2026-06-01", "%Y-%m-%d").unwrap());
+        calendar.set_reminder_days(Some(14));
+
+        assert_eq!(
+            calendar
+                .expirations()
+                .iter()
+                .map(|e| (e.kind, e.id.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (ExpirationKind::Profile, "P1"),
+                (ExpirationKind::Certificate, "C1"),
+                (ExpirationKind::Membership, "membership"),
+            ]
+        );
+
+        let ics = calendar.to_ics(Utc.timestamp(1767225600, 0));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:certificate-C1@apple-codesign\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20260301\r\nDTEND;VALUE=DATE:20260302\r\n"));
+        assert!(ics.contains("SUMMARY:Certificate Example\\, Inc. expires\r\n"));
+        assert!(ics.contains("TRIGGER:-P14D\r\n"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+
+        Ok(())
+    }
+}
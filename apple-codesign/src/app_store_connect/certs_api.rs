@@ -0,0 +1,815 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App Store Connect signing certificates, provisioning profiles, and devices.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/certificates>,
+//! <https://developer.apple.com/documentation/appstoreconnectapi/profiles>,
+//! and <https://developer.apple.com/documentation/appstoreconnectapi/devices>.
+
+pub use asconnect_types::certs::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            batch::BatchReport,
+            ipa_preflight::{CertificateExpiryWarning, ProvisioningProfile},
+            json_api::{
+                CertificateQuery, DeviceQuery, Document, RelationshipData, RequestDocument,
+                ResourceIdentifier, Updated,
+            },
+            AppStoreConnectClient,
+        },
+        certificate::AppleCertificate,
+        AppleCodesignError,
+    },
+    chrono::Utc,
+    log::warn,
+    serde::{Deserialize, Serialize},
+    x509_certificate::CapturedX509Certificate,
+};
+
+/// Warn about profile certificates expiring within this many days by default.
+pub const DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS: i64 = 30;
+
+/// Maximum number of Developer ID certificates of each type a team can have.
+pub const DEVELOPER_ID_CERTIFICATE_LIMIT: usize = 5;
+
+/// Validate a device UDID and normalize it to the form Apple reports.
+///
+/// Three formats exist:
+///
+/// * 40 hex digits, used by devices released before 2018. Normalized to
+///   lowercase.
+/// * 8 and 16 hex digits separated by a dash (`00008120-001A2B3C4D5E6F70`),
+///   used by later devices. Normalized to uppercase.
+/// * A UUID (`8-4-4-4-12` hex digits), used by Intel Macs. Normalized to
+///   uppercase.
+///
+/// Surrounding whitespace is ignored and dashes may be missing or misplaced,
+/// as happens when UDIDs are copied from other tools.
+pub fn normalize_udid(udid: &str) -> Result<String, AppleCodesignError> {
+    let invalid = |reason: &str| AppleCodesignError::InvalidUdid(udid.to_string(), reason.into());
+
+    let digits = udid
+        .trim()
+        .chars()
+        .filter(|c| *c != '-')
+        .collect::<String>();
+
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(invalid(&format!("{:?} is not a hex digit", c)));
+    }
+    if digits.chars().all(|c| c == '0') {
+        return Err(invalid("all digits are zero"));
+    }
+
+    match digits.len() {
+        40 => Ok(digits.to_ascii_lowercase()),
+        24 => {
+            let digits = digits.to_ascii_uppercase();
+            Ok(format!("{}-{}", &digits[0..8], &digits[8..]))
+        }
+        32 => {
+            let digits = digits.to_ascii_uppercase();
+            Ok(format!(
+                "{}-{}-{}-{}-{}",
+                &digits[0..8],
+                &digits[8..12],
+                &digits[12..16],
+                &digits[16..20],
+                &digits[20..]
+            ))
+        }
+        n => Err(invalid(&format!(
+            "has {} hex digits; expected 40, 24 (8-16), or 32 (a UUID)",
+            n
+        ))),
+    }
+}
+
+/// A device to register with [AppStoreConnectClient::register_devices()].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeviceRegistration {
+    pub name: String,
+    pub udid: String,
+
+    /// `IOS`, `MAC_OS`, or `VISION_OS`.
+    pub platform: String,
+}
+
+/// Parse a device list in the tab-separated format of the Apple Developer website.
+///
+/// Each line holds the UDID, name, and optionally the platform (`ios`,
+/// `mac`, or `visionos`, defaulting to `ios`) of a device. The
+/// `Device ID\tDevice Name\tDevice Platform` header line, blank lines, and
+/// lines starting with `#` are ignored. UDIDs aren't validated here, so a
+/// malformed UDID only fails its own registration.
+pub fn parse_device_list(data: &str) -> Result<Vec<DeviceRegistration>, AppleCodesignError> {
+    let mut devices = vec![];
+
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Device ID") {
+            continue;
+        }
+
+        let fields = line.split('\t').map(|f| f.trim()).collect::<Vec<_>>();
+
+        let (udid, name, platform) = match fields.as_slice() {
+            [udid, name] => (udid, name, "ios"),
+            [udid, name, platform] => (udid, name, *platform),
+            _ => {
+                return Err(AppleCodesignError::DeviceList(format!(
+                    "line {}: expected 2 or 3 tab separated fields",
+                    i + 1
+                )))
+            }
+        };
+
+        let platform = match platform.to_ascii_lowercase().as_str() {
+            "ios" => "IOS",
+            "mac" | "macos" | "mac_os" => "MAC_OS",
+            "visionos" | "vision_os" => "VISION_OS",
+            other => {
+                return Err(AppleCodesignError::DeviceList(format!(
+                    "line {}: unknown platform {}",
+                    i + 1,
+                    other
+                )))
+            }
+        };
+
+        devices.push(DeviceRegistration {
+            name: name.to_string(),
+            udid: udid.to_string(),
+            platform: platform.to_string(),
+        });
+    }
+
+    Ok(devices)
+}
+
+/// Decode a certificate and resolve the Apple CA certificates it chains to.
+///
+/// The returned chain starts with the certificate itself. If the issuing CA
+/// isn't known, the chain only holds the certificate.
+pub fn certificate_chain(
+    certificate: &Certificate,
+) -> Result<Vec<CapturedX509Certificate>, AppleCodesignError> {
+    let der = base64::decode(
+        certificate
+            .attributes
+            .certificate_content
+            .as_deref()
+            .unwrap_or_default(),
+    )?;
+    let cert = CapturedX509Certificate::from_der(der)?;
+
+    Ok(match cert.apple_root_certificate_chain() {
+        Some(chain) => chain,
+        None => {
+            warn!(
+                "certificate {} does not chain to a known Apple CA",
+                certificate.id
+            );
+            vec![cert]
+        }
+    })
+}
+
+/// The type of Apple Developer account.
+///
+/// The account type determines which certificates and profiles can be created.
+/// Apple Developer Enterprise Program accounts distribute apps in-house
+/// (outside the App Store), so they can create in-house profiles but not App
+/// Store profiles or Developer ID certificates.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccountType {
+    /// An Apple Developer Program account.
+    #[default]
+    Standard,
+    /// An Apple Developer Enterprise Program account.
+    Enterprise,
+}
+
+impl AccountType {
+    /// Whether a profile type can be created by this account type.
+    pub fn allows_profile_type(&self, profile_type: ProfileType) -> bool {
+        match profile_type {
+            ProfileType::IosAppInhouse | ProfileType::TvosAppInhouse => *self == Self::Enterprise,
+            ProfileType::IosAppStore
+            | ProfileType::TvosAppStore
+            | ProfileType::VisionOsAppStore
+            | ProfileType::MacAppStore
+            | ProfileType::MacCatalystAppStore
+            | ProfileType::MacAppDirect
+            | ProfileType::MacCatalystAppDirect
+            | ProfileType::DriverkitAppStore
+            | ProfileType::DriverkitAppDirect => *self == Self::Standard,
+            _ => true,
+        }
+    }
+
+    /// Whether a certificate type can be created by this account type.
+    pub fn allows_certificate_type(&self, certificate_type: CertificateType) -> bool {
+        match certificate_type {
+            CertificateType::MacAppDistribution
+            | CertificateType::MacInstallerDistribution
+            | CertificateType::DeveloperIdKext
+            | CertificateType::DeveloperIdApplication
+            | CertificateType::DeveloperIdInstaller => *self == Self::Standard,
+            _ => true,
+        }
+    }
+
+    /// Verify a profile type can be created by this account type.
+    pub fn validate_profile_type(
+        &self,
+        profile_type: ProfileType,
+    ) -> Result<(), AppleCodesignError> {
+        if self.allows_profile_type(profile_type) {
+            Ok(())
+        } else {
+            Err(AppleCodesignError::AccountTypeMismatch(format!(
+                "{:?} profiles cannot be created by {:?} accounts",
+                profile_type, self
+            )))
+        }
+    }
+
+    /// Verify a certificate type can be created by this account type.
+    pub fn validate_certificate_type(
+        &self,
+        certificate_type: CertificateType,
+    ) -> Result<(), AppleCodesignError> {
+        if self.allows_certificate_type(certificate_type) {
+            Ok(())
+        } else {
+            Err(AppleCodesignError::AccountTypeMismatch(format!(
+                "{:?} certificates cannot be created by {:?} accounts",
+                certificate_type, self
+            )))
+        }
+    }
+}
+
+/// The decoded `.mobileprovision` content of a [Profile].
+#[derive(Clone, Debug)]
+pub struct DownloadedProfile {
+    /// Raw content of the `.mobileprovision` file.
+    pub data: Vec<u8>,
+
+    /// The parsed profile.
+    pub profile: ProvisioningProfile,
+
+    /// Embedded certificates that expire too soon.
+    pub certificate_warnings: Vec<CertificateExpiryWarning>,
+}
+
+impl DownloadedProfile {
+    /// Decode the content of a profile returned by the API.
+    ///
+    /// Embedded certificates expiring before the profile or within
+    /// `certificate_expiry_days` days are logged as warnings.
+    pub fn from_profile(
+        profile: &Profile,
+        certificate_expiry_days: i64,
+    ) -> Result<Self, AppleCodesignError> {
+        let data = base64::decode(
+            profile
+                .attributes
+                .profile_content
+                .as_deref()
+                .unwrap_or_default(),
+        )?;
+        let parsed = ProvisioningProfile::from_der(&data)?;
+        let certificate_warnings =
+            parsed.certificate_expiry_warnings(Utc::now(), certificate_expiry_days);
+
+        for warning in &certificate_warnings {
+            warn!("profile {}: {}", parsed.name, warning);
+        }
+
+        Ok(Self {
+            data,
+            profile: parsed,
+            certificate_warnings,
+        })
+    }
+}
+
+/// The request document creating a certificate of a type allowed for an account.
+pub(crate) fn certificate_request(
+    account_type: AccountType,
+    csr_pem: &str,
+    certificate_type: CertificateType,
+) -> Result<RequestDocument<CertificateAttributes>, AppleCodesignError> {
+    account_type.validate_certificate_type(certificate_type)?;
+
+    Ok(RequestDocument::create(
+        "certificates",
+        CertificateAttributes {
+            certificate_type: Some(certificate_type),
+            csr_content: Some(csr_pem.to_string()),
+            ..Default::default()
+        },
+    ))
+}
+
+/// The request document creating a profile of a type allowed for an account.
+///
+/// See [AppStoreConnectClient::create_profile()].
+pub(crate) fn profile_request(
+    account_type: AccountType,
+    name: &str,
+    profile_type: ProfileType,
+    bundle_id_id: &str,
+    certificate_ids: &[String],
+    device_ids: &[String],
+) -> Result<RequestDocument<ProfileAttributes>, AppleCodesignError> {
+    account_type.validate_profile_type(profile_type)?;
+
+    if !profile_type.requires_devices() && !device_ids.is_empty() {
+        return Err(AppleCodesignError::AccountTypeMismatch(format!(
+            "{:?} profiles cannot be restricted to devices",
+            profile_type
+        )));
+    }
+
+    Ok(RequestDocument::create(
+        "profiles",
+        ProfileAttributes {
+            name: Some(name.to_string()),
+            profile_type: Some(profile_type),
+            ..Default::default()
+        },
+    )
+    .with_relationship("bundleId", "bundleIds", bundle_id_id)
+    .with_relationships(
+        "certificates",
+        certificate_ids
+            .iter()
+            .map(|id| ResourceIdentifier::new("certificates", id))
+            .collect(),
+    )
+    .with_relationships(
+        "devices",
+        device_ids
+            .iter()
+            .map(|id| ResourceIdentifier::new("devices", id))
+            .collect(),
+    ))
+}
+
+/// The request document registering a device, with a normalized UDID.
+pub(crate) fn device_request(
+    name: &str,
+    udid: &str,
+    platform: &str,
+) -> Result<RequestDocument<DeviceAttributes>, AppleCodesignError> {
+    Ok(RequestDocument::create(
+        "devices",
+        DeviceAttributes {
+            name: Some(name.to_string()),
+            platform: Some(platform.to_string()),
+            udid: Some(normalize_udid(udid)?),
+            ..Default::default()
+        },
+    ))
+}
+
+impl AppStoreConnectClient {
+    /// List signing certificates in the account.
+    pub fn list_certificates(&self) -> Result<Vec<Certificate>, AppleCodesignError> {
+        self.api_get_all("/v1/certificates", &[])
+    }
+
+    /// List signing certificates of a type.
+    pub fn list_certificates_of_type(
+        &self,
+        certificate_type: CertificateType,
+    ) -> Result<Vec<Certificate>, AppleCodesignError> {
+        self.api_get_all(
+            "/v1/certificates",
+            &CertificateQuery::new()
+                .certificate_type(certificate_type)
+                .to_query(),
+        )
+    }
+
+    /// Fetch a signing certificate by its ID.
+    pub fn get_certificate(&self, certificate_id: &str) -> Result<Certificate, AppleCodesignError> {
+        let doc = self.api_get::<Document<Certificate>>(
+            &format!("/v1/certificates/{}", certificate_id),
+            &[],
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// Create a signing certificate from a certificate signing request.
+    ///
+    /// The certificate type is validated against the client's [AccountType].
+    pub fn create_certificate(
+        &self,
+        csr_pem: &str,
+        certificate_type: CertificateType,
+    ) -> Result<Certificate, AppleCodesignError> {
+        let body = certificate_request(self.account_type(), csr_pem, certificate_type)?;
+
+        let doc = self.api_post::<_, Document<Certificate>>("/v1/certificates", &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// Create an in-house distribution certificate.
+    ///
+    /// Only Apple Developer Enterprise Program accounts can distribute in-house.
+    pub fn create_in_house_distribution_certificate(
+        &self,
+        csr_pem: &str,
+    ) -> Result<Certificate, AppleCodesignError> {
+        if self.account_type() != AccountType::Enterprise {
+            return Err(AppleCodesignError::AccountTypeMismatch(
+                "in-house distribution requires an Enterprise account".into(),
+            ));
+        }
+
+        self.create_certificate(csr_pem, CertificateType::Distribution)
+    }
+
+    /// Create a Developer ID certificate.
+    ///
+    /// Teams can only have [DEVELOPER_ID_CERTIFICATE_LIMIT] Developer ID
+    /// certificates of each type, and revoking one stops software signed with
+    /// it from launching. Creation is refused once the limit is reached so a
+    /// slot is never freed by accident.
+    pub fn create_developer_id_certificate(
+        &self,
+        csr_pem: &str,
+        certificate_type: CertificateType,
+    ) -> Result<Certificate, AppleCodesignError> {
+        if !certificate_type.is_developer_id() {
+            return Err(AppleCodesignError::CertificateGeneric(format!(
+                "{:?} is not a Developer ID certificate type",
+                certificate_type
+            )));
+        }
+
+        let existing = self.list_certificates_of_type(certificate_type)?;
+
+        if existing.len() >= DEVELOPER_ID_CERTIFICATE_LIMIT {
+            return Err(AppleCodesignError::DeveloperIdCertificateLimit(format!(
+                "{} {:?} certificates exist (limit {}): {}",
+                existing.len(),
+                certificate_type,
+                DEVELOPER_ID_CERTIFICATE_LIMIT,
+                existing
+                    .iter()
+                    .map(|c| c.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        self.create_certificate(csr_pem, certificate_type)
+    }
+
+    /// Revoke a signing certificate.
+    ///
+    /// Provisioning profiles containing the certificate become invalid.
+    pub fn revoke_certificate(&self, certificate_id: &str) -> Result<(), AppleCodesignError> {
+        self.api_delete(&format!("/v1/certificates/{}", certificate_id))
+    }
+
+    /// List provisioning profiles in the account.
+    pub fn list_profiles(&self) -> Result<Vec<Profile>, AppleCodesignError> {
+        self.api_get_all("/v1/profiles", &[])
+    }
+
+    /// Fetch a provisioning profile by its ID.
+    pub fn get_profile(&self, profile_id: &str) -> Result<Profile, AppleCodesignError> {
+        let doc =
+            self.api_get::<Document<Profile>>(&format!("/v1/profiles/{}", profile_id), &[])?;
+
+        Ok(doc.data)
+    }
+
+    /// Download the `.mobileprovision` content of a provisioning profile.
+    ///
+    /// See [DownloadedProfile::from_profile()] for the certificate expiry warnings.
+    pub fn download_profile(
+        &self,
+        profile_id: &str,
+        certificate_expiry_days: i64,
+    ) -> Result<DownloadedProfile, AppleCodesignError> {
+        DownloadedProfile::from_profile(&self.get_profile(profile_id)?, certificate_expiry_days)
+    }
+
+    /// Create a provisioning profile.
+    ///
+    /// `bundle_id_id` is the resource ID of the bundle ID (not the bundle
+    /// identifier string). Devices are required for development and ad hoc
+    /// profiles and must be empty for others. The profile type is validated
+    /// against the client's [AccountType].
+    pub fn create_profile(
+        &self,
+        name: &str,
+        profile_type: ProfileType,
+        bundle_id_id: &str,
+        certificate_ids: &[String],
+        device_ids: &[String],
+    ) -> Result<Profile, AppleCodesignError> {
+        let body = profile_request(
+            self.account_type(),
+            name,
+            profile_type,
+            bundle_id_id,
+            certificate_ids,
+            device_ids,
+        )?;
+
+        let doc = self.api_post::<_, Document<Profile>>("/v1/profiles", &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// Delete a provisioning profile.
+    pub fn delete_profile(&self, profile_id: &str) -> Result<(), AppleCodesignError> {
+        self.api_delete(&format!("/v1/profiles/{}", profile_id))
+    }
+
+    /// List devices registered to the account.
+    pub fn list_devices(&self) -> Result<Vec<Device>, AppleCodesignError> {
+        self.api_get_all("/v1/devices", &[])
+    }
+
+    /// Fetch a registered device by its ID.
+    pub fn get_device(&self, device_id: &str) -> Result<Device, AppleCodesignError> {
+        let doc = self.api_get::<Document<Device>>(&format!("/v1/devices/{}", device_id), &[])?;
+
+        Ok(doc.data)
+    }
+
+    /// Find a registered device by its UDID.
+    ///
+    /// The UDID is normalized with [normalize_udid()].
+    pub fn find_device_by_udid(&self, udid: &str) -> Result<Option<Device>, AppleCodesignError> {
+        let udid = normalize_udid(udid)?;
+
+        Ok(self
+            .api_get_all::<DeviceAttributes>(
+                "/v1/devices",
+                &DeviceQuery::new().udid(&udid).to_query(),
+            )?
+            .into_iter()
+            .next())
+    }
+
+    /// Register a device to the account.
+    ///
+    /// `platform` is `IOS`, `MAC_OS`, or `VISION_OS`. The UDID is normalized
+    /// with [normalize_udid()], so malformed UDIDs are rejected before they
+    /// are sent.
+    pub fn register_device(
+        &self,
+        name: &str,
+        udid: &str,
+        platform: &str,
+    ) -> Result<Device, AppleCodesignError> {
+        let body = device_request(name, udid, platform)?;
+
+        let doc = self.api_post::<_, Document<Device>>("/v1/devices", &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// Register many devices, continuing past failures.
+    ///
+    /// Devices already registered with the UDID aren't registered again;
+    /// the existing device is reported instead. Items of the report are
+    /// named by UDID.
+    pub fn register_devices(&self, devices: &[DeviceRegistration]) -> BatchReport<Device> {
+        BatchReport::run(devices.iter().map(|d| (d.udid.clone(), d)), |device| {
+            if let Some(existing) = self.find_device_by_udid(&device.udid)? {
+                warn!("device {} is already registered", device.udid);
+                return Ok(existing);
+            }
+
+            warn!("registering device {} ({})", device.udid, device.name);
+            self.register_device(&device.name, &device.udid, &device.platform)
+        })
+    }
+
+    /// Rename, enable, or disable a registered device.
+    ///
+    /// Only the `name` and `status` attributes can be modified.
+    pub fn update_device(
+        &self,
+        device_id: &str,
+        name: Option<String>,
+        status: Option<DeviceStatus>,
+    ) -> Result<Updated<DeviceAttributes>, AppleCodesignError> {
+        let body = RequestDocument::update(
+            "devices",
+            device_id,
+            DeviceAttributes {
+                name,
+                status,
+                ..Default::default()
+            },
+        );
+
+        self.api_update(&format!("/v1/devices/{}", device_id), &body)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    const ENTERPRISE_PROFILES: &str = include_str!("../testdata/asc-enterprise-profiles.json");
+    const ENTERPRISE_CERTIFICATES: &str =
+        include_str!("../testdata/asc-enterprise-certificates.json");
+
+    #[test]
+    fn udid_normalization() {
+        assert_eq!(
+            normalize_udid(" 00008120-001a2b3c4d5e6f70\n").unwrap(),
+            "00008120-001A2B3C4D5E6F70"
+        );
+        assert_eq!(
+            normalize_udid("00008120001A2B3C4D5E6F70").unwrap(),
+            "00008120-001A2B3C4D5E6F70"
+        );
+        assert_eq!(
+            normalize_udid("A1B2C3D4E5F6A7B8C9D0A1B2C3D4E5F6A7B8C9D0").unwrap(),
+            "a1b2c3d4e5f6a7b8c9d0a1b2c3d4e5f6a7b8c9d0"
+        );
+        assert_eq!(
+            normalize_udid("0a1b2c3d-4e5f-6a7b-8c9d-0e1f2a3b4c5d").unwrap(),
+            "0A1B2C3D-4E5F-6A7B-8C9D-0E1F2A3B4C5D"
+        );
+
+        assert!(normalize_udid("0001").is_err());
+        assert!(normalize_udid("00008120-001A2B3C4D5E6F7G").is_err());
+        assert!(normalize_udid("00000000-0000000000000000").is_err());
+    }
+
+    #[test]
+    fn device_lists() -> Result<(), AppleCodesignError> {
+        let devices = parse_device_list(
+            "Device ID\tDevice Name\tDevice Platform\n\
+             00008120-001A2B3C4D5E6F70\tPhone\tios\n\
+             \n\
+             # retired\n\
+             00008120-001A2B3C4D5E6F71\tMac\tmac\n\
+             bogus\tTablet\n",
+        )?;
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices[1].platform, "MAC_OS");
+        assert_eq!(devices[2].platform, "IOS");
+        assert!(parse_device_list("00008120-001A2B3C4D5E6F70\n").is_err());
+        assert!(parse_device_list("00008120-001A2B3C4D5E6F70\tWatch\twatchos\n").is_err());
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/devices",
+                200,
+                fixtures::collection(vec![fixtures::device("D1", "00008120-001A2B3C4D5E6F70")]),
+            )
+            .json("GET", "/v1/devices", 200, fixtures::collection(vec![]))
+            .json(
+                "POST",
+                "/v1/devices",
+                201,
+                fixtures::document(fixtures::device("D2", "00008120-001A2B3C4D5E6F71")),
+            )
+            .start()?;
+
+        let report = server.client()?.register_devices(&devices);
+        assert_eq!(
+            report
+                .successes()
+                .map(|(_, d)| d.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["D1", "D2"]
+        );
+        assert!(matches!(
+            report.results()[2].result,
+            Err(AppleCodesignError::InvalidUdid(_, _))
+        ));
+        assert!(matches!(
+            report.result(),
+            Err(AppleCodesignError::BatchPartiallyFailed(1, 3))
+        ));
+
+        // The malformed UDID never reached the server.
+        assert_eq!(server.requests().len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn account_type_validation() {
+        assert!(AccountType::Enterprise.allows_profile_type(ProfileType::IosAppInhouse));
+        assert!(!AccountType::Standard.allows_profile_type(ProfileType::IosAppInhouse));
+        assert!(!AccountType::Enterprise.allows_profile_type(ProfileType::IosAppStore));
+        assert!(AccountType::Enterprise.allows_profile_type(ProfileType::IosAppDevelopment));
+        assert!(!AccountType::Enterprise
+            .allows_certificate_type(CertificateType::DeveloperIdApplication));
+        assert!(AccountType::Enterprise
+            .validate_certificate_type(CertificateType::Distribution)
+            .is_ok());
+    }
+
+    #[test]
+    fn enterprise_responses() -> Result<(), AppleCodesignError> {
+        let profiles = serde_json::from_str::<Document<Vec<Profile>>>(ENTERPRISE_PROFILES)?.data;
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(
+            profiles[0].attributes.profile_type,
+            Some(ProfileType::IosAppInhouse)
+        );
+        assert_eq!(
+            profiles[1].attributes.profile_state,
+            Some(ProfileState::Invalid)
+        );
+        for profile in &profiles {
+            AccountType::Enterprise
+                .validate_profile_type(profile.attributes.profile_type.unwrap())?;
+        }
+        assert!(AccountType::Standard
+            .validate_profile_type(profiles[0].attributes.profile_type.unwrap())
+            .is_err());
+
+        let certs =
+            serde_json::from_str::<Document<Vec<Certificate>>>(ENTERPRISE_CERTIFICATES)?.data;
+
+        assert_eq!(certs.len(), 1);
+        assert_eq!(
+            certs[0].attributes.certificate_type,
+            Some(CertificateType::Distribution)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn developer_id_limit() -> Result<(), AppleCodesignError> {
+        let cert = |id: &str| fixtures::certificate(id, "DEVELOPER_ID_APPLICATION", "");
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/certificates",
+                200,
+                fixtures::collection((1..=5).map(|i| cert(&format!("C{}", i))).collect()),
+            )
+            .json(
+                "GET",
+                "/v1/certificates",
+                200,
+                fixtures::collection(vec![cert("C1")]),
+            )
+            .json(
+                "POST",
+                "/v1/certificates",
+                201,
+                fixtures::document(cert("C2")),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        assert!(matches!(
+            client.create_developer_id_certificate("csr", CertificateType::DeveloperIdApplication),
+            Err(AppleCodesignError::DeveloperIdCertificateLimit(_))
+        ));
+        assert!(client
+            .create_developer_id_certificate("csr", CertificateType::MacAppDistribution)
+            .is_err());
+
+        let created = client
+            .create_developer_id_certificate("csr", CertificateType::DeveloperIdApplication)?;
+        assert_eq!(created.id, "C2");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0]
+            .query
+            .as_deref()
+            .unwrap_or_default()
+            .contains("filter%5BcertificateType%5D=DEVELOPER_ID_APPLICATION"));
+        assert_eq!(requests[2].method, "POST");
+
+        Ok(())
+    }
+}
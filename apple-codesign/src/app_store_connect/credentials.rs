@@ -0,0 +1,159 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Authentication modes of an App Store Connect client.
+//!
+//! Clients normally authenticate with an API Key, minting a JWT for each
+//! token lifetime. Some legacy Transporter (iTMS) endpoints, as used by
+//! `altool`, accept an Apple ID session instead: the Apple ID and an
+//! [app-specific password](https://support.apple.com/en-us/HT204397) sent
+//! with HTTP basic authentication.
+//!
+//! [AppStoreConnectClient::from_credentials()] constructs a client with
+//! either mode. The endpoints of the App Store Connect API reject Apple ID
+//! sessions and this crate doesn't implement Transporter endpoints, so
+//! requests of clients using an Apple ID session fail with
+//! [AppleCodesignError::AppStoreConnectAppleIdSession] without being sent.
+//! The app-specific password is never sent to the App Store Connect API.
+
+use {
+    crate::{
+        app_store_connect::{api_token::ConnectTokenEncoder, AppStoreConnectClient},
+        AppleCodesignError,
+    },
+    zeroize::Zeroize,
+};
+
+/// How a client authenticates its requests.
+pub enum Credentials {
+    /// An App Store Connect API Key.
+    ApiKey(ConnectTokenEncoder),
+
+    /// An Apple ID and an app-specific password for it.
+    ///
+    /// The password is zeroed on drop.
+    AppleId {
+        apple_id: String,
+        app_specific_password: String,
+    },
+}
+
+impl Drop for Credentials {
+    fn drop(&mut self) {
+        if let Self::AppleId {
+            app_specific_password,
+            ..
+        } = self
+        {
+            app_specific_password.zeroize();
+        }
+    }
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ApiKey(encoder) => f.debug_tuple("ApiKey").field(&encoder.key_id()).finish(),
+            Self::AppleId { apple_id, .. } => f
+                .debug_struct("AppleId")
+                .field("apple_id", apple_id)
+                .field("app_specific_password", &crate::redact::REDACTED)
+                .finish(),
+        }
+    }
+}
+
+impl From<ConnectTokenEncoder> for Credentials {
+    fn from(encoder: ConnectTokenEncoder) -> Self {
+        Self::ApiKey(encoder)
+    }
+}
+
+/// The Apple ID a client authenticates as.
+pub(crate) struct AppleIdSession {
+    apple_id: String,
+}
+
+impl AppStoreConnectClient {
+    /// Construct a client authenticating with the given credentials.
+    pub fn from_credentials(credentials: Credentials) -> Result<Self, AppleCodesignError> {
+        match credentials {
+            Credentials::ApiKey(ref encoder) => Self::new(encoder.clone()),
+            Credentials::AppleId { ref apple_id, .. } => {
+                // Tokens are never minted, but the client requires an encoder.
+                // Naming it after the Apple ID partitions cached state by
+                // account.
+                let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+                    &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+                    &ring::rand::SystemRandom::new(),
+                )
+                .map_err(|_| {
+                    AppleCodesignError::AppStoreConnectApiKey("error generating ECDSA key".into())
+                })?;
+
+                let mut client = Self::new(ConnectTokenEncoder::individual_from_ecdsa_der(
+                    apple_id.clone(),
+                    key.as_ref(),
+                )?)?;
+                client.apple_id = Some(AppleIdSession {
+                    apple_id: apple_id.clone(),
+                });
+
+                Ok(client)
+            }
+        }
+    }
+
+    /// The Apple ID the client authenticates as, if it uses an Apple ID session.
+    pub fn apple_id(&self) -> Option<&str> {
+        self.apple_id
+            .as_ref()
+            .map(|session| session.apple_id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn apple_id() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![]))
+            .start()?;
+
+        let credentials = Credentials::AppleId {
+            apple_id: "dev@example.com".into(),
+            app_specific_password: "abcd-efgh-ijkl-mnop".into(),
+        };
+        assert!(!format!("{:?}", credentials).contains("abcd"));
+
+        let mut client = AppStoreConnectClient::from_credentials(credentials)?;
+        client.set_api_url(server.url());
+        assert_eq!(client.apple_id(), Some("dev@example.com"));
+        assert_eq!(client.api_key_id(), "dev@example.com");
+
+        // The App Store Connect API doesn't accept the session, so the
+        // password isn't sent to it.
+        assert!(matches!(
+            client.api_get::<serde_json::Value>("/v1/apps", &[]),
+            Err(AppleCodesignError::AppStoreConnectAppleIdSession)
+        ));
+        assert!(server.requests().is_empty());
+
+        // API Keys keep using tokens.
+        let client = server.client()?;
+        assert!(client.apple_id().is_none());
+        client.api_get::<serde_json::Value>("/v1/apps", &[])?;
+        assert!(server.requests()[0]
+            .header("authorization")
+            .unwrap()
+            .starts_with("Bearer "));
+
+        Ok(())
+    }
+}
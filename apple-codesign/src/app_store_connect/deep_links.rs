@@ -0,0 +1,296 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Stable identifiers and web pages of resources.
+//!
+//! Tickets and chat alerts generated from this crate refer to resources.
+//! Resource IDs alone are meaningless to humans and ambiguous across teams,
+//! so resources are identified by a [ResourceUrn] of the form
+//! `asc://team/<team ID>/<kind>/<resource ID>`, e.g.
+//! `asc://team/DEADBEEF42/profile/ABC123`. [web_url()] resolves the page
+//! showing a resource on the App Store Connect or Apple Developer website,
+//! where one is known.
+//!
+//! [add_resource_links()] adds both to the `links` of every resource object
+//! in a JSON document, as CLI commands do for their JSON output.
+
+use {
+    crate::AppleCodesignError,
+    serde::Serialize,
+    serde_json::Value,
+    std::{fmt::Display, str::FromStr},
+};
+
+/// The scheme of [ResourceUrn].
+pub const URN_SCHEME: &str = "asc";
+
+/// API resource types and the kind naming them in a [ResourceUrn].
+const KINDS: &[(&str, &str)] = &[
+    ("apps", "app"),
+    ("appStoreVersions", "app-store-version"),
+    ("betaGroups", "beta-group"),
+    ("betaTesters", "beta-tester"),
+    ("builds", "build"),
+    ("bundleIds", "bundle-id"),
+    ("certificates", "certificate"),
+    ("devices", "device"),
+    ("profiles", "profile"),
+    ("users", "user"),
+];
+
+/// The [ResourceUrn] kind of an API resource type, e.g. `bundle-id` for `bundleIds`.
+pub fn resource_kind(resource_type: &str) -> Option<&'static str> {
+    KINDS
+        .iter()
+        .find(|(t, _)| *t == resource_type)
+        .map(|(_, kind)| *kind)
+}
+
+/// The API resource type of a [ResourceUrn] kind.
+pub fn kind_resource_type(kind: &str) -> Option<&'static str> {
+    KINDS.iter().find(|(_, k)| *k == kind).map(|(t, _)| *t)
+}
+
+/// The web page showing a resource, if one is known.
+///
+/// `app_id` is the app the resource belongs to. Pages of beta groups are
+/// only known with it.
+pub fn web_url(resource_type: &str, id: &str, app_id: Option<&str>) -> Option<String> {
+    const ASC: &str = "https://appstoreconnect.apple.com";
+    const DEVELOPER: &str = "https://developer.apple.com/account/resources";
+
+    match (resource_type, app_id) {
+        ("apps", _) => Some(format!("{}/apps/{}/appstore", ASC, id)),
+        ("betaGroups", Some(app_id)) => {
+            Some(format!("{}/apps/{}/testflight/groups/{}", ASC, app_id, id))
+        }
+        ("bundleIds", _) => Some(format!("{}/identifiers/bundleId/edit/{}", DEVELOPER, id)),
+        ("certificates", _) => Some(format!("{}/certificates/download/{}", DEVELOPER, id)),
+        ("devices", _) => Some(format!("{}/devices/edit/{}", DEVELOPER, id)),
+        ("profiles", _) => Some(format!("{}/profiles/review/{}", DEVELOPER, id)),
+        _ => None,
+    }
+}
+
+/// A stable identifier of a resource of a team.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResourceUrn {
+    pub team_id: String,
+
+    /// The API resource type, e.g. `profiles`.
+    pub resource_type: String,
+
+    pub id: String,
+}
+
+impl ResourceUrn {
+    /// Construct an instance for a resource.
+    ///
+    /// Returns [None] if resources of the type have no [ResourceUrn] kind.
+    pub fn new(team_id: &str, resource_type: &str, id: &str) -> Option<Self> {
+        resource_kind(resource_type).map(|_| Self {
+            team_id: team_id.to_string(),
+            resource_type: resource_type.to_string(),
+            id: id.to_string(),
+        })
+    }
+
+    /// The web page showing the resource. See [web_url()].
+    pub fn web_url(&self, app_id: Option<&str>) -> Option<String> {
+        web_url(&self.resource_type, &self.id, app_id)
+    }
+}
+
+impl Display for ResourceUrn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}://team/{}/{}/{}",
+            URN_SCHEME,
+            self.team_id,
+            resource_kind(&self.resource_type).unwrap_or(&self.resource_type),
+            self.id
+        )
+    }
+}
+
+impl FromStr for ResourceUrn {
+    type Err = AppleCodesignError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AppleCodesignError::ResourceUrn(s.to_string());
+
+        let rest = s
+            .strip_prefix(URN_SCHEME)
+            .and_then(|rest| rest.strip_prefix("://team/"))
+            .ok_or_else(invalid)?;
+
+        match rest.split('/').collect::<Vec<_>>().as_slice() {
+            [team_id, kind, id] if !team_id.is_empty() && !id.is_empty() => Ok(Self {
+                team_id: team_id.to_string(),
+                resource_type: kind_resource_type(kind).ok_or_else(invalid)?.to_string(),
+                id: id.to_string(),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The identifier and web page of a resource.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ResourceLinks {
+    /// The [ResourceUrn], if the team is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urn: Option<String>,
+
+    /// The [web_url()], if one is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web: Option<String>,
+}
+
+impl ResourceLinks {
+    /// Resolve the links of a resource.
+    pub fn new(team_id: Option<&str>, resource_type: &str, id: &str, app_id: Option<&str>) -> Self {
+        Self {
+            urn: team_id
+                .and_then(|team_id| ResourceUrn::new(team_id, resource_type, id))
+                .map(|urn| urn.to_string()),
+            web: web_url(resource_type, id, app_id),
+        }
+    }
+
+    /// Whether neither link is known.
+    pub fn is_empty(&self) -> bool {
+        self.urn.is_none() && self.web.is_none()
+    }
+}
+
+/// Add [ResourceLinks] to the `links` of every resource object in a JSON value.
+///
+/// Resource objects are objects with string `type` and `id` members of a
+/// resource type having a [ResourceUrn] kind and `attributes` or
+/// `relationships`. Resource identifiers in relationships are left alone.
+/// `team_id` is called at most once, when the first resource is found, so
+/// documents without resources don't cost a lookup of the team.
+pub fn add_resource_links(value: &mut Value, team_id: impl FnOnce() -> Option<String>) {
+    let mut team_id = Some(team_id);
+    let mut resolved = None;
+
+    visit(value, &mut || {
+        if let Some(f) = team_id.take() {
+            resolved = f();
+        }
+
+        resolved.clone()
+    });
+
+    fn visit(value: &mut Value, team_id: &mut dyn FnMut() -> Option<String>) {
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    visit(value, team_id);
+                }
+            }
+            Value::Object(object) => {
+                for value in object.values_mut() {
+                    visit(value, team_id);
+                }
+
+                if !object.contains_key("attributes") && !object.contains_key("relationships") {
+                    return;
+                }
+
+                let (resource_type, id) = match (object.get("type"), object.get("id")) {
+                    (Some(Value::String(t)), Some(Value::String(id)))
+                        if resource_kind(t).is_some() =>
+                    {
+                        (t.clone(), id.clone())
+                    }
+                    _ => return,
+                };
+
+                let app_id = object
+                    .get("relationships")
+                    .and_then(|r| r.get("app"))
+                    .and_then(|r| r["data"]["id"].as_str())
+                    .map(|id| id.to_string());
+
+                let links = ResourceLinks::new(
+                    team_id().as_deref(),
+                    &resource_type,
+                    &id,
+                    app_id.as_deref(),
+                );
+                if links.is_empty() {
+                    return;
+                }
+
+                let target = object
+                    .entry("links")
+                    .or_insert_with(|| Value::Object(Default::default()));
+                if let Value::Object(target) = target {
+                    if let Some(urn) = links.urn {
+                        target.insert("urn".into(), urn.into());
+                    }
+                    if let Some(web) = links.web {
+                        target.insert("web".into(), web.into());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn urns() -> Result<(), AppleCodesignError> {
+        let urn = ResourceUrn::new("DEADBEEF42", "bundleIds", "ABC123").unwrap();
+        assert_eq!(urn.to_string(), "asc://team/DEADBEEF42/bundle-id/ABC123");
+        assert_eq!(ResourceUrn::from_str(&urn.to_string())?, urn);
+        assert_eq!(
+            urn.web_url(None).as_deref(),
+            Some("https://developer.apple.com/account/resources/identifiers/bundleId/edit/ABC123")
+        );
+
+        assert!(ResourceUrn::new("DEADBEEF42", "sandboxTesters", "1").is_none());
+        assert!(ResourceUrn::from_str("asc://team/DEADBEEF42/widget/1").is_err());
+        assert!(ResourceUrn::from_str("asc://team/DEADBEEF42/profile").is_err());
+        assert!(ResourceUrn::from_str("https://example.com").is_err());
+
+        let mut value = json!([
+            {"type": "profiles", "id": "P1", "attributes": {}, "links": {"self": "x"}},
+            {"type": "betaGroups", "id": "G1", "relationships": {"app": {"data": {"type": "apps", "id": "A1"}}}},
+            {"type": "sandboxTesters", "id": "S1", "attributes": {}},
+        ]);
+        let mut lookups = 0;
+        add_resource_links(&mut value, || {
+            lookups += 1;
+            Some("DEADBEEF42".into())
+        });
+        assert_eq!(lookups, 1);
+
+        assert_eq!(value[0]["links"]["self"], "x");
+        assert_eq!(value[0]["links"]["urn"], "asc://team/DEADBEEF42/profile/P1");
+        assert_eq!(
+            value[1]["links"]["web"],
+            "https://appstoreconnect.apple.com/apps/A1/testflight/groups/G1"
+        );
+        assert!(value[1]["relationships"]["app"]["data"]
+            .get("links")
+            .is_none());
+        assert!(value[2].get("links").is_none());
+
+        // Without a team, only web pages are linked.
+        let mut value = json!({"type": "certificates", "id": "C1", "attributes": {}});
+        add_resource_links(&mut value, || None);
+        assert!(value["links"].get("urn").is_none());
+        assert!(value["links"]["web"].is_string());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Dependencies between App Store Connect resources.
+//!
+//! Revoking, deleting, or disabling a resource affects the resources referring
+//! to it: revoking a certificate invalidates the provisioning profiles
+//! containing it, a disabled device can no longer run apps using profiles
+//! listing it. [AppStoreConnectClient::dependencies_of()] answers which
+//! resources depend on a given resource by walking JSON:API relationships.
+//!
+//! Known dependencies:
+//!
+//! | Resource type  | Dependents                                               |
+//! | -------------- | -------------------------------------------------------- |
+//! | `certificates` | `profiles` with the certificate                          |
+//! | `devices`      | `profiles` with the device                               |
+//! | `bundleIds`    | `profiles` for the bundle ID, `apps` with its identifier |
+//!
+//! Resources of other types have no known dependents.
+
+use {
+    crate::{
+        app_store_connect::{
+            certs_api::Profile, json_api::ResourceIdentifier, AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    serde::Serialize,
+};
+
+/// Relationships of profiles to include when walking dependencies.
+const PROFILE_RELATIONSHIPS: &[&str] = &["bundleId", "certificates", "devices"];
+
+/// Maximum number of linked resources per to-many relationship.
+///
+/// This is the maximum allowed by the API. Dependents linked beyond it are
+/// not found.
+const RELATIONSHIP_LIMIT: usize = 50;
+
+/// A resource depending on another resource.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependent {
+    /// The depending resource.
+    pub resource: ResourceIdentifier,
+
+    /// Human readable name of the depending resource, if it has one.
+    pub name: Option<String>,
+}
+
+impl Dependent {
+    fn profile(profile: &Profile) -> Self {
+        Self {
+            resource: ResourceIdentifier::new("profiles", &profile.id),
+            name: profile.attributes.name.clone(),
+        }
+    }
+}
+
+impl AppStoreConnectClient {
+    /// List provisioning profiles with their relationships to bundle IDs,
+    /// certificates, and devices.
+    fn list_profiles_with_relationships(&self) -> Result<Vec<Profile>, AppleCodesignError> {
+        self.api_get_all(
+            "/v1/profiles",
+            &[
+                ("include", PROFILE_RELATIONSHIPS.join(",")),
+                ("limit[certificates]", RELATIONSHIP_LIMIT.to_string()),
+                ("limit[devices]", RELATIONSHIP_LIMIT.to_string()),
+            ],
+        )
+    }
+
+    /// Resolve the resources depending on a resource.
+    ///
+    /// See the [module documentation](self) for the dependencies found.
+    pub fn dependencies_of(
+        &self,
+        resource: &ResourceIdentifier,
+    ) -> Result<Vec<Dependent>, AppleCodesignError> {
+        let relationship = match resource.r#type.as_str() {
+            "bundleIds" => "bundleId",
+            "certificates" => "certificates",
+            "devices" => "devices",
+            _ => return Ok(vec![]),
+        };
+
+        let mut dependents = self
+            .list_profiles_with_relationships()?
+            .iter()
+            .filter(|profile| profile.related(relationship).contains(&resource))
+            .map(Dependent::profile)
+            .collect::<Vec<_>>();
+
+        // Apps refer to their bundle ID by identifier, not by relationship.
+        if resource.r#type == "bundleIds" {
+            let bundle_id = self.get_bundle_id(&resource.id)?;
+
+            if let Some(identifier) = bundle_id.attributes.identifier {
+                dependents.extend(
+                    self.list_apps()?
+                        .into_iter()
+                        .filter(|app| app.attributes.bundle_id.as_ref() == Some(&identifier))
+                        .map(|app| Dependent {
+                            resource: ResourceIdentifier::new("apps", &app.id),
+                            name: app.attributes.name,
+                        }),
+                );
+            }
+        }
+
+        Ok(dependents)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::{json, Value},
+    };
+
+    fn profile(id: &str, bundle_id: &str, certificate_ids: &[&str], device_ids: &[&str]) -> Value {
+        let identifiers = |r#type: &str, ids: &[&str]| {
+            ids.iter()
+                .map(|id| json!({"type": r#type, "id": id}))
+                .collect::<Vec<_>>()
+        };
+
+        let mut profile = fixtures::profile(id, id, "IOS_APP_DEVELOPMENT", "");
+        profile["relationships"] = json!({
+            "bundleId": {"data": {"type": "bundleIds", "id": bundle_id}},
+            "certificates": {"data": identifiers("certificates", certificate_ids)},
+            "devices": {"data": identifiers("devices", device_ids)},
+        });
+
+        profile
+    }
+
+    fn ids(dependents: &[Dependent]) -> Vec<String> {
+        dependents
+            .iter()
+            .map(|d| format!("{}/{}", d.resource.r#type, d.resource.id))
+            .collect()
+    }
+
+    #[test]
+    fn dependents() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/profiles",
+                200,
+                fixtures::collection(vec![
+                    profile("P1", "B1", &["C1", "C2"], &["D1"]),
+                    profile("P2", "B2", &["C2"], &[]),
+                ]),
+            )
+            .json(
+                "GET",
+                "/v1/bundleIds/B1",
+                200,
+                fixtures::document(fixtures::bundle_id("B1", "com.example.a")),
+            )
+            .json(
+                "GET",
+                "/v1/apps",
+                200,
+                fixtures::collection(vec![
+                    fixtures::app("A1", "com.example.a"),
+                    fixtures::app("A2", "com.example.b"),
+                ]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let of = |r#type: &str, id: &str| {
+            client
+                .dependencies_of(&ResourceIdentifier::new(r#type, id))
+                .map(|x| ids(&x))
+        };
+
+        assert_eq!(of("certificates", "C1")?, vec!["profiles/P1"]);
+        assert_eq!(
+            of("certificates", "C2")?,
+            vec!["profiles/P1", "profiles/P2"]
+        );
+        assert_eq!(of("devices", "D1")?, vec!["profiles/P1"]);
+        assert!(of("devices", "D2")?.is_empty());
+        assert_eq!(of("bundleIds", "B1")?, vec!["profiles/P1", "apps/A1"]);
+        assert!(of("profiles", "P1")?.is_empty());
+
+        assert!(server.requests()[0]
+            .query
+            .as_deref()
+            .unwrap_or_default()
+            .contains("include=bundleId%2Ccertificates%2Cdevices"));
+
+        Ok(())
+    }
+}
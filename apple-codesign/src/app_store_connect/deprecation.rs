@@ -0,0 +1,304 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Warnings about deprecated API endpoints.
+//!
+//! Apple announces the removal of endpoints in release notes, which are easy
+//! to miss until requests start failing with `410 Gone`. Every response is
+//! checked for signs of deprecation:
+//!
+//! * A `Deprecation` header (draft-ietf-httpapi-deprecation-header).
+//! * A `Sunset` header (RFC 8594).
+//! * A `410 Gone` status.
+//! * A path in [KNOWN_DEPRECATIONS], the endpoints Apple has announced as
+//!   deprecated.
+//!
+//! A [Deprecation] is logged and delivered as a
+//! [NotificationKind::EndpointDeprecated] event to the client's notification
+//! sink (see [super::notify]), once per endpoint per client. The replacement
+//! comes from a `Link` header with `rel="successor-version"` or from
+//! [KNOWN_DEPRECATIONS].
+
+use {
+    crate::app_store_connect::{
+        notify::{NotificationEvent, NotificationKind},
+        AppStoreConnectClient,
+    },
+    log::warn,
+    reqwest::{header::HeaderMap, Method},
+    serde::Serialize,
+};
+
+/// Endpoints Apple has deprecated and their replacements.
+///
+/// Paths are matched by prefix. `*` matches any single segment, such as an ID.
+pub const KNOWN_DEPRECATIONS: &[(&str, &str)] = &[
+    (
+        "/v1/appPrices",
+        "app price schedules (/v1/appPriceSchedules)",
+    ),
+    (
+        "/v1/apps/*/prices",
+        "the app price schedule (/v1/apps/{id}/appPriceSchedule)",
+    ),
+    ("/v1/appPricePoints", "/v3/appPricePoints"),
+    (
+        "/v1/appPreOrders",
+        "app availabilities (/v2/appAvailabilities)",
+    ),
+    (
+        "/v1/apps/*/preOrder",
+        "app availabilities (/v2/appAvailabilities)",
+    ),
+    (
+        "/v1/apps/*/availableTerritories",
+        "app availabilities (/v2/appAvailabilities)",
+    ),
+    ("/v1/inAppPurchases", "/v2/inAppPurchases"),
+    (
+        "/v1/apps/*/inAppPurchases",
+        "/v1/apps/{id}/inAppPurchasesV2",
+    ),
+];
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let mut segments = path.split('/');
+
+    pattern.split('/').all(|expected| match segments.next() {
+        Some(segment) => expected == "*" || expected == segment,
+        None => false,
+    })
+}
+
+/// Find the replacement of a known deprecated endpoint.
+pub fn known_replacement(path: &str) -> Option<(&'static str, &'static str)> {
+    KNOWN_DEPRECATIONS
+        .iter()
+        .find(|(pattern, _)| path_matches(pattern, path))
+        .copied()
+}
+
+/// The target of a `Link` header entry with a given relation type.
+fn link_with_rel(headers: &HeaderMap, rel: &str) -> Option<String> {
+    headers
+        .get_all(reqwest::header::LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';');
+            let target = parts.next()?.trim();
+            let target = target.strip_prefix('<')?.strip_suffix('>')?;
+
+            parts
+                .any(|param| {
+                    param
+                        .trim()
+                        .strip_prefix("rel=")
+                        .map(|value| value.trim_matches('"').split(' ').any(|x| x == rel))
+                        .unwrap_or_default()
+                })
+                .then(|| target.to_string())
+        })
+}
+
+/// A response from a deprecated endpoint.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deprecation {
+    /// HTTP method of the request.
+    pub method: String,
+
+    /// Path of the request.
+    pub path: String,
+
+    /// Value of the `Deprecation` header.
+    pub deprecation: Option<String>,
+
+    /// Value of the `Sunset` header: when the endpoint will be removed.
+    pub sunset: Option<String>,
+
+    /// Whether the endpoint has been removed (`410 Gone`).
+    pub gone: bool,
+
+    /// The API to use instead, if known.
+    pub replacement: Option<String>,
+}
+
+impl Deprecation {
+    /// Inspect a response for signs of deprecation.
+    pub fn detect(method: &Method, path: &str, status: u16, headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        let known = known_replacement(path);
+        let deprecation = header("deprecation");
+        let sunset = header("sunset");
+        let gone = status == 410;
+
+        if known.is_none() && deprecation.is_none() && sunset.is_none() && !gone {
+            return None;
+        }
+
+        Some(Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            deprecation,
+            sunset,
+            gone,
+            replacement: link_with_rel(headers, "successor-version")
+                .or_else(|| known.map(|(_, replacement)| replacement.to_string())),
+        })
+    }
+
+    /// A human readable description.
+    pub fn message(&self) -> String {
+        let mut message = if self.gone {
+            format!("{} {} has been removed by Apple", self.method, self.path)
+        } else {
+            format!("{} {} is deprecated by Apple", self.method, self.path)
+        };
+
+        if let (Some(sunset), false) = (&self.sunset, self.gone) {
+            message.push_str(&format!(" and will be removed after {}", sunset));
+        }
+        if let Some(replacement) = &self.replacement {
+            message.push_str(&format!("; use {} instead", replacement));
+        }
+
+        message
+    }
+
+    /// Key identifying the endpoint, so it is reported once.
+    fn key(&self) -> String {
+        let endpoint = known_replacement(&self.path)
+            .map(|(pattern, _)| pattern)
+            .unwrap_or(self.path.as_str());
+
+        format!("{} {}", self.method, endpoint)
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Report a response if it comes from a deprecated endpoint.
+    pub(crate) fn check_deprecation(
+        &self,
+        method: &Method,
+        path: &str,
+        status: u16,
+        headers: &HeaderMap,
+    ) {
+        let deprecation = match Deprecation::detect(method, path, status, headers) {
+            Some(deprecation) => deprecation,
+            None => return,
+        };
+
+        if !self
+            .deprecations_reported
+            .lock()
+            .unwrap()
+            .insert(deprecation.key())
+        {
+            return;
+        }
+
+        let message = deprecation.message();
+        warn!("{}", message);
+
+        self.notify(NotificationEvent::new(
+            NotificationKind::EndpointDeprecated,
+            &deprecation.path,
+            message,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            app_store_connect::{
+                notify::NotificationSink,
+                testing::{fixtures, MockResponse, MockServerBuilder},
+            },
+            AppleCodesignError,
+        },
+        std::sync::{Arc, Mutex},
+    };
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<NotificationEvent>>);
+
+    impl NotificationSink for Events {
+        fn notify(&self, event: &NotificationEvent) -> Result<(), AppleCodesignError> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn detect() {
+        let mut headers = HeaderMap::new();
+        assert!(Deprecation::detect(&Method::GET, "/v1/apps", 200, &headers).is_none());
+
+        let known = Deprecation::detect(&Method::GET, "/v1/apps/A1/prices", 200, &headers).unwrap();
+        assert_eq!(
+            known.replacement.as_deref(),
+            Some("the app price schedule (/v1/apps/{id}/appPriceSchedule)")
+        );
+        assert!(
+            Deprecation::detect(&Method::GET, "/v1/subscriptions/S1/prices", 200, &headers)
+                .is_none()
+        );
+
+        let gone = Deprecation::detect(&Method::GET, "/v1/widgets", 410, &headers).unwrap();
+        assert!(gone.gone);
+        assert_eq!(gone.message(), "GET /v1/widgets has been removed by Apple");
+
+        headers.insert("deprecation", "true".parse().unwrap());
+        headers.insert("sunset", "Sat, 01 Aug 2026 00:00:00 GMT".parse().unwrap());
+        headers.insert(
+            "link",
+            "<https://developer.apple.com/docs>; rel=\"deprecation\", </v2/widgets>; rel=\"successor-version\""
+                .parse()
+                .unwrap(),
+        );
+        let deprecated = Deprecation::detect(&Method::GET, "/v1/widgets", 200, &headers).unwrap();
+        assert_eq!(
+            deprecated.message(),
+            "GET /v1/widgets is deprecated by Apple and will be removed after \
+             Sat, 01 Aug 2026 00:00:00 GMT; use /v2/widgets instead"
+        );
+    }
+
+    #[test]
+    fn client_reports_once() -> Result<(), AppleCodesignError> {
+        let mut response = MockResponse::json(200, &fixtures::collection(vec![]));
+        response
+            .headers
+            .push(("Deprecation".into(), "@1767225600".into()));
+
+        let server = MockServerBuilder::default()
+            .route("GET", "/v1/users", response)
+            .start()?;
+        let events = Arc::new(Events::default());
+        let mut client = server.client()?;
+        client.set_notification_sink(Some(events.clone()));
+
+        client.list_users()?;
+        client.list_users()?;
+
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, NotificationKind::EndpointDeprecated);
+        assert_eq!(events[0].subject, "/v1/users");
+
+        Ok(())
+    }
+}
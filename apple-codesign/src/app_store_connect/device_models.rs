@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Marketing names of device model identifiers.
+//!
+//! The names bundled with this crate (see [DEVICE_MODELS]) go stale as Apple
+//! releases devices. [DeviceModels] extends them with a TOML data file mapping
+//! model identifiers to names, which takes precedence over the bundled names:
+//!
+//! ```toml
+//! "iPhone17,5" = "iPhone 16e"
+//! ```
+//!
+//! The data file is read from `asconnect/device-models.toml` in the user
+//! configuration directory by default (see [DeviceModels::default_path()]).
+
+pub use asconnect_types::device_models::*;
+
+use {
+    crate::{app_store_connect::certs_api::Device, AppleCodesignError},
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Marketing names of model identifiers.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceModels {
+    /// Names read from a data file.
+    names: BTreeMap<String, String>,
+}
+
+impl DeviceModels {
+    /// The default path of the data file, if the configuration directory is known.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("asconnect").join("device-models.toml"))
+    }
+
+    /// Extend the bundled names with a TOML data file.
+    pub fn from_toml(data: &str) -> Result<Self, AppleCodesignError> {
+        let names =
+            toml::from_str(data).map_err(|e| AppleCodesignError::DeviceModels(e.to_string()))?;
+
+        Ok(Self { names })
+    }
+
+    /// Extend the bundled names with the TOML data file at a path.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Read the data file at `path`, or at [Self::default_path()] if it exists.
+    ///
+    /// Only the bundled names are available if neither is present.
+    pub fn load(path: Option<&Path>) -> Result<Self, AppleCodesignError> {
+        match path {
+            Some(path) => Self::from_path(path),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => Self::from_path(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    /// Number of names read from the data file.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether no names were read from the data file.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The marketing name of a model identifier, if it is known.
+    pub fn marketing_name(&self, model: &str) -> Option<&str> {
+        self.names
+            .get(model)
+            .map(|name| name.as_str())
+            .or_else(|| marketing_name(model))
+    }
+
+    /// The marketing name of the model of a device, if it is known.
+    pub fn device_marketing_name(&self, device: &Device) -> Option<&str> {
+        device
+            .attributes
+            .model
+            .as_deref()
+            .and_then(|model| self.marketing_name(model))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn marketing_names() -> Result<(), AppleCodesignError> {
+        let bundled = DeviceModels::default();
+        assert_eq!(
+            bundled.marketing_name("iPhone15,3"),
+            Some("iPhone 14 Pro Max")
+        );
+        assert_eq!(bundled.marketing_name("iPhone99,1"), None);
+
+        let models = DeviceModels::from_toml(
+            r#"
+            "iPhone99,1" = "iPhone 99"
+            "iPhone15,3" = "Renamed"
+            "#,
+        )?;
+        assert_eq!(models.len(), 2);
+        assert_eq!(models.marketing_name("iPhone99,1"), Some("iPhone 99"));
+        assert_eq!(models.marketing_name("iPhone15,3"), Some("Renamed"));
+        assert_eq!(
+            models.marketing_name("iPad13,18"),
+            Some("iPad (10th generation)")
+        );
+
+        assert!(DeviceModels::from_toml("\"iPhone99,1\" = 1").is_err());
+
+        Ok(())
+    }
+}
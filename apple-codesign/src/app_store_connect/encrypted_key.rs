@@ -0,0 +1,290 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Passphrase encrypted [UnifiedApiKey] files.
+//!
+//! [UnifiedApiKey::write_json_file()] relies on file permissions to protect
+//! the private key, which doesn't help once the file is committed to a shared
+//! configuration store. [UnifiedApiKey::write_encrypted_json_file()] writes an
+//! [EncryptedApiKey] instead:
+//!
+//! ```json
+//! {
+//!   "format": "rcodesign-encrypted-api-key",
+//!   "version": 1,
+//!   "issuer_id": "...",
+//!   "key_id": "DEADBEEF42",
+//!   "kdf": {"algorithm": "pbkdf2-hmac-sha256", "iterations": 600000, "salt": "..."},
+//!   "cipher": {"algorithm": "aes-256-gcm", "nonce": "..."},
+//!   "ciphertext": "..."
+//! }
+//! ```
+//!
+//! The encryption key is derived from the password with PBKDF2-HMAC-SHA256.
+//! The unified key JSON is sealed with AES-256-GCM. The issuer and key IDs are
+//! stored in the clear so files can be told apart, but are authenticated along
+//! with the ciphertext. Binary values are base64 encoded.
+
+use {
+    crate::{
+        app_store_connect::{write_private_file, UnifiedApiKey},
+        AppleCodesignError,
+    },
+    ring::{
+        aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+        pbkdf2,
+        rand::{SecureRandom, SystemRandom},
+    },
+    serde::{Deserialize, Serialize},
+    std::{num::NonZeroU32, path::Path},
+    zeroize::Zeroizing,
+};
+
+/// Value of the `format` field of [EncryptedApiKey].
+pub const ENCRYPTED_API_KEY_FORMAT: &str = "rcodesign-encrypted-api-key";
+
+/// PBKDF2 iterations of newly encrypted keys.
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
+
+const SALT_LEN: usize = 16;
+
+/// Key derivation parameters.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub iterations: u32,
+    pub salt: String,
+}
+
+/// Cipher parameters.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CipherParams {
+    pub algorithm: String,
+    pub nonce: String,
+}
+
+/// A [UnifiedApiKey] encrypted with a password.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EncryptedApiKey {
+    pub format: String,
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer_id: Option<String>,
+    pub key_id: String,
+    pub kdf: KdfParams,
+    pub cipher: CipherParams,
+    pub ciphertext: String,
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, AppleCodesignError> {
+    base64::decode(value).map_err(|e| {
+        AppleCodesignError::AppStoreConnectApiKey(format!("invalid base64 in {}: {}", field, e))
+    })
+}
+
+fn derive_key(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> Result<LessSafeKey, AppleCodesignError> {
+    let iterations = NonZeroU32::new(iterations).ok_or_else(|| {
+        AppleCodesignError::AppStoreConnectApiKey("PBKDF2 iterations must not be 0".into())
+    })?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        password,
+        &mut *key,
+    );
+
+    Ok(LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, &*key).expect("key length should match AES-256"),
+    ))
+}
+
+impl EncryptedApiKey {
+    /// Whether JSON data looks like an encrypted key, rather than a plain [UnifiedApiKey].
+    pub fn is_encrypted_json(data: &[u8]) -> bool {
+        serde_json::from_slice::<serde_json::Value>(data)
+            .map(|value| value["format"] == ENCRYPTED_API_KEY_FORMAT)
+            .unwrap_or_default()
+    }
+
+    /// Authenticated data binding the cleartext fields to the ciphertext.
+    fn aad(&self) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.format,
+            self.version,
+            self.issuer_id.as_deref().unwrap_or_default(),
+            self.key_id
+        )
+    }
+
+    /// Encrypt a key with a password.
+    pub fn encrypt(key: &UnifiedApiKey, password: &[u8]) -> Result<Self, AppleCodesignError> {
+        Self::encrypt_with_iterations(key, password, PBKDF2_ITERATIONS)
+    }
+
+    fn encrypt_with_iterations(
+        key: &UnifiedApiKey,
+        password: &[u8],
+        iterations: u32,
+    ) -> Result<Self, AppleCodesignError> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt)
+            .and_then(|_| rng.fill(&mut nonce))
+            .map_err(|_| {
+                AppleCodesignError::AppStoreConnectApiKey("failed to generate random data".into())
+            })?;
+
+        let mut encrypted = Self {
+            format: ENCRYPTED_API_KEY_FORMAT.to_string(),
+            version: 1,
+            issuer_id: key.issuer_id.clone(),
+            key_id: key.key_id.clone(),
+            kdf: KdfParams {
+                algorithm: "pbkdf2-hmac-sha256".into(),
+                iterations,
+                salt: base64::encode(salt),
+            },
+            cipher: CipherParams {
+                algorithm: "aes-256-gcm".into(),
+                nonce: base64::encode(nonce),
+            },
+            ciphertext: String::new(),
+        };
+
+        let mut data = Zeroizing::new(key.to_json_string()?.into_bytes());
+        derive_key(password, &salt, iterations)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(encrypted.aad()),
+                &mut *data,
+            )
+            .map_err(|_| AppleCodesignError::AppStoreConnectApiKey("encryption failed".into()))?;
+        encrypted.ciphertext = base64::encode(&*data);
+
+        Ok(encrypted)
+    }
+
+    /// Decrypt the key with a password.
+    pub fn decrypt(&self, password: &[u8]) -> Result<UnifiedApiKey, AppleCodesignError> {
+        if self.format != ENCRYPTED_API_KEY_FORMAT || self.version != 1 {
+            return Err(AppleCodesignError::AppStoreConnectApiKey(format!(
+                "unsupported encrypted API Key format {} version {}",
+                self.format, self.version
+            )));
+        }
+        if self.kdf.algorithm != "pbkdf2-hmac-sha256" || self.cipher.algorithm != "aes-256-gcm" {
+            return Err(AppleCodesignError::AppStoreConnectApiKey(format!(
+                "unsupported encryption {} with {}",
+                self.cipher.algorithm, self.kdf.algorithm
+            )));
+        }
+
+        let salt = decode("kdf.salt", &self.kdf.salt)?;
+        let nonce = Nonce::try_assume_unique_for_key(&decode("cipher.nonce", &self.cipher.nonce)?)
+            .map_err(|_| AppleCodesignError::AppStoreConnectApiKey("invalid nonce".into()))?;
+        let mut data = Zeroizing::new(decode("ciphertext", &self.ciphertext)?);
+
+        let plaintext = derive_key(password, &salt, self.kdf.iterations)?
+            .open_in_place(nonce, Aad::from(self.aad()), &mut *data)
+            .map_err(|_| {
+                AppleCodesignError::AppStoreConnectApiKey(
+                    "unable to decrypt API Key: wrong password or corrupted file".into(),
+                )
+            })?;
+
+        UnifiedApiKey::from_json(plaintext)
+    }
+}
+
+impl UnifiedApiKey {
+    /// Serialize this instance to JSON encrypted with a password.
+    ///
+    /// See [EncryptedApiKey].
+    pub fn to_encrypted_json_string(&self, password: &[u8]) -> Result<String, AppleCodesignError> {
+        Ok(serde_json::to_string_pretty(&EncryptedApiKey::encrypt(
+            self, password,
+        )?)?)
+    }
+
+    /// Write this instance to a JSON file encrypted with a password.
+    ///
+    /// As with [Self::write_json_file()], the file has limited read permissions
+    /// where this is implemented.
+    pub fn write_encrypted_json_file(
+        &self,
+        path: impl AsRef<Path>,
+        password: &[u8],
+    ) -> Result<(), AppleCodesignError> {
+        write_private_file(
+            path.as_ref(),
+            self.to_encrypted_json_string(password)?.as_bytes(),
+        )
+    }
+
+    /// Construct an instance from encrypted JSON.
+    pub fn from_encrypted_json(
+        data: impl AsRef<[u8]>,
+        password: &[u8],
+    ) -> Result<Self, AppleCodesignError> {
+        serde_json::from_slice::<EncryptedApiKey>(data.as_ref())?.decrypt(password)
+    }
+
+    /// Construct an instance from an encrypted JSON file.
+    pub fn from_encrypted_json_path(
+        path: impl AsRef<Path>,
+        password: &[u8],
+    ) -> Result<Self, AppleCodesignError> {
+        Self::from_encrypted_json(std::fs::read(path.as_ref())?, password)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key() -> UnifiedApiKey {
+        UnifiedApiKey {
+            issuer_id: Some("ISSUER".into()),
+            key_id: "KEY".into(),
+            private_key: base64::encode(b"not really a key"),
+        }
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), AppleCodesignError> {
+        // Few iterations keep the test fast.
+        let json = serde_json::to_string(&EncryptedApiKey::encrypt_with_iterations(
+            &key(),
+            b"hunter2",
+            1000,
+        )?)?;
+
+        assert!(EncryptedApiKey::is_encrypted_json(json.as_bytes()));
+        assert!(!EncryptedApiKey::is_encrypted_json(
+            key().to_json_string()?.as_bytes()
+        ));
+        assert!(!json.contains(&key().private_key));
+
+        let decrypted = UnifiedApiKey::from_encrypted_json(&json, b"hunter2")?;
+        assert_eq!(decrypted.to_json_string()?, key().to_json_string()?);
+
+        assert!(UnifiedApiKey::from_encrypted_json(&json, b"hunter3").is_err());
+
+        // The cleartext key ID is authenticated.
+        let mut tampered = serde_json::from_str::<EncryptedApiKey>(&json)?;
+        tampered.key_id = "OTHER".into();
+        assert!(tampered.decrypt(b"hunter2").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Servers of the API.
+//!
+//! Apple Developer Enterprise Program accounts don't have access to App Store
+//! Connect. Instead, the Enterprise Program API at [ENTERPRISE_API_URL]
+//! serves the provisioning resources (bundle IDs, certificates, devices, and
+//! profiles) with the same resource model and API keys.
+//! [AppStoreConnectClient::set_api_environment()] points a client at it, after
+//! which the certificate, device, and profile operations work as usual.
+//!
+//! Requests for resource types the Enterprise Program API doesn't serve are
+//! refused without contacting the server.
+
+use crate::app_store_connect::{api_versions::resource_type, APP_STORE_CONNECT_API_URL};
+
+/// Base URL of the Apple Developer Enterprise Program API.
+pub const ENTERPRISE_API_URL: &str = "https://api.enterprise.developer.apple.com";
+
+/// Resource types served by the Enterprise Program API.
+pub const ENTERPRISE_RESOURCE_TYPES: &[&str] = &[
+    "bundleIdCapabilities",
+    "bundleIds",
+    "certificates",
+    "devices",
+    "profiles",
+];
+
+/// The server a client talks to.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ApiEnvironment {
+    /// The App Store Connect API.
+    #[default]
+    AppStoreConnect,
+
+    /// The Apple Developer Enterprise Program API.
+    Enterprise,
+}
+
+impl ApiEnvironment {
+    /// Human readable name of the API.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AppStoreConnect => "App Store Connect API",
+            Self::Enterprise => "Enterprise Program API",
+        }
+    }
+
+    /// The base URL of the API.
+    pub fn api_url(&self) -> &'static str {
+        match self {
+            Self::AppStoreConnect => APP_STORE_CONNECT_API_URL,
+            Self::Enterprise => ENTERPRISE_API_URL,
+        }
+    }
+
+    /// Whether a request path is served in this environment.
+    pub fn serves_path(&self, path: &str) -> bool {
+        match self {
+            Self::AppStoreConnect => true,
+            Self::Enterprise => resource_type(path)
+                .map(|resource| ENTERPRISE_RESOURCE_TYPES.contains(&resource))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{app_store_connect::testing::MockServerBuilder, AppleCodesignError},
+        reqwest::Method,
+        serde_json::json,
+    };
+
+    #[test]
+    fn enterprise() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/devices", 200, json!({"data": []}))
+            .start()?;
+        let mut client = server.client()?;
+        client.set_api_environment(ApiEnvironment::Enterprise);
+        assert_eq!(client.api_url(), ENTERPRISE_API_URL);
+
+        client.set_api_url(server.url());
+        client.api_get::<serde_json::Value>("/v1/devices", &[])?;
+
+        assert!(matches!(
+            client.api_request(Method::GET, "/v1/apps"),
+            Err(AppleCodesignError::AppStoreConnectUnsupportedResource(..))
+        ));
+        assert_eq!(server.requests().len(), 1);
+
+        Ok(())
+    }
+}
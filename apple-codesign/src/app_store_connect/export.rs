@@ -0,0 +1,172 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Streaming export of App Store Connect resources.
+//!
+//! Large accounts can have tens of thousands of devices or builds. Rather than
+//! buffering an entire collection in memory, [export_ndjson()] writes each
+//! resource as a line of JSON (NDJSON) as soon as its page is fetched.
+//!
+//! Exports are resumable. After each page is written, the URL of the next page
+//! is persisted to a cursor file. If an export is interrupted, running it again
+//! with the same cursor file continues from the page after the last one
+//! written. The cursor file is removed once the export completes. Resources
+//! from a page that was only partially written when the export was interrupted
+//! are written again, so consumers should tolerate duplicate lines.
+//!
+//! Cursor files record the [CacheScope] of the API key they were written with.
+//! A cursor written with another API key is ignored.
+
+use {
+    crate::{
+        app_store_connect::{cache_scope::CacheScope, AppStoreConnectClient},
+        AppleCodesignError,
+    },
+    log::warn,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::{io::Write, path::Path, str::FromStr},
+};
+
+/// Content of a cursor file.
+#[derive(Deserialize, Serialize)]
+struct ExportCursor {
+    /// [CacheScope] of the API key the cursor was written with.
+    scope: String,
+
+    /// URL of the next page to export.
+    next: String,
+}
+
+/// A collection that can be exported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportResource {
+    Apps,
+    Builds,
+    BundleIds,
+    Certificates,
+    Devices,
+    Profiles,
+}
+
+impl ExportResource {
+    /// All exportable collections.
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Apps,
+            Self::Builds,
+            Self::BundleIds,
+            Self::Certificates,
+            Self::Devices,
+            Self::Profiles,
+        ]
+    }
+
+    /// The name of the collection as used on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Apps => "apps",
+            Self::Builds => "builds",
+            Self::BundleIds => "bundle-ids",
+            Self::Certificates => "certificates",
+            Self::Devices => "devices",
+            Self::Profiles => "profiles",
+        }
+    }
+
+    /// The API path of the collection.
+    pub fn api_path(&self) -> &'static str {
+        match self {
+            Self::Apps => "/v1/apps",
+            Self::Builds => "/v1/builds",
+            Self::BundleIds => "/v1/bundleIds",
+            Self::Certificates => "/v1/certificates",
+            Self::Devices => "/v1/devices",
+            Self::Profiles => "/v1/profiles",
+        }
+    }
+}
+
+impl FromStr for ExportResource {
+    type Err = AppleCodesignError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .iter()
+            .find(|r| r.as_str() == s)
+            .copied()
+            .ok_or(AppleCodesignError::CliBadArgument)
+    }
+}
+
+/// Export a collection as NDJSON.
+///
+/// Each resource is written to `writer` as a single line of JSON. If `cursor_path`
+/// is defined, the export resumes from the page recorded in it (if it exists)
+/// and records progress to it after each page. Returns the number of resources
+/// written.
+pub fn export_ndjson(
+    client: &AppStoreConnectClient,
+    resource: ExportResource,
+    writer: &mut impl Write,
+    cursor_path: Option<&Path>,
+) -> Result<usize, AppleCodesignError> {
+    let scope = client.cache_scope();
+
+    let resume_from = match cursor_path {
+        Some(path) if path.exists() => {
+            match serde_json::from_slice::<ExportCursor>(&std::fs::read(path)?) {
+                Ok(cursor) if cursor.scope == scope.as_str() => {
+                    warn!("resuming {} export from {}", resource.as_str(), cursor.next);
+                    Some(cursor.next)
+                }
+                _ => {
+                    warn!(
+                        "ignoring cursor {} written with another API key or version",
+                        path.display()
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let mut count = 0;
+
+    client.api_get_pages::<Value>(
+        resource.api_path(),
+        &[("limit", "200".to_string())],
+        resume_from,
+        |page, next| {
+            for r in page {
+                serde_json::to_writer(&mut *writer, &r)?;
+                writer.write_all(b"\n")?;
+                count += 1;
+            }
+
+            writer.flush()?;
+
+            if let (Some(path), Some(next)) = (cursor_path, next) {
+                let cursor = ExportCursor {
+                    scope: scope.to_string(),
+                    next: next.to_string(),
+                };
+                std::fs::write(path, serde_json::to_vec(&cursor)?)?;
+            }
+
+            warn!("exported {} {}", count, resource.as_str());
+
+            Ok(())
+        },
+    )?;
+
+    if let Some(path) = cursor_path {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    Ok(count)
+}
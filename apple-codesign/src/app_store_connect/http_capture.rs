@@ -0,0 +1,293 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Capture of HTTP exchanges for support tickets.
+//!
+//! Apple Developer support asks for the requests and responses of a failed
+//! operation, including the request IDs Apple assigned to them. [HttpCapture]
+//! records the exchanges of an [AppStoreConnectClient](super::AppStoreConnectClient)
+//! and writes them to a zip archive that can be attached to a ticket.
+//!
+//! Captured data is sanitized: the `Authorization` and cookie headers are
+//! dropped and everything else is passed through [redact()]. Bodies of
+//! successful responses are not captured, as they are streamed to the caller.
+
+use {
+    crate::{redact::redact, AppleCodesignError},
+    chrono::Utc,
+    reqwest::{blocking::Request, header::HeaderMap},
+    serde::Serialize,
+    std::{
+        io::{Seek, Write},
+        path::Path,
+        sync::Mutex,
+        time::Instant,
+    },
+};
+
+/// Bodies larger than this are replaced by their size.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Headers never captured.
+const DROPPED_HEADERS: &[&str] = &["authorization", "cookie", "set-cookie"];
+
+/// Response headers carrying the ID Apple assigned to a request.
+const REQUEST_ID_HEADERS: &[&str] = &["x-request-id", "x-apple-request-uuid"];
+
+/// A captured request and its response.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedExchange {
+    /// When the request was sent, in RFC 3339 format.
+    pub started_at: String,
+
+    /// Time until the response headers were received.
+    pub duration_ms: u128,
+
+    pub method: String,
+
+    pub url: String,
+
+    pub request_headers: Vec<(String, String)>,
+
+    pub request_body: Option<String>,
+
+    pub status: u16,
+
+    pub response_headers: Vec<(String, String)>,
+
+    /// Body of the response. Only captured for unsuccessful responses.
+    pub response_body: Option<String>,
+
+    /// The ID Apple assigned to the request, if the response had one.
+    pub request_id: Option<String>,
+}
+
+impl CapturedExchange {
+    /// Whether the response indicates failure.
+    pub fn is_failure(&self) -> bool {
+        !(200..300).contains(&self.status)
+    }
+}
+
+/// Sanitize headers for capture.
+pub(crate) fn capture_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| !DROPPED_HEADERS.contains(&name.as_str()))
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                redact(&String::from_utf8_lossy(value.as_bytes())).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Sanitize a body for capture.
+pub(crate) fn capture_body(body: &[u8]) -> Option<String> {
+    if body.is_empty() {
+        None
+    } else if body.len() > MAX_BODY_SIZE {
+        Some(format!("<{} bytes>", body.len()))
+    } else if let Ok(text) = std::str::from_utf8(body) {
+        Some(redact(text).to_string())
+    } else {
+        Some(format!("<{} bytes of binary data>", body.len()))
+    }
+}
+
+/// Find the ID Apple assigned to a request in response headers.
+pub(crate) fn request_id(headers: &HeaderMap) -> Option<String> {
+    REQUEST_ID_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name)?.to_str().ok())
+        .map(|id| id.to_string())
+}
+
+/// A request whose response is yet to be captured.
+pub(crate) struct PendingExchange {
+    started_at: String,
+    started: Instant,
+    method: String,
+    url: String,
+    request_headers: Vec<(String, String)>,
+    request_body: Option<String>,
+}
+
+impl PendingExchange {
+    /// Capture a request about to be sent.
+    pub(crate) fn new(request: &Request) -> Self {
+        Self {
+            started_at: Utc::now().to_rfc3339(),
+            started: Instant::now(),
+            method: request.method().to_string(),
+            url: redact(request.url().as_str()).to_string(),
+            request_headers: capture_headers(request.headers()),
+            request_body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .and_then(capture_body),
+        }
+    }
+
+    /// Complete the exchange with the response.
+    pub(crate) fn finish(
+        self,
+        status: u16,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+    ) -> CapturedExchange {
+        CapturedExchange {
+            started_at: self.started_at,
+            duration_ms: self.started.elapsed().as_millis(),
+            method: self.method,
+            url: self.url,
+            request_headers: self.request_headers,
+            request_body: self.request_body,
+            status,
+            response_headers: capture_headers(headers),
+            response_body: body.and_then(capture_body),
+            request_id: request_id(headers),
+        }
+    }
+}
+
+/// Records HTTP exchanges.
+#[derive(Debug, Default)]
+pub struct HttpCapture {
+    exchanges: Mutex<Vec<CapturedExchange>>,
+}
+
+impl HttpCapture {
+    /// Record an exchange.
+    pub fn record(&self, exchange: CapturedExchange) {
+        self.exchanges.lock().unwrap().push(exchange);
+    }
+
+    /// The exchanges recorded so far.
+    pub fn exchanges(&self) -> Vec<CapturedExchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+
+    /// Write the recorded exchanges as a zip archive.
+    ///
+    /// The archive has a `README.txt` listing all exchanges and the request
+    /// IDs of failed ones, plus an `exchanges/NNN.json` file per exchange.
+    pub fn write_archive<W: Write + Seek>(&self, writer: W) -> Result<W, AppleCodesignError> {
+        let exchanges = self.exchanges();
+        let mut zf = zip::ZipWriter::new(writer);
+        let options = zip::write::FileOptions::default().unix_permissions(0o644);
+
+        let mut readme = format!(
+            "HTTP exchanges captured by rcodesign {}\n\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        for (i, exchange) in exchanges.iter().enumerate() {
+            readme.push_str(&format!(
+                "{:03} {} {} {} -> {}{}\n",
+                i,
+                exchange.started_at,
+                exchange.method,
+                exchange.url,
+                exchange.status,
+                match (&exchange.request_id, exchange.is_failure()) {
+                    (Some(id), true) => format!(" (failed; request ID {})", id),
+                    (None, true) => " (failed)".to_string(),
+                    _ => "".to_string(),
+                }
+            ));
+        }
+
+        zf.start_file("README.txt", options)?;
+        zf.write_all(readme.as_bytes())?;
+
+        for (i, exchange) in exchanges.iter().enumerate() {
+            zf.start_file(format!("exchanges/{:03}.json", i), options)?;
+            zf.write_all(serde_json::to_string_pretty(exchange)?.as_bytes())?;
+        }
+
+        Ok(zf.finish()?)
+    }
+
+    /// Write the recorded exchanges to a zip file.
+    pub fn write_archive_file(&self, path: &Path) -> Result<(), AppleCodesignError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        self.write_archive(std::fs::File::create(path)?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        std::{io::Read, sync::Arc},
+    };
+
+    #[test]
+    fn capture_failed_request() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![]))
+            .json(
+                "GET",
+                "/v1/apps/A1",
+                403,
+                fixtures::error(403, "FORBIDDEN_ERROR", "not allowed"),
+            )
+            .start()?;
+
+        let capture = Arc::new(HttpCapture::default());
+        let mut client = server.client()?;
+        client.set_http_capture(Some(capture.clone()));
+
+        client.list_apps()?;
+        assert!(client.get_app("A1").is_err());
+
+        let exchanges = capture.exchanges();
+        assert_eq!(exchanges.len(), 2);
+        assert!(!exchanges[0].is_failure());
+        assert_eq!(exchanges[0].response_body, None);
+        assert_eq!(exchanges[1].status, 403);
+        assert!(exchanges[1]
+            .response_body
+            .as_deref()
+            .unwrap_or_default()
+            .contains("FORBIDDEN_ERROR"));
+        assert!(exchanges
+            .iter()
+            .flat_map(|e| e.request_headers.iter())
+            .all(|(name, _)| name != "authorization"));
+
+        let data = capture
+            .write_archive(std::io::Cursor::new(vec![]))?
+            .into_inner();
+        let mut za = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+        assert_eq!(za.len(), 3);
+
+        let mut readme = String::new();
+        za.by_name("README.txt")?.read_to_string(&mut readme)?;
+        assert!(readme.contains("/v1/apps/A1 -> 403 (failed"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sanitize_body() {
+        assert_eq!(capture_body(b""), None);
+        assert_eq!(
+            capture_body(br#"{"password": "hunter2"}"#).as_deref(),
+            Some(r#"{"password": "<redacted>"}"#)
+        );
+        assert_eq!(
+            capture_body(&[0xff, 0xfe]).as_deref(),
+            Some("<2 bytes of binary data>")
+        );
+    }
+}
@@ -0,0 +1,402 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Create operations that take effect once across retries.
+//!
+//! The App Store Connect API has no idempotency keys. Retrying a `POST` that
+//! timed out, or rerunning a pipeline step that died after its request was
+//! sent, creates the resource again. For builds this wastes a version number
+//! and for review submissions it leaves stray drafts.
+//!
+//! An [IdempotencyJournal] persists a marker for each create operation,
+//! keyed by a digest of the operation and the attributes of the resource:
+//!
+//! 1. If the journal records the resource the operation created, it is reused
+//!    without contacting the server.
+//! 2. Otherwise the server is searched for a resource with matching attributes
+//!    (e.g. a build upload of the same version). A match is recorded and
+//!    reused.
+//! 3. Otherwise a marker is written before the resource is created, and the ID
+//!    of the created resource is recorded after.
+//!
+//! The journal is a JSON file. Like [PipelineState](super::pipeline::PipelineState),
+//! it is tied to the [CacheScope] of the API key and discarded if used with
+//! another key.
+
+use {
+    crate::{
+        app_store_connect::{cache_scope::CacheScope, AppStoreConnectClient},
+        AppleCodesignError,
+    },
+    chrono::Utc,
+    log::warn,
+    serde::{Deserialize, Serialize},
+    sha2::Digest,
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+};
+
+#[cfg(feature = "asc-builds")]
+use crate::app_store_connect::build_upload_api::{
+    BuildUploadAttributes, BuildUploadState, IpaInfo, Platform,
+};
+#[cfg(feature = "asc-testflight")]
+use crate::app_store_connect::testflight_api::BetaAppReviewSubmissionAttributes;
+
+/// A create operation recorded in an [IdempotencyJournal].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Name of the operation. e.g. `buildUploads`.
+    pub operation: String,
+
+    /// RFC 3339 time the operation was first attempted.
+    pub started: String,
+
+    /// ID of the resource the operation created or found.
+    ///
+    /// [None] if the operation was interrupted before it was known.
+    pub resource_id: Option<String>,
+}
+
+/// How [IdempotencyJournal::create_once()] obtained a resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CreateOutcome {
+    /// The journal recorded the resource.
+    Recorded,
+
+    /// A resource with matching attributes was found on the server.
+    Found,
+
+    /// The resource was created.
+    Created,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct JournalFile {
+    scope: String,
+    entries: BTreeMap<String, JournalEntry>,
+}
+
+/// A persisted record of create operations.
+///
+/// See the [module documentation](self).
+pub struct IdempotencyJournal {
+    path: PathBuf,
+    file: JournalFile,
+}
+
+impl IdempotencyJournal {
+    /// Open the journal at a path for an API key.
+    ///
+    /// The file is created when the first operation is recorded.
+    pub fn open(path: impl AsRef<Path>, scope: &CacheScope) -> Result<Self, AppleCodesignError> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut file = if path.exists() {
+            serde_json::from_slice::<JournalFile>(&std::fs::read(&path)?)?
+        } else {
+            JournalFile::default()
+        };
+
+        if file.scope != scope.as_str() {
+            if !file.entries.is_empty() {
+                warn!(
+                    "idempotency journal {} was written with another API key; discarding it",
+                    path.display()
+                );
+            }
+
+            file = JournalFile {
+                scope: scope.to_string(),
+                entries: BTreeMap::new(),
+            };
+        }
+
+        Ok(Self { path, file })
+    }
+
+    /// Path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The key identifying an operation creating a resource with attributes.
+    pub fn key(operation: &str, attributes: &impl Serialize) -> Result<String, AppleCodesignError> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(operation.as_bytes());
+        hasher.update([0]);
+        hasher.update(serde_json::to_vec(&serde_json::to_value(attributes)?)?);
+
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// The entry recorded for a key, if any.
+    pub fn entry(&self, key: &str) -> Option<&JournalEntry> {
+        self.file.entries.get(key)
+    }
+
+    fn record(
+        &mut self,
+        key: &str,
+        operation: &str,
+        resource_id: Option<String>,
+    ) -> Result<(), AppleCodesignError> {
+        let started = self
+            .file
+            .entries
+            .get(key)
+            .map(|entry| entry.started.clone())
+            .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+        self.file.entries.insert(
+            key.to_string(),
+            JournalEntry {
+                operation: operation.to_string(),
+                started,
+                resource_id,
+            },
+        );
+
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&self.file)?)?;
+
+        Ok(())
+    }
+
+    /// Create a resource unless the journal or the server has it already.
+    ///
+    /// `find` searches the server for a resource with `attributes` and
+    /// `create` creates one. Both return the ID of the resource.
+    pub fn create_once(
+        &mut self,
+        operation: &str,
+        attributes: &impl Serialize,
+        find: impl FnOnce() -> Result<Option<String>, AppleCodesignError>,
+        create: impl FnOnce() -> Result<String, AppleCodesignError>,
+    ) -> Result<(String, CreateOutcome), AppleCodesignError> {
+        let key = Self::key(operation, attributes)?;
+
+        match self.entry(&key) {
+            Some(JournalEntry {
+                resource_id: Some(id),
+                ..
+            }) => {
+                warn!("{} {} was created by a previous attempt", operation, id);
+                return Ok((id.clone(), CreateOutcome::Recorded));
+            }
+            Some(entry) => {
+                warn!(
+                    "a previous attempt to create {} started at {} was interrupted",
+                    operation, entry.started
+                );
+            }
+            None => {}
+        }
+
+        if let Some(id) = find()? {
+            warn!("reusing existing {} {}", operation, id);
+            self.record(&key, operation, Some(id.clone()))?;
+            return Ok((id, CreateOutcome::Found));
+        }
+
+        self.record(&key, operation, None)?;
+        let id = create()?;
+        self.record(&key, operation, Some(id.clone()))?;
+
+        Ok((id, CreateOutcome::Created))
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Upload an `.ipa` file unless a build upload of its version exists.
+    ///
+    /// Build uploads of the app with the version and platform of the `.ipa`
+    /// that didn't fail and aren't awaiting their file are reused. Returns the
+    /// ID of the build upload.
+    #[cfg(feature = "asc-builds")]
+    pub fn upload_ipa_once(
+        &self,
+        journal: &mut IdempotencyJournal,
+        app_id: &str,
+        path: &Path,
+        platform: Platform,
+    ) -> Result<(String, CreateOutcome), AppleCodesignError> {
+        let info = IpaInfo::from_ipa_data(&std::fs::read(path)?)?;
+
+        journal.create_once(
+            "buildUploads",
+            &(app_id, platform, &info.short_version, &info.version),
+            || {
+                Ok(self
+                    .api_get_all::<BuildUploadAttributes>(
+                        &format!("/v1/apps/{}/buildUploads", app_id),
+                        &[("filter[cfBundleVersion]", info.version.clone())],
+                    )?
+                    .into_iter()
+                    .find(|upload| {
+                        upload.attributes.cf_bundle_short_version_string.as_ref()
+                            == Some(&info.short_version)
+                            && upload.attributes.cf_bundle_version.as_ref() == Some(&info.version)
+                            && upload.attributes.platform == Some(platform)
+                            && !matches!(
+                                upload.attributes.state,
+                                Some(BuildUploadState::Failed)
+                                    | Some(BuildUploadState::AwaitingUpload)
+                            )
+                    })
+                    .map(|upload| upload.id))
+            },
+            || Ok(self.upload_ipa(app_id, path, platform)?.id),
+        )
+    }
+
+    /// Create a review submission unless the app has an open one.
+    ///
+    /// Returns the ID of the review submission.
+    #[cfg(feature = "asc-testflight")]
+    pub fn create_review_submission_once(
+        &self,
+        journal: &mut IdempotencyJournal,
+        app_id: &str,
+        platform: Platform,
+    ) -> Result<(String, CreateOutcome), AppleCodesignError> {
+        journal.create_once(
+            "reviewSubmissions",
+            &(app_id, platform),
+            || {
+                Ok(self
+                    .find_open_review_submission(app_id, platform)?
+                    .map(|s| s.id))
+            },
+            || Ok(self.create_review_submission(app_id, platform)?.id),
+        )
+    }
+
+    /// Submit a build for beta app review unless it was submitted.
+    ///
+    /// Returns the ID of the beta app review submission.
+    #[cfg(feature = "asc-testflight")]
+    pub fn submit_build_for_beta_review_once(
+        &self,
+        journal: &mut IdempotencyJournal,
+        build_id: &str,
+    ) -> Result<(String, CreateOutcome), AppleCodesignError> {
+        journal.create_once(
+            "betaAppReviewSubmissions",
+            &build_id,
+            || {
+                Ok(self
+                    .api_get_all::<BetaAppReviewSubmissionAttributes>(
+                        "/v1/betaAppReviewSubmissions",
+                        &[("filter[build]", build_id.to_string())],
+                    )?
+                    .into_iter()
+                    .next()
+                    .map(|s| s.id))
+            },
+            || Ok(self.submit_build_for_beta_review(build_id)?.id),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::json,
+    };
+
+    #[test]
+    fn create_once() -> Result<(), AppleCodesignError> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("journal.json");
+        let scope = CacheScope::new("issuer", "KEY");
+
+        let mut journal = IdempotencyJournal::open(&path, &scope)?;
+        let (id, outcome) = journal.create_once(
+            "things",
+            &json!({"name": "a"}),
+            || Ok(None),
+            || Ok("T1".into()),
+        )?;
+        assert_eq!((id.as_str(), outcome), ("T1", CreateOutcome::Created));
+
+        // The journal is persisted and consulted before the server.
+        let mut journal = IdempotencyJournal::open(&path, &scope)?;
+        let (id, outcome) = journal.create_once(
+            "things",
+            &json!({"name": "a"}),
+            || panic!("server should not be searched"),
+            || panic!("resource should not be created"),
+        )?;
+        assert_eq!((id.as_str(), outcome), ("T1", CreateOutcome::Recorded));
+
+        // A failed create leaves a marker; the retry finds the resource.
+        assert!(journal
+            .create_once(
+                "things",
+                &json!({"name": "b"}),
+                || Ok(None),
+                || { Err(AppleCodesignError::NotarizeServerError) }
+            )
+            .is_err());
+        let key = IdempotencyJournal::key("things", &json!({"name": "b"}))?;
+        assert_eq!(
+            journal.entry(&key).map(|e| e.resource_id.clone()),
+            Some(None)
+        );
+
+        let (id, outcome) = journal.create_once(
+            "things",
+            &json!({"name": "b"}),
+            || Ok(Some("T2".into())),
+            || panic!("resource should not be created"),
+        )?;
+        assert_eq!((id.as_str(), outcome), ("T2", CreateOutcome::Found));
+
+        // Another API key doesn't see the entries.
+        let journal = IdempotencyJournal::open(&path, &CacheScope::new("issuer", "OTHER"))?;
+        assert!(journal.entry(&key).is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "asc-testflight")]
+    #[test]
+    fn beta_review_submitted_once() -> Result<(), AppleCodesignError> {
+        let dir = tempfile::tempdir()?;
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/betaAppReviewSubmissions",
+                200,
+                fixtures::collection(vec![json!({
+                    "type": "betaAppReviewSubmissions",
+                    "id": "S1",
+                    "attributes": {"betaReviewState": "WAITING_FOR_REVIEW"}
+                })]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let mut journal =
+            IdempotencyJournal::open(dir.path().join("journal.json"), &client.cache_scope())?;
+        let (id, outcome) = client.submit_build_for_beta_review_once(&mut journal, "B1")?;
+        assert_eq!((id.as_str(), outcome), ("S1", CreateOutcome::Found));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "GET");
+
+        Ok(())
+    }
+}
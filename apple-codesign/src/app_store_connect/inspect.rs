@@ -0,0 +1,668 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Read-only reports on how an artifact is signed.
+//!
+//! Answering "why won't this install" or "is this release notarized" means
+//! looking at the code signature, the embedded provisioning profile, the
+//! notarization ticket, and the account, each with a different tool.
+//! [ArtifactInspection::from_path()] gathers the signing details of an `.ipa`,
+//! app bundle, `.pkg`, `.dmg`, or Mach-O binary into one report:
+//!
+//! * The identifier, team ID, signing certificate, hardened runtime flag,
+//!   and entitlements of the code signature. For bundles and `.ipa` files,
+//!   this is the signature of the main executable.
+//! * A summary of the embedded provisioning profile.
+//! * Whether a notarization ticket is stapled and, when given a [Stapler],
+//!   whether Apple publishes one.
+//!
+//! [AppStoreConnectClient::match_artifact_inspection()] then finds the bundle
+//! ID, certificate, and profile of the account matching the artifact. Nothing
+//! is modified.
+
+use {
+    crate::{
+        app_store_connect::{
+            certs_api::{Certificate, Profile},
+            ipa_preflight::ProvisioningProfile,
+            AppStoreConnectClient,
+        },
+        certificate::AppleCertificate,
+        code_directory::CodeSignatureFlags,
+        dmg::DmgReader,
+        embedded_signature::{CodeSigningSlot, EmbeddedSignature},
+        macho::MachFile,
+        reader::PathType,
+        stapling::Stapler,
+        AppleCodesignError,
+    },
+    apple_bundles::DirectoryBundle,
+    apple_xar::reader::XarReader,
+    plist::Dictionary,
+    serde::Serialize,
+    std::{
+        fs::File,
+        io::{Read, Seek, SeekFrom},
+        path::{Path, PathBuf},
+    },
+    x509_certificate::CapturedX509Certificate,
+};
+
+/// Magic of the trailer stapled to XAR archives. See [crate::stapling].
+const XAR_TICKET_TRAILER_MAGIC: &[u8; 4] = b"t8lr";
+
+/// Kind of an inspected artifact.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    Ipa,
+    Bundle,
+    Pkg,
+    Dmg,
+    MachO,
+}
+
+/// Details of a code signature.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningSummary {
+    /// Identifier in the code directory.
+    pub identifier: Option<String>,
+
+    /// Team ID in the code directory or, failing that, the signing certificate.
+    pub team_id: Option<String>,
+
+    /// Subject of the signing certificate. [None] for ad-hoc signatures.
+    pub certificate: Option<String>,
+
+    /// Hex encoded SHA-256 fingerprint of the signing certificate.
+    pub certificate_sha256: Option<String>,
+
+    /// Whether the hardened runtime is enabled.
+    pub hardened_runtime: bool,
+
+    /// Flags of the code directory.
+    pub flags: Option<String>,
+
+    /// Entitlements the code is signed with.
+    pub entitlements: Option<Dictionary>,
+}
+
+/// Summary of an embedded provisioning profile.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSummary {
+    pub name: String,
+    pub uuid: String,
+    pub team_identifiers: Vec<String>,
+    pub expiration_date: String,
+
+    /// Number of devices the profile is restricted to.
+    pub provisioned_devices: usize,
+    pub provisions_all_devices: bool,
+}
+
+impl From<&ProvisioningProfile> for ProfileSummary {
+    fn from(profile: &ProvisioningProfile) -> Self {
+        Self {
+            name: profile.name.clone(),
+            uuid: profile.uuid.clone(),
+            team_identifiers: profile.team_identifiers.clone(),
+            expiration_date: profile.expiration_date.to_rfc3339(),
+            provisioned_devices: profile.provisioned_devices.len(),
+            provisions_all_devices: profile.provisions_all_devices,
+        }
+    }
+}
+
+/// Notarization state of an artifact.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TicketStatus {
+    /// Whether a ticket is stapled to the artifact.
+    pub stapled: bool,
+
+    /// Whether Apple publishes a ticket for the artifact. [None] if not looked up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published: Option<bool>,
+
+    /// Why the ticket couldn't be looked up, e.g. for unsupported bundle types.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup_error: Option<String>,
+}
+
+/// A resource of the account matching an artifact.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchedResource {
+    pub id: String,
+    pub name: Option<String>,
+
+    /// Type of certificates and state of profiles.
+    pub detail: Option<String>,
+}
+
+/// Resources of the account matching an artifact.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountMatches {
+    /// The bundle ID registered for the identifier of the artifact.
+    pub bundle_id: Option<MatchedResource>,
+
+    /// The certificate the artifact is signed with.
+    pub certificate: Option<MatchedResource>,
+
+    /// The embedded provisioning profile.
+    pub profile: Option<MatchedResource>,
+}
+
+/// A report on how an artifact is signed.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactInspection {
+    pub path: PathBuf,
+    pub kind: ArtifactKind,
+
+    /// `CFBundleIdentifier` of bundles.
+    pub bundle_identifier: Option<String>,
+
+    /// The code signature. [None] if unsigned.
+    pub signing: Option<SigningSummary>,
+
+    /// The embedded provisioning profile, if any.
+    pub profile: Option<ProfileSummary>,
+
+    pub notarization: TicketStatus,
+
+    /// Matching account resources, once looked up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<AccountMatches>,
+}
+
+fn sha256_fingerprint(cert: &CapturedX509Certificate) -> Result<String, AppleCodesignError> {
+    Ok(hex::encode(cert.sha256_fingerprint()?.as_ref()))
+}
+
+/// The leaf of the certificates of a signature.
+///
+/// Apple issued leaf certificates carry the team ID. Otherwise the first
+/// certificate is assumed to be the leaf.
+fn leaf_certificate(certs: Vec<CapturedX509Certificate>) -> Option<CapturedX509Certificate> {
+    certs
+        .iter()
+        .find(|cert| cert.apple_team_id().is_some())
+        .or_else(|| certs.first())
+        .cloned()
+}
+
+fn signing_summary(sig: &EmbeddedSignature) -> Result<SigningSummary, AppleCodesignError> {
+    let cd = sig.code_directory()?;
+
+    let certificate = sig
+        .signed_data()?
+        .and_then(|signed_data| leaf_certificate(signed_data.certificates().cloned().collect()));
+
+    let mut summary = summary_from_certificate(certificate.as_ref())?;
+
+    if let Some(cd) = cd {
+        summary.identifier = Some(cd.ident.to_string());
+        if let Some(team) = &cd.team_name {
+            summary.team_id = Some(team.to_string());
+        }
+        summary.hardened_runtime = cd.flags.contains(CodeSignatureFlags::RUNTIME);
+        summary.flags = Some(format!("{:?}", cd.flags));
+    }
+
+    summary.entitlements = match sig.entitlements()? {
+        Some(blob) => plist::Value::from_reader_xml(blob.as_str().as_bytes())
+            .map_err(AppleCodesignError::PlistParseXml)?
+            .into_dictionary(),
+        None => None,
+    };
+
+    Ok(summary)
+}
+
+fn summary_from_certificate(
+    certificate: Option<&CapturedX509Certificate>,
+) -> Result<SigningSummary, AppleCodesignError> {
+    Ok(match certificate {
+        Some(cert) => SigningSummary {
+            team_id: cert.apple_team_id(),
+            certificate: Some(
+                cert.subject_name()
+                    .user_friendly_str()
+                    .map_err(AppleCodesignError::CertificateDecode)?,
+            ),
+            certificate_sha256: Some(sha256_fingerprint(cert)?),
+            ..Default::default()
+        },
+        None => SigningSummary::default(),
+    })
+}
+
+fn macho_signing_summary(data: &[u8]) -> Result<Option<SigningSummary>, AppleCodesignError> {
+    for macho in MachFile::parse(data)?.iter_macho() {
+        if let Some(sig) = macho.code_signature()? {
+            return Ok(Some(signing_summary(&sig)?));
+        }
+    }
+
+    Ok(None)
+}
+
+impl ArtifactInspection {
+    fn new(path: &Path, kind: ArtifactKind) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            bundle_identifier: None,
+            signing: None,
+            profile: None,
+            notarization: TicketStatus::default(),
+            account: None,
+        }
+    }
+
+    /// Inspect the artifact at a path.
+    ///
+    /// If `stapler` is given, Apple's servers are asked whether a notarization
+    /// ticket is published for the artifact.
+    pub fn from_path(path: &Path, stapler: Option<&Stapler>) -> Result<Self, AppleCodesignError> {
+        match PathType::from_path(path)? {
+            PathType::Bundle => {
+                let bundle = DirectoryBundle::new_from_path(path)
+                    .map_err(AppleCodesignError::DirectoryBundle)?;
+
+                Self::from_bundle(path, ArtifactKind::Bundle, &bundle, stapler)
+            }
+            PathType::Zip => Self::from_ipa(path, stapler),
+            PathType::Dmg => Self::from_dmg(path, stapler),
+            PathType::Xar => Self::from_xar(path, stapler),
+            PathType::MachO => {
+                let mut inspection = Self::new(path, ArtifactKind::MachO);
+                inspection.signing = macho_signing_summary(&std::fs::read(path)?)?;
+                inspection.notarization.lookup_error =
+                    Some("tickets can't be stapled to Mach-O binaries".into());
+
+                Ok(inspection)
+            }
+            PathType::Other => Err(AppleCodesignError::UnrecognizedPathType),
+        }
+    }
+
+    fn from_bundle(
+        path: &Path,
+        kind: ArtifactKind,
+        bundle: &DirectoryBundle,
+        stapler: Option<&Stapler>,
+    ) -> Result<Self, AppleCodesignError> {
+        let mut inspection = Self::new(path, kind);
+
+        inspection.bundle_identifier = bundle
+            .identifier()
+            .map_err(AppleCodesignError::DirectoryBundle)?;
+
+        if let Some(main_exe) = bundle
+            .files(false)
+            .map_err(AppleCodesignError::DirectoryBundle)?
+            .into_iter()
+            .find(|file| matches!(file.is_main_executable(), Ok(true)))
+        {
+            inspection.signing = macho_signing_summary(&std::fs::read(main_exe.absolute_path())?)?;
+        }
+
+        for name in ["embedded.mobileprovision", "embedded.provisionprofile"] {
+            let profile_path = bundle.resolve_path(name);
+
+            if profile_path.is_file() {
+                let profile = ProvisioningProfile::from_der(&std::fs::read(profile_path)?)?;
+                inspection.profile = Some(ProfileSummary::from(&profile));
+                break;
+            }
+        }
+
+        inspection.notarization.stapled = bundle.resolve_path("CodeResources").is_file();
+        if let Some(stapler) = stapler {
+            inspection.record_lookup(stapler.lookup_ticket_for_app_bundle(bundle));
+        }
+
+        Ok(inspection)
+    }
+
+    fn from_ipa(path: &Path, stapler: Option<&Stapler>) -> Result<Self, AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        zip::ZipArchive::new(File::open(path)?)?.extract(td.path())?;
+
+        let app = std::fs::read_dir(td.path().join("Payload"))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.extension().map(|ext| ext == "app").unwrap_or_default())
+            .ok_or_else(|| AppleCodesignError::IpaMalformed("no Payload/*.app".into()))?;
+
+        let bundle =
+            DirectoryBundle::new_from_path(&app).map_err(AppleCodesignError::DirectoryBundle)?;
+
+        Self::from_bundle(path, ArtifactKind::Ipa, &bundle, stapler)
+    }
+
+    fn from_dmg(path: &Path, stapler: Option<&Stapler>) -> Result<Self, AppleCodesignError> {
+        let mut inspection = Self::new(path, ArtifactKind::Dmg);
+        let dmg = DmgReader::new(&mut File::open(path)?)?;
+
+        if let Some(sig) = dmg.embedded_signature()? {
+            inspection.signing = Some(signing_summary(&sig)?);
+            inspection.notarization.stapled = sig.find_slot(CodeSigningSlot::Ticket).is_some();
+        }
+
+        if let Some(stapler) = stapler {
+            inspection.record_lookup(stapler.lookup_ticket_for_dmg(&dmg));
+        }
+
+        Ok(inspection)
+    }
+
+    fn from_xar(path: &Path, stapler: Option<&Stapler>) -> Result<Self, AppleCodesignError> {
+        let mut inspection = Self::new(path, ArtifactKind::Pkg);
+
+        let mut fh = File::open(path)?;
+        let mut trailer = [0u8; 16];
+        if fh.metadata()?.len() >= trailer.len() as u64 {
+            fh.seek(SeekFrom::End(-(trailer.len() as i64)))?;
+            fh.read_exact(&mut trailer)?;
+            inspection.notarization.stapled = &trailer[0..4] == XAR_TICKET_TRAILER_MAGIC;
+        }
+
+        let mut xar = XarReader::new(File::open(path)?)?;
+
+        let certs = match xar.cms_signature()? {
+            Some(signed_data) => signed_data.certificates().cloned().collect(),
+            None => xar
+                .rsa_signature()?
+                .map(|(_, certs)| certs)
+                .unwrap_or_default(),
+        };
+
+        if let Some(cert) = leaf_certificate(certs) {
+            inspection.signing = Some(summary_from_certificate(Some(&cert))?);
+        }
+
+        if let Some(stapler) = stapler {
+            inspection.record_lookup(stapler.lookup_ticket_for_xar(&mut xar));
+        }
+
+        Ok(inspection)
+    }
+
+    fn record_lookup(&mut self, result: Result<Vec<u8>, AppleCodesignError>) {
+        match result {
+            Ok(_) => {
+                self.notarization.published = Some(true);
+            }
+            Err(AppleCodesignError::NotarizationRecordNotInResponse)
+            | Err(AppleCodesignError::NotarizationLookupFailure(..)) => {
+                self.notarization.published = Some(false);
+            }
+            Err(e) => {
+                self.notarization.lookup_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Find the resources of the account matching the artifact.
+    pub fn match_account(
+        &mut self,
+        bundle_id: Option<MatchedResource>,
+        certificates: &[Certificate],
+        profiles: &[Profile],
+    ) -> Result<(), AppleCodesignError> {
+        let mut matches = AccountMatches {
+            bundle_id,
+            ..Default::default()
+        };
+
+        if let Some(fingerprint) = self
+            .signing
+            .as_ref()
+            .and_then(|s| s.certificate_sha256.as_deref())
+        {
+            for cert in certificates {
+                let der = base64::decode(
+                    cert.attributes
+                        .certificate_content
+                        .as_deref()
+                        .unwrap_or_default(),
+                )?;
+
+                if sha256_fingerprint(&CapturedX509Certificate::from_der(der)?)? == fingerprint {
+                    matches.certificate = Some(MatchedResource {
+                        id: cert.id.clone(),
+                        name: cert.attributes.name.clone(),
+                        detail: cert.attributes.certificate_type.map(|t| format!("{:?}", t)),
+                    });
+                    break;
+                }
+            }
+        }
+
+        if let Some(uuid) = self.profile.as_ref().map(|p| p.uuid.as_str()) {
+            matches.profile = profiles
+                .iter()
+                .find(|p| {
+                    p.attributes
+                        .uuid
+                        .as_deref()
+                        .map(|u| u.eq_ignore_ascii_case(uuid))
+                        .unwrap_or_default()
+                })
+                .map(|p| MatchedResource {
+                    id: p.id.clone(),
+                    name: p.attributes.name.clone(),
+                    detail: p.attributes.profile_state.map(|s| format!("{:?}", s)),
+                });
+        }
+
+        self.account = Some(matches);
+
+        Ok(())
+    }
+
+    /// Render the report as human readable lines of text.
+    pub fn to_text_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("path: {}", self.path.display()),
+            format!("kind: {:?}", self.kind),
+        ];
+
+        if let Some(identifier) = &self.bundle_identifier {
+            lines.push(format!("bundle identifier: {}", identifier));
+        }
+
+        match &self.signing {
+            Some(signing) => {
+                let or_none = |v: &Option<String>| v.clone().unwrap_or_else(|| "none".into());
+
+                lines.push(format!("identifier: {}", or_none(&signing.identifier)));
+                lines.push(format!("team ID: {}", or_none(&signing.team_id)));
+                lines.push(format!(
+                    "signing certificate: {}",
+                    match (&signing.certificate, &signing.certificate_sha256) {
+                        (Some(subject), Some(fingerprint)) =>
+                            format!("{} ({})", subject, fingerprint),
+                        _ => "none (ad-hoc)".into(),
+                    }
+                ));
+                lines.push(format!("hardened runtime: {}", signing.hardened_runtime));
+                lines.push(format!(
+                    "entitlements: {}",
+                    signing
+                        .entitlements
+                        .as_ref()
+                        .map(|e| e.keys().cloned().collect::<Vec<_>>().join(", "))
+                        .filter(|keys| !keys.is_empty())
+                        .unwrap_or_else(|| "none".into())
+                ));
+            }
+            None => lines.push("signature: none".into()),
+        }
+
+        match &self.profile {
+            Some(p) => {
+                lines.push(format!(
+                    "profile: {} ({}) for team {}, expires {}",
+                    p.name,
+                    p.uuid,
+                    p.team_identifiers.join(", "),
+                    p.expiration_date
+                ));
+                lines.push(format!(
+                    "profile devices: {}",
+                    if p.provisions_all_devices {
+                        "all".to_string()
+                    } else {
+                        p.provisioned_devices.to_string()
+                    }
+                ));
+            }
+            None => lines.push("profile: none".into()),
+        }
+
+        lines.push(format!(
+            "notarization ticket stapled: {}",
+            self.notarization.stapled
+        ));
+        if let Some(published) = self.notarization.published {
+            lines.push(format!("notarization ticket published: {}", published));
+        }
+        if let Some(error) = &self.notarization.lookup_error {
+            lines.push(format!("notarization ticket lookup: {}", error));
+        }
+
+        if let Some(account) = &self.account {
+            for (label, resource) in [
+                ("account bundle ID", &account.bundle_id),
+                ("account certificate", &account.certificate),
+                ("account profile", &account.profile),
+            ] {
+                lines.push(match resource {
+                    Some(r) => format!(
+                        "{}: {} {} {}",
+                        label,
+                        r.id,
+                        r.name.as_deref().unwrap_or_default(),
+                        r.detail.as_deref().unwrap_or_default()
+                    )
+                    .trim_end()
+                    .to_string(),
+                    None => format!("{}: not found", label),
+                });
+            }
+        }
+
+        lines
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Find the resources of the account matching an inspected artifact.
+    ///
+    /// Only performs read requests.
+    pub fn match_artifact_inspection(
+        &self,
+        inspection: &mut ArtifactInspection,
+    ) -> Result<(), AppleCodesignError> {
+        let identifier = inspection.bundle_identifier.clone().or_else(|| {
+            inspection
+                .signing
+                .as_ref()
+                .and_then(|s| s.identifier.clone())
+        });
+
+        let bundle_id = match identifier {
+            Some(identifier) => {
+                self.find_bundle_id(&identifier)?
+                    .map(|bundle_id| MatchedResource {
+                        id: bundle_id.id,
+                        name: bundle_id.attributes.name,
+                        detail: None,
+                    })
+            }
+            None => None,
+        };
+
+        let certificates = if inspection
+            .signing
+            .as_ref()
+            .and_then(|s| s.certificate_sha256.as_ref())
+            .is_some()
+        {
+            self.list_certificates()?
+        } else {
+            vec![]
+        };
+
+        let profiles = if inspection.profile.is_some() {
+            self.list_profiles()?
+        } else {
+            vec![]
+        };
+
+        inspection.match_account(bundle_id, &certificates, &profiles)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::json,
+    };
+
+    #[test]
+    fn unsigned_ipa() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let ipa = td.path().join("Example.ipa");
+        std::fs::write(&ipa, fixtures::ipa("com.example.app", "1.0", "1"))?;
+
+        let mut inspection = ArtifactInspection::from_path(&ipa, None)?;
+        assert_eq!(inspection.kind, ArtifactKind::Ipa);
+        assert_eq!(
+            inspection.bundle_identifier.as_deref(),
+            Some("com.example.app")
+        );
+        assert!(inspection.signing.is_none());
+        assert!(inspection.profile.is_none());
+        assert!(!inspection.notarization.stapled);
+        assert!(inspection
+            .to_text_lines()
+            .contains(&"signature: none".to_string()));
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/bundleIds",
+                200,
+                fixtures::collection(vec![fixtures::bundle_id("B1", "com.example.app")]),
+            )
+            .start()?;
+        server
+            .client()?
+            .match_artifact_inspection(&mut inspection)?;
+
+        let account = inspection.account.as_ref().unwrap();
+        assert_eq!(account.bundle_id.as_ref().unwrap().id, "B1");
+        assert!(account.certificate.is_none());
+        assert!(account.profile.is_none());
+
+        // Without a signature or profile, certificates and profiles aren't listed.
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(
+            serde_json::to_value(&inspection)?["account"]["bundleId"]["id"],
+            json!("B1")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Mac Installer Distribution signing assets.
+//!
+//! Apps are delivered to the Mac App Store as installer packages (`.pkg`)
+//! signed with a Mac Installer Distribution certificate.
+//! [AppStoreConnectClient::create_installer_signing_assets()] obtains such a
+//! certificate for a private key and packages both as a `.p12` file usable
+//! with `--p12-file`. [installer_package_problems()] checks that a local
+//! package is signed with such a certificate before it is delivered.
+
+use {
+    crate::{
+        app_store_connect::{certs_api::CertificateType, AppStoreConnectClient},
+        certificate::{AppleCertificate, CertificateProfile},
+        cryptography::{create_pfx_data, InMemoryPrivateKey, PrivateKey},
+        AppleCodesignError,
+    },
+    apple_xar::reader::XarReader,
+    log::warn,
+    std::path::Path,
+    x509_certificate::{CapturedX509Certificate, KeyAlgorithm, Sign, X509CertificateBuilder},
+};
+
+/// Size of generated private keys. Apple only issues certificates for 2048 bit RSA keys.
+pub const INSTALLER_KEY_BITS: usize = 2048;
+
+/// A Mac Installer Distribution certificate and its private key.
+pub struct InstallerSigningAssets {
+    /// App Store Connect ID of the certificate.
+    pub certificate_id: String,
+
+    /// The issued certificate.
+    pub certificate: CapturedX509Certificate,
+
+    /// Apple CA certificates the certificate chains to, excluding itself.
+    pub chain: Vec<CapturedX509Certificate>,
+
+    /// PFX data holding the certificate and private key.
+    pub p12: Vec<u8>,
+}
+
+impl InstallerSigningAssets {
+    /// Assemble the assets of an issued certificate and its private key.
+    ///
+    /// The `.p12` data is encrypted with `p12_password`.
+    pub fn new(
+        certificate_id: &str,
+        certificate: CapturedX509Certificate,
+        key: &InMemoryPrivateKey,
+        p12_password: &str,
+    ) -> Result<Self, AppleCodesignError> {
+        if certificate.public_key_data() != key.public_key_data() {
+            return Err(AppleCodesignError::CertificateGeneric(format!(
+                "certificate {} was not issued for the private key",
+                certificate_id
+            )));
+        }
+
+        let chain = match certificate.apple_root_certificate_chain() {
+            // The chain starts with the certificate itself.
+            Some(chain) => chain.into_iter().skip(1).collect(),
+            None => {
+                warn!(
+                    "certificate {} does not chain to a known Apple CA",
+                    certificate_id
+                );
+                vec![]
+            }
+        };
+
+        let p12 = create_pfx_data(&certificate, key, p12_password, certificate_id)?;
+
+        Ok(Self {
+            certificate_id: certificate_id.to_string(),
+            certificate,
+            chain,
+            p12,
+        })
+    }
+
+    /// PEM encoding of the certificate followed by its chain.
+    pub fn chain_pem(&self) -> Result<String, AppleCodesignError> {
+        Ok(std::iter::once(&self.certificate)
+            .chain(self.chain.iter())
+            .map(|cert| cert.encode_pem())
+            .collect())
+    }
+}
+
+/// Create a PEM encoded certificate signing request for a private key.
+pub fn create_certificate_signing_request(
+    key: &InMemoryPrivateKey,
+    common_name: &str,
+) -> Result<String, AppleCodesignError> {
+    let key_algorithm = key.key_algorithm().ok_or_else(|| {
+        AppleCodesignError::CertificateGeneric("unable to determine key algorithm".into())
+    })?;
+
+    let mut builder = X509CertificateBuilder::new(key_algorithm);
+    builder
+        .subject()
+        .append_common_name_utf8_string(common_name)
+        .map_err(|e| AppleCodesignError::CertificateBuildError(format!("{:?}", e)))?;
+
+    Ok(builder
+        .create_certificate_signing_request(key.as_key_info_signer())?
+        .encode_pem()?)
+}
+
+impl AppStoreConnectClient {
+    /// Obtain a Mac Installer Distribution certificate for a private key.
+    ///
+    /// A certificate signing request for the key is submitted and the issued
+    /// certificate is packaged with the key. The key should be an RSA key of
+    /// [INSTALLER_KEY_BITS] bits (see [InMemoryPrivateKey::generate_rsa()]).
+    pub fn create_installer_signing_assets(
+        &self,
+        key: &InMemoryPrivateKey,
+        common_name: &str,
+        p12_password: &str,
+    ) -> Result<InstallerSigningAssets, AppleCodesignError> {
+        if !matches!(key.key_algorithm(), Some(KeyAlgorithm::Rsa)) {
+            return Err(AppleCodesignError::CertificateGeneric(
+                "Mac Installer Distribution certificates require an RSA key".into(),
+            ));
+        }
+
+        let csr = create_certificate_signing_request(key, common_name)?;
+        let cert = self.create_certificate(&csr, CertificateType::MacInstallerDistribution)?;
+
+        let der = base64::decode(cert.attributes.certificate_content.unwrap_or_default())?;
+        let certificate = CapturedX509Certificate::from_der(der)?;
+
+        InstallerSigningAssets::new(&cert.id, certificate, key, p12_password)
+    }
+}
+
+/// Find problems preventing delivery of an installer package to the Mac App Store.
+///
+/// The package must carry a valid signature made with a Mac Installer
+/// Distribution certificate issued by Apple. If `certificate` is given, the
+/// package must be signed with it. Returns a human readable description of
+/// every problem found.
+pub fn installer_package_problems(
+    path: &Path,
+    certificate: Option<&CapturedX509Certificate>,
+) -> Result<Vec<String>, AppleCodesignError> {
+    let mut xar = XarReader::new(std::fs::File::open(path)?)?;
+    let mut problems = vec![];
+
+    let signer = match xar.rsa_signature()? {
+        Some((_, certs)) => certs.into_iter().next(),
+        None => {
+            problems.push(format!("{} is not signed", path.display()));
+            return Ok(problems);
+        }
+    };
+
+    let signer = if let Some(signer) = signer {
+        signer
+    } else {
+        problems.push(format!(
+            "signature of {} lacks a signing certificate",
+            path.display()
+        ));
+        return Ok(problems);
+    };
+
+    if !xar.verify_table_of_contents_checksum()? {
+        problems.push(format!(
+            "table of contents checksum of {} does not verify",
+            path.display()
+        ));
+    }
+
+    if !xar.verify_rsa_checksum_signature().unwrap_or(false) {
+        problems.push(format!("signature of {} does not verify", path.display()));
+    }
+
+    let name = signer
+        .subject_common_name()
+        .unwrap_or_else(|| "<unknown>".to_string());
+
+    if signer.apple_guess_profile() != Some(CertificateProfile::MacInstallerDistribution) {
+        problems.push(format!(
+            "{} is signed with {}, which is not a Mac Installer Distribution certificate",
+            path.display(),
+            name
+        ));
+    }
+
+    if !signer.chains_to_apple_root_ca() {
+        problems.push(format!(
+            "signing certificate {} is not issued by Apple",
+            name
+        ));
+    }
+
+    if let Some(certificate) = certificate {
+        if signer.constructed_data() != certificate.constructed_data() {
+            problems.push(format!(
+                "{} is signed with {} instead of the expected certificate",
+                path.display(),
+                name
+            ));
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Error if problems preventing delivery of an installer package are found.
+pub fn verify_installer_package(
+    path: &Path,
+    certificate: Option<&CapturedX509Certificate>,
+) -> Result<(), AppleCodesignError> {
+    let problems = installer_package_problems(path, certificate)?;
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(AppleCodesignError::InstallerPackageInvalid(
+            problems.join("; "),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            app_store_connect::testing::{fixtures, MockServerBuilder},
+            certificate::create_self_signed_code_signing_certificate,
+            cryptography::parse_pfx_data,
+        },
+        x509_certificate::EcdsaCurve,
+    };
+
+    #[test]
+    fn assets() -> Result<(), AppleCodesignError> {
+        let (cert, _, key_document) = create_self_signed_code_signing_certificate(
+            KeyAlgorithm::Ecdsa(EcdsaCurve::Secp256r1),
+            CertificateProfile::MacInstallerDistribution,
+            "ABCDE12345",
+            "Installer",
+            "US",
+            chrono::Duration::days(1),
+        )?;
+        let key = InMemoryPrivateKey::from_pkcs8_der(key_document.as_ref())?;
+
+        let assets = InstallerSigningAssets::new("C1", cert.clone(), &key, "secret")?;
+        assert!(assets.chain.is_empty());
+        assert_eq!(assets.chain_pem()?, cert.encode_pem());
+
+        let (p12_cert, _) = parse_pfx_data(&assets.p12, "secret")?;
+        assert_eq!(p12_cert.constructed_data(), cert.constructed_data());
+
+        // The certificate must match the key.
+        let other = InMemoryPrivateKey::from_pkcs8_der(include_bytes!("../testdata/rsa-2048.pk8"))?;
+        assert!(InstallerSigningAssets::new("C1", cert, &other, "secret").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_assets() -> Result<(), AppleCodesignError> {
+        let cert_der = include_bytes!("../testdata/apple-signed-apple-distribution.cer");
+        let certificate = fixtures::certificate(
+            "C1",
+            "MAC_INSTALLER_DISTRIBUTION",
+            &base64::encode(cert_der),
+        );
+
+        let server = MockServerBuilder::default()
+            .json(
+                "POST",
+                "/v1/certificates",
+                201,
+                fixtures::document(certificate),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let key = InMemoryPrivateKey::from_pkcs8_der(include_bytes!("../testdata/rsa-2048.pk8"))?;
+
+        // The returned certificate wasn't issued for the key.
+        assert!(matches!(
+            client.create_installer_signing_assets(&key, "Installer", "secret"),
+            Err(AppleCodesignError::CertificateGeneric(_))
+        ));
+
+        let body = serde_json::from_slice::<serde_json::Value>(&server.requests()[0].body)?;
+        let attributes = &body["data"]["attributes"];
+        assert_eq!(attributes["certificateType"], "MAC_INSTALLER_DISTRIBUTION");
+        assert!(attributes["csrContent"]
+            .as_str()
+            .unwrap_or_default()
+            .starts_with("-----BEGIN CERTIFICATE REQUEST-----"));
+
+        let ecdsa =
+            InMemoryPrivateKey::from_pkcs8_der(include_bytes!("../testdata/secp256r1.pk8"))?;
+        assert!(client
+            .create_installer_signing_assets(&ecdsa, "Installer", "secret")
+            .is_err());
+        assert_eq!(server.requests().len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1265 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Pre-flight validation of `.ipa` files before upload.
+//!
+//! Apple only validates the signing of an uploaded `.ipa` after the upload
+//! has been processed, and reports problems with generic messages. This module
+//! performs the most common checks locally: the entitlements of the main
+//! executable are compared against the `embedded.mobileprovision` of the app
+//! bundle, and the Mach-O binaries of the app are inspected for issues Apple
+//! is known to reject. DriverKit driver extensions (`.dext` bundles) embedded
+//! in the app are checked for the metadata and entitlements drivers require.
+
+use {
+    crate::{
+        macho::{MachFile, Platform},
+        AppleCodesignError,
+    },
+    chrono::{DateTime, Utc},
+    cryptographic_message_syntax::SignedData,
+    goblin::mach::cputype::{
+        get_arch_name_from_types, CPU_TYPE_ARM64, CPU_TYPE_X86, CPU_TYPE_X86_64,
+    },
+    log::warn,
+    plist::{Dictionary, Value},
+    std::io::{Cursor, Read},
+    x509_certificate::CapturedX509Certificate,
+};
+
+/// Undefined symbols of private API functions known to cause App Store rejections.
+pub const PRIVATE_API_SYMBOLS: &[&str] = &[
+    "_CTTelephonyCenterAddObserver",
+    "_CTTelephonyCenterGetDefault",
+    "_GSEventGetType",
+    "_GSSendEvent",
+    "_IOSurfaceAcceleratorCreate",
+    "_MGCopyAnswer",
+    "_SBSCopyApplicationDisplayIdentifiers",
+    "_SBSLaunchApplicationWithIdentifier",
+    "_UIGetScreenImage",
+    "__CTServerConnectionCreate",
+];
+
+/// Leading bytes of Mach-O and universal binaries.
+const MACHO_MAGICS: &[[u8; 4]] = &[
+    [0xfe, 0xed, 0xfa, 0xce],
+    [0xfe, 0xed, 0xfa, 0xcf],
+    [0xce, 0xfa, 0xed, 0xfe],
+    [0xcf, 0xfa, 0xed, 0xfe],
+    [0xca, 0xfe, 0xba, 0xbe],
+    [0xca, 0xfe, 0xba, 0xbf],
+];
+
+/// Directory of driver extensions within an app bundle.
+const SYSTEM_EXTENSIONS_DIR: &str = "SystemExtensions";
+
+/// Entitlement every driver extension must be signed with.
+const DRIVERKIT_ENTITLEMENT: &str = "com.apple.developer.driverkit";
+
+/// Parsed content of a `.mobileprovision` file.
+#[derive(Clone, Debug)]
+pub struct ProvisioningProfile {
+    /// Name of the profile.
+    pub name: String,
+
+    /// UUID of the profile.
+    pub uuid: String,
+
+    /// Team identifiers the profile is issued to.
+    pub team_identifiers: Vec<String>,
+
+    /// When the profile expires.
+    pub expiration_date: DateTime<Utc>,
+
+    /// UDIDs of devices the profile is restricted to.
+    ///
+    /// Only development and ad hoc profiles have these.
+    pub provisioned_devices: Vec<String>,
+
+    /// Whether the profile runs on any device. Only in-house profiles do.
+    pub provisions_all_devices: bool,
+
+    /// Entitlements granted by the profile.
+    pub entitlements: Dictionary,
+
+    /// Signing certificates the profile can be used with.
+    pub developer_certificates: Vec<CapturedX509Certificate>,
+}
+
+/// A certificate embedded in a provisioning profile that expires too soon.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CertificateExpiryWarning {
+    /// Common name of the certificate's subject.
+    pub subject: String,
+
+    /// When the certificate expires.
+    pub expiration_date: DateTime<Utc>,
+
+    /// Whether the certificate expires before the profile does.
+    pub expires_before_profile: bool,
+
+    /// Days until the certificate expires. Negative if it already has.
+    pub days_remaining: i64,
+}
+
+impl std::fmt::Display for CertificateExpiryWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.days_remaining < 0 {
+            write!(f, "certificate {} has expired", self.subject)?;
+        } else {
+            write!(
+                f,
+                "certificate {} expires in {} days",
+                self.subject, self.days_remaining
+            )?;
+        }
+
+        write!(f, " ({})", self.expiration_date.to_rfc3339())?;
+
+        if self.expires_before_profile {
+            f.write_str(", before the provisioning profile does")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProvisioningProfile {
+    /// Parse a profile from the content of a `.mobileprovision` file.
+    ///
+    /// The file is a CMS signed data structure wrapping a plist. The signature
+    /// isn't verified.
+    pub fn from_der(data: &[u8]) -> Result<Self, AppleCodesignError> {
+        let signed_data = SignedData::parse_ber(data)?;
+
+        let content = signed_data.signed_content().ok_or_else(|| {
+            AppleCodesignError::IpaMalformed("provisioning profile has no content".into())
+        })?;
+
+        Self::from_plist_data(content)
+    }
+
+    /// Parse a profile from its plist content.
+    pub fn from_plist_data(data: &[u8]) -> Result<Self, AppleCodesignError> {
+        let value = Value::from_reader(Cursor::new(data)).map_err(|e| {
+            AppleCodesignError::IpaMalformed(format!("provisioning profile: {}", e))
+        })?;
+
+        let dict = value.as_dictionary().ok_or_else(|| {
+            AppleCodesignError::IpaMalformed("provisioning profile is not a dictionary".into())
+        })?;
+
+        let missing = |key: &str| {
+            AppleCodesignError::IpaMalformed(format!("provisioning profile lacks {}", key))
+        };
+
+        let get_string = |key: &str| -> Result<String, AppleCodesignError> {
+            dict.get(key)
+                .and_then(|v| v.as_string())
+                .map(|v| v.to_string())
+                .ok_or_else(|| missing(key))
+        };
+
+        let get_strings = |key: &str| -> Vec<String> {
+            dict.get(key)
+                .and_then(|v| v.as_array())
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|v| v.as_string())
+                        .map(|v| v.to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let expiration_date = dict
+            .get("ExpirationDate")
+            .and_then(|v| v.as_date())
+            .map(|v| DateTime::<Utc>::from(std::time::SystemTime::from(v)))
+            .ok_or_else(|| missing("ExpirationDate"))?;
+
+        let entitlements = dict
+            .get("Entitlements")
+            .and_then(|v| v.as_dictionary())
+            .cloned()
+            .ok_or_else(|| missing("Entitlements"))?;
+
+        let developer_certificates = dict
+            .get("DeveloperCertificates")
+            .and_then(|v| v.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_data())
+                    .map(CapturedX509Certificate::from_der)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            name: get_string("Name")?,
+            uuid: get_string("UUID")?,
+            team_identifiers: get_strings("TeamIdentifier"),
+            expiration_date,
+            provisioned_devices: get_strings("ProvisionedDevices"),
+            provisions_all_devices: matches!(
+                dict.get("ProvisionsAllDevices"),
+                Some(Value::Boolean(true))
+            ),
+            entitlements,
+            developer_certificates,
+        })
+    }
+
+    /// Find embedded certificates that expire before the profile or within `days` days.
+    ///
+    /// A profile is only usable with its embedded certificates, so a certificate
+    /// expiring is the usual cause of a profile that suddenly stops working.
+    pub fn certificate_expiry_warnings(
+        &self,
+        now: DateTime<Utc>,
+        days: i64,
+    ) -> Vec<CertificateExpiryWarning> {
+        let mut warnings = self
+            .developer_certificates
+            .iter()
+            .filter_map(|cert| {
+                let expiration_date = cert.validity_not_after();
+                let expires_before_profile = expiration_date < self.expiration_date;
+                let days_remaining = (expiration_date - now).num_days();
+
+                if expires_before_profile || expiration_date - now <= chrono::Duration::days(days) {
+                    Some(CertificateExpiryWarning {
+                        subject: cert
+                            .subject_common_name()
+                            .unwrap_or_else(|| "<unknown>".to_string()),
+                        expiration_date,
+                        expires_before_profile,
+                        days_remaining,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        warnings.sort_by_key(|w| w.expiration_date);
+
+        warnings
+    }
+}
+
+/// Signing information of the main app in an `.ipa` file.
+#[derive(Clone, Debug)]
+pub struct IpaSigningInfo {
+    /// Path of the app bundle within the archive. e.g. `Payload/MyApp.app`.
+    pub app_path: String,
+
+    /// The `CFBundleIdentifier` of the main app.
+    pub bundle_identifier: String,
+
+    /// The `embedded.mobileprovision` of the main app, if present.
+    pub profile: Option<ProvisioningProfile>,
+
+    /// Entitlements of the main executable, if it is signed with any.
+    pub entitlements: Option<Dictionary>,
+
+    /// Driver extensions embedded in the main app.
+    pub driver_extensions: Vec<DriverExtension>,
+}
+
+/// A DriverKit driver extension embedded in an app.
+#[derive(Clone, Debug)]
+pub struct DriverExtension {
+    /// Path of the `.dext` bundle within the archive.
+    pub path: String,
+
+    /// Content of the `Info.plist` of the bundle.
+    pub info: Dictionary,
+
+    /// Entitlements of the driver executable, if it is signed with any.
+    pub entitlements: Option<Dictionary>,
+}
+
+impl DriverExtension {
+    /// Find problems preventing the driver from being distributed with its host app.
+    pub fn problems(&self, host_bundle_identifier: &str) -> Vec<String> {
+        let mut problems = vec![];
+
+        let get_string = |key: &str| self.info.get(key).and_then(|v| v.as_string());
+
+        if get_string("CFBundlePackageType") != Some("DEXT") {
+            problems.push(format!(
+                "{} does not have CFBundlePackageType DEXT",
+                self.path
+            ));
+        }
+
+        match get_string("CFBundleIdentifier") {
+            Some(identifier) => {
+                if !identifier.starts_with(&format!("{}.", host_bundle_identifier)) {
+                    problems.push(format!(
+                        "{} has bundle identifier {}, which is not prefixed by the bundle identifier of its app ({})",
+                        self.path, identifier, host_bundle_identifier
+                    ));
+                }
+            }
+            None => {
+                problems.push(format!("{} lacks CFBundleIdentifier", self.path));
+            }
+        }
+
+        let has_personalities = self
+            .info
+            .get("IOKitPersonalities")
+            .and_then(|v| v.as_dictionary())
+            .map(|d| !d.is_empty())
+            .unwrap_or_default();
+
+        if !has_personalities {
+            problems.push(format!(
+                "{} does not declare IOKitPersonalities to match hardware with",
+                self.path
+            ));
+        }
+
+        if get_string("OSBundleUsageDescription").is_none() {
+            problems.push(format!(
+                "{} lacks OSBundleUsageDescription, which is shown when the driver is approved",
+                self.path
+            ));
+        }
+
+        match &self.entitlements {
+            Some(entitlements) => {
+                if !matches!(
+                    entitlements.get(DRIVERKIT_ENTITLEMENT),
+                    Some(Value::Boolean(true))
+                ) {
+                    problems.push(format!(
+                        "executable of {} is not signed with the {} entitlement",
+                        self.path, DRIVERKIT_ENTITLEMENT
+                    ));
+                }
+            }
+            None => {
+                problems.push(format!(
+                    "executable of {} is not signed with entitlements",
+                    self.path
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Resolve the entitlements of the first signed Mach-O in an executable.
+fn executable_entitlements(data: &[u8]) -> Result<Option<Dictionary>, AppleCodesignError> {
+    for macho in MachFile::parse(data)?.iter_macho() {
+        if let Some(blob) = macho
+            .code_signature()?
+            .map(|sig| sig.entitlements())
+            .transpose()?
+            .flatten()
+        {
+            let value = Value::from_reader_xml(blob.as_str().as_bytes())
+                .map_err(AppleCodesignError::PlistParseXml)?;
+
+            return Ok(value.into_dictionary());
+        }
+    }
+
+    Ok(None)
+}
+
+impl IpaSigningInfo {
+    /// Resolve signing information from the content of an `.ipa` file.
+    pub fn from_ipa_data(data: &[u8]) -> Result<Self, AppleCodesignError> {
+        let mut za = zip::ZipArchive::new(Cursor::new(data))?;
+
+        let app_path = find_app_path(&za)?;
+
+        let dext_prefix = format!("{}/{}/", app_path, SYSTEM_EXTENSIONS_DIR);
+        let dext_paths = za
+            .file_names()
+            .filter_map(|name| {
+                let rest = name.strip_prefix(&dext_prefix)?;
+                let (bundle, file) = rest.split_once('/')?;
+
+                if bundle.ends_with(".dext") && file == "Info.plist" {
+                    Some(format!("{}{}", dext_prefix, bundle))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut read_file = |name: &str| -> Result<Option<Vec<u8>>, AppleCodesignError> {
+            match za.by_name(name) {
+                Ok(mut zf) => {
+                    let mut data = vec![];
+                    zf.read_to_end(&mut data)?;
+                    Ok(Some(data))
+                }
+                Err(zip::result::ZipError::FileNotFound) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        };
+
+        let info_plist_path = format!("{}/Info.plist", app_path);
+        let info_plist = read_file(&info_plist_path)?.unwrap_or_default();
+        let info = Value::from_reader(Cursor::new(info_plist))
+            .map_err(|e| AppleCodesignError::IpaMalformed(format!("{}: {}", info_plist_path, e)))?;
+
+        let get_string = |key: &str| -> Result<String, AppleCodesignError> {
+            info.as_dictionary()
+                .and_then(|dict| dict.get(key))
+                .and_then(|v| v.as_string())
+                .map(|v| v.to_string())
+                .ok_or_else(|| {
+                    AppleCodesignError::IpaMalformed(format!("{} lacks {}", info_plist_path, key))
+                })
+        };
+
+        let bundle_identifier = get_string("CFBundleIdentifier")?;
+        let executable_path = format!("{}/{}", app_path, get_string("CFBundleExecutable")?);
+
+        let profile = read_file(&format!("{}/embedded.mobileprovision", app_path))?
+            .map(|data| ProvisioningProfile::from_der(&data))
+            .transpose()?;
+
+        let executable = read_file(&executable_path)?.ok_or_else(|| {
+            AppleCodesignError::IpaMalformed(format!("{} does not exist", executable_path))
+        })?;
+
+        let entitlements = executable_entitlements(&executable)?;
+
+        let mut driver_extensions = vec![];
+
+        for path in dext_paths {
+            let info_plist_path = format!("{}/Info.plist", path);
+            let info = Value::from_reader(Cursor::new(
+                read_file(&info_plist_path)?.unwrap_or_default(),
+            ))
+            .map_err(|e| AppleCodesignError::IpaMalformed(format!("{}: {}", info_plist_path, e)))?
+            .into_dictionary()
+            .ok_or_else(|| {
+                AppleCodesignError::IpaMalformed(format!("{} is not a dictionary", info_plist_path))
+            })?;
+
+            let entitlements = match info.get("CFBundleExecutable").and_then(|v| v.as_string()) {
+                Some(name) => read_file(&format!("{}/{}", path, name))?
+                    .map(|data| executable_entitlements(&data))
+                    .transpose()?
+                    .flatten(),
+                None => None,
+            };
+
+            driver_extensions.push(DriverExtension {
+                path,
+                info,
+                entitlements,
+            });
+        }
+
+        Ok(Self {
+            app_path,
+            bundle_identifier,
+            profile,
+            entitlements,
+            driver_extensions,
+        })
+    }
+
+    /// Find problems that would cause App Store Connect to reject an upload.
+    ///
+    /// Returns a human readable description of every problem found. An empty
+    /// result means no problems were detected.
+    pub fn upload_problems(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut problems = vec![];
+
+        let profile = if let Some(profile) = &self.profile {
+            profile
+        } else {
+            problems.push(format!(
+                "{} lacks an embedded.mobileprovision",
+                self.app_path
+            ));
+            return problems;
+        };
+
+        let entitlements = if let Some(entitlements) = &self.entitlements {
+            entitlements
+        } else {
+            problems.push(format!(
+                "main executable of {} is not signed with entitlements",
+                self.app_path
+            ));
+            return problems;
+        };
+
+        if profile.expiration_date <= now {
+            problems.push(format!(
+                "provisioning profile {} ({}) expired on {}",
+                profile.name,
+                profile.uuid,
+                profile.expiration_date.to_rfc3339()
+            ));
+        }
+
+        if profile.provisions_all_devices {
+            problems.push(format!(
+                "provisioning profile {} is an in-house profile, which cannot be used for App Store distribution",
+                profile.name
+            ));
+        } else if !profile.provisioned_devices.is_empty() {
+            problems.push(format!(
+                "provisioning profile {} provisions {} devices, so it is a development or ad hoc profile; an App Store profile is required",
+                profile.name,
+                profile.provisioned_devices.len()
+            ));
+        }
+
+        let team_id = entitlements
+            .get("com.apple.developer.team-identifier")
+            .and_then(|v| v.as_string());
+
+        if let Some(team_id) = team_id {
+            if !profile.team_identifiers.iter().any(|x| x == team_id) {
+                problems.push(format!(
+                    "executable is signed for team {} but provisioning profile {} belongs to team {}",
+                    team_id,
+                    profile.name,
+                    profile.team_identifiers.join(", ")
+                ));
+            }
+        }
+
+        match entitlements
+            .get("application-identifier")
+            .and_then(|v| v.as_string())
+        {
+            Some(app_id) => {
+                let expected = team_id
+                    .or_else(|| profile.team_identifiers.first().map(|x| x.as_str()))
+                    .map(|team_id| format!("{}.{}", team_id, self.bundle_identifier));
+
+                if let Some(expected) = expected {
+                    if app_id != expected {
+                        problems.push(format!(
+                            "application-identifier entitlement {} does not match bundle identifier {} (expected {})",
+                            app_id, self.bundle_identifier, expected
+                        ));
+                    }
+                }
+            }
+            None => {
+                problems.push("executable lacks the application-identifier entitlement".into());
+            }
+        }
+
+        if matches!(
+            entitlements.get("get-task-allow"),
+            Some(Value::Boolean(true))
+        ) {
+            problems.push(
+                "executable is signed with get-task-allow, which is only permitted for development"
+                    .into(),
+            );
+        }
+
+        for (key, requested) in entitlements {
+            if key == "get-task-allow" {
+                continue;
+            }
+
+            match profile.entitlements.get(key) {
+                Some(granted) => {
+                    if !entitlement_value_allowed(granted, requested) {
+                        problems.push(format!(
+                            "entitlement {} has a value not permitted by provisioning profile {}",
+                            key, profile.name
+                        ));
+                    }
+                }
+                None => {
+                    problems.push(format!(
+                        "entitlement {} is not granted by provisioning profile {}",
+                        key, profile.name
+                    ));
+                }
+            }
+        }
+
+        for dext in &self.driver_extensions {
+            problems.extend(dext.problems(&self.bundle_identifier));
+        }
+
+        problems
+    }
+
+    /// Error if problems that would cause App Store Connect to reject an upload are found.
+    pub fn validate_for_upload(&self, now: DateTime<Utc>) -> Result<(), AppleCodesignError> {
+        let problems = self.upload_problems(now);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(AppleCodesignError::IpaPreflightFailed(problems.join("; ")))
+        }
+    }
+}
+
+/// How likely a finding is to cause App Store Connect to reject an upload.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum RejectionRisk {
+    /// The upload may be rejected, typically during app review.
+    Possible,
+    /// The upload will almost certainly be rejected during processing.
+    Likely,
+}
+
+impl std::fmt::Display for RejectionRisk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Possible => f.write_str("possible"),
+            Self::Likely => f.write_str("likely"),
+        }
+    }
+}
+
+/// A problem found in a Mach-O binary of an `.ipa`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BinaryFinding {
+    /// Path of the binary within the archive.
+    pub path: String,
+
+    /// How likely the problem is to cause a rejection.
+    pub risk: RejectionRisk,
+
+    /// Human readable description of the problem.
+    pub message: String,
+}
+
+impl std::fmt::Display for BinaryFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({} rejection)",
+            self.path, self.message, self.risk
+        )
+    }
+}
+
+/// Result of inspecting the Mach-O binaries in an `.ipa`.
+#[derive(Clone, Debug, Default)]
+pub struct BinaryInspection {
+    /// Paths of the Mach-O binaries that were inspected.
+    pub binaries: Vec<String>,
+
+    /// Problems found in the binaries.
+    pub findings: Vec<BinaryFinding>,
+}
+
+impl BinaryInspection {
+    /// Inspect every Mach-O binary in the main app bundle of an `.ipa`.
+    ///
+    /// Binaries are checked for a missing `arm64` slice, simulator slices (`x86`
+    /// slices and slices built for a simulator platform), bitcode, missing code
+    /// signatures, a minimum OS version above the `MinimumOSVersion` the app
+    /// declares, and use of private frameworks and known private APIs. Binaries
+    /// of driver extensions must target DriverKit instead, whose versions are
+    /// not comparable to `MinimumOSVersion`.
+    pub fn from_ipa_data(data: &[u8]) -> Result<Self, AppleCodesignError> {
+        let mut za = zip::ZipArchive::new(Cursor::new(data))?;
+        let app_path = find_app_path(&za)?;
+        let prefix = format!("{}/", app_path);
+
+        let mut info_plist = vec![];
+        za.by_name(&format!("{}Info.plist", prefix))?
+            .read_to_end(&mut info_plist)?;
+
+        let deployment_target = Value::from_reader(Cursor::new(info_plist))
+            .ok()
+            .and_then(|v| {
+                v.as_dictionary()
+                    .and_then(|dict| dict.get("MinimumOSVersion"))
+                    .and_then(|v| v.as_string())
+                    .and_then(parse_os_version)
+            });
+
+        let mut inspection = Self::default();
+
+        for index in 0..za.len() {
+            let mut zf = za.by_index(index)?;
+
+            if !zf.is_file() || !zf.name().starts_with(&prefix) || zf.size() < 4 {
+                continue;
+            }
+
+            let path = zf.name().to_string();
+            let driver = path
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_prefix(SYSTEM_EXTENSIONS_DIR))
+                .map(|rest| rest.contains(".dext/"))
+                .unwrap_or_default();
+
+            let mut data = vec![0; 4];
+            zf.read_exact(&mut data)?;
+
+            if !MACHO_MAGICS.iter().any(|magic| magic == &data[..]) {
+                continue;
+            }
+
+            zf.read_to_end(&mut data)?;
+
+            // Java class files share the magic of universal binaries.
+            let mach = if let Ok(mach) = MachFile::parse(&data) {
+                mach
+            } else {
+                continue;
+            };
+
+            if driver {
+                inspection.inspect_driver_binary(&path, &mach)?;
+            } else {
+                inspection.inspect_binary(&path, &mach, deployment_target.as_ref())?;
+            }
+        }
+
+        Ok(inspection)
+    }
+
+    fn inspect_binary(
+        &mut self,
+        path: &str,
+        mach: &MachFile,
+        deployment_target: Option<&semver::Version>,
+    ) -> Result<(), AppleCodesignError> {
+        self.binaries.push(path.to_string());
+
+        let mut findings = vec![];
+        let mut have_arm64 = false;
+
+        for macho in mach.iter_macho() {
+            let cputype = macho.macho.header.cputype();
+            let arch = get_arch_name_from_types(cputype, macho.macho.header.cpusubtype)
+                .unwrap_or("unknown");
+
+            match cputype {
+                CPU_TYPE_ARM64 => have_arm64 = true,
+                CPU_TYPE_X86 | CPU_TYPE_X86_64 => findings.push((
+                    RejectionRisk::Likely,
+                    format!("contains {} slice, which only runs in the simulator", arch),
+                )),
+                _ => {}
+            }
+
+            if macho.code_signature()?.is_none() {
+                findings.push((
+                    RejectionRisk::Likely,
+                    format!("{} slice is not signed", arch),
+                ));
+            }
+
+            if macho
+                .macho
+                .segments
+                .iter()
+                .any(|segment| matches!(segment.name(), Ok("__LLVM")))
+            {
+                findings.push((
+                    RejectionRisk::Likely,
+                    format!(
+                        "{} slice contains bitcode, which is no longer accepted",
+                        arch
+                    ),
+                ));
+            }
+
+            let target = macho.find_targeting()?;
+
+            if let Some(target) = &target {
+                if target.platform.is_simulator() {
+                    findings.push((
+                        RejectionRisk::Likely,
+                        format!("{} slice is built for {}", arch, target.platform),
+                    ));
+                }
+            }
+
+            if let (Some(target), Some(deployment_target)) = (target, deployment_target) {
+                if &target.minimum_os_version > deployment_target {
+                    findings.push((
+                        RejectionRisk::Likely,
+                        format!(
+                            "{} slice requires {} {} but the app declares MinimumOSVersion {}",
+                            arch, target.platform, target.minimum_os_version, deployment_target
+                        ),
+                    ));
+                }
+            }
+
+            for lib in macho.macho.libs.iter() {
+                if lib.contains("/PrivateFrameworks/") {
+                    findings.push((
+                        RejectionRisk::Likely,
+                        format!("{} slice links private framework {}", arch, lib),
+                    ));
+                }
+            }
+
+            for (name, nlist) in macho.macho.symbols().flatten() {
+                if nlist.is_undefined() && PRIVATE_API_SYMBOLS.contains(&name) {
+                    findings.push((
+                        RejectionRisk::Possible,
+                        format!("{} slice references private API {}", arch, name),
+                    ));
+                }
+            }
+        }
+
+        if !have_arm64 {
+            findings.push((RejectionRisk::Likely, "lacks an arm64 slice".to_string()));
+        }
+
+        self.findings
+            .extend(findings.into_iter().map(|(risk, message)| BinaryFinding {
+                path: path.to_string(),
+                risk,
+                message,
+            }));
+
+        Ok(())
+    }
+
+    fn inspect_driver_binary(
+        &mut self,
+        path: &str,
+        mach: &MachFile,
+    ) -> Result<(), AppleCodesignError> {
+        self.inspect_binary(path, mach, None)?;
+
+        let mut findings = vec![];
+
+        for macho in mach.iter_macho() {
+            let arch = get_arch_name_from_types(
+                macho.macho.header.cputype(),
+                macho.macho.header.cpusubtype,
+            )
+            .unwrap_or("unknown");
+
+            match macho.find_targeting()? {
+                Some(target) if matches!(target.platform, Platform::DriverKit) => {}
+                Some(target) => findings.push(format!(
+                    "{} slice of driver extension is built for {} instead of DriverKit",
+                    arch, target.platform
+                )),
+                None => findings.push(format!(
+                    "{} slice of driver extension does not declare its target platform",
+                    arch
+                )),
+            }
+        }
+
+        self.findings
+            .extend(findings.into_iter().map(|message| BinaryFinding {
+                path: path.to_string(),
+                risk: RejectionRisk::Likely,
+                message,
+            }));
+
+        Ok(())
+    }
+
+    /// The highest rejection risk of any finding.
+    pub fn risk(&self) -> Option<RejectionRisk> {
+        self.findings.iter().map(|finding| finding.risk).max()
+    }
+
+    /// A one line summary of the inspection.
+    pub fn summary(&self) -> String {
+        let count = |risk| self.findings.iter().filter(|f| f.risk == risk).count();
+
+        format!(
+            "inspected {} binaries; {} likely and {} possible rejection causes",
+            self.binaries.len(),
+            count(RejectionRisk::Likely),
+            count(RejectionRisk::Possible)
+        )
+    }
+
+    /// Error if any finding is likely to cause a rejection.
+    ///
+    /// All findings are logged.
+    pub fn validate_for_upload(&self) -> Result<(), AppleCodesignError> {
+        for finding in &self.findings {
+            warn!("{}", finding);
+        }
+
+        if self.risk() == Some(RejectionRisk::Likely) {
+            Err(AppleCodesignError::IpaPreflightFailed(self.summary()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Parse an OS version like `15.0` into a [semver::Version].
+fn parse_os_version(s: &str) -> Option<semver::Version> {
+    let mut parts = s.split('.').map(|part| part.parse::<u64>().ok());
+
+    let major = parts.next().flatten()?;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+
+    Some(semver::Version::new(major, minor, patch))
+}
+
+/// Find the path of the main app bundle in an `.ipa`. e.g. `Payload/MyApp.app`.
+fn find_app_path(za: &zip::ZipArchive<Cursor<&[u8]>>) -> Result<String, AppleCodesignError> {
+    za.file_names()
+        .find_map(|name| {
+            let parts = name.split('/').collect::<Vec<_>>();
+
+            if parts.len() == 3
+                && parts[0] == "Payload"
+                && parts[1].ends_with(".app")
+                && parts[2] == "Info.plist"
+            {
+                Some(format!("{}/{}", parts[0], parts[1]))
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| AppleCodesignError::IpaMalformed("no Payload/*.app/Info.plist".into()))
+}
+
+/// Whether a string matches a profile entitlement value, which may end with `*`.
+fn wildcard_matches(pattern: &str, value: &str) -> bool {
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        value.starts_with(prefix)
+    } else {
+        pattern == value
+    }
+}
+
+/// Whether an entitlement value requested by an executable is permitted by the
+/// value granted by a provisioning profile.
+fn entitlement_value_allowed(granted: &Value, requested: &Value) -> bool {
+    match (granted, requested) {
+        (Value::String(granted), Value::String(requested)) => wildcard_matches(granted, requested),
+        (Value::Array(granted), Value::String(_)) => granted
+            .iter()
+            .any(|granted| entitlement_value_allowed(granted, requested)),
+        (_, Value::Array(requested)) => requested
+            .iter()
+            .all(|requested| entitlement_value_allowed(granted, requested)),
+        (Value::Boolean(granted), Value::Boolean(requested)) => *granted || !*requested,
+        (granted, requested) => granted == requested,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PROFILE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Name</key>
+    <string>MyApp App Store</string>
+    <key>UUID</key>
+    <string>9d8a3c9e-0000-4000-8000-000000000000</string>
+    <key>TeamIdentifier</key>
+    <array><string>ABCDE12345</string></array>
+    <key>ExpirationDate</key>
+    <date>2030-01-01T00:00:00Z</date>
+    <key>Entitlements</key>
+    <dict>
+        <key>application-identifier</key>
+        <string>ABCDE12345.com.example.app</string>
+        <key>com.apple.developer.team-identifier</key>
+        <string>ABCDE12345</string>
+        <key>keychain-access-groups</key>
+        <array><string>ABCDE12345.*</string></array>
+        <key>aps-environment</key>
+        <string>production</string>
+        <key>get-task-allow</key>
+        <false/>
+    </dict>
+</dict>
+</plist>"#;
+
+    fn signing_info() -> Result<IpaSigningInfo, AppleCodesignError> {
+        let profile = ProvisioningProfile::from_plist_data(PROFILE.as_bytes())?;
+
+        let mut entitlements = Dictionary::new();
+        entitlements.insert(
+            "application-identifier".into(),
+            "ABCDE12345.com.example.app".into(),
+        );
+        entitlements.insert(
+            "com.apple.developer.team-identifier".into(),
+            "ABCDE12345".into(),
+        );
+        entitlements.insert(
+            "keychain-access-groups".into(),
+            Value::Array(vec!["ABCDE12345.com.example.app".into()]),
+        );
+        entitlements.insert("get-task-allow".into(), false.into());
+
+        Ok(IpaSigningInfo {
+            app_path: "Payload/MyApp.app".into(),
+            bundle_identifier: "com.example.app".into(),
+            profile: Some(profile),
+            entitlements: Some(entitlements),
+            driver_extensions: vec![],
+        })
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parse_profile() -> Result<(), AppleCodesignError> {
+        let profile = ProvisioningProfile::from_plist_data(PROFILE.as_bytes())?;
+
+        assert_eq!(profile.name, "MyApp App Store");
+        assert_eq!(profile.team_identifiers, vec!["ABCDE12345".to_string()]);
+        assert!(profile.provisioned_devices.is_empty());
+        assert!(!profile.provisions_all_devices);
+        assert_eq!(
+            profile.expiration_date.to_rfc3339(),
+            "2030-01-01T00:00:00+00:00"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn certificate_expiry() -> Result<(), AppleCodesignError> {
+        let cert_der = include_bytes!("../testdata/apple-signed-apple-distribution.cer");
+        let cert = CapturedX509Certificate::from_der(cert_der.to_vec())?;
+        let not_after = cert.validity_not_after();
+
+        let mut value = Value::from_reader_xml(PROFILE.as_bytes())
+            .map_err(AppleCodesignError::PlistParseXml)?;
+        let dict = value.as_dictionary_mut().unwrap();
+        dict.insert(
+            "DeveloperCertificates".into(),
+            Value::Array(vec![Value::Data(cert_der.to_vec())]),
+        );
+        dict.insert(
+            "ExpirationDate".into(),
+            Value::Date(std::time::SystemTime::from(not_after - chrono::Duration::days(1)).into()),
+        );
+
+        let mut data = vec![];
+        value
+            .to_writer_xml(&mut data)
+            .map_err(AppleCodesignError::PlistSerializeXml)?;
+        let profile = ProvisioningProfile::from_plist_data(&data)?;
+        assert_eq!(profile.developer_certificates.len(), 1);
+
+        let now = not_after - chrono::Duration::days(60);
+        assert!(profile.certificate_expiry_warnings(now, 30).is_empty());
+
+        let warnings = profile.certificate_expiry_warnings(now, 90);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].days_remaining, 60);
+        assert!(!warnings[0].expires_before_profile);
+
+        let mut profile = profile;
+        profile.expiration_date = not_after + chrono::Duration::days(1);
+        let warnings = profile.certificate_expiry_warnings(now, 30);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].expires_before_profile);
+        assert!(warnings[0]
+            .to_string()
+            .ends_with("before the provisioning profile does"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn valid() -> Result<(), AppleCodesignError> {
+        let info = signing_info()?;
+
+        assert!(info.upload_problems(now()).is_empty());
+        info.validate_for_upload(now())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn mismatches() -> Result<(), AppleCodesignError> {
+        let mut info = signing_info()?;
+        info.bundle_identifier = "com.example.other".into();
+
+        let profile = info.profile.as_mut().unwrap();
+        profile
+            .provisioned_devices
+            .push("00008030-000000000000002E".into());
+
+        let entitlements = info.entitlements.as_mut().unwrap();
+        entitlements.insert("aps-environment".into(), "development".into());
+        entitlements.insert(
+            "com.apple.developer.associated-domains".into(),
+            Value::Array(vec!["applinks:example.com".into()]),
+        );
+
+        let problems = info.upload_problems(now());
+        assert_eq!(problems.len(), 4);
+        assert!(problems[0].contains("provisions 1 devices"));
+        assert!(problems[1].contains("does not match bundle identifier com.example.other"));
+        assert!(problems[2].contains("aps-environment has a value not permitted"));
+        assert!(problems[3].contains("associated-domains is not granted"));
+
+        let later = DateTime::parse_from_rfc3339("2031-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert!(info.upload_problems(later)[0].contains("expired on 2030-01-01"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_profile() -> Result<(), AppleCodesignError> {
+        let mut info = signing_info()?;
+        info.profile = None;
+
+        assert_eq!(
+            info.upload_problems(now()),
+            vec!["Payload/MyApp.app lacks an embedded.mobileprovision".to_string()]
+        );
+        assert!(matches!(
+            info.validate_for_upload(now()),
+            Err(AppleCodesignError::IpaPreflightFailed(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn driver_extension() -> Result<(), AppleCodesignError> {
+        let mut personalities = Dictionary::new();
+        personalities.insert("Driver".into(), Value::Dictionary(Dictionary::new()));
+
+        let mut dext_info = Dictionary::new();
+        dext_info.insert("CFBundlePackageType".into(), "DEXT".into());
+        dext_info.insert("CFBundleIdentifier".into(), "com.example.app.driver".into());
+        dext_info.insert(
+            "IOKitPersonalities".into(),
+            Value::Dictionary(personalities),
+        );
+        dext_info.insert("OSBundleUsageDescription".into(), "Drives things".into());
+
+        let mut entitlements = Dictionary::new();
+        entitlements.insert(DRIVERKIT_ENTITLEMENT.into(), true.into());
+
+        let mut dext = DriverExtension {
+            path: "Payload/MyApp.app/SystemExtensions/com.example.app.driver.dext".into(),
+            info: dext_info,
+            entitlements: Some(entitlements),
+        };
+
+        let mut info = signing_info()?;
+        info.driver_extensions.push(dext.clone());
+        assert!(info.upload_problems(now()).is_empty());
+
+        dext.info.remove("OSBundleUsageDescription");
+        dext.info.remove("IOKitPersonalities");
+        dext.info
+            .insert("CFBundleIdentifier".into(), "com.other.driver".into());
+        dext.entitlements = Some(Dictionary::new());
+
+        let problems = dext.problems("com.example.app");
+        assert_eq!(problems.len(), 4);
+        assert!(problems[0].contains("not prefixed by the bundle identifier of its app"));
+        assert!(problems[1].contains("IOKitPersonalities"));
+        assert!(problems[2].contains("lacks OSBundleUsageDescription"));
+        assert!(problems[3].contains("com.apple.developer.driverkit entitlement"));
+
+        dext.entitlements = None;
+        info.driver_extensions = vec![dext];
+        assert!(info
+            .upload_problems(now())
+            .last()
+            .unwrap()
+            .ends_with("is not signed with entitlements"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn os_versions() {
+        assert_eq!(parse_os_version("15"), Some(semver::Version::new(15, 0, 0)));
+        assert_eq!(
+            parse_os_version("15.4"),
+            Some(semver::Version::new(15, 4, 0))
+        );
+        assert_eq!(
+            parse_os_version("15.4.1"),
+            Some(semver::Version::new(15, 4, 1))
+        );
+        assert_eq!(parse_os_version("15.x"), None);
+        assert_eq!(parse_os_version(""), None);
+    }
+
+    #[test]
+    fn inspection_risk() {
+        let mut inspection = BinaryInspection {
+            binaries: vec!["Payload/MyApp.app/MyApp".into()],
+            findings: vec![BinaryFinding {
+                path: "Payload/MyApp.app/MyApp".into(),
+                risk: RejectionRisk::Possible,
+                message: "arm64 slice references private API _UIGetScreenImage".into(),
+            }],
+        };
+
+        assert_eq!(inspection.risk(), Some(RejectionRisk::Possible));
+        assert!(inspection.validate_for_upload().is_ok());
+
+        inspection.findings.push(BinaryFinding {
+            path: "Payload/MyApp.app/Frameworks/Foo.framework/Foo".into(),
+            risk: RejectionRisk::Likely,
+            message: "lacks an arm64 slice".into(),
+        });
+
+        assert_eq!(inspection.risk(), Some(RejectionRisk::Likely));
+        assert_eq!(
+            inspection.summary(),
+            "inspected 1 binaries; 1 likely and 1 possible rejection causes"
+        );
+        assert_eq!(
+            inspection.findings[1].to_string(),
+            "Payload/MyApp.app/Frameworks/Foo.framework/Foo: lacks an arm64 slice (likely rejection)"
+        );
+        assert!(matches!(
+            inspection.validate_for_upload(),
+            Err(AppleCodesignError::IpaPreflightFailed(_))
+        ));
+    }
+
+    #[test]
+    fn value_matching() {
+        assert!(entitlement_value_allowed(&"A.*".into(), &"A.b".into()));
+        assert!(!entitlement_value_allowed(&"A.*".into(), &"B.b".into()));
+        assert!(entitlement_value_allowed(
+            &Value::Array(vec!["A.*".into(), "B.c".into()]),
+            &Value::Array(vec!["A.x".into(), "B.c".into()])
+        ));
+        assert!(entitlement_value_allowed(&true.into(), &false.into()));
+        assert!(!entitlement_value_allowed(&false.into(), &true.into()));
+    }
+}
@@ -0,0 +1,83 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generic JSON:API document types used by the App Store Connect API.
+//!
+//! The App Store Connect API (as opposed to the Notary API) follows the
+//! JSON:API specification. Every response is a *document* holding a primary
+//! `data` member, which is a single resource or a list of resources. Resources
+//! have an `id`, a `type`, and resource specific `attributes`.
+//!
+//! The types in this module model the envelope and are defined in the
+//! `asconnect-types` crate. Resource specific attributes are defined by the
+//! individual `*_api` modules. Typed filters of collection requests, like
+//! [DeviceQuery], are re-exported from `asconnect_types::query`.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi>.
+
+pub use asconnect_types::{json_api::*, query::*};
+
+use {
+    chrono::{DateTime, Utc},
+    log::warn,
+    serde::de::DeserializeOwned,
+    serde_json::Value,
+    std::collections::HashMap,
+};
+
+/// Parse a date-time string from an API response.
+///
+/// The API mostly emits RFC 3339 date-times. But some resources use a UTC
+/// offset without a colon (e.g. `2023-01-01T00:00:00.000+0000`), which isn't
+/// valid RFC 3339. Both forms are accepted.
+pub fn parse_api_date(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Index the included resources of a type by their ID.
+pub fn included_resources<A: DeserializeOwned>(
+    included: &[Value],
+    r#type: &str,
+) -> Result<HashMap<String, Resource<A>>, serde_json::Error> {
+    included
+        .iter()
+        .filter(|value| value.get("type").and_then(|t| t.as_str()) == Some(r#type))
+        .map(|value| {
+            let resource = serde_json::from_value::<Resource<A>>(value.clone())?;
+            Ok((resource.id.clone(), resource))
+        })
+        .collect()
+}
+
+/// Log attribute changes made by an update.
+pub fn log_attribute_changes(changes: &[AttributeChange]) {
+    for change in changes {
+        warn!(
+            "  {}: {} -> {}",
+            change.attribute, change.before, change.after
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_dates() {
+        let expected = Some(
+            DateTime::parse_from_rfc3339("2023-04-01T12:30:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        );
+
+        assert_eq!(parse_api_date("2023-04-01T12:30:00Z"), expected);
+        assert_eq!(parse_api_date("2023-04-01T12:30:00.000+00:00"), expected);
+        assert_eq!(parse_api_date("2023-04-01T12:30:00.000+0000"), expected);
+        assert_eq!(parse_api_date("not a date"), None);
+    }
+}
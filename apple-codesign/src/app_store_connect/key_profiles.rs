@@ -0,0 +1,287 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Named API Keys in a configuration file.
+//!
+//! Working with several teams means several API Keys. Rather than keeping
+//! track of which file holds which key, [KeyProfiles] reads named profiles
+//! from a TOML file:
+//!
+//! ```toml
+//! default = "acme"
+//!
+//! [profiles.acme]
+//! api_key_path = "keys/acme.json"
+//! output = "json"
+//!
+//! [profiles.example]
+//! issuer_id = "68a9f6c4-3c0a-4c6e-9b5b-3f3a7d1e4c21"
+//! key_id = "DEADBEEF42"
+//! private_key = "MIGTAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBHkwdwIBAQQg..."
+//! ```
+//!
+//! A profile holds either the fields of a [UnifiedApiKey] or the path of a
+//! JSON file holding one, plus defaults of commands using it. Relative paths
+//! are resolved against the directory of the configuration file. `default`
+//! names the profile used when no other API Key is given.
+//!
+//! The file is read from `asconnect/config.toml` in the user configuration
+//! directory by default (see [KeyProfiles::default_path()]).
+//!
+//! This module is available with the `cli` feature.
+
+use {
+    crate::{
+        app_store_connect::{encrypted_key::EncryptedApiKey, UnifiedApiKey},
+        AppleCodesignError,
+    },
+    serde::Deserialize,
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+    zeroize::{Zeroize, Zeroizing},
+};
+
+/// How commands print their results.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable text.
+    #[default]
+    Text,
+
+    /// JSON, as with `--json`.
+    Json,
+}
+
+/// A named API Key and defaults of commands using it.
+#[derive(Clone, Default, Deserialize)]
+pub struct KeyProfile {
+    /// Issuer ID of a team key.
+    #[serde(default)]
+    pub issuer_id: Option<String>,
+
+    /// Key ID of a key given inline.
+    #[serde(default)]
+    pub key_id: Option<String>,
+
+    /// Base64 encoded DER of the private key of a key given inline.
+    ///
+    /// Zeroed on drop.
+    #[serde(default)]
+    private_key: Option<String>,
+
+    /// Path of a [UnifiedApiKey] JSON file, instead of an inline key.
+    #[serde(default)]
+    pub api_key_path: Option<PathBuf>,
+
+    /// How commands print their results.
+    #[serde(default)]
+    pub output: OutputFormat,
+}
+
+impl Drop for KeyProfile {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for KeyProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyProfile")
+            .field("issuer_id", &self.issuer_id)
+            .field("key_id", &self.key_id)
+            .field(
+                "private_key",
+                &self.private_key.as_ref().map(|_| crate::redact::REDACTED),
+            )
+            .field("api_key_path", &self.api_key_path)
+            .field("output", &self.output)
+            .finish()
+    }
+}
+
+impl KeyProfile {
+    /// Resolve the API Key of this profile.
+    ///
+    /// Encrypted key files can't be read without a password. Read them with
+    /// [UnifiedApiKey::from_encrypted_json_path()] instead.
+    pub fn api_key(&self) -> Result<UnifiedApiKey, AppleCodesignError> {
+        match (&self.api_key_path, &self.key_id, &self.private_key) {
+            (Some(path), None, None) => {
+                let data = Zeroizing::new(std::fs::read(path)?);
+
+                if EncryptedApiKey::is_encrypted_json(&data) {
+                    Err(AppleCodesignError::KeyProfiles(format!(
+                        "{} is encrypted and needs a password",
+                        path.display()
+                    )))
+                } else {
+                    UnifiedApiKey::from_json(&*data)
+                }
+            }
+            (None, Some(key_id), Some(private_key)) => Ok(UnifiedApiKey {
+                issuer_id: self.issuer_id.clone(),
+                key_id: key_id.clone(),
+                private_key: private_key.clone(),
+            }),
+            (Some(_), _, _) => Err(AppleCodesignError::KeyProfiles(
+                "api_key_path and an inline key are mutually exclusive".into(),
+            )),
+            _ => Err(AppleCodesignError::KeyProfiles(
+                "either api_key_path or key_id and private_key must be set".into(),
+            )),
+        }
+    }
+}
+
+/// Named API Keys read from a configuration file.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct KeyProfiles {
+    /// Name of the profile used when no other API Key is given.
+    #[serde(default)]
+    pub default: Option<String>,
+
+    /// Profiles by name.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, KeyProfile>,
+}
+
+impl KeyProfiles {
+    /// The default path of the configuration file, if the configuration directory is known.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("asconnect").join("config.toml"))
+    }
+
+    /// Parse profiles from TOML.
+    pub fn from_toml(data: &str) -> Result<Self, AppleCodesignError> {
+        toml::from_str(data).map_err(|e| AppleCodesignError::KeyProfiles(e.to_string()))
+    }
+
+    /// Read profiles from the TOML file at a path.
+    ///
+    /// Relative key paths are resolved against the directory of the file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        let mut profiles = Self::from_toml(&std::fs::read_to_string(path)?)?;
+
+        if let Some(dir) = path.parent() {
+            for profile in profiles.profiles.values_mut() {
+                if let Some(key_path) = &mut profile.api_key_path {
+                    if key_path.is_relative() {
+                        *key_path = dir.join(&key_path);
+                    }
+                }
+            }
+        }
+
+        Ok(profiles)
+    }
+
+    /// Read the file at `path`, or at [Self::default_path()] if it exists.
+    ///
+    /// There are no profiles if neither is present.
+    pub fn load(path: Option<&Path>) -> Result<Self, AppleCodesignError> {
+        match path {
+            Some(path) => Self::from_path(path),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => Self::from_path(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    /// Obtain a profile by name.
+    pub fn get(&self, name: &str) -> Result<&KeyProfile, AppleCodesignError> {
+        self.profiles.get(name).ok_or_else(|| {
+            AppleCodesignError::KeyProfiles(format!(
+                "no profile named {}; known profiles: {}",
+                name,
+                self.profiles
+                    .keys()
+                    .map(|k| k.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })
+    }
+
+    /// The profile named by [Self::default], if any.
+    pub fn default_profile(&self) -> Result<Option<&KeyProfile>, AppleCodesignError> {
+        self.default
+            .as_deref()
+            .map(|name| self.get(name))
+            .transpose()
+    }
+}
+
+impl UnifiedApiKey {
+    /// Construct an instance from a profile of the default configuration file.
+    ///
+    /// See [KeyProfiles].
+    pub fn from_profile(name: &str) -> Result<Self, AppleCodesignError> {
+        KeyProfiles::load(None)?.get(name)?.api_key()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profiles() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let config = td.path().join("config.toml");
+
+        std::fs::create_dir(td.path().join("keys"))?;
+        std::fs::write(
+            td.path().join("keys").join("acme.json"),
+            UnifiedApiKey {
+                issuer_id: Some("ISSUER".into()),
+                key_id: "ACME".into(),
+                private_key: base64::encode(b"not really a key"),
+            }
+            .to_json_string()?,
+        )?;
+        std::fs::write(
+            &config,
+            r#"
+            default = "acme"
+
+            [profiles.acme]
+            api_key_path = "keys/acme.json"
+            output = "json"
+
+            [profiles.example]
+            key_id = "EXAMPLE"
+            private_key = "a2V5"
+
+            [profiles.empty]
+            "#,
+        )?;
+
+        let profiles = KeyProfiles::from_path(&config)?;
+
+        let acme = profiles.default_profile()?.unwrap();
+        assert_eq!(acme.output, OutputFormat::Json);
+        let key = acme.api_key()?;
+        assert_eq!(key.issuer_id(), Some("ISSUER"));
+        assert_eq!(key.key_id(), "ACME");
+
+        let example = profiles.get("example")?;
+        assert_eq!(example.output, OutputFormat::Text);
+        let key = example.api_key()?;
+        assert_eq!(key.issuer_id(), None);
+        assert_eq!(key.key_id(), "EXAMPLE");
+        assert!(!format!("{:?}", example).contains("a2V5"));
+
+        assert!(matches!(
+            profiles.get("empty")?.api_key(),
+            Err(AppleCodesignError::KeyProfiles(_))
+        ));
+        assert!(profiles.get("missing").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Storage of [UnifiedApiKey] in operating system secret stores.
+//!
+//! A [UnifiedApiKey] written as a JSON file relies on file permissions to
+//! protect the private key. [ApiKeyStore] implementations keep the key in the
+//! secret store of the operating system instead:
+//!
+//! * [MacOsKeychain]: a generic password in the login keychain.
+//! * [WindowsCredentialManager]: a generic credential of the current user.
+//! * [SecretService]: the freedesktop Secret Service (GNOME Keyring, KWallet),
+//!   via the `secret-tool` program from libsecret.
+//!
+//! Keys are stored as their JSON representation under [KEY_STORE_SERVICE] and
+//! looked up by key ID. [default_key_store] returns the store of the current
+//! platform.
+
+use crate::{app_store_connect::UnifiedApiKey, AppleCodesignError};
+
+/// Service name keys are stored under.
+pub const KEY_STORE_SERVICE: &str = "rcodesign App Store Connect API Key";
+
+/// A place to keep [UnifiedApiKey] instances.
+pub trait ApiKeyStore {
+    /// Human readable name of the store.
+    fn name(&self) -> &'static str;
+
+    /// Store a key, replacing any key with the same key ID.
+    fn store(&self, key: &UnifiedApiKey) -> Result<(), AppleCodesignError>;
+
+    /// Load the key with a key ID.
+    fn load(&self, key_id: &str) -> Result<UnifiedApiKey, AppleCodesignError>;
+
+    /// Remove the key with a key ID.
+    fn delete(&self, key_id: &str) -> Result<(), AppleCodesignError>;
+}
+
+#[cfg(any(unix, windows))]
+fn not_found(store: &dyn ApiKeyStore, key_id: &str) -> AppleCodesignError {
+    AppleCodesignError::ApiKeyStore(format!("no API key {} in {}", key_id, store.name()))
+}
+
+/// The secret store of the current platform.
+pub fn default_key_store() -> Result<Box<dyn ApiKeyStore>, AppleCodesignError> {
+    #[cfg(target_os = "macos")]
+    {
+        Ok(Box::new(MacOsKeychain))
+    }
+
+    #[cfg(windows)]
+    {
+        Ok(Box::new(WindowsCredentialManager))
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        Ok(Box::new(SecretService))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        Err(AppleCodesignError::ApiKeyStore(
+            "no secret store is supported on this platform".into(),
+        ))
+    }
+}
+
+/// The macOS Keychain.
+#[cfg(target_os = "macos")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MacOsKeychain;
+
+#[cfg(target_os = "macos")]
+impl ApiKeyStore for MacOsKeychain {
+    fn name(&self) -> &'static str {
+        "the macOS Keychain"
+    }
+
+    fn store(&self, key: &UnifiedApiKey) -> Result<(), AppleCodesignError> {
+        let json = zeroize::Zeroizing::new(key.to_json_string()?);
+
+        Ok(security_framework::passwords::set_generic_password(
+            KEY_STORE_SERVICE,
+            key.key_id(),
+            json.as_bytes(),
+        )?)
+    }
+
+    fn load(&self, key_id: &str) -> Result<UnifiedApiKey, AppleCodesignError> {
+        match security_framework::passwords::get_generic_password(KEY_STORE_SERVICE, key_id) {
+            Ok(data) => UnifiedApiKey::from_json(&*zeroize::Zeroizing::new(data)),
+            Err(e) if e.code() == ERR_SEC_ITEM_NOT_FOUND => Err(not_found(self, key_id)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn delete(&self, key_id: &str) -> Result<(), AppleCodesignError> {
+        match security_framework::passwords::delete_generic_password(KEY_STORE_SERVICE, key_id) {
+            Ok(()) => Ok(()),
+            Err(e) if e.code() == ERR_SEC_ITEM_NOT_FOUND => Err(not_found(self, key_id)),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+/// The Windows Credential Manager.
+#[cfg(windows)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WindowsCredentialManager;
+
+#[cfg(windows)]
+impl WindowsCredentialManager {
+    fn target_name(key_id: &str) -> Vec<u16> {
+        format!("{}/{}", KEY_STORE_SERVICE, key_id)
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+
+    fn error(&self, key_id: &str, operation: &str) -> AppleCodesignError {
+        let error = std::io::Error::last_os_error();
+
+        if error.raw_os_error() == Some(ERROR_NOT_FOUND) {
+            not_found(self, key_id)
+        } else {
+            AppleCodesignError::ApiKeyStore(format!(
+                "failed to {} credential in {}: {}",
+                operation,
+                self.name(),
+                error
+            ))
+        }
+    }
+}
+
+#[cfg(windows)]
+const ERROR_NOT_FOUND: i32 = 1168;
+
+#[cfg(windows)]
+impl ApiKeyStore for WindowsCredentialManager {
+    fn name(&self) -> &'static str {
+        "the Windows Credential Manager"
+    }
+
+    fn store(&self, key: &UnifiedApiKey) -> Result<(), AppleCodesignError> {
+        use windows_sys::Win32::Security::Credentials::{
+            CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+        };
+
+        let mut target = Self::target_name(key.key_id());
+        let mut user: Vec<u16> = key
+            .key_id()
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        let mut json = zeroize::Zeroizing::new(key.to_json_string()?.into_bytes());
+
+        let credential = CREDENTIALW {
+            Flags: 0,
+            Type: CRED_TYPE_GENERIC,
+            TargetName: target.as_mut_ptr(),
+            Comment: std::ptr::null_mut(),
+            LastWritten: unsafe { std::mem::zeroed() },
+            CredentialBlobSize: json.len() as u32,
+            CredentialBlob: json.as_mut_ptr(),
+            Persist: CRED_PERSIST_LOCAL_MACHINE,
+            AttributeCount: 0,
+            Attributes: std::ptr::null_mut(),
+            TargetAlias: std::ptr::null_mut(),
+            UserName: user.as_mut_ptr(),
+        };
+
+        // SAFETY: all pointers reference buffers living until the call returns.
+        if unsafe { CredWriteW(&credential, 0) } == 0 {
+            Err(self.error(key.key_id(), "write"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn load(&self, key_id: &str) -> Result<UnifiedApiKey, AppleCodesignError> {
+        use windows_sys::Win32::Security::Credentials::{
+            CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC,
+        };
+
+        let target = Self::target_name(key_id);
+        let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+
+        // SAFETY: on success the credential is allocated by the system and
+        // released with CredFree once its blob is copied.
+        let data = unsafe {
+            if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) == 0 {
+                return Err(self.error(key_id, "read"));
+            }
+
+            let data = zeroize::Zeroizing::new(
+                std::slice::from_raw_parts(
+                    (*credential).CredentialBlob,
+                    (*credential).CredentialBlobSize as usize,
+                )
+                .to_vec(),
+            );
+            CredFree(credential as *const _);
+
+            data
+        };
+
+        UnifiedApiKey::from_json(&*data)
+    }
+
+    fn delete(&self, key_id: &str) -> Result<(), AppleCodesignError> {
+        use windows_sys::Win32::Security::Credentials::{CredDeleteW, CRED_TYPE_GENERIC};
+
+        let target = Self::target_name(key_id);
+
+        // SAFETY: target is a NUL terminated wide string.
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            Err(self.error(key_id, "delete"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The freedesktop Secret Service.
+///
+/// Requires the `secret-tool` program (usually in a `libsecret-tools` or
+/// `libsecret` package) and a running Secret Service provider.
+#[cfg(all(unix, not(target_os = "macos")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SecretService;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl SecretService {
+    fn secret_tool(
+        &self,
+        args: &[&str],
+        key_id: &str,
+        stdin: Option<&[u8]>,
+    ) -> Result<std::process::Output, AppleCodesignError> {
+        use std::{
+            io::Write,
+            process::{Command, Stdio},
+        };
+
+        let mut child = Command::new("secret-tool")
+            .args(args)
+            .args(["service", KEY_STORE_SERVICE, "account", key_id])
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                AppleCodesignError::ApiKeyStore(format!(
+                    "failed to run secret-tool (is libsecret installed?): {}",
+                    e
+                ))
+            })?;
+
+        if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            pipe.write_all(data)?;
+        }
+
+        Ok(child.wait_with_output()?)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl ApiKeyStore for SecretService {
+    fn name(&self) -> &'static str {
+        "the Secret Service"
+    }
+
+    fn store(&self, key: &UnifiedApiKey) -> Result<(), AppleCodesignError> {
+        let json = zeroize::Zeroizing::new(key.to_json_string()?);
+        let label = format!("{} {}", KEY_STORE_SERVICE, key.key_id());
+
+        let output = self.secret_tool(
+            &["store", "--label", &label],
+            key.key_id(),
+            Some(json.as_bytes()),
+        )?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(AppleCodesignError::ApiKeyStore(format!(
+                "secret-tool store failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+
+    fn load(&self, key_id: &str) -> Result<UnifiedApiKey, AppleCodesignError> {
+        let output = self.secret_tool(&["lookup"], key_id, None)?;
+        let data = zeroize::Zeroizing::new(output.stdout);
+
+        // secret-tool exits with 1 and prints nothing for missing items.
+        if data.is_empty() {
+            return Err(not_found(self, key_id));
+        }
+
+        UnifiedApiKey::from_json(&*data)
+    }
+
+    fn delete(&self, key_id: &str) -> Result<(), AppleCodesignError> {
+        // Make the missing case an error, as with the other stores.
+        self.load(key_id)?;
+
+        let output = self.secret_tool(&["clear"], key_id, None)?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(AppleCodesignError::ApiKeyStore(format!(
+                "secret-tool clear failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+}
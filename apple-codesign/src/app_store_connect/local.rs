@@ -0,0 +1,607 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Signing identities and provisioning profiles installed on this machine.
+//!
+//! Signing needs a certificate with its private key (an identity) in the
+//! keychain and a provisioning profile embedding that certificate. Both drift
+//! from the account over time: certificates get revoked or expire, profiles
+//! get regenerated, and new ones are never installed.
+//!
+//! A [LocalInventory] holds the identities and profiles found on this machine.
+//! Identities are read from the macOS keychain and profiles from the
+//! directories Xcode installs them into ([default_profile_dirs()]).
+//! [LocalInventory::reconcile()] cross-references them with the certificates
+//! and profiles of the account and produces an [InventoryReport] of stale and
+//! missing items.
+
+use {
+    crate::{
+        app_store_connect::{
+            certs_api::{Certificate, CertificateType, Profile, ProfileState},
+            ipa_preflight::ProvisioningProfile,
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::{DateTime, Utc},
+    log::warn,
+    serde::Serialize,
+    std::{
+        collections::BTreeSet,
+        path::{Path, PathBuf},
+    },
+    x509_certificate::CapturedX509Certificate,
+};
+
+#[cfg(target_os = "macos")]
+use crate::macos::{keychain_find_code_signing_certificates, KeychainDomain};
+
+/// Directories Xcode and the Apple Configurator install provisioning profiles into.
+pub fn default_profile_dirs() -> Vec<PathBuf> {
+    dirs::home_dir()
+        .map(|home| {
+            vec![
+                home.join("Library/MobileDevice/Provisioning Profiles"),
+                home.join("Library/Developer/Xcode/UserData/Provisioning Profiles"),
+            ]
+        })
+        .unwrap_or_default()
+}
+
+fn sha256_fingerprint(cert: &CapturedX509Certificate) -> Result<String, AppleCodesignError> {
+    Ok(hex::encode(cert.sha256_fingerprint()?.as_ref()))
+}
+
+/// A signing certificate whose private key is available locally.
+#[derive(Clone, Debug)]
+pub struct LocalIdentity {
+    /// Common name of the certificate's subject.
+    pub common_name: String,
+
+    /// Hex encoded SHA-256 fingerprint of the certificate.
+    pub sha256_fingerprint: String,
+
+    /// When the certificate expires.
+    pub expiration_date: DateTime<Utc>,
+}
+
+impl LocalIdentity {
+    /// Describe the identity having a certificate.
+    pub fn from_certificate(cert: &CapturedX509Certificate) -> Result<Self, AppleCodesignError> {
+        Ok(Self {
+            common_name: cert
+                .subject_common_name()
+                .unwrap_or_else(|| "<unknown>".to_string()),
+            sha256_fingerprint: sha256_fingerprint(cert)?,
+            expiration_date: cert.validity_not_after(),
+        })
+    }
+}
+
+/// A provisioning profile file installed locally.
+#[derive(Clone, Debug)]
+pub struct InstalledProfile {
+    pub path: PathBuf,
+    pub profile: ProvisioningProfile,
+}
+
+/// Identities and provisioning profiles found on this machine.
+#[derive(Clone, Debug, Default)]
+pub struct LocalInventory {
+    pub identities: Vec<LocalIdentity>,
+    pub profiles: Vec<InstalledProfile>,
+
+    /// Provisioning profile files that couldn't be parsed.
+    pub unreadable_profiles: Vec<PathBuf>,
+}
+
+impl LocalInventory {
+    /// Scan the user keychain and the default profile directories.
+    #[cfg(target_os = "macos")]
+    pub fn scan() -> Result<Self, AppleCodesignError> {
+        let mut inventory = Self::default();
+
+        inventory.add_keychain_identities(KeychainDomain::User)?;
+        for dir in default_profile_dirs() {
+            inventory.add_profiles_dir(&dir)?;
+        }
+
+        Ok(inventory)
+    }
+
+    /// Add the code signing identities of a keychain.
+    #[cfg(target_os = "macos")]
+    pub fn add_keychain_identities(
+        &mut self,
+        domain: KeychainDomain,
+    ) -> Result<(), AppleCodesignError> {
+        for cert in keychain_find_code_signing_certificates(domain, None)? {
+            self.identities
+                .push(LocalIdentity::from_certificate(&cert)?);
+        }
+
+        Ok(())
+    }
+
+    /// Add the provisioning profiles in a directory.
+    ///
+    /// `.mobileprovision` and `.provisionprofile` files are read. A missing
+    /// directory has no profiles.
+    pub fn add_profiles_dir(&mut self, dir: &Path) -> Result<(), AppleCodesignError> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+
+        let mut paths = std::fs::read_dir(dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+        paths.sort();
+
+        for path in paths {
+            if !matches!(
+                path.extension().and_then(|x| x.to_str()),
+                Some("mobileprovision" | "provisionprofile")
+            ) {
+                continue;
+            }
+
+            match ProvisioningProfile::from_der(&std::fs::read(&path)?) {
+                Ok(profile) => self.profiles.push(InstalledProfile { path, profile }),
+                Err(e) => {
+                    warn!("unable to parse {}: {}", path.display(), e);
+                    self.unreadable_profiles.push(path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Cross-reference the inventory with the certificates and profiles of the account.
+    pub fn reconcile(
+        &self,
+        certificates: &[Certificate],
+        profiles: &[Profile],
+        now: DateTime<Utc>,
+    ) -> Result<InventoryReport, AppleCodesignError> {
+        let mut report = InventoryReport {
+            unreadable_profiles: self
+                .unreadable_profiles
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut account_fingerprints = BTreeSet::new();
+        for cert in certificates {
+            let der = base64::decode(
+                cert.attributes
+                    .certificate_content
+                    .as_deref()
+                    .unwrap_or_default(),
+            )?;
+            let fingerprint = sha256_fingerprint(&CapturedX509Certificate::from_der(der)?)?;
+
+            if !self
+                .identities
+                .iter()
+                .any(|i| i.sha256_fingerprint == fingerprint)
+            {
+                report
+                    .certificates_without_identity
+                    .push(UninstalledCertificate {
+                        id: cert.id.clone(),
+                        name: cert.attributes.name.clone(),
+                        certificate_type: cert.attributes.certificate_type,
+                        expiration_date: cert.attributes.expiration_date.clone(),
+                    });
+            }
+
+            account_fingerprints.insert(fingerprint);
+        }
+
+        let mut usable_fingerprints = BTreeSet::new();
+        for identity in &self.identities {
+            let reported = ReportedIdentity {
+                common_name: identity.common_name.clone(),
+                sha256_fingerprint: identity.sha256_fingerprint.clone(),
+                expiration_date: identity.expiration_date.to_rfc3339(),
+            };
+
+            if identity.expiration_date < now {
+                report.expired_identities.push(reported);
+            } else if !account_fingerprints.contains(&identity.sha256_fingerprint) {
+                report.unknown_identities.push(reported);
+            } else {
+                usable_fingerprints.insert(identity.sha256_fingerprint.as_str());
+            }
+        }
+
+        let installed_uuids = self
+            .profiles
+            .iter()
+            .map(|p| p.profile.uuid.to_lowercase())
+            .collect::<BTreeSet<_>>();
+
+        for installed in &self.profiles {
+            let reported = ReportedProfile {
+                path: installed.path.display().to_string(),
+                name: installed.profile.name.clone(),
+                uuid: installed.profile.uuid.clone(),
+                expiration_date: installed.profile.expiration_date.to_rfc3339(),
+            };
+
+            let account_profile = profiles.iter().find(|p| {
+                p.attributes
+                    .uuid
+                    .as_deref()
+                    .map(|uuid| uuid.eq_ignore_ascii_case(&installed.profile.uuid))
+                    .unwrap_or_default()
+            });
+
+            if installed.profile.expiration_date < now {
+                report.expired_profiles.push(reported);
+                continue;
+            }
+
+            let account_profile = match account_profile {
+                Some(p) => p,
+                None => {
+                    report.unknown_profiles.push(reported);
+                    continue;
+                }
+            };
+
+            if account_profile.attributes.profile_state != Some(ProfileState::Active) {
+                report.invalid_profiles.push(reported);
+                continue;
+            }
+
+            let usable = installed
+                .profile
+                .developer_certificates
+                .iter()
+                .map(sha256_fingerprint)
+                .collect::<Result<Vec<_>, _>>()?
+                .iter()
+                .any(|fingerprint| usable_fingerprints.contains(fingerprint.as_str()));
+
+            if !usable {
+                report.profiles_without_identity.push(reported);
+            }
+        }
+
+        for profile in profiles {
+            if profile.attributes.profile_state != Some(ProfileState::Active) {
+                continue;
+            }
+
+            let installed = profile
+                .attributes
+                .uuid
+                .as_deref()
+                .map(|uuid| installed_uuids.contains(&uuid.to_lowercase()))
+                .unwrap_or_default();
+
+            if !installed {
+                report.uninstalled_profiles.push(UninstalledProfile {
+                    id: profile.id.clone(),
+                    name: profile.attributes.name.clone(),
+                    uuid: profile.attributes.uuid.clone(),
+                });
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// A local identity reported by an [InventoryReport].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedIdentity {
+    pub common_name: String,
+    pub sha256_fingerprint: String,
+    pub expiration_date: String,
+}
+
+/// An installed provisioning profile reported by an [InventoryReport].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedProfile {
+    pub path: String,
+    pub name: String,
+    pub uuid: String,
+    pub expiration_date: String,
+}
+
+/// A certificate of the account without a local identity.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstalledCertificate {
+    pub id: String,
+    pub name: Option<String>,
+    pub certificate_type: Option<CertificateType>,
+    pub expiration_date: Option<String>,
+}
+
+/// An active profile of the account that isn't installed.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstalledProfile {
+    pub id: String,
+    pub name: Option<String>,
+    pub uuid: Option<String>,
+}
+
+/// Differences between the local inventory and the account.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryReport {
+    /// Identities whose certificate has expired.
+    pub expired_identities: Vec<ReportedIdentity>,
+
+    /// Identities whose certificate isn't in the account.
+    ///
+    /// The certificate was revoked or belongs to another team.
+    pub unknown_identities: Vec<ReportedIdentity>,
+
+    /// Certificates of the account whose private key isn't available locally.
+    pub certificates_without_identity: Vec<UninstalledCertificate>,
+
+    /// Installed profiles that have expired.
+    pub expired_profiles: Vec<ReportedProfile>,
+
+    /// Installed profiles that aren't in the account, e.g. because they were
+    /// regenerated or deleted.
+    pub unknown_profiles: Vec<ReportedProfile>,
+
+    /// Installed profiles the account reports as invalid.
+    pub invalid_profiles: Vec<ReportedProfile>,
+
+    /// Installed profiles embedding no certificate of a usable local identity.
+    pub profiles_without_identity: Vec<ReportedProfile>,
+
+    /// Active profiles of the account that aren't installed.
+    pub uninstalled_profiles: Vec<UninstalledProfile>,
+
+    pub unreadable_profiles: Vec<String>,
+}
+
+impl InventoryReport {
+    /// Whether no installed item is stale.
+    ///
+    /// Items of the account missing locally aren't considered, since a machine
+    /// usually only signs with some of them.
+    pub fn is_clean(&self) -> bool {
+        self.expired_identities.is_empty()
+            && self.unknown_identities.is_empty()
+            && self.expired_profiles.is_empty()
+            && self.unknown_profiles.is_empty()
+            && self.invalid_profiles.is_empty()
+            && self.profiles_without_identity.is_empty()
+            && self.unreadable_profiles.is_empty()
+    }
+
+    /// Render the report as human readable lines of text.
+    pub fn to_text_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        for (label, identities) in [
+            ("expired identities", &self.expired_identities),
+            ("identities not in the account", &self.unknown_identities),
+        ] {
+            lines.push(format!("{}: {}", label, identities.len()));
+            for i in identities {
+                lines.push(format!(
+                    "  {} ({}) expires {}",
+                    i.common_name, i.sha256_fingerprint, i.expiration_date
+                ));
+            }
+        }
+
+        lines.push(format!(
+            "certificates without local identity: {}",
+            self.certificates_without_identity.len()
+        ));
+        for c in &self.certificates_without_identity {
+            lines.push(format!(
+                "  {} ({}) expires {}",
+                c.name.as_deref().unwrap_or_default(),
+                c.id,
+                c.expiration_date.as_deref().unwrap_or("unknown")
+            ));
+        }
+
+        for (label, profiles) in [
+            ("expired profiles", &self.expired_profiles),
+            ("profiles not in the account", &self.unknown_profiles),
+            ("invalid profiles", &self.invalid_profiles),
+            (
+                "profiles without local identity",
+                &self.profiles_without_identity,
+            ),
+        ] {
+            lines.push(format!("{}: {}", label, profiles.len()));
+            for p in profiles {
+                lines.push(format!("  {} ({}) at {}", p.name, p.uuid, p.path));
+            }
+        }
+
+        lines.push(format!(
+            "profiles not installed: {}",
+            self.uninstalled_profiles.len()
+        ));
+        for p in &self.uninstalled_profiles {
+            lines.push(format!(
+                "  {} ({}) {}",
+                p.name.as_deref().unwrap_or_default(),
+                p.id,
+                p.uuid.as_deref().unwrap_or_default()
+            ));
+        }
+
+        lines.push(format!(
+            "unreadable profiles: {}",
+            self.unreadable_profiles.len()
+        ));
+        for path in &self.unreadable_profiles {
+            lines.push(format!("  {}", path));
+        }
+
+        lines
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Cross-reference a local inventory with the account.
+    pub fn reconcile_local_inventory(
+        &self,
+        inventory: &LocalInventory,
+    ) -> Result<InventoryReport, AppleCodesignError> {
+        warn!("listing certificates");
+        let certificates = self.list_certificates()?;
+        warn!("listing provisioning profiles");
+        let profiles = self.list_profiles()?;
+
+        inventory.reconcile(&certificates, &profiles, Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            app_store_connect::testing::{fixtures, MockServerBuilder},
+            certificate::{create_self_signed_code_signing_certificate, CertificateProfile},
+        },
+        serde_json::json,
+        x509_certificate::{EcdsaCurve, KeyAlgorithm},
+    };
+
+    fn installed(name: &str, uuid: &str, cert: &CapturedX509Certificate) -> InstalledProfile {
+        InstalledProfile {
+            path: PathBuf::from(format!("{}.mobileprovision", name)),
+            profile: ProvisioningProfile {
+                name: name.to_string(),
+                uuid: uuid.to_string(),
+                team_identifiers: vec!["ABCDE12345".into()],
+                expiration_date: Utc::now() + chrono::Duration::days(30),
+                provisioned_devices: vec![],
+                provisions_all_devices: false,
+                entitlements: Default::default(),
+                developer_certificates: vec![cert.clone()],
+            },
+        }
+    }
+
+    #[test]
+    fn reconcile() -> Result<(), AppleCodesignError> {
+        let mut certs = vec![];
+        for name in ["Account", "Other", "Revoked"] {
+            let (cert, _, _) = create_self_signed_code_signing_certificate(
+                KeyAlgorithm::Ecdsa(EcdsaCurve::Secp256r1),
+                CertificateProfile::AppleDistribution,
+                "ABCDE12345",
+                name,
+                "US",
+                chrono::Duration::days(30),
+            )?;
+            certs.push(cert);
+        }
+        let (account, other, revoked) = (&certs[0], &certs[1], &certs[2]);
+
+        let inventory = LocalInventory {
+            identities: vec![
+                LocalIdentity::from_certificate(account)?,
+                LocalIdentity::from_certificate(revoked)?,
+            ],
+            profiles: vec![
+                installed("Good", "UUID-GOOD", account),
+                installed("Deleted", "UUID-DELETED", account),
+                installed("Orphaned", "UUID-ORPHANED", revoked),
+                installed("Invalid", "UUID-INVALID", account),
+            ],
+            unreadable_profiles: vec![],
+        };
+
+        let mut invalid = fixtures::profile("P3", "Invalid", "IOS_APP_STORE", "");
+        invalid["attributes"]["profileState"] = json!("INVALID");
+        invalid["attributes"]["uuid"] = json!("uuid-invalid");
+        let mut good = fixtures::profile("P1", "Good", "IOS_APP_STORE", "");
+        good["attributes"]["uuid"] = json!("uuid-good");
+        let mut orphaned = fixtures::profile("P2", "Orphaned", "IOS_APP_STORE", "");
+        orphaned["attributes"]["uuid"] = json!("uuid-orphaned");
+        let mut new = fixtures::profile("P4", "New", "IOS_APP_STORE", "");
+        new["attributes"]["uuid"] = json!("uuid-new");
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/certificates",
+                200,
+                fixtures::collection(vec![
+                    fixtures::certificate(
+                        "C1",
+                        "DISTRIBUTION",
+                        &base64::encode(account.constructed_data()),
+                    ),
+                    fixtures::certificate(
+                        "C2",
+                        "DISTRIBUTION",
+                        &base64::encode(other.constructed_data()),
+                    ),
+                ]),
+            )
+            .json(
+                "GET",
+                "/v1/profiles",
+                200,
+                fixtures::collection(vec![good, orphaned, invalid, new]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let report = client.reconcile_local_inventory(&inventory)?;
+
+        assert!(report.expired_identities.is_empty());
+        assert_eq!(report.unknown_identities.len(), 1);
+        assert_eq!(report.unknown_identities[0].common_name, "Revoked");
+        assert_eq!(
+            report
+                .certificates_without_identity
+                .iter()
+                .map(|c| c.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["C2"]
+        );
+        assert!(report.expired_profiles.is_empty());
+        assert_eq!(report.unknown_profiles.len(), 1);
+        assert_eq!(report.unknown_profiles[0].name, "Deleted");
+        assert_eq!(report.invalid_profiles.len(), 1);
+        assert_eq!(report.profiles_without_identity.len(), 1);
+        assert_eq!(report.profiles_without_identity[0].name, "Orphaned");
+        assert_eq!(
+            report
+                .uninstalled_profiles
+                .iter()
+                .map(|p| p.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["P4"]
+        );
+        assert!(!report.is_clean());
+
+        // Expiry takes precedence over the state in the account.
+        let later = Utc::now() + chrono::Duration::days(60);
+        let report = inventory.reconcile(&[], &[], later)?;
+        assert_eq!(report.expired_identities.len(), 2);
+        assert_eq!(report.expired_profiles.len(), 4);
+        assert!(report.unknown_profiles.is_empty());
+
+        Ok(())
+    }
+}
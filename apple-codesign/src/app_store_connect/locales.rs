@@ -0,0 +1,283 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Locales accepted by App Store Connect.
+//!
+//! Localized resources are keyed by a locale code. The server rejects codes it
+//! doesn't know about, but only after other work (such as uploading a build)
+//! may have been performed. The functions in this module validate locale codes
+//! locally so mistakes are caught before any network calls, and suggest the
+//! intended locale for near misses (e.g. `pt_br` or `pt-PR` for `pt-BR`).
+//!
+//! Listings in many locales are rarely complete: translators handle the
+//! description while URLs and contact addresses are only maintained for one
+//! locale. [apply_locale_fallback()] fills the fields a localization lacks
+//! from a fallback locale and reports each [InheritedField].
+
+use {
+    crate::AppleCodesignError,
+    serde::{de::DeserializeOwned, Serialize},
+    serde_json::Value,
+    std::collections::BTreeMap,
+};
+
+/// Locales accepted for App Store and TestFlight metadata.
+pub const APP_STORE_LOCALES: &[&str] = &[
+    "ar-SA", "ca", "cs", "da", "de-DE", "el", "en-AU", "en-CA", "en-GB", "en-US", "es-ES", "es-MX",
+    "fi", "fr-CA", "fr-FR", "he", "hi", "hr", "hu", "id", "it", "ja", "ko", "ms", "nl-NL", "no",
+    "pl", "pt-BR", "pt-PT", "ro", "ru", "sk", "sv", "th", "tr", "uk", "vi", "zh-Hans", "zh-Hant",
+];
+
+/// A type of localized resource.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LocalizedResource {
+    /// Localized App Store information of an app.
+    AppInfo,
+    /// Localized App Store information of an App Store version.
+    AppStoreVersion,
+    /// Localized TestFlight information of an app.
+    BetaApp,
+    /// Localized TestFlight information of a build (*What to Test*).
+    BetaBuild,
+}
+
+impl LocalizedResource {
+    /// The locale codes accepted for this resource type.
+    pub fn accepted_locales(&self) -> &'static [&'static str] {
+        match self {
+            Self::AppInfo | Self::AppStoreVersion | Self::BetaApp | Self::BetaBuild => {
+                APP_STORE_LOCALES
+            }
+        }
+    }
+
+    /// Validate a locale code for this resource type.
+    ///
+    /// The error suggests the closest accepted locale, if there is one.
+    pub fn validate_locale(&self, locale: &str) -> Result<(), AppleCodesignError> {
+        let accepted = self.accepted_locales();
+
+        if accepted.contains(&locale) {
+            return Ok(());
+        }
+
+        let hint = match suggest_locale(accepted, locale) {
+            Some(suggestion) => format!("; did you mean {}?", suggestion),
+            None => "".to_string(),
+        };
+
+        Err(AppleCodesignError::InvalidLocale(locale.to_string(), hint))
+    }
+
+    /// Validate multiple locale codes for this resource type.
+    pub fn validate_locales<'a>(
+        &self,
+        locales: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), AppleCodesignError> {
+        for locale in locales {
+            self.validate_locale(locale)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn normalize(locale: &str) -> String {
+    locale.replace('_', "-").to_ascii_lowercase()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Find the accepted locale a user most likely meant.
+///
+/// Case and `_` versus `-` differences are ignored. A bare language code
+/// (e.g. `de`) matches the first accepted locale for that language. Otherwise
+/// the closest locale within a small edit distance is returned.
+pub fn suggest_locale(accepted: &[&'static str], locale: &str) -> Option<&'static str> {
+    let wanted = normalize(locale);
+
+    if let Some(exact) = accepted.iter().find(|l| normalize(l) == wanted) {
+        return Some(exact);
+    }
+
+    if let Some(language) = accepted
+        .iter()
+        .find(|l| normalize(l).split('-').next() == Some(wanted.as_str()))
+    {
+        return Some(language);
+    }
+
+    accepted
+        .iter()
+        .map(|l| (edit_distance(&normalize(l), &wanted), *l))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, l)| l)
+}
+
+/// A field of a localization filled from the fallback locale.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InheritedField {
+    pub locale: String,
+
+    /// Name of the field as the API spells it, e.g. `feedbackEmail`.
+    pub field: String,
+
+    pub from_locale: String,
+}
+
+/// Fill fields missing from localizations with the values of a fallback locale.
+///
+/// Fields are missing if they are absent, null, or empty strings. The
+/// `locale` field is never inherited. Returns the inherited fields, ordered
+/// by locale.
+pub fn apply_locale_fallback<T: Serialize + DeserializeOwned>(
+    localizations: &mut BTreeMap<String, T>,
+    fallback_locale: &str,
+) -> Result<Vec<InheritedField>, AppleCodesignError> {
+    let fallback = match localizations.get(fallback_locale) {
+        Some(fallback) => serde_json::to_value(fallback)?,
+        None => {
+            return Err(AppleCodesignError::LocaleFallback(
+                fallback_locale.to_string(),
+            ))
+        }
+    };
+    let fallback = fallback.as_object().cloned().unwrap_or_default();
+
+    let mut inherited = vec![];
+
+    for (locale, localization) in localizations.iter_mut() {
+        if locale == fallback_locale {
+            continue;
+        }
+
+        let mut value = serde_json::to_value(&*localization)?;
+        let object = match value.as_object_mut() {
+            Some(object) => object,
+            None => continue,
+        };
+
+        let missing = |v: Option<&Value>| match v {
+            None | Some(Value::Null) => true,
+            Some(Value::String(s)) => s.is_empty(),
+            Some(_) => false,
+        };
+
+        let mut changed = false;
+        for (field, fallback_value) in &fallback {
+            if field == "locale" || missing(Some(fallback_value)) || !missing(object.get(field)) {
+                continue;
+            }
+
+            object.insert(field.clone(), fallback_value.clone());
+            inherited.push(InheritedField {
+                locale: locale.clone(),
+                field: field.clone(),
+                from_locale: fallback_locale.to_string(),
+            });
+            changed = true;
+        }
+
+        if changed {
+            *localization = serde_json::from_value(value)?;
+        }
+    }
+
+    Ok(inherited)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        let resource = LocalizedResource::BetaApp;
+
+        assert!(resource.validate_locale("en-US").is_ok());
+        assert!(resource.validate_locales(["ja", "zh-Hans"]).is_ok());
+        assert!(resource.validate_locale("xx-YY").is_err());
+    }
+
+    #[test]
+    fn suggest() {
+        assert_eq!(suggest_locale(APP_STORE_LOCALES, "pt_br"), Some("pt-BR"));
+        assert_eq!(suggest_locale(APP_STORE_LOCALES, "EN-us"), Some("en-US"));
+        assert_eq!(suggest_locale(APP_STORE_LOCALES, "pt-PR"), Some("pt-BR"));
+        assert_eq!(suggest_locale(APP_STORE_LOCALES, "de"), Some("de-DE"));
+        assert_eq!(suggest_locale(APP_STORE_LOCALES, "klingon"), None);
+
+        match LocalizedResource::BetaApp.validate_locale("pt_BR") {
+            Err(AppleCodesignError::InvalidLocale(locale, hint)) => {
+                assert_eq!(locale, "pt_BR");
+                assert_eq!(hint, "; did you mean pt-BR?");
+            }
+            _ => panic!("expected InvalidLocale error"),
+        }
+    }
+
+    #[test]
+    fn fallback() -> Result<(), AppleCodesignError> {
+        let mut localizations = BTreeMap::from([
+            (
+                "en-US".to_string(),
+                serde_json::json!({"description": "My app", "marketingUrl": "https://example.com"}),
+            ),
+            (
+                "de-DE".to_string(),
+                serde_json::json!({"description": "Meine App", "marketingUrl": ""}),
+            ),
+            ("ja".to_string(), serde_json::json!({"locale": "ja"})),
+        ]);
+
+        let inherited = apply_locale_fallback(&mut localizations, "en-US")?;
+
+        assert_eq!(
+            inherited
+                .iter()
+                .map(|f| (f.locale.as_str(), f.field.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("de-DE", "marketingUrl"),
+                ("ja", "description"),
+                ("ja", "marketingUrl")
+            ]
+        );
+        assert_eq!(localizations["de-DE"]["description"], "Meine App");
+        assert_eq!(
+            localizations["de-DE"]["marketingUrl"],
+            "https://example.com"
+        );
+        assert_eq!(localizations["ja"]["locale"], "ja");
+
+        assert!(matches!(
+            apply_locale_fallback(&mut localizations, "fr-FR"),
+            Err(AppleCodesignError::LocaleFallback(_))
+        ));
+
+        Ok(())
+    }
+}
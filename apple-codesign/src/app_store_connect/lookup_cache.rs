@@ -0,0 +1,211 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! In-process memoization of identifier lookups.
+//!
+//! Composite operations resolve the same bundle identifier to its bundle ID
+//! resource and the app having it several times. The identifier of a bundle ID
+//! and the bundle identifier of an app can't be changed, so [LookupCache]
+//! keeps resources found by [AppStoreConnectClient::find_bundle_id()],
+//! [AppStoreConnectClient::get_bundle_id()],
+//! [AppStoreConnectClient::find_app_by_bundle_id()], and
+//! [AppStoreConnectClient::get_app()] for the lifetime of the client.
+//!
+//! The team of the API key (see [AppStoreConnectClient::team_info()]) is kept
+//! too.
+//!
+//! Other attributes of cached resources, like names, may be stale. Lookups
+//! finding nothing aren't cached, so newly registered resources are found.
+//! Operations changing or deleting resources should invalidate them; see
+//! [AppStoreConnectClient::lookup_cache()].
+
+use {
+    crate::{
+        app_store_connect::{
+            app_api::{App, AppAttributes},
+            json_api::Resource,
+            team::TeamInfo,
+        },
+        AppleCodesignError,
+    },
+    asconnect_types::bundles::{BundleId, BundleIdAttributes},
+    std::{collections::HashMap, sync::Mutex},
+};
+
+/// Resources keyed by their immutable identifier.
+type Memo<A> = Mutex<HashMap<String, Resource<A>>>;
+
+/// Look up a resource by key, calling a function on a miss.
+fn get_or_find<A: Clone>(
+    memo: &Memo<A>,
+    key: &str,
+    key_of: impl Fn(&Resource<A>) -> Option<&String>,
+    find: impl FnOnce() -> Result<Option<Resource<A>>, AppleCodesignError>,
+) -> Result<Option<Resource<A>>, AppleCodesignError> {
+    if let Some(resource) = memo.lock().unwrap().get(key) {
+        return Ok(Some(resource.clone()));
+    }
+
+    let resource = find()?;
+
+    if let Some(resource) = &resource {
+        if let Some(key) = key_of(resource) {
+            memo.lock().unwrap().insert(key.clone(), resource.clone());
+        }
+    }
+
+    Ok(resource)
+}
+
+/// Look up a resource by ID, calling a function on a miss.
+fn get_or_fetch<A: Clone>(
+    memo: &Memo<A>,
+    id: &str,
+    key_of: impl Fn(&Resource<A>) -> Option<&String>,
+    fetch: impl FnOnce() -> Result<Resource<A>, AppleCodesignError>,
+) -> Result<Resource<A>, AppleCodesignError> {
+    if let Some(resource) = memo.lock().unwrap().values().find(|r| r.id == id) {
+        return Ok(resource.clone());
+    }
+
+    let resource = fetch()?;
+
+    if let Some(key) = key_of(&resource) {
+        memo.lock().unwrap().insert(key.clone(), resource.clone());
+    }
+
+    Ok(resource)
+}
+
+/// Memoized bundle ID and app lookups.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Default)]
+pub struct LookupCache {
+    /// Bundle IDs by identifier.
+    bundle_ids: Memo<BundleIdAttributes>,
+    /// Apps by bundle identifier.
+    apps: Memo<AppAttributes>,
+    /// The team of the API key.
+    team: Mutex<Option<TeamInfo>>,
+}
+
+impl LookupCache {
+    pub(crate) fn bundle_id_by_identifier(
+        &self,
+        identifier: &str,
+        find: impl FnOnce() -> Result<Option<BundleId>, AppleCodesignError>,
+    ) -> Result<Option<BundleId>, AppleCodesignError> {
+        get_or_find(
+            &self.bundle_ids,
+            identifier,
+            |b| b.attributes.identifier.as_ref(),
+            find,
+        )
+    }
+
+    pub(crate) fn bundle_id_by_id(
+        &self,
+        id: &str,
+        fetch: impl FnOnce() -> Result<BundleId, AppleCodesignError>,
+    ) -> Result<BundleId, AppleCodesignError> {
+        get_or_fetch(
+            &self.bundle_ids,
+            id,
+            |b| b.attributes.identifier.as_ref(),
+            fetch,
+        )
+    }
+
+    pub(crate) fn app_by_bundle_identifier(
+        &self,
+        bundle_identifier: &str,
+        find: impl FnOnce() -> Result<Option<App>, AppleCodesignError>,
+    ) -> Result<Option<App>, AppleCodesignError> {
+        get_or_find(
+            &self.apps,
+            bundle_identifier,
+            |app| app.attributes.bundle_id.as_ref(),
+            find,
+        )
+    }
+
+    pub(crate) fn app_by_id(
+        &self,
+        id: &str,
+        fetch: impl FnOnce() -> Result<App, AppleCodesignError>,
+    ) -> Result<App, AppleCodesignError> {
+        get_or_fetch(
+            &self.apps,
+            id,
+            |app| app.attributes.bundle_id.as_ref(),
+            fetch,
+        )
+    }
+
+    pub(crate) fn team(&self) -> Option<TeamInfo> {
+        self.team.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_team(&self, team: TeamInfo) {
+        self.team.lock().unwrap().replace(team);
+    }
+
+    /// Forget the bundle ID having an identifier.
+    pub fn invalidate_bundle_id(&self, identifier: &str) {
+        self.bundle_ids.lock().unwrap().remove(identifier);
+    }
+
+    /// Forget the app having a bundle identifier.
+    pub fn invalidate_app(&self, bundle_identifier: &str) {
+        self.apps.lock().unwrap().remove(bundle_identifier);
+    }
+
+    /// Forget all cached resources.
+    pub fn clear(&self) {
+        self.bundle_ids.lock().unwrap().clear();
+        self.apps.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn app_lookups() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                200,
+                fixtures::collection(vec![fixtures::app("A1", "com.example.app")]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let app = client.find_app_by_bundle_id("com.example.app")?;
+        assert_eq!(app.map(|app| app.id), Some("A1".to_string()));
+        assert_eq!(
+            client.find_app_by_bundle_id("com.example.app")?.unwrap().id,
+            "A1"
+        );
+        assert_eq!(client.get_app("A1")?.id, "A1");
+        assert_eq!(server.requests().len(), 1);
+
+        // Apps not found are looked up again.
+        assert!(client.find_app_by_bundle_id("com.example.other")?.is_none());
+        assert!(client.find_app_by_bundle_id("com.example.other")?.is_none());
+        assert_eq!(server.requests().len(), 3);
+
+        client.lookup_cache().invalidate_app("com.example.app");
+        client.find_app_by_bundle_id("com.example.app")?;
+        assert_eq!(server.requests().len(), 4);
+
+        Ok(())
+    }
+}
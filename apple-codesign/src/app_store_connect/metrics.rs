@@ -0,0 +1,185 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Metrics about App Store Connect API usage and account health.
+//!
+//! Metrics are emitted to a [MetricsRecorder], which is pluggable so metrics
+//! can be forwarded to whatever system a deployment uses. [PrometheusRecorder]
+//! retains the latest values in memory and renders them in the Prometheus text
+//! exposition format, which is suitable for the node exporter's textfile
+//! collector or for serving from an HTTP endpoint.
+//!
+//! The following metrics are emitted:
+//!
+//! * `app_store_connect_api_requests_total{status}` (counter)
+//! * `app_store_connect_api_errors_total` (counter)
+//! * `app_store_connect_rate_limit_remaining` (gauge)
+//! * `app_store_connect_certificate_expiry_days{id,name}` (gauge)
+//! * `app_store_connect_invalid_profiles` (gauge)
+//! * `app_store_connect_stuck_builds` (gauge)
+//! * `app_store_connect_pending_review_submissions` (gauge)
+//! * `app_store_connect_notarization_failures` (gauge)
+
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex};
+
+/// Labels attached to a metric sample.
+pub type Labels<'a> = &'a [(&'a str, &'a str)];
+
+/// Receives metric samples.
+pub trait MetricsRecorder: Send + Sync {
+    /// Set a gauge to a value.
+    fn set_gauge(&self, name: &str, labels: Labels, value: f64);
+
+    /// Increment a counter.
+    fn increment_counter(&self, name: &str, labels: Labels, value: u64);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+/// Samples of a metric, keyed by their rendered labels.
+#[derive(Debug)]
+struct MetricFamily {
+    kind: MetricKind,
+    samples: BTreeMap<String, f64>,
+}
+
+impl MetricFamily {
+    fn new(kind: MetricKind) -> Self {
+        Self {
+            kind,
+            samples: BTreeMap::new(),
+        }
+    }
+}
+
+/// A [MetricsRecorder] rendering metrics in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct PrometheusRecorder {
+    metrics: Mutex<BTreeMap<String, MetricFamily>>,
+}
+
+impl PrometheusRecorder {
+    fn format_labels(labels: Labels) -> String {
+        if labels.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "{{{}}}",
+                labels
+                    .iter()
+                    .map(|(k, v)| format!(
+                        "{}=\"{}\"",
+                        k,
+                        v.replace('\\', "\\\\")
+                            .replace('"', "\\\"")
+                            .replace('\n', "\\n")
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )
+        }
+    }
+
+    /// Render all recorded metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let mut s = String::new();
+
+        for (name, family) in metrics.iter() {
+            let kind = match family.kind {
+                MetricKind::Counter => "counter",
+                MetricKind::Gauge => "gauge",
+            };
+
+            writeln!(s, "# TYPE {} {}", name, kind).unwrap();
+            for (labels, value) in &family.samples {
+                writeln!(s, "{}{} {}", name, labels, value).unwrap();
+            }
+        }
+
+        s
+    }
+}
+
+impl MetricsRecorder for PrometheusRecorder {
+    fn set_gauge(&self, name: &str, labels: Labels, value: f64) {
+        let mut metrics = self.metrics.lock().unwrap();
+
+        metrics
+            .entry(name.to_string())
+            .or_insert_with(|| MetricFamily::new(MetricKind::Gauge))
+            .samples
+            .insert(Self::format_labels(labels), value);
+    }
+
+    fn increment_counter(&self, name: &str, labels: Labels, value: u64) {
+        let mut metrics = self.metrics.lock().unwrap();
+
+        *metrics
+            .entry(name.to_string())
+            .or_insert_with(|| MetricFamily::new(MetricKind::Counter))
+            .samples
+            .entry(Self::format_labels(labels))
+            .or_default() += value as f64;
+    }
+}
+
+/// Parse the `X-Rate-Limit` response header.
+///
+/// The header has the form `user-hour-lim:3500;user-hour-rem:3499;`. Returns
+/// the limit and the remaining requests.
+pub fn parse_rate_limit_header(value: &str) -> Option<(u64, u64)> {
+    let mut limit = None;
+    let mut remaining = None;
+
+    for part in value.split(';') {
+        if let Some((key, value)) = part.trim().split_once(':') {
+            match key {
+                "user-hour-lim" => limit = value.parse().ok(),
+                "user-hour-rem" => remaining = value.parse().ok(),
+                _ => {}
+            }
+        }
+    }
+
+    Some((limit?, remaining?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prometheus_render() {
+        let recorder = PrometheusRecorder::default();
+
+        recorder.increment_counter("requests_total", &[("status", "200")], 1);
+        recorder.increment_counter("requests_total", &[("status", "200")], 2);
+        recorder.set_gauge("expiry_days", &[("name", "My \"Cert\"")], 12.0);
+        recorder.set_gauge("invalid", &[], 0.0);
+
+        assert_eq!(
+            recorder.render(),
+            "# TYPE expiry_days gauge\n\
+            expiry_days{name=\"My \\\"Cert\\\"\"} 12\n\
+            # TYPE invalid gauge\n\
+            invalid 0\n\
+            # TYPE requests_total counter\n\
+            requests_total{status=\"200\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn rate_limit_header() {
+        assert_eq!(
+            parse_rate_limit_header("user-hour-lim:3500;user-hour-rem:3499;"),
+            Some((3500, 3499))
+        );
+        assert_eq!(parse_rate_limit_header("garbage"), None);
+    }
+}
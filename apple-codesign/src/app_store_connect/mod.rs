@@ -2,22 +2,192 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod api_error;
+pub mod api_keys;
 pub mod api_token;
+pub mod api_versions;
+pub mod app_api;
+pub mod artifact_signing;
+#[cfg(feature = "asc-builds")]
+pub mod asset_upload;
+#[cfg(feature = "asc-provisioning")]
+pub mod assets_dir;
+#[cfg(feature = "async")]
+pub mod r#async;
+pub mod bandwidth;
+pub mod batch;
+#[cfg(feature = "asc-builds")]
+pub mod build_api;
+#[cfg(feature = "asc-builds")]
+pub mod build_upload_api;
+#[cfg(feature = "asc-provisioning")]
+pub mod bundle_api;
+pub mod cache_scope;
+#[cfg(feature = "asc-provisioning")]
+pub mod calendar;
+#[cfg(feature = "asc-provisioning")]
+pub mod certs_api;
+pub mod credentials;
+pub mod deep_links;
+#[cfg(feature = "asc-provisioning")]
+pub mod dependencies;
+pub mod deprecation;
+#[cfg(feature = "asc-provisioning")]
+pub mod device_models;
+pub mod encrypted_key;
+pub mod environment;
+pub mod export;
+pub mod http_capture;
+pub mod idempotency;
+#[cfg(feature = "asc-provisioning")]
+pub mod inspect;
+#[cfg(feature = "asc-provisioning")]
+pub mod installer;
+#[cfg(any(feature = "asc-builds", feature = "asc-provisioning"))]
+pub mod ipa_preflight;
+pub mod json_api;
+#[cfg(feature = "cli")]
+pub mod key_profiles;
+pub mod key_store;
+#[cfg(feature = "asc-provisioning")]
+pub mod local;
+pub mod locales;
+pub mod lookup_cache;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod notary_api;
+pub mod notify;
+pub mod orchestrate;
+#[cfg(feature = "asc-testflight")]
+pub mod pipeline;
+#[cfg(feature = "asc-testflight")]
+pub mod preview;
+pub mod pricing_api;
+pub mod progress;
+#[cfg(feature = "asc-builds")]
+pub mod provenance;
+#[cfg(all(feature = "asc-builds", feature = "asc-provisioning"))]
+pub mod queue;
+pub mod relationships;
+#[cfg(feature = "asc-testflight")]
+pub mod review_api;
+#[cfg(feature = "cli")]
+pub mod routing;
+#[cfg(all(feature = "asc-builds", feature = "asc-provisioning"))]
+pub mod rpc;
+#[cfg(feature = "asc-builds")]
+pub mod schedule;
+pub mod schema_drift;
+#[cfg(feature = "asc-provisioning")]
+pub mod search;
+pub mod smoke_test;
+pub mod snapshot;
+#[cfg(all(feature = "asc-provisioning", feature = "asc-testflight"))]
+pub mod status;
+pub mod subscriptions_api;
+#[cfg(feature = "cli")]
+pub mod tabular;
+pub mod team;
+#[cfg(feature = "asc-testflight")]
+pub mod testflight_api;
+pub mod testing;
+pub mod token_cache;
+#[cfg(feature = "async")]
+pub mod token_client;
+#[cfg(feature = "asc-testflight")]
+pub mod train;
+pub mod users_api;
+#[cfg(all(
+    test,
+    feature = "asc-builds",
+    feature = "asc-provisioning",
+    feature = "asc-testflight"
+))]
+mod wire_format;
 
 use {
-    self::api_token::{AppStoreConnectToken, ConnectTokenEncoder},
-    crate::AppleCodesignError,
-    log::{debug, error},
-    reqwest::blocking::Client,
+    self::{
+        api_error::ApiError,
+        api_token::{AppStoreConnectToken, ConnectTokenEncoder},
+        api_versions::ApiVersions,
+        bandwidth::{BandwidthLimit, TokenBucket},
+        cache_scope::CacheScope,
+        environment::ApiEnvironment,
+        http_capture::{HttpCapture, PendingExchange},
+        json_api::{diff_attributes, Document, ErrorDocument, RequestDocument, Resource, Updated},
+        lookup_cache::LookupCache,
+        notify::NotificationSink,
+        progress::ProgressSink,
+        schema_drift::SchemaDrift,
+        snapshot::AccountSnapshot,
+        token_cache::TokenCache,
+    },
+    crate::{redact::redact, AppleCodesignError},
+    log::{debug, error, warn},
+    reqwest::{
+        blocking::{Client, RequestBuilder, Response},
+        Method,
+    },
     serde::{de::DeserializeOwned, Deserialize, Serialize},
     serde_json::Value,
-    std::{fs::Permissions, io::Write, path::Path, sync::Mutex},
+    std::{
+        collections::BTreeSet,
+        fs::Permissions,
+        io::Write,
+        path::Path,
+        sync::{mpsc, Arc, Mutex},
+        thread::JoinHandle,
+        time::{Duration, Instant},
+    },
+    zeroize::{Zeroize, Zeroizing},
 };
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+/// Clock skew in seconds above which tokens are rejected by the server.
+///
+/// Apple doesn't document this, so this is conservative.
+pub const CLOCK_SKEW_TOLERANCE_SECONDS: i64 = 60;
+
+/// Fraction of the token lifetime after which a cached token is replaced.
+///
+/// This leaves a margin so tokens don't expire while a request is in flight.
+/// With the default lifetime, tokens are replaced after 240 seconds.
+pub(crate) const TOKEN_REFRESH_FRACTION: f64 = 0.8;
+
+/// Fraction of the token lifetime after which a [TokenRefresher] replaces
+/// the cached token.
+///
+/// This is below [TOKEN_REFRESH_FRACTION], so requests find a fresh token
+/// rather than minting one.
+const TOKEN_PREWARM_FRACTION: f64 = 0.7;
+
+/// Interval at which a [TokenRefresher] checks the age of the cached token.
+const TOKEN_REFRESH_TICK: Duration = Duration::from_secs(10);
+
+/// Base URL of the App Store Connect API.
+pub const APP_STORE_CONNECT_API_URL: &str = "https://api.appstoreconnect.apple.com";
+
+/// Environment variable enabling [AppStoreConnectClient::set_read_only()].
+pub const READ_ONLY_ENV: &str = "ASCONNECT_READ_ONLY";
+
+/// Environment variable holding the issuer ID for [UnifiedApiKey::from_env()].
+pub const ISSUER_ID_ENV: &str = "APP_STORE_CONNECT_ISSUER_ID";
+
+/// Environment variable holding the key ID for [UnifiedApiKey::from_env()].
+pub const KEY_ID_ENV: &str = "APP_STORE_CONNECT_KEY_ID";
+
+/// Environment variable holding the private key for [UnifiedApiKey::from_env()].
+pub const PRIVATE_KEY_ENV: &str = "APP_STORE_CONNECT_PRIVATE_KEY";
+
+fn read_only_from_env() -> bool {
+    matches!(
+        std::env::var(READ_ONLY_ENV).as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
 #[cfg(unix)]
 fn set_permissions_private(p: &mut Permissions) {
     p.set_mode(0o600);
@@ -32,12 +202,13 @@ fn set_permissions_private(_: &mut Permissions) {}
 /// of an App Store Connect API Key. The type supports serialization so we save as a single
 /// file or payload to enhance usability (so people don't need to provide all 3 pieces of the
 /// API Key for all operations).
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct UnifiedApiKey {
     /// Who issued the key.
     ///
-    /// Likely a UUID.
-    issuer_id: String,
+    /// Likely a UUID. Absent for individual keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    issuer_id: Option<String>,
 
     /// Key identifier.
     ///
@@ -45,9 +216,27 @@ pub struct UnifiedApiKey {
     key_id: String,
 
     /// Base64 encoded DER of ECDSA private key material.
+    ///
+    /// Zeroed on drop.
     private_key: String,
 }
 
+impl Drop for UnifiedApiKey {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+impl std::fmt::Debug for UnifiedApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnifiedApiKey")
+            .field("issuer_id", &self.issuer_id)
+            .field("key_id", &self.key_id)
+            .field("private_key", &crate::redact::REDACTED)
+            .finish()
+    }
+}
+
 impl UnifiedApiKey {
     /// Construct an instance from constitute parts and a PEM encoded ECDSA private key.
     ///
@@ -58,27 +247,137 @@ impl UnifiedApiKey {
         key_id: impl ToString,
         path: impl AsRef<Path>,
     ) -> Result<Self, AppleCodesignError> {
-        let pem_data = std::fs::read(path.as_ref())?;
+        let pem_data = Zeroizing::new(std::fs::read(path.as_ref())?);
 
-        let parsed = pem::parse(pem_data).map_err(|e| {
-            AppleCodesignError::AppStoreConnectApiKey(format!("error parsing PEM: {}", e))
-        })?;
+        Self::from_ecdsa_pem(issuer_id, key_id, &*pem_data)
+    }
 
-        if parsed.tag != "PRIVATE KEY" {
-            return Err(AppleCodesignError::AppStoreConnectApiKey(
-                "does not look like a PRIVATE KEY".to_string(),
-            ));
-        }
+    /// Construct an instance from an issuer ID and the ID of a key downloaded from Apple.
+    ///
+    /// The `AuthKey_<key_id>.p8` file is searched for in the locations used by
+    /// `altool` and `notarytool`, such as `./private_keys` and
+    /// `~/.appstoreconnect/private_keys`.
+    pub fn from_key_id(
+        issuer_id: impl ToString,
+        key_id: impl ToString,
+    ) -> Result<Self, AppleCodesignError> {
+        let key_id = key_id.to_string();
+        let path = api_token::find_api_key_path(&key_id)?;
+
+        Self::from_ecdsa_pem_path(issuer_id, key_id, path)
+    }
+
+    /// Construct an individual key from the ID of a key downloaded from Apple.
+    ///
+    /// The private key is searched for like [Self::from_key_id()].
+    pub fn individual_from_key_id(key_id: impl ToString) -> Result<Self, AppleCodesignError> {
+        let key_id = key_id.to_string();
+        let path = api_token::find_api_key_path(&key_id)?;
+        let pem_data = Zeroizing::new(std::fs::read(path)?);
+
+        Self::individual_from_ecdsa_pem(key_id, &*pem_data)
+    }
+
+    /// Construct an instance from constitute parts and PEM encoded ECDSA private key data.
+    pub fn from_ecdsa_pem(
+        issuer_id: impl ToString,
+        key_id: impl ToString,
+        pem_data: impl AsRef<[u8]>,
+    ) -> Result<Self, AppleCodesignError> {
+        let der = api_token::parse_private_key_pem(pem_data.as_ref())?;
+
+        Ok(Self {
+            issuer_id: Some(issuer_id.to_string()),
+            key_id: key_id.to_string(),
+            private_key: base64::encode(&*der),
+        })
+    }
 
-        let private_key = base64::encode(parsed.contents);
+    /// Construct an individual key from its ID and PEM encoded ECDSA private key data.
+    ///
+    /// Individual keys belong to a user and have no issuer ID.
+    pub fn individual_from_ecdsa_pem(
+        key_id: impl ToString,
+        pem_data: impl AsRef<[u8]>,
+    ) -> Result<Self, AppleCodesignError> {
+        let der = api_token::parse_private_key_pem(pem_data.as_ref())?;
 
         Ok(Self {
-            issuer_id: issuer_id.to_string(),
+            issuer_id: None,
             key_id: key_id.to_string(),
-            private_key,
+            private_key: base64::encode(&*der),
         })
     }
 
+    /// The issuer ID of a team key. `None` for an individual key.
+    pub fn issuer_id(&self) -> Option<&str> {
+        self.issuer_id.as_deref()
+    }
+
+    /// The key ID.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Construct an instance from environment variables.
+    ///
+    /// The key ID is read from [KEY_ID_ENV] and the private key from
+    /// [PRIVATE_KEY_ENV], either PEM encoded (as downloaded from Apple) or base64
+    /// encoded DER. The issuer ID is read from [ISSUER_ID_ENV]; without it the key
+    /// is an individual key.
+    ///
+    /// Returns `None` if neither the key ID nor the private key is set.
+    pub fn from_env() -> Result<Option<Self>, AppleCodesignError> {
+        Self::from_env_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_env_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Self>, AppleCodesignError> {
+        let issuer_id = lookup(ISSUER_ID_ENV).filter(|v| !v.is_empty());
+        let key_id = lookup(KEY_ID_ENV).filter(|v| !v.is_empty());
+        let private_key = lookup(PRIVATE_KEY_ENV)
+            .filter(|v| !v.is_empty())
+            .map(Zeroizing::new);
+
+        let (key_id, private_key) = match (key_id, private_key) {
+            (None, None) => return Ok(None),
+            (Some(key_id), Some(private_key)) => (key_id, private_key),
+            (Some(_), None) => {
+                return Err(AppleCodesignError::AppStoreConnectApiKey(format!(
+                    "{} is set but {} is not",
+                    KEY_ID_ENV, PRIVATE_KEY_ENV
+                )))
+            }
+            (None, Some(_)) => {
+                return Err(AppleCodesignError::AppStoreConnectApiKey(format!(
+                    "{} is set but {} is not",
+                    PRIVATE_KEY_ENV, KEY_ID_ENV
+                )))
+            }
+        };
+
+        let der = if private_key.contains("-----BEGIN") {
+            // CI systems often store multi-line values with escaped newlines.
+            let pem_data = Zeroizing::new(private_key.replace("\\n", "\n"));
+
+            api_token::parse_private_key_pem(pem_data.as_bytes())?
+        } else {
+            Zeroizing::new(base64::decode(private_key.trim()).map_err(|e| {
+                AppleCodesignError::AppStoreConnectApiKey(format!(
+                    "{} is neither PEM nor base64: {}",
+                    PRIVATE_KEY_ENV, e
+                ))
+            })?)
+        };
+
+        Ok(Some(Self {
+            issuer_id,
+            key_id,
+            private_key: base64::encode(&*der),
+        }))
+    }
+
     /// Construct an instance from serialized JSON.
     pub fn from_json(data: impl AsRef<[u8]>) -> Result<Self, AppleCodesignError> {
         Ok(serde_json::from_slice(data.as_ref())?)
@@ -86,9 +385,9 @@ impl UnifiedApiKey {
 
     /// Construct an instance from a JSON file.
     pub fn from_json_path(path: impl AsRef<Path>) -> Result<Self, AppleCodesignError> {
-        let data = std::fs::read(path.as_ref())?;
+        let data = Zeroizing::new(std::fs::read(path.as_ref())?);
 
-        Self::from_json(data)
+        Self::from_json(&*data)
     }
 
     /// Serialize this instance to a JSON object.
@@ -105,36 +404,128 @@ impl UnifiedApiKey {
     /// Permissions on the resulting file may not be as restrictive as desired. It is up
     /// to callers to additionally harden as desired.
     pub fn write_json_file(&self, path: impl AsRef<Path>) -> Result<(), AppleCodesignError> {
-        let path = path.as_ref();
+        let data = Zeroizing::new(self.to_json_string()?);
 
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+        write_private_file(path.as_ref(), data.as_bytes())
+    }
+}
 
-        let data = self.to_json_string()?;
+/// Write a file readable only by the current user, creating parent directories.
+fn write_private_file(path: &Path, data: &[u8]) -> Result<(), AppleCodesignError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
 
-        let mut fh = std::fs::File::create(path)?;
-        let mut permissions = fh.metadata()?.permissions();
-        set_permissions_private(&mut permissions);
-        fh.set_permissions(permissions)?;
-        fh.write_all(data.as_bytes())?;
+    let mut fh = std::fs::File::create(path)?;
+    let mut permissions = fh.metadata()?.permissions();
+    set_permissions_private(&mut permissions);
+    fh.set_permissions(permissions)?;
+    fh.write_all(data)?;
 
-        Ok(())
-    }
+    Ok(())
 }
 
 impl TryFrom<UnifiedApiKey> for ConnectTokenEncoder {
     type Error = AppleCodesignError;
 
     fn try_from(value: UnifiedApiKey) -> Result<Self, Self::Error> {
-        let der = base64::decode(value.private_key).map_err(|e| {
+        let der = Zeroizing::new(base64::decode(&value.private_key).map_err(|e| {
             AppleCodesignError::AppStoreConnectApiKey(format!(
                 "failed to base64 decode private key: {}",
                 e
             ))
-        })?;
+        })?);
 
-        Self::from_ecdsa_der(value.key_id, value.issuer_id, &der)
+        match &value.issuer_id {
+            Some(issuer_id) => Self::from_ecdsa_der(value.key_id.clone(), issuer_id.clone(), &der),
+            None => Self::individual_from_ecdsa_der(value.key_id.clone(), &der),
+        }
+    }
+}
+
+/// Timeouts applied to individual HTTP requests.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestTimeouts {
+    /// Timeout of API requests, such as reading or modifying metadata.
+    pub api: Duration,
+
+    /// Timeout of requests transferring asset data, such as uploading a build.
+    pub upload: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            api: Duration::from_secs(30),
+            upload: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Maximum number of resources per page of a collection.
+pub const MAX_PAGE_LIMIT: u32 = 200;
+
+/// How many resources to request per page of a collection.
+///
+/// Requests passing an explicit `limit` query parameter are left alone.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PageSize {
+    /// Don't send `limit`, so Apple's default page size applies.
+    Server,
+
+    /// Request this many resources per page. Clamped to [MAX_PAGE_LIMIT].
+    Fixed(u32),
+
+    /// Request the first page at Apple's default size and following pages at
+    /// [MAX_PAGE_LIMIT].
+    ///
+    /// Small collections take a single small request, while large collections,
+    /// such as the devices or builds of a big account, take a fraction of the
+    /// requests.
+    Adaptive,
+}
+
+impl Default for PageSize {
+    fn default() -> Self {
+        Self::Adaptive
+    }
+}
+
+impl std::str::FromStr for PageSize {
+    type Err = AppleCodesignError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "server" => Ok(Self::Server),
+            "adaptive" => Ok(Self::Adaptive),
+            _ => match s.parse::<u32>() {
+                Ok(limit) if (1..=MAX_PAGE_LIMIT).contains(&limit) => Ok(Self::Fixed(limit)),
+                _ => Err(AppleCodesignError::CliGeneralError(format!(
+                    "invalid page size {}; expected server, adaptive, or 1-{}",
+                    s, MAX_PAGE_LIMIT
+                ))),
+            },
+        }
+    }
+}
+
+/// Refreshes the token of an [AppStoreConnectClient] in the background.
+///
+/// Created by [AppStoreConnectClient::spawn_token_refresher()]. The background
+/// thread stops when this is dropped.
+pub struct TokenRefresher {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for TokenRefresher {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the thread up.
+        self.stop.take();
+
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
     }
 }
 
@@ -143,8 +534,31 @@ impl TryFrom<UnifiedApiKey> for ConnectTokenEncoder {
 /// The client isn't generic. Don't get any ideas.
 pub struct AppStoreConnectClient {
     client: Client,
+    api_url: String,
+    api_environment: ApiEnvironment,
+    api_versions: ApiVersions,
     connect_token: ConnectTokenEncoder,
-    token: Mutex<Option<AppStoreConnectToken>>,
+    token: Mutex<Option<(AppStoreConnectToken, Instant)>>,
+    token_cache: Option<TokenCache>,
+    clock_offset: Mutex<Option<i64>>,
+    correct_clock_skew: bool,
+    timeouts: RequestTimeouts,
+    page_size: PageSize,
+    deadline: Option<Instant>,
+    http_capture: Option<Arc<HttpCapture>>,
+    notification_sink: Option<Arc<dyn NotificationSink>>,
+    progress_sink: Option<Arc<dyn ProgressSink>>,
+    upload_bucket: Option<Arc<TokenBucket>>,
+    read_only: bool,
+    deprecations_reported: Mutex<BTreeSet<String>>,
+    schema_drift: Option<Arc<SchemaDrift>>,
+    lookup_cache: LookupCache,
+    snapshot: Option<Arc<AccountSnapshot>>,
+    apple_id: Option<credentials::AppleIdSession>,
+    #[cfg(feature = "asc-provisioning")]
+    account_type: certs_api::AccountType,
+    #[cfg(feature = "metrics")]
+    metrics: Option<std::sync::Arc<dyn metrics::MetricsRecorder>>,
 }
 
 impl AppStoreConnectClient {
@@ -152,49 +566,949 @@ impl AppStoreConnectClient {
     pub fn new(connect_token: ConnectTokenEncoder) -> Result<Self, AppleCodesignError> {
         Ok(Self {
             client: crate::ticket_lookup::default_client()?,
+            api_url: APP_STORE_CONNECT_API_URL.to_string(),
+            api_environment: ApiEnvironment::default(),
+            api_versions: ApiVersions::default(),
             connect_token,
             token: Mutex::new(None),
+            token_cache: None,
+            clock_offset: Mutex::new(None),
+            correct_clock_skew: false,
+            timeouts: RequestTimeouts::default(),
+            page_size: PageSize::default(),
+            deadline: None,
+            http_capture: None,
+            notification_sink: None,
+            progress_sink: None,
+            upload_bucket: None,
+            read_only: read_only_from_env(),
+            deprecations_reported: Mutex::new(BTreeSet::new()),
+            schema_drift: None,
+            lookup_cache: LookupCache::default(),
+            snapshot: None,
+            apple_id: None,
+            #[cfg(feature = "asc-provisioning")]
+            account_type: certs_api::AccountType::default(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
         })
     }
 
+    /// Set the base URL of the App Store Connect API.
+    ///
+    /// Defaults to [APP_STORE_CONNECT_API_URL]. This is used to talk to a mock
+    /// server (see [testing]).
+    pub fn set_api_url(&mut self, url: impl ToString) {
+        self.api_url = url.to_string().trim_end_matches('/').to_string();
+    }
+
+    /// The base URL of the App Store Connect API.
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// Talk to the API of an environment, e.g. the Enterprise Program API.
+    ///
+    /// This sets the base URL (see [Self::set_api_url()]) and restricts
+    /// requests to the resource types served in the environment. For
+    /// [ApiEnvironment::Enterprise], the account type is set to
+    /// [certs_api::AccountType::Enterprise] too.
+    pub fn set_api_environment(&mut self, environment: ApiEnvironment) {
+        self.api_environment = environment;
+        self.api_url = environment.api_url().to_string();
+
+        #[cfg(feature = "asc-provisioning")]
+        if environment == ApiEnvironment::Enterprise {
+            self.account_type = certs_api::AccountType::Enterprise;
+        }
+    }
+
+    /// The environment of the API the client talks to.
+    pub fn api_environment(&self) -> ApiEnvironment {
+        self.api_environment
+    }
+
+    /// Request a resource type at a given API version.
+    ///
+    /// Overrides the defaults in [api_versions::DEFAULT_API_VERSIONS]. e.g.
+    /// `set_api_version("sandboxTesters", 3)` requests `/v3/sandboxTesters`.
+    pub fn set_api_version(&mut self, resource: impl ToString, version: u8) {
+        self.api_versions.set(resource, version);
+    }
+
+    /// The versions requested for each resource type.
+    pub fn api_versions(&self) -> &ApiVersions {
+        &self.api_versions
+    }
+
+    /// The scope state persisted for this client's API key is partitioned by.
+    pub fn cache_scope(&self) -> CacheScope {
+        self.connect_token.cache_scope()
+    }
+
+    /// The ID of the API key the client is authenticated with.
+    pub fn api_key_id(&self) -> &str {
+        self.connect_token.key_id()
+    }
+
+    /// Set the type of account the client is authenticated against.
+    ///
+    /// This controls which certificate and profile types may be created.
+    #[cfg(feature = "asc-provisioning")]
+    pub fn set_account_type(&mut self, account_type: certs_api::AccountType) {
+        self.account_type = account_type;
+    }
+
+    /// The type of account the client is authenticated against.
+    #[cfg(feature = "asc-provisioning")]
+    pub fn account_type(&self) -> certs_api::AccountType {
+        self.account_type
+    }
+
+    /// Set the timeouts applied to individual requests.
+    pub fn set_request_timeouts(&mut self, timeouts: RequestTimeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Set how many resources collection requests ask for per page.
+    pub fn set_page_size(&mut self, page_size: PageSize) {
+        self.page_size = page_size;
+    }
+
+    /// How many resources collection requests ask for per page.
+    pub fn page_size(&self) -> PageSize {
+        self.page_size
+    }
+
+    /// The query of the first page of a collection, with a `limit` for the page size.
+    fn collection_query<'a>(&self, query: &[(&'a str, String)]) -> Vec<(&'a str, String)> {
+        let mut query = query.to_vec();
+
+        if let PageSize::Fixed(limit) = self.page_size {
+            if !query.iter().any(|(name, _)| *name == "limit") {
+                query.push(("limit", limit.clamp(1, MAX_PAGE_LIMIT).to_string()));
+            }
+        }
+
+        query
+    }
+
+    /// The URL of the next page of a collection, with its `limit` raised for the page size.
+    ///
+    /// Next links repeat the `limit` of the first request, so only
+    /// [PageSize::Adaptive] changes them.
+    pub(crate) fn next_page_url(&self, next: &str) -> String {
+        if self.page_size != PageSize::Adaptive {
+            return next.to_string();
+        }
+
+        let mut url = match reqwest::Url::parse(next) {
+            Ok(url) => url,
+            Err(_) => return next.to_string(),
+        };
+
+        let limit = url
+            .query_pairs()
+            .find(|(name, _)| name == "limit")
+            .and_then(|(_, value)| value.parse::<u32>().ok());
+        if limit.is_some_and(|limit| limit >= MAX_PAGE_LIMIT) {
+            return next.to_string();
+        }
+
+        let mut pairs = url
+            .query_pairs()
+            .filter(|(name, _)| name != "limit")
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect::<Vec<_>>();
+        pairs.push(("limit".into(), MAX_PAGE_LIMIT.to_string()));
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+
+        url.to_string()
+    }
+
+    /// Set a deadline by which all operations must complete.
+    ///
+    /// Request timeouts are shortened so no request runs past the deadline and
+    /// requests issued after it fail immediately. Operations that wait on
+    /// server-side processing stop waiting at the deadline, even if their own
+    /// wait limit is longer.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// The deadline by which all operations must complete, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Record API requests and their responses.
+    ///
+    /// See [http_capture] for what is captured.
+    pub fn set_http_capture(&mut self, capture: Option<Arc<HttpCapture>>) {
+        self.http_capture = capture;
+    }
+
+    /// Set the sink notified when waiting operations reach a terminal state.
+    ///
+    /// See [notify].
+    pub fn set_notification_sink(&mut self, sink: Option<Arc<dyn NotificationSink>>) {
+        self.notification_sink = sink;
+    }
+
+    /// Set the sink receiving progress of uploads and waits on processing.
+    ///
+    /// See [progress].
+    pub fn set_progress_sink(&mut self, sink: Option<Arc<dyn ProgressSink>>) {
+        self.progress_sink = sink;
+    }
+
+    /// Record response fields unknown to this crate.
+    ///
+    /// See [schema_drift].
+    pub fn set_schema_drift(&mut self, drift: Option<Arc<SchemaDrift>>) {
+        self.schema_drift = drift;
+    }
+
+    /// Refuse to send requests that modify resources.
+    ///
+    /// Requests other than `GET`, `HEAD`, `OPTIONS`, and `TRACE` fail with
+    /// [AppleCodesignError::AppStoreConnectReadOnly] before being sent. This
+    /// lets audit and reporting jobs use production keys without risking
+    /// accidental writes.
+    ///
+    /// Defaults to enabled if the [READ_ONLY_ENV] environment variable is `1`
+    /// or `true`.
+    pub fn set_read_only(&mut self, value: bool) {
+        self.read_only = value;
+    }
+
+    /// Whether requests that modify resources are refused.
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The memo of bundle ID and app lookups.
+    ///
+    /// See [lookup_cache] for what is cached and when to invalidate it.
+    pub fn lookup_cache(&self) -> &LookupCache {
+        &self.lookup_cache
+    }
+
+    /// Constrain a wait limit so it doesn't extend past the deadline.
+    pub fn constrain_wait_limit(&self, wait_limit: Duration) -> Duration {
+        match self.deadline {
+            Some(deadline) => wait_limit.min(deadline.saturating_duration_since(Instant::now())),
+            None => wait_limit,
+        }
+    }
+
+    /// Resolve the timeout of a request, honoring the deadline.
+    fn request_timeout(&self, timeout: Duration) -> Result<Duration, AppleCodesignError> {
+        match self.deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                if remaining.is_zero() {
+                    Err(AppleCodesignError::AppStoreConnectDeadlineExceeded)
+                } else {
+                    Ok(timeout.min(remaining))
+                }
+            }
+            None => Ok(timeout),
+        }
+    }
+
+    /// The timeout of an API request.
+    pub(crate) fn api_timeout(&self) -> Result<Duration, AppleCodesignError> {
+        self.request_timeout(self.timeouts.api)
+    }
+
+    /// The timeout of a request transferring asset data.
+    pub(crate) fn upload_timeout(&self) -> Result<Duration, AppleCodesignError> {
+        self.request_timeout(self.timeouts.upload)
+    }
+
+    /// Limit the bandwidth used to upload asset data.
+    ///
+    /// All uploads of this client share the limit. See [bandwidth].
+    pub fn set_upload_rate_limit(&mut self, limit: Option<BandwidthLimit>) {
+        self.upload_bucket = limit.map(|limit| Arc::new(TokenBucket::new(limit)));
+    }
+
+    /// The bandwidth limit of uploads, if any.
+    pub fn upload_rate_limit(&self) -> Option<BandwidthLimit> {
+        self.upload_bucket.as_ref().map(|bucket| bucket.limit())
+    }
+
+    /// Persist tokens so other clients of the same API key reuse them.
+    ///
+    /// Before minting a token, the cache is consulted for a token that isn't
+    /// yet due for replacement. Newly minted tokens are written to the cache.
+    /// See [TokenCache].
+    pub fn set_token_cache(&mut self, cache: Option<TokenCache>) {
+        self.token_cache = cache;
+    }
+
+    /// Set whether to compensate for a skewed local clock.
+    ///
+    /// The offset between the local clock and the server's clock is measured
+    /// from the `Date` header of the first response. When enabled, tokens are
+    /// minted using the server's notion of the current time and a request
+    /// rejected due to a skewed token is retried once with a corrected token.
+    /// When disabled, such a rejection results in an error explaining the skew.
+    pub fn set_correct_clock_skew(&mut self, value: bool) {
+        self.correct_clock_skew = value;
+    }
+
+    /// The number of seconds the server's clock is ahead of the local clock.
+    ///
+    /// [None] until a response has been received.
+    pub fn clock_offset(&self) -> Option<i64> {
+        *self.clock_offset.lock().unwrap()
+    }
+
+    /// Record the clock offset from a response, if it isn't yet known.
+    ///
+    /// Returns whether a skew exceeding [CLOCK_SKEW_TOLERANCE_SECONDS] was newly
+    /// detected.
+    fn observe_server_date(&self, response: &Response) -> bool {
+        let mut offset = self.clock_offset.lock().unwrap();
+
+        if offset.is_some() {
+            return false;
+        }
+
+        let server_time = match response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+        {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let value = server_time.timestamp() - chrono::Utc::now().timestamp();
+        offset.replace(value);
+
+        if value.abs() > CLOCK_SKEW_TOLERANCE_SECONDS {
+            warn!(
+                "local clock differs from App Store Connect server clock by {} seconds",
+                value
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set the recorder receiving metrics about API requests.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics_recorder(&mut self, recorder: std::sync::Arc<dyn metrics::MetricsRecorder>) {
+        self.metrics = Some(recorder);
+    }
+
+    /// The recorder receiving metrics, if one is set.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_recorder(&self) -> Option<&dyn metrics::MetricsRecorder> {
+        self.metrics.as_deref()
+    }
+
     fn get_token(&self) -> Result<String, AppleCodesignError> {
+        self.cached_token(self.token_lifetime().mul_f64(TOKEN_REFRESH_FRACTION))
+    }
+
+    /// The lifetime of tokens minted by the client.
+    ///
+    /// See [ConnectTokenEncoder::with_token_duration()].
+    fn token_lifetime(&self) -> Duration {
+        Duration::from_secs(self.connect_token.token_duration())
+    }
+
+    /// The token cache, unless tokens are scoped.
+    ///
+    /// Scoped tokens would be reused by clients of the same key needing
+    /// other requests.
+    fn usable_token_cache(&self) -> Option<&TokenCache> {
+        self.token_cache
+            .as_ref()
+            .filter(|_| self.connect_token.scope().is_empty())
+    }
+
+    /// Mint a token unless the cached token is younger than `max_age`.
+    fn cached_token(&self, max_age: Duration) -> Result<String, AppleCodesignError> {
         let mut token = self.token.lock().unwrap();
 
-        // TODO need to handle token expiration.
+        if let Some((_, minted)) = token.as_ref() {
+            if minted.elapsed() >= max_age {
+                debug!("replacing expiring App Store Connect API token");
+                token.take();
+            }
+        }
+
         if token.is_none() {
-            token.replace(self.connect_token.new_token(300)?);
+            let lifetime = self.token_lifetime();
+            let cached = self.usable_token_cache().and_then(|cache| {
+                cache.load(&self.cache_scope(), lifetime.saturating_sub(max_age))
+            });
+
+            if let Some((value, remaining)) = cached {
+                debug!("reusing cached App Store Connect API token");
+                let minted = Instant::now()
+                    .checked_sub(lifetime.saturating_sub(remaining))
+                    .unwrap_or_else(Instant::now);
+                token.replace((value, minted));
+            } else {
+                let clock_offset = if self.correct_clock_skew {
+                    self.clock_offset().unwrap_or_default()
+                } else {
+                    0
+                };
+
+                let value = self
+                    .connect_token
+                    .new_token_with_clock_offset(lifetime.as_secs(), clock_offset)?;
+                if let Some(cache) = self.usable_token_cache() {
+                    cache.store_or_warn(&self.cache_scope(), &value, lifetime);
+                }
+                token.replace((value, Instant::now()));
+            }
         }
 
-        Ok(token.as_ref().unwrap().clone())
+        Ok(token.as_ref().unwrap().0.clone())
+    }
+
+    /// Mint a token ahead of the first request.
+    ///
+    /// A cached token close to expiry is replaced. Failures, such as a
+    /// malformed private key, surface here rather than on the first request.
+    pub fn prewarm_token(&self) -> Result<(), AppleCodesignError> {
+        self.cached_token(self.token_lifetime().mul_f64(TOKEN_PREWARM_FRACTION))
+            .map(|_| ())
+    }
+
+    /// Refresh the token on a background thread until the returned
+    /// [TokenRefresher] is dropped.
+    ///
+    /// Long-running processes sharing a client (such as the
+    /// `app-store-connect-serve` daemon) use this so no request waits for a
+    /// token to be minted and no upload starts with a token about to expire.
+    pub fn spawn_token_refresher(self: &Arc<Self>) -> TokenRefresher {
+        let client = self.clone();
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let handle = std::thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) =
+                stopped.recv_timeout(TOKEN_REFRESH_TICK)
+            {
+                if let Err(e) = client.prewarm_token() {
+                    warn!(
+                        "failed to refresh App Store Connect API token: {}",
+                        redact(&e.to_string())
+                    );
+                }
+            }
+        });
+
+        TokenRefresher {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
     }
 
     pub(crate) fn send_request<T: DeserializeOwned>(
         &self,
-        request: reqwest::blocking::RequestBuilder,
+        request: RequestBuilder,
     ) -> Result<T, AppleCodesignError> {
+        if let Some(snapshot) = &self.snapshot {
+            let request = request.build()?;
+            let url = request.url();
+
+            let document = if request.method() == Method::GET {
+                snapshot.answer(
+                    url.path(),
+                    &url.query_pairs()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect::<Vec<_>>(),
+                )
+            } else {
+                None
+            };
+
+            return match document {
+                Some(document) => Ok(serde_json::from_value(document)?),
+                None => Err(AppleCodesignError::AppStoreConnectOffline(format!(
+                    "{} {}",
+                    request.method(),
+                    url.path()
+                ))),
+            };
+        }
+
+        let response = self.send_request_raw(request)?;
+
+        match &self.schema_drift {
+            Some(drift) => {
+                let path = response.url().path().to_string();
+                let value = response.json::<Value>()?;
+
+                drift.observe(&path, &value, schema_drift::attribute_fields::<T>());
+
+                Ok(serde_json::from_value(value)?)
+            }
+            None => Ok(response.json::<T>()?),
+        }
+    }
+
+    /// Send a request and return the successful [Response] without decoding its body.
+    pub(crate) fn send_request_raw(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<Response, AppleCodesignError> {
         let request = request.build()?;
         let url = request.url().to_string();
 
-        debug!("{} {}", request.method(), url);
+        if self.snapshot.is_some() {
+            return Err(AppleCodesignError::AppStoreConnectOffline(format!(
+                "{} {}",
+                request.method(),
+                request.url().path()
+            )));
+        }
 
-        let response = self.client.execute(request)?;
+        if self.read_only && !request.method().is_safe() {
+            return Err(AppleCodesignError::AppStoreConnectReadOnly(
+                request.method().to_string(),
+                redact(&url).to_string(),
+            ));
+        }
+
+        debug!("{} {}", request.method(), redact(&url));
+
+        let method = request.method().clone();
+        let path = request.url().path().to_string();
+
+        let pending = self
+            .http_capture
+            .as_ref()
+            .map(|_| PendingExchange::new(&request));
+
+        let retry_request = request.try_clone();
+        let mut response = self.client.execute(request)?;
+
+        let skew_detected = self.observe_server_date(&response);
+
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            if let Some(offset) = self
+                .clock_offset()
+                .filter(|offset| offset.abs() > CLOCK_SKEW_TOLERANCE_SECONDS)
+            {
+                match retry_request {
+                    Some(mut retry_request)
+                        if self.correct_clock_skew && skew_detected && self.apple_id.is_none() =>
+                    {
+                        warn!("retrying request with token corrected for clock skew");
+                        self.token.lock().unwrap().take();
+
+                        retry_request.headers_mut().insert(
+                            reqwest::header::AUTHORIZATION,
+                            format!("Bearer {}", self.get_token()?)
+                                .parse()
+                                .expect("JWT should be a valid header value"),
+                        );
+
+                        response = self.client.execute(retry_request)?;
+                    }
+                    _ => {
+                        return Err(AppleCodesignError::AppStoreConnectClockSkew(offset));
+                    }
+                }
+            }
+        }
+
+        self.check_deprecation(
+            &method,
+            &path,
+            response.status().as_u16(),
+            response.headers(),
+        );
+
+        #[cfg(feature = "metrics")]
+        if let Some(recorder) = &self.metrics {
+            recorder.increment_counter(
+                "app_store_connect_api_requests_total",
+                &[("status", response.status().as_str())],
+                1,
+            );
+
+            if !response.status().is_success() {
+                recorder.increment_counter("app_store_connect_api_errors_total", &[], 1);
+            }
+
+            if let Some((_, remaining)) = response
+                .headers()
+                .get("x-rate-limit")
+                .and_then(|v| v.to_str().ok())
+                .and_then(metrics::parse_rate_limit_header)
+            {
+                recorder.set_gauge(
+                    "app_store_connect_rate_limit_remaining",
+                    &[],
+                    remaining as f64,
+                );
+            }
+        }
 
         if response.status().is_success() {
-            Ok(response.json::<T>()?)
+            if let (Some(capture), Some(pending)) = (&self.http_capture, pending) {
+                capture.record(pending.finish(
+                    response.status().as_u16(),
+                    response.headers(),
+                    None,
+                ));
+            }
+
+            Ok(response)
         } else {
-            error!("HTTP error from {}", url);
+            error!("HTTP error from {}", redact(&url));
 
+            let status = response.status().as_u16();
+            let headers = response.headers().clone();
             let body = response.bytes()?;
 
+            if let (Some(capture), Some(pending)) = (&self.http_capture, pending) {
+                capture.record(pending.finish(status, &headers, Some(body.as_ref())));
+            }
+
+            if let Ok(document) = serde_json::from_slice::<ErrorDocument>(body.as_ref()) {
+                if !document.errors.is_empty() {
+                    return Err(AppleCodesignError::AppStoreConnectApi(ApiError::new(
+                        status, document,
+                    )));
+                }
+            }
+
             if let Ok(value) = serde_json::from_slice::<Value>(body.as_ref()) {
-                for line in serde_json::to_string_pretty(&value)?.lines() {
+                for line in redact(&serde_json::to_string_pretty(&value)?).lines() {
                     error!("{}", line);
                 }
             } else {
-                error!("{}", String::from_utf8_lossy(body.as_ref()));
+                error!("{}", redact(&String::from_utf8_lossy(body.as_ref())));
             }
 
-            Err(AppleCodesignError::NotarizeServerError)
+            Err(AppleCodesignError::AppStoreConnectApi(
+                ApiError::from_status(status),
+            ))
+        }
+    }
+
+    /// Construct an authenticated request to the App Store Connect API.
+    ///
+    /// `path` is relative to [Self::api_url()] and should begin with the API
+    /// version. e.g. `/v1/builds`. The version is rewritten if the resource
+    /// type is served at another version (see [api_versions]).
+    pub(crate) fn api_request(
+        &self,
+        method: Method,
+        path: &str,
+    ) -> Result<RequestBuilder, AppleCodesignError> {
+        if !self.api_environment.serves_path(path) {
+            return Err(AppleCodesignError::AppStoreConnectUnsupportedResource(
+                path.split('?').next().unwrap_or_default().to_string(),
+                self.api_environment.name().to_string(),
+            ));
         }
+
+        let request = self
+            .client
+            .request(
+                method,
+                format!("{}{}", self.api_url, self.api_versions.resolve(path)),
+            )
+            .timeout(self.api_timeout()?)
+            .header("Accept", "application/json");
+
+        self.authenticate(request)
+    }
+
+    /// Add the credentials of the client to a request.
+    fn authenticate(&self, request: RequestBuilder) -> Result<RequestBuilder, AppleCodesignError> {
+        if self.snapshot.is_some() {
+            // Offline clients have no usable API Key.
+            Ok(request)
+        } else if self.apple_id.is_some() {
+            // Don't send the app-specific password where it isn't accepted.
+            Err(AppleCodesignError::AppStoreConnectAppleIdSession)
+        } else {
+            Ok(request.bearer_auth(self.get_token()?))
+        }
+    }
+
+    /// Perform a `GET` request against the App Store Connect API.
+    pub(crate) fn api_get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, AppleCodesignError> {
+        let req = self.api_request(Method::GET, path)?.query(query);
+
+        self.send_request(req)
+    }
+
+    /// Perform a `GET` request against a collection, following pagination links.
+    ///
+    /// All resources from all pages are returned.
+    pub(crate) fn api_get_all<A: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<Resource<A>>, AppleCodesignError> {
+        let mut res = vec![];
+
+        self.api_get_pages(path, query, None, |page, _| {
+            res.extend(page);
+            Ok(())
+        })?;
+
+        Ok(res)
+    }
+
+    /// Perform a `GET` request against a collection, following pagination links.
+    ///
+    /// All resources from all pages are returned along with the related
+    /// resources requested via the `include` query parameter.
+    pub(crate) fn api_get_all_with_included<A: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<(Vec<Resource<A>>, Vec<Value>), AppleCodesignError> {
+        let mut res = vec![];
+        let mut included = vec![];
+
+        let mut page =
+            self.api_get::<Document<Vec<Resource<A>>>>(path, &self.collection_query(query))?;
+
+        loop {
+            res.extend(page.data);
+            included.extend(page.included);
+
+            match page.links.and_then(|links| links.next) {
+                Some(next) => {
+                    page = self.api_get_url(&self.next_page_url(&next))?;
+                }
+                None => break,
+            }
+        }
+
+        Ok((res, included))
+    }
+
+    /// Perform a `GET` request against a collection, calling a function for each page.
+    ///
+    /// The function receives the resources of a page and the URL of the next page,
+    /// if there is one. The next page URL can be passed as `resume_from` to
+    /// continue iterating from that page at a later time, in which case `path`
+    /// and `query` are ignored.
+    pub(crate) fn api_get_pages<A: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        resume_from: Option<String>,
+        mut f: impl FnMut(Vec<Resource<A>>, Option<&str>) -> Result<(), AppleCodesignError>,
+    ) -> Result<(), AppleCodesignError> {
+        let mut page = if let Some(url) = resume_from {
+            self.api_get_url::<Document<Vec<Resource<A>>>>(&url)?
+        } else {
+            self.api_get::<Document<Vec<Resource<A>>>>(path, &self.collection_query(query))?
+        };
+
+        loop {
+            let next = page
+                .links
+                .and_then(|links| links.next)
+                .map(|next| self.next_page_url(&next));
+
+            f(page.data, next.as_deref())?;
+
+            match next {
+                Some(next) => {
+                    page = self.api_get_url(&next)?;
+                }
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Perform a `GET` request against an absolute URL, such as a pagination link.
+    ///
+    /// The URL must have the origin of [Self::api_url()]. URLs may come from
+    /// files, such as export cursors, and credentials must not leak elsewhere.
+    pub(crate) fn api_get_url<T: DeserializeOwned>(
+        &self,
+        url: &str,
+    ) -> Result<T, AppleCodesignError> {
+        let origin = |url: &str| reqwest::Url::parse(url).ok().map(|url| url.origin());
+
+        match origin(url) {
+            Some(url_origin) if Some(&url_origin) == origin(&self.api_url).as_ref() => {}
+            _ => {
+                return Err(AppleCodesignError::AppStoreConnectForeignUrl(
+                    redact(url).to_string(),
+                ));
+            }
+        }
+
+        let req = self.authenticate(
+            self.client
+                .get(url)
+                .timeout(self.api_timeout()?)
+                .header("Accept", "application/json"),
+        )?;
+
+        self.send_request(req)
+    }
+
+    /// Perform a `POST` request with a JSON body against the App Store Connect API.
+    pub(crate) fn api_post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, AppleCodesignError> {
+        let req = self
+            .api_request(Method::POST, path)?
+            .header("Content-Type", "application/json")
+            .json(body);
+
+        self.send_request(req)
+    }
+
+    /// Perform a `POST` request with a JSON body whose response has no content.
+    ///
+    /// This is used for modifying relationships, which yields `204 No Content`.
+    pub(crate) fn api_post_no_content<B: Serialize>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<(), AppleCodesignError> {
+        let req = self
+            .api_request(Method::POST, path)?
+            .header("Content-Type", "application/json")
+            .json(body);
+
+        self.send_request_raw(req)?;
+
+        Ok(())
+    }
+
+    /// Perform a `PATCH` request with a JSON body against the App Store Connect API.
+    pub(crate) fn api_patch<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, AppleCodesignError> {
+        let req = self
+            .api_request(Method::PATCH, path)?
+            .header("Content-Type", "application/json")
+            .json(body);
+
+        self.send_request(req)
+    }
+
+    /// Update a resource, reporting which attributes changed.
+    ///
+    /// The resource at `path` is read before being modified with a `PATCH`
+    /// request, and the attributes before and after are compared.
+    pub(crate) fn api_update<A: Serialize + DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &RequestDocument<A>,
+    ) -> Result<Updated<A>, AppleCodesignError> {
+        let before = self.api_get::<Document<Resource<A>>>(path, &[])?.data;
+        let after = self.api_patch::<_, Document<Resource<A>>>(path, body)?.data;
+
+        let changes = diff_attributes(&before.attributes, &after.attributes)?;
+
+        Ok(Updated {
+            resource: after,
+            changes,
+        })
+    }
+
+    /// Perform a `DELETE` request against the App Store Connect API.
+    pub(crate) fn api_delete(&self, path: &str) -> Result<(), AppleCodesignError> {
+        let req = self.api_request(Method::DELETE, path)?;
+
+        self.send_request_raw(req)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::app_store_connect::testing::MockServerBuilder};
+
+    #[test]
+    fn token_refresher() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default().start()?;
+        let client = Arc::new(server.client()?);
+        assert!(client.token.lock().unwrap().is_none());
+
+        client.prewarm_token()?;
+        let minted = client.token.lock().unwrap().as_ref().unwrap().1;
+
+        // A fresh token is kept.
+        client.prewarm_token()?;
+        assert_eq!(client.token.lock().unwrap().as_ref().unwrap().1, minted);
+
+        // Dropping the refresher stops its thread without waiting for a tick.
+        let started = Instant::now();
+        drop(client.spawn_token_refresher());
+        assert!(started.elapsed() < TOKEN_REFRESH_TICK);
+        assert_eq!(Arc::strong_count(&client), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unified_api_key_from_env() -> Result<(), AppleCodesignError> {
+        let der = vec![0x30, 0x03, 0x02, 0x01, 0x01];
+        let pem_data = pem::encode(&pem::Pem {
+            tag: "PRIVATE KEY".into(),
+            contents: der.clone(),
+        });
+
+        let lookup = |vars: Vec<(&'static str, String)>| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.clone())
+            }
+        };
+
+        assert!(UnifiedApiKey::from_env_lookup(lookup(vec![]))?.is_none());
+        assert!(UnifiedApiKey::from_env_lookup(lookup(vec![(KEY_ID_ENV, "KEY".into())])).is_err());
+
+        let pem_key = UnifiedApiKey::from_env_lookup(lookup(vec![
+            (ISSUER_ID_ENV, "ISSUER".into()),
+            (KEY_ID_ENV, "KEY".into()),
+            (PRIVATE_KEY_ENV, pem_data.replace('\n', "\\n")),
+        ]))?
+        .unwrap();
+        assert_eq!(pem_key.issuer_id(), Some("ISSUER"));
+        assert_eq!(pem_key.key_id(), "KEY");
+        assert_eq!(pem_key.private_key, base64::encode(&der));
+
+        let der_key = UnifiedApiKey::from_env_lookup(lookup(vec![
+            (KEY_ID_ENV, "KEY".into()),
+            (PRIVATE_KEY_ENV, base64::encode(&der)),
+        ]))?
+        .unwrap();
+        assert_eq!(der_key.issuer_id(), None);
+        assert_eq!(der_key.private_key, pem_key.private_key);
+
+        Ok(())
     }
 }
@@ -62,7 +62,7 @@ pub struct NewSubmissionResponse {
     pub meta: Value,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "PascalCase")]
 pub enum SubmissionResponseStatus {
     Accepted,
@@ -118,6 +118,14 @@ impl SubmissionResponse {
     }
 }
 
+/// The notary service’s response to a request for previous submissions.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionListResponse {
+    pub data: Vec<SubmissionResponseData>,
+    pub meta: Value,
+}
+
 /// Information about the log associated with the submission.
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -176,6 +184,7 @@ impl NotaryApiClient {
         let req = self
             .client
             .post(APPLE_NOTARY_SUBMIT_SOFTWARE_URL)
+            .timeout(self.api_timeout()?)
             .bearer_auth(token)
             .header("Accept", "application/json")
             .header("Content-Type", "application/json")
@@ -197,6 +206,23 @@ impl NotaryApiClient {
                 "{}/{}",
                 APPLE_NOTARY_SUBMIT_SOFTWARE_URL, submission_id
             ))
+            .timeout(self.api_timeout()?)
+            .bearer_auth(token)
+            .header("Accept", "application/json");
+
+        self.send_request(req)
+    }
+
+    /// Fetch the most recent submissions made by the team.
+    ///
+    /// The service returns at most the 100 most recent submissions.
+    pub fn list_submissions(&self) -> Result<SubmissionListResponse, AppleCodesignError> {
+        let token = self.get_token()?;
+
+        let req = self
+            .client
+            .get(APPLE_NOTARY_SUBMIT_SOFTWARE_URL)
+            .timeout(self.api_timeout()?)
             .bearer_auth(token)
             .header("Accept", "application/json");
 
@@ -213,13 +239,19 @@ impl NotaryApiClient {
                 "{}/{}/logs",
                 APPLE_NOTARY_SUBMIT_SOFTWARE_URL, submission_id
             ))
+            .timeout(self.api_timeout()?)
             .bearer_auth(token)
             .header("Accept", "application/json");
 
         let res = self.send_request::<SubmissionLogResponse>(req)?;
 
         let url = res.data.attributes.developer_log_url;
-        let logs = self.client.get(url).send()?.json::<Value>()?;
+        let logs = self
+            .client
+            .get(url)
+            .timeout(self.api_timeout()?)
+            .send()?
+            .json::<Value>()?;
 
         Ok(logs)
     }
@@ -0,0 +1,289 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Notifications about the outcome of long running operations.
+//!
+//! Waiting on build processing or notarization can take a long time. Rather
+//! than wrapping the CLI in another script to find out how it went, a
+//! [NotificationSink] can be attached to [AppStoreConnectClient] and
+//! [crate::notarization::Notarizer]. It receives a [NotificationEvent] when an
+//! operation reaches a terminal state, and when the client talks to a
+//! deprecated endpoint (see [super::deprecation]).
+//!
+//! [WebhookSink] POSTs a JSON document to a URL, e.g. a Slack incoming
+//! webhook. With the `cli` feature, it can be configured with TOML:
+//!
+//! ```toml
+//! url = "https://hooks.slack.com/services/T000/B000/XXXX"
+//! template = '{"text": "{{event}}: {{message}}"}'
+//! events = ["build-processing-failed", "notarization-invalid"]
+//!
+//! [headers]
+//! X-Team = "release"
+//! ```
+//!
+//! The template is a JSON document in which `{{event}}`, `{{subject}}`,
+//! `{{message}}`, and `{{timestamp}}` are replaced by the properties of the
+//! event. Values are escaped for use inside JSON strings. Without a template,
+//! the event is sent as a JSON object with those keys. Without `events`,
+//! every event is sent.
+//!
+//! Notification failures are logged and don't fail the operation.
+
+use {
+    crate::{app_store_connect::AppStoreConnectClient, AppleCodesignError},
+    chrono::Utc,
+    log::warn,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+};
+
+/// The terminal state an operation reached.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationKind {
+    /// A build finished processing and can be distributed.
+    BuildProcessed,
+
+    /// Processing of a build failed.
+    BuildProcessingFailed,
+
+    /// Apple accepted a notarization submission.
+    NotarizationAccepted,
+
+    /// Apple found problems with a notarization submission.
+    NotarizationInvalid,
+
+    /// App Review rejected a submission.
+    ReviewRejected,
+
+    /// A request was answered by an endpoint Apple has deprecated or removed.
+    EndpointDeprecated,
+}
+
+impl NotificationKind {
+    /// The name of the kind as used in configuration and payloads.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BuildProcessed => "build-processed",
+            Self::BuildProcessingFailed => "build-processing-failed",
+            Self::NotarizationAccepted => "notarization-accepted",
+            Self::NotarizationInvalid => "notarization-invalid",
+            Self::ReviewRejected => "review-rejected",
+            Self::EndpointDeprecated => "endpoint-deprecated",
+        }
+    }
+}
+
+/// An operation reached a terminal state.
+#[derive(Clone, Debug, Serialize)]
+pub struct NotificationEvent {
+    pub event: NotificationKind,
+
+    /// ID of the resource the operation was about, e.g. a build ID.
+    pub subject: String,
+
+    /// Human readable description of the outcome.
+    pub message: String,
+
+    /// RFC 3339 time the event occurred.
+    pub timestamp: String,
+}
+
+impl NotificationEvent {
+    /// Construct an event occurring now.
+    pub fn new(event: NotificationKind, subject: impl ToString, message: impl ToString) -> Self {
+        Self {
+            event,
+            subject: subject.to_string(),
+            message: message.to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Receives [NotificationEvent]s.
+pub trait NotificationSink: Send + Sync {
+    /// Deliver an event.
+    fn notify(&self, event: &NotificationEvent) -> Result<(), AppleCodesignError>;
+}
+
+/// A [NotificationSink] POSTing JSON to a URL.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSink {
+    /// URL to POST to.
+    pub url: String,
+
+    /// JSON payload template. See the [module documentation](self).
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Kinds of events to send. All kinds if not set.
+    #[serde(default)]
+    pub events: Option<Vec<NotificationKind>>,
+
+    /// Additional HTTP headers.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl WebhookSink {
+    /// Parse a webhook from TOML.
+    #[cfg(feature = "cli")]
+    pub fn from_toml(data: &str) -> Result<Self, AppleCodesignError> {
+        let sink = toml::from_str::<Self>(data)
+            .map_err(|e| AppleCodesignError::NotificationConfig(e.to_string()))?;
+
+        sink.validate()?;
+
+        Ok(sink)
+    }
+
+    /// Read a webhook from a TOML file.
+    #[cfg(feature = "cli")]
+    pub fn from_path(path: &std::path::Path) -> Result<Self, AppleCodesignError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Check that the URL is an HTTP(S) URL and the template renders.
+    pub fn validate(&self) -> Result<(), AppleCodesignError> {
+        if !self.url.starts_with("https://") && !self.url.starts_with("http://") {
+            return Err(AppleCodesignError::NotificationConfig(format!(
+                "url must be an HTTP(S) URL: {}",
+                self.url
+            )));
+        }
+
+        // Catch broken templates up front rather than when an event occurs.
+        self.payload(&NotificationEvent::new(
+            NotificationKind::BuildProcessed,
+            "subject",
+            "message",
+        ))?;
+
+        Ok(())
+    }
+
+    /// Whether an event of a kind is sent.
+    pub fn wants(&self, kind: NotificationKind) -> bool {
+        self.events
+            .as_ref()
+            .is_none_or(|events| events.contains(&kind))
+    }
+
+    /// The JSON document sent for an event.
+    pub fn payload(
+        &self,
+        event: &NotificationEvent,
+    ) -> Result<serde_json::Value, AppleCodesignError> {
+        let template = match &self.template {
+            Some(template) => template,
+            None => return Ok(serde_json::to_value(event)?),
+        };
+
+        let mut rendered = template.clone();
+        for (placeholder, value) in [
+            ("{{event}}", event.event.as_str()),
+            ("{{subject}}", event.subject.as_str()),
+            ("{{message}}", event.message.as_str()),
+            ("{{timestamp}}", event.timestamp.as_str()),
+        ] {
+            // Escape the value as a JSON string and strip the quotes.
+            let escaped = serde_json::to_string(value)?;
+            rendered = rendered.replace(placeholder, &escaped[1..escaped.len() - 1]);
+        }
+
+        serde_json::from_str(&rendered).map_err(|e| {
+            AppleCodesignError::NotificationConfig(format!("template is not valid JSON: {}", e))
+        })
+    }
+}
+
+impl NotificationSink for WebhookSink {
+    fn notify(&self, event: &NotificationEvent) -> Result<(), AppleCodesignError> {
+        if !self.wants(event.event) {
+            return Ok(());
+        }
+
+        let mut request = crate::ticket_lookup::default_client()?
+            .post(&self.url)
+            .json(&self.payload(event)?);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        request.send()?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Deliver an event to the notification sink, if one is set.
+    ///
+    /// Failures are logged.
+    pub fn notify(&self, event: NotificationEvent) {
+        if let Some(sink) = &self.notification_sink {
+            if let Err(e) = sink.notify(&event) {
+                warn!(
+                    "unable to deliver {} notification: {}",
+                    event.event.as_str(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{MockResponse, MockServerBuilder},
+    };
+
+    #[test]
+    fn webhook() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .route("POST", "/hook", MockResponse::empty(200))
+            .start()?;
+
+        let sink = WebhookSink::from_toml(&format!(
+            r#"
+            url = "{}/hook"
+            template = '{{"text": "{{{{event}}}} {{{{subject}}}}: {{{{message}}}}"}}'
+            events = ["build-processing-failed"]
+
+            [headers]
+            X-Team = "release"
+            "#,
+            server.url()
+        ))?;
+
+        sink.notify(&NotificationEvent::new(
+            NotificationKind::BuildProcessed,
+            "B1",
+            "finished processing",
+        ))?;
+        sink.notify(&NotificationEvent::new(
+            NotificationKind::BuildProcessingFailed,
+            "B2",
+            "state \"INVALID\"",
+        ))?;
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("x-team"), Some("release"));
+        assert_eq!(
+            requests[0].json()?,
+            serde_json::json!({"text": "build-processing-failed B2: state \"INVALID\""})
+        );
+
+        assert!(WebhookSink::from_toml("url = \"https://example.com\"\ntemplate = '{'").is_err());
+        assert!(WebhookSink::from_toml("url = \"ftp://example.com\"").is_err());
+
+        Ok(())
+    }
+}
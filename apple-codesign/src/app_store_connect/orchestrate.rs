@@ -0,0 +1,98 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Running operations across many apps.
+//!
+//! Accounts managing many apps often need to perform the same operation (pull
+//! metadata, distribute builds, etc) on each of them. [orchestrate()] runs an
+//! operation against a set of apps concurrently and records the outcome for
+//! each app, so a failure for one app doesn't abort the others.
+
+use {
+    crate::{app_store_connect::AppStoreConnectClient, AppleCodesignError},
+    log::{error, warn},
+};
+
+/// Default number of apps operated on concurrently.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// The outcome of an operation for a single app.
+#[derive(Debug)]
+pub struct AppResult<T> {
+    /// ID of the app the operation ran against.
+    pub app_id: String,
+
+    /// Result of the operation.
+    pub result: Result<T, AppleCodesignError>,
+}
+
+/// Run an operation against multiple apps concurrently.
+///
+/// At most `concurrency` operations are in flight at once. Results are returned
+/// in the order of `app_ids`, regardless of completion order.
+pub fn orchestrate<T, F>(
+    client: &AppStoreConnectClient,
+    app_ids: &[String],
+    concurrency: usize,
+    op: F,
+) -> Result<Vec<AppResult<T>>, AppleCodesignError>
+where
+    T: Send,
+    F: Fn(&AppStoreConnectClient, &str) -> Result<T, AppleCodesignError> + Sync,
+{
+    run_per_app(app_ids, concurrency, |app_id| op(client, app_id))
+}
+
+/// Run an operation against multiple apps concurrently.
+///
+/// Like [orchestrate()], but the operation obtains its own client.
+pub(crate) fn run_per_app<T, F>(
+    app_ids: &[String],
+    concurrency: usize,
+    op: F,
+) -> Result<Vec<AppResult<T>>, AppleCodesignError>
+where
+    T: Send,
+    F: Fn(&str) -> Result<T, AppleCodesignError> + Sync,
+{
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency.max(1))
+        .build()
+        .map_err(|e| AppleCodesignError::LogicError(format!("thread pool: {}", e)))?;
+
+    warn!(
+        "running operation against {} apps ({} concurrently)",
+        app_ids.len(),
+        concurrency.max(1)
+    );
+
+    Ok(pool.install(|| {
+        app_ids
+            .par_iter()
+            .map(|app_id| {
+                let result = op(app_id);
+
+                if let Err(e) = &result {
+                    error!("app {}: {}", app_id, e);
+                }
+
+                AppResult {
+                    app_id: app_id.clone(),
+                    result,
+                }
+            })
+            .collect::<Vec<_>>()
+    }))
+}
+
+impl AppStoreConnectClient {
+    /// Resolve the IDs of every app in the account.
+    ///
+    /// Useful as input to [orchestrate()] for account-wide operations.
+    pub fn all_app_ids(&self) -> Result<Vec<String>, AppleCodesignError> {
+        Ok(self.list_apps()?.into_iter().map(|app| app.id).collect())
+    }
+}
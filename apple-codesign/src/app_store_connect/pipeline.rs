@@ -0,0 +1,435 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Declarative TestFlight release pipelines.
+//!
+//! Releasing a build to TestFlight involves several API operations: uploading
+//! the `.ipa`, waiting for Apple to process it, defining what testers should
+//! test, distributing the build to beta groups, and submitting it for beta app
+//! review. A [PipelineConfig] describes all of these in a TOML file so the
+//! release is a single reproducible unit.
+//!
+//! Pipelines are resumable. After each step completes, a [PipelineState] is
+//! written next to the pipeline file. If the pipeline is run again (e.g. after a
+//! network failure or a CI timeout), completed steps are skipped. The state is
+//! discarded if the `.ipa` content changes.
+//!
+//! Steps creating resources (the upload and the beta app review submission)
+//! record them in an [IdempotencyJournal] next to the pipeline file and look
+//! for them on the server before creating them. A step interrupted after its
+//! request was sent doesn't create a duplicate when the pipeline is resumed.
+//!
+//! If `write_provenance` is set, a [Provenance] of the delivery is written next
+//! to the `.ipa` once the build is known. It is signed if an [ArtifactSigner] is
+//! set with [Pipeline::set_artifact_signer()].
+//!
+//! An example pipeline file:
+//!
+//! ```toml
+//! app_id = "1234567890"
+//! ipa = "build/MyApp.ipa"
+//! max_wait_seconds = 3600
+//! uses_non_exempt_encryption = false
+//! beta_groups = ["QA", "Friends and Family"]
+//! submit_for_beta_review = true
+//! write_provenance = true
+//!
+//! [whats_to_test]
+//! en-US = "Try the new onboarding flow."
+//! ```
+
+use {
+    crate::{
+        app_store_connect::{
+            artifact_signing::ArtifactSigner, build_upload_api::Platform, cache_scope::CacheScope,
+            idempotency::IdempotencyJournal, json_api::parse_api_date, locales::LocalizedResource,
+            provenance::Provenance, AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::Utc,
+    log::warn,
+    serde::{Deserialize, Serialize},
+    sha2::Digest,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Path, PathBuf},
+        time::Duration,
+    },
+};
+
+fn default_max_wait_seconds() -> u64 {
+    3600
+}
+
+fn default_platform() -> Platform {
+    Platform::Ios
+}
+
+/// Definition of a pipeline.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// ID of the app being released.
+    pub app_id: String,
+
+    /// Path to the `.ipa` to upload.
+    ///
+    /// Relative paths are relative to the directory of the pipeline file.
+    pub ipa: PathBuf,
+
+    /// Platform of the `.ipa`.
+    #[serde(default = "default_platform")]
+    pub platform: Platform,
+
+    /// Maximum time in seconds to wait for Apple to process the upload.
+    #[serde(default = "default_max_wait_seconds")]
+    pub max_wait_seconds: u64,
+
+    /// Export compliance declaration to make if the build lacks one.
+    #[serde(default)]
+    pub uses_non_exempt_encryption: Option<bool>,
+
+    /// *What to Test* text, keyed by locale.
+    #[serde(default)]
+    pub whats_to_test: BTreeMap<String, String>,
+
+    /// Names of beta groups to distribute the build to.
+    #[serde(default)]
+    pub beta_groups: Vec<String>,
+
+    /// Whether to submit the build for beta app review.
+    #[serde(default)]
+    pub submit_for_beta_review: bool,
+
+    /// Whether to write a [Provenance] next to the `.ipa`.
+    #[serde(default)]
+    pub write_provenance: bool,
+}
+
+impl PipelineConfig {
+    /// Parse a pipeline from TOML.
+    ///
+    /// Locales of *What to Test* text are validated.
+    pub fn from_toml(data: &str) -> Result<Self, AppleCodesignError> {
+        let config = toml::from_str::<Self>(data)
+            .map_err(|e| AppleCodesignError::PipelineConfig(e.to_string()))?;
+
+        LocalizedResource::BetaBuild
+            .validate_locales(config.whats_to_test.keys().map(|x| x.as_str()))?;
+
+        Ok(config)
+    }
+}
+
+/// A step of a pipeline.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum PipelineStep {
+    Upload,
+    WaitProcessing,
+    WhatsToTest,
+    Distribute,
+    SubmitBetaReview,
+}
+
+/// Checkpoint state of a pipeline run.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PipelineState {
+    /// SHA-256 of the `.ipa` this state applies to.
+    pub ipa_sha256: String,
+
+    /// [CacheScope] of the API key this state was written with.
+    #[serde(default)]
+    pub scope: String,
+
+    /// ID of the build upload created by the upload step.
+    pub build_upload_id: Option<String>,
+
+    /// RFC 3339 times the upload step started and finished.
+    #[serde(default)]
+    pub upload_started: Option<String>,
+    #[serde(default)]
+    pub upload_finished: Option<String>,
+
+    /// ID of the build resulting from the upload.
+    pub build_id: Option<String>,
+
+    /// Steps that have completed.
+    pub completed: BTreeSet<PipelineStep>,
+}
+
+/// A runnable pipeline.
+pub struct Pipeline {
+    config: PipelineConfig,
+    base_dir: PathBuf,
+    state_path: PathBuf,
+    journal_path: PathBuf,
+    signer: Option<ArtifactSigner>,
+}
+
+impl Pipeline {
+    /// Load a pipeline from a TOML file.
+    ///
+    /// Checkpoint state is stored in a `.state.json` file next to it and the
+    /// [IdempotencyJournal] in a `.journal.json` file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        let config = PipelineConfig::from_toml(&std::fs::read_to_string(path)?)?;
+
+        let base_dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+
+        let mut state_path = path.as_os_str().to_owned();
+        state_path.push(".state.json");
+        let mut journal_path = path.as_os_str().to_owned();
+        journal_path.push(".journal.json");
+
+        Ok(Self {
+            config,
+            base_dir,
+            state_path: PathBuf::from(state_path),
+            journal_path: PathBuf::from(journal_path),
+            signer: None,
+        })
+    }
+
+    /// Sign artifacts written by the pipeline, such as the provenance.
+    pub fn set_artifact_signer(&mut self, signer: Option<ArtifactSigner>) {
+        self.signer = signer;
+    }
+
+    /// The definition of this pipeline.
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Path of the file holding checkpoint state.
+    pub fn state_path(&self) -> &Path {
+        &self.state_path
+    }
+
+    /// Path of the file holding the [IdempotencyJournal].
+    pub fn journal_path(&self) -> &Path {
+        &self.journal_path
+    }
+
+    /// Path of the `.ipa` to upload.
+    pub fn ipa_path(&self) -> PathBuf {
+        self.base_dir.join(&self.config.ipa)
+    }
+
+    fn load_state(
+        &self,
+        ipa_sha256: &str,
+        scope: &CacheScope,
+    ) -> Result<PipelineState, AppleCodesignError> {
+        if self.state_path.exists() {
+            let state = serde_json::from_slice::<PipelineState>(&std::fs::read(&self.state_path)?)?;
+
+            if state.scope != scope.as_str() {
+                warn!("pipeline state was written with another API key; starting pipeline from scratch");
+            } else if state.ipa_sha256 == ipa_sha256 {
+                warn!(
+                    "resuming pipeline from {}; completed steps: {:?}",
+                    self.state_path.display(),
+                    state.completed
+                );
+                return Ok(state);
+            } else {
+                warn!("ipa content changed since last run; starting pipeline from scratch");
+            }
+        }
+
+        Ok(PipelineState {
+            ipa_sha256: ipa_sha256.to_string(),
+            scope: scope.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn save_state(&self, state: &PipelineState) -> Result<(), AppleCodesignError> {
+        std::fs::write(&self.state_path, serde_json::to_vec_pretty(state)?)?;
+
+        Ok(())
+    }
+
+    fn write_provenance(
+        &self,
+        client: &AppStoreConnectClient,
+        ipa_path: &Path,
+        upload_id: &str,
+        build_id: &str,
+        state: &PipelineState,
+    ) -> Result<(), AppleCodesignError> {
+        // State written before upload times were recorded lacks them.
+        let parse = |s: &Option<String>| s.as_deref().and_then(parse_api_date);
+        let started = parse(&state.upload_started).unwrap_or_else(Utc::now);
+        let finished = parse(&state.upload_finished).unwrap_or(started);
+
+        let mut provenance = Provenance::new(
+            &ipa_path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            &std::fs::read(ipa_path)?,
+            &self.config.app_id,
+            upload_id,
+            client.api_key_id(),
+            started,
+            finished,
+        )?;
+        provenance.build_id = Some(build_id.to_string());
+
+        let path = Provenance::path_for(ipa_path);
+        warn!("writing provenance to {}", path.display());
+        provenance.write(&path)?;
+
+        if let Some(signer) = &self.signer {
+            warn!("signing provenance with key {}", signer.key_id());
+            signer.sign_file(&path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the pipeline, skipping steps completed by previous runs.
+    pub fn run(&self, client: &AppStoreConnectClient) -> Result<PipelineState, AppleCodesignError> {
+        let ipa_path = self.ipa_path();
+        let ipa_sha256 = hex::encode(sha2::Sha256::digest(std::fs::read(&ipa_path)?));
+
+        let mut state = self.load_state(&ipa_sha256, &client.cache_scope())?;
+        let mut journal = IdempotencyJournal::open(&self.journal_path, &client.cache_scope())?;
+        let wait_limit = Duration::from_secs(self.config.max_wait_seconds);
+        let poll_interval = Duration::from_secs(15);
+
+        if !state.completed.contains(&PipelineStep::Upload) {
+            warn!("pipeline step: upload {}", ipa_path.display());
+            let started = Utc::now();
+            let (upload_id, _) = client.upload_ipa_once(
+                &mut journal,
+                &self.config.app_id,
+                &ipa_path,
+                self.config.platform,
+            )?;
+            state.build_upload_id = Some(upload_id);
+            state.upload_started = Some(started.to_rfc3339());
+            state.upload_finished = Some(Utc::now().to_rfc3339());
+            state.completed.insert(PipelineStep::Upload);
+            self.save_state(&state)?;
+        }
+
+        if !state.completed.contains(&PipelineStep::WaitProcessing) {
+            warn!("pipeline step: wait for processing");
+            let upload_id = state.build_upload_id.clone().ok_or_else(|| {
+                AppleCodesignError::PipelineConfig("state lacks build upload ID".into())
+            })?;
+
+            let build = client.wait_on_build_upload(&upload_id, wait_limit, poll_interval)?;
+            let build = client.wait_on_build_processing(&build.id, wait_limit, poll_interval)?;
+
+            if build.attributes.uses_non_exempt_encryption.is_none() {
+                if let Some(value) = self.config.uses_non_exempt_encryption {
+                    client.set_build_uses_non_exempt_encryption(&build.id, value)?;
+                }
+            }
+
+            if self.config.write_provenance {
+                self.write_provenance(client, &ipa_path, &upload_id, &build.id, &state)?;
+            }
+
+            state.build_id = Some(build.id);
+            state.completed.insert(PipelineStep::WaitProcessing);
+            self.save_state(&state)?;
+        }
+
+        let build_id = state
+            .build_id
+            .clone()
+            .ok_or_else(|| AppleCodesignError::PipelineConfig("state lacks build ID".into()))?;
+
+        if !state.completed.contains(&PipelineStep::WhatsToTest) {
+            for (locale, text) in &self.config.whats_to_test {
+                warn!("pipeline step: set what to test for {}", locale);
+                client.set_build_whats_new(&build_id, locale, text)?;
+            }
+
+            state.completed.insert(PipelineStep::WhatsToTest);
+            self.save_state(&state)?;
+        }
+
+        if !state.completed.contains(&PipelineStep::Distribute) {
+            if !self.config.beta_groups.is_empty() {
+                let groups = client.list_beta_groups(&self.config.app_id, None)?;
+
+                for name in &self.config.beta_groups {
+                    let group = groups
+                        .iter()
+                        .find(|g| g.attributes.name.as_ref() == Some(name))
+                        .ok_or_else(|| {
+                            AppleCodesignError::PipelineConfig(format!(
+                                "beta group {} does not exist",
+                                name
+                            ))
+                        })?;
+
+                    warn!("pipeline step: distribute to beta group {}", name);
+                    client.add_builds_to_beta_group(&group.id, &[&build_id])?;
+                }
+            }
+
+            state.completed.insert(PipelineStep::Distribute);
+            self.save_state(&state)?;
+        }
+
+        if !state.completed.contains(&PipelineStep::SubmitBetaReview) {
+            if self.config.submit_for_beta_review {
+                warn!("pipeline step: submit for beta app review");
+                client.submit_build_for_beta_review_once(&mut journal, &build_id)?;
+            }
+
+            state.completed.insert(PipelineStep::SubmitBetaReview);
+            self.save_state(&state)?;
+        }
+
+        warn!("pipeline complete; build {}", build_id);
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_config() -> Result<(), AppleCodesignError> {
+        let config = PipelineConfig::from_toml(
+            r#"
+            app_id = "42"
+            ipa = "MyApp.ipa"
+            beta_groups = ["QA"]
+
+            [whats_to_test]
+            en-US = "Everything"
+            "#,
+        )?;
+
+        assert_eq!(config.app_id, "42");
+        assert_eq!(config.platform, Platform::Ios);
+        assert_eq!(config.max_wait_seconds, 3600);
+        assert_eq!(config.beta_groups, vec!["QA".to_string()]);
+        assert_eq!(
+            config.whats_to_test.get("en-US").map(|x| x.as_str()),
+            Some("Everything")
+        );
+        assert!(!config.submit_for_beta_review);
+
+        assert!(PipelineConfig::from_toml("app_id = \"42\"\nipa = \"a\"\nbogus = 1").is_err());
+        assert!(PipelineConfig::from_toml(
+            "app_id = \"42\"\nipa = \"a\"\n[whats_to_test]\nen_us = \"x\""
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Local previews of metadata before it is pushed.
+//!
+//! `beta-metadata-push` replaces the TestFlight information testers see.
+//! [render_beta_app_metadata_html()] renders a [BetaAppMetadata] as a single,
+//! self-contained HTML page with a section per locale, so the metadata can be
+//! reviewed and signed off before it is pushed. Screenshots found by
+//! [find_screenshots()] are embedded next to the metadata of their locale.
+//!
+//! This crate doesn't manage App Store product page metadata, so previews
+//! only cover TestFlight metadata.
+
+use {
+    crate::{app_store_connect::testflight_api::BetaAppMetadata, AppleCodesignError},
+    std::{collections::BTreeMap, fmt::Write, path::Path},
+};
+
+/// An image to show in a preview.
+#[derive(Clone, Debug)]
+pub struct PreviewScreenshot {
+    /// File name of the image.
+    pub name: String,
+
+    /// MIME type of the image.
+    pub media_type: &'static str,
+
+    pub data: Vec<u8>,
+}
+
+/// Escape text for inclusion in HTML content or attribute values.
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Find the screenshots of a locale.
+///
+/// Screenshots are PNG or JPEG files in the `<locale>` subdirectory of `dir`,
+/// ordered by file name. A locale without a subdirectory has no screenshots.
+pub fn find_screenshots(
+    dir: &Path,
+    locale: &str,
+) -> Result<Vec<PreviewScreenshot>, AppleCodesignError> {
+    let dir = dir.join(locale);
+
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut paths = std::fs::read_dir(&dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    paths.sort();
+
+    let mut screenshots = vec![];
+
+    for path in paths {
+        let media_type = match path
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_lowercase())
+            .as_deref()
+        {
+            Some("png") => "image/png",
+            Some("jpg" | "jpeg") => "image/jpeg",
+            _ => continue,
+        };
+
+        screenshots.push(PreviewScreenshot {
+            name: path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            media_type,
+            data: std::fs::read(&path)?,
+        });
+    }
+
+    Ok(screenshots)
+}
+
+/// Render TestFlight metadata and screenshots keyed by locale as an HTML page.
+pub fn render_beta_app_metadata_html(
+    title: &str,
+    metadata: &BetaAppMetadata,
+    screenshots: &BTreeMap<String, Vec<PreviewScreenshot>>,
+) -> String {
+    let mut html = String::new();
+
+    // Writing to a String doesn't fail.
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n\
+         body {{ font-family: -apple-system, sans-serif; max-width: 60em; margin: auto; }}\n\
+         section {{ border-top: 1px solid #ccc; padding: 1em 0; }}\n\
+         .description {{ white-space: pre-wrap; }}\n\
+         .screenshots img {{ max-height: 24em; margin-right: 1em; }}\n\
+         </style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape_html(title),
+        escape_html(title)
+    );
+
+    for (locale, localization) in &metadata.localizations {
+        let _ = writeln!(
+            html,
+            "<section lang=\"{}\">\n<h2>{}</h2>",
+            escape_html(locale),
+            escape_html(locale)
+        );
+
+        if let Some(description) = &localization.description {
+            let _ = writeln!(
+                html,
+                "<p class=\"description\">{}</p>",
+                escape_html(description)
+            );
+        }
+
+        if let Some(images) = screenshots.get(locale) {
+            html.push_str("<div class=\"screenshots\">\n");
+            for image in images {
+                let _ = writeln!(
+                    html,
+                    "<img alt=\"{}\" src=\"data:{};base64,{}\">",
+                    escape_html(&image.name),
+                    image.media_type,
+                    base64::encode(&image.data)
+                );
+            }
+            html.push_str("</div>\n");
+        }
+
+        html.push_str("<dl>\n");
+        for (label, value) in [
+            ("Feedback email", &localization.feedback_email),
+            ("Marketing URL", &localization.marketing_url),
+            ("Privacy policy URL", &localization.privacy_policy_url),
+            ("tvOS privacy policy", &localization.tv_os_privacy_policy),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(html, "<dt>{}</dt><dd>{}</dd>", label, escape_html(value));
+            }
+        }
+        html.push_str("</dl>\n</section>\n");
+    }
+
+    if let Some(agreement) = &metadata.license_agreement {
+        let _ = writeln!(
+            html,
+            "<section>\n<h2>License agreement</h2>\n<p class=\"description\">{}</p>\n</section>",
+            escape_html(agreement)
+        );
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::app_store_connect::testflight_api::BetaAppLocalizationAttributes};
+
+    #[test]
+    fn render() -> Result<(), AppleCodesignError> {
+        let mut metadata = BetaAppMetadata {
+            license_agreement: Some("Be nice.".into()),
+            ..Default::default()
+        };
+        metadata.localizations.insert(
+            "en-US".into(),
+            BetaAppLocalizationAttributes {
+                description: Some("Try <b>this</b> & that".into()),
+                feedback_email: Some("beta@example.com".into()),
+                ..Default::default()
+            },
+        );
+
+        let td = tempfile::TempDir::new()?;
+        std::fs::create_dir(td.path().join("en-US"))?;
+        std::fs::write(td.path().join("en-US").join("2.png"), b"png")?;
+        std::fs::write(td.path().join("en-US").join("1.JPG"), b"jpg")?;
+        std::fs::write(td.path().join("en-US").join("notes.txt"), b"text")?;
+
+        let screenshots = find_screenshots(td.path(), "en-US")?;
+        assert_eq!(
+            screenshots
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1.JPG", "2.png"]
+        );
+        assert!(find_screenshots(td.path(), "de-DE")?.is_empty());
+
+        let html = render_beta_app_metadata_html(
+            "My App",
+            &metadata,
+            &[("en-US".to_string(), screenshots)].into_iter().collect(),
+        );
+        assert!(html.contains("Try &lt;b&gt;this&lt;/b&gt; &amp; that"));
+        assert!(html.contains("<dt>Feedback email</dt><dd>beta@example.com</dd>"));
+        assert!(html.contains("src=\"data:image/jpeg;base64,anBn\""));
+        assert!(html.contains("Be nice."));
+
+        Ok(())
+    }
+}
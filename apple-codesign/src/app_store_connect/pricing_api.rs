@@ -0,0 +1,526 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App prices per territory.
+//!
+//! The price schedule of an app has a base territory and manual prices for
+//! individual territories. Territories without a manual price are priced by
+//! App Store Connect based on the base territory.
+//!
+//! Prices can be edited in bulk as CSV with one [PriceRow] per territory:
+//! [AppStoreConnectClient::list_app_manual_prices()] obtains the rows,
+//! `read_price_rows()` and `write_price_rows()` convert them from and to CSV
+//! (with the `cli` feature).
+//! [AppStoreConnectClient::plan_app_price_change()] resolves the price points
+//! of edited rows and [AppStoreConnectClient::apply_app_price_change()]
+//! replaces the price schedule with one containing the changes.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/app_price_schedules>.
+
+pub use asconnect_types::pricing::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            json_api::{
+                included_resources, AppPricePointQuery, Document, RequestDocument,
+                ResourceIdentifier,
+            },
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::{NaiveDate, Utc},
+    serde::{Deserialize, Serialize},
+};
+
+/// A price of an app in a territory, as a CSV record.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PriceRow {
+    /// ISO 3166-1 alpha-3 code of the territory, like `USA`.
+    pub territory: String,
+
+    /// Currency of prices in the territory. Ignored when importing.
+    #[serde(default)]
+    pub currency: Option<String>,
+
+    /// Price paid by customers, as a decimal number.
+    pub customer_price: String,
+
+    /// Proceeds of the developer. Ignored when importing.
+    #[serde(default)]
+    pub proceeds: Option<String>,
+
+    /// First day the price is in effect. Ignored when importing.
+    #[serde(default)]
+    pub start_date: Option<String>,
+
+    /// Day the price stops being in effect. Ignored when importing.
+    #[serde(default)]
+    pub end_date: Option<String>,
+}
+
+impl PriceRow {
+    /// Whether the price is in effect on a day formatted as `YYYY-MM-DD`.
+    pub fn is_in_effect(&self, day: &str) -> bool {
+        self.start_date.as_deref().map(|d| d <= day).unwrap_or(true)
+            && self.end_date.as_deref().map(|d| d > day).unwrap_or(true)
+    }
+}
+
+/// A manual price in a price schedule.
+#[derive(Clone, Debug)]
+pub struct ManualPrice {
+    /// ID of the price point of the price.
+    pub price_point_id: String,
+
+    pub row: PriceRow,
+}
+
+/// A change of the price in a territory.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceChange {
+    pub territory: String,
+
+    /// The customer price in effect, if the territory has a manual price.
+    pub before: Option<String>,
+
+    /// The new customer price.
+    pub after: String,
+
+    /// ID of the price point of the new price.
+    pub price_point_id: String,
+}
+
+/// Changes to the price schedule of an app, to be applied with
+/// [AppStoreConnectClient::apply_app_price_change()].
+#[derive(Clone, Debug)]
+pub struct PricePlan {
+    pub app_id: String,
+
+    /// The base territory of the price schedule.
+    pub base_territory: String,
+
+    /// Day the changes take effect. Changes take effect immediately if unset.
+    pub start_date: Option<String>,
+
+    /// Manual prices currently in effect.
+    pub current: Vec<ManualPrice>,
+
+    pub changes: Vec<PriceChange>,
+}
+
+/// Read price rows from CSV data having a header row.
+#[cfg(feature = "cli")]
+pub fn read_price_rows(reader: impl std::io::Read) -> Result<Vec<PriceRow>, AppleCodesignError> {
+    csv::Reader::from_reader(reader)
+        .deserialize()
+        .map(|row| row.map_err(AppleCodesignError::from))
+        .collect()
+}
+
+/// Write price rows as CSV data having a header row.
+#[cfg(feature = "cli")]
+pub fn write_price_rows(
+    writer: impl std::io::Write,
+    rows: &[PriceRow],
+) -> Result<(), AppleCodesignError> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    for row in rows {
+        writer.serialize(row)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Parse a decimal price.
+pub(crate) fn parse_price(value: &str) -> Result<f64, AppleCodesignError> {
+    value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| AppleCodesignError::InvalidPrice(format!("{} is not a number", value)))
+}
+
+/// Whether two decimal prices are equal.
+///
+/// Prices may be formatted differently, e.g. `1.9` and `1.90`.
+fn same_price(a: &str, b: &str) -> bool {
+    match (parse_price(a), parse_price(b)) {
+        (Ok(a), Ok(b)) => (a - b).abs() < 0.0005,
+        _ => false,
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Obtain the price schedule of an app.
+    ///
+    /// The relationship to the base territory is included.
+    pub fn get_app_price_schedule(
+        &self,
+        app_id: &str,
+    ) -> Result<AppPriceSchedule, AppleCodesignError> {
+        let doc = self.api_get::<Document<AppPriceSchedule>>(
+            &format!("/v1/apps/{}/appPriceSchedule", app_id),
+            &[("include", "baseTerritory".to_string())],
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// List the manual prices of a price schedule.
+    ///
+    /// This includes past and scheduled prices.
+    pub fn list_app_manual_prices(
+        &self,
+        schedule_id: &str,
+    ) -> Result<Vec<ManualPrice>, AppleCodesignError> {
+        let (prices, included) = self.api_get_all_with_included::<AppPriceAttributes>(
+            &format!("/v1/appPriceSchedules/{}/manualPrices", schedule_id),
+            &[
+                ("include", "appPricePoint,territory".to_string()),
+                ("limit", "200".to_string()),
+            ],
+        )?;
+
+        // Price points and territories are only linked from prices via
+        // included resources.
+        let points = included_resources::<AppPricePointAttributes>(&included, "appPricePoints")?;
+        let territories = included_resources::<TerritoryAttributes>(&included, "territories")?;
+
+        Ok(prices
+            .into_iter()
+            .filter_map(|price| {
+                let point_id = price.related("appPricePoint").first()?.id.clone();
+                let territory = price.related("territory").first()?.id.clone();
+                let point = points.get(&point_id);
+
+                Some(ManualPrice {
+                    row: PriceRow {
+                        currency: territories
+                            .get(&territory)
+                            .and_then(|t| t.attributes.currency.clone()),
+                        territory,
+                        customer_price: point
+                            .and_then(|p| p.attributes.customer_price.clone())
+                            .unwrap_or_default(),
+                        proceeds: point.and_then(|p| p.attributes.proceeds.clone()),
+                        start_date: price.attributes.start_date,
+                        end_date: price.attributes.end_date,
+                    },
+                    price_point_id: point_id,
+                })
+            })
+            .collect())
+    }
+
+    /// List the price points of an app in a territory.
+    pub fn list_app_price_points(
+        &self,
+        app_id: &str,
+        territory: &str,
+    ) -> Result<Vec<AppPricePoint>, AppleCodesignError> {
+        let mut query = AppPricePointQuery::new().territory(territory).to_query();
+        query.push(("limit", "200".to_string()));
+
+        self.api_get_all(&format!("/v1/apps/{}/appPricePoints", app_id), &query)
+    }
+
+    /// Compute the changes needed for an app to have the prices of rows.
+    ///
+    /// Only the territory and customer price of rows are used. Rows whose price
+    /// is already in effect are ignored. The customer price of other rows must
+    /// be a price point of the territory.
+    ///
+    /// `start_date` is the day (formatted as `YYYY-MM-DD`) the changes take
+    /// effect. Changes take effect immediately if it is unset.
+    pub fn plan_app_price_change(
+        &self,
+        app_id: &str,
+        rows: &[PriceRow],
+        start_date: Option<&str>,
+    ) -> Result<PricePlan, AppleCodesignError> {
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+
+        if let Some(start_date) = start_date {
+            NaiveDate::parse_from_str(start_date, "%Y-%m-%d").map_err(|_| {
+                AppleCodesignError::InvalidPrice(format!(
+                    "{} is not a date like 2024-01-31",
+                    start_date
+                ))
+            })?;
+
+            if start_date <= today.as_str() {
+                return Err(AppleCodesignError::InvalidPrice(format!(
+                    "start date {} is not in the future",
+                    start_date
+                )));
+            }
+        }
+
+        for row in rows {
+            parse_price(&row.customer_price)?;
+        }
+
+        let schedule = self.get_app_price_schedule(app_id)?;
+        let base_territory = schedule
+            .related("baseTerritory")
+            .first()
+            .map(|t| t.id.clone())
+            .ok_or_else(|| {
+                AppleCodesignError::InvalidPrice(format!(
+                    "price schedule of app {} has no base territory",
+                    app_id
+                ))
+            })?;
+
+        let current = self
+            .list_app_manual_prices(&schedule.id)?
+            .into_iter()
+            .filter(|price| price.row.is_in_effect(&today))
+            .collect::<Vec<_>>();
+
+        let mut changes = vec![];
+
+        for row in rows {
+            let before = current
+                .iter()
+                .find(|price| price.row.territory == row.territory)
+                .map(|price| price.row.customer_price.clone());
+
+            if let Some(before) = &before {
+                if same_price(before, &row.customer_price) {
+                    continue;
+                }
+            }
+
+            let point = self
+                .list_app_price_points(app_id, &row.territory)?
+                .into_iter()
+                .find(|point| {
+                    point
+                        .attributes
+                        .customer_price
+                        .as_deref()
+                        .map(|price| same_price(price, &row.customer_price))
+                        .unwrap_or_default()
+                })
+                .ok_or_else(|| {
+                    AppleCodesignError::InvalidPrice(format!(
+                        "{} is not a price point in {}",
+                        row.customer_price, row.territory
+                    ))
+                })?;
+
+            changes.push(PriceChange {
+                territory: row.territory.clone(),
+                before,
+                after: point.attributes.customer_price.unwrap_or_default(),
+                price_point_id: point.id,
+            });
+        }
+
+        Ok(PricePlan {
+            app_id: app_id.to_string(),
+            base_territory,
+            start_date: start_date.map(|x| x.to_string()),
+            current,
+            changes,
+        })
+    }
+
+    /// Replace the price schedule of an app with one containing planned changes.
+    ///
+    /// The new schedule has the manual prices currently in effect and the
+    /// changed prices. Prices previously scheduled for the future are dropped.
+    pub fn apply_app_price_change(
+        &self,
+        plan: &PricePlan,
+    ) -> Result<AppPriceSchedule, AppleCodesignError> {
+        let mut prices = plan
+            .current
+            .iter()
+            // Changes taking effect immediately replace the current price.
+            .filter(|price| {
+                plan.start_date.is_some()
+                    || !plan
+                        .changes
+                        .iter()
+                        .any(|change| change.territory == price.row.territory)
+            })
+            .map(|price| (price.price_point_id.clone(), None))
+            .collect::<Vec<_>>();
+        prices.extend(
+            plan.changes
+                .iter()
+                .map(|change| (change.price_point_id.clone(), plan.start_date.clone())),
+        );
+
+        let mut body = RequestDocument::create("appPriceSchedules", serde_json::json!({}))
+            .with_relationship("app", "apps", &plan.app_id)
+            .with_relationship("baseTerritory", "territories", &plan.base_territory);
+        let mut identifiers = vec![];
+
+        for (i, (price_point_id, start_date)) in prices.into_iter().enumerate() {
+            // Prices are created along with the schedule and referenced by local ID.
+            let local_id = format!("${{price{}}}", i);
+
+            let mut price = RequestDocument::create(
+                "appPrices",
+                AppPriceAttributes {
+                    start_date,
+                    ..Default::default()
+                },
+            )
+            .with_relationship("appPricePoint", "appPricePoints", price_point_id);
+            price.data.id = Some(local_id.clone());
+
+            body = body.with_included(serde_json::to_value(price.data)?);
+            identifiers.push(ResourceIdentifier::new("appPrices", local_id));
+        }
+
+        let body = body.with_relationships("manualPrices", identifiers);
+
+        let doc = self.api_post::<_, Document<AppPriceSchedule>>("/v1/appPriceSchedules", &body)?;
+
+        Ok(doc.data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::{json, Value},
+    };
+
+    fn price_point(id: &str, customer_price: &str) -> Value {
+        fixtures::resource(
+            "appPricePoints",
+            id,
+            json!({"customerPrice": customer_price, "proceeds": "0.70"}),
+        )
+    }
+
+    fn manual_price(id: &str, price_point_id: &str, territory: &str) -> Value {
+        let mut price = fixtures::resource("appPrices", id, json!({"manual": true}));
+        price["relationships"] = json!({
+            "appPricePoint": {"data": {"type": "appPricePoints", "id": price_point_id}},
+            "territory": {"data": {"type": "territories", "id": territory}},
+        });
+
+        price
+    }
+
+    fn row(territory: &str, customer_price: &str) -> PriceRow {
+        PriceRow {
+            territory: territory.into(),
+            currency: None,
+            customer_price: customer_price.into(),
+            proceeds: None,
+            start_date: None,
+            end_date: None,
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn csv_round_trip() -> Result<(), AppleCodesignError> {
+        let rows = read_price_rows("territory,customer_price\nUSA,1.99\n".as_bytes())?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].territory, "USA");
+        assert_eq!(rows[0].currency, None);
+
+        let mut data = vec![];
+        write_price_rows(&mut data, &rows)?;
+        assert_eq!(
+            String::from_utf8(data).unwrap(),
+            "territory,currency,customer_price,proceeds,start_date,end_date\nUSA,,1.99,,,\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn price_change() -> Result<(), AppleCodesignError> {
+        let mut schedule = fixtures::resource("appPriceSchedules", "A1", json!(null));
+        schedule["relationships"] =
+            json!({"baseTerritory": {"data": {"type": "territories", "id": "USA"}}});
+
+        let mut prices = fixtures::collection(vec![
+            manual_price("P1", "USA-099", "USA"),
+            manual_price("P2", "DEU-109", "DEU"),
+        ]);
+        prices["included"] = json!([
+            price_point("USA-099", "0.99"),
+            price_point("DEU-109", "1.09"),
+            fixtures::resource("territories", "USA", json!({"currency": "USD"})),
+            fixtures::resource("territories", "DEU", json!({"currency": "EUR"})),
+        ]);
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps/A1/appPriceSchedule",
+                200,
+                fixtures::document(schedule.clone()),
+            )
+            .json("GET", "/v1/appPriceSchedules/A1/manualPrices", 200, prices)
+            .json(
+                "GET",
+                "/v1/apps/A1/appPricePoints",
+                200,
+                fixtures::collection(vec![
+                    price_point("USA-099", "0.99"),
+                    price_point("USA-199", "1.99"),
+                ]),
+            )
+            .json(
+                "POST",
+                "/v1/appPriceSchedules",
+                201,
+                fixtures::document(schedule),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let rows = vec![row("USA", "1.99"), row("DEU", "1.090")];
+        let plan = client.plan_app_price_change("A1", &rows, Some("2099-01-01"))?;
+        assert_eq!(plan.base_territory, "USA");
+        assert_eq!(plan.current.len(), 2);
+        assert_eq!(plan.current[1].row.currency.as_deref(), Some("EUR"));
+        assert_eq!(plan.changes.len(), 1);
+        assert_eq!(plan.changes[0].before.as_deref(), Some("0.99"));
+        assert_eq!(plan.changes[0].price_point_id, "USA-199");
+
+        client.apply_app_price_change(&plan)?;
+
+        let requests = server.requests();
+        let body = serde_json::from_slice::<Value>(&requests[requests.len() - 1].body)?;
+        assert_eq!(
+            body["data"]["relationships"]["manualPrices"]["data"]
+                .as_array()
+                .map(|x| x.len()),
+            Some(3)
+        );
+        assert_eq!(body["included"][2]["attributes"]["startDate"], "2099-01-01");
+        assert_eq!(
+            body["included"][2]["relationships"]["appPricePoint"]["data"]["id"],
+            "USA-199"
+        );
+
+        // Prices must be price points and changes must not be in the past.
+        let rows = vec![row("USA", "1.98")];
+        assert!(client.plan_app_price_change("A1", &rows, None).is_err());
+        assert!(client
+            .plan_app_price_change("A1", &rows, Some("2000-01-01"))
+            .is_err());
+
+        Ok(())
+    }
+}
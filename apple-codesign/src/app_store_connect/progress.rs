@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Progress of long running operations for machine consumption.
+//!
+//! Uploads and waits on processing are reported in log messages meant for
+//! humans. A [ProgressSink] attached to [AppStoreConnectClient] and
+//! [crate::notarization::Notarizer] additionally receives a [ProgressEvent]
+//! as these operations advance, so GUI wrappers and CI dashboards can render
+//! progress without parsing log output.
+//!
+//! [JsonLinesProgress] writes each event as a line of JSON to stderr:
+//!
+//! ```json
+//! {"operation":"asset-upload","subject":"App.ipa","state":"uploading","percent":50.0,"bytes":1048576,"totalBytes":2097152,"timestamp":"2026-10-16T09:30:00Z"}
+//! ```
+//!
+//! [TransporterLog] writes events to stdout in the log format of Apple's
+//! Transporter, for systems built around parsing its output.
+
+use {
+    crate::app_store_connect::AppStoreConnectClient,
+    chrono::{DateTime, Utc},
+    serde::Serialize,
+    std::{io::Write, time::Duration},
+};
+
+/// The state of a long running operation.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    /// The kind of operation, e.g. `asset-upload` or `notarization-wait`.
+    pub operation: String,
+
+    /// What the operation acts on, e.g. a file name or build ID.
+    pub subject: String,
+
+    /// The state of the operation, e.g. `uploading` or the processing state
+    /// reported by Apple.
+    pub state: String,
+
+    /// Completion in percent, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+
+    /// Bytes transferred so far.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+
+    /// Bytes to transfer in total.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_bytes: Option<u64>,
+
+    /// Seconds spent waiting so far.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_seconds: Option<u64>,
+
+    /// RFC 3339 time the event was emitted.
+    pub timestamp: String,
+}
+
+impl ProgressEvent {
+    /// Construct an event without measures of completion.
+    pub fn new(operation: &str, subject: impl ToString, state: impl ToString) -> Self {
+        Self {
+            operation: operation.to_string(),
+            subject: subject.to_string(),
+            state: state.to_string(),
+            percent: None,
+            bytes: None,
+            total_bytes: None,
+            elapsed_seconds: None,
+            timestamp: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        }
+    }
+
+    /// Record the bytes transferred, deriving the percentage.
+    pub fn with_bytes(mut self, bytes: u64, total_bytes: u64) -> Self {
+        self.bytes = Some(bytes);
+        self.total_bytes = Some(total_bytes);
+        self.percent = Some(if total_bytes == 0 {
+            100.0
+        } else {
+            bytes as f64 * 100.0 / total_bytes as f64
+        });
+        self
+    }
+
+    /// Record the time spent waiting.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed_seconds = Some(elapsed.as_secs());
+        self
+    }
+}
+
+/// The name of a processing state as sent by Apple, e.g. `PROCESSING`.
+pub(crate) fn state_name(state: Option<&impl Serialize>) -> String {
+    state
+        .and_then(|state| serde_json::to_value(state).ok())
+        .and_then(|value| value.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| "UNKNOWN".into())
+}
+
+/// Receives progress of long running operations.
+///
+/// Implementations must not block for long: they are called on the thread
+/// performing the operation.
+pub trait ProgressSink: Send + Sync {
+    /// Handle an event.
+    fn progress(&self, event: &ProgressEvent);
+}
+
+/// Writes events to stderr as newline-delimited JSON.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonLinesProgress;
+
+impl ProgressSink for JsonLinesProgress {
+    fn progress(&self, event: &ProgressEvent) {
+        if let Ok(line) = serde_json::to_string(event) {
+            let mut stderr = std::io::stderr().lock();
+            writeln!(stderr, "{}", line).ok();
+        }
+    }
+}
+
+/// Writes events to stdout as log lines of Apple's Transporter (`iTMSTransporter`).
+///
+/// Release orchestration systems built around Transporter parse its output.
+/// Lines follow its layout:
+///
+/// ```text
+/// [2026-10-16 09:30:00 UTC] <main>  INFO: [asset-upload] App.ipa upload progress: 50.00% (1048576 of 2097152 bytes)
+/// [2026-10-16 09:31:00 UTC] <main>  INFO: [build-upload-processing] 7d3e1c2a state: PROCESSING (60s elapsed)
+/// ```
+///
+/// [Self::package_summary()] renders the summary Transporter ends an upload
+/// with.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TransporterLog;
+
+impl TransporterLog {
+    /// Format a log line of a level such as `INFO`, `ERROR`, or `DBG-X`.
+    pub fn line(time: DateTime<Utc>, level: &str, message: &str) -> String {
+        format!(
+            "[{}] <main> {:>5}: {}",
+            time.format("%Y-%m-%d %H:%M:%S UTC"),
+            level,
+            message
+        )
+    }
+
+    /// The message logged for an event.
+    pub fn event_message(event: &ProgressEvent) -> String {
+        match (event.percent, event.bytes, event.total_bytes) {
+            (Some(percent), Some(bytes), Some(total_bytes)) => format!(
+                "[{}] {} upload progress: {:.2}% ({} of {} bytes)",
+                event.operation, event.subject, percent, bytes, total_bytes
+            ),
+            _ => {
+                let mut message = format!(
+                    "[{}] {} state: {}",
+                    event.operation, event.subject, event.state
+                );
+                if let Some(elapsed) = event.elapsed_seconds {
+                    message.push_str(&format!(" ({}s elapsed)", elapsed));
+                }
+
+                message
+            }
+        }
+    }
+
+    /// The lines ending the upload of a package, failed if `error` is set.
+    pub fn package_summary(time: DateTime<Utc>, package: &str, error: Option<&str>) -> Vec<String> {
+        let mut lines = vec![];
+
+        match error {
+            None => {
+                lines.push(Self::line(time, "INFO", ""));
+                lines.push("Package Summary:".into());
+                lines.push(" ".into());
+                lines.push("1 package(s) were uploaded successfully:".into());
+                lines.push(format!("\t{}", package));
+                lines.push(Self::line(time, "DBG-X", "Returning 0"));
+            }
+            Some(error) => {
+                lines.push(Self::line(time, "ERROR", error));
+                lines.push(Self::line(time, "INFO", ""));
+                lines.push("Package Summary:".into());
+                lines.push(" ".into());
+                lines.push("1 package(s) were not uploaded because they had problems:".into());
+                lines.push(format!("\t{} - Error Messages:", package));
+                lines.push(format!("\t\t{}", error));
+                lines.push(Self::line(time, "DBG-X", "Returning 1"));
+            }
+        }
+
+        lines
+    }
+}
+
+impl ProgressSink for TransporterLog {
+    fn progress(&self, event: &ProgressEvent) {
+        let time = DateTime::parse_from_rfc3339(&event.timestamp)
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+
+        let mut stdout = std::io::stdout().lock();
+        writeln!(
+            stdout,
+            "{}",
+            Self::line(time, "INFO", &Self::event_message(event))
+        )
+        .ok();
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Deliver an event to the progress sink, if one is configured.
+    pub fn report_progress(&self, event: ProgressEvent) {
+        if let Some(sink) = &self.progress_sink {
+            sink.progress(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            app_store_connect::testing::{MockResponse, MockServerBuilder},
+            AppleCodesignError,
+        },
+        std::sync::{Arc, Mutex},
+    };
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<ProgressEvent>>);
+
+    impl ProgressSink for Events {
+        fn progress(&self, event: &ProgressEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn serialize() {
+        let mut event = ProgressEvent::new("asset-upload", "App.ipa", "uploading").with_bytes(1, 4);
+        event.timestamp = "2026-10-16T09:30:00Z".into();
+
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            "{\"operation\":\"asset-upload\",\"subject\":\"App.ipa\",\"state\":\"uploading\",\
+             \"percent\":25.0,\"bytes\":1,\"totalBytes\":4,\"timestamp\":\"2026-10-16T09:30:00Z\"}"
+        );
+    }
+
+    #[test]
+    fn transporter_log() {
+        let time = DateTime::parse_from_rfc3339("2026-10-16T09:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let event = ProgressEvent::new("asset-upload", "App.ipa", "uploading").with_bytes(1, 4);
+        assert_eq!(
+            TransporterLog::line(time, "INFO", &TransporterLog::event_message(&event)),
+            "[2026-10-16 09:30:00 UTC] <main>  INFO: [asset-upload] App.ipa upload progress: \
+             25.00% (1 of 4 bytes)"
+        );
+
+        let event = ProgressEvent::new("build-upload-processing", "U1", "PROCESSING")
+            .with_elapsed(Duration::from_secs(60));
+        assert_eq!(
+            TransporterLog::event_message(&event),
+            "[build-upload-processing] U1 state: PROCESSING (60s elapsed)"
+        );
+
+        let lines = TransporterLog::package_summary(time, "App.ipa", None);
+        assert_eq!(lines[1], "Package Summary:");
+        assert_eq!(lines[4], "\tApp.ipa");
+        assert_eq!(
+            lines.last().unwrap(),
+            "[2026-10-16 09:30:00 UTC] <main> DBG-X: Returning 0"
+        );
+
+        let lines = TransporterLog::package_summary(time, "App.ipa", Some("upload failed"));
+        assert_eq!(
+            lines[0],
+            "[2026-10-16 09:30:00 UTC] <main> ERROR: upload failed"
+        );
+        assert!(lines.contains(&"\t\tupload failed".to_string()));
+        assert!(lines.last().unwrap().ends_with("Returning 1"));
+    }
+
+    #[cfg(feature = "asc-builds")]
+    #[test]
+    fn upload_operations() -> Result<(), AppleCodesignError> {
+        use crate::app_store_connect::asset_upload::UploadOperation;
+
+        let server = MockServerBuilder::default()
+            .route("PUT", "/upload", MockResponse::empty(200))
+            .start()?;
+        let events = Arc::new(Events::default());
+        let mut client = server.client()?;
+        client.set_progress_sink(Some(events.clone()));
+
+        let operations = [0, 2]
+            .into_iter()
+            .map(|offset| UploadOperation {
+                method: "PUT".into(),
+                url: format!("{}/upload", server.url()),
+                offset,
+                length: 2,
+                request_headers: vec![],
+            })
+            .collect::<Vec<_>>();
+        client.perform_upload_operations("App.ipa", &operations, b"abcd")?;
+
+        let events = events.0.lock().unwrap();
+        assert_eq!(
+            events.iter().map(|e| e.percent).collect::<Vec<_>>(),
+            vec![Some(0.0), Some(50.0), Some(100.0)]
+        );
+        assert_eq!(events[2].state, "uploaded");
+
+        Ok(())
+    }
+}
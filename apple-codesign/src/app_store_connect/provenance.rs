@@ -0,0 +1,279 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Provenance records of uploaded builds.
+//!
+//! Once an `.ipa` is delivered, nothing in the file says which build it became.
+//! A [Provenance] records the checksums of the uploaded file together with the
+//! build upload (the delivery), the resulting build, the API key used, and
+//! when the upload happened. It is written as JSON next to the `.ipa` (see
+//! [Provenance::path_for()]).
+//!
+//! Verification checks that a file is the one recorded
+//! ([Provenance::ipa_problems()]) and that App Store Connect agrees the build
+//! upload delivered the recorded build
+//! ([AppStoreConnectClient::provenance_problems()]).
+
+use {
+    crate::{
+        app_store_connect::{
+            asset_upload::asset_checksum,
+            build_upload_api::{Build, BuildUpload, IpaInfo},
+            json_api::Document,
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
+    sha2::Digest,
+    std::path::{Path, PathBuf},
+};
+
+/// Record of the delivery of an `.ipa`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// File name of the uploaded `.ipa`.
+    pub ipa_file_name: String,
+    pub ipa_size: u64,
+    pub ipa_sha256: String,
+
+    /// MD5 of the `.ipa`, as sent to Apple as the source file checksum.
+    pub ipa_md5: String,
+
+    pub bundle_identifier: String,
+    pub short_version: String,
+    pub version: String,
+
+    pub app_id: String,
+
+    /// ID of the build upload, which identifies the delivery.
+    pub build_upload_id: String,
+
+    /// ID of the build created from the upload, once known.
+    pub build_id: Option<String>,
+
+    /// ID of the API key the upload was performed with.
+    pub api_key_id: String,
+
+    /// RFC 3339 time the upload started.
+    pub upload_started: String,
+
+    /// RFC 3339 time the upload was committed.
+    pub upload_finished: String,
+
+    pub upload_duration_seconds: f64,
+}
+
+impl Provenance {
+    /// Describe the delivery of `.ipa` content.
+    pub fn new(
+        ipa_file_name: &str,
+        ipa_data: &[u8],
+        app_id: &str,
+        build_upload_id: &str,
+        api_key_id: &str,
+        upload_started: DateTime<Utc>,
+        upload_finished: DateTime<Utc>,
+    ) -> Result<Self, AppleCodesignError> {
+        let info = IpaInfo::from_ipa_data(ipa_data)?;
+
+        Ok(Self {
+            ipa_file_name: ipa_file_name.to_string(),
+            ipa_size: ipa_data.len() as u64,
+            ipa_sha256: hex::encode(sha2::Sha256::digest(ipa_data)),
+            ipa_md5: asset_checksum(ipa_data),
+            bundle_identifier: info.bundle_identifier,
+            short_version: info.short_version,
+            version: info.version,
+            app_id: app_id.to_string(),
+            build_upload_id: build_upload_id.to_string(),
+            build_id: None,
+            api_key_id: api_key_id.to_string(),
+            upload_started: upload_started.to_rfc3339(),
+            upload_finished: upload_finished.to_rfc3339(),
+            upload_duration_seconds: (upload_finished - upload_started).num_milliseconds() as f64
+                / 1000.0,
+        })
+    }
+
+    /// The path of the provenance of an `.ipa`: `<ipa>.provenance.json`.
+    pub fn path_for(ipa_path: &Path) -> PathBuf {
+        let mut path = ipa_path.as_os_str().to_owned();
+        path.push(".provenance.json");
+
+        PathBuf::from(path)
+    }
+
+    /// Read a provenance from a JSON file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// Write this provenance to a JSON file.
+    pub fn write(&self, path: &Path) -> Result<(), AppleCodesignError> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Describe how `.ipa` content differs from the recorded content.
+    ///
+    /// Empty if the content is the one that was delivered.
+    pub fn ipa_problems(&self, ipa_data: &[u8]) -> Vec<String> {
+        let mut problems = vec![];
+
+        if ipa_data.len() as u64 != self.ipa_size {
+            problems.push(format!(
+                "size is {} bytes; {} bytes were delivered",
+                ipa_data.len(),
+                self.ipa_size
+            ));
+        }
+
+        let sha256 = hex::encode(sha2::Sha256::digest(ipa_data));
+        if sha256 != self.ipa_sha256 {
+            problems.push(format!(
+                "SHA-256 is {}; {} was delivered",
+                sha256, self.ipa_sha256
+            ));
+        }
+
+        problems
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Describe how App Store Connect disagrees with a provenance.
+    ///
+    /// The build upload must exist, be for the recorded version, and have
+    /// resulted in the recorded build. Empty if everything matches.
+    pub fn provenance_problems(
+        &self,
+        provenance: &Provenance,
+    ) -> Result<Vec<String>, AppleCodesignError> {
+        let doc = self.api_get::<Document<BuildUpload>>(
+            &format!("/v1/buildUploads/{}", provenance.build_upload_id),
+            &[("include", "build".to_string())],
+        )?;
+
+        let mut problems = vec![];
+
+        for (name, actual, recorded) in [
+            (
+                "CFBundleShortVersionString",
+                &doc.data.attributes.cf_bundle_short_version_string,
+                &provenance.short_version,
+            ),
+            (
+                "CFBundleVersion",
+                &doc.data.attributes.cf_bundle_version,
+                &provenance.version,
+            ),
+        ] {
+            if actual.as_ref() != Some(recorded) {
+                problems.push(format!(
+                    "build upload {} has {} {}; {} was recorded",
+                    provenance.build_upload_id,
+                    name,
+                    actual.as_deref().unwrap_or("unknown"),
+                    recorded
+                ));
+            }
+        }
+
+        if let Some(build_id) = &provenance.build_id {
+            let build = doc
+                .included
+                .into_iter()
+                .filter_map(|v| serde_json::from_value::<Build>(v).ok())
+                .find(|r| r.r#type == "builds");
+
+            match build {
+                Some(build) if build.id == *build_id => {
+                    if build.attributes.version.as_ref() != Some(&provenance.version) {
+                        problems.push(format!(
+                            "build {} has version {}; {} was recorded",
+                            build_id,
+                            build.attributes.version.as_deref().unwrap_or("unknown"),
+                            provenance.version
+                        ));
+                    }
+                }
+                Some(build) => {
+                    problems.push(format!(
+                        "build upload {} resulted in build {}; {} was recorded",
+                        provenance.build_upload_id, build.id, build_id
+                    ));
+                }
+                None => {
+                    problems.push(format!(
+                        "build upload {} has no build; {} was recorded",
+                        provenance.build_upload_id, build_id
+                    ));
+                }
+            }
+        }
+
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::json,
+    };
+
+    #[test]
+    fn verify() -> Result<(), AppleCodesignError> {
+        let ipa = fixtures::ipa("com.example.app", "1.0", "42");
+        let started = Utc::now();
+        let mut provenance = Provenance::new(
+            "app.ipa",
+            &ipa,
+            "A1",
+            "U1",
+            "MOCKKEY123",
+            started,
+            started + chrono::Duration::milliseconds(1500),
+        )?;
+        provenance.build_id = Some("B1".into());
+
+        assert_eq!(provenance.upload_duration_seconds, 1.5);
+        assert!(provenance.ipa_problems(&ipa).is_empty());
+        assert_eq!(provenance.ipa_problems(b"other").len(), 2);
+
+        let td = tempfile::TempDir::new()?;
+        let path = Provenance::path_for(&td.path().join("app.ipa"));
+        assert!(path.ends_with("app.ipa.provenance.json"));
+        provenance.write(&path)?;
+        assert_eq!(Provenance::from_path(&path)?, provenance);
+
+        let mut upload = fixtures::document(fixtures::resource(
+            "buildUploads",
+            "U1",
+            json!({"cfBundleShortVersionString": "1.0", "cfBundleVersion": "42", "state": "COMPLETE"}),
+        ));
+        upload["included"] = json!([fixtures::build("B1", "42", "VALID")]);
+
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/buildUploads/U1", 200, upload)
+            .start()?;
+        let client = server.client()?;
+        assert!(client.provenance_problems(&provenance)?.is_empty());
+
+        provenance.build_id = Some("B2".into());
+        let problems = client.provenance_problems(&provenance)?;
+        assert_eq!(
+            problems,
+            vec!["build upload U1 resulted in build B1; B2 was recorded"]
+        );
+
+        Ok(())
+    }
+}
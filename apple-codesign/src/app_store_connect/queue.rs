@@ -0,0 +1,443 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A durable queue of mutating App Store Connect operations.
+//!
+//! Build farms on unreliable networks can journal operations to disk with
+//! [OperationQueue::enqueue()] and run them with [OperationQueue::run()].
+//! Operations stay in the queue until they succeed, so they are retried
+//! across process restarts.
+//!
+//! Before an operation is performed, the server is checked for its effect
+//! (e.g. a device with the UDID is registered, a build with the version
+//! exists). Operations whose effect is present are acknowledged without being
+//! performed again. This makes an operation take effect once even if the
+//! process dies after the server applied it but before it was acknowledged.
+//!
+//! Each operation is stored as a JSON file in a subdirectory of the queue
+//! directory named after the [CacheScope] of the API key, so operations are
+//! only run with the API key they were queued for. Only one process should
+//! run a queue at a time.
+//!
+//! Operations can be deferred until an instant, e.g. the opening of an
+//! off-peak [ScheduleWindow](super::schedule::ScheduleWindow). Runs of the
+//! queue before that instant skip them.
+
+use {
+    crate::{
+        app_store_connect::{
+            build_upload_api::{IpaInfo, Platform},
+            cache_scope::CacheScope,
+            certs_api::DeviceStatus,
+            AppStoreConnectClient,
+        },
+        redact::redact,
+        AppleCodesignError,
+    },
+    chrono::{DateTime, TimeZone, Utc},
+    log::{info, warn},
+    serde::{Deserialize, Serialize},
+    std::{
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// A mutating operation.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Operation {
+    /// Register a device. Done if a device with the UDID is registered.
+    RegisterDevice {
+        name: String,
+        udid: String,
+        platform: String,
+    },
+
+    /// Upload an `.ipa` file. Done if the app has a build with the version of
+    /// the `.ipa`.
+    ///
+    /// Builds appear some time after their upload. An upload interrupted
+    /// after it completed but before its build appeared is repeated, which
+    /// App Store Connect rejects as a duplicate version.
+    UploadIpa {
+        app_id: String,
+        path: PathBuf,
+        platform: Platform,
+    },
+
+    /// Rename, enable, or disable a device. Done if the device has the
+    /// requested name and status.
+    UpdateDevice {
+        device_id: String,
+        name: Option<String>,
+        status: Option<DeviceStatus>,
+    },
+}
+
+impl Operation {
+    /// Whether the effect of this operation is present on the server.
+    pub fn is_done(&self, client: &AppStoreConnectClient) -> Result<bool, AppleCodesignError> {
+        match self {
+            Self::RegisterDevice { udid, .. } => Ok(client.find_device_by_udid(udid)?.is_some()),
+            Self::UploadIpa { app_id, path, .. } => {
+                let info = IpaInfo::from_ipa_data(&std::fs::read(path)?)?;
+
+                Ok(!client.list_builds(app_id, Some(&info.version))?.is_empty())
+            }
+            Self::UpdateDevice {
+                device_id,
+                name,
+                status,
+            } => {
+                let device = client.get_device(device_id)?;
+
+                Ok((name.is_none() || device.attributes.name == *name)
+                    && (status.is_none() || device.attributes.status == *status))
+            }
+        }
+    }
+
+    /// Perform the operation.
+    pub fn perform(&self, client: &AppStoreConnectClient) -> Result<(), AppleCodesignError> {
+        match self {
+            Self::RegisterDevice {
+                name,
+                udid,
+                platform,
+            } => {
+                let device = client.register_device(name, udid, platform)?;
+                info!("registered device {} as {}", udid, device.id);
+            }
+            Self::UploadIpa {
+                app_id,
+                path,
+                platform,
+            } => {
+                let upload = client.upload_ipa(app_id, path, *platform)?;
+                info!("uploaded {} as {}", path.display(), upload.id);
+            }
+            Self::UpdateDevice {
+                device_id,
+                name,
+                status,
+            } => {
+                client.update_device(device_id, name.clone(), *status)?;
+                info!("updated device {}", device_id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An operation in the queue.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QueueEntry {
+    /// Unique ID of the entry. Entries are run in the order of their IDs.
+    pub id: String,
+
+    /// The operation to perform.
+    pub operation: Operation,
+
+    /// Number of failed attempts to perform the operation.
+    pub attempts: u32,
+
+    /// The (redacted) error of the last failed attempt.
+    pub last_error: Option<String>,
+
+    /// Unix time in seconds before which the operation isn't attempted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<i64>,
+}
+
+impl QueueEntry {
+    /// Whether the operation is deferred at an instant.
+    pub fn is_deferred(&self, now: DateTime<Utc>) -> bool {
+        self.not_before
+            .map(|t| now.timestamp() < t)
+            .unwrap_or_default()
+    }
+
+    /// The instant before which the operation isn't attempted.
+    pub fn not_before_time(&self) -> Option<DateTime<Utc>> {
+        self.not_before.map(|t| Utc.timestamp(t, 0))
+    }
+}
+
+/// Outcome of running a queue.
+#[derive(Clone, Debug, Default)]
+pub struct QueueReport {
+    /// Number of operations performed.
+    pub performed: usize,
+
+    /// Number of operations acknowledged because their effect was present.
+    pub already_done: usize,
+
+    /// Number of operations remaining in the queue after failing.
+    pub remaining: usize,
+
+    /// Number of operations not attempted because they are deferred.
+    pub deferred: usize,
+}
+
+/// A queue of operations persisted in a directory.
+pub struct OperationQueue {
+    dir: PathBuf,
+}
+
+impl OperationQueue {
+    /// Open the queue of an API key in a directory, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>, scope: &CacheScope) -> Result<Self, AppleCodesignError> {
+        let dir = scope.dir(dir);
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir })
+    }
+
+    fn entry_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Write an entry such that a crash leaves either the old or new content.
+    fn write_entry(&self, entry: &QueueEntry) -> Result<(), AppleCodesignError> {
+        let path = self.entry_path(&entry.id);
+        let temp_path = path.with_extension("json.tmp");
+
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(entry)?)?;
+        std::fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    }
+
+    /// Add an operation to the queue.
+    pub fn enqueue(&self, operation: Operation) -> Result<QueueEntry, AppleCodesignError> {
+        self.enqueue_not_before(operation, None)
+    }
+
+    /// Add an operation to the queue that isn't attempted before an instant.
+    pub fn enqueue_not_before(
+        &self,
+        operation: Operation,
+        not_before: Option<DateTime<Utc>>,
+    ) -> Result<QueueEntry, AppleCodesignError> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        let entry = QueueEntry {
+            id: format!("{:024}-{:08x}", nanos, rand::random::<u32>()),
+            operation,
+            attempts: 0,
+            last_error: None,
+            not_before: not_before.map(|t| t.timestamp()),
+        };
+
+        self.write_entry(&entry)?;
+
+        Ok(entry)
+    }
+
+    /// Entries in the queue, in the order they are run.
+    pub fn entries(&self) -> Result<Vec<QueueEntry>, AppleCodesignError> {
+        let mut paths = std::fs::read_dir(&self.dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|path| path.extension().map(|x| x == "json").unwrap_or_default())
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| Ok(serde_json::from_slice(&std::fs::read(path)?)?))
+            .collect()
+    }
+
+    /// Remove an entry from the queue.
+    pub fn acknowledge(&self, id: &str) -> Result<(), AppleCodesignError> {
+        std::fs::remove_file(self.entry_path(id))?;
+
+        Ok(())
+    }
+
+    /// Attempt each operation in the queue once.
+    ///
+    /// Operations that fail are kept in the queue with their error recorded.
+    /// Later operations are still attempted. Deferred operations are skipped.
+    pub fn run(&self, client: &AppStoreConnectClient) -> Result<QueueReport, AppleCodesignError> {
+        let mut report = QueueReport::default();
+
+        for mut entry in self.entries()? {
+            if entry.is_deferred(Utc::now()) {
+                info!("{} deferred", entry.id);
+                report.deferred += 1;
+                continue;
+            }
+
+            let res = entry.operation.is_done(client).and_then(|done| {
+                if !done {
+                    entry.operation.perform(client)?;
+                }
+
+                Ok(done)
+            });
+
+            match res {
+                Ok(done) => {
+                    self.acknowledge(&entry.id)?;
+
+                    if done {
+                        info!("{} already done", entry.id);
+                        report.already_done += 1;
+                    } else {
+                        report.performed += 1;
+                    }
+                }
+                Err(e) => {
+                    let message = redact(&e.to_string()).to_string();
+                    warn!("{} failed: {}", entry.id, message);
+
+                    entry.attempts += 1;
+                    entry.last_error = Some(message);
+                    self.write_entry(&entry)?;
+
+                    report.remaining += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Run the queue until no failed operations remain or `max_rounds`
+    /// rounds were run.
+    ///
+    /// Waits `retry_delay` between rounds.
+    pub fn drain(
+        &self,
+        client: &AppStoreConnectClient,
+        max_rounds: usize,
+        retry_delay: Duration,
+    ) -> Result<QueueReport, AppleCodesignError> {
+        let mut total = QueueReport::default();
+
+        for round in 0..max_rounds {
+            if round > 0 {
+                warn!(
+                    "{} operations remaining; retrying in {}s",
+                    total.remaining,
+                    retry_delay.as_secs()
+                );
+                std::thread::sleep(retry_delay);
+            }
+
+            let report = self.run(client)?;
+            total.performed += report.performed;
+            total.already_done += report.already_done;
+            total.remaining = report.remaining;
+            total.deferred = report.deferred;
+
+            if total.remaining == 0 {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    const UDID: &str = "00008120-001A2B3C4D5E6F70";
+
+    #[test]
+    fn retry_and_precheck() -> Result<(), AppleCodesignError> {
+        // Responses are served in order. Devices are listed once per run.
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/devices", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/devices", 200, fixtures::collection(vec![]))
+            .json(
+                "GET",
+                "/v1/devices",
+                200,
+                fixtures::collection(vec![fixtures::device("D1", UDID)]),
+            )
+            .json(
+                "POST",
+                "/v1/devices",
+                503,
+                fixtures::error(503, "UNAVAILABLE", "down"),
+            )
+            .json(
+                "POST",
+                "/v1/devices",
+                201,
+                fixtures::document(fixtures::device("D1", UDID)),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let td = tempfile::TempDir::new()?;
+        let queue = OperationQueue::open(td.path(), &client.cache_scope())?;
+        let entry = queue.enqueue(Operation::RegisterDevice {
+            name: "phone".into(),
+            udid: UDID.into(),
+            platform: "IOS".into(),
+        })?;
+
+        // The first attempt fails and is kept for a later process.
+        let report = queue.run(&client)?;
+        assert_eq!(report.remaining, 1);
+
+        // Queues of other API keys are separate.
+        let other = OperationQueue::open(td.path(), &CacheScope::new("other", "KEY"))?;
+        assert!(other.entries()?.is_empty());
+
+        let queue = OperationQueue::open(td.path(), &client.cache_scope())?;
+        let entries = queue.entries()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, entry.id);
+        assert_eq!(entries[0].attempts, 1);
+        assert!(entries[0].last_error.is_some());
+
+        let report = queue.run(&client)?;
+        assert_eq!(report.performed, 1);
+        assert_eq!(report.remaining, 0);
+        assert!(queue.entries()?.is_empty());
+
+        // Deferred operations are kept without being attempted.
+        let deferred = queue.enqueue_not_before(
+            entry.operation.clone(),
+            Some(Utc::now() + chrono::Duration::hours(1)),
+        )?;
+        assert!(deferred.not_before_time().is_some());
+        let report = queue.run(&client)?;
+        assert_eq!(report.deferred, 1);
+        assert_eq!(report.remaining, 0);
+        queue.acknowledge(&deferred.id)?;
+
+        // The device now exists, so registering it again is a no-op.
+        queue.enqueue(entry.operation)?;
+
+        let report = queue.run(&client)?;
+        assert_eq!(report.already_done, 1);
+        assert_eq!(
+            server
+                .requests()
+                .iter()
+                .filter(|r| r.method == "POST")
+                .count(),
+            2
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Traversal of JSON:API relationships.
+//!
+//! Resources refer to other resources through relationships. A relationship
+//! carries a `related` link to an endpoint serving the related resources and,
+//! if requested with the `include` query parameter, linkage data identifying
+//! them.
+//!
+//! [AppStoreConnectClient::fetch_related()] follows the `related` link of a
+//! single resource. To resolve a relationship of many resources,
+//! [AppStoreConnectClient::fetch_related_all()] looks up the linked resources
+//! in the `included` member of the response first and fetches the remaining
+//! ones with [AppStoreConnectClient::fetch_by_ids()], which puts up to
+//! [FILTER_ID_BATCH_SIZE] IDs in each request instead of issuing a request
+//! per resource.
+
+use {
+    crate::{
+        app_store_connect::{
+            json_api::{included_resources, Document, Resource},
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    serde::{de::DeserializeOwned, Deserialize},
+    serde_json::Value,
+    std::collections::{BTreeMap, BTreeSet, HashMap},
+};
+
+/// Maximum number of IDs in the `filter[id]` query parameter of a request.
+pub const FILTER_ID_BATCH_SIZE: usize = 50;
+
+/// The primary data of a `related` endpoint.
+///
+/// To-many relationships serve a list. To-one relationships serve a single
+/// resource or `null`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RelatedData<A> {
+    Many(Vec<Resource<A>>),
+    One(Option<Resource<A>>),
+}
+
+impl AppStoreConnectClient {
+    /// Fetch the resources a relationship of a resource links to.
+    ///
+    /// The `related` link of the relationship is followed, including pagination.
+    /// If the resource has no such link (e.g. because it was constructed from
+    /// linkage data), the conventional `/v1/<type>/<id>/<relationship>` path is
+    /// requested.
+    pub fn fetch_related<A: DeserializeOwned, B>(
+        &self,
+        resource: &Resource<B>,
+        relationship: &str,
+    ) -> Result<Vec<Resource<A>>, AppleCodesignError> {
+        let link = resource
+            .relationships
+            .get(relationship)
+            .and_then(|r| r.links.as_ref())
+            .and_then(|links| links.related.as_deref());
+
+        let doc = match link {
+            Some(url) => self.api_get_url::<Document<RelatedData<A>>>(url)?,
+            None => self.api_get(
+                &format!("/v1/{}/{}/{}", resource.r#type, resource.id, relationship),
+                &[],
+            )?,
+        };
+
+        let mut res = match doc.data {
+            RelatedData::Many(resources) => resources,
+            RelatedData::One(resource) => return Ok(resource.into_iter().collect()),
+        };
+
+        let mut next = doc.links.and_then(|links| links.next);
+        while let Some(url) = next {
+            let page = self.api_get_url::<Document<Vec<Resource<A>>>>(&self.next_page_url(&url))?;
+            res.extend(page.data);
+            next = page.links.and_then(|links| links.next);
+        }
+
+        Ok(res)
+    }
+
+    /// Fetch resources of a type by their IDs.
+    ///
+    /// IDs are deduplicated and requested in batches of [FILTER_ID_BATCH_SIZE]
+    /// from the `/v1/<type>` collection, which must support the `filter[id]`
+    /// query parameter. IDs not found are silently omitted and resources are
+    /// returned in no particular order.
+    pub fn fetch_by_ids<A: DeserializeOwned>(
+        &self,
+        r#type: &str,
+        ids: &[&str],
+    ) -> Result<Vec<Resource<A>>, AppleCodesignError> {
+        let ids = ids.iter().copied().collect::<BTreeSet<_>>();
+        let ids = ids.into_iter().collect::<Vec<_>>();
+
+        let mut res = vec![];
+
+        for batch in ids.chunks(FILTER_ID_BATCH_SIZE) {
+            res.extend(self.api_get_all(
+                &format!("/v1/{}", r#type),
+                &[
+                    ("filter[id]", batch.join(",")),
+                    ("limit", FILTER_ID_BATCH_SIZE.to_string()),
+                ],
+            )?);
+        }
+
+        Ok(res)
+    }
+
+    /// Resolve a relationship of many resources, indexed by ID.
+    ///
+    /// The resources must carry linkage data for the relationship. Linked
+    /// resources are taken from `included`, the `included` member of the
+    /// response the resources came from, and the others are fetched with
+    /// [Self::fetch_by_ids()].
+    pub fn fetch_related_all<A: DeserializeOwned, B>(
+        &self,
+        resources: &[Resource<B>],
+        relationship: &str,
+        included: &[Value],
+    ) -> Result<HashMap<String, Resource<A>>, AppleCodesignError> {
+        let mut linked = BTreeMap::<&str, BTreeSet<&str>>::new();
+        for resource in resources {
+            for id in resource.related(relationship) {
+                linked.entry(&id.r#type).or_default().insert(&id.id);
+            }
+        }
+
+        let mut res = HashMap::new();
+
+        for (r#type, ids) in linked {
+            let mut found = included_resources::<A>(included, r#type)?;
+            found.retain(|id, _| ids.contains(id.as_str()));
+
+            let missing = ids
+                .into_iter()
+                .filter(|id| !found.contains_key(*id))
+                .collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                found.extend(
+                    self.fetch_by_ids::<A>(r#type, &missing)?
+                        .into_iter()
+                        .map(|resource| (resource.id.clone(), resource)),
+                );
+            }
+
+            res.extend(found);
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::{
+            app_api::AppAttributes,
+            testing::{fixtures, MockServerBuilder},
+        },
+        asconnect_types::builds::BuildAttributes,
+        serde_json::json,
+    };
+
+    #[test]
+    fn related_links() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/builds/B1/app",
+                200,
+                fixtures::document(fixtures::app("A1", "com.example.app")),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let mut first = fixtures::collection(vec![fixtures::build("B1", "1", "VALID")]);
+        first["links"] = json!({"next": format!("{}/v1/apps/A1/builds/page2", server.url())});
+        server.add_json("GET", "/v1/apps/A1/builds", 200, first);
+        server.add_json(
+            "GET",
+            "/v1/apps/A1/builds/page2",
+            200,
+            fixtures::collection(vec![fixtures::build("B2", "2", "VALID")]),
+        );
+
+        let mut app = serde_json::from_value::<Resource<AppAttributes>>(fixtures::app(
+            "A1",
+            "com.example.app",
+        ))?;
+        let builds = client.fetch_related::<BuildAttributes, _>(&app, "builds")?;
+        assert_eq!(
+            builds.iter().map(|b| b.id.as_str()).collect::<Vec<_>>(),
+            vec!["B1", "B2"]
+        );
+
+        // Links are preferred over the conventional path.
+        app.relationships = serde_json::from_value(json!({
+            "builds": {"links": {"related": format!("{}/v1/apps/A1/builds/page2", server.url())}},
+        }))?;
+        let builds = client.fetch_related::<BuildAttributes, _>(&app, "builds")?;
+        assert_eq!(builds.len(), 1);
+        assert_eq!(builds[0].id, "B2");
+
+        let build = serde_json::from_value::<Resource<BuildAttributes>>(fixtures::build(
+            "B1", "1", "VALID",
+        ))?;
+        let apps = client.fetch_related::<AppAttributes, _>(&build, "app")?;
+        assert_eq!(apps.len(), 1);
+        assert_eq!(
+            apps[0].attributes.bundle_id.as_deref(),
+            Some("com.example.app")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn batched_ids() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                200,
+                fixtures::collection(vec![fixtures::app("A2", "com.example.two")]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        // 120 builds of 120 apps, of which A1 is included.
+        let builds = (0..120)
+            .map(|i| {
+                let mut build = fixtures::build(&format!("B{}", i), "1", "VALID");
+                build["relationships"] = json!({
+                    "app": {"data": {"type": "apps", "id": format!("A{}", i)}},
+                });
+                serde_json::from_value::<Resource<BuildAttributes>>(build)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let included = vec![
+            fixtures::app("A1", "com.example.one"),
+            fixtures::app("X1", "com.example.unrelated"),
+        ];
+
+        let apps = client.fetch_related_all::<AppAttributes, _>(&builds, "app", &included)?;
+
+        assert_eq!(apps.len(), 2);
+        assert_eq!(
+            apps["A1"].attributes.bundle_id.as_deref(),
+            Some("com.example.one")
+        );
+        assert_eq!(
+            apps["A2"].attributes.bundle_id.as_deref(),
+            Some("com.example.two")
+        );
+
+        // The 119 apps not included are fetched in 3 requests.
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        for request in &requests {
+            let query = request.query.as_deref().unwrap_or_default();
+            assert!(query.starts_with("filter%5Bid%5D="));
+            assert!(query.ends_with("&limit=50"));
+            assert!(!query.contains("A1%2C"));
+        }
+
+        Ok(())
+    }
+}
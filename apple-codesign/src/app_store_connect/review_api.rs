@@ -0,0 +1,393 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App Store Review details and attachments.
+//!
+//! Every App Store version submitted for review has an *App Store review detail*
+//! holding contact information, demo account credentials, and notes for the
+//! reviewer. Files (such as a video demonstrating a feature) can be attached to it.
+//!
+//! Rejections are surfaced from review submissions: a submission App Review
+//! found issues with has state `UNRESOLVED_ISSUES` and its rejected items have
+//! state `REJECTED`. [AppStoreConnectClient::list_review_rejections()] collects
+//! them as [ReviewRejection]. The API doesn't expose the messages App Review
+//! sends via the Resolution Center, so the reasons must still be read there.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/app_store_review_details>,
+//! <https://developer.apple.com/documentation/appstoreconnectapi/app_store_review_attachments>,
+//! and <https://developer.apple.com/documentation/appstoreconnectapi/review_submissions>.
+
+pub use asconnect_types::review::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            asset_upload::asset_checksum,
+            build_upload_api::Platform,
+            json_api::{
+                Document, FilterValue, RequestDocument, ResourceIdentifier, ReviewSubmissionQuery,
+            },
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    log::warn,
+    serde::Serialize,
+    std::path::Path,
+};
+
+/// Relationships of review submission items to the resources under review.
+const REVIEW_ITEM_RELATIONSHIPS: &[&str] = &[
+    "appStoreVersion",
+    "appCustomProductPageVersion",
+    "appStoreVersionExperiment",
+    "appEvent",
+];
+
+/// An item of a review submission that App Review rejected.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedItem {
+    /// ID of the review submission item.
+    pub id: String,
+
+    /// The resource that was rejected, if known.
+    pub resource: Option<ResourceIdentifier>,
+}
+
+/// A review submission with issues App Review found.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewRejection {
+    pub app_id: String,
+    pub submission_id: String,
+    pub platform: Option<String>,
+    pub submitted_date: Option<String>,
+    pub state: Option<ReviewSubmissionState>,
+
+    /// The rejected items of the submission.
+    ///
+    /// Can be empty if the submission as a whole has unresolved issues.
+    pub items: Vec<RejectedItem>,
+}
+
+impl AppStoreConnectClient {
+    /// Obtain the App Store review detail of an App Store version, if one exists.
+    pub fn get_app_store_review_detail(
+        &self,
+        app_store_version_id: &str,
+    ) -> Result<Option<AppStoreReviewDetail>, AppleCodesignError> {
+        let doc = self.api_get::<Document<Option<AppStoreReviewDetail>>>(
+            &format!(
+                "/v1/appStoreVersions/{}/appStoreReviewDetail",
+                app_store_version_id
+            ),
+            &[],
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// Create the App Store review detail for an App Store version.
+    pub fn create_app_store_review_detail(
+        &self,
+        app_store_version_id: &str,
+        attributes: AppStoreReviewDetailAttributes,
+    ) -> Result<AppStoreReviewDetail, AppleCodesignError> {
+        let body = RequestDocument::create("appStoreReviewDetails", attributes).with_relationship(
+            "appStoreVersion",
+            "appStoreVersions",
+            app_store_version_id,
+        );
+
+        let doc =
+            self.api_post::<_, Document<AppStoreReviewDetail>>("/v1/appStoreReviewDetails", &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// Modify an existing App Store review detail.
+    pub fn update_app_store_review_detail(
+        &self,
+        review_detail_id: &str,
+        attributes: AppStoreReviewDetailAttributes,
+    ) -> Result<AppStoreReviewDetail, AppleCodesignError> {
+        let body = RequestDocument::update("appStoreReviewDetails", review_detail_id, attributes);
+
+        let doc = self.api_patch::<_, Document<AppStoreReviewDetail>>(
+            &format!("/v1/appStoreReviewDetails/{}", review_detail_id),
+            &body,
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// Create or update the App Store review detail of an App Store version.
+    ///
+    /// This is what you want to use to fill in review information at submission
+    /// time, as it works regardless of whether the detail already exists.
+    pub fn set_app_store_review_detail(
+        &self,
+        app_store_version_id: &str,
+        attributes: AppStoreReviewDetailAttributes,
+    ) -> Result<AppStoreReviewDetail, AppleCodesignError> {
+        match self.get_app_store_review_detail(app_store_version_id)? {
+            Some(existing) => self.update_app_store_review_detail(&existing.id, attributes),
+            None => self.create_app_store_review_detail(app_store_version_id, attributes),
+        }
+    }
+
+    /// List the review submissions of an app.
+    pub fn list_review_submissions(
+        &self,
+        app_id: &str,
+    ) -> Result<Vec<ReviewSubmission>, AppleCodesignError> {
+        self.api_get_all(
+            "/v1/reviewSubmissions",
+            &ReviewSubmissionQuery::new().app(app_id).to_query(),
+        )
+    }
+
+    /// Create a review submission of an app.
+    ///
+    /// The submission is a shell items are added to with
+    /// [Self::add_review_submission_item()]. It is sent to App Review once
+    /// submitted in App Store Connect.
+    pub fn create_review_submission(
+        &self,
+        app_id: &str,
+        platform: Platform,
+    ) -> Result<ReviewSubmission, AppleCodesignError> {
+        let body = RequestDocument::create(
+            "reviewSubmissions",
+            ReviewSubmissionAttributes {
+                platform: Some(platform.filter_value()),
+                ..Default::default()
+            },
+        )
+        .with_relationship("app", "apps", app_id);
+
+        let doc = self.api_post::<_, Document<ReviewSubmission>>("/v1/reviewSubmissions", &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// Add an App Store version to a review submission.
+    pub fn add_review_submission_item(
+        &self,
+        submission_id: &str,
+        app_store_version_id: &str,
+    ) -> Result<ReviewSubmissionItem, AppleCodesignError> {
+        let body = RequestDocument::create(
+            "reviewSubmissionItems",
+            ReviewSubmissionItemAttributes::default(),
+        )
+        .with_relationship("reviewSubmission", "reviewSubmissions", submission_id)
+        .with_relationship("appStoreVersion", "appStoreVersions", app_store_version_id);
+
+        let doc =
+            self.api_post::<_, Document<ReviewSubmissionItem>>("/v1/reviewSubmissionItems", &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// List the items of a review submission.
+    ///
+    /// Relationships to the resources under review are included.
+    pub fn list_review_submission_items(
+        &self,
+        submission_id: &str,
+    ) -> Result<Vec<ReviewSubmissionItem>, AppleCodesignError> {
+        self.api_get_all(
+            &format!("/v1/reviewSubmissions/{}/items", submission_id),
+            &[("include", REVIEW_ITEM_RELATIONSHIPS.join(","))],
+        )
+    }
+
+    /// Collect the review submissions of an app with unresolved issues.
+    ///
+    /// See the [module documentation](self) for what is available.
+    pub fn list_review_rejections(
+        &self,
+        app_id: &str,
+    ) -> Result<Vec<ReviewRejection>, AppleCodesignError> {
+        let mut rejections = vec![];
+
+        for submission in self.list_review_submissions(app_id)? {
+            if submission.attributes.state != Some(ReviewSubmissionState::UnresolvedIssues) {
+                continue;
+            }
+
+            let items = self
+                .list_review_submission_items(&submission.id)?
+                .into_iter()
+                .filter(|item| item.attributes.state == Some(ReviewSubmissionItemState::Rejected))
+                .map(|item| RejectedItem {
+                    resource: REVIEW_ITEM_RELATIONSHIPS
+                        .iter()
+                        .find_map(|r| item.related(r).into_iter().next().cloned()),
+                    id: item.id,
+                })
+                .collect();
+
+            rejections.push(ReviewRejection {
+                app_id: app_id.to_string(),
+                submission_id: submission.id,
+                platform: submission.attributes.platform,
+                submitted_date: submission.attributes.submitted_date,
+                state: submission.attributes.state,
+                items,
+            });
+        }
+
+        Ok(rejections)
+    }
+
+    /// List the attachments of an App Store review detail.
+    pub fn list_app_store_review_attachments(
+        &self,
+        review_detail_id: &str,
+    ) -> Result<Vec<AppStoreReviewAttachment>, AppleCodesignError> {
+        self.api_get_all(
+            &format!(
+                "/v1/appStoreReviewDetails/{}/appStoreReviewAttachments",
+                review_detail_id
+            ),
+            &[],
+        )
+    }
+
+    /// Delete an App Store review attachment.
+    pub fn delete_app_store_review_attachment(
+        &self,
+        attachment_id: &str,
+    ) -> Result<(), AppleCodesignError> {
+        self.api_delete(&format!("/v1/appStoreReviewAttachments/{}", attachment_id))
+    }
+
+    /// Upload a file as an attachment to an App Store review detail.
+    ///
+    /// This reserves the attachment, uploads the file content, and commits the
+    /// reservation.
+    pub fn upload_app_store_review_attachment(
+        &self,
+        review_detail_id: &str,
+        path: &Path,
+    ) -> Result<AppStoreReviewAttachment, AppleCodesignError> {
+        let data = std::fs::read(path)?;
+        let file_name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                AppleCodesignError::AppStoreConnectUpload(format!(
+                    "{} does not have a file name",
+                    path.display()
+                ))
+            })?;
+
+        warn!(
+            "reserving App Store review attachment {} ({} bytes)",
+            file_name,
+            data.len()
+        );
+
+        let body = RequestDocument::create(
+            "appStoreReviewAttachments",
+            AppStoreReviewAttachmentAttributes {
+                file_name: Some(file_name.clone()),
+                file_size: Some(data.len() as u64),
+                ..Default::default()
+            },
+        )
+        .with_relationship(
+            "appStoreReviewDetail",
+            "appStoreReviewDetails",
+            review_detail_id,
+        );
+
+        let reservation = self
+            .api_post::<_, Document<AppStoreReviewAttachment>>(
+                "/v1/appStoreReviewAttachments",
+                &body,
+            )?
+            .data;
+
+        self.perform_upload_operations(
+            &file_name,
+            reservation
+                .attributes
+                .upload_operations
+                .as_deref()
+                .unwrap_or_default(),
+            &data,
+        )?;
+
+        warn!("committing App Store review attachment {}", reservation.id);
+
+        let body = RequestDocument::update(
+            "appStoreReviewAttachments",
+            &reservation.id,
+            AppStoreReviewAttachmentAttributes {
+                source_file_checksum: Some(asset_checksum(&data)),
+                uploaded: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let doc = self.api_patch::<_, Document<AppStoreReviewAttachment>>(
+            &format!("/v1/appStoreReviewAttachments/{}", reservation.id),
+            &body,
+        )?;
+
+        Ok(doc.data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn rejections() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/reviewSubmissions",
+                200,
+                fixtures::collection(vec![
+                    fixtures::review_submission("S1", "COMPLETE"),
+                    fixtures::review_submission("S2", "UNRESOLVED_ISSUES"),
+                ]),
+            )
+            .json(
+                "GET",
+                "/v1/reviewSubmissions/S2/items",
+                200,
+                fixtures::collection(vec![
+                    fixtures::review_submission_item("I1", "REJECTED", "V1"),
+                    fixtures::review_submission_item("I2", "ACCEPTED", "V2"),
+                ]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let rejections = client.list_review_rejections("A1")?;
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].submission_id, "S2");
+        assert_eq!(rejections[0].items.len(), 1);
+        assert_eq!(rejections[0].items[0].id, "I1");
+        assert_eq!(
+            rejections[0].items[0].resource,
+            Some(ResourceIdentifier::new("appStoreVersions", "V1"))
+        );
+
+        // Items of submissions without issues aren't fetched.
+        assert_eq!(server.requests().len(), 2);
+
+        Ok(())
+    }
+}
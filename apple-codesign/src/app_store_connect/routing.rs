@@ -0,0 +1,428 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Routing operations to the API keys of several teams.
+//!
+//! API keys are scoped to a single team. Agencies managing apps of several
+//! teams would otherwise run every operation once per key. A [KeyRouter] holds
+//! a client per team and selects the client of the team owning a resource:
+//!
+//! * Bundle identifiers are routed to the team with the longest matching
+//!   `bundle_id_prefixes` entry.
+//! * Apps are routed to the team listing them in `app_ids`. Other apps are
+//!   looked up with each team's key and routed to the team that can see them.
+//! * Anything else is routed to `default_team`, if set.
+//!
+//! Routing is configured with TOML:
+//!
+//! ```toml
+//! default_team = "acme"
+//!
+//! [[team]]
+//! name = "acme"
+//! api_key_path = "/keys/acme.json"
+//! bundle_id_prefixes = ["com.acme."]
+//!
+//! [[team]]
+//! name = "globex"
+//! api_key_path = "/keys/globex.json"
+//! app_ids = ["1234567890"]
+//! ```
+//!
+//! `api_key_path` is a JSON file holding a [UnifiedApiKey].
+//!
+//! This module is available with the `cli` feature.
+
+use {
+    crate::{
+        app_store_connect::{
+            orchestrate::{run_per_app, AppResult},
+            AppStoreConnectClient, UnifiedApiKey,
+        },
+        AppleCodesignError,
+    },
+    serde::Deserialize,
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// The API key of a team and the resources it is used for.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TeamRoute {
+    /// Name identifying the team in logs and `default_team`.
+    pub name: String,
+
+    /// Path to the [UnifiedApiKey] JSON file of the team.
+    pub api_key_path: PathBuf,
+
+    /// Bundle identifier prefixes of the team's apps.
+    #[serde(default)]
+    pub bundle_id_prefixes: Vec<String>,
+
+    /// IDs of the team's apps.
+    #[serde(default)]
+    pub app_ids: Vec<String>,
+}
+
+/// Routing of operations to team API keys.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingConfig {
+    /// Team receiving operations no other rule matches.
+    #[serde(default)]
+    pub default_team: Option<String>,
+
+    #[serde(rename = "team")]
+    pub teams: Vec<TeamRoute>,
+}
+
+impl RoutingConfig {
+    /// Parse routing from TOML.
+    pub fn from_toml(data: &str) -> Result<Self, AppleCodesignError> {
+        let config = toml::from_str::<Self>(data)
+            .map_err(|e| AppleCodesignError::KeyRouting(e.to_string()))?;
+
+        if config.teams.is_empty() {
+            return Err(AppleCodesignError::KeyRouting(
+                "at least one team must be defined".into(),
+            ));
+        }
+        for (i, team) in config.teams.iter().enumerate() {
+            if config.teams[..i].iter().any(|t| t.name == team.name) {
+                return Err(AppleCodesignError::KeyRouting(format!(
+                    "team {} is defined more than once",
+                    team.name
+                )));
+            }
+        }
+        if let Some(name) = &config.default_team {
+            if !config.teams.iter().any(|t| &t.name == name) {
+                return Err(AppleCodesignError::KeyRouting(format!(
+                    "default_team {} is not defined",
+                    name
+                )));
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Read routing from a TOML file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+}
+
+struct RoutedTeam {
+    route: TeamRoute,
+    client: AppStoreConnectClient,
+}
+
+/// Selects the client of the team owning a resource.
+///
+/// See the [module documentation](self).
+pub struct KeyRouter {
+    teams: Vec<RoutedTeam>,
+    default_team: Option<usize>,
+
+    /// Teams of apps found by looking them up.
+    app_teams: Mutex<HashMap<String, usize>>,
+}
+
+impl KeyRouter {
+    /// Construct a router from configuration and a client per team.
+    ///
+    /// `clients` holds the client of each team of `config`, in order.
+    pub fn new(
+        config: RoutingConfig,
+        clients: Vec<AppStoreConnectClient>,
+    ) -> Result<Self, AppleCodesignError> {
+        if clients.len() != config.teams.len() {
+            return Err(AppleCodesignError::KeyRouting(format!(
+                "{} clients given for {} teams",
+                clients.len(),
+                config.teams.len()
+            )));
+        }
+
+        let default_team = config
+            .default_team
+            .as_ref()
+            .and_then(|name| config.teams.iter().position(|t| &t.name == name));
+
+        Ok(Self {
+            teams: config
+                .teams
+                .into_iter()
+                .zip(clients)
+                .map(|(route, client)| RoutedTeam { route, client })
+                .collect(),
+            default_team,
+            app_teams: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Construct a router from configuration, reading the API key of each team.
+    ///
+    /// `configure` is called with the client of each team, e.g. to set
+    /// timeouts.
+    pub fn from_config(
+        config: RoutingConfig,
+        configure: impl Fn(&mut AppStoreConnectClient),
+    ) -> Result<Self, AppleCodesignError> {
+        let clients = config
+            .teams
+            .iter()
+            .map(|team| {
+                let key = UnifiedApiKey::from_json_path(&team.api_key_path)?;
+                let mut client = AppStoreConnectClient::new(key.try_into()?)?;
+                configure(&mut client);
+
+                Ok(client)
+            })
+            .collect::<Result<Vec<_>, AppleCodesignError>>()?;
+
+        Self::new(config, clients)
+    }
+
+    /// A router sending everything to a single client.
+    pub fn single(client: AppStoreConnectClient) -> Self {
+        Self {
+            teams: vec![RoutedTeam {
+                route: TeamRoute {
+                    name: "default".into(),
+                    api_key_path: PathBuf::new(),
+                    bundle_id_prefixes: vec![],
+                    app_ids: vec![],
+                },
+                client,
+            }],
+            default_team: Some(0),
+            app_teams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Names of the teams, in configuration order.
+    pub fn team_names(&self) -> Vec<&str> {
+        self.teams.iter().map(|t| t.route.name.as_str()).collect()
+    }
+
+    fn default_client(&self, what: &str) -> Result<&AppStoreConnectClient, AppleCodesignError> {
+        self.default_team
+            .map(|i| &self.teams[i].client)
+            .ok_or_else(|| {
+                AppleCodesignError::KeyRouting(format!(
+                    "no team is configured for {} and there is no default_team",
+                    what
+                ))
+            })
+    }
+
+    /// The client of the team owning a bundle identifier.
+    pub fn client_for_bundle_id(
+        &self,
+        identifier: &str,
+    ) -> Result<&AppStoreConnectClient, AppleCodesignError> {
+        let team = self
+            .teams
+            .iter()
+            .filter_map(|team| {
+                team.route
+                    .bundle_id_prefixes
+                    .iter()
+                    .filter(|prefix| identifier.starts_with(prefix.as_str()))
+                    .map(|prefix| prefix.len())
+                    .max()
+                    .map(|len| (len, team))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, team)| team);
+
+        match team {
+            Some(team) => Ok(&team.client),
+            None => self.default_client(&format!("bundle identifier {}", identifier)),
+        }
+    }
+
+    /// The client of the team owning an app.
+    ///
+    /// Apps not listed in the configuration are looked up with each team's
+    /// key, in order. The team found is remembered.
+    pub fn client_for_app(
+        &self,
+        app_id: &str,
+    ) -> Result<&AppStoreConnectClient, AppleCodesignError> {
+        if let Some(team) = self
+            .teams
+            .iter()
+            .find(|team| team.route.app_ids.iter().any(|id| id == app_id))
+        {
+            return Ok(&team.client);
+        }
+
+        if let Some(i) = self.app_teams.lock().unwrap().get(app_id) {
+            return Ok(&self.teams[*i].client);
+        }
+
+        if self.teams.len() == 1 {
+            return Ok(&self.teams[0].client);
+        }
+
+        for (i, team) in self.teams.iter().enumerate() {
+            match team.client.get_app(app_id) {
+                Ok(app) => {
+                    // Bundle identifier prefixes take precedence, in case
+                    // several teams can see the app.
+                    let i = app
+                        .attributes
+                        .bundle_id
+                        .as_deref()
+                        .and_then(|identifier| {
+                            let client = self.client_for_bundle_id(identifier).ok()?;
+                            self.teams
+                                .iter()
+                                .position(|t| std::ptr::eq(&t.client, client))
+                        })
+                        .unwrap_or(i);
+
+                    self.app_teams.lock().unwrap().insert(app_id.to_string(), i);
+                    return Ok(&self.teams[i].client);
+                }
+                Err(AppleCodesignError::AppStoreConnectApi(e))
+                    if e.status == 403 || e.status == 404 => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.default_client(&format!("app {}", app_id))
+    }
+
+    /// Resolve the IDs of every app of every team.
+    pub fn all_app_ids(&self) -> Result<Vec<String>, AppleCodesignError> {
+        let mut app_ids = vec![];
+
+        for (i, team) in self.teams.iter().enumerate() {
+            for app_id in team.client.all_app_ids()? {
+                if !app_ids.contains(&app_id) {
+                    self.app_teams.lock().unwrap().insert(app_id.clone(), i);
+                    app_ids.push(app_id);
+                }
+            }
+        }
+
+        Ok(app_ids)
+    }
+
+    /// Run an operation against multiple apps concurrently with the client of each app's team.
+    ///
+    /// See [crate::app_store_connect::orchestrate::orchestrate()].
+    pub fn orchestrate<T, F>(
+        &self,
+        app_ids: &[String],
+        concurrency: usize,
+        op: F,
+    ) -> Result<Vec<AppResult<T>>, AppleCodesignError>
+    where
+        T: Send,
+        F: Fn(&AppStoreConnectClient, &str) -> Result<T, AppleCodesignError> + Sync,
+    {
+        run_per_app(app_ids, concurrency, |app_id| {
+            op(self.client_for_app(app_id)?, app_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn routing() -> Result<(), AppleCodesignError> {
+        let config = RoutingConfig::from_toml(
+            r#"
+            default_team = "acme"
+
+            [[team]]
+            name = "acme"
+            api_key_path = "acme.json"
+            bundle_id_prefixes = ["com.acme."]
+
+            [[team]]
+            name = "globex"
+            api_key_path = "globex.json"
+            bundle_id_prefixes = ["com.acme.globex."]
+            app_ids = ["G1"]
+            "#,
+        )?;
+
+        let acme = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                200,
+                fixtures::collection(vec![fixtures::app("A1", "com.acme.app")]),
+            )
+            .json(
+                "GET",
+                "/v1/apps/A1",
+                200,
+                fixtures::document(fixtures::app("A1", "com.acme.app")),
+            )
+            .start()?;
+        let globex = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                200,
+                fixtures::collection(vec![fixtures::app("G1", "com.acme.globex.app")]),
+            )
+            .start()?;
+
+        let router = KeyRouter::new(config, vec![acme.client()?, globex.client()?])?;
+        assert_eq!(router.team_names(), vec!["acme", "globex"]);
+
+        let team_of = |client: &AppStoreConnectClient| {
+            router
+                .teams
+                .iter()
+                .find(|t| std::ptr::eq(&t.client, client))
+                .map(|t| t.route.name.clone())
+                .unwrap()
+        };
+
+        assert_eq!(
+            team_of(router.client_for_bundle_id("com.acme.app")?),
+            "acme"
+        );
+        assert_eq!(
+            team_of(router.client_for_bundle_id("com.acme.globex.app")?),
+            "globex"
+        );
+        assert_eq!(team_of(router.client_for_bundle_id("org.other")?), "acme");
+        assert_eq!(team_of(router.client_for_app("G1")?), "globex");
+        assert_eq!(team_of(router.client_for_app("A1")?), "acme");
+
+        assert_eq!(router.all_app_ids()?, vec!["A1", "G1"]);
+
+        let results = router.orchestrate(&router.all_app_ids()?, 2, |client, app_id| {
+            Ok(format!("{}:{}", team_of(client), app_id))
+        })?;
+        assert_eq!(results[0].result.as_ref().unwrap(), "acme:A1");
+        assert_eq!(results[1].result.as_ref().unwrap(), "globex:G1");
+
+        assert!(RoutingConfig::from_toml(
+            "default_team = \"x\"\n[[team]]\nname = \"y\"\napi_key_path = \"y.json\""
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
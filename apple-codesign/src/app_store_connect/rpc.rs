@@ -0,0 +1,542 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A local JSON-RPC interface to an App Store Connect client.
+//!
+//! [serve()] accepts connections on a Unix domain socket and answers
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification) requests, one JSON
+//! document per line, using a single [AppStoreConnectClient]. Many
+//! short-lived build jobs on a machine can share the client, so tokens are
+//! minted and connections are established once rather than per job.
+//!
+//! Methods and their parameters:
+//!
+//! * `ping` - returns `"pong"`.
+//! * `list_apps`, `list_certificates`, `list_profiles`, `list_devices` -
+//!   return arrays of resources.
+//! * `list_builds` - `app_id` and optional `version`.
+//! * `get_build` - `build_id`.
+//! * `download_profile` - `profile_id` and optional `output_path`. Without a
+//!   path, the base64 encoded `.mobileprovision` is returned.
+//! * `upload_ipa` - `app_id`, `path`, and `platform` (e.g. `IOS`).
+//! * `shutdown` - stops the server after responding.
+//!
+//! Error messages are redacted before being returned to callers.
+
+use {
+    crate::{
+        app_store_connect::{
+            build_upload_api::Platform, certs_api::DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS,
+            AppStoreConnectClient,
+        },
+        redact::redact,
+        AppleCodesignError,
+    },
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    serde_json::{json, Value},
+    std::path::PathBuf,
+};
+
+#[cfg(unix)]
+use {
+    log::{info, warn},
+    std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Write},
+        net::Shutdown,
+        os::unix::{
+            fs::{FileTypeExt, PermissionsExt},
+            net::{UnixListener, UnixStream},
+        },
+        path::Path,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+    },
+};
+
+/// Invalid JSON was received.
+pub const PARSE_ERROR: i64 = -32700;
+
+/// The method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Invalid method parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The App Store Connect operation failed.
+pub const SERVER_ERROR: i64 = -32000;
+
+/// A JSON-RPC request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl RpcRequest {
+    /// Construct a request.
+    pub fn new(id: impl Into<Value>, method: impl ToString, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            id: id.into(),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// A JSON-RPC error object.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: redact(&message.to_string()).to_string(),
+        }
+    }
+}
+
+impl From<AppleCodesignError> for RpcError {
+    fn from(e: AppleCodesignError) -> Self {
+        Self::new(SERVER_ERROR, e)
+    }
+}
+
+/// A JSON-RPC response.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+
+        Self {
+            jsonrpc: "2.0".into(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListBuildsParams {
+    app_id: String,
+    version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GetBuildParams {
+    build_id: String,
+}
+
+#[derive(Deserialize)]
+struct DownloadProfileParams {
+    profile_id: String,
+    output_path: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct UploadIpaParams {
+    app_id: String,
+    path: PathBuf,
+    platform: Platform,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(SERVER_ERROR, e))
+}
+
+/// Perform a method call against a client.
+///
+/// `shutdown` is handled by [serve()] and is unknown here.
+pub fn dispatch(
+    client: &AppStoreConnectClient,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    match method {
+        "ping" => Ok(json!("pong")),
+        "list_apps" => to_value(client.list_apps()?),
+        "list_certificates" => to_value(client.list_certificates()?),
+        "list_profiles" => to_value(client.list_profiles()?),
+        "list_devices" => to_value(client.list_devices()?),
+        "list_builds" => {
+            let p = self::params::<ListBuildsParams>(params)?;
+            to_value(client.list_builds(&p.app_id, p.version.as_deref())?)
+        }
+        "get_build" => {
+            let p = self::params::<GetBuildParams>(params)?;
+            to_value(client.get_build(&p.build_id)?)
+        }
+        "download_profile" => {
+            let p = self::params::<DownloadProfileParams>(params)?;
+            let profile =
+                client.download_profile(&p.profile_id, DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS)?;
+
+            if let Some(path) = p.output_path {
+                std::fs::write(&path, &profile.data).map_err(AppleCodesignError::from)?;
+                Ok(json!({ "output_path": path }))
+            } else {
+                Ok(json!({ "data": base64::encode(&profile.data) }))
+            }
+        }
+        "upload_ipa" => {
+            let p = self::params::<UploadIpaParams>(params)?;
+            let upload = client.upload_ipa(&p.app_id, &p.path, p.platform)?;
+            to_value(upload)
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method: {}", method),
+        )),
+    }
+}
+
+/// Handle a single line of input, returning the response line.
+///
+/// The boolean is true if the request asked the server to shut down.
+pub fn handle_line(client: &AppStoreConnectClient, line: &str) -> (String, bool) {
+    let (response, shutdown) = match serde_json::from_str::<RpcRequest>(line) {
+        Ok(request) if request.method == "shutdown" => {
+            (RpcResponse::new(request.id, Ok(Value::Null)), true)
+        }
+        Ok(request) => (
+            RpcResponse::new(
+                request.id,
+                dispatch(client, &request.method, request.params),
+            ),
+            false,
+        ),
+        Err(e) => (
+            RpcResponse::new(Value::Null, Err(RpcError::new(PARSE_ERROR, e))),
+            false,
+        ),
+    };
+
+    (
+        serde_json::to_string(&response).expect("responses should serialize"),
+        shutdown,
+    )
+}
+
+#[cfg(unix)]
+fn handle_connection(
+    client: &AppStoreConnectClient,
+    stream: UnixStream,
+    stop: &AtomicBool,
+    socket_path: &Path,
+) -> Result<(), AppleCodesignError> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (response, shutdown) = handle_line(client, &line);
+        writer.write_all(response.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+
+        if shutdown {
+            stop.store(true, Ordering::SeqCst);
+            // Wake up the accept loop so it notices.
+            UnixStream::connect(socket_path).ok();
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Bind a socket at `socket_path` only accessible to the current user.
+///
+/// The socket is bound in a private directory next to `socket_path` and
+/// moved into place once its permissions are restricted, so it is never
+/// accessible to others, whatever the umask.
+#[cfg(unix)]
+fn bind_private(socket_path: &Path) -> Result<UnixListener, AppleCodesignError> {
+    let parent = match socket_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    // Created with mode 0700.
+    let staging = tempfile::Builder::new()
+        .prefix(".asconnect-serve-")
+        .tempdir_in(parent)?;
+    let staged_path = staging.path().join("socket");
+
+    let listener = UnixListener::bind(&staged_path)?;
+    std::fs::set_permissions(&staged_path, std::fs::Permissions::from_mode(0o600))?;
+    std::fs::rename(&staged_path, socket_path)?;
+
+    Ok(listener)
+}
+
+/// Serve JSON-RPC requests on a Unix domain socket until `shutdown` is called.
+///
+/// A stale socket at `socket_path` is replaced, but nothing other than a
+/// socket is. The socket is only accessible to the current user and is
+/// removed when the server stops. Each connection is handled on its own
+/// thread. On `shutdown`, other connections are closed. Requests they are
+/// performing run to completion, but their responses are dropped.
+#[cfg(unix)]
+pub fn serve(client: AppStoreConnectClient, socket_path: &Path) -> Result<(), AppleCodesignError> {
+    // Fail before binding the socket if no token can be minted.
+    client.prewarm_token()?;
+
+    match std::fs::symlink_metadata(socket_path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(AppleCodesignError::AppStoreConnectRpc(format!(
+                "refusing to replace {}: not a socket",
+                socket_path.display()
+            )));
+        }
+        Ok(_) => {
+            if UnixStream::connect(socket_path).is_ok() {
+                return Err(AppleCodesignError::AppStoreConnectRpc(format!(
+                    "a server is already listening on {}",
+                    socket_path.display()
+                )));
+            }
+
+            warn!("removing stale socket {}", socket_path.display());
+            std::fs::remove_file(socket_path)?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let listener = bind_private(socket_path)?;
+    info!("listening on {}", socket_path.display());
+
+    let client = Arc::new(client);
+    let stop = Arc::new(AtomicBool::new(false));
+    let connections = Mutex::new(HashMap::<u64, UnixStream>::new());
+
+    // Keep the token fresh, so requests don't wait for one to be minted.
+    let _refresher = client.spawn_token_refresher();
+
+    let res = std::thread::scope(|scope| -> Result<(), AppleCodesignError> {
+        for (id, stream) in (0u64..).zip(listener.incoming()) {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let stream = stream?;
+            connections
+                .lock()
+                .expect("lock should not be poisoned")
+                .insert(id, stream.try_clone()?);
+
+            let client = client.clone();
+            let stop = stop.clone();
+            let connections = &connections;
+
+            scope.spawn(move || {
+                if let Err(e) = handle_connection(&client, stream, &stop, socket_path) {
+                    warn!("connection error: {}", redact(&e.to_string()));
+                }
+
+                connections
+                    .lock()
+                    .expect("lock should not be poisoned")
+                    .remove(&id);
+            });
+        }
+
+        // Unblock connections waiting for requests, so joining them doesn't
+        // wait for their clients to disconnect.
+        for stream in connections
+            .lock()
+            .expect("lock should not be poisoned")
+            .values()
+        {
+            stream.shutdown(Shutdown::Both).ok();
+        }
+
+        Ok(())
+    });
+
+    std::fs::remove_file(socket_path).ok();
+    info!("server stopped");
+
+    res
+}
+
+/// Call a method on a server listening on `socket_path`.
+#[cfg(unix)]
+pub fn call(socket_path: &Path, method: &str, params: Value) -> Result<Value, AppleCodesignError> {
+    let mut stream = UnixStream::connect(socket_path)?;
+
+    let request = serde_json::to_string(&RpcRequest::new(1, method, params))?;
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    let response = serde_json::from_str::<RpcResponse>(&line)?;
+
+    match (response.result, response.error) {
+        (_, Some(e)) => Err(AppleCodesignError::AppStoreConnectRpc(format!(
+            "{} (code {})",
+            e.message, e.code
+        ))),
+        (Some(value), None) => Ok(value),
+        (None, None) => Ok(Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn dispatch_methods() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/devices",
+                200,
+                fixtures::collection(vec![fixtures::device("D1", "1")]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let (response, shutdown) = handle_line(
+            &client,
+            r#"{"jsonrpc": "2.0", "id": 7, "method": "list_devices"}"#,
+        );
+        let response = serde_json::from_str::<RpcResponse>(&response)?;
+        assert!(!shutdown);
+        assert_eq!(response.id, json!(7));
+        assert_eq!(response.result.unwrap()[0]["id"], json!("D1"));
+
+        let (response, _) =
+            handle_line(&client, r#"{"jsonrpc": "2.0", "id": 1, "method": "nope"}"#);
+        let response = serde_json::from_str::<RpcResponse>(&response)?;
+        assert_eq!(response.error.unwrap().code, METHOD_NOT_FOUND);
+
+        let (response, _) = handle_line(
+            &client,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "get_build", "params": {}}"#,
+        );
+        let response = serde_json::from_str::<RpcResponse>(&response)?;
+        assert_eq!(response.error.unwrap().code, INVALID_PARAMS);
+
+        let (response, _) = handle_line(&client, "{");
+        let response = serde_json::from_str::<RpcResponse>(&response)?;
+        assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+
+        let (_, shutdown) = handle_line(
+            &client,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}"#,
+        );
+        assert!(shutdown);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket_round_trip() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                200,
+                fixtures::collection(vec![fixtures::app("A1", "com.example.app")]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let td = tempfile::TempDir::new()?;
+        let socket_path = td.path().join("asconnect.sock");
+
+        let serve_path = socket_path.clone();
+        let handle = std::thread::spawn(move || serve(client, &serve_path));
+
+        for _ in 0..100 {
+            if socket_path.exists() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(call(&socket_path, "ping", Value::Null)?, json!("pong"));
+        // Both calls are served with the same token.
+        call(&socket_path, "list_apps", Value::Null)?;
+        let apps = call(&socket_path, "list_apps", Value::Null)?;
+        assert_eq!(apps[0]["id"], json!("A1"));
+
+        let requests = server.requests();
+        assert_eq!(
+            requests[0].header("authorization"),
+            requests[1].header("authorization")
+        );
+
+        // An idle connection doesn't keep the server running.
+        let _idle = UnixStream::connect(&socket_path)?;
+
+        call(&socket_path, "shutdown", Value::Null)?;
+        handle.join().unwrap()?;
+        assert!(!socket_path.exists());
+
+        // The private directory the socket was bound in is gone too.
+        assert_eq!(std::fs::read_dir(td.path())?.count(), 0);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuse_non_socket() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default().start()?;
+
+        let td = tempfile::TempDir::new()?;
+        let path = td.path().join("important.txt");
+        std::fs::write(&path, "keep me")?;
+
+        assert!(matches!(
+            serve(server.client()?, &path),
+            Err(AppleCodesignError::AppStoreConnectRpc(_))
+        ));
+        assert_eq!(std::fs::read_to_string(&path)?, "keep me");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Scheduling of operations into off-peak windows.
+//!
+//! Large uploads can be held back until a time of day, e.g. 01:00 in
+//! `Europe/Berlin`. [ScheduleWindow::next_opening()] resolves the next instant
+//! an operation may start. Queued operations persist this instant (see
+//! [super::queue]) so they are deferred until a later run of the queue.
+//! Commands performing an operation directly wait for it with [wait_until()].
+//!
+//! This module is available with the `asc-builds` feature.
+
+use {
+    crate::AppleCodesignError,
+    chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc},
+    chrono_tz::Tz,
+    log::warn,
+    std::time::Instant,
+};
+
+/// A daily window during which operations may start.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ScheduleWindow {
+    /// Local time the window opens.
+    pub start: NaiveTime,
+
+    /// Local time the window closes.
+    ///
+    /// If unset, operations only start at [Self::start]. Windows closing
+    /// before they open span midnight.
+    pub end: Option<NaiveTime>,
+
+    /// Time zone of the start and end times.
+    pub timezone: Tz,
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, AppleCodesignError> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| {
+        AppleCodesignError::InvalidSchedule(format!("{} is not a time like 01:00", value))
+    })
+}
+
+impl ScheduleWindow {
+    /// Construct an instance from `HH:MM` times and an IANA time zone name.
+    pub fn parse(
+        start: &str,
+        end: Option<&str>,
+        timezone: &str,
+    ) -> Result<Self, AppleCodesignError> {
+        Ok(Self {
+            start: parse_time(start)?,
+            end: end.map(parse_time).transpose()?,
+            timezone: timezone.parse().map_err(|_| {
+                AppleCodesignError::InvalidSchedule(format!("unknown time zone {}", timezone))
+            })?,
+        })
+    }
+
+    /// Whether an instant is within the window.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let time = at.with_timezone(&self.timezone).time();
+
+        match self.end {
+            Some(end) if self.start <= end => self.start <= time && time < end,
+            Some(end) => time >= self.start || time < end,
+            None => false,
+        }
+    }
+
+    /// The first instant at or after `now` an operation may start.
+    pub fn next_opening(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        if self.contains(now) {
+            return now;
+        }
+
+        let today = now.with_timezone(&self.timezone).naive_local().date();
+
+        (0..3)
+            .filter_map(|days| {
+                let local = (today + Duration::days(days)).and_time(self.start);
+
+                // Local times skipped by a daylight saving time transition
+                // open an hour later.
+                self.timezone
+                    .from_local_datetime(&local)
+                    .earliest()
+                    .or_else(|| {
+                        self.timezone
+                            .from_local_datetime(&(local + Duration::hours(1)))
+                            .earliest()
+                    })
+            })
+            .map(|start| start.with_timezone(&Utc))
+            .find(|start| *start >= now)
+            .expect("window should open within 3 days")
+    }
+}
+
+/// Block until an instant.
+///
+/// Errors without waiting if `deadline` is reached first.
+pub fn wait_until(at: DateTime<Utc>, deadline: Option<Instant>) -> Result<(), AppleCodesignError> {
+    let delay = match (at - Utc::now()).to_std() {
+        Ok(delay) => delay,
+        // The instant has passed.
+        Err(_) => return Ok(()),
+    };
+
+    if let Some(deadline) = deadline {
+        if Instant::now() + delay > deadline {
+            return Err(AppleCodesignError::AppStoreConnectDeadlineExceeded);
+        }
+    }
+
+    warn!("waiting until {} to start", at.to_rfc3339());
+    std::thread::sleep(delay);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn next_opening() -> Result<(), AppleCodesignError> {
+        let window = ScheduleWindow::parse("01:00", None, "Europe/Berlin")?;
+
+        // 01:00 CET is 00:00 UTC.
+        assert_eq!(
+            window.next_opening(utc("2022-01-10T12:00:00Z")),
+            utc("2022-01-11T00:00:00Z")
+        );
+        assert_eq!(
+            window.next_opening(utc("2022-01-10T23:30:00Z")),
+            utc("2022-01-11T00:00:00Z")
+        );
+        // 01:00 CEST is 23:00 UTC the day before.
+        assert_eq!(
+            window.next_opening(utc("2022-07-10T12:00:00Z")),
+            utc("2022-07-10T23:00:00Z")
+        );
+
+        let window = ScheduleWindow::parse("22:00", Some("06:00"), "UTC")?;
+        let now = utc("2022-01-10T03:00:00Z");
+        assert!(window.contains(now));
+        assert_eq!(window.next_opening(now), now);
+        assert_eq!(
+            window.next_opening(utc("2022-01-10T07:00:00Z")),
+            utc("2022-01-10T22:00:00Z")
+        );
+
+        // 02:30 doesn't exist on the day daylight saving time starts.
+        let window = ScheduleWindow::parse("02:30", None, "Europe/Berlin")?;
+        assert_eq!(
+            window.next_opening(utc("2022-03-27T00:00:00Z")),
+            utc("2022-03-27T01:30:00Z")
+        );
+
+        assert!(ScheduleWindow::parse("25:00", None, "UTC").is_err());
+        assert!(ScheduleWindow::parse("01:00", None, "Mars/Olympus").is_err());
+
+        Ok(())
+    }
+}
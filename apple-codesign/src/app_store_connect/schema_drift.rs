@@ -0,0 +1,455 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Detection of response fields unknown to this crate.
+//!
+//! Apple extends resources with new attributes and relationships without
+//! notice. Unknown fields are ignored when deserializing responses, so they go
+//! unnoticed until someone reads the API changelog. A [SchemaDrift] attached to
+//! [AppStoreConnectClient::set_schema_drift()] inspects every response
+//! document and records fields that are unknown:
+//!
+//! * Attributes of primary data missing from the attribute type the crate
+//!   deserializes the resource into.
+//! * Attributes and relationships missing from a baseline: the fields of each
+//!   resource type seen in earlier runs (see [SchemaDrift::from_path()]).
+//!
+//! Resource types without an attribute type or a baseline entry are only
+//! recorded. Each unknown field is reported once in [SchemaDrift::entries()],
+//! with the number of responses it appeared in.
+
+use {
+    crate::AppleCodesignError,
+    log::debug,
+    serde::{
+        de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess},
+        forward_to_deserialize_any, Deserialize, Deserializer, Serialize,
+    },
+    serde_json::Value,
+    std::{
+        cell::Cell,
+        collections::{BTreeMap, BTreeSet},
+        path::Path,
+        sync::Mutex,
+    },
+};
+
+#[derive(Debug)]
+struct ProbeError;
+
+impl std::fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("probe finished")
+    }
+}
+
+impl std::error::Error for ProbeError {}
+
+impl de::Error for ProbeError {
+    fn custom<T: std::fmt::Display>(_: T) -> Self {
+        Self
+    }
+}
+
+/// A deserializer walking from a document to the attributes of its resources.
+///
+/// Derived `Deserialize` implementations pass the names of their fields to
+/// [Deserializer::deserialize_struct()]. The probe enters the `data` member of
+/// documents, elements of lists, and the `attributes` member of resources, and
+/// captures the field names of the attributes type.
+struct Probe<'a> {
+    fields: &'a Cell<Option<&'static [&'static str]>>,
+    in_attributes: bool,
+}
+
+impl<'de, 'a> Deserializer<'de> for Probe<'a> {
+    type Error = ProbeError;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, _: V) -> Result<V::Value, ProbeError> {
+        Err(ProbeError)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProbeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProbeError> {
+        visitor.visit_seq(ProbeAccess {
+            key: None,
+            probe: Some(self),
+        })
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ProbeError> {
+        if self.in_attributes {
+            self.fields.set(Some(fields));
+            return Err(ProbeError);
+        }
+
+        let key = if fields.contains(&"data") {
+            "data"
+        } else if fields.contains(&"type") && fields.contains(&"attributes") {
+            "attributes"
+        } else {
+            return Err(ProbeError);
+        };
+
+        visitor.visit_map(ProbeAccess {
+            key: Some(key),
+            probe: Some(Probe {
+                fields: self.fields,
+                in_attributes: key == "attributes",
+            }),
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple tuple_struct map
+        enum identifier ignored_any
+    }
+}
+
+/// A single map entry or list element whose value is probed.
+struct ProbeAccess<'a> {
+    key: Option<&'static str>,
+    probe: Option<Probe<'a>>,
+}
+
+impl<'de, 'a> MapAccess<'de> for ProbeAccess<'a> {
+    type Error = ProbeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ProbeError> {
+        match self.key.take() {
+            Some(key) => seed
+                .deserialize(IntoDeserializer::<ProbeError>::into_deserializer(key))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ProbeError> {
+        seed.deserialize(self.probe.take().ok_or(ProbeError)?)
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for ProbeAccess<'a> {
+    type Error = ProbeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ProbeError> {
+        match self.probe.take() {
+            Some(probe) => seed.deserialize(probe).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Names of the attributes of the resources in a document type.
+///
+/// e.g. the fields of `A` for `Document<Vec<Resource<A>>>`. `None` if `T` isn't
+/// a document of resources or the attributes type doesn't declare its fields
+/// (such as `serde_json::Value`).
+pub fn attribute_fields<T: DeserializeOwned>() -> Option<&'static [&'static str]> {
+    let fields = Cell::new(None);
+
+    let _ = T::deserialize(Probe {
+        fields: &fields,
+        in_attributes: false,
+    });
+
+    fields.get()
+}
+
+/// Whether a field is an attribute or a relationship.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FieldKind {
+    Attribute,
+    Relationship,
+}
+
+impl FieldKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Attribute => "attribute",
+            Self::Relationship => "relationship",
+        }
+    }
+}
+
+/// The fields of a resource type.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceFields {
+    #[serde(default)]
+    pub attributes: BTreeSet<String>,
+    #[serde(default)]
+    pub relationships: BTreeSet<String>,
+}
+
+impl ResourceFields {
+    fn get_mut(&mut self, kind: FieldKind) -> &mut BTreeSet<String> {
+        match kind {
+            FieldKind::Attribute => &mut self.attributes,
+            FieldKind::Relationship => &mut self.relationships,
+        }
+    }
+
+    fn contains(&self, kind: FieldKind, name: &str) -> bool {
+        match kind {
+            FieldKind::Attribute => self.attributes.contains(name),
+            FieldKind::Relationship => self.relationships.contains(name),
+        }
+    }
+}
+
+/// Fields seen per resource type. Serialized as a JSON object keyed by type.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ObservedSchema(pub BTreeMap<String, ResourceFields>);
+
+/// A field unknown to the crate or the baseline.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDriftEntry {
+    pub resource_type: String,
+    pub kind: FieldKind,
+    pub name: String,
+
+    /// Number of responses the field appeared in.
+    pub count: usize,
+
+    /// Request path of the first response the field appeared in.
+    pub first_path: String,
+}
+
+#[derive(Default)]
+struct State {
+    baseline: ObservedSchema,
+    observed: ObservedSchema,
+    drift: BTreeMap<(String, FieldKind, String), SchemaDriftEntry>,
+}
+
+/// Records response fields unknown to the crate. See the [module documentation](self).
+#[derive(Default)]
+pub struct SchemaDrift {
+    state: Mutex<State>,
+}
+
+impl SchemaDrift {
+    /// Construct an instance comparing against a baseline.
+    pub fn new(baseline: ObservedSchema) -> Self {
+        Self {
+            state: Mutex::new(State {
+                baseline,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Construct an instance with the baseline in a JSON file, if it exists.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        let baseline = if path.exists() {
+            serde_json::from_slice(&std::fs::read(path)?)?
+        } else {
+            ObservedSchema::default()
+        };
+
+        Ok(Self::new(baseline))
+    }
+
+    /// Record the resources of a response document.
+    ///
+    /// `attributes` are the attribute names the primary data is deserialized
+    /// with. See [attribute_fields()].
+    pub fn observe(&self, path: &str, document: &Value, attributes: Option<&[&str]>) {
+        let primary = match &document["data"] {
+            Value::Array(resources) => resources.iter().collect::<Vec<_>>(),
+            Value::Object(_) => vec![&document["data"]],
+            _ => vec![],
+        };
+        let included = document["included"]
+            .as_array()
+            .map(|resources| resources.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        let mut state = self.state.lock().unwrap();
+        let mut seen = BTreeSet::new();
+
+        for (resource, model) in primary
+            .into_iter()
+            .map(|r| (r, attributes))
+            .chain(included.into_iter().map(|r| (r, None)))
+        {
+            let resource_type = match resource["type"].as_str() {
+                Some(value) => value,
+                None => continue,
+            };
+
+            for (kind, model, member) in [
+                (FieldKind::Attribute, model, "attributes"),
+                (FieldKind::Relationship, None, "relationships"),
+            ] {
+                for name in resource[member]
+                    .as_object()
+                    .into_iter()
+                    .flat_map(|o| o.keys())
+                {
+                    state
+                        .observed
+                        .0
+                        .entry(resource_type.to_string())
+                        .or_default()
+                        .get_mut(kind)
+                        .insert(name.clone());
+
+                    let baseline = state.baseline.0.get(resource_type);
+                    if model.is_none() && baseline.is_none() {
+                        continue;
+                    }
+                    if model.is_some_and(|fields| fields.contains(&name.as_str()))
+                        || baseline.is_some_and(|fields| fields.contains(kind, name))
+                    {
+                        continue;
+                    }
+
+                    // Count each field once per response.
+                    let key = (resource_type.to_string(), kind, name.clone());
+                    if !seen.insert(key.clone()) {
+                        continue;
+                    }
+
+                    let entry = state.drift.entry(key).or_insert_with(|| {
+                        debug!(
+                            "{} {} has unknown {} {}",
+                            path,
+                            resource_type,
+                            kind.as_str(),
+                            name
+                        );
+
+                        SchemaDriftEntry {
+                            resource_type: resource_type.to_string(),
+                            kind,
+                            name: name.clone(),
+                            count: 0,
+                            first_path: path.to_string(),
+                        }
+                    });
+                    entry.count += 1;
+                }
+            }
+        }
+    }
+
+    /// The unknown fields seen so far.
+    pub fn entries(&self) -> Vec<SchemaDriftEntry> {
+        self.state.lock().unwrap().drift.values().cloned().collect()
+    }
+
+    /// The baseline extended with the fields seen so far.
+    pub fn observed(&self) -> ObservedSchema {
+        let state = self.state.lock().unwrap();
+        let mut schema = state.baseline.clone();
+
+        for (resource_type, fields) in &state.observed.0 {
+            let entry = schema.0.entry(resource_type.clone()).or_default();
+            entry.attributes.extend(fields.attributes.iter().cloned());
+            entry
+                .relationships
+                .extend(fields.relationships.iter().cloned());
+        }
+
+        schema
+    }
+
+    /// Write [Self::observed()] to a JSON file, to be used as the next baseline.
+    pub fn write_baseline(&self, path: &Path) -> Result<(), AppleCodesignError> {
+        std::fs::write(path, serde_json::to_vec_pretty(&self.observed())?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::{
+            app_api::AppAttributes,
+            json_api::{Document, Resource},
+            testing::{fixtures, MockServerBuilder},
+        },
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn probe_attribute_fields() {
+        let fields = attribute_fields::<Document<Vec<Resource<AppAttributes>>>>().unwrap();
+        assert!(fields.contains(&"bundleId"));
+        assert_eq!(
+            attribute_fields::<Document<Resource<AppAttributes>>>(),
+            Some(fields)
+        );
+        assert_eq!(attribute_fields::<Value>(), None);
+        assert_eq!(attribute_fields::<Document<Value>>(), None);
+    }
+
+    #[test]
+    fn client_records_drift() -> Result<(), AppleCodesignError> {
+        let mut app = fixtures::app("A1", "com.example.app");
+        app["attributes"]["streamlinedPurchasingEnabled"] = true.into();
+        app["relationships"] = serde_json::json!({"appInfos": {}, "ciProduct": {}});
+
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![app]))
+            .start()?;
+
+        let mut baseline = ObservedSchema::default();
+        baseline.0.insert(
+            "apps".into(),
+            ResourceFields {
+                relationships: ["appInfos".to_string()].into_iter().collect(),
+                ..Default::default()
+            },
+        );
+        let drift = Arc::new(SchemaDrift::new(baseline));
+
+        let mut client = server.client()?;
+        client.set_schema_drift(Some(drift.clone()));
+        client.list_apps()?;
+        client.list_apps()?;
+
+        assert_eq!(
+            drift
+                .entries()
+                .iter()
+                .map(|e| (e.kind, e.name.as_str(), e.count))
+                .collect::<Vec<_>>(),
+            vec![
+                (FieldKind::Attribute, "streamlinedPurchasingEnabled", 2),
+                (FieldKind::Relationship, "ciProduct", 2),
+            ]
+        );
+        assert!(drift.observed().0["apps"]
+            .relationships
+            .contains("ciProduct"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Searching resources across an account.
+//!
+//! [AppStoreConnectClient::search()] lists apps, bundle IDs, provisioning
+//! profiles, certificates, and devices concurrently and matches a query
+//! against their names and identifiers:
+//!
+//! | Resource    | Fields                                 |
+//! |-------------|----------------------------------------|
+//! | App         | ID, name, bundle ID, SKU               |
+//! | Bundle ID   | ID, name, identifier                   |
+//! | Profile     | ID, name, UUID                         |
+//! | Certificate | ID, name, display name, serial number  |
+//! | Device      | ID, name, UDID, model                  |
+//!
+//! Matching is case-insensitive and finds the query anywhere in a field.
+//! Dashes, colons, and whitespace are ignored in UDIDs, UUIDs, and serial
+//! numbers, so they match however they were copied.
+
+use {
+    crate::{app_store_connect::AppStoreConnectClient, AppleCodesignError},
+    serde::Serialize,
+};
+
+/// The type of a resource found by a search.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchResourceType {
+    App,
+    BundleId,
+    Profile,
+    Certificate,
+    Device,
+}
+
+impl SearchResourceType {
+    /// The name of the type as printed by the CLI.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::App => "app",
+            Self::BundleId => "bundle-id",
+            Self::Profile => "profile",
+            Self::Certificate => "certificate",
+            Self::Device => "device",
+        }
+    }
+}
+
+/// A resource matching a search query.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub resource_type: SearchResourceType,
+
+    /// ID of the resource.
+    pub id: String,
+
+    /// Name of the resource, if it has one.
+    pub name: Option<String>,
+
+    /// Name of the field the query matched. e.g. `udid`.
+    pub field: &'static str,
+
+    /// Value of the field the query matched.
+    pub value: String,
+
+    /// Whether the query matched the whole field.
+    pub exact: bool,
+}
+
+/// A field of a resource that is searched.
+struct Field<'a> {
+    name: &'static str,
+    value: Option<&'a str>,
+    /// Whether separators are ignored when matching.
+    compact: bool,
+}
+
+impl<'a> Field<'a> {
+    fn text(name: &'static str, value: Option<&'a str>) -> Self {
+        Self {
+            name,
+            value,
+            compact: false,
+        }
+    }
+
+    fn identifier(name: &'static str, value: Option<&'a str>) -> Self {
+        Self {
+            name,
+            value,
+            compact: true,
+        }
+    }
+}
+
+fn normalize(s: &str, compact: bool) -> String {
+    s.chars()
+        .filter(|c| !compact || !(*c == '-' || *c == ':' || c.is_whitespace()))
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// A search query.
+#[derive(Clone, Debug)]
+pub struct SearchQuery {
+    text: String,
+    compact: String,
+}
+
+impl SearchQuery {
+    /// Construct a query. Leading and trailing whitespace is ignored.
+    pub fn new(query: &str) -> Result<Self, AppleCodesignError> {
+        let query = query.trim();
+
+        if query.is_empty() {
+            return Err(AppleCodesignError::CliGeneralError(
+                "search query must not be empty".into(),
+            ));
+        }
+
+        Ok(Self {
+            text: normalize(query, false),
+            compact: normalize(query, true),
+        })
+    }
+
+    /// Match the query against the fields of a resource.
+    ///
+    /// Returns a hit for the best matching field. Exact matches are better
+    /// than partial matches, and earlier fields are better than later ones.
+    fn match_resource<'a>(
+        &self,
+        resource_type: SearchResourceType,
+        id: &'a str,
+        name: Option<&str>,
+        fields: impl IntoIterator<Item = Field<'a>>,
+    ) -> Option<SearchHit> {
+        let mut best: Option<SearchHit> = None;
+
+        for field in std::iter::once(Field::identifier("id", Some(id))).chain(fields) {
+            let value = match field.value {
+                Some(value) if !value.is_empty() => value,
+                _ => continue,
+            };
+
+            let query = if field.compact {
+                &self.compact
+            } else {
+                &self.text
+            };
+            let normalized = normalize(value, field.compact);
+
+            if !normalized.contains(query.as_str()) {
+                continue;
+            }
+
+            let exact = normalized == *query;
+            if best.as_ref().is_none_or(|hit| exact && !hit.exact) {
+                best = Some(SearchHit {
+                    resource_type,
+                    id: id.to_string(),
+                    name: name.map(|x| x.to_string()),
+                    field: field.name,
+                    value: value.to_string(),
+                    exact,
+                });
+            }
+        }
+
+        best
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Search apps, bundle IDs, profiles, certificates, and devices.
+    ///
+    /// See the [module documentation](self) for which fields are searched.
+    /// Exact matches are sorted first, followed by resource type and name.
+    pub fn search(&self, query: &str) -> Result<Vec<SearchHit>, AppleCodesignError> {
+        let query = SearchQuery::new(query)?;
+
+        let (apps, bundle_ids, profiles, certificates, devices) = std::thread::scope(|scope| {
+            let apps = scope.spawn(|| self.list_apps());
+            let bundle_ids = scope.spawn(|| self.list_bundle_ids());
+            let profiles = scope.spawn(|| self.list_profiles());
+            let certificates = scope.spawn(|| self.list_certificates());
+            let devices = scope.spawn(|| self.list_devices());
+
+            (
+                apps.join().expect("search thread should not panic"),
+                bundle_ids.join().expect("search thread should not panic"),
+                profiles.join().expect("search thread should not panic"),
+                certificates.join().expect("search thread should not panic"),
+                devices.join().expect("search thread should not panic"),
+            )
+        });
+
+        let mut hits = vec![];
+
+        hits.extend(apps?.iter().filter_map(|app| {
+            let a = &app.attributes;
+            query.match_resource(
+                SearchResourceType::App,
+                &app.id,
+                a.name.as_deref(),
+                [
+                    Field::text("name", a.name.as_deref()),
+                    Field::text("bundleId", a.bundle_id.as_deref()),
+                    Field::text("sku", a.sku.as_deref()),
+                ],
+            )
+        }));
+        hits.extend(bundle_ids?.iter().filter_map(|bundle_id| {
+            let a = &bundle_id.attributes;
+            query.match_resource(
+                SearchResourceType::BundleId,
+                &bundle_id.id,
+                a.name.as_deref(),
+                [
+                    Field::text("name", a.name.as_deref()),
+                    Field::text("identifier", a.identifier.as_deref()),
+                ],
+            )
+        }));
+        hits.extend(profiles?.iter().filter_map(|profile| {
+            let a = &profile.attributes;
+            query.match_resource(
+                SearchResourceType::Profile,
+                &profile.id,
+                a.name.as_deref(),
+                [
+                    Field::text("name", a.name.as_deref()),
+                    Field::identifier("uuid", a.uuid.as_deref()),
+                ],
+            )
+        }));
+        hits.extend(certificates?.iter().filter_map(|certificate| {
+            let a = &certificate.attributes;
+            query.match_resource(
+                SearchResourceType::Certificate,
+                &certificate.id,
+                a.display_name.as_deref().or(a.name.as_deref()),
+                [
+                    Field::text("name", a.name.as_deref()),
+                    Field::text("displayName", a.display_name.as_deref()),
+                    Field::identifier("serialNumber", a.serial_number.as_deref()),
+                ],
+            )
+        }));
+        hits.extend(devices?.iter().filter_map(|device| {
+            let a = &device.attributes;
+            query.match_resource(
+                SearchResourceType::Device,
+                &device.id,
+                a.name.as_deref(),
+                [
+                    Field::text("name", a.name.as_deref()),
+                    Field::identifier("udid", a.udid.as_deref()),
+                    Field::text("model", a.model.as_deref()),
+                ],
+            )
+        }));
+
+        hits.sort_by(|a, b| {
+            b.exact
+                .cmp(&a.exact)
+                .then(a.resource_type.cmp(&b.resource_type))
+                .then(a.name.cmp(&b.name))
+        });
+
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn search_resources() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps",
+                200,
+                fixtures::collection(vec![fixtures::app("A1", "com.example.app")]),
+            )
+            .json(
+                "GET",
+                "/v1/bundleIds",
+                200,
+                fixtures::collection(vec![fixtures::bundle_id("B1", "com.example.app")]),
+            )
+            .json("GET", "/v1/profiles", 200, fixtures::collection(vec![]))
+            .json("GET", "/v1/certificates", 200, fixtures::collection(vec![]))
+            .json(
+                "GET",
+                "/v1/devices",
+                200,
+                fixtures::collection(vec![
+                    fixtures::device("D1", "00008120-001A2B3C4D5E6F70"),
+                    fixtures::device("D2", "00008120-00FFFFFFFFFFFFFF"),
+                ]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let hits = client.search("com.example")?;
+        assert_eq!(
+            hits.iter()
+                .map(|h| (h.resource_type, h.id.as_str(), h.exact))
+                .collect::<Vec<_>>(),
+            vec![
+                (SearchResourceType::App, "A1", false),
+                (SearchResourceType::BundleId, "B1", false),
+            ]
+        );
+
+        // Separators are ignored in UDIDs.
+        let hits = client.search("00008120001a2b3c4d5e6f70")?;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, "D1");
+        assert_eq!(hits[0].field, "udid");
+        assert!(hits[0].exact);
+
+        assert!(client.search("  ").is_err());
+
+        Ok(())
+    }
+}
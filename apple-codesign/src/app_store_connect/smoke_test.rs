@@ -0,0 +1,238 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Smoke tests of API keys.
+//!
+//! After an API key is rotated, the new key should be verified before it is
+//! wired into pipelines. [SmokeTestReport::run()] makes one representative
+//! read-only request per area of the API this crate was built with (see
+//! [SMOKE_TESTS]) and records whether each passed.
+//!
+//! Smoke tests are time boxed by the deadline of the client (see
+//! [AppStoreConnectClient::set_deadline()]). Areas not tested before the
+//! deadline are reported as skipped.
+//!
+//! Unlike [AppStoreConnectClient::probe_permissions()], a failure doesn't stop
+//! the remaining tests and every failure is reported, including forbidden
+//! requests.
+
+use {
+    crate::{
+        app_store_connect::{notary_api::NotaryApiClient, AppStoreConnectClient},
+        AppleCodesignError,
+    },
+    log::{error, warn},
+    serde::Serialize,
+    serde_json::Value,
+    std::time::Instant,
+};
+
+/// A read-only request exercising an area of the API.
+#[derive(Clone, Copy)]
+pub struct SmokeTest {
+    /// Name of the area.
+    pub area: &'static str,
+
+    /// Human readable description of the request.
+    pub description: &'static str,
+
+    /// Performs the request.
+    pub run: fn(&NotaryApiClient) -> Result<(), AppleCodesignError>,
+}
+
+/// Request the first resource of a collection.
+fn get_first(client: &AppStoreConnectClient, path: &str) -> Result<(), AppleCodesignError> {
+    client.api_get::<Value>(path, &[("limit", "1".to_string())])?;
+
+    Ok(())
+}
+
+/// Smoke tests of the areas of the API enabled in this build.
+pub const SMOKE_TESTS: &[SmokeTest] = &[
+    SmokeTest {
+        area: "apps",
+        description: "list apps",
+        run: |client| get_first(client, "/v1/apps"),
+    },
+    #[cfg(feature = "asc-builds")]
+    SmokeTest {
+        area: "builds",
+        description: "list builds",
+        run: |client| get_first(client, "/v1/builds"),
+    },
+    #[cfg(feature = "asc-provisioning")]
+    SmokeTest {
+        area: "provisioning",
+        description: "list bundle IDs",
+        run: |client| get_first(client, "/v1/bundleIds"),
+    },
+    #[cfg(feature = "asc-testflight")]
+    SmokeTest {
+        area: "testflight",
+        description: "list beta groups",
+        run: |client| get_first(client, "/v1/betaGroups"),
+    },
+    SmokeTest {
+        area: "notary",
+        description: "list notarization submissions",
+        run: |client| client.list_submissions().map(|_| ()),
+    },
+];
+
+/// The outcome of the smoke test of an area.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SmokeTestOutcome {
+    Passed,
+    Failed,
+
+    /// Not run because the deadline passed.
+    Skipped,
+}
+
+/// The result of the smoke test of an area.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeTestResult {
+    /// See [SmokeTest::area].
+    pub area: &'static str,
+
+    /// See [SmokeTest::description].
+    pub description: &'static str,
+
+    pub outcome: SmokeTestOutcome,
+
+    /// How long the request took.
+    pub duration_ms: u64,
+
+    /// Why the test failed.
+    pub error: Option<String>,
+}
+
+/// Results of smoke tests.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SmokeTestReport {
+    pub results: Vec<SmokeTestResult>,
+}
+
+impl SmokeTestReport {
+    /// Run smoke tests in order, e.g. [SMOKE_TESTS].
+    pub fn run(client: &NotaryApiClient, tests: &[SmokeTest]) -> Self {
+        let mut report = Self::default();
+
+        for test in tests {
+            let start = Instant::now();
+
+            let (outcome, error) = if client.deadline().is_some_and(|d| start >= d) {
+                warn!("{}: skipped; deadline exceeded", test.area);
+                (SmokeTestOutcome::Skipped, None)
+            } else {
+                warn!("{}: {}", test.area, test.description);
+                match (test.run)(client) {
+                    Ok(()) => (SmokeTestOutcome::Passed, None),
+                    Err(e) => {
+                        error!("{}: {}", test.area, e);
+                        (SmokeTestOutcome::Failed, Some(e.to_string()))
+                    }
+                }
+            };
+
+            report.results.push(SmokeTestResult {
+                area: test.area,
+                description: test.description,
+                outcome,
+                duration_ms: start.elapsed().as_millis() as u64,
+                error,
+            });
+        }
+
+        report
+    }
+
+    /// Number of areas that didn't pass.
+    pub fn failures(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.outcome != SmokeTestOutcome::Passed)
+            .count()
+    }
+
+    /// Render the results as lines of text, one per area.
+    pub fn to_text_lines(&self) -> Vec<String> {
+        self.results
+            .iter()
+            .map(|r| {
+                let outcome = match r.outcome {
+                    SmokeTestOutcome::Passed => "pass",
+                    SmokeTestOutcome::Failed => "FAIL",
+                    SmokeTestOutcome::Skipped => "skip",
+                };
+
+                let mut line = format!(
+                    "{} {} ({}; {} ms)",
+                    outcome, r.area, r.description, r.duration_ms
+                );
+                if let Some(error) = &r.error {
+                    line.push_str(&format!(": {}", error));
+                }
+
+                line
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn run() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![]))
+            .json(
+                "GET",
+                "/v1/builds",
+                403,
+                fixtures::error(403, "FORBIDDEN_ERROR", "not allowed"),
+            )
+            .start()?;
+        let client = NotaryApiClient::from(server.client()?);
+
+        let tests = [
+            SmokeTest {
+                area: "apps",
+                description: "list apps",
+                run: |client| get_first(client, "/v1/apps"),
+            },
+            SmokeTest {
+                area: "builds",
+                description: "list builds",
+                run: |client| get_first(client, "/v1/builds"),
+            },
+        ];
+
+        let report = SmokeTestReport::run(&client, &tests);
+        assert_eq!(report.results[0].outcome, SmokeTestOutcome::Passed);
+        assert_eq!(report.results[1].outcome, SmokeTestOutcome::Failed);
+        assert!(report.results[1].error.is_some());
+        assert_eq!(report.failures(), 1);
+        assert!(report.to_text_lines()[1].starts_with("FAIL builds"));
+
+        // Nothing is requested past the deadline.
+        let mut client = server.client()?;
+        client.set_deadline(Some(Instant::now()));
+        let report = SmokeTestReport::run(&NotaryApiClient::from(client), &tests);
+        assert!(report
+            .results
+            .iter()
+            .all(|r| r.outcome == SmokeTestOutcome::Skipped));
+        assert_eq!(server.requests().len(), 2);
+
+        Ok(())
+    }
+}
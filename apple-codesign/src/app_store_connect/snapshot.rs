@@ -0,0 +1,328 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Offline access to an exported account.
+//!
+//! An [AccountSnapshot] holds the resources of the collections of an account
+//! (see [ExportResource]) at one point in time. It is written as JSON by
+//! [AppStoreConnectClient::export_snapshot()]:
+//!
+//! ```json
+//! {
+//!   "format": "asconnect-account-snapshot",
+//!   "version": 1,
+//!   "exportedAt": "2026-10-16T09:30:00Z",
+//!   "resources": {
+//!     "certificates": [{"type": "certificates", "id": "...", "attributes": {...}}]
+//!   }
+//! }
+//! ```
+//!
+//! [AppStoreConnectClient::offline()] constructs a client answering `GET`
+//! requests from a snapshot rather than the server. Read-only operations such
+//! as listing certificates or looking up devices and profiles then work
+//! without credentials or network access, e.g. to review the signing
+//! inventory of an account on an air-gapped machine.
+//!
+//! Collections (`/v1/certificates`) and single resources
+//! (`/v1/certificates/{id}`) are answered. `filter[...]` query parameters
+//! are applied to the `id`, attributes, and relationship linkage of
+//! resources. Other query parameters are ignored. Anything else, including
+//! related resource paths and requests modifying resources, fails with
+//! [AppleCodesignError::AppStoreConnectOffline].
+
+use {
+    crate::{
+        app_store_connect::{
+            api_token::ConnectTokenEncoder, api_versions::resource_type, export::ExportResource,
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::Utc,
+    serde::{Deserialize, Serialize},
+    serde_json::{json, Value},
+    std::{collections::BTreeMap, path::Path, sync::Arc},
+};
+
+/// Value of the `format` field of [AccountSnapshot].
+pub const ACCOUNT_SNAPSHOT_FORMAT: &str = "asconnect-account-snapshot";
+
+/// Resources of an account at one point in time.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSnapshot {
+    pub format: String,
+    pub version: u32,
+
+    /// RFC 3339 time the snapshot was exported.
+    pub exported_at: String,
+
+    /// Resource objects by resource type.
+    pub resources: BTreeMap<String, Vec<Value>>,
+}
+
+impl Default for AccountSnapshot {
+    fn default() -> Self {
+        Self {
+            format: ACCOUNT_SNAPSHOT_FORMAT.to_string(),
+            version: 1,
+            exported_at: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            resources: BTreeMap::new(),
+        }
+    }
+}
+
+/// The string a filter value is compared with.
+fn filter_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(_) | Value::Number(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a resource matches a `filter[field]` query parameter.
+///
+/// Returns [None] if the resource has no such field.
+fn filter_matches(resource: &Value, field: &str, values: &[&str]) -> Option<bool> {
+    let candidates = if field == "id" {
+        vec![resource["id"].clone()]
+    } else if let Some(value) = resource["attributes"].get(field) {
+        match value {
+            Value::Array(items) => items.clone(),
+            value => vec![value.clone()],
+        }
+    } else {
+        match &resource["relationships"].get(field)?["data"] {
+            Value::Array(ids) => ids.iter().map(|id| id["id"].clone()).collect(),
+            Value::Object(id) => vec![id.get("id").cloned().unwrap_or_default()],
+            _ => return None,
+        }
+    };
+
+    Some(
+        candidates
+            .iter()
+            .filter_map(filter_string)
+            .any(|candidate| values.contains(&candidate.as_str())),
+    )
+}
+
+impl AccountSnapshot {
+    /// Parse a snapshot from JSON.
+    pub fn from_json(data: &[u8]) -> Result<Self, AppleCodesignError> {
+        let snapshot = serde_json::from_slice::<Self>(data)?;
+
+        if snapshot.format != ACCOUNT_SNAPSHOT_FORMAT || snapshot.version != 1 {
+            return Err(AppleCodesignError::AccountSnapshot(format!(
+                "unsupported format {} version {}",
+                snapshot.format, snapshot.version
+            )));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Read a snapshot from a JSON file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        Self::from_json(&std::fs::read(path)?)
+    }
+
+    /// Write the snapshot to a JSON file.
+    pub fn write_path(&self, path: &Path) -> Result<(), AppleCodesignError> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Answer a `GET` request for a path and query.
+    ///
+    /// Returns the response document, or [None] if the snapshot can't answer
+    /// the request.
+    pub fn answer(&self, path: &str, query: &[(String, String)]) -> Option<Value> {
+        let r#type = resource_type(path)?;
+        let resources = self.resources.get(r#type)?;
+
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+        match segments.as_slice() {
+            [_, _] => {
+                let mut filters = vec![];
+                for (name, value) in query {
+                    if let Some(field) = name
+                        .strip_prefix("filter[")
+                        .and_then(|s| s.strip_suffix(']'))
+                    {
+                        filters.push((field, value.split(',').collect::<Vec<_>>()));
+                    }
+                }
+
+                let mut data = vec![];
+                for resource in resources {
+                    let mut matches = true;
+                    for (field, values) in &filters {
+                        match filter_matches(resource, field, values) {
+                            Some(m) => matches &= m,
+                            // Attributes without a value are omitted, so a
+                            // field is only unknown if no resource has it.
+                            None if resources
+                                .iter()
+                                .any(|r| filter_matches(r, field, values).is_some()) =>
+                            {
+                                matches = false
+                            }
+                            None => return None,
+                        }
+                    }
+
+                    if matches {
+                        data.push(resource.clone());
+                    }
+                }
+
+                Some(json!({ "data": data, "links": { "self": path } }))
+            }
+            [_, _, id] => resources
+                .iter()
+                .find(|r| r["id"] == *id)
+                .map(|r| json!({ "data": r, "links": { "self": path } })),
+            _ => None,
+        }
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Construct a client answering requests from a snapshot.
+    ///
+    /// The client needs no API Key and never contacts the server. It is
+    /// read-only.
+    pub fn offline(snapshot: AccountSnapshot) -> Result<Self, AppleCodesignError> {
+        // Tokens are never minted, but the client requires an encoder.
+        let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .map_err(|_| {
+            AppleCodesignError::AppStoreConnectApiKey("error generating ECDSA key".into())
+        })?;
+
+        let mut client = Self::new(ConnectTokenEncoder::individual_from_ecdsa_der(
+            "OFFLINE".into(),
+            key.as_ref(),
+        )?)?;
+        client.snapshot = Some(Arc::new(snapshot));
+        client.set_read_only(true);
+
+        Ok(client)
+    }
+
+    /// The snapshot requests are answered from, if the client is offline.
+    pub fn snapshot(&self) -> Option<&AccountSnapshot> {
+        self.snapshot.as_deref()
+    }
+
+    /// Export the collections of the account to a snapshot.
+    pub fn export_snapshot(
+        &self,
+        resources: &[ExportResource],
+    ) -> Result<AccountSnapshot, AppleCodesignError> {
+        let mut snapshot = AccountSnapshot::default();
+
+        for resource in resources {
+            let mut values = vec![];
+            self.api_get_pages::<Value>(
+                resource.api_path(),
+                &[("limit", "200".to_string())],
+                None,
+                |page, _| {
+                    for r in page {
+                        values.push(serde_json::to_value(r)?);
+                    }
+
+                    Ok(())
+                },
+            )?;
+
+            snapshot.resources.insert(
+                resource_type(resource.api_path())
+                    .expect("export paths should have a resource type")
+                    .to_string(),
+                values,
+            );
+        }
+
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "asc-provisioning")]
+    #[test]
+    fn offline() -> Result<(), AppleCodesignError> {
+        use crate::app_store_connect::{
+            certs_api::CertificateType,
+            testing::{fixtures, MockServerBuilder},
+        };
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/certificates",
+                200,
+                fixtures::collection(vec![
+                    fixtures::certificate("C1", "DEVELOPMENT", ""),
+                    fixtures::certificate("C2", "DISTRIBUTION", ""),
+                ]),
+            )
+            .start()?;
+
+        let snapshot = server
+            .client()?
+            .export_snapshot(&[ExportResource::Certificates])?;
+        let snapshot = AccountSnapshot::from_json(&serde_json::to_vec(&snapshot)?)?;
+        assert_eq!(snapshot.resources["certificates"].len(), 2);
+
+        let client = AppStoreConnectClient::offline(snapshot)?;
+
+        let certificates = client.list_certificates()?;
+        assert_eq!(certificates.len(), 2);
+        assert_eq!(
+            certificates[1].attributes.certificate_type,
+            Some(CertificateType::Distribution)
+        );
+
+        let document = client.snapshot().unwrap().answer(
+            "/v1/certificates",
+            &[("filter[certificateType]".into(), "DISTRIBUTION".into())],
+        );
+        assert_eq!(document.unwrap()["data"][0]["id"], "C2");
+
+        let document = client
+            .snapshot()
+            .unwrap()
+            .answer("/v1/certificates/C1", &[]);
+        assert_eq!(document.unwrap()["data"]["id"], "C1");
+
+        // Unknown resources and fields can't be answered.
+        assert!(client.snapshot().unwrap().answer("/v1/apps", &[]).is_none());
+        assert!(client
+            .snapshot()
+            .unwrap()
+            .answer("/v1/certificates", &[("filter[app]".into(), "1".into())])
+            .is_none());
+        assert!(matches!(
+            client.list_profiles(),
+            Err(AppleCodesignError::AppStoreConnectOffline(_))
+        ));
+
+        // Only the export made requests.
+        assert_eq!(server.requests().len(), 1);
+
+        Ok(())
+    }
+}
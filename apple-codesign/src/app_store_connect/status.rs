@@ -0,0 +1,349 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Account-wide health summary.
+//!
+//! [AccountStatus] aggregates information spanning several APIs into a single
+//! report: signing certificates nearing expiration, invalid provisioning
+//! profiles, builds stuck in processing, review submissions awaiting a decision,
+//! and recent notarization failures. It is intended to be generated
+//! periodically (e.g. from a daily cron job) to catch problems before they
+//! block a release.
+
+use {
+    crate::{
+        app_store_connect::{
+            build_api::BuildProcessingState,
+            certs_api::{CertificateType, ProfileState, ProfileType},
+            json_api::parse_api_date,
+            notary_api::{NotaryApiClient, SubmissionResponseStatus},
+            review_api::ReviewSubmissionState,
+        },
+        AppleCodesignError,
+    },
+    chrono::{DateTime, Duration, Utc},
+    log::warn,
+    serde::Serialize,
+};
+
+/// Thresholds controlling what [AccountStatus] reports.
+#[derive(Clone, Debug)]
+pub struct StatusOptions {
+    /// Report certificates expiring within this many days.
+    pub certificate_expiry_days: i64,
+
+    /// Report builds that have been processing for longer than this many hours.
+    pub stuck_build_hours: i64,
+
+    /// Report notarization failures within this many days.
+    pub notarization_days: i64,
+}
+
+impl Default for StatusOptions {
+    fn default() -> Self {
+        Self {
+            certificate_expiry_days: 30,
+            stuck_build_hours: 2,
+            notarization_days: 7,
+        }
+    }
+}
+
+/// A certificate nearing expiration.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiringCertificate {
+    pub id: String,
+    pub name: Option<String>,
+    pub certificate_type: Option<CertificateType>,
+    pub expiration_date: String,
+    pub days_remaining: i64,
+}
+
+/// A provisioning profile that is no longer valid.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidProfile {
+    pub id: String,
+    pub name: Option<String>,
+    pub profile_type: Option<ProfileType>,
+    pub profile_state: Option<ProfileState>,
+}
+
+/// A build that has been processing for an unusually long time.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StuckBuild {
+    pub id: String,
+    pub app_id: Option<String>,
+    pub bundle_id: Option<String>,
+    pub version: Option<String>,
+    pub uploaded_date: Option<String>,
+    pub hours_processing: i64,
+}
+
+/// A review submission awaiting a decision.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingReviewSubmission {
+    pub app_id: String,
+    pub id: String,
+    pub state: Option<ReviewSubmissionState>,
+    pub submitted_date: Option<String>,
+}
+
+/// A recent notarization submission that didn't succeed.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotarizationFailure {
+    pub id: String,
+    pub name: String,
+    pub created_date: String,
+    pub status: String,
+}
+
+/// An account-wide health summary.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountStatus {
+    pub expiring_certificates: Vec<ExpiringCertificate>,
+    pub invalid_profiles: Vec<InvalidProfile>,
+    pub stuck_builds: Vec<StuckBuild>,
+    pub pending_review_submissions: Vec<PendingReviewSubmission>,
+    pub notarization_failures: Vec<NotarizationFailure>,
+}
+
+impl AccountStatus {
+    /// Gather the status of the account the client is authenticated against.
+    pub fn gather(
+        client: &NotaryApiClient,
+        options: &StatusOptions,
+    ) -> Result<Self, AppleCodesignError> {
+        let now = Utc::now();
+        let mut status = Self::default();
+
+        warn!("checking certificates");
+        for cert in client.list_certificates()? {
+            let expiration = match cert.attributes.expiration_date.clone() {
+                Some(v) => v,
+                None => continue,
+            };
+            let expires = match parse_api_date(&expiration) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let days_remaining = (expires - now).num_days();
+
+            if expires - now <= Duration::days(options.certificate_expiry_days) {
+                status.expiring_certificates.push(ExpiringCertificate {
+                    id: cert.id,
+                    name: cert.attributes.name,
+                    certificate_type: cert.attributes.certificate_type,
+                    expiration_date: expiration,
+                    days_remaining,
+                });
+            }
+        }
+        status
+            .expiring_certificates
+            .sort_by_key(|c| c.days_remaining);
+
+        warn!("checking provisioning profiles");
+        for profile in client.list_profiles()? {
+            if profile.attributes.profile_state != Some(ProfileState::Active) {
+                status.invalid_profiles.push(InvalidProfile {
+                    id: profile.id,
+                    name: profile.attributes.name,
+                    profile_type: profile.attributes.profile_type,
+                    profile_state: profile.attributes.profile_state,
+                });
+            }
+        }
+
+        warn!("checking builds in processing");
+        for (build, app) in client.list_builds_in_state(BuildProcessingState::Processing)? {
+            let uploaded = build
+                .attributes
+                .uploaded_date
+                .as_deref()
+                .and_then(parse_api_date);
+
+            let hours_processing = uploaded.map(|d| (now - d).num_hours()).unwrap_or_default();
+
+            if hours_processing >= options.stuck_build_hours {
+                status.stuck_builds.push(StuckBuild {
+                    id: build.id,
+                    app_id: app.as_ref().map(|app| app.id.clone()),
+                    bundle_id: app.and_then(|app| app.attributes.bundle_id),
+                    version: build.attributes.version,
+                    uploaded_date: build.attributes.uploaded_date,
+                    hours_processing,
+                });
+            }
+        }
+
+        warn!("checking review submissions");
+        for app in client.list_apps()? {
+            for submission in client.list_review_submissions(&app.id)? {
+                if submission
+                    .attributes
+                    .state
+                    .map(|s| s.is_pending())
+                    .unwrap_or_default()
+                {
+                    status
+                        .pending_review_submissions
+                        .push(PendingReviewSubmission {
+                            app_id: app.id.clone(),
+                            id: submission.id,
+                            state: submission.attributes.state,
+                            submitted_date: submission.attributes.submitted_date,
+                        });
+                }
+            }
+        }
+
+        warn!("checking notarization submissions");
+        let cutoff: DateTime<Utc> = now - Duration::days(options.notarization_days);
+        for submission in client.list_submissions()?.data {
+            let attributes = submission.attributes;
+
+            if !matches!(
+                attributes.status,
+                SubmissionResponseStatus::Invalid | SubmissionResponseStatus::Rejected
+            ) {
+                continue;
+            }
+
+            if parse_api_date(&attributes.created_date)
+                .map(|d| d >= cutoff)
+                .unwrap_or(true)
+            {
+                status.notarization_failures.push(NotarizationFailure {
+                    id: submission.id,
+                    name: attributes.name,
+                    created_date: attributes.created_date,
+                    status: format!("{:?}", attributes.status),
+                });
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Emit the status as metrics.
+    #[cfg(feature = "metrics")]
+    pub fn record_metrics(
+        &self,
+        recorder: &dyn crate::app_store_connect::metrics::MetricsRecorder,
+    ) {
+        for c in &self.expiring_certificates {
+            recorder.set_gauge(
+                "app_store_connect_certificate_expiry_days",
+                &[
+                    ("id", c.id.as_str()),
+                    ("name", c.name.as_deref().unwrap_or_default()),
+                ],
+                c.days_remaining as f64,
+            );
+        }
+
+        recorder.set_gauge(
+            "app_store_connect_invalid_profiles",
+            &[],
+            self.invalid_profiles.len() as f64,
+        );
+        recorder.set_gauge(
+            "app_store_connect_stuck_builds",
+            &[],
+            self.stuck_builds.len() as f64,
+        );
+        recorder.set_gauge(
+            "app_store_connect_pending_review_submissions",
+            &[],
+            self.pending_review_submissions.len() as f64,
+        );
+        recorder.set_gauge(
+            "app_store_connect_notarization_failures",
+            &[],
+            self.notarization_failures.len() as f64,
+        );
+    }
+
+    /// Whether the status contains nothing requiring attention.
+    pub fn is_healthy(&self) -> bool {
+        self.expiring_certificates.is_empty()
+            && self.invalid_profiles.is_empty()
+            && self.stuck_builds.is_empty()
+            && self.notarization_failures.is_empty()
+    }
+
+    /// Render the status as human readable lines of text.
+    pub fn to_text_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        lines.push(format!(
+            "certificates expiring soon: {}",
+            self.expiring_certificates.len()
+        ));
+        for c in &self.expiring_certificates {
+            lines.push(format!(
+                "  {} ({}) expires {} ({} days)",
+                c.name.as_deref().unwrap_or_default(),
+                c.id,
+                c.expiration_date,
+                c.days_remaining
+            ));
+        }
+
+        lines.push(format!("invalid profiles: {}", self.invalid_profiles.len()));
+        for p in &self.invalid_profiles {
+            lines.push(format!(
+                "  {} ({}) {:?}",
+                p.name.as_deref().unwrap_or_default(),
+                p.id,
+                p.profile_state
+            ));
+        }
+
+        lines.push(format!(
+            "builds stuck in processing: {}",
+            self.stuck_builds.len()
+        ));
+        for b in &self.stuck_builds {
+            lines.push(format!(
+                "  build {} ({}) of {} processing for {} hours",
+                b.version.as_deref().unwrap_or_default(),
+                b.id,
+                b.bundle_id.as_deref().unwrap_or("unknown app"),
+                b.hours_processing
+            ));
+        }
+
+        lines.push(format!(
+            "pending review submissions: {}",
+            self.pending_review_submissions.len()
+        ));
+        for s in &self.pending_review_submissions {
+            lines.push(format!(
+                "  app {} submission {} {:?}",
+                s.app_id, s.id, s.state
+            ));
+        }
+
+        lines.push(format!(
+            "recent notarization failures: {}",
+            self.notarization_failures.len()
+        ));
+        for n in &self.notarization_failures {
+            lines.push(format!(
+                "  {} ({}) {} on {}",
+                n.name, n.id, n.status, n.created_date
+            ));
+        }
+
+        lines
+    }
+}
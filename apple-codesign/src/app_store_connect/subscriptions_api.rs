@@ -0,0 +1,316 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Auto-renewable subscriptions and their price changes.
+//!
+//! Subscriptions belong to subscription groups of an app and have prices per
+//! territory. A price change is a price whose start date is in the future.
+//! Unless the new price is *preserved* for existing subscribers, they are
+//! charged the new price, and a price increase requires notifying them or
+//! obtaining their consent. [AppStoreConnectClient::list_subscription_price_changes()]
+//! reports scheduled changes with the [Consent] they likely need.
+//!
+//! The API doesn't expose Apple's decision on whether consent is required or
+//! how many subscribers consented. [Consent] is estimated from Apple's
+//! criteria for increases without consent, of which only the limit of 50% of
+//! the current price is checked. Limits on the absolute amount and frequency
+//! of increases and laws of individual territories aren't, so
+//! [Consent::NotificationOnly] may still turn out to require consent.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/subscription_prices>
+//! and <https://developer.apple.com/help/app-store-connect/manage-subscriptions/manage-pricing-for-auto-renewable-subscriptions>.
+
+pub use asconnect_types::subscriptions::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            json_api::included_resources, pricing_api::parse_price, AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::Utc,
+    serde::Serialize,
+    std::collections::BTreeMap,
+};
+
+/// Largest increase in percent of the current price not requiring consent.
+const MAX_INCREASE_WITHOUT_CONSENT: f64 = 50.0;
+
+/// What a price change requires from existing subscribers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Consent {
+    /// Existing subscribers aren't charged more.
+    NotRequired,
+    /// Existing subscribers are notified of the increase.
+    NotificationOnly,
+    /// Existing subscribers must agree to the increase or their
+    /// subscription expires.
+    Required,
+}
+
+impl Consent {
+    /// Estimate what a price change requires from existing subscribers.
+    ///
+    /// `before` is the current price, if there is one.
+    pub fn estimate(before: Option<f64>, after: f64, preserved: bool) -> Self {
+        match before {
+            Some(before) if !preserved && after > before => {
+                if (after - before) / before * 100.0 > MAX_INCREASE_WITHOUT_CONSENT {
+                    Self::Required
+                } else {
+                    Self::NotificationOnly
+                }
+            }
+            _ => Self::NotRequired,
+        }
+    }
+}
+
+/// A scheduled change of the price of a subscription in a territory.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionPriceChange {
+    pub subscription_id: String,
+    pub product_id: Option<String>,
+    pub territory: String,
+
+    /// Day the new price takes effect, formatted as `YYYY-MM-DD`.
+    pub start_date: String,
+
+    /// The customer price before the change, if there is one.
+    pub before: Option<String>,
+
+    /// The customer price after the change.
+    pub after: String,
+
+    /// Whether existing subscribers keep paying the price before the change.
+    pub preserved: bool,
+
+    pub consent: Consent,
+}
+
+/// A price of a subscription, resolved to its territory and customer price.
+struct TerritoryPrice {
+    start_date: Option<String>,
+    preserved: bool,
+    customer_price: String,
+}
+
+impl AppStoreConnectClient {
+    /// List the subscription groups of an app.
+    pub fn list_subscription_groups(
+        &self,
+        app_id: &str,
+    ) -> Result<Vec<SubscriptionGroup>, AppleCodesignError> {
+        self.api_get_all(&format!("/v1/apps/{}/subscriptionGroups", app_id), &[])
+    }
+
+    /// List the subscriptions of a subscription group.
+    pub fn list_subscriptions(
+        &self,
+        group_id: &str,
+    ) -> Result<Vec<Subscription>, AppleCodesignError> {
+        self.api_get_all(
+            &format!("/v1/subscriptionGroups/{}/subscriptions", group_id),
+            &[],
+        )
+    }
+
+    /// List the prices of a subscription by territory, ordered by start date.
+    fn list_subscription_territory_prices(
+        &self,
+        subscription_id: &str,
+    ) -> Result<BTreeMap<String, Vec<TerritoryPrice>>, AppleCodesignError> {
+        let (prices, included) = self.api_get_all_with_included::<SubscriptionPriceAttributes>(
+            &format!("/v1/subscriptions/{}/prices", subscription_id),
+            &[
+                ("include", "subscriptionPricePoint,territory".to_string()),
+                ("limit", "200".to_string()),
+            ],
+        )?;
+        let points = included_resources::<SubscriptionPricePointAttributes>(
+            &included,
+            "subscriptionPricePoints",
+        )?;
+
+        let mut territories = BTreeMap::<String, Vec<TerritoryPrice>>::new();
+
+        for price in prices {
+            let (territory, customer_price) = match (
+                price.related("territory").first(),
+                price
+                    .related("subscriptionPricePoint")
+                    .first()
+                    .and_then(|point| points.get(&point.id))
+                    .and_then(|point| point.attributes.customer_price.clone()),
+            ) {
+                (Some(territory), Some(customer_price)) => (territory.id.clone(), customer_price),
+                _ => continue,
+            };
+
+            territories
+                .entry(territory)
+                .or_default()
+                .push(TerritoryPrice {
+                    start_date: price.attributes.start_date,
+                    preserved: price.attributes.preserved.unwrap_or_default(),
+                    customer_price,
+                });
+        }
+
+        // Prices without a start date sort first.
+        for prices in territories.values_mut() {
+            prices.sort_by(|a, b| a.start_date.cmp(&b.start_date));
+        }
+
+        Ok(territories)
+    }
+
+    /// List the scheduled price changes of the subscriptions of an app.
+    ///
+    /// See the [module documentation](self) for how [Consent] is estimated.
+    pub fn list_subscription_price_changes(
+        &self,
+        app_id: &str,
+    ) -> Result<Vec<SubscriptionPriceChange>, AppleCodesignError> {
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let mut changes = vec![];
+
+        for group in self.list_subscription_groups(app_id)? {
+            for subscription in
+                self.fetch_related::<SubscriptionAttributes, _>(&group, "subscriptions")?
+            {
+                let territories = self.list_subscription_territory_prices(&subscription.id)?;
+
+                for (territory, prices) in territories {
+                    let mut before: Option<&TerritoryPrice> = None;
+
+                    for price in &prices {
+                        if let Some(start_date) =
+                            price.start_date.as_deref().filter(|d| *d > today.as_str())
+                        {
+                            let after = parse_price(&price.customer_price)?;
+
+                            changes.push(SubscriptionPriceChange {
+                                subscription_id: subscription.id.clone(),
+                                product_id: subscription.attributes.product_id.clone(),
+                                territory: territory.clone(),
+                                start_date: start_date.to_string(),
+                                before: before.map(|p| p.customer_price.clone()),
+                                after: price.customer_price.clone(),
+                                preserved: price.preserved,
+                                consent: Consent::estimate(
+                                    before.map(|p| parse_price(&p.customer_price)).transpose()?,
+                                    after,
+                                    price.preserved,
+                                ),
+                            });
+                        }
+
+                        before = Some(price);
+                    }
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::{json, Value},
+    };
+
+    fn price(id: &str, territory: &str, start_date: Option<&str>, preserved: bool) -> Value {
+        let mut price = fixtures::resource(
+            "subscriptionPrices",
+            id,
+            json!({"startDate": start_date, "preserved": preserved}),
+        );
+        price["relationships"] = json!({
+            "subscriptionPricePoint": {"data": {"type": "subscriptionPricePoints", "id": id}},
+            "territory": {"data": {"type": "territories", "id": territory}},
+        });
+
+        price
+    }
+
+    fn price_point(id: &str, customer_price: &str) -> Value {
+        fixtures::resource(
+            "subscriptionPricePoints",
+            id,
+            json!({"customerPrice": customer_price}),
+        )
+    }
+
+    #[test]
+    fn price_changes() -> Result<(), AppleCodesignError> {
+        let mut prices = fixtures::collection(vec![
+            price("P1", "USA", None, false),
+            price("P2", "USA", Some("2099-01-01"), false),
+            price("P3", "DEU", None, false),
+            price("P4", "DEU", Some("2099-01-01"), false),
+            price("P5", "FRA", Some("2099-01-01"), true),
+            price("P6", "FRA", None, false),
+        ]);
+        prices["included"] = json!([
+            price_point("P1", "9.99"),
+            price_point("P2", "12.99"),
+            price_point("P3", "9.99"),
+            price_point("P4", "19.99"),
+            price_point("P5", "19.99"),
+            price_point("P6", "9.99"),
+        ]);
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps/A1/subscriptionGroups",
+                200,
+                fixtures::collection(vec![fixtures::resource(
+                    "subscriptionGroups",
+                    "G1",
+                    json!({"referenceName": "Premium"}),
+                )]),
+            )
+            .json(
+                "GET",
+                "/v1/subscriptionGroups/G1/subscriptions",
+                200,
+                fixtures::collection(vec![fixtures::resource(
+                    "subscriptions",
+                    "S1",
+                    json!({"productId": "premium.monthly", "subscriptionPeriod": "ONE_MONTH"}),
+                )]),
+            )
+            .json("GET", "/v1/subscriptions/S1/prices", 200, prices)
+            .start()?;
+        let client = server.client()?;
+
+        let changes = client.list_subscription_price_changes("A1")?;
+        let consent = changes
+            .iter()
+            .map(|c| (c.territory.as_str(), c.before.as_deref(), c.consent))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            consent,
+            vec![
+                ("DEU", Some("9.99"), Consent::Required),
+                ("FRA", Some("9.99"), Consent::NotRequired),
+                ("USA", Some("9.99"), Consent::NotificationOnly),
+            ]
+        );
+        assert_eq!(changes[0].product_id.as_deref(), Some("premium.monthly"));
+        assert_eq!(changes[0].start_date, "2099-01-01");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! CSV export of resource listings.
+//!
+//! Resources implementing [Tabular] have a fixed set of columns. Columns are
+//! never reordered or removed, and new columns are only appended, so
+//! spreadsheets and scripts reading exports by position keep working across
+//! releases. Missing values are empty cells and enumerations are written as
+//! the API spells them (e.g. `IOS_DISTRIBUTION`).
+//!
+//! This module is available with the `cli` feature.
+
+use {
+    crate::{app_store_connect::app_api::App, AppleCodesignError},
+    serde::Serialize,
+    std::io::Write,
+};
+
+#[cfg(feature = "asc-builds")]
+use crate::app_store_connect::build_api::Build;
+#[cfg(feature = "asc-provisioning")]
+use crate::app_store_connect::certs_api::{Certificate, Device, Profile};
+#[cfg(feature = "asc-testflight")]
+use crate::app_store_connect::{review_api::ReviewRejection, testflight_api::BetaTester};
+
+/// A value that can be written as rows of a CSV file.
+pub trait Tabular {
+    /// Names of the columns, in output order.
+    fn columns() -> &'static [&'static str];
+
+    /// The rows representing the value. Most values are a single row.
+    fn rows(&self) -> Vec<Vec<String>>;
+}
+
+/// Write values as CSV with a header row.
+pub fn write_csv<T: Tabular>(writer: impl Write, values: &[T]) -> Result<(), AppleCodesignError> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    writer.write_record(T::columns())?;
+    for value in values {
+        for row in value.rows() {
+            writer.write_record(&row)?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn cell(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+/// Format an optional enumeration or scalar as the API spells it.
+fn cell_value<T: Serialize>(value: &Option<T>) -> String {
+    match value.as_ref().map(serde_json::to_value) {
+        Some(Ok(serde_json::Value::String(s))) => s,
+        Some(Ok(serde_json::Value::Null)) | None | Some(Err(_)) => String::new(),
+        Some(Ok(value)) => value.to_string(),
+    }
+}
+
+impl Tabular for App {
+    fn columns() -> &'static [&'static str] {
+        &["id", "name", "bundleId", "sku", "primaryLocale"]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let a = &self.attributes;
+
+        vec![vec![
+            self.id.clone(),
+            cell(&a.name),
+            cell(&a.bundle_id),
+            cell(&a.sku),
+            cell(&a.primary_locale),
+        ]]
+    }
+}
+
+#[cfg(feature = "asc-builds")]
+impl Tabular for Build {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "version",
+            "processingState",
+            "uploadedDate",
+            "expirationDate",
+            "expired",
+            "minOsVersion",
+            "usesNonExemptEncryption",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let a = &self.attributes;
+
+        vec![vec![
+            self.id.clone(),
+            cell(&a.version),
+            cell_value(&a.processing_state),
+            cell(&a.uploaded_date),
+            cell(&a.expiration_date),
+            cell_value(&a.expired),
+            cell(&a.min_os_version),
+            cell_value(&a.uses_non_exempt_encryption),
+        ]]
+    }
+}
+
+#[cfg(feature = "asc-provisioning")]
+impl Tabular for Certificate {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "displayName",
+            "certificateType",
+            "serialNumber",
+            "platform",
+            "expirationDate",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let a = &self.attributes;
+
+        vec![vec![
+            self.id.clone(),
+            cell(&a.name),
+            cell(&a.display_name),
+            cell_value(&a.certificate_type),
+            cell(&a.serial_number),
+            cell(&a.platform),
+            cell(&a.expiration_date),
+        ]]
+    }
+}
+
+/// A device and the marketing name of its model.
+///
+/// See [DeviceModels](super::device_models::DeviceModels) for marketing names.
+#[cfg(feature = "asc-provisioning")]
+pub struct DeviceRow<'a> {
+    pub device: &'a Device,
+    pub marketing_name: Option<&'a str>,
+}
+
+#[cfg(feature = "asc-provisioning")]
+impl<'a> Tabular for DeviceRow<'a> {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "udid",
+            "platform",
+            "status",
+            "deviceClass",
+            "model",
+            "marketingName",
+            "addedDate",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let a = &self.device.attributes;
+
+        vec![vec![
+            self.device.id.clone(),
+            cell(&a.name),
+            cell(&a.udid),
+            cell(&a.platform),
+            cell_value(&a.status),
+            cell(&a.device_class),
+            cell(&a.model),
+            self.marketing_name.unwrap_or_default().to_string(),
+            cell(&a.added_date),
+        ]]
+    }
+}
+
+#[cfg(feature = "asc-provisioning")]
+impl Tabular for Profile {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "profileType",
+            "profileState",
+            "platform",
+            "uuid",
+            "createdDate",
+            "expirationDate",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let a = &self.attributes;
+
+        vec![vec![
+            self.id.clone(),
+            cell(&a.name),
+            cell_value(&a.profile_type),
+            cell_value(&a.profile_state),
+            cell(&a.platform),
+            cell(&a.uuid),
+            cell(&a.created_date),
+            cell(&a.expiration_date),
+        ]]
+    }
+}
+
+#[cfg(feature = "asc-testflight")]
+impl Tabular for BetaTester {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "firstName",
+            "lastName",
+            "email",
+            "inviteType",
+            "state",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let a = &self.attributes;
+
+        vec![vec![
+            self.id.clone(),
+            cell(&a.first_name),
+            cell(&a.last_name),
+            cell(&a.email),
+            cell_value(&a.invite_type),
+            cell_value(&a.state),
+        ]]
+    }
+}
+
+/// A row per rejected item. Submissions without rejected items are a row
+/// with empty item columns.
+#[cfg(feature = "asc-testflight")]
+impl Tabular for ReviewRejection {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "appId",
+            "submissionId",
+            "platform",
+            "submittedDate",
+            "state",
+            "itemId",
+            "resourceType",
+            "resourceId",
+        ]
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let submission = vec![
+            self.app_id.clone(),
+            self.submission_id.clone(),
+            cell(&self.platform),
+            cell(&self.submitted_date),
+            cell_value(&self.state),
+        ];
+
+        if self.items.is_empty() {
+            let mut row = submission;
+            row.extend([String::new(), String::new(), String::new()]);
+            return vec![row];
+        }
+
+        self.items
+            .iter()
+            .map(|item| {
+                let mut row = submission.clone();
+                row.push(item.id.clone());
+                row.push(
+                    item.resource
+                        .as_ref()
+                        .map(|r| r.r#type.clone())
+                        .unwrap_or_default(),
+                );
+                row.push(
+                    item.resource
+                        .as_ref()
+                        .map(|r| r.id.clone())
+                        .unwrap_or_default(),
+                );
+                row
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::{json_api::Document, testing::fixtures},
+    };
+
+    #[test]
+    fn csv_columns() -> Result<(), AppleCodesignError> {
+        let mut app = serde_json::from_value::<Document<App>>(fixtures::document(fixtures::app(
+            "1",
+            "com.example.app",
+        )))?
+        .data;
+        app.attributes.name = Some("Example, \"Pro\"".into());
+
+        let mut out = vec![];
+        write_csv(&mut out, &[app])?;
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,name,bundleId,sku,primaryLocale\n\
+             1,\"Example, \"\"Pro\"\"\",com.example.app,EXAMPLE,en-US\n"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "asc-provisioning")]
+    #[test]
+    fn csv_enumerations() -> Result<(), AppleCodesignError> {
+        let certificate =
+            serde_json::from_value::<Certificate>(fixtures::certificate("C1", "DISTRIBUTION", ""))?;
+
+        let rows = certificate.rows();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].len(), Certificate::columns().len());
+        assert_eq!(rows[0][3], "DISTRIBUTION");
+
+        Ok(())
+    }
+}
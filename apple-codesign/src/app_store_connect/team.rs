@@ -0,0 +1,193 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Discovery of the team an API key belongs to.
+//!
+//! Entitlements and certificate subjects embed the 10 character team ID, but
+//! the App Store Connect API has no resource describing the team.
+//! [AppStoreConnectClient::team_info()] derives it from resources of the team:
+//!
+//! 1. The subject of a signing certificate, whose organizational unit is the
+//!    team ID and whose organization is the team name.
+//! 2. The seed ID (App ID prefix) of a bundle ID. Seed IDs equal the team ID,
+//!    except for some teams created before 2011.
+//!
+//! The team of an API key can't change, so the result is memoized for the
+//! lifetime of the client.
+
+use {
+    crate::{
+        app_store_connect::{json_api::Document, AppStoreConnectClient},
+        certificate::AppleCertificate,
+        AppleCodesignError,
+    },
+    asconnect_types::{bundles::BundleId, certs::Certificate},
+    bcder::Oid,
+    serde::Serialize,
+    x509_certificate::{rfc4519::OID_ORGANIZATION_NAME, CapturedX509Certificate},
+};
+
+/// How the team ID was discovered.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum TeamIdSource {
+    /// The subject of the certificate with this ID.
+    Certificate { id: String },
+
+    /// The seed ID of the bundle ID with this ID.
+    BundleId { id: String },
+}
+
+/// The team an API key belongs to.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamInfo {
+    /// The 10 character team ID. e.g. `ABCDE12345`.
+    pub team_id: String,
+
+    /// The name of the team, if it is known.
+    pub team_name: Option<String>,
+
+    pub source: TeamIdSource,
+}
+
+impl TeamInfo {
+    /// Derive the team from the subject of a signing certificate.
+    pub fn from_certificate(certificate: &Certificate) -> Result<Option<Self>, AppleCodesignError> {
+        let content = match certificate.attributes.certificate_content.as_deref() {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        let cert = CapturedX509Certificate::from_der(base64::decode(content)?)?;
+
+        Ok(cert.apple_team_id().map(|team_id| Self {
+            team_id,
+            team_name: cert
+                .subject_name()
+                .find_first_attribute_string(Oid(OID_ORGANIZATION_NAME.as_ref().into()))
+                .unwrap_or(None),
+            source: TeamIdSource::Certificate {
+                id: certificate.id.clone(),
+            },
+        }))
+    }
+
+    /// Derive the team from the seed ID of a bundle ID.
+    pub fn from_bundle_id(bundle_id: &BundleId) -> Option<Self> {
+        bundle_id.attributes.seed_id.as_ref().map(|seed_id| Self {
+            team_id: seed_id.clone(),
+            team_name: None,
+            source: TeamIdSource::BundleId {
+                id: bundle_id.id.clone(),
+            },
+        })
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Discover the team the API key belongs to.
+    ///
+    /// See the [module documentation](self) for how.
+    pub fn team_info(&self) -> Result<TeamInfo, AppleCodesignError> {
+        if let Some(team) = self.lookup_cache().team() {
+            return Ok(team);
+        }
+
+        let team = self.find_team_info()?.ok_or_else(|| {
+            AppleCodesignError::TeamIdUnknown(
+                "the team has no signing certificates or bundle IDs".into(),
+            )
+        })?;
+        self.lookup_cache().set_team(team.clone());
+
+        Ok(team)
+    }
+
+    /// The 10 character ID of the team the API key belongs to.
+    ///
+    /// See [Self::team_info()].
+    pub fn team_id(&self) -> Result<String, AppleCodesignError> {
+        Ok(self.team_info()?.team_id)
+    }
+
+    fn find_team_info(&self) -> Result<Option<TeamInfo>, AppleCodesignError> {
+        let certificates = self
+            .api_get::<Document<Vec<Certificate>>>("/v1/certificates", &[("limit", "10".into())])?
+            .data;
+        for certificate in &certificates {
+            if let Some(team) = TeamInfo::from_certificate(certificate)? {
+                return Ok(Some(team));
+            }
+        }
+
+        Ok(self
+            .api_get::<Document<Vec<BundleId>>>("/v1/bundleIds", &[("limit", "1".into())])?
+            .data
+            .iter()
+            .find_map(TeamInfo::from_bundle_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            app_store_connect::testing::{fixtures, MockServerBuilder},
+            certificate::{create_self_signed_code_signing_certificate, CertificateProfile},
+        },
+        x509_certificate::{EcdsaCurve, KeyAlgorithm},
+    };
+
+    #[test]
+    fn team_discovery() -> Result<(), AppleCodesignError> {
+        let (cert, _, _) = create_self_signed_code_signing_certificate(
+            KeyAlgorithm::Ecdsa(EcdsaCurve::Secp256r1),
+            CertificateProfile::AppleDistribution,
+            "ABCDE12345",
+            "Example Inc",
+            "US",
+            chrono::Duration::days(30),
+        )?;
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/certificates",
+                200,
+                fixtures::collection(vec![fixtures::certificate(
+                    "C1",
+                    "DISTRIBUTION",
+                    &base64::encode(cert.encode_der()?),
+                )]),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let team = client.team_info()?;
+        assert_eq!(team.team_id, "ABCDE12345");
+        assert_eq!(team.team_name.as_deref(), Some("Example Inc"));
+        assert_eq!(team.source, TeamIdSource::Certificate { id: "C1".into() });
+
+        // The team is memoized.
+        assert_eq!(client.team_id()?, "ABCDE12345");
+        assert_eq!(server.requests().len(), 1);
+
+        // Without certificates, the seed ID of a bundle ID is used.
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/certificates", 200, fixtures::collection(vec![]))
+            .json(
+                "GET",
+                "/v1/bundleIds",
+                200,
+                fixtures::collection(vec![fixtures::bundle_id("B1", "com.example.app")]),
+            )
+            .start()?;
+        let team = server.client()?.team_info()?;
+        assert_eq!(team.team_id, "ABCDE12345");
+        assert_eq!(team.source, TeamIdSource::BundleId { id: "B1".into() });
+
+        Ok(())
+    }
+}
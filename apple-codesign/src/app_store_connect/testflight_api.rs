@@ -0,0 +1,599 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! TestFlight beta testing.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/prerelease_versions_and_beta_testers>.
+
+pub use asconnect_types::testflight::*;
+
+use {
+    crate::{
+        app_store_connect::{
+            build_api::Build,
+            json_api::{
+                log_attribute_changes, BetaAppLocalizationQuery, BetaGroupQuery, BetaTesterQuery,
+                Document, RelationshipRequest, RequestDocument, ResourceIdentifier, Updated,
+            },
+            locales::{apply_locale_fallback, InheritedField, LocalizedResource},
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    log::{info, warn},
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, time::Duration},
+};
+
+/// TestFlight metadata of an app in a form suitable for storing in version control.
+///
+/// Localizations are keyed by locale. The `locale` attribute of each
+/// localization is ignored. Field names match the names used by the API.
+///
+/// With `fallbackLocale`, fields missing from a localization are inherited
+/// from the localization of that locale when pushed. See
+/// [BetaAppMetadata::resolve_fallback()].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BetaAppMetadata {
+    /// Text of the beta license agreement testers must accept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_agreement: Option<String>,
+
+    /// Locale whose localization fills fields missing from other localizations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_locale: Option<String>,
+
+    #[serde(default)]
+    pub localizations: BTreeMap<String, BetaAppLocalizationAttributes>,
+}
+
+impl BetaAppMetadata {
+    /// Verify all localizations use locales accepted by App Store Connect.
+    pub fn validate(&self) -> Result<(), AppleCodesignError> {
+        LocalizedResource::BetaApp.validate_locales(
+            self.localizations
+                .keys()
+                .chain(self.fallback_locale.iter())
+                .map(|x| x.as_str()),
+        )
+    }
+
+    /// Fill fields missing from localizations from the fallback locale.
+    ///
+    /// `fallback_locale` overrides `fallbackLocale` of the metadata. Returns
+    /// metadata without a fallback locale and the inherited fields. Without
+    /// any fallback locale, the metadata is returned unchanged.
+    pub fn resolve_fallback(
+        &self,
+        fallback_locale: Option<&str>,
+    ) -> Result<(Self, Vec<InheritedField>), AppleCodesignError> {
+        let mut resolved = Self {
+            fallback_locale: None,
+            ..self.clone()
+        };
+
+        let inherited = match fallback_locale.or(self.fallback_locale.as_deref()) {
+            Some(locale) => apply_locale_fallback(&mut resolved.localizations, locale)?,
+            None => vec![],
+        };
+
+        Ok((resolved, inherited))
+    }
+}
+
+impl AppStoreConnectClient {
+    /// Fetch the beta details of a build.
+    pub fn get_build_beta_detail(
+        &self,
+        build_id: &str,
+    ) -> Result<BuildBetaDetail, AppleCodesignError> {
+        let doc = self.api_get::<Document<BuildBetaDetail>>(
+            &format!("/v1/builds/{}/buildBetaDetail", build_id),
+            &[],
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// Modify the beta details of a build.
+    pub fn update_build_beta_detail(
+        &self,
+        build_beta_detail_id: &str,
+        attributes: BuildBetaDetailAttributes,
+    ) -> Result<BuildBetaDetail, AppleCodesignError> {
+        let body = RequestDocument::update("buildBetaDetails", build_beta_detail_id, attributes);
+
+        let doc = self.api_patch::<_, Document<BuildBetaDetail>>(
+            &format!("/v1/buildBetaDetails/{}", build_beta_detail_id),
+            &body,
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// List the beta groups of an app.
+    ///
+    /// If `internal` is set, only internal or external groups are returned.
+    pub fn list_beta_groups(
+        &self,
+        app_id: &str,
+        internal: Option<bool>,
+    ) -> Result<Vec<BetaGroup>, AppleCodesignError> {
+        let mut query = BetaGroupQuery::new().app(app_id);
+        if let Some(internal) = internal {
+            query = query.is_internal_group(internal);
+        }
+
+        self.api_get_all("/v1/betaGroups", &query.to_query())
+    }
+
+    /// Give the testers of a beta group access to builds.
+    pub fn add_builds_to_beta_group(
+        &self,
+        beta_group_id: &str,
+        build_ids: &[&str],
+    ) -> Result<(), AppleCodesignError> {
+        let body = RelationshipRequest {
+            data: build_ids
+                .iter()
+                .map(|id| ResourceIdentifier::new("builds", id))
+                .collect::<Vec<_>>(),
+        };
+
+        self.api_post_no_content(
+            &format!("/v1/betaGroups/{}/relationships/builds", beta_group_id),
+            &body,
+        )
+    }
+
+    /// Make a build available to internal TestFlight testers.
+    ///
+    /// This waits for the build to finish processing, declares its export compliance
+    /// if it hasn't been declared yet, and adds the build to every internal beta group
+    /// of the app that doesn't already have access to all builds.
+    ///
+    /// `uses_non_exempt_encryption` is the export compliance declaration to make. If
+    /// [None] and the build lacks a declaration, the build will remain in the
+    /// `MISSING_EXPORT_COMPLIANCE` state and won't be testable.
+    pub fn distribute_build_internally(
+        &self,
+        app_id: &str,
+        build_id: &str,
+        uses_non_exempt_encryption: Option<bool>,
+        wait_limit: Duration,
+    ) -> Result<BuildBetaDetail, AppleCodesignError> {
+        let build: Build =
+            self.wait_on_build_processing(build_id, wait_limit, Duration::from_secs(10))?;
+
+        if build.attributes.uses_non_exempt_encryption.is_none() {
+            if let Some(value) = uses_non_exempt_encryption {
+                warn!(
+                    "declaring build {} usesNonExemptEncryption={}",
+                    build_id, value
+                );
+                self.set_build_uses_non_exempt_encryption(build_id, value)?;
+            } else {
+                warn!(
+                    "build {} has no export compliance declaration; it will not be testable until one is made",
+                    build_id
+                );
+            }
+        }
+
+        for group in self.list_beta_groups(app_id, Some(true))? {
+            if group.attributes.has_access_to_all_builds == Some(true) {
+                continue;
+            }
+
+            warn!(
+                "adding build {} to internal beta group {}",
+                build_id,
+                group.attributes.name.as_deref().unwrap_or(&group.id)
+            );
+            self.add_builds_to_beta_group(&group.id, &[build_id])?;
+        }
+
+        self.get_build_beta_detail(build_id)
+    }
+
+    /// Define the *What to Test* text of a build for a locale.
+    ///
+    /// The localization is created if it doesn't exist.
+    pub fn set_build_whats_new(
+        &self,
+        build_id: &str,
+        locale: &str,
+        whats_new: &str,
+    ) -> Result<BetaBuildLocalization, AppleCodesignError> {
+        let existing = self
+            .api_get_all::<BetaBuildLocalizationAttributes>(
+                &format!("/v1/builds/{}/betaBuildLocalizations", build_id),
+                &[],
+            )?
+            .into_iter()
+            .find(|l| l.attributes.locale.as_deref() == Some(locale));
+
+        let doc = if let Some(existing) = existing {
+            let body = RequestDocument::update(
+                "betaBuildLocalizations",
+                &existing.id,
+                BetaBuildLocalizationAttributes {
+                    locale: None,
+                    whats_new: Some(whats_new.to_string()),
+                },
+            );
+
+            self.api_patch::<_, Document<BetaBuildLocalization>>(
+                &format!("/v1/betaBuildLocalizations/{}", existing.id),
+                &body,
+            )?
+        } else {
+            let body = RequestDocument::create(
+                "betaBuildLocalizations",
+                BetaBuildLocalizationAttributes {
+                    locale: Some(locale.to_string()),
+                    whats_new: Some(whats_new.to_string()),
+                },
+            )
+            .with_relationship("build", "builds", build_id);
+
+            self.api_post::<_, Document<BetaBuildLocalization>>(
+                "/v1/betaBuildLocalizations",
+                &body,
+            )?
+        };
+
+        Ok(doc.data)
+    }
+
+    /// Submit a build for TestFlight beta app review.
+    ///
+    /// Builds must pass beta app review before they can be distributed to
+    /// external testers.
+    pub fn submit_build_for_beta_review(
+        &self,
+        build_id: &str,
+    ) -> Result<BetaAppReviewSubmission, AppleCodesignError> {
+        let body = RequestDocument::create(
+            "betaAppReviewSubmissions",
+            BetaAppReviewSubmissionAttributes::default(),
+        )
+        .with_relationship("build", "builds", build_id);
+
+        let doc = self.api_post::<_, Document<BetaAppReviewSubmission>>(
+            "/v1/betaAppReviewSubmissions",
+            &body,
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// Fetch the beta license agreement of an app.
+    pub fn get_beta_license_agreement(
+        &self,
+        app_id: &str,
+    ) -> Result<BetaLicenseAgreement, AppleCodesignError> {
+        let doc = self.api_get::<Document<BetaLicenseAgreement>>(
+            &format!("/v1/apps/{}/betaLicenseAgreement", app_id),
+            &[],
+        )?;
+
+        Ok(doc.data)
+    }
+
+    /// Modify the text of a beta license agreement.
+    pub fn update_beta_license_agreement(
+        &self,
+        agreement_id: &str,
+        agreement_text: &str,
+    ) -> Result<Updated<BetaLicenseAgreementAttributes>, AppleCodesignError> {
+        let body = RequestDocument::update(
+            "betaLicenseAgreements",
+            agreement_id,
+            BetaLicenseAgreementAttributes {
+                agreement_text: Some(agreement_text.to_string()),
+            },
+        );
+
+        self.api_update(
+            &format!("/v1/betaLicenseAgreements/{}", agreement_id),
+            &body,
+        )
+    }
+
+    /// List the beta testers of an app.
+    pub fn list_beta_testers(&self, app_id: &str) -> Result<Vec<BetaTester>, AppleCodesignError> {
+        self.api_get_all(
+            "/v1/betaTesters",
+            &BetaTesterQuery::new().apps(app_id).to_query(),
+        )
+    }
+
+    /// List the localized TestFlight information of an app.
+    pub fn list_beta_app_localizations(
+        &self,
+        app_id: &str,
+    ) -> Result<Vec<BetaAppLocalization>, AppleCodesignError> {
+        self.api_get_all(
+            "/v1/betaAppLocalizations",
+            &BetaAppLocalizationQuery::new().app(app_id).to_query(),
+        )
+    }
+
+    /// Create localized TestFlight information for an app.
+    ///
+    /// `attributes` must have its `locale` set.
+    pub fn create_beta_app_localization(
+        &self,
+        app_id: &str,
+        attributes: BetaAppLocalizationAttributes,
+    ) -> Result<BetaAppLocalization, AppleCodesignError> {
+        let body = RequestDocument::create("betaAppLocalizations", attributes)
+            .with_relationship("app", "apps", app_id);
+
+        let doc =
+            self.api_post::<_, Document<BetaAppLocalization>>("/v1/betaAppLocalizations", &body)?;
+
+        Ok(doc.data)
+    }
+
+    /// Modify localized TestFlight information.
+    pub fn update_beta_app_localization(
+        &self,
+        localization_id: &str,
+        attributes: BetaAppLocalizationAttributes,
+    ) -> Result<Updated<BetaAppLocalizationAttributes>, AppleCodesignError> {
+        let body = RequestDocument::update(
+            "betaAppLocalizations",
+            localization_id,
+            BetaAppLocalizationAttributes {
+                locale: None,
+                ..attributes
+            },
+        );
+
+        self.api_update(
+            &format!("/v1/betaAppLocalizations/{}", localization_id),
+            &body,
+        )
+    }
+
+    /// Delete localized TestFlight information.
+    pub fn delete_beta_app_localization(
+        &self,
+        localization_id: &str,
+    ) -> Result<(), AppleCodesignError> {
+        self.api_delete(&format!("/v1/betaAppLocalizations/{}", localization_id))
+    }
+
+    /// Obtain the TestFlight metadata of an app.
+    pub fn pull_beta_app_metadata(
+        &self,
+        app_id: &str,
+    ) -> Result<BetaAppMetadata, AppleCodesignError> {
+        let agreement = self.get_beta_license_agreement(app_id)?;
+
+        let localizations = self
+            .list_beta_app_localizations(app_id)?
+            .into_iter()
+            .filter_map(|l| {
+                let mut attributes = l.attributes;
+                attributes.locale.take().map(|locale| (locale, attributes))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(BetaAppMetadata {
+            license_agreement: agreement.attributes.agreement_text,
+            fallback_locale: None,
+            localizations,
+        })
+    }
+
+    /// Apply TestFlight metadata to an app.
+    ///
+    /// Localizations are created or updated to match `metadata`. Localizations that
+    /// exist on the server but aren't present in `metadata` are left untouched. The
+    /// license agreement is only modified if `metadata` defines one. Fields
+    /// missing from localizations are inherited from its fallback locale, if
+    /// any.
+    pub fn push_beta_app_metadata(
+        &self,
+        app_id: &str,
+        metadata: &BetaAppMetadata,
+    ) -> Result<(), AppleCodesignError> {
+        metadata.validate()?;
+        let (metadata, inherited) = metadata.resolve_fallback(None)?;
+        for field in &inherited {
+            info!(
+                "{} inherits {} from {}",
+                field.locale, field.field, field.from_locale
+            );
+        }
+
+        if let Some(text) = &metadata.license_agreement {
+            let agreement = self.get_beta_license_agreement(app_id)?;
+
+            if agreement.attributes.agreement_text.as_ref() != Some(text) {
+                warn!("updating beta license agreement");
+                let updated = self.update_beta_license_agreement(&agreement.id, text)?;
+                log_attribute_changes(&updated.changes);
+            }
+        }
+
+        let existing = self
+            .list_beta_app_localizations(app_id)?
+            .into_iter()
+            .filter_map(|l| l.attributes.locale.clone().map(|locale| (locale, l)))
+            .collect::<BTreeMap<_, _>>();
+
+        for (locale, attributes) in &metadata.localizations {
+            let wanted = BetaAppLocalizationAttributes {
+                locale: Some(locale.clone()),
+                ..attributes.clone()
+            };
+
+            match existing.get(locale) {
+                Some(current) if is_up_to_date(&current.attributes, &wanted) => {}
+                Some(current) => {
+                    warn!("updating beta app localization {}", locale);
+                    let updated = self.update_beta_app_localization(&current.id, wanted)?;
+                    log_attribute_changes(&updated.changes);
+                }
+                None => {
+                    warn!("creating beta app localization {}", locale);
+                    self.create_beta_app_localization(app_id, wanted)?;
+                }
+            }
+        }
+
+        for locale in existing.keys() {
+            if !metadata.localizations.contains_key(locale) {
+                warn!(
+                    "beta app localization {} exists but is not defined in metadata; ignoring",
+                    locale
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a localization has every field `wanted` sets.
+///
+/// Fields `wanted` leaves unset are kept by updates, so they don't matter.
+fn is_up_to_date(
+    current: &BetaAppLocalizationAttributes,
+    wanted: &BetaAppLocalizationAttributes,
+) -> bool {
+    [
+        (&current.description, &wanted.description),
+        (&current.feedback_email, &wanted.feedback_email),
+        (&current.marketing_url, &wanted.marketing_url),
+        (&current.privacy_policy_url, &wanted.privacy_policy_url),
+        (&current.tv_os_privacy_policy, &wanted.tv_os_privacy_policy),
+    ]
+    .into_iter()
+    .all(|(current, wanted)| wanted.is_none() || wanted == current)
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    #[test]
+    fn beta_app_metadata_yaml() -> Result<(), serde_yaml::Error> {
+        let metadata = serde_yaml::from_str::<BetaAppMetadata>(
+            "licenseAgreement: Be nice.\n\
+            localizations:\n  \
+              en-US:\n    \
+                description: My app\n    \
+                feedbackEmail: beta@example.com\n",
+        )?;
+
+        assert_eq!(metadata.license_agreement.as_deref(), Some("Be nice."));
+        assert_eq!(
+            metadata.localizations.get("en-US"),
+            Some(&BetaAppLocalizationAttributes {
+                description: Some("My app".into()),
+                feedback_email: Some("beta@example.com".into()),
+                ..Default::default()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn beta_app_metadata_fallback() -> Result<(), AppleCodesignError> {
+        let metadata = serde_yaml::from_str::<BetaAppMetadata>(
+            "fallbackLocale: en-US\n\
+            localizations:\n  \
+              en-US:\n    \
+                description: My app\n    \
+                feedbackEmail: beta@example.com\n  \
+              de-DE:\n    \
+                description: Meine App\n",
+        )?;
+        metadata.validate()?;
+
+        let (resolved, inherited) = metadata.resolve_fallback(None)?;
+        assert!(resolved.fallback_locale.is_none());
+        assert_eq!(inherited.len(), 1);
+        assert_eq!(
+            resolved.localizations["de-DE"].feedback_email.as_deref(),
+            Some("beta@example.com")
+        );
+        assert_eq!(
+            resolved.localizations["de-DE"].description.as_deref(),
+            Some("Meine App")
+        );
+
+        // The fallback locale can be overridden.
+        let (_, inherited) = metadata.resolve_fallback(Some("de-DE"))?;
+        assert!(inherited.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn push_partial_localization() -> Result<(), AppleCodesignError> {
+        let localization = fixtures::beta_app_localization("L1", "en-US");
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/betaAppLocalizations",
+                200,
+                fixtures::collection(vec![localization.clone()]),
+            )
+            .json(
+                "GET",
+                "/v1/betaAppLocalizations/L1",
+                200,
+                fixtures::document(localization.clone()),
+            )
+            .json(
+                "PATCH",
+                "/v1/betaAppLocalizations/L1",
+                200,
+                fixtures::document(localization),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let patches = || {
+            server
+                .requests()
+                .iter()
+                .filter(|r| r.method == "PATCH")
+                .count()
+        };
+
+        // Unset fields don't cause updates.
+        let mut metadata = BetaAppMetadata::default();
+        metadata.localizations.insert(
+            "en-US".into(),
+            BetaAppLocalizationAttributes {
+                description: Some("An example app.".into()),
+                ..Default::default()
+            },
+        );
+        client.push_beta_app_metadata("A1", &metadata)?;
+        assert_eq!(patches(), 0);
+
+        metadata
+            .localizations
+            .get_mut("en-US")
+            .unwrap()
+            .marketing_url = Some("https://example.com".into());
+        client.push_beta_app_metadata("A1", &metadata)?;
+        assert_eq!(patches(), 1);
+
+        Ok(())
+    }
+}
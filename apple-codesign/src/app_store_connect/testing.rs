@@ -0,0 +1,906 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Hermetic testing of App Store Connect workflows.
+//!
+//! [MockServer] is a minimal HTTP server answering requests with canned
+//! responses. [MockServer::client()] returns an [AppStoreConnectClient] talking
+//! to it, so entire workflows (create a certificate, create a profile, upload a
+//! build) can be exercised without network access or an Apple account.
+//!
+//! The [fixtures] module provides response documents for the resources
+//! supported by this crate.
+//!
+//! ```no_run
+//! use apple_codesign::app_store_connect::testing::{fixtures, MockServerBuilder};
+//!
+//! let server = MockServerBuilder::default()
+//!     .json("GET", "/v1/apps/1", 200, fixtures::document(fixtures::app("1", "com.example.app")))
+//!     .start()
+//!     .unwrap();
+//!
+//! let app = server.client().unwrap().get_app("1").unwrap();
+//! assert_eq!(app.attributes.bundle_id.as_deref(), Some("com.example.app"));
+//! assert_eq!(server.requests().len(), 1);
+//! ```
+
+use {
+    crate::{
+        app_store_connect::{api_token::ConnectTokenEncoder, AppStoreConnectClient},
+        AppleCodesignError,
+    },
+    serde_json::Value,
+    std::{
+        collections::{BTreeMap, VecDeque},
+        io::{BufRead, BufReader, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread::JoinHandle,
+    },
+};
+
+/// Canned response documents for App Store Connect API resources.
+///
+/// Each function returns a resource object. Wrap it with [document()] for
+/// single resource responses or [collection()] for list responses.
+pub mod fixtures {
+    use serde_json::{json, Value};
+
+    /// A resource object with the given type, ID, and attributes.
+    pub fn resource(r#type: &str, id: &str, attributes: Value) -> Value {
+        json!({
+            "type": r#type,
+            "id": id,
+            "attributes": attributes,
+        })
+    }
+
+    /// A document holding a single resource.
+    pub fn document(resource: Value) -> Value {
+        json!({ "data": resource })
+    }
+
+    /// A document holding a list of resources, without pagination links.
+    pub fn collection(resources: Vec<Value>) -> Value {
+        json!({
+            "data": resources,
+            "meta": {"paging": {"total": resources.len(), "limit": 200}},
+        })
+    }
+
+    /// An app.
+    pub fn app(id: &str, bundle_id: &str) -> Value {
+        resource(
+            "apps",
+            id,
+            json!({
+                "name": "Example",
+                "bundleId": bundle_id,
+                "sku": "EXAMPLE",
+                "primaryLocale": "en-US",
+            }),
+        )
+    }
+
+    /// An App Store version of an iOS app.
+    pub fn app_store_version(id: &str, version_string: &str, app_store_state: &str) -> Value {
+        resource(
+            "appStoreVersions",
+            id,
+            json!({
+                "platform": "IOS",
+                "versionString": version_string,
+                "appStoreState": app_store_state,
+                "releaseType": "MANUAL",
+                "createdDate": "2022-01-01T00:00:00.000+0000",
+            }),
+        )
+    }
+
+    /// A bundle ID.
+    pub fn bundle_id(id: &str, identifier: &str) -> Value {
+        resource(
+            "bundleIds",
+            id,
+            json!({
+                "name": "Example",
+                "identifier": identifier,
+                "platform": "IOS",
+                "seedId": "ABCDE12345",
+            }),
+        )
+    }
+
+    /// A bundle ID capability.
+    pub fn bundle_id_capability(id: &str, capability_type: &str) -> Value {
+        resource(
+            "bundleIdCapabilities",
+            id,
+            json!({
+                "capabilityType": capability_type,
+                "settings": [],
+            }),
+        )
+    }
+
+    /// A signing certificate.
+    ///
+    /// `certificate_content` is the base64 encoded DER of the certificate.
+    pub fn certificate(id: &str, certificate_type: &str, certificate_content: &str) -> Value {
+        resource(
+            "certificates",
+            id,
+            json!({
+                "name": "Apple Distribution: Example (ABCDE12345)",
+                "displayName": "Example",
+                "certificateType": certificate_type,
+                "certificateContent": certificate_content,
+                "serialNumber": "1A2B3C4D5E6F",
+                "platform": "IOS",
+                "expirationDate": "2030-01-01T00:00:00.000+0000",
+            }),
+        )
+    }
+
+    /// A provisioning profile.
+    ///
+    /// `profile_content` is the base64 encoded `.mobileprovision` file.
+    pub fn profile(id: &str, name: &str, profile_type: &str, profile_content: &str) -> Value {
+        resource(
+            "profiles",
+            id,
+            json!({
+                "name": name,
+                "platform": "IOS",
+                "profileType": profile_type,
+                "profileState": "ACTIVE",
+                "profileContent": profile_content,
+                "uuid": "9d8a3c9e-0000-4000-8000-000000000000",
+                "createdDate": "2025-01-01T00:00:00.000+0000",
+                "expirationDate": "2026-01-01T00:00:00.000+0000",
+            }),
+        )
+    }
+
+    /// A registered device.
+    pub fn device(id: &str, udid: &str) -> Value {
+        resource(
+            "devices",
+            id,
+            json!({
+                "name": "iPhone",
+                "platform": "IOS",
+                "udid": udid,
+                "status": "ENABLED",
+                "deviceClass": "IPHONE",
+                "model": "iPhone 14",
+                "addedDate": "2025-01-01T00:00:00.000+0000",
+            }),
+        )
+    }
+
+    /// A build.
+    pub fn build(id: &str, version: &str, processing_state: &str) -> Value {
+        resource(
+            "builds",
+            id,
+            json!({
+                "version": version,
+                "uploadedDate": "2025-01-01T00:00:00.000+0000",
+                "expirationDate": "2025-04-01T00:00:00.000+0000",
+                "expired": false,
+                "minOsVersion": "15.0",
+                "processingState": processing_state,
+                "usesNonExemptEncryption": false,
+            }),
+        )
+    }
+
+    /// A build upload.
+    pub fn build_upload(id: &str, state: &str) -> Value {
+        resource(
+            "buildUploads",
+            id,
+            json!({
+                "cfBundleShortVersionString": "1.0.0",
+                "cfBundleVersion": "1",
+                "platform": "IOS",
+                "state": state,
+            }),
+        )
+    }
+
+    /// A build upload file whose content is uploaded in one `PUT` to `upload_url`.
+    pub fn build_upload_file(id: &str, file_size: u64, upload_url: &str) -> Value {
+        resource(
+            "buildUploadFiles",
+            id,
+            json!({
+                "assetType": "ASSET",
+                "fileName": "app.ipa",
+                "fileSize": file_size,
+                "uti": "com.apple.ipa",
+                "uploadOperations": [{
+                    "method": "PUT",
+                    "url": upload_url,
+                    "length": file_size,
+                    "offset": 0,
+                    "requestHeaders": [
+                        {"name": "Content-Type", "value": "application/octet-stream"},
+                    ],
+                }],
+                "assetDeliveryState": {"errors": [], "warnings": [], "state": "AWAITING_UPLOAD"},
+            }),
+        )
+    }
+
+    /// The TestFlight beta details of a build.
+    pub fn build_beta_detail(id: &str) -> Value {
+        resource(
+            "buildBetaDetails",
+            id,
+            json!({
+                "autoNotifyEnabled": false,
+                "internalBuildState": "READY_FOR_BETA_TESTING",
+                "externalBuildState": "READY_FOR_BETA_SUBMISSION",
+            }),
+        )
+    }
+
+    /// A TestFlight beta group.
+    pub fn beta_group(id: &str, name: &str, is_internal_group: bool) -> Value {
+        resource(
+            "betaGroups",
+            id,
+            json!({
+                "name": name,
+                "isInternalGroup": is_internal_group,
+                "hasAccessToAllBuilds": false,
+                "publicLinkEnabled": false,
+            }),
+        )
+    }
+
+    /// The TestFlight beta license agreement of an app.
+    pub fn beta_license_agreement(id: &str, agreement_text: &str) -> Value {
+        resource(
+            "betaLicenseAgreements",
+            id,
+            json!({ "agreementText": agreement_text }),
+        )
+    }
+
+    /// The localized TestFlight information of an app.
+    pub fn beta_app_localization(id: &str, locale: &str) -> Value {
+        resource(
+            "betaAppLocalizations",
+            id,
+            json!({
+                "locale": locale,
+                "description": "An example app.",
+                "feedbackEmail": "feedback@example.com",
+            }),
+        )
+    }
+
+    /// The localized *What to Test* text of a build.
+    pub fn beta_build_localization(id: &str, locale: &str, whats_new: &str) -> Value {
+        resource(
+            "betaBuildLocalizations",
+            id,
+            json!({
+                "locale": locale,
+                "whatsNew": whats_new,
+            }),
+        )
+    }
+
+    /// The App Store review details of an app version.
+    pub fn app_store_review_detail(id: &str) -> Value {
+        resource(
+            "appStoreReviewDetails",
+            id,
+            json!({
+                "contactFirstName": "Jane",
+                "contactLastName": "Doe",
+                "contactEmail": "jane@example.com",
+                "contactPhone": "+1 555 0100",
+                "demoAccountRequired": false,
+            }),
+        )
+    }
+
+    /// A review submission.
+    pub fn review_submission(id: &str, state: &str) -> Value {
+        resource(
+            "reviewSubmissions",
+            id,
+            json!({
+                "platform": "IOS",
+                "submittedDate": "2022-01-01T00:00:00.000+0000",
+                "state": state,
+            }),
+        )
+    }
+
+    /// An item of a review submission for an App Store version.
+    pub fn review_submission_item(id: &str, state: &str, app_store_version_id: &str) -> Value {
+        let mut item = resource("reviewSubmissionItems", id, json!({ "state": state }));
+        item["relationships"] = json!({
+            "appStoreVersion": {"data": {"type": "appStoreVersions", "id": app_store_version_id}},
+        });
+
+        item
+    }
+
+    /// An `.ipa` file holding only the `Info.plist` of an app.
+    ///
+    /// This is sufficient for
+    /// [AppStoreConnectClient::upload_ipa_data_unchecked()](crate::app_store_connect::AppStoreConnectClient::upload_ipa_data_unchecked).
+    pub fn ipa(bundle_identifier: &str, short_version: &str, version: &str) -> Vec<u8> {
+        let mut info = plist::Dictionary::new();
+        info.insert("CFBundleIdentifier".into(), bundle_identifier.into());
+        info.insert("CFBundleShortVersionString".into(), short_version.into());
+        info.insert("CFBundleVersion".into(), version.into());
+
+        let mut zf = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        zf.start_file("Payload/Example.app/Info.plist", Default::default())
+            .expect("zip writing should succeed");
+        plist::Value::Dictionary(info)
+            .to_writer_xml(&mut zf)
+            .expect("plist writing should succeed");
+
+        zf.finish()
+            .expect("zip writing should succeed")
+            .into_inner()
+    }
+
+    /// An error document, as returned with 4xx and 5xx statuses.
+    pub fn error(status: u16, code: &str, detail: &str) -> Value {
+        json!({
+            "errors": [{
+                "status": status.to_string(),
+                "code": code,
+                "title": code,
+                "detail": detail,
+            }],
+        })
+    }
+}
+
+/// A canned HTTP response.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// A response with a JSON body.
+    pub fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: serde_json::to_vec(body).expect("JSON values should serialize"),
+        }
+    }
+
+    /// A response without a body.
+    pub fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+}
+
+/// A request received by a [MockServer].
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The path of the request, excluding the query string.
+    pub path: String,
+    pub query: Option<String>,
+    /// Headers, with lowercase names.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    /// The value of a header, looked up case insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+
+        self.headers
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Decode the body as JSON.
+    pub fn json(&self) -> Result<Value, AppleCodesignError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+type Routes = BTreeMap<(String, String), VecDeque<MockResponse>>;
+
+/// Defines the responses of a [MockServer].
+///
+/// Responses are keyed by HTTP method and path (excluding the query string).
+/// If multiple responses are registered for the same route, they are returned
+/// in order and the last one is repeated. This allows simulating a resource
+/// whose state changes while it is polled. Requests without a route receive
+/// a `404` error document.
+///
+/// Routes can also be added after the server is started, e.g. for responses
+/// embedding [MockServer::url()].
+#[derive(Clone, Debug, Default)]
+pub struct MockServerBuilder {
+    routes: Routes,
+}
+
+impl MockServerBuilder {
+    /// Register a response for a method and path.
+    #[must_use]
+    pub fn route(mut self, method: &str, path: &str, response: MockResponse) -> Self {
+        add_route(&mut self.routes, method, path, response);
+        self
+    }
+
+    /// Register a JSON response for a method and path.
+    #[must_use]
+    pub fn json(self, method: &str, path: &str, status: u16, body: Value) -> Self {
+        self.route(method, path, MockResponse::json(status, &body))
+    }
+
+    /// Start serving on an ephemeral port of the loopback interface.
+    pub fn start(self) -> Result<MockServer, AppleCodesignError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+
+        let routes = Arc::new(Mutex::new(self.routes));
+        let requests = Arc::new(Mutex::new(vec![]));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let routes = routes.clone();
+            let requests = requests.clone();
+            let shutdown = shutdown.clone();
+
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    if let Ok(stream) = stream {
+                        // Errors only affect the client of this connection, which
+                        // will notice the failure.
+                        let _ = handle_connection(stream, &routes, &requests);
+                    }
+                }
+            })
+        };
+
+        Ok(MockServer {
+            address,
+            routes,
+            requests,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// An HTTP server answering requests with canned responses.
+///
+/// The server runs on a background thread until dropped.
+pub struct MockServer {
+    address: SocketAddr,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+
+        // Wake up the accept loop so it notices the shutdown.
+        let _ = TcpStream::connect(self.address);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl MockServer {
+    /// The base URL of the server. e.g. `http://127.0.0.1:12345`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// Register a response for a method and path.
+    ///
+    /// See [MockServerBuilder] for how responses are selected.
+    pub fn add_route(&self, method: &str, path: &str, response: MockResponse) {
+        add_route(&mut self.routes.lock().unwrap(), method, path, response);
+    }
+
+    /// Register a JSON response for a method and path.
+    pub fn add_json(&self, method: &str, path: &str, status: u16, body: Value) {
+        self.add_route(method, path, MockResponse::json(status, &body));
+    }
+
+    /// Requests received so far, in the order they were received.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Construct a client talking to this server.
+    ///
+    /// The client is authenticated with a newly generated key. The server
+    /// doesn't verify tokens.
+    pub fn client(&self) -> Result<AppStoreConnectClient, AppleCodesignError> {
+        let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .map_err(|_| {
+            AppleCodesignError::AppStoreConnectApiKey("error generating ECDSA key".into())
+        })?;
+
+        let encoder = ConnectTokenEncoder::from_ecdsa_der(
+            "MOCKKEY123".into(),
+            "00000000-0000-0000-0000-000000000000".into(),
+            key.as_ref(),
+        )?;
+
+        let mut client = AppStoreConnectClient::new(encoder)?;
+        client.set_api_url(self.url());
+
+        Ok(client)
+    }
+}
+
+fn add_route(routes: &mut Routes, method: &str, path: &str, response: MockResponse) {
+    routes
+        .entry((method.to_uppercase(), path.to_string()))
+        .or_default()
+        .push_back(response);
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    routes: &Mutex<Routes>,
+    requests: &Mutex<Vec<RecordedRequest>>,
+) -> Result<(), AppleCodesignError> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target.to_string(), None),
+    };
+
+    let mut headers = vec![];
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+
+        match line.trim_end().split_once(':') {
+            Some((name, value)) => {
+                headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+            }
+            None => break,
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(k, _)| k == "content-length")
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or_default();
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let response = {
+        let mut routes = routes.lock().unwrap();
+
+        match routes.get_mut(&(method.clone(), path.clone())) {
+            Some(queue) if queue.len() > 1 => queue.pop_front(),
+            Some(queue) => queue.front().cloned(),
+            None => None,
+        }
+    }
+    .unwrap_or_else(|| {
+        MockResponse::json(
+            404,
+            &fixtures::error(
+                404,
+                "NOT_FOUND",
+                &format!("no route for {} {}", method, path),
+            ),
+        )
+    });
+
+    requests.lock().unwrap().push(RecordedRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    });
+
+    let mut stream = stream;
+    write!(stream, "HTTP/1.1 {} Mock\r\n", response.status)?;
+    for (name, value) in &response.headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::app_store_connect::PageSize};
+
+    #[test]
+    fn read_only_client() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![]))
+            .route("DELETE", "/v1/devices/D1", MockResponse::empty(204))
+            .start()?;
+        let mut client = server.client()?;
+        client.set_read_only(true);
+
+        client.api_get::<serde_json::Value>("/v1/apps", &[])?;
+        assert!(matches!(
+            client.api_delete("/v1/devices/D1"),
+            Err(AppleCodesignError::AppStoreConnectReadOnly(method, _)) if method == "DELETE"
+        ));
+        assert_eq!(server.requests().len(), 1);
+
+        client.set_read_only(false);
+        client.api_delete("/v1/devices/D1")?;
+        assert_eq!(server.requests().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn foreign_url() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default()
+            .json("GET", "/v1/apps", 200, fixtures::collection(vec![]))
+            .start()?;
+        let client = server.client()?;
+
+        client.api_get_url::<serde_json::Value>(&format!("{}/v1/apps", server.url()))?;
+
+        for url in [
+            "https://example.com/v1/apps".to_string(),
+            format!(
+                "{}/v1/apps",
+                server.url().replacen("http://", "https://", 1)
+            ),
+            "not a url".to_string(),
+        ] {
+            assert!(matches!(
+                client.api_get_url::<serde_json::Value>(&url),
+                Err(AppleCodesignError::AppStoreConnectForeignUrl(_))
+            ));
+        }
+        assert_eq!(server.requests().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn page_size() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default().start()?;
+
+        let mut first = fixtures::collection(vec![fixtures::app("A1", "com.example.a")]);
+        first["links"] = serde_json::json!({"next": format!("{}/v1/apps/page2?cursor=Mg&limit=50", server.url())});
+        server.add_json("GET", "/v1/apps", 200, first);
+        server.add_json(
+            "GET",
+            "/v1/apps/page2",
+            200,
+            fixtures::collection(vec![fixtures::app("A2", "com.example.b")]),
+        );
+
+        let mut client = server.client()?;
+        assert_eq!(client.page_size(), PageSize::Adaptive);
+        assert_eq!(client.api_get_all::<Value>("/v1/apps", &[])?.len(), 2);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].query, None);
+        assert_eq!(requests[1].query.as_deref(), Some("cursor=Mg&limit=200"));
+
+        client.set_page_size("10".parse()?);
+        client.api_get_all::<Value>("/v1/apps", &[])?;
+        assert_eq!(server.requests()[2].query.as_deref(), Some("limit=10"));
+
+        client.api_get_all::<Value>("/v1/apps", &[("limit", "1".into())])?;
+        assert_eq!(server.requests()[4].query.as_deref(), Some("limit=1"));
+
+        assert!("201".parse::<PageSize>().is_err());
+
+        Ok(())
+    }
+
+    #[cfg(all(
+        feature = "asc-builds",
+        feature = "asc-provisioning",
+        feature = "asc-testflight"
+    ))]
+    #[test]
+    fn fixtures_deserialize() -> Result<(), AppleCodesignError> {
+        use crate::app_store_connect::{
+            app_api::App, build_api::Build, build_upload_api::BuildUploadFile,
+            bundle_api::BundleId, certs_api::Certificate, certs_api::Device, certs_api::Profile,
+            json_api::Document, review_api::AppStoreReviewDetail, testflight_api::BuildBetaDetail,
+        };
+
+        serde_json::from_value::<Document<App>>(fixtures::document(fixtures::app("1", "a")))?;
+        serde_json::from_value::<Document<Vec<BundleId>>>(fixtures::collection(vec![
+            fixtures::bundle_id("1", "a"),
+        ]))?;
+        serde_json::from_value::<Certificate>(fixtures::certificate("1", "DISTRIBUTION", ""))?;
+        serde_json::from_value::<Profile>(fixtures::profile("1", "a", "IOS_APP_STORE", ""))?;
+        serde_json::from_value::<Device>(fixtures::device("1", "a"))?;
+        serde_json::from_value::<Build>(fixtures::build("1", "1", "VALID"))?;
+        serde_json::from_value::<BuildUploadFile>(fixtures::build_upload_file("1", 1, "a"))?;
+        serde_json::from_value::<BuildBetaDetail>(fixtures::build_beta_detail("1"))?;
+        serde_json::from_value::<AppStoreReviewDetail>(fixtures::app_store_review_detail("1"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_route() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default().start()?;
+
+        assert!(server.client()?.get_app("1").is_err());
+        assert_eq!(server.requests()[0].path, "/v1/apps/1");
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "asc-builds", feature = "asc-provisioning"))]
+    #[test]
+    fn certificate_profile_upload_workflow() -> Result<(), AppleCodesignError> {
+        use {
+            crate::app_store_connect::{
+                build_upload_api::{BuildUploadState, Platform},
+                certs_api::{CertificateType, ProfileType},
+            },
+            std::time::Duration,
+        };
+
+        let data = fixtures::ipa("com.example.app", "1.0.0", "1");
+
+        let server = MockServerBuilder::default()
+            .json(
+                "POST",
+                "/v1/certificates",
+                201,
+                fixtures::document(fixtures::certificate("C1", "DISTRIBUTION", "")),
+            )
+            .json(
+                "POST",
+                "/v1/profiles",
+                201,
+                fixtures::document(fixtures::profile("P1", "Example", "IOS_APP_STORE", "")),
+            )
+            .json(
+                "POST",
+                "/v1/buildUploads",
+                201,
+                fixtures::document(fixtures::build_upload("U1", "AWAITING_UPLOAD")),
+            )
+            .route("PUT", "/upload/1", MockResponse::empty(200))
+            .json(
+                "GET",
+                "/v1/buildUploads/U1",
+                200,
+                fixtures::document(fixtures::build_upload("U1", "PROCESSING")),
+            )
+            .json("GET", "/v1/buildUploads/U1", 200, {
+                let mut doc = fixtures::document(fixtures::build_upload("U1", "COMPLETE"));
+                doc["included"] = Value::Array(vec![fixtures::build("B1", "1", "PROCESSING")]);
+                doc
+            })
+            .start()?;
+
+        let upload_file = fixtures::document(fixtures::build_upload_file(
+            "F1",
+            data.len() as u64,
+            &format!("{}/upload/1", server.url()),
+        ));
+        server.add_json("POST", "/v1/buildUploadFiles", 201, upload_file.clone());
+        server.add_json("PATCH", "/v1/buildUploadFiles/F1", 200, upload_file);
+
+        let client = server.client()?;
+
+        let cert = client.create_certificate("CSR", CertificateType::Distribution)?;
+        let profile = client.create_profile(
+            "Example",
+            ProfileType::IosAppStore,
+            "BUNDLE1",
+            &[cert.id.clone()],
+            &[],
+        )?;
+        assert_eq!(profile.id, "P1");
+
+        let upload = client.upload_ipa_data_unchecked("APP1", "app.ipa", &data, Platform::Ios)?;
+        assert_eq!(
+            upload.attributes.state,
+            Some(BuildUploadState::AwaitingUpload)
+        );
+
+        let build = client.wait_on_build_upload(
+            &upload.id,
+            Duration::from_secs(10),
+            Duration::from_millis(1),
+        )?;
+        assert_eq!(build.id, "B1");
+
+        let requests = server.requests();
+        let paths = requests
+            .iter()
+            .map(|r| format!("{} {}", r.method, r.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "POST /v1/certificates",
+                "POST /v1/profiles",
+                "POST /v1/buildUploads",
+                "POST /v1/buildUploadFiles",
+                "PUT /upload/1",
+                "PATCH /v1/buildUploadFiles/F1",
+                "GET /v1/buildUploads/U1",
+                "GET /v1/buildUploads/U1",
+            ]
+        );
+
+        assert!(requests[0]
+            .header("authorization")
+            .unwrap()
+            .starts_with("Bearer "));
+        assert_eq!(
+            requests[1].json()?["data"]["relationships"]["certificates"]["data"][0]["id"],
+            "C1"
+        );
+        assert_eq!(requests[4].body, data);
+        assert!(requests[4].header("authorization").is_none());
+        assert_eq!(
+            requests[5].json()?["data"]["attributes"]["uploaded"],
+            Value::Bool(true)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Persistence of API tokens across processes.
+//!
+//! [AppStoreConnectClient] mints a token on its first request. Short-lived
+//! processes, such as invocations of `rcodesign`, would each mint a token,
+//! which is wasted work and shows up as noise in Apple's audit logs. A
+//! [TokenCache] attached with [AppStoreConnectClient::set_token_cache()]
+//! keeps the last minted token of each API key on disk so later processes
+//! reuse it until it is due for replacement.
+//!
+//! Tokens are stored per [CacheScope] in files only readable by the current
+//! user. A token grants access to the account until it expires, so the cache
+//! is opt-in.
+
+use {
+    crate::{
+        app_store_connect::{cache_scope::CacheScope, write_private_file},
+        AppleCodesignError,
+    },
+    log::{debug, warn},
+    serde::{Deserialize, Serialize},
+    std::{
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// A token as stored on disk.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CachedToken {
+    token: String,
+
+    /// Seconds since the UNIX epoch at which the token expires.
+    expires_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// A directory of API tokens keyed by the API key they were minted with.
+#[derive(Clone, Debug)]
+pub struct TokenCache {
+    dir: PathBuf,
+}
+
+impl TokenCache {
+    /// Construct an instance storing tokens in a directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The default directory of cached tokens.
+    ///
+    /// `~/.cache/asconnect/tokens` on Linux.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("asconnect").join("tokens"))
+    }
+
+    /// The directory tokens are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, scope: &CacheScope) -> PathBuf {
+        self.dir.join(format!("{}.json", scope))
+    }
+
+    /// Load the token of an API key if it is valid for at least `min_lifetime`.
+    ///
+    /// Returns the token and its remaining lifetime. Unreadable entries are
+    /// treated as missing.
+    pub fn load(&self, scope: &CacheScope, min_lifetime: Duration) -> Option<(String, Duration)> {
+        let path = self.path(scope);
+
+        let cached = match std::fs::read(&path) {
+            Ok(data) => match serde_json::from_slice::<CachedToken>(&data) {
+                Ok(cached) => cached,
+                Err(e) => {
+                    debug!("ignoring malformed cached token {}: {}", path.display(), e);
+                    return None;
+                }
+            },
+            Err(_) => return None,
+        };
+
+        let remaining = Duration::from_secs(cached.expires_at.saturating_sub(now()));
+
+        if remaining > min_lifetime {
+            Some((cached.token, remaining))
+        } else {
+            None
+        }
+    }
+
+    /// Store the token of an API key expiring after `lifetime`.
+    pub fn store(
+        &self,
+        scope: &CacheScope,
+        token: &str,
+        lifetime: Duration,
+    ) -> Result<(), AppleCodesignError> {
+        let cached = CachedToken {
+            token: token.to_string(),
+            expires_at: now() + lifetime.as_secs(),
+        };
+
+        write_private_file(&self.path(scope), &serde_json::to_vec(&cached)?)
+    }
+
+    /// Store a token, logging rather than returning failures.
+    ///
+    /// A cache that can't be written shouldn't fail requests.
+    pub(crate) fn store_or_warn(&self, scope: &CacheScope, token: &str, lifetime: Duration) {
+        if let Err(e) = self.store(scope, token, lifetime) {
+            warn!(
+                "failed to cache App Store Connect API token in {}: {}",
+                self.dir.display(),
+                e
+            );
+        }
+    }
+
+    /// Remove the token of an API key, if any.
+    pub fn remove(&self, scope: &CacheScope) -> Result<(), AppleCodesignError> {
+        match std::fs::remove_file(self.path(scope)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::app_store_connect::testing::MockServerBuilder};
+
+    #[test]
+    fn store_load() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let cache = TokenCache::new(td.path());
+        let scope = CacheScope::new("issuer", "KEY1");
+
+        assert!(cache.load(&scope, Duration::ZERO).is_none());
+
+        cache.store(&scope, "token", Duration::from_secs(300))?;
+        let (token, remaining) = cache.load(&scope, Duration::from_secs(60)).unwrap();
+        assert_eq!(token, "token");
+        assert!(remaining <= Duration::from_secs(300));
+
+        // Tokens about to expire aren't returned.
+        assert!(cache.load(&scope, Duration::from_secs(300)).is_none());
+
+        // Tokens of other API keys aren't found.
+        assert!(cache
+            .load(&CacheScope::new("issuer", "KEY2"), Duration::ZERO)
+            .is_none());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(cache.path(&scope))?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        cache.remove(&scope)?;
+        assert!(cache.load(&scope, Duration::ZERO).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn client_reuses_cached_token() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let server = MockServerBuilder::default().start()?;
+        let mut client = server.client()?;
+        let cache = TokenCache::new(td.path());
+
+        cache.store(&client.cache_scope(), "cached", Duration::from_secs(300))?;
+        client.set_token_cache(Some(cache.clone()));
+        assert_eq!(client.get_token()?, "cached");
+
+        // Tokens due for replacement are replaced and the cache is updated.
+        cache.store(&client.cache_scope(), "expiring", Duration::from_secs(30))?;
+        let mut client = server.client()?;
+        client.set_token_cache(Some(cache.clone()));
+        let token = client.get_token()?;
+        assert_ne!(token, "expiring");
+        assert_eq!(
+            cache.load(&client.cache_scope(), Duration::ZERO).unwrap().0,
+            token
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! An async client authenticating with a token minted elsewhere.
+//!
+//! [TokenClient] lists certificates, profiles, devices, apps, and builds.
+//! Unlike the [async client](super::r#async::AppStoreConnectClient), it
+//! doesn't hold an API key: it is given a token minted elsewhere (e.g. by a
+//! server holding the key), and it doesn't touch the filesystem.
+//!
+//! This module is available with the `async` feature.
+
+use {
+    crate::{
+        app_store_connect::{
+            app_api::App,
+            build_api::Build,
+            certs_api::{Certificate, Device, Profile},
+            json_api::{BuildQuery, Document, Resource},
+            r#async::execute,
+            APP_STORE_CONNECT_API_URL,
+        },
+        AppleCodesignError,
+    },
+    serde::de::DeserializeOwned,
+};
+
+/// An async client for App Store Connect API authenticating with a given token.
+pub struct TokenClient {
+    client: reqwest::Client,
+    api_url: String,
+    token: String,
+}
+
+impl TokenClient {
+    /// Construct a client authenticating with a previously minted token.
+    ///
+    /// See [ConnectTokenEncoder](super::api_token::ConnectTokenEncoder) for how
+    /// tokens are minted.
+    pub fn new(token: impl ToString) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: APP_STORE_CONNECT_API_URL.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Set the base URL of the App Store Connect API.
+    pub fn set_api_url(&mut self, url: impl ToString) {
+        self.api_url = url.to_string().trim_end_matches('/').to_string();
+    }
+
+    /// Replace the token, e.g. after the previous one expired.
+    pub fn set_token(&mut self, token: impl ToString) {
+        self.token = token.to_string();
+    }
+
+    async fn get_url<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, AppleCodesignError> {
+        let request = self
+            .client
+            .get(url)
+            .query(query)
+            .bearer_auth(&self.token)
+            .header("Accept", "application/json");
+
+        Ok(execute(&self.client, request).await?.json::<T>().await?)
+    }
+
+    /// Fetch all resources of a collection, following pagination links.
+    async fn get_all<A: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<Resource<A>>, AppleCodesignError> {
+        let mut page = self
+            .get_url::<Document<Vec<Resource<A>>>>(&format!("{}{}", self.api_url, path), query)
+            .await?;
+        let mut res = vec![];
+
+        loop {
+            res.extend(page.data);
+
+            // Pagination links include the query string.
+            match page.links.and_then(|links| links.next) {
+                Some(next) => {
+                    page = self.get_url(&next, &[]).await?;
+                }
+                None => break,
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// List signing certificates in the account.
+    pub async fn list_certificates(&self) -> Result<Vec<Certificate>, AppleCodesignError> {
+        self.get_all("/v1/certificates", &[]).await
+    }
+
+    /// List provisioning profiles in the account.
+    pub async fn list_profiles(&self) -> Result<Vec<Profile>, AppleCodesignError> {
+        self.get_all("/v1/profiles", &[]).await
+    }
+
+    /// List devices registered to the account.
+    pub async fn list_devices(&self) -> Result<Vec<Device>, AppleCodesignError> {
+        self.get_all("/v1/devices", &[]).await
+    }
+
+    /// List all apps in the account.
+    pub async fn list_apps(&self) -> Result<Vec<App>, AppleCodesignError> {
+        self.get_all("/v1/apps", &[]).await
+    }
+
+    /// List builds of an app.
+    pub async fn list_builds(&self, app_id: &str) -> Result<Vec<Build>, AppleCodesignError> {
+        self.get_all("/v1/builds", &BuildQuery::new().app(app_id).to_query())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+        serde_json::json,
+    };
+
+    #[test]
+    fn list_paginated() -> Result<(), AppleCodesignError> {
+        let server = MockServerBuilder::default().start()?;
+
+        let mut first = fixtures::collection(vec![fixtures::device("D1", "1")]);
+        first["links"] = json!({"next": format!("{}/v1/devices/page2", server.url())});
+        server.add_json("GET", "/v1/devices", 200, first);
+        server.add_json(
+            "GET",
+            "/v1/devices/page2",
+            200,
+            fixtures::collection(vec![fixtures::device("D2", "2")]),
+        );
+
+        let mut client = TokenClient::new("token");
+        client.set_api_url(server.url());
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let devices = rt.block_on(client.list_devices())?;
+
+        assert_eq!(
+            devices.iter().map(|d| d.id.as_str()).collect::<Vec<_>>(),
+            vec!["D1", "D2"]
+        );
+        assert_eq!(
+            server.requests()[0].header("authorization"),
+            Some("Bearer token")
+        );
+
+        server.add_json(
+            "GET",
+            "/v1/apps",
+            401,
+            fixtures::error(401, "NOT_AUTHORIZED", "expired token"),
+        );
+        assert!(matches!(
+            rt.block_on(client.list_apps()),
+            Err(AppleCodesignError::AppStoreConnectApi(e)) if e.status == 401
+        ));
+
+        Ok(())
+    }
+}
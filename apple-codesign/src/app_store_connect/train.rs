@@ -0,0 +1,610 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Release trains.
+//!
+//! Teams releasing on a fixed cadence cut a new App Store version every few
+//! weeks. A [TrainConfig] describes the cadence: the date of the first
+//! release, the number of days between releases, and which component of the
+//! version string each train bumps.
+//!
+//! [AppStoreConnectClient::start_train()] computes the next version string from
+//! the versions of an app, creates the App Store version, and opens a review
+//! submission holding it, to be submitted once a build is attached.
+//! [AppStoreConnectClient::train_status()] summarizes where an app is in its
+//! current train.
+//!
+//! An example train file:
+//!
+//! ```toml
+//! platform = "IOS"
+//! bump = "minor"
+//! cadence_days = 14
+//! first_release = "2022-01-10"
+//! scheduled_release = true
+//! release_hour = 16
+//! ```
+
+use {
+    crate::{
+        app_store_connect::{
+            app_api::{AppStoreVersion, AppStoreVersionAttributes, AppStoreVersionState},
+            build_upload_api::Platform,
+            json_api::{AppStoreVersionQuery, ReviewSubmissionQuery},
+            review_api::{ReviewSubmission, ReviewSubmissionAttributes, ReviewSubmissionState},
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    chrono::{Duration, NaiveDate},
+    log::warn,
+    serde::{Deserialize, Serialize},
+    std::path::Path,
+};
+
+fn default_platform() -> Platform {
+    Platform::Ios
+}
+
+fn default_bump() -> VersionBump {
+    VersionBump::Minor
+}
+
+fn default_initial_version() -> String {
+    "1.0.0".to_string()
+}
+
+/// Parse a dotted version string like `1.2.3` into its components.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version.split('.').map(|c| c.parse::<u64>().ok()).collect()
+}
+
+/// The component of the version string incremented by each train.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionBump {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl VersionBump {
+    /// Bump a dotted version string.
+    ///
+    /// Components after the bumped one are reset to 0. Missing components are
+    /// added, so bumping the patch component of `1.2` yields `1.2.1`.
+    pub fn apply(&self, version: &str) -> Result<String, AppleCodesignError> {
+        let mut components = parse_version(version).ok_or_else(|| {
+            AppleCodesignError::TrainConfig(format!("cannot bump version {}", version))
+        })?;
+
+        let index = match self {
+            Self::Major => 0,
+            Self::Minor => 1,
+            Self::Patch => 2,
+        };
+
+        while components.len() <= index.max(1) {
+            components.push(0);
+        }
+
+        components[index] += 1;
+        for c in components.iter_mut().skip(index + 1) {
+            *c = 0;
+        }
+
+        Ok(components
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join("."))
+    }
+}
+
+/// The cadence of a release train.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrainConfig {
+    /// Platform of the App Store versions.
+    #[serde(default = "default_platform")]
+    pub platform: Platform,
+
+    /// Component of the version string each train increments.
+    #[serde(default = "default_bump")]
+    pub bump: VersionBump,
+
+    /// Number of days between releases.
+    pub cadence_days: u32,
+
+    /// Day of the first release, as `YYYY-MM-DD`.
+    ///
+    /// Later releases are a multiple of `cadence_days` after it.
+    pub first_release: String,
+
+    /// Whether versions are released automatically on their release day.
+    ///
+    /// Otherwise they are released manually once approved.
+    #[serde(default)]
+    pub scheduled_release: bool,
+
+    /// Hour (UTC) of scheduled releases.
+    #[serde(default)]
+    pub release_hour: u32,
+
+    /// Version string of the first train of an app without versions.
+    #[serde(default = "default_initial_version")]
+    pub initial_version: String,
+}
+
+impl TrainConfig {
+    /// Parse a train from TOML.
+    pub fn from_toml(data: &str) -> Result<Self, AppleCodesignError> {
+        let config = toml::from_str::<Self>(data)
+            .map_err(|e| AppleCodesignError::TrainConfig(e.to_string()))?;
+
+        if config.cadence_days == 0 {
+            return Err(AppleCodesignError::TrainConfig(
+                "cadence_days must be positive".into(),
+            ));
+        }
+        if config.release_hour > 23 {
+            return Err(AppleCodesignError::TrainConfig(
+                "release_hour must be between 0 and 23".into(),
+            ));
+        }
+        if parse_version(&config.initial_version).is_none() {
+            return Err(AppleCodesignError::TrainConfig(format!(
+                "invalid initial_version: {}",
+                config.initial_version
+            )));
+        }
+        config.first_release_date()?;
+
+        Ok(config)
+    }
+
+    /// Read a train from a TOML file.
+    pub fn from_path(path: &Path) -> Result<Self, AppleCodesignError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    fn first_release_date(&self) -> Result<NaiveDate, AppleCodesignError> {
+        NaiveDate::parse_from_str(&self.first_release, "%Y-%m-%d").map_err(|e| {
+            AppleCodesignError::TrainConfig(format!(
+                "invalid first_release {}: {}",
+                self.first_release, e
+            ))
+        })
+    }
+
+    /// The first release day on or after a day.
+    pub fn next_release_date(&self, today: NaiveDate) -> Result<NaiveDate, AppleCodesignError> {
+        let first = self.first_release_date()?;
+
+        if today <= first {
+            return Ok(first);
+        }
+
+        let cadence = self.cadence_days as i64;
+        let trains = ((today - first).num_days() + cadence - 1) / cadence;
+
+        Ok(first + Duration::days(trains * cadence))
+    }
+
+    /// The version string of the train following the latest of some versions.
+    pub fn next_version(&self, versions: &[AppStoreVersion]) -> Result<String, AppleCodesignError> {
+        match latest_version(versions) {
+            Some(latest) => self.bump.apply(
+                latest
+                    .attributes
+                    .version_string
+                    .as_deref()
+                    .unwrap_or_default(),
+            ),
+            None => Ok(self.initial_version.clone()),
+        }
+    }
+}
+
+/// The version with the highest version string.
+fn latest_version<'a>(
+    versions: impl IntoIterator<Item = &'a AppStoreVersion>,
+) -> Option<&'a AppStoreVersion> {
+    versions
+        .into_iter()
+        .filter_map(|v| {
+            v.attributes
+                .version_string
+                .as_deref()
+                .and_then(parse_version)
+                .map(|parsed| (parsed, v))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, v)| v)
+}
+
+/// Where an app is in its current train.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrainPhase {
+    /// Every version is released. The next train can start.
+    Idle,
+
+    /// A version is being prepared for review.
+    Preparing,
+
+    /// A version is with App Review.
+    InReview,
+
+    /// App Review rejected the version or it was withdrawn.
+    Rejected,
+
+    /// A version is approved and awaits release.
+    PendingRelease,
+}
+
+impl TrainPhase {
+    fn of(state: Option<AppStoreVersionState>) -> Self {
+        match state {
+            None => Self::Preparing,
+            Some(state) if state.is_released() => Self::Idle,
+            Some(state) if state.is_in_review() => Self::InReview,
+            Some(state) if state.is_rejected() => Self::Rejected,
+            Some(
+                AppStoreVersionState::Accepted
+                | AppStoreVersionState::PendingAppleRelease
+                | AppStoreVersionState::PendingContract
+                | AppStoreVersionState::PendingDeveloperRelease
+                | AppStoreVersionState::ProcessingForAppStore,
+            ) => Self::PendingRelease,
+            Some(_) => Self::Preparing,
+        }
+    }
+}
+
+/// The state of the train of an app.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainStatus {
+    pub app_id: String,
+    pub phase: TrainPhase,
+
+    /// Version string of the latest released version.
+    pub released_version: Option<String>,
+
+    /// Version string of the version of the current train, if not released.
+    pub current_version: Option<String>,
+    pub current_state: Option<AppStoreVersionState>,
+
+    /// The open review submission of the platform, if any.
+    pub review_submission_id: Option<String>,
+    pub review_submission_state: Option<ReviewSubmissionState>,
+
+    /// Version string of the next train.
+    pub next_version: String,
+
+    /// Release day of the next train.
+    pub next_release_date: String,
+}
+
+/// The outcome of [AppStoreConnectClient::start_train()].
+#[derive(Clone, Debug)]
+pub struct TrainStart {
+    /// The version of the train.
+    pub version: AppStoreVersion,
+
+    /// Whether the version was created, rather than being in progress already.
+    pub created: bool,
+
+    /// The review submission holding the version.
+    ///
+    /// [None] if the version is no longer editable, e.g. because it is in review.
+    pub review_submission: Option<ReviewSubmission>,
+}
+
+impl AppStoreConnectClient {
+    /// Find the open review submission of an app for a platform.
+    pub(crate) fn find_open_review_submission(
+        &self,
+        app_id: &str,
+        platform: Platform,
+    ) -> Result<Option<ReviewSubmission>, AppleCodesignError> {
+        Ok(self
+            .api_get_all::<ReviewSubmissionAttributes>(
+                "/v1/reviewSubmissions",
+                &ReviewSubmissionQuery::new()
+                    .app(app_id)
+                    .platform(platform)
+                    .to_query(),
+            )?
+            .into_iter()
+            .find(|s| {
+                s.attributes.state.is_some_and(|state| {
+                    state == ReviewSubmissionState::ReadyForReview || state.is_pending()
+                })
+            }))
+    }
+
+    /// Start the next train of an app.
+    ///
+    /// If the latest version of the app isn't released, it is the version of
+    /// the current train and no version is created. Otherwise a version with
+    /// the next version string is created, scheduled for the next release day
+    /// if the train has scheduled releases.
+    ///
+    /// The version is then added to the draft review submission of the
+    /// platform, which is created if needed.
+    pub fn start_train(
+        &self,
+        app_id: &str,
+        config: &TrainConfig,
+        today: NaiveDate,
+    ) -> Result<TrainStart, AppleCodesignError> {
+        let versions = self.list_app_store_versions(
+            app_id,
+            &AppStoreVersionQuery::new().platform(config.platform),
+        )?;
+
+        let current = latest_version(&versions)
+            .filter(|v| TrainPhase::of(v.attributes.app_store_state) != TrainPhase::Idle);
+
+        let (version, created) = if let Some(current) = current {
+            warn!(
+                "version {} is in progress; not starting a new train",
+                current
+                    .attributes
+                    .version_string
+                    .as_deref()
+                    .unwrap_or_default()
+            );
+            (current.clone(), false)
+        } else {
+            let version_string = config.next_version(&versions)?;
+            let release_date = config.next_release_date(today)?;
+            warn!(
+                "creating version {} for release on {}",
+                version_string, release_date
+            );
+
+            let attributes = AppStoreVersionAttributes {
+                platform: Some(config.platform),
+                version_string: Some(version_string),
+                release_type: Some(
+                    if config.scheduled_release {
+                        "SCHEDULED"
+                    } else {
+                        "MANUAL"
+                    }
+                    .to_string(),
+                ),
+                earliest_release_date: if config.scheduled_release {
+                    Some(format!(
+                        "{}T{:02}:00:00+00:00",
+                        release_date, config.release_hour
+                    ))
+                } else {
+                    None
+                },
+                ..Default::default()
+            };
+
+            (self.create_app_store_version(app_id, attributes)?, true)
+        };
+
+        if !matches!(
+            TrainPhase::of(version.attributes.app_store_state),
+            TrainPhase::Preparing | TrainPhase::Rejected
+        ) {
+            return Ok(TrainStart {
+                version,
+                created,
+                review_submission: None,
+            });
+        }
+
+        let submission = match self.find_open_review_submission(app_id, config.platform)? {
+            Some(submission) => submission,
+            None => {
+                warn!("creating review submission");
+                self.create_review_submission(app_id, config.platform)?
+            }
+        };
+
+        if submission.attributes.state == Some(ReviewSubmissionState::ReadyForReview)
+            && !self
+                .list_review_submission_items(&submission.id)?
+                .iter()
+                .any(|item| {
+                    item.related("appStoreVersion")
+                        .iter()
+                        .any(|r| r.id == version.id)
+                })
+        {
+            warn!("adding version to review submission {}", submission.id);
+            self.add_review_submission_item(&submission.id, &version.id)?;
+        }
+
+        Ok(TrainStart {
+            version,
+            created,
+            review_submission: Some(submission),
+        })
+    }
+
+    /// Summarize where an app is in its current train.
+    pub fn train_status(
+        &self,
+        app_id: &str,
+        config: &TrainConfig,
+        today: NaiveDate,
+    ) -> Result<TrainStatus, AppleCodesignError> {
+        let versions = self.list_app_store_versions(
+            app_id,
+            &AppStoreVersionQuery::new().platform(config.platform),
+        )?;
+
+        let latest = latest_version(&versions);
+        let released = latest_version(versions.iter().filter(|v| {
+            v.attributes
+                .app_store_state
+                .is_some_and(|state| state.is_released())
+        }));
+        let phase = latest
+            .map(|v| TrainPhase::of(v.attributes.app_store_state))
+            .unwrap_or(TrainPhase::Idle);
+        let current = latest.filter(|_| phase != TrainPhase::Idle);
+
+        let submission = self.find_open_review_submission(app_id, config.platform)?;
+
+        Ok(TrainStatus {
+            app_id: app_id.to_string(),
+            phase,
+            released_version: released.and_then(|v| v.attributes.version_string.clone()),
+            current_version: current.and_then(|v| v.attributes.version_string.clone()),
+            current_state: current.and_then(|v| v.attributes.app_store_state),
+            review_submission_id: submission.as_ref().map(|s| s.id.clone()),
+            review_submission_state: submission.and_then(|s| s.attributes.state),
+            next_version: if current.is_some() {
+                config.bump.apply(
+                    current
+                        .and_then(|v| v.attributes.version_string.as_deref())
+                        .unwrap_or_default(),
+                )?
+            } else {
+                config.next_version(&versions)?
+            },
+            next_release_date: config.next_release_date(today)?.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::app_store_connect::testing::{fixtures, MockServerBuilder},
+    };
+
+    const TRAIN: &str = r#"
+        cadence_days = 14
+        first_release = "2022-01-10"
+        scheduled_release = true
+        release_hour = 16
+    "#;
+
+    #[test]
+    fn cadence() -> Result<(), AppleCodesignError> {
+        let config = TrainConfig::from_toml(TRAIN)?;
+        let day = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        assert_eq!(
+            config.next_release_date(day("2022-01-01"))?,
+            day("2022-01-10")
+        );
+        assert_eq!(
+            config.next_release_date(day("2022-01-10"))?,
+            day("2022-01-10")
+        );
+        assert_eq!(
+            config.next_release_date(day("2022-01-11"))?,
+            day("2022-01-24")
+        );
+
+        assert_eq!(VersionBump::Minor.apply("1.9.3")?, "1.10.0");
+        assert_eq!(VersionBump::Major.apply("1.2")?, "2.0");
+        assert_eq!(VersionBump::Patch.apply("1.2")?, "1.2.1");
+        assert!(VersionBump::Minor.apply("1.x").is_err());
+
+        assert!(
+            TrainConfig::from_toml("cadence_days = 0\nfirst_release = \"2022-01-10\"").is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn start() -> Result<(), AppleCodesignError> {
+        let config = TrainConfig::from_toml(TRAIN)?;
+        let today = NaiveDate::parse_from_str("2022-01-11", "%Y-%m-%d").unwrap();
+
+        let server = MockServerBuilder::default()
+            .json(
+                "GET",
+                "/v1/apps/A1/appStoreVersions",
+                200,
+                fixtures::collection(vec![
+                    fixtures::app_store_version("V1", "1.9.0", "REPLACED_WITH_NEW_VERSION"),
+                    fixtures::app_store_version("V2", "1.10.0", "READY_FOR_SALE"),
+                ]),
+            )
+            .json(
+                "POST",
+                "/v1/appStoreVersions",
+                201,
+                fixtures::document(fixtures::app_store_version(
+                    "V3",
+                    "1.11.0",
+                    "PREPARE_FOR_SUBMISSION",
+                )),
+            )
+            .json(
+                "GET",
+                "/v1/reviewSubmissions",
+                200,
+                fixtures::collection(vec![]),
+            )
+            .json(
+                "POST",
+                "/v1/reviewSubmissions",
+                201,
+                fixtures::document(fixtures::review_submission("R1", "READY_FOR_REVIEW")),
+            )
+            .json(
+                "GET",
+                "/v1/reviewSubmissions/R1/items",
+                200,
+                fixtures::collection(vec![]),
+            )
+            .json(
+                "POST",
+                "/v1/reviewSubmissionItems",
+                201,
+                fixtures::document(fixtures::review_submission_item(
+                    "I1",
+                    "READY_FOR_REVIEW",
+                    "V3",
+                )),
+            )
+            .start()?;
+        let client = server.client()?;
+
+        let status = client.train_status("A1", &config, today)?;
+        assert_eq!(status.phase, TrainPhase::Idle);
+        assert_eq!(status.released_version.as_deref(), Some("1.10.0"));
+        assert_eq!(status.next_version, "1.11.0");
+        assert_eq!(status.next_release_date, "2022-01-24");
+
+        let start = client.start_train("A1", &config, today)?;
+        assert!(start.created);
+        assert_eq!(start.version.id, "V3");
+        assert_eq!(start.review_submission.map(|s| s.id), Some("R1".into()));
+
+        let requests = server.requests();
+        let create = requests
+            .iter()
+            .find(|r| r.method == "POST" && r.path == "/v1/appStoreVersions")
+            .unwrap()
+            .json()?;
+        assert_eq!(create["data"]["attributes"]["versionString"], "1.11.0");
+        assert_eq!(
+            create["data"]["attributes"]["earliestReleaseDate"],
+            "2022-01-24T16:00:00+00:00"
+        );
+        assert!(requests
+            .iter()
+            .any(|r| r.method == "POST" && r.path == "/v1/reviewSubmissionItems"));
+
+        Ok(())
+    }
+}
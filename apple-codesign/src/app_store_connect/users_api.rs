@@ -0,0 +1,20 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Users of the team.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi/users>.
+
+pub use asconnect_types::users::*;
+
+use crate::{app_store_connect::AppStoreConnectClient, AppleCodesignError};
+
+impl AppStoreConnectClient {
+    /// List all users of the team.
+    ///
+    /// This requires an API key with the Admin role.
+    pub fn list_users(&self) -> Result<Vec<User>, AppleCodesignError> {
+        self.api_get_all("/v1/users", &[])
+    }
+}
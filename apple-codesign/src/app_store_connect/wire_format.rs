@@ -0,0 +1,394 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests guarding the wire format of the App Store Connect API.
+//!
+//! Request bodies sent by [AppStoreConnectClient] methods are captured by a
+//! [MockServer] and compared against golden files in
+//! `src/testdata/asc-requests`. Set `APPLE_CODESIGN_UPDATE_GOLDEN=1` to rewrite
+//! the golden files after an intentional change.
+//!
+//! Responses are deserialized after randomly injecting members the API may add
+//! in the future, such as unknown attributes and enum values, which must not
+//! cause errors.
+
+use {
+    crate::{
+        app_store_connect::{
+            build_api::{Build, BuildAttributes},
+            build_upload_api::{
+                BuildUpload, BuildUploadAttributes, BuildUploadFile, BuildUploadFileAttributes,
+                Platform,
+            },
+            certs_api::{
+                Certificate, CertificateAttributes, Device, DeviceAttributes, DeviceStatus,
+                Profile, ProfileAttributes, ProfileType,
+            },
+            json_api::{Document, Resource},
+            testflight_api::{BuildBetaDetail, BuildBetaDetailAttributes},
+            testing::{fixtures, MockResponse, MockServer, MockServerBuilder},
+            AppStoreConnectClient,
+        },
+        AppleCodesignError,
+    },
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    serde::{de::DeserializeOwned, Serialize},
+    serde_json::{json, Value},
+    std::path::PathBuf,
+};
+
+/// Number of mutated variants of each response to deserialize.
+const FUZZ_ITERATIONS: usize = 256;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("testdata")
+        .join("asc-requests")
+        .join(format!("{}.json", name))
+}
+
+/// Replace the values of members that vary between runs.
+fn normalize(value: &mut Value, members: &[&str]) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if members.contains(&k.as_str()) {
+                    *v = Value::String(format!("<{}>", k));
+                } else {
+                    normalize(v, members);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for v in values {
+                normalize(v, members);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Compare the request bodies received by a server against a golden file.
+///
+/// The golden file holds an array of `{"request": "<method> <path>", "body": ...}`
+/// objects for requests having a JSON body. Values of `volatile` members are
+/// replaced by placeholders.
+fn assert_golden(name: &str, server: &MockServer, volatile: &[&str]) {
+    let mut actual = server
+        .requests()
+        .into_iter()
+        .filter(|r| {
+            r.header("content-type")
+                .is_some_and(|v| v.starts_with("application/json"))
+        })
+        .map(|r| {
+            json!({
+                "request": format!("{} {}", r.method, r.path),
+                "body": r.json().expect("request body should be JSON"),
+            })
+        })
+        .collect::<Value>();
+    normalize(&mut actual, volatile);
+
+    let path = golden_path(name);
+
+    if std::env::var_os("APPLE_CODESIGN_UPDATE_GOLDEN").is_some() {
+        let mut data = serde_json::to_string_pretty(&actual).unwrap();
+        data.push('\n');
+        std::fs::write(&path, data).unwrap();
+        return;
+    }
+
+    let expected = serde_json::from_slice::<Value>(
+        &std::fs::read(&path).unwrap_or_else(|e| panic!("error reading {}: {}", path.display(), e)),
+    )
+    .unwrap();
+
+    assert_eq!(
+        actual,
+        expected,
+        "request bodies differ from {}; got:\n{}",
+        path.display(),
+        serde_json::to_string_pretty(&actual).unwrap()
+    );
+}
+
+fn client(server: &MockServer) -> AppStoreConnectClient {
+    server.client().unwrap()
+}
+
+#[test]
+fn golden_create_profile() -> Result<(), AppleCodesignError> {
+    let server = MockServerBuilder::default()
+        .json(
+            "POST",
+            "/v1/profiles",
+            201,
+            fixtures::document(fixtures::profile(
+                "P1",
+                "Example",
+                "IOS_APP_DEVELOPMENT",
+                "",
+            )),
+        )
+        .start()?;
+
+    client(&server).create_profile(
+        "Example",
+        ProfileType::IosAppDevelopment,
+        "BUNDLE1",
+        &["C1".to_string(), "C2".to_string()],
+        &["D1".to_string()],
+    )?;
+
+    assert_golden("create-profile", &server, &[]);
+
+    Ok(())
+}
+
+#[test]
+fn golden_update_device() -> Result<(), AppleCodesignError> {
+    let server = MockServerBuilder::default()
+        .json(
+            "GET",
+            "/v1/devices/D1",
+            200,
+            fixtures::document(fixtures::device("D1", "00008030-0000000000000000")),
+        )
+        .json(
+            "PATCH",
+            "/v1/devices/D1",
+            200,
+            fixtures::document(fixtures::device("D1", "00008030-0000000000000000")),
+        )
+        .start()?;
+
+    client(&server).update_device("D1", Some("Renamed".into()), Some(DeviceStatus::Disabled))?;
+
+    assert_golden("update-device", &server, &[]);
+
+    Ok(())
+}
+
+#[test]
+fn golden_update_build() -> Result<(), AppleCodesignError> {
+    let server = MockServerBuilder::default()
+        .json(
+            "PATCH",
+            "/v1/builds/B1",
+            200,
+            fixtures::document(fixtures::build("B1", "1", "VALID")),
+        )
+        .start()?;
+
+    client(&server).set_build_uses_non_exempt_encryption("B1", false)?;
+
+    assert_golden("update-build", &server, &[]);
+
+    Ok(())
+}
+
+#[test]
+fn golden_build_delivery() -> Result<(), AppleCodesignError> {
+    let data = fixtures::ipa("com.example.app", "1.2.0", "42");
+
+    let server = MockServerBuilder::default()
+        .json(
+            "POST",
+            "/v1/buildUploads",
+            201,
+            fixtures::document(fixtures::build_upload("U1", "AWAITING_UPLOAD")),
+        )
+        .start()?;
+
+    let upload_file = fixtures::document(fixtures::build_upload_file(
+        "F1",
+        data.len() as u64,
+        &format!("{}/upload", server.url()),
+    ));
+    server.add_json("POST", "/v1/buildUploadFiles", 201, upload_file.clone());
+    server.add_json("PATCH", "/v1/buildUploadFiles/F1", 200, upload_file);
+    server.add_route("PUT", "/upload", MockResponse::empty(200));
+
+    client(&server).upload_ipa_data_unchecked("APP1", "Example.ipa", &data, Platform::Ios)?;
+
+    assert_eq!(
+        server.requests()[1].json()?["data"]["attributes"]["fileSize"],
+        data.len()
+    );
+
+    assert_golden(
+        "build-delivery",
+        &server,
+        &["fileSize", "sourceFileChecksum"],
+    );
+
+    Ok(())
+}
+
+/// Generate a random JSON value of limited depth.
+fn random_value(rng: &mut StdRng, depth: usize) -> Value {
+    match rng.gen_range(0..if depth == 0 { 4 } else { 6 }) {
+        0 => Value::Null,
+        1 => Value::Bool(rng.gen()),
+        2 => json!(rng.gen::<i32>()),
+        3 => Value::String(format!("VALUE_{}", rng.gen::<u16>())),
+        4 => Value::Array(
+            (0..rng.gen_range(0..3))
+                .map(|_| random_value(rng, depth - 1))
+                .collect(),
+        ),
+        _ => Value::Object(
+            (0..rng.gen_range(0..3))
+                .map(|i| (format!("field{}", i), random_value(rng, depth - 1)))
+                .collect(),
+        ),
+    }
+}
+
+/// Insert unknown members into a response document.
+///
+/// Members are added to the document, the resource, its attributes, and its
+/// relationships. Enum valued attributes named in `enums` are replaced with
+/// unknown values.
+fn mutate(rng: &mut StdRng, doc: &mut Value, enums: &[&str]) {
+    let targets: [&[&str]; 4] = [&[], &["data"], &["data", "attributes"], &["data", "meta"]];
+
+    for _ in 0..rng.gen_range(1..6) {
+        let target = targets[rng.gen_range(0..targets.len())];
+
+        let mut value = &mut *doc;
+        for key in target {
+            value = value
+                .as_object_mut()
+                .unwrap()
+                .entry(key.to_string())
+                .or_insert_with(|| json!({}));
+        }
+
+        let name = format!("unknown{}", rng.gen::<u16>());
+        let member = random_value(rng, 2);
+        value.as_object_mut().unwrap().insert(name, member);
+    }
+
+    if rng.gen() {
+        doc["data"]["relationships"] = json!({
+            "unknownRelationship": {
+                "data": {"type": "unknownThings", "id": "X1"},
+                "links": {"self": "https://example.com", "unknown": true},
+            }
+        });
+    }
+
+    for name in enums {
+        if rng.gen() {
+            doc["data"]["attributes"][*name] =
+                Value::String(format!("NEW_STATE_{}", rng.gen::<u16>()));
+        }
+    }
+}
+
+/// Deserialize mutated variants of a response, verifying that known members
+/// are unaffected by unknown ones.
+fn fuzz_resource<A: std::fmt::Debug + DeserializeOwned>(
+    seed: u64,
+    resource: Value,
+    enums: &[&str],
+) {
+    let doc = fixtures::document(resource);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for _ in 0..FUZZ_ITERATIONS {
+        let mut mutated = doc.clone();
+        mutate(&mut rng, &mut mutated, enums);
+
+        let actual = serde_json::from_value::<Document<Resource<A>>>(mutated.clone())
+            .unwrap_or_else(|e| {
+                panic!(
+                    "error deserializing {}: {}",
+                    serde_json::to_string_pretty(&mutated).unwrap(),
+                    e
+                )
+            });
+
+        // Replaced enum values should become the `Unknown` variant.
+        let mut reference = doc.clone();
+        for name in enums {
+            if mutated["data"]["attributes"][*name] != doc["data"]["attributes"][*name] {
+                reference["data"]["attributes"][*name] = Value::String("UNKNOWN".into());
+            }
+        }
+        let expected = serde_json::from_value::<Document<Resource<A>>>(reference).unwrap();
+
+        assert_eq!(
+            format!("{:?}", actual.data.attributes),
+            format!("{:?}", expected.data.attributes)
+        );
+    }
+}
+
+#[test]
+fn fuzz_responses() {
+    fuzz_resource::<CertificateAttributes>(
+        1,
+        fixtures::certificate("C1", "DISTRIBUTION", ""),
+        &["certificateType"],
+    );
+    fuzz_resource::<ProfileAttributes>(
+        2,
+        fixtures::profile("P1", "Example", "IOS_APP_STORE", ""),
+        &["profileType", "profileState"],
+    );
+    fuzz_resource::<DeviceAttributes>(
+        3,
+        fixtures::device("D1", "00008030-0000000000000000"),
+        &["status"],
+    );
+    fuzz_resource::<BuildAttributes>(4, fixtures::build("B1", "1", "VALID"), &["processingState"]);
+    fuzz_resource::<BuildUploadAttributes>(5, fixtures::build_upload("U1", "COMPLETE"), &["state"]);
+    fuzz_resource::<BuildUploadFileAttributes>(
+        6,
+        fixtures::build_upload_file("F1", 1, "https://example.com/upload"),
+        &[],
+    );
+    fuzz_resource::<BuildBetaDetailAttributes>(
+        7,
+        fixtures::build_beta_detail("BD1"),
+        &["internalBuildState", "externalBuildState"],
+    );
+}
+
+/// Serializing a deserialized response and deserializing it again is lossless.
+fn assert_round_trip<T: Serialize + DeserializeOwned>(value: Value) {
+    let parsed = serde_json::from_value::<T>(value).unwrap();
+    let serialized = serde_json::to_value(&parsed).unwrap();
+    let reparsed = serde_json::from_value::<T>(serialized.clone()).unwrap();
+
+    assert_eq!(serde_json::to_value(&reparsed).unwrap(), serialized);
+}
+
+#[test]
+fn round_trip() {
+    assert_round_trip::<Document<Certificate>>(fixtures::document(fixtures::certificate(
+        "C1",
+        "DISTRIBUTION",
+        "",
+    )));
+    assert_round_trip::<Document<Vec<Profile>>>(fixtures::collection(vec![
+        fixtures::profile("P1", "Example", "IOS_APP_STORE", ""),
+        fixtures::profile("P2", "Example", "IOS_APP_ADHOC", ""),
+    ]));
+    assert_round_trip::<Document<Device>>(fixtures::document(fixtures::device("D1", "0")));
+    assert_round_trip::<Document<Build>>(fixtures::document(fixtures::build("B1", "1", "VALID")));
+    assert_round_trip::<Document<BuildUpload>>(fixtures::document(fixtures::build_upload(
+        "U1", "COMPLETE",
+    )));
+    assert_round_trip::<Document<BuildUploadFile>>(fixtures::document(
+        fixtures::build_upload_file("F1", 1, "https://example.com/upload"),
+    ));
+    assert_round_trip::<Document<BuildBetaDetail>>(fixtures::document(
+        fixtures::build_beta_detail("BD1"),
+    ));
+}
@@ -545,4 +545,43 @@ mod test {
             assert_eq!(chain.len(), apple_chain.len());
         }
     }
+
+    #[test]
+    fn issuers() {
+        assert!(KnownCertificate::AppleRootCa
+            .subject_key_identifier()
+            .is_some());
+        assert_eq!(
+            KnownCertificate::WwdrG3.authority_key_identifier(),
+            KnownCertificate::AppleRootCa.subject_key_identifier()
+        );
+
+        for (cert, issuer) in [
+            (KnownCertificate::WwdrG3, KnownCertificate::AppleRootCa),
+            (KnownCertificate::WwdrG4, KnownCertificate::AppleRootCa),
+            (
+                KnownCertificate::WwdrG6,
+                KnownCertificate::AppleRootCaG3Root,
+            ),
+            (
+                KnownCertificate::DeveloperIdG1,
+                KnownCertificate::AppleRootCa,
+            ),
+            (
+                KnownCertificate::DeveloperIdG2,
+                KnownCertificate::AppleRootCa,
+            ),
+        ] {
+            assert_eq!(cert.apple_issuer(), Some(issuer), "issuer of {:?}", cert);
+            assert_eq!(
+                cert.apple_root_certificate_chain(),
+                Some(vec![(*cert).clone(), (*issuer).clone()])
+            );
+        }
+
+        assert_eq!(
+            KnownCertificate::AppleRootCa.apple_issuer(),
+            Some(KnownCertificate::AppleRootCa)
+        );
+    }
 }
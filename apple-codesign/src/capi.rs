@@ -0,0 +1,377 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! C ABI for embedding App Store Connect workflows in other build systems.
+//!
+//! This module is available with the `capi` feature. Build a shared or static
+//! library with e.g.
+//! `cargo rustc -p apple-codesign --lib --release --features capi --crate-type cdylib`.
+//! The C header is `include/apple_codesign.h`, generated by cbindgen (see
+//! `cbindgen.toml`).
+//!
+//! Functions return an [AppleCodesignStatus]. On failure, a description of
+//! the error is available from [apple_codesign_last_error()]. Strings returned
+//! through out parameters must be released with [apple_codesign_string_free()].
+
+use {
+    crate::{
+        app_store_connect::{
+            build_upload_api::Platform, certs_api::DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS,
+            AppStoreConnectClient, UnifiedApiKey,
+        },
+        notarization::{NotarizationUpload, Notarizer},
+        redact::redact,
+        AppleCodesignError,
+    },
+    std::{
+        cell::RefCell,
+        ffi::{CStr, CString},
+        os::raw::c_char,
+        panic::{catch_unwind, AssertUnwindSafe},
+        path::PathBuf,
+        time::Duration,
+    },
+};
+
+/// Result of a C API call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AppleCodesignStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// An argument was NULL or not valid UTF-8.
+    InvalidArgument = 1,
+    /// The operation failed.
+    Error = 2,
+    /// The operation panicked.
+    Panic = 3,
+}
+
+/// An App Store Connect client created by [apple_codesign_client_new()].
+pub struct AppleCodesignClient {
+    key: UnifiedApiKey,
+    client: AppStoreConnectClient,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: impl ToString) {
+    let message = redact(&message.to_string()).replace('\0', " ");
+
+    LAST_ERROR.with(|e| {
+        e.replace(CString::new(message).ok());
+    });
+}
+
+enum CallError {
+    InvalidArgument(String),
+    Codesign(AppleCodesignError),
+}
+
+impl From<AppleCodesignError> for CallError {
+    fn from(e: AppleCodesignError) -> Self {
+        Self::Codesign(e)
+    }
+}
+
+/// Run the body of an exported function, recording errors and catching panics.
+///
+/// The last error is cleared first, so it only describes a failure of the
+/// most recent call.
+fn call(f: impl FnOnce() -> Result<(), CallError>) -> AppleCodesignStatus {
+    LAST_ERROR.with(|e| e.replace(None));
+
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => AppleCodesignStatus::Ok,
+        Ok(Err(CallError::InvalidArgument(message))) => {
+            set_last_error(message);
+            AppleCodesignStatus::InvalidArgument
+        }
+        Ok(Err(CallError::Codesign(e))) => {
+            set_last_error(e);
+            AppleCodesignStatus::Error
+        }
+        Err(_) => {
+            set_last_error("panic in apple-codesign");
+            AppleCodesignStatus::Panic
+        }
+    }
+}
+
+/// Resolve a string argument.
+///
+/// # Safety
+///
+/// `value` must be NULL or a NUL terminated string.
+unsafe fn str_arg<'a>(name: &str, value: *const c_char) -> Result<&'a str, CallError> {
+    if value.is_null() {
+        return Err(CallError::InvalidArgument(format!("{} is NULL", name)));
+    }
+
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|_| CallError::InvalidArgument(format!("{} is not UTF-8", name)))
+}
+
+/// Store a string in an out parameter, if one is given.
+///
+/// # Safety
+///
+/// `out` must be NULL or point to writable memory for a pointer.
+unsafe fn set_string_out(out: *mut *mut c_char, value: String) {
+    if !out.is_null() {
+        *out = CString::new(value)
+            .map(|s| s.into_raw())
+            .unwrap_or(std::ptr::null_mut());
+    }
+}
+
+/// Obtain a description of the last error on the calling thread.
+///
+/// Returns NULL if the last call on the calling thread succeeded. The string is
+/// owned by the library and valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn apple_codesign_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map(|s| s.as_ptr())
+            .unwrap_or(std::ptr::null())
+    })
+}
+
+/// Release a string returned by this library.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned through an out parameter of this
+/// library that hasn't been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn apple_codesign_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Create a client from the content of a unified API key JSON file.
+///
+/// Such files are produced by `rcodesign encode-app-store-connect-api-key`.
+/// On success, the client is stored in `client_out` and must be released with
+/// [apple_codesign_client_free()].
+///
+/// # Safety
+///
+/// `api_key_json` must be a NUL terminated string. `client_out` must point to
+/// writable memory for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn apple_codesign_client_new(
+    api_key_json: *const c_char,
+    client_out: *mut *mut AppleCodesignClient,
+) -> AppleCodesignStatus {
+    call(|| {
+        if client_out.is_null() {
+            return Err(CallError::InvalidArgument("client_out is NULL".into()));
+        }
+
+        let key = UnifiedApiKey::from_json(str_arg("api_key_json", api_key_json)?)?;
+        let client = AppStoreConnectClient::new(key.clone().try_into()?)?;
+
+        *client_out = Box::into_raw(Box::new(AppleCodesignClient { key, client }));
+
+        Ok(())
+    })
+}
+
+/// Release a client created by [apple_codesign_client_new()].
+///
+/// # Safety
+///
+/// `client` must be NULL or a client that hasn't been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn apple_codesign_client_free(client: *mut AppleCodesignClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Resolve a client argument.
+///
+/// # Safety
+///
+/// `client` must be NULL or a live client.
+unsafe fn client_arg<'a>(
+    client: *const AppleCodesignClient,
+) -> Result<&'a AppleCodesignClient, CallError> {
+    client
+        .as_ref()
+        .ok_or_else(|| CallError::InvalidArgument("client is NULL".into()))
+}
+
+/// Upload an `.ipa` file for an app.
+///
+/// `platform` is one of `IOS`, `MAC_OS`, `TV_OS`, or `VISION_OS`. On success,
+/// the ID of the build upload is stored in `upload_id_out`, if not NULL.
+///
+/// # Safety
+///
+/// String arguments must be NUL terminated strings. `upload_id_out` must be
+/// NULL or point to writable memory for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn apple_codesign_upload_ipa(
+    client: *const AppleCodesignClient,
+    app_id: *const c_char,
+    ipa_path: *const c_char,
+    platform: *const c_char,
+    upload_id_out: *mut *mut c_char,
+) -> AppleCodesignStatus {
+    call(|| {
+        let client = client_arg(client)?;
+        let app_id = str_arg("app_id", app_id)?;
+        let ipa_path = PathBuf::from(str_arg("ipa_path", ipa_path)?);
+        let platform = str_arg("platform", platform)?;
+
+        let platform = serde_json::from_value::<Platform>(platform.into())
+            .map_err(|_| CallError::InvalidArgument(format!("invalid platform: {}", platform)))?;
+
+        let upload = client.client.upload_ipa(app_id, &ipa_path, platform)?;
+        set_string_out(upload_id_out, upload.id);
+
+        Ok(())
+    })
+}
+
+/// Notarize a bundle, `.dmg`, or `.pkg`.
+///
+/// If `wait_seconds` is non-zero, waits up to that long for notarization to
+/// finish and fails if notarization failed. On success, the ID of the
+/// submission is stored in `submission_id_out`, if not NULL.
+///
+/// # Safety
+///
+/// `path` must be a NUL terminated string. `submission_id_out` must be NULL or
+/// point to writable memory for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn apple_codesign_notarize_path(
+    client: *const AppleCodesignClient,
+    path: *const c_char,
+    wait_seconds: u64,
+    submission_id_out: *mut *mut c_char,
+) -> AppleCodesignStatus {
+    call(|| {
+        let client = client_arg(client)?;
+        let path = PathBuf::from(str_arg("path", path)?);
+
+        let mut notarizer = Notarizer::new()?;
+        notarizer.set_token_encoder(client.key.clone().try_into()?);
+
+        let wait_limit = if wait_seconds > 0 {
+            Some(Duration::from_secs(wait_seconds))
+        } else {
+            None
+        };
+
+        let id = match notarizer.notarize_path(&path, wait_limit)? {
+            NotarizationUpload::UploadId(id) => id,
+            NotarizationUpload::NotaryResponse(response) => response.data.id,
+        };
+        set_string_out(submission_id_out, id);
+
+        Ok(())
+    })
+}
+
+/// Write the `.mobileprovision` file of a provisioning profile to `output_path`.
+///
+/// # Safety
+///
+/// String arguments must be NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn apple_codesign_fetch_profile(
+    client: *const AppleCodesignClient,
+    profile_id: *const c_char,
+    output_path: *const c_char,
+) -> AppleCodesignStatus {
+    call(|| {
+        let client = client_arg(client)?;
+        let profile_id = str_arg("profile_id", profile_id)?;
+        let output_path = PathBuf::from(str_arg("output_path", output_path)?);
+
+        let profile = client
+            .client
+            .download_profile(profile_id, DEFAULT_CERTIFICATE_EXPIRY_WARNING_DAYS)?;
+        std::fs::write(&output_path, profile.data).map_err(AppleCodesignError::from)?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(apple_codesign_last_error()) }
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let mut client = std::ptr::null_mut();
+
+        let status = unsafe { apple_codesign_client_new(std::ptr::null(), &mut client) };
+        assert_eq!(status, AppleCodesignStatus::InvalidArgument);
+        assert_eq!(last_error(), "api_key_json is NULL");
+        assert!(client.is_null());
+
+        let json = CString::new("{}").unwrap();
+        let status = unsafe { apple_codesign_client_new(json.as_ptr(), &mut client) };
+        assert_eq!(status, AppleCodesignStatus::Error);
+        assert!(client.is_null());
+
+        let path = CString::new("app.dmg").unwrap();
+        let status = unsafe {
+            apple_codesign_notarize_path(std::ptr::null(), path.as_ptr(), 0, std::ptr::null_mut())
+        };
+        assert_eq!(status, AppleCodesignStatus::InvalidArgument);
+        assert_eq!(last_error(), "client is NULL");
+
+        unsafe {
+            apple_codesign_client_free(std::ptr::null_mut());
+            apple_codesign_string_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn last_error_cleared() {
+        let mut client = std::ptr::null_mut();
+
+        let status = unsafe { apple_codesign_client_new(std::ptr::null(), &mut client) };
+        assert_eq!(status, AppleCodesignStatus::InvalidArgument);
+        assert!(!apple_codesign_last_error().is_null());
+
+        let key = ring::signature::EcdsaKeyPair::generate_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &ring::rand::SystemRandom::new(),
+        )
+        .unwrap();
+        let json = CString::new(
+            serde_json::json!({
+                "issuer_id": "00000000-0000-0000-0000-000000000000",
+                "key_id": "MOCKKEY123",
+                "private_key": base64::encode(key.as_ref()),
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let status = unsafe { apple_codesign_client_new(json.as_ptr(), &mut client) };
+        assert_eq!(status, AppleCodesignStatus::Ok);
+        assert!(apple_codesign_last_error().is_null());
+
+        unsafe { apple_codesign_client_free(client) };
+    }
+}
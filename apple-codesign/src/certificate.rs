@@ -7,8 +7,9 @@
 use {
     crate::{apple_certificates::KnownCertificate, error::AppleCodesignError},
     bcder::{
+        decode::Constructed,
         encode::{PrimitiveContent, Values},
-        ConstOid, Oid,
+        ConstOid, Mode, OctetString, Oid, Tag,
     },
     bytes::Bytes,
     std::{
@@ -21,6 +22,16 @@ use {
     },
 };
 
+/// Subject Key Identifier extension.
+///
+/// 2.5.29.14
+const OID_SUBJECT_KEY_IDENTIFIER: ConstOid = Oid(&[85, 29, 14]);
+
+/// Authority Key Identifier extension.
+///
+/// 2.5.29.35
+const OID_AUTHORITY_KEY_IDENTIFIER: ConstOid = Oid(&[85, 29, 35]);
+
 /// Extended Key Usage extension.
 ///
 /// 2.5.29.37
@@ -805,6 +816,51 @@ impl FromStr for CertificateProfile {
     }
 }
 
+/// Obtain the raw value of the first extension with the given OID.
+fn find_extension_value(cert: &CapturedX509Certificate, oid: &ConstOid) -> Option<Bytes> {
+    let cert: &x509_certificate::rfc5280::Certificate = cert.as_ref();
+
+    cert.iter_extensions()
+        .find(|extension| extension.id.as_ref() == oid.as_ref())
+        .map(|extension| extension.value.to_bytes())
+}
+
+/// Find the known Apple certificate issuing a certificate, ignoring `exclude`.
+///
+/// See [AppleCertificate::apple_issuer] for how candidates are selected.
+fn find_apple_issuer(
+    cert: &CapturedX509Certificate,
+    exclude: &[&CapturedX509Certificate],
+) -> Option<&'static CapturedX509Certificate> {
+    let authority_key_id = cert.authority_key_identifier();
+
+    KnownCertificate::all()
+        .iter()
+        .copied()
+        .filter(|candidate| !exclude.contains(candidate))
+        .filter(|candidate| cert.verify_signed_by_certificate(*candidate).is_ok())
+        .max_by_key(|candidate| {
+            let key_id_matches = authority_key_id.is_some()
+                && candidate.subject_key_identifier() == authority_key_id;
+            let name_matches = candidate.subject_name() == cert.issuer_name();
+
+            (key_id_matches, name_matches, candidate.validity_not_after())
+        })
+}
+
+/// Resolve the chain of known Apple certificates issuing a certificate.
+fn resolve_apple_issuers(cert: &CapturedX509Certificate) -> Vec<&'static CapturedX509Certificate> {
+    let mut chain: Vec<&'static CapturedX509Certificate> = vec![];
+    let mut last = cert;
+
+    while let Some(issuer) = find_apple_issuer(last, &chain) {
+        chain.push(issuer);
+        last = issuer;
+    }
+
+    chain
+}
+
 /// Extends functionality of [CapturedX509Certificate] with Apple specific certificate knowledge.
 pub trait AppleCertificate: Sized {
     /// Whether this is a known Apple root certificate authority.
@@ -845,11 +901,32 @@ pub trait AppleCertificate: Sized {
     /// this extension.
     fn apple_guess_profile(&self) -> Option<CertificateProfile>;
 
+    /// Obtain the key identifier of the Subject Key Identifier extension.
+    fn subject_key_identifier(&self) -> Option<Bytes>;
+
+    /// Obtain the key identifier of the Authority Key Identifier extension.
+    ///
+    /// This is the Subject Key Identifier of the issuing certificate.
+    fn authority_key_identifier(&self) -> Option<Bytes>;
+
+    /// Find the known Apple certificate that issued this certificate.
+    ///
+    /// Candidates are known certificates whose key verifies the signature of
+    /// this certificate. Apple has reissued some of its intermediates with the
+    /// same key (e.g. the WWDR and Developer ID CAs), so several candidates
+    /// may verify. Those whose Subject Key Identifier matches our Authority
+    /// Key Identifier and whose subject matches our issuer are preferred,
+    /// followed by the one expiring last.
+    ///
+    /// Self-signed roots are their own issuer.
+    fn apple_issuer(&self) -> Option<KnownCertificate>;
+
     /// Attempt to resolve the certificate issuer chain back to [AppleCertificate].
     ///
-    /// This is a glorified wrapper around [CapturedX509Certificate::resolve_signing_chain]
-    /// that filters matches against certificates in our known set of Apple
-    /// certificates and maps them back to our [KnownCertificate] Rust enumeration.
+    /// The chain is resolved by repeatedly finding the issuer as described by
+    /// [Self::apple_issuer], never visiting a certificate twice. So leaf
+    /// certificates of different types resolve through the intermediate that
+    /// actually issued them (e.g. WWDR G3, G4, or G6 or Developer ID).
     ///
     /// False negatives (read: missing certificates) can be encountered if
     /// we don't know about an Apple CA certificate.
@@ -965,8 +1042,42 @@ impl AppleCertificate for CapturedX509Certificate {
         }
     }
 
+    fn subject_key_identifier(&self) -> Option<Bytes> {
+        let value = find_extension_value(self, &OID_SUBJECT_KEY_IDENTIFIER)?;
+
+        Constructed::decode(value.as_ref(), Mode::Der, |cons| {
+            OctetString::take_from(cons)
+        })
+        .ok()
+        .map(|id| id.to_bytes())
+    }
+
+    fn authority_key_identifier(&self) -> Option<Bytes> {
+        let value = find_extension_value(self, &OID_AUTHORITY_KEY_IDENTIFIER)?;
+
+        // AuthorityKeyIdentifier ::= SEQUENCE {
+        //   keyIdentifier             [0] KeyIdentifier           OPTIONAL,
+        //   authorityCertIssuer       [1] GeneralNames            OPTIONAL,
+        //   authorityCertSerialNumber [2] CertificateSerialNumber OPTIONAL  }
+        Constructed::decode(value.as_ref(), Mode::Der, |cons| {
+            cons.take_sequence(|cons| {
+                let id = cons.take_opt_value_if(Tag::CTX_0, OctetString::from_content)?;
+                cons.skip_all()?;
+
+                Ok(id)
+            })
+        })
+        .ok()
+        .flatten()
+        .map(|id| id.to_bytes())
+    }
+
+    fn apple_issuer(&self) -> Option<KnownCertificate> {
+        find_apple_issuer(self, &[])
+    }
+
     fn apple_issuing_chain(&self) -> Vec<KnownCertificate> {
-        self.resolve_signing_chain(KnownCertificate::all().iter().copied())
+        resolve_apple_issuers(self)
             .into_iter()
             .filter_map(|cert| KnownCertificate::try_from(cert).ok())
             .collect::<Vec<_>>()
@@ -976,7 +1087,7 @@ impl AppleCertificate for CapturedX509Certificate {
         if self.is_apple_root_ca() {
             true
         } else {
-            self.resolve_signing_chain(KnownCertificate::all().iter().copied())
+            resolve_apple_issuers(self)
                 .into_iter()
                 .any(|cert| cert.is_apple_root_ca())
         }
@@ -985,7 +1096,7 @@ impl AppleCertificate for CapturedX509Certificate {
     fn apple_root_certificate_chain(&self) -> Option<Vec<CapturedX509Certificate>> {
         let mut chain = vec![self.clone()];
 
-        for cert in self.resolve_signing_chain(KnownCertificate::all().iter().copied()) {
+        for cert in resolve_apple_issuers(self) {
             chain.push(cert.clone());
 
             if cert.is_apple_root_ca() {
@@ -1655,6 +1766,7 @@ mod tests {
             cert.apple_guess_profile(),
             Some(CertificateProfile::DeveloperIdApplication)
         );
+        assert_eq!(cert.apple_issuer(), Some(KnownCertificate::DeveloperIdG1));
         assert_eq!(
             cert.apple_issuing_chain(),
             vec![
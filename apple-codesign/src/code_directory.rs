@@ -661,6 +661,10 @@ impl<'a> CodeDirectoryBlob<'a> {
                         CodeDirectoryVersion::Initial
                     }
                 }
+                // visionOS postdates the modern code signature format.
+                Platform::VisionOs | Platform::VisionOsSimulator => {
+                    CodeDirectoryVersion::SupportsExecutableSegment
+                }
                 // Let's bump the minimum version for macOS 12 out of principle.
                 Platform::MacOs => {
                     if target.minimum_os_version >= Version::new(12, 0, 0) {
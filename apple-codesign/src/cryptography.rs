@@ -23,8 +23,9 @@ use {
     pkcs8::{AlgorithmIdentifier, EncodePrivateKey, ObjectIdentifier, PrivateKeyInfo},
     ring::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair},
     rsa::{
-        algorithms::mgf1_xor, pkcs1::DecodeRsaPrivateKey, BigUint, PaddingScheme,
-        RsaPrivateKey as RsaConstructedKey,
+        algorithms::mgf1_xor,
+        pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey},
+        BigUint, PaddingScheme, RsaPrivateKey as RsaConstructedKey,
     },
     signature::Signer,
     subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption},
@@ -451,6 +452,19 @@ impl InMemoryPrivateKey {
         Ok(Self::Rsa(key))
     }
 
+    /// Generate a new RSA private key with a modulus of `bits` bits.
+    pub fn generate_rsa(bits: usize) -> Result<Self, AppleCodesignError> {
+        let key = RsaConstructedKey::new(&mut rand::thread_rng(), bits).map_err(|e| {
+            AppleCodesignError::CertificateGeneric(format!("error generating RSA key: {}", e))
+        })?;
+
+        let der = key.to_pkcs1_der().map_err(|e| {
+            AppleCodesignError::CertificateGeneric(format!("when encoding RSA key: {}", e))
+        })?;
+
+        Self::from_pkcs1_der(der.as_bytes())
+    }
+
     /// Construct an instance by parsing PKCS#8 DER data.
     pub fn from_pkcs8_der(data: impl AsRef<[u8]>) -> Result<Self, AppleCodesignError> {
         let pki = PrivateKeyInfo::try_from(data.as_ref()).map_err(|e| {
@@ -592,6 +606,32 @@ pub fn parse_pfx_data(
     }
 }
 
+/// Create PFX data holding a certificate and its private key.
+///
+/// This is the inverse of [parse_pfx_data()]. The key is encrypted with the
+/// password, which may be the empty string.
+pub fn create_pfx_data(
+    certificate: &CapturedX509Certificate,
+    key: &InMemoryPrivateKey,
+    password: &str,
+    name: &str,
+) -> Result<Vec<u8>, AppleCodesignError> {
+    let key_der = key.to_pkcs8_der().map_err(|e| {
+        AppleCodesignError::CertificateGeneric(format!("when encoding PKCS#8 data: {}", e))
+    })?;
+
+    let pfx = p12::PFX::new(
+        certificate.constructed_data(),
+        key_der.as_bytes(),
+        None,
+        password,
+        name,
+    )
+    .ok_or_else(|| AppleCodesignError::PfxParseError("error creating PFX data".into()))?;
+
+    Ok(pfx.to_der())
+}
+
 /// RSA OAEP post decrypt depadding.
 ///
 /// This implements the procedure described by RFC 3447 Section 7.1.2
@@ -686,6 +726,27 @@ mod test {
         parse_pfx_data(data, "password123").unwrap();
     }
 
+    #[test]
+    fn create_pfx() -> Result<(), AppleCodesignError> {
+        let data = include_bytes!("apple-codesign-testuser.p12");
+        let (cert, key) = parse_pfx_data(data, "password123")?;
+
+        let pfx = create_pfx_data(&cert, &key, "secret", "test")?;
+        assert!(matches!(
+            parse_pfx_data(&pfx, "password123"),
+            Err(AppleCodesignError::PfxBadPassword)
+        ));
+
+        let (cert2, key2) = parse_pfx_data(&pfx, "secret")?;
+        assert_eq!(cert2.constructed_data(), cert.constructed_data());
+        assert_eq!(
+            key2.to_pkcs8_der().unwrap().as_bytes(),
+            key.to_pkcs8_der().unwrap().as_bytes()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn rsa_key_operations() -> Result<(), AppleCodesignError> {
         let ring_key = RsaKeyPair::from_pkcs8(RSA_2048_PKCS8_DER).unwrap();
@@ -4,6 +4,7 @@
 
 use {
     crate::{
+        app_store_connect::api_error::ApiError,
         macho_universal::UniversalMachOError,
         remote_signing::RemoteSignError
     },
@@ -22,6 +23,9 @@ pub enum AppleCodesignError {
     #[error("bad argument")]
     CliBadArgument,
 
+    #[error("secret input error: {0}")]
+    SecretInput(String),
+
     #[error("{0}")]
     CliGeneralError(String),
 
@@ -52,6 +56,10 @@ pub enum AppleCodesignError {
     #[error("YAML serialization error: {0}")]
     SerdeYaml(#[from] serde_yaml::Error),
 
+    #[cfg(feature = "cli")]
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
     #[error("glob error: {0}")]
     GlobPattern(#[from] glob::PatternError),
 
@@ -184,7 +192,7 @@ pub enum AppleCodesignError {
     #[error("invalid builder operation: {0}")]
     SignatureBuilder(&'static str),
 
-    #[error("HTTP error: {0}")]
+    #[error("HTTP error: {}", crate::redact::redact(&.0.to_string()))]
     Reqwest(#[from] reqwest::Error),
 
     #[error("unknown digest algorithm")]
@@ -268,12 +276,150 @@ pub enum AppleCodesignError {
     #[error("signing XAR archives requires a signing certificate")]
     XarNoAdhoc,
 
+    #[error("App Store Connect API error: {0}")]
+    AppStoreConnectApi(ApiError),
+
     #[error("App Store Connect API Key error: {0}")]
     AppStoreConnectApiKey(String),
 
     #[error("Could not find App Store Connect API key in default search locations")]
     AppStoreConnectApiKeyNotFound,
 
+    #[error("API key store error: {0}")]
+    ApiKeyStore(String),
+
+    #[error("no authentication credentials to perform App Store Connect API request")]
+    AppStoreConnectNoAuthCredentials,
+
+    #[error("App Store Connect asset upload error: {0}")]
+    AppStoreConnectUpload(String),
+
+    #[error("pipeline error: {0}")]
+    PipelineConfig(String),
+
+    #[error("release train error: {0}")]
+    TrainConfig(String),
+
+    #[error("notification config error: {0}")]
+    NotificationConfig(String),
+
+    #[error("device model data error: {0}")]
+    DeviceModels(String),
+
+    #[error("API Key profile error: {0}")]
+    KeyProfiles(String),
+
+    #[error("account snapshot error: {0}")]
+    AccountSnapshot(String),
+
+    #[error("{0} can't be answered offline from the account snapshot")]
+    AppStoreConnectOffline(String),
+
+    #[error("invalid resource URN {0}; expected asc://team/<team ID>/<kind>/<ID>")]
+    ResourceUrn(String),
+
+    #[error("invalid UDID {0}: {1}")]
+    InvalidUdid(String, String),
+
+    #[error("invalid device list: {0}")]
+    DeviceList(String),
+
+    #[error("invalid bundle identifier {0}: {1}")]
+    InvalidBundleIdentifier(String, String),
+
+    #[error("unable to determine the team ID: {0}")]
+    TeamIdUnknown(String),
+
+    #[error("key routing error: {0}")]
+    KeyRouting(String),
+
+    #[error("refusing {0} {1}: the App Store Connect client is read-only")]
+    AppStoreConnectReadOnly(String, String),
+
+    #[error("refusing to send credentials to {0}: not an App Store Connect API URL")]
+    AppStoreConnectForeignUrl(String),
+
+    #[error("the App Store Connect API doesn't accept Apple ID sessions; use an API Key")]
+    AppStoreConnectAppleIdSession,
+
+    #[error("{0} isn't served by the {1}")]
+    AppStoreConnectUnsupportedResource(String, String),
+
+    #[error("App Store Connect account has issues requiring attention")]
+    AppStoreConnectAccountUnhealthy,
+
+    #[error("smoke test failed for {0} of {1} areas")]
+    AppStoreConnectSmokeTestFailed(usize, usize),
+
+    #[error("local signing identities or provisioning profiles are stale")]
+    LocalInventoryStale,
+
+    #[error("invalid assets manifest: {0}")]
+    AssetsManifest(String),
+
+    #[error("artifact signature error: {0}")]
+    ArtifactSignature(String),
+
+    #[error("authentication failed and the local clock differs from the App Store Connect server clock by {0} seconds; correct the system clock or enable clock skew correction")]
+    AppStoreConnectClockSkew(i64),
+
+    #[error("token lifetime of {0} seconds is outside the accepted range of 1 to {1} seconds")]
+    AppStoreConnectTokenDuration(u64, u64),
+
+    #[error("{0} queued operations remain")]
+    AppStoreConnectQueuePending(usize),
+
+    #[error("operation failed for {0} of {1} items")]
+    BatchPartiallyFailed(usize, usize),
+
+    #[error("operation failed for all {0} items")]
+    BatchFailed(usize),
+
+    #[error("skipped after an earlier error: {0}")]
+    BatchItemSkipped(String),
+
+    #[error("App Store Connect RPC error: {0}")]
+    AppStoreConnectRpc(String),
+
+    #[error("operation deadline exceeded")]
+    AppStoreConnectDeadlineExceeded,
+
+    #[error("App Store Connect API Key cannot access: {0}")]
+    AppStoreConnectPermissionsMissing(String),
+
+    #[error("invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("invalid price: {0}")]
+    InvalidPrice(String),
+
+    #[error("locale {0} is not accepted by App Store Connect{1}")]
+    InvalidLocale(String, String),
+
+    #[error("fallback locale {0} has no localization")]
+    LocaleFallback(String),
+
+    #[error("{0}")]
+    AccountTypeMismatch(String),
+
+    #[error("malformed .ipa: {0}")]
+    IpaMalformed(String),
+
+    #[error(".ipa failed pre-flight validation: {0}")]
+    IpaPreflightFailed(String),
+
+    #[error("Developer ID certificate limit reached: {0}")]
+    DeveloperIdCertificateLimit(String),
+
+    #[error("installer package failed verification: {0}")]
+    InstallerPackageInvalid(String),
+
+    #[error("processing of build {0} failed: {1}")]
+    BuildProcessingFailed(String, String),
+
+    #[error("reached time limit waiting for build processing to complete")]
+    BuildProcessingWaitLimitReached,
+
     #[error("do not know how to notarize {0}")]
     NotarizeUnsupportedPath(PathBuf),
 
@@ -295,6 +441,9 @@ pub enum AppleCodesignError {
     #[error("notarization package is invalid")]
     NotarizeInvalid,
 
+    #[error("notarization failed for {0} of {1} artifacts")]
+    NotarizeArtifactsFailed(usize, usize),
+
     #[error("notarization record not in response: {0}")]
     NotarizationRecordNotInResponse(String),
 
@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Localization of command line output.
+//!
+//! Messages are [Fluent](https://projectfluent.org/) resources in the
+//! `locales` directory, compiled into the binary. The locale is set with
+//! `--locale` or derived from the `LC_ALL`, `LC_MESSAGES`, and `LANG`
+//! environment variables. Messages missing from a locale fall back to English.
+//!
+//! Log messages are not localized.
+
+use {
+    fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource},
+    once_cell::sync::OnceCell,
+    unic_langid::LanguageIdentifier,
+};
+
+/// The locale whose resource defines every message.
+const FALLBACK_LOCALE: &str = "en-US";
+
+/// Available locales and their Fluent resources.
+const RESOURCES: &[(&str, &str)] = &[
+    ("de", include_str!("../locales/de.ftl")),
+    ("en-US", include_str!("../locales/en-US.ftl")),
+    ("ja", include_str!("../locales/ja.ftl")),
+];
+
+static LOCALIZER: OnceCell<Localizer> = OnceCell::new();
+
+/// Resolves message IDs to messages of a locale.
+pub struct Localizer {
+    /// Bundles consulted in order. The last is the fallback locale.
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Localizer {
+    /// Construct an instance for a locale name.
+    ///
+    /// POSIX locale names like `de_DE.UTF-8` are accepted. Locales are matched
+    /// by language, so `de-AT` uses the `de` messages.
+    pub fn new(locale: &str) -> Self {
+        let mut bundles = vec![];
+
+        if let Some(id) = negotiate(locale) {
+            if id != FALLBACK_LOCALE {
+                bundles.push(bundle(id));
+            }
+        }
+
+        bundles.push(bundle(FALLBACK_LOCALE));
+
+        Self { bundles }
+    }
+
+    /// The locale messages are primarily resolved from.
+    pub fn locale(&self) -> String {
+        self.bundles[0].locales[0].to_string()
+    }
+
+    /// Resolve a message, if it is defined.
+    pub fn message(&self, id: &str, args: &[(&str, &str)]) -> Option<String> {
+        let mut fluent_args = FluentArgs::new();
+        for (k, v) in args {
+            fluent_args.set(*k, v.to_string());
+        }
+
+        self.bundles.iter().find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value()?;
+            let mut errors = vec![];
+
+            Some(
+                bundle
+                    .format_pattern(pattern, Some(&fluent_args), &mut errors)
+                    .to_string(),
+            )
+        })
+    }
+}
+
+/// Resolve the name of an available locale matching a locale name.
+fn negotiate(locale: &str) -> Option<&'static str> {
+    // de_DE.UTF-8@euro -> de-DE
+    let locale = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+
+    let requested = locale.parse::<LanguageIdentifier>().ok()?;
+
+    RESOURCES.iter().map(|(id, _)| *id).find(|id| {
+        id.parse::<LanguageIdentifier>()
+            .map(|available| available.language == requested.language)
+            .unwrap_or_default()
+    })
+}
+
+fn bundle(id: &str) -> FluentBundle<FluentResource> {
+    let source = RESOURCES
+        .iter()
+        .find(|(x, _)| *x == id)
+        .expect("locale should be available")
+        .1;
+
+    let mut bundle =
+        FluentBundle::new_concurrent(vec![id.parse().expect("locale ID should be valid")]);
+    // Unicode isolation marks around arguments show up as garbage in terminals.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(
+            FluentResource::try_new(source.to_string()).expect("Fluent resource should parse"),
+        )
+        .expect("Fluent resource should not redefine messages");
+
+    bundle
+}
+
+/// Obtain the locale name from the environment.
+pub fn locale_from_env() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Set the locale of messages.
+///
+/// Has no effect if messages were already resolved.
+pub fn init(locale: Option<&str>) {
+    let locale = locale
+        .map(|x| x.to_string())
+        .or_else(locale_from_env)
+        .unwrap_or_default();
+
+    let _ = LOCALIZER.set(Localizer::new(&locale));
+}
+
+/// Resolve a message with arguments.
+///
+/// Uses the locale from the environment if [init()] wasn't called. Resolves to
+/// the message ID if the message isn't defined.
+pub fn tr(id: &str, args: &[(&str, &str)]) -> String {
+    LOCALIZER
+        .get_or_init(|| Localizer::new(&locale_from_env().unwrap_or_default()))
+        .message(id, args)
+        .unwrap_or_else(|| id.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::app_store_connect::api_error::REMEDIATIONS};
+
+    #[test]
+    fn resources() {
+        let (_, source) = RESOURCES
+            .iter()
+            .find(|(id, _)| *id == FALLBACK_LOCALE)
+            .unwrap();
+        let fallback = FluentResource::try_new(source.to_string()).unwrap();
+        let fallback_ids = fallback
+            .entries()
+            .filter_map(|entry| match entry {
+                fluent_syntax::ast::Entry::Message(m) => Some(m.id.name),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (id, source) in RESOURCES {
+            let resource = FluentResource::try_new(source.to_string())
+                .unwrap_or_else(|_| panic!("{} should parse", id));
+
+            for entry in resource.entries() {
+                if let fluent_syntax::ast::Entry::Message(m) = entry {
+                    assert!(
+                        fallback_ids.contains(&m.id.name),
+                        "{} message {} should be defined in {}",
+                        id,
+                        m.id.name,
+                        FALLBACK_LOCALE
+                    );
+                }
+            }
+        }
+
+        let en = Localizer::new("C");
+        for r in REMEDIATIONS {
+            assert_eq!(en.message(r.hint.id, &[]).as_deref(), Some(r.hint.text));
+        }
+    }
+
+    #[test]
+    fn locale_selection() {
+        assert_eq!(Localizer::new("de_DE.UTF-8").locale(), "de");
+        assert_eq!(Localizer::new("ja-JP").locale(), "ja");
+        assert_eq!(Localizer::new("fr_FR").locale(), "en-US");
+        assert_eq!(Localizer::new("").locale(), "en-US");
+
+        let de = Localizer::new("de");
+        assert_eq!(
+            de.message("cli-error", &[("message", "x")]).as_deref(),
+            Some("Fehler: x")
+        );
+        assert_eq!(de.message("missing", &[]), None);
+    }
+}
@@ -114,8 +114,11 @@ pub use apple_certificates::*;
 pub mod app_store_connect;
 mod bundle_signing;
 pub use bundle_signing::*;
+#[cfg(feature = "capi")]
+pub mod capi;
 mod certificate;
 pub use certificate::*;
+#[cfg(feature = "cli")]
 pub mod cli;
 mod code_directory;
 pub use code_directory::*;
@@ -132,6 +135,8 @@ pub use embedded_signature_builder::*;
 pub mod entitlements;
 mod error;
 pub use error::*;
+#[cfg(feature = "cli")]
+pub mod i18n;
 mod macho;
 pub use macho::*;
 #[cfg(target_os = "macos")]
@@ -149,7 +154,9 @@ mod policy;
 pub use policy::*;
 mod reader;
 pub use reader::*;
+pub mod redact;
 pub mod remote_signing;
+pub mod secret_input;
 mod signing_settings;
 pub use signing_settings::*;
 mod signing;
@@ -544,6 +544,8 @@ pub enum Platform {
     TvOsSimulator,
     WatchOsSimulator,
     DriverKit,
+    VisionOs,
+    VisionOsSimulator,
     Unknown(u32),
 }
 
@@ -560,6 +562,8 @@ impl std::fmt::Display for Platform {
             Self::TvOsSimulator => f.write_str("tvOSSimulator"),
             Self::WatchOsSimulator => f.write_str("watchOSSimulator"),
             Self::DriverKit => f.write_str("driverKit"),
+            Self::VisionOs => f.write_str("visionOS"),
+            Self::VisionOsSimulator => f.write_str("visionOSSimulator"),
             Self::Unknown(v) => f.write_fmt(format_args!("Unknown ({})", v)),
         }
     }
@@ -578,12 +582,25 @@ impl From<u32> for Platform {
             8 => Self::TvOsSimulator,
             9 => Self::WatchOsSimulator,
             10 => Self::DriverKit,
+            11 => Self::VisionOs,
+            12 => Self::VisionOsSimulator,
             _ => Self::Unknown(v),
         }
     }
 }
 
 impl Platform {
+    /// Whether this is a simulator platform.
+    pub fn is_simulator(&self) -> bool {
+        matches!(
+            self,
+            Self::IosSimulator
+                | Self::TvOsSimulator
+                | Self::WatchOsSimulator
+                | Self::VisionOsSimulator
+        )
+    }
+
     /// Resolve SHA-256 digest/signatures support for a given platform type.
     pub fn sha256_digest_support(&self) -> Result<semver::VersionReq, AppleCodesignError> {
         let version = match self {
@@ -30,6 +30,7 @@ mod embedded_signature_builder;
 mod entitlements;
 mod error;
 pub use error::*;
+mod i18n;
 #[allow(unused)]
 mod macho;
 #[allow(unused)]
@@ -43,7 +44,10 @@ mod notarization;
 #[allow(unused)]
 mod policy;
 mod reader;
+mod redact;
 mod remote_signing;
+#[allow(unused)]
+mod secret_input;
 mod signing;
 #[allow(unused)]
 mod signing_settings;
@@ -64,8 +68,28 @@ fn main() {
     let exit_code = match cli::main_impl() {
         Ok(()) => 0,
         Err(err) => {
-            eprintln!("Error: {}", err);
-            1
+            eprintln!(
+                "{}",
+                i18n::tr(
+                    "cli-error",
+                    &[("message", &redact::redact(&err.to_string()))]
+                )
+            );
+
+            if let AppleCodesignError::AppStoreConnectApi(e) = &err {
+                for hint in e.hints() {
+                    eprintln!(
+                        "{}",
+                        i18n::tr("cli-hint", &[("hint", &i18n::tr(hint.id, &[]))])
+                    );
+                }
+            }
+
+            if let AppleCodesignError::BatchPartiallyFailed(_, _) = &err {
+                app_store_connect::batch::PARTIAL_FAILURE_EXIT_CODE
+            } else {
+                1
+            }
         }
     };
 
@@ -11,17 +11,41 @@ a fancy word for *attached*) to the content you upload.
 
 This module implements functionality for uploading content to Apple
 and waiting on the availability of a notarization ticket.
+
+Submissions are recorded in a history directory by the SHA-256 of the
+submitted content (see [Notarizer::set_history_dir()]). Content Apple
+already accepted isn't submitted again, so repeated runs on unchanged
+artifacts skip straight to stapling.
+
+Releases often consist of multiple artifacts (e.g. a DMG per architecture).
+[Notarizer::notarize_all()] uploads them concurrently and waits on all
+submissions with a single polling loop.
+
+Bundles are zipped before upload. Bundles full of already compressed assets
+(images, media, archives) gain little from deflating them again, so by
+default each file is stored or deflated depending on its extension and a
+sample of its content. See [ZipCompression]. Entries of zips don't record
+the modification times of files. With [Notarizer::set_reproducible_zips()],
+they are also ordered by name and can be dated with `SOURCE_DATE_EPOCH`, so
+identical bundles produce byte-identical zips on any machine and submission
+records are reused across machines.
 */
 
 use {
     crate::{
         app_store_connect::{
             api_token::ConnectTokenEncoder,
+            bandwidth::{throttled_byte_stream, BandwidthLimit, TokenBucket},
+            cache_scope::CacheScope,
+            http_capture::HttpCapture,
             notary_api::{
                 NewSubmissionResponse, NotaryApiClient, SubmissionResponse,
                 SubmissionResponseStatus,
             },
-            AppStoreConnectClient,
+            notify::{NotificationEvent, NotificationKind, NotificationSink},
+            progress::{state_name, ProgressEvent, ProgressSink},
+            token_cache::TokenCache,
+            AppStoreConnectClient, RequestTimeouts,
         },
         reader::PathType,
         AppleCodesignError,
@@ -29,13 +53,19 @@ use {
     apple_bundles::DirectoryBundle,
     aws_sdk_s3::{Credentials, Region},
     aws_smithy_http::byte_stream::ByteStream,
-    log::{info, warn},
+    chrono::{Datelike, Timelike},
+    log::{error, info, warn},
+    rayon::prelude::*,
+    serde::{Deserialize, Serialize},
     sha2::Digest,
     std::{
+        fmt::{Display, Formatter},
         fs::File,
         io::{Read, Seek, SeekFrom, Write},
         path::{Path, PathBuf},
-        time::Duration,
+        str::FromStr,
+        sync::Arc,
+        time::{Duration, Instant},
     },
 };
 
@@ -62,44 +92,211 @@ fn digest_sha256<R: Read>(reader: &mut R) -> Result<(u64, Vec<u8>), AppleCodesig
     digest::<sha2::Sha256, R>(reader)
 }
 
+/// Extensions of files whose content is already compressed.
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    "7z", "aac", "br", "bz2", "dmg", "gif", "gz", "heic", "heif", "ipa", "jar", "jpeg", "jpg",
+    "lz4", "lzma", "m4a", "m4v", "mov", "mp3", "mp4", "ogg", "pkg", "png", "webm", "webp", "xar",
+    "xip", "xz", "zip", "zst",
+];
+
+/// Bytes of content sampled to estimate its entropy.
+const ENTROPY_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Files smaller than this are always deflated.
+const ENTROPY_MIN_SIZE: usize = 4096;
+
+/// Entropy in bits per byte above which content is considered compressed.
+const ENTROPY_THRESHOLD: f64 = 7.5;
+
+/// How files are compressed in zips of bundles.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ZipCompression {
+    /// Store or deflate each file depending on its content.
+    ///
+    /// Files with extensions of compressed formats are stored. Larger files
+    /// whose leading bytes have an entropy close to random data are also
+    /// stored. Everything else is deflated. The choice only depends on the
+    /// file, so the same bundle always produces the same zip.
+    #[default]
+    Auto,
+
+    /// Deflate all files.
+    Deflate,
+
+    /// Store all files without compression.
+    Store,
+}
+
+impl ZipCompression {
+    /// The compression method to use for a file.
+    pub fn method_for(&self, name: &str, data: &[u8]) -> zip::CompressionMethod {
+        match self {
+            Self::Deflate => zip::CompressionMethod::Deflated,
+            Self::Store => zip::CompressionMethod::Stored,
+            Self::Auto => {
+                if is_compressed_extension(name)
+                    || (data.len() >= ENTROPY_MIN_SIZE && sample_entropy(data) > ENTROPY_THRESHOLD)
+                {
+                    zip::CompressionMethod::Stored
+                } else {
+                    zip::CompressionMethod::Deflated
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for ZipCompression {
+    type Err = AppleCodesignError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "deflate" => Ok(Self::Deflate),
+            "store" => Ok(Self::Store),
+            _ => Err(AppleCodesignError::CliGeneralError(format!(
+                "invalid zip compression {}; expected auto, deflate, or store",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for ZipCompression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Auto => "auto",
+            Self::Deflate => "deflate",
+            Self::Store => "store",
+        })
+    }
+}
+
+fn is_compressed_extension(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            COMPRESSED_EXTENSIONS
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(ext))
+        })
+        .unwrap_or_default()
+}
+
+/// Shannon entropy in bits per byte of the leading bytes of data.
+fn sample_entropy(data: &[u8]) -> f64 {
+    let sample = &data[..data.len().min(ENTROPY_SAMPLE_SIZE)];
+    if sample.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    for byte in sample {
+        counts[*byte as usize] += 1;
+    }
+
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / sample.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Convert a civil time to the representation of zip files.
+fn zip_time(t: &(impl Datelike + Timelike)) -> Option<zip::DateTime> {
+    zip::DateTime::from_date_and_time(
+        u16::try_from(t.year()).ok()?,
+        t.month() as u8,
+        t.day() as u8,
+        t.hour() as u8,
+        t.minute() as u8,
+        t.second() as u8,
+    )
+    .ok()
+}
+
+/// The modification time of entries in reproducible zips.
+///
+/// This is the time in the `SOURCE_DATE_EPOCH` environment variable, if set, or
+/// else the earliest time zip files can represent (1980-01-01), like entries
+/// of other zips.
+fn reproducible_zip_time() -> zip::DateTime {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .and_then(|seconds| chrono::NaiveDateTime::from_timestamp_opt(seconds, 0))
+        .and_then(|t| zip_time(&t))
+        .unwrap_or_default()
+}
+
 /// Produce zip file data from a [DirectoryBundle].
 ///
 /// The built zip file will contain all the files from the bundle under a directory
 /// tree having the bundle name. e.g. if you pass `MyApp.app`, the zip will have
 /// files like `MyApp.app/Contents/Info.plist`.
-pub fn bundle_to_zip(bundle: &DirectoryBundle) -> Result<Vec<u8>, AppleCodesignError> {
+///
+/// `compression` controls whether each file is stored or deflated.
+///
+/// Entries don't record the modification times of files: they all have the
+/// earliest time zip files can represent (1980-01-01). If `reproducible` is
+/// set, entries are also ordered by name and have the time in
+/// `SOURCE_DATE_EPOCH`, if set, so the zip only depends on the names, content,
+/// and executable bits of files. Permissions are always normalized to `0755`
+/// for executables and `0644` otherwise.
+pub fn bundle_to_zip(
+    bundle: &DirectoryBundle,
+    compression: ZipCompression,
+    reproducible: bool,
+) -> Result<Vec<u8>, AppleCodesignError> {
     let mut zf = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
 
     let mut symlinks = vec![];
 
-    for file in bundle
+    let mut files = bundle
         .files(true)
         .map_err(AppleCodesignError::DirectoryBundle)?
-    {
-        let entry = file
-            .as_file_entry()
-            .map_err(AppleCodesignError::DirectoryBundle)?;
+        .into_iter()
+        .map(|file| {
+            let name =
+                format!("{}/{}", bundle.name(), file.relative_path().display()).replace('\\', "/");
 
-        let name =
-            format!("{}/{}", bundle.name(), file.relative_path().display()).replace('\\', "/");
+            (name, file)
+        })
+        .collect::<Vec<_>>();
 
-        let options = zip::write::FileOptions::default();
+    let time = if reproducible {
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        reproducible_zip_time()
+    } else {
+        zip::DateTime::default()
+    };
 
-        let options = if entry.link_target().is_some() {
-            symlinks.push(name.as_bytes().to_vec());
-            options.compression_method(zip::CompressionMethod::Stored)
-        } else if entry.is_executable() {
-            options.unix_permissions(0o755)
-        } else {
-            options.unix_permissions(0o644)
-        };
+    for (name, file) in files {
+        let entry = file
+            .as_file_entry()
+            .map_err(AppleCodesignError::DirectoryBundle)?;
 
-        zf.start_file(name, options)?;
+        let options = zip::write::FileOptions::default().last_modified_time(time);
 
         if let Some(target) = entry.link_target() {
+            symlinks.push(name.as_bytes().to_vec());
+            zf.start_file(
+                name,
+                options.compression_method(zip::CompressionMethod::Stored),
+            )?;
             zf.write_all(target.to_string_lossy().replace('\\', "/").as_bytes())?;
         } else {
-            zf.write_all(&entry.resolve_content()?)?;
+            let content = entry.resolve_content()?;
+            let options = options
+                .compression_method(compression.method_for(&name, &content))
+                .unix_permissions(if entry.is_executable() { 0o755 } else { 0o644 });
+
+            zf.start_file(name, options)?;
+            zf.write_all(&content)?;
         }
     }
 
@@ -125,6 +322,7 @@ pub fn bundle_to_zip(bundle: &DirectoryBundle) -> Result<Vec<u8>, AppleCodesignE
 }
 
 /// Represents the result of a notarization upload.
+#[derive(Debug)]
 pub enum NotarizationUpload {
     /// We performed the upload and only have the upload ID / UUID for it.
     ///
@@ -135,6 +333,88 @@ pub enum NotarizationUpload {
     NotaryResponse(SubmissionResponse),
 }
 
+/// The outcome of notarizing one of multiple artifacts.
+#[derive(Debug)]
+pub struct ArtifactNotarization {
+    /// Path of the artifact.
+    pub path: PathBuf,
+
+    /// ID of the submission, if the artifact was uploaded.
+    pub submission_id: Option<String>,
+
+    /// Result of the notarization.
+    ///
+    /// When waiting, an error unless Apple accepted the artifact.
+    pub result: Result<NotarizationUpload, AppleCodesignError>,
+
+    /// The processing log, fetched for submissions that weren't accepted.
+    pub log: Option<serde_json::Value>,
+}
+
+impl ArtifactNotarization {
+    /// Describe the state of the notarization for display.
+    pub fn status_text(&self) -> String {
+        match &self.result {
+            Ok(NotarizationUpload::UploadId(_)) => "uploaded".to_string(),
+            Ok(NotarizationUpload::NotaryResponse(status)) => {
+                format!("{:?}", status.data.attributes.status)
+            }
+            Err(e) => e.to_string(),
+        }
+    }
+}
+
+/// A record of the submission of content for notarization.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmissionRecord {
+    /// Hex encoded SHA-256 of the submitted content.
+    pub sha256: String,
+
+    /// Name the content was submitted as.
+    pub name: String,
+
+    /// ID of the submission.
+    pub submission_id: String,
+
+    /// RFC 3339 time of the submission.
+    pub submitted_at: String,
+}
+
+impl SubmissionRecord {
+    /// The default directory of submission records.
+    ///
+    /// `~/.cache/asconnect/notarizations` on Linux.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("asconnect").join("notarizations"))
+    }
+
+    /// The path of the record of content in a directory of an API key.
+    pub fn path_for(dir: &Path, scope: &CacheScope, sha256: &str) -> PathBuf {
+        scope.dir(dir).join(format!("{}.json", sha256))
+    }
+
+    /// Read a record, if it exists.
+    pub fn from_path(path: &Path) -> Result<Option<Self>, AppleCodesignError> {
+        match std::fs::read(path) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write this record, creating parent directories as needed.
+    pub fn write(&self, path: &Path) -> Result<(), AppleCodesignError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
 enum UploadKind {
     Data(Vec<u8>),
     Path(PathBuf),
@@ -149,8 +429,41 @@ enum UploadKind {
 pub struct Notarizer {
     token_encoder: Option<ConnectTokenEncoder>,
 
+    /// Whether API clients compensate for a skewed local clock.
+    correct_clock_skew: bool,
+
+    /// Timeouts of individual API requests.
+    request_timeouts: RequestTimeouts,
+
+    /// Deadline by which all operations must complete.
+    deadline: Option<Instant>,
+
+    /// Recorder of API requests.
+    http_capture: Option<Arc<HttpCapture>>,
+
+    /// Receives events when submissions finish processing.
+    notification_sink: Option<Arc<dyn NotificationSink>>,
+
+    /// Receives progress of uploads and waits on processing.
+    progress_sink: Option<Arc<dyn ProgressSink>>,
+
+    /// Meters the bandwidth of uploads to S3.
+    upload_bucket: Option<Arc<TokenBucket>>,
+
+    /// Persists tokens across processes.
+    token_cache: Option<TokenCache>,
+
     /// How long to wait between polling the server for upload status.
     wait_poll_interval: Duration,
+
+    /// Directory of [SubmissionRecord]s.
+    history_dir: Option<PathBuf>,
+
+    /// How files are compressed when zipping bundles.
+    zip_compression: ZipCompression,
+
+    /// Whether zips of bundles are reproducible.
+    reproducible_zips: bool,
 }
 
 impl Notarizer {
@@ -158,7 +471,18 @@ impl Notarizer {
     pub fn new() -> Result<Self, AppleCodesignError> {
         Ok(Self {
             token_encoder: None,
+            correct_clock_skew: false,
+            request_timeouts: RequestTimeouts::default(),
+            deadline: None,
+            http_capture: None,
+            notification_sink: None,
+            progress_sink: None,
+            upload_bucket: None,
+            token_cache: None,
             wait_poll_interval: Duration::from_secs(3),
+            history_dir: None,
+            zip_compression: ZipCompression::default(),
+            reproducible_zips: false,
         })
     }
 
@@ -169,6 +493,101 @@ impl Notarizer {
         self.token_encoder = Some(encoder);
     }
 
+    /// Set whether to compensate for a skewed local clock when talking to the Notary API.
+    ///
+    /// See [AppStoreConnectClient::set_correct_clock_skew()].
+    pub fn set_correct_clock_skew(&mut self, value: bool) {
+        self.correct_clock_skew = value;
+    }
+
+    /// Set the timeouts of individual Notary API requests.
+    pub fn set_request_timeouts(&mut self, timeouts: RequestTimeouts) {
+        self.request_timeouts = timeouts;
+    }
+
+    /// Set a deadline by which all operations must complete.
+    ///
+    /// Waiting on notarization stops at the deadline, even if the wait limit
+    /// is longer. See also [AppStoreConnectClient::set_deadline()].
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// The deadline by which all operations must complete, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Record API requests and their responses.
+    ///
+    /// See [AppStoreConnectClient::set_http_capture()].
+    pub fn set_http_capture(&mut self, capture: Option<Arc<HttpCapture>>) {
+        self.http_capture = capture;
+    }
+
+    /// Set the sink notified when submissions finish processing.
+    ///
+    /// See [AppStoreConnectClient::set_notification_sink()].
+    pub fn set_notification_sink(&mut self, sink: Option<Arc<dyn NotificationSink>>) {
+        self.notification_sink = sink;
+    }
+
+    /// Set the sink receiving progress of uploads and waits on processing.
+    ///
+    /// See [AppStoreConnectClient::set_progress_sink()].
+    pub fn set_progress_sink(&mut self, sink: Option<Arc<dyn ProgressSink>>) {
+        self.progress_sink = sink;
+    }
+
+    /// Limit the bandwidth used to upload to S3.
+    ///
+    /// See [AppStoreConnectClient::set_upload_rate_limit()].
+    pub fn set_upload_rate_limit(&mut self, limit: Option<BandwidthLimit>) {
+        self.upload_bucket = limit.map(|limit| Arc::new(TokenBucket::new(limit)));
+    }
+
+    /// Persist tokens so other processes using the same API key reuse them.
+    ///
+    /// See [AppStoreConnectClient::set_token_cache()].
+    pub fn set_token_cache(&mut self, cache: Option<TokenCache>) {
+        self.token_cache = cache;
+    }
+
+    fn report_progress(&self, event: ProgressEvent) {
+        if let Some(sink) = &self.progress_sink {
+            sink.progress(&event);
+        }
+    }
+
+    /// Set the directory recording submissions by the SHA-256 of their content.
+    ///
+    /// Records are partitioned by the [CacheScope] of the API key. Before
+    /// content is submitted, its record is looked up. If Apple accepted the
+    /// recorded submission, that submission is returned instead of submitting
+    /// the content again. See [SubmissionRecord::default_dir()].
+    pub fn set_history_dir(&mut self, dir: Option<PathBuf>) {
+        self.history_dir = dir;
+    }
+
+    /// Set how files are compressed when zipping bundles for upload.
+    ///
+    /// Bundles are zipped with [ZipCompression::Auto] by default. Since
+    /// submissions are recorded by the SHA-256 of the zip, changing the
+    /// compression causes unchanged bundles to be submitted again.
+    pub fn set_zip_compression(&mut self, compression: ZipCompression) {
+        self.zip_compression = compression;
+    }
+
+    /// Set whether zips of bundles are reproducible.
+    ///
+    /// See [bundle_to_zip()]. Reproducible zips of identical bundles have the
+    /// same SHA-256, so submission records (see [Self::set_history_dir()])
+    /// are reused even if the bundle was built on another machine, where files
+    /// may be listed in a different order.
+    pub fn set_reproducible_zips(&mut self, value: bool) {
+        self.reproducible_zips = value;
+    }
+
     /// Set the API key used to upload.
     ///
     /// The API issuer is required when using an API key.
@@ -210,6 +629,53 @@ impl Notarizer {
         }
     }
 
+    /// Notarize multiple artifacts concurrently.
+    ///
+    /// Every path is uploaded as by [Self::notarize_path()], concurrently. If
+    /// `wait_limit` is provided, the submissions are then polled together until
+    /// all of them finish processing or the limit is reached, and the processing
+    /// logs of submissions that weren't accepted are fetched.
+    ///
+    /// A failure for one artifact doesn't affect the others. Results are returned
+    /// in the order of `paths`.
+    pub fn notarize_all(
+        &self,
+        paths: &[PathBuf],
+        wait_limit: Option<Duration>,
+    ) -> Result<Vec<ArtifactNotarization>, AppleCodesignError> {
+        warn!("submitting {} artifacts for notarization", paths.len());
+
+        let mut results = paths
+            .par_iter()
+            .map(|path| {
+                let result = self.notarize_path(path, None);
+
+                if let Err(e) = &result {
+                    error!("{}: {}", path.display(), e);
+                }
+
+                ArtifactNotarization {
+                    path: path.clone(),
+                    submission_id: match &result {
+                        Ok(NotarizationUpload::UploadId(id)) => Some(id.clone()),
+                        Ok(NotarizationUpload::NotaryResponse(status)) => {
+                            Some(status.data.id.clone())
+                        }
+                        Err(_) => None,
+                    },
+                    result,
+                    log: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(wait_limit) = wait_limit {
+            self.wait_on_all(&mut results, wait_limit)?;
+        }
+
+        Ok(results)
+    }
+
     /// Attempt to notarize an on-disk bundle.
     ///
     /// If `wait_limit` is provided, we will wait for the upload to finish processing.
@@ -219,12 +685,15 @@ impl Notarizer {
         bundle: &DirectoryBundle,
         wait_limit: Option<Duration>,
     ) -> Result<NotarizationUpload, AppleCodesignError> {
-        let zipfile = bundle_to_zip(bundle)?;
+        let zipfile = bundle_to_zip(bundle, self.zip_compression, self.reproducible_zips)?;
         let digest = sha2::Sha256::digest(&zipfile);
 
-        let submission = self.create_submission(&digest, &format!("{}.zip", bundle.name()))?;
-
-        self.upload_s3_and_maybe_wait(submission, UploadKind::Data(zipfile), wait_limit)
+        self.submit(
+            &digest,
+            &format!("{}.zip", bundle.name()),
+            UploadKind::Data(zipfile),
+            wait_limit,
+        )
     }
 
     /// Attempt to notarize a DMG file.
@@ -240,10 +709,9 @@ impl Notarizer {
 
         let (_, digest) = digest_sha256(&mut File::open(dmg_path)?)?;
 
-        let submission = self.create_submission(&digest, &filename)?;
-
-        self.upload_s3_and_maybe_wait(
-            submission,
+        self.submit(
+            &digest,
+            &filename,
             UploadKind::Path(dmg_path.to_path_buf()),
             wait_limit,
         )
@@ -262,29 +730,161 @@ impl Notarizer {
 
         let (_, digest) = digest_sha256(&mut File::open(pkg_path)?)?;
 
-        let submission = self.create_submission(&digest, &filename)?;
-
-        self.upload_s3_and_maybe_wait(
-            submission,
+        self.submit(
+            &digest,
+            &filename,
             UploadKind::Path(pkg_path.to_path_buf()),
             wait_limit,
         )
     }
 }
 
+/// Configure the AWS S3 client receiving the upload of a submission.
+///
+/// Apple provides temporary credentials for the object to upload to.
+pub(crate) fn s3_config_loader(submission: &NewSubmissionResponse) -> aws_config::ConfigLoader {
+    aws_config::from_env()
+        .credentials_provider(Credentials::new(
+            submission.data.attributes.aws_access_key_id.clone(),
+            submission.data.attributes.aws_secret_access_key.clone(),
+            Some(submission.data.attributes.aws_session_token.clone()),
+            None,
+            "apple-codesign",
+        ))
+        // The region is not given anywhere in the Apple documentation. From
+        // manually testing all available regions, it appears to be
+        // us-west-2.
+        .region(Region::new("us-west-2"))
+}
+
+/// Describe the terminal state of a submission of an artifact.
+fn notification_event(
+    submission_id: &str,
+    artifact: &str,
+    status: &SubmissionResponse,
+) -> NotificationEvent {
+    let state = status.data.attributes.status;
+
+    NotificationEvent::new(
+        if state == SubmissionResponseStatus::Accepted {
+            NotificationKind::NotarizationAccepted
+        } else {
+            NotificationKind::NotarizationInvalid
+        },
+        submission_id,
+        format!("notarization of {} finished: {:?}", artifact, state),
+    )
+}
+
 impl Notarizer {
+    /// Obtain a client to the Notary API.
+    fn client(&self) -> Result<NotaryApiClient, AppleCodesignError> {
+        let token = self
+            .token_encoder
+            .as_ref()
+            .ok_or(AppleCodesignError::NotarizeNoAuthCredentials)?;
+
+        let mut client = AppStoreConnectClient::new(token.clone())?;
+        client.set_correct_clock_skew(self.correct_clock_skew);
+        client.set_request_timeouts(self.request_timeouts);
+        client.set_deadline(self.deadline);
+        client.set_http_capture(self.http_capture.clone());
+        client.set_notification_sink(self.notification_sink.clone());
+        client.set_progress_sink(self.progress_sink.clone());
+        client.set_token_cache(self.token_cache.clone());
+
+        Ok(NotaryApiClient::from(client))
+    }
+
+    /// The path of the submission record of content, if submissions are recorded.
+    fn history_path(&self, sha256: &str) -> Option<PathBuf> {
+        match (&self.history_dir, &self.token_encoder) {
+            (Some(dir), Some(token)) => Some(SubmissionRecord::path_for(
+                dir,
+                &token.cache_scope(),
+                sha256,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Find an accepted submission of content in the submission history.
+    fn find_accepted_submission(
+        &self,
+        sha256: &str,
+    ) -> Result<Option<SubmissionResponse>, AppleCodesignError> {
+        let record = match self.history_path(sha256) {
+            Some(path) => SubmissionRecord::from_path(&path)?,
+            None => None,
+        };
+
+        let record = if let Some(record) = record {
+            record
+        } else {
+            return Ok(None);
+        };
+
+        match self.client()?.get_submission(&record.submission_id) {
+            Ok(status) if status.data.attributes.status == SubmissionResponseStatus::Accepted => {
+                warn!(
+                    "{} (sha256: {}) was accepted as submission {}; not submitting again",
+                    record.name, sha256, record.submission_id
+                );
+                Ok(Some(status))
+            }
+            Ok(status) => {
+                info!(
+                    "previous submission {} of {} is {:?}; submitting again",
+                    record.submission_id, record.name, status.data.attributes.status
+                );
+                Ok(None)
+            }
+            Err(e) => {
+                warn!(
+                    "unable to query previous submission {}: {}",
+                    record.submission_id, e
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Submit content unless Apple already accepted it.
+    fn submit(
+        &self,
+        raw_digest: &[u8],
+        name: &str,
+        upload: UploadKind,
+        wait_limit: Option<Duration>,
+    ) -> Result<NotarizationUpload, AppleCodesignError> {
+        let sha256 = hex::encode(raw_digest);
+
+        if let Some(status) = self.find_accepted_submission(&sha256)? {
+            return Ok(NotarizationUpload::NotaryResponse(status));
+        }
+
+        let submission = self.create_submission(raw_digest, name)?;
+
+        if let Some(path) = self.history_path(&sha256) {
+            SubmissionRecord {
+                sha256,
+                name: name.to_string(),
+                submission_id: submission.data.id.clone(),
+                submitted_at: chrono::Utc::now().to_rfc3339(),
+            }
+            .write(&path)?;
+        }
+
+        self.upload_s3_and_maybe_wait(submission, upload, wait_limit)
+    }
+
     /// Tell the notary service to expect an upload to S3.
     fn create_submission(
         &self,
         raw_digest: &[u8],
         name: &str,
     ) -> Result<NewSubmissionResponse, AppleCodesignError> {
-        let client = match &self.token_encoder {
-            Some(token) => Ok(NotaryApiClient::from(AppStoreConnectClient::new(
-                token.clone(),
-            )?)),
-            _ => Err(AppleCodesignError::NotarizeNoAuthCredentials),
-        }?;
+        let client = self.client()?;
 
         let digest = hex::encode(raw_digest);
         warn!(
@@ -307,28 +907,40 @@ impl Notarizer {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()?;
-        let bytestream = match upload {
-            UploadKind::Data(data) => ByteStream::from(data),
-            UploadKind::Path(path) => rt.block_on(ByteStream::from_path(path))?,
+        let size = match &upload {
+            UploadKind::Data(data) => data.len() as u64,
+            UploadKind::Path(path) => std::fs::metadata(path)?.len(),
+        };
+        let bytestream = match (upload, &self.upload_bucket) {
+            (UploadKind::Data(data), Some(bucket)) => {
+                let data = bytes::Bytes::from(data);
+                throttled_byte_stream(
+                    move || {
+                        Ok(Box::new(std::io::Cursor::new(data.clone()))
+                            as Box<dyn Read + Send + Sync>)
+                    },
+                    size,
+                    bucket.clone(),
+                )
+            }
+            (UploadKind::Path(path), Some(bucket)) => throttled_byte_stream(
+                move || Ok(Box::new(File::open(&path)?) as Box<dyn Read + Send + Sync>),
+                size,
+                bucket.clone(),
+            ),
+            (UploadKind::Data(data), None) => ByteStream::from(data),
+            (UploadKind::Path(path), None) => rt.block_on(ByteStream::from_path(path))?,
+        };
+        let progress = |state: &str, bytes: u64| {
+            self.report_progress(
+                ProgressEvent::new("notarization-upload", &submission.data.id, state)
+                    .with_bytes(bytes, size),
+            )
         };
 
         // upload using s3 api
         warn!("resolving AWS S3 configuration from Apple-provided credentials");
-        let config = rt.block_on(
-            aws_config::from_env()
-                .credentials_provider(Credentials::new(
-                    submission.data.attributes.aws_access_key_id.clone(),
-                    submission.data.attributes.aws_secret_access_key.clone(),
-                    Some(submission.data.attributes.aws_session_token.clone()),
-                    None,
-                    "apple-codesign",
-                ))
-                // The region is not given anywhere in the Apple documentation. From
-                // manually testing all available regions, it appears to be
-                // us-west-2.
-                .region(Region::new("us-west-2"))
-                .load(),
-        );
+        let config = rt.block_on(s3_config_loader(submission).load());
 
         let s3_client = aws_sdk_s3::Client::new(&config);
 
@@ -337,6 +949,7 @@ impl Notarizer {
             submission.data.attributes.bucket, submission.data.attributes.object
         );
         info!("(you may see additional log output from S3 client)");
+        progress("uploading", 0);
 
         // TODO: Support multi-part upload.
         // Unfortunately, aws-sdk-s3 does not have a simple upload_file helper
@@ -346,12 +959,14 @@ impl Notarizer {
             .put_object()
             .bucket(submission.data.attributes.bucket.clone())
             .key(submission.data.attributes.object.clone())
+            .content_length(size as i64)
             .body(bytestream)
             .send();
 
         rt.block_on(fut).map_err(aws_sdk_s3::Error::from)?;
 
         warn!("S3 upload completed successfully");
+        progress("uploaded", size);
 
         Ok(())
     }
@@ -381,6 +996,11 @@ impl Notarizer {
         submission_id: &str,
         wait_limit: Duration,
     ) -> Result<SubmissionResponse, AppleCodesignError> {
+        let wait_limit = match self.deadline {
+            Some(deadline) => wait_limit.min(deadline.saturating_duration_since(Instant::now())),
+            None => wait_limit,
+        };
+
         warn!(
             "waiting up to {}s for package upload {} to finish processing",
             wait_limit.as_secs(),
@@ -390,12 +1010,7 @@ impl Notarizer {
         let start_time = std::time::Instant::now();
 
         loop {
-            let client = match &self.token_encoder {
-                Some(token) => Ok(NotaryApiClient::from(AppStoreConnectClient::new(
-                    token.clone(),
-                )?)),
-                None => Err(AppleCodesignError::NotarizeNoAuthCredentials),
-            }?;
+            let client = self.client()?;
 
             let status = client.get_submission(submission_id)?;
 
@@ -406,9 +1021,18 @@ impl Notarizer {
                 elapsed.as_secs(),
                 status.data.attributes.status
             );
+            self.report_progress(
+                ProgressEvent::new(
+                    "notarization-wait",
+                    submission_id,
+                    state_name(Some(&status.data.attributes.status)),
+                )
+                .with_elapsed(elapsed),
+            );
 
             if status.data.attributes.status != SubmissionResponseStatus::InProgress {
                 warn!("Notary API Server has finished processing the uploaded asset");
+                client.notify(notification_event(submission_id, submission_id, &status));
 
                 return Ok(status);
             }
@@ -422,18 +1046,129 @@ impl Notarizer {
         }
     }
 
+    /// Poll uploaded submissions until all of them finish processing.
+    fn wait_on_all(
+        &self,
+        results: &mut [ArtifactNotarization],
+        wait_limit: Duration,
+    ) -> Result<(), AppleCodesignError> {
+        let wait_limit = match self.deadline {
+            Some(deadline) => wait_limit.min(deadline.saturating_duration_since(Instant::now())),
+            None => wait_limit,
+        };
+
+        let mut pending = results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| match &r.result {
+                Ok(NotarizationUpload::UploadId(id)) => Some((i, id.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        warn!(
+            "waiting up to {}s for {} submissions to finish processing",
+            wait_limit.as_secs(),
+            pending.len()
+        );
+
+        let client = self.client()?;
+        let start_time = std::time::Instant::now();
+
+        while !pending.is_empty() {
+            let elapsed = start_time.elapsed();
+
+            pending.retain(|(i, id)| {
+                let result = &mut results[*i];
+                let status = client.get_submission(id);
+
+                if let Ok(status) = &status {
+                    self.report_progress(
+                        ProgressEvent::new(
+                            "notarization-wait",
+                            id,
+                            state_name(Some(&status.data.attributes.status)),
+                        )
+                        .with_elapsed(elapsed),
+                    );
+                }
+
+                match status {
+                    Ok(status)
+                        if status.data.attributes.status
+                            == SubmissionResponseStatus::InProgress =>
+                    {
+                        true
+                    }
+                    Ok(status) => {
+                        warn!(
+                            "{}: finished processing: {:?}",
+                            result.path.display(),
+                            status.data.attributes.status
+                        );
+                        client.notify(notification_event(
+                            id,
+                            &result.path.display().to_string(),
+                            &status,
+                        ));
+                        result.result =
+                            status.into_result().map(NotarizationUpload::NotaryResponse);
+                        false
+                    }
+                    Err(e) => {
+                        error!("{}: {}", result.path.display(), e);
+                        result.result = Err(e);
+                        false
+                    }
+                }
+            });
+
+            let elapsed = start_time.elapsed();
+            info!(
+                "{} submissions still processing after {}s",
+                pending.len(),
+                elapsed.as_secs()
+            );
+
+            if !pending.is_empty() {
+                if elapsed >= wait_limit {
+                    warn!("reached wait limit after {}s", elapsed.as_secs());
+                    for (i, _) in &pending {
+                        results[*i].result = Err(AppleCodesignError::NotarizeWaitLimitReached);
+                    }
+                    break;
+                }
+
+                std::thread::sleep(self.wait_poll_interval);
+            }
+        }
+
+        for result in results.iter_mut() {
+            if matches!(
+                result.result,
+                Err(AppleCodesignError::NotarizeWaitLimitReached)
+            ) {
+                continue;
+            }
+
+            if let (Some(id), Err(_)) = (&result.submission_id, &result.result) {
+                match self.fetch_notarization_log(id) {
+                    Ok(log) => result.log = Some(log),
+                    Err(e) => warn!("unable to fetch notarization log for {}: {}", id, e),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Obtain the processing log from an upload.
     pub fn fetch_notarization_log(
         &self,
         submission_id: &str,
     ) -> Result<serde_json::Value, AppleCodesignError> {
         warn!("fetching notarization log for {}", submission_id);
-        let client = match &self.token_encoder {
-            Some(token) => Ok(NotaryApiClient::from(AppStoreConnectClient::new(
-                token.clone(),
-            )?)),
-            None => Err(AppleCodesignError::NotarizeNoAuthCredentials),
-        }?;
+        let client = self.client()?;
         client.get_submission_log(submission_id)
     }
 
@@ -457,3 +1192,116 @@ impl Notarizer {
         Ok(status)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn submission_records() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let scope = CacheScope::new("issuer", "KEY1");
+        let path = SubmissionRecord::path_for(td.path(), &scope, "abcd");
+
+        assert!(SubmissionRecord::from_path(&path)?.is_none());
+
+        let record = SubmissionRecord {
+            sha256: "abcd".into(),
+            name: "MyApp.dmg".into(),
+            submission_id: "S1".into(),
+            submitted_at: chrono::Utc::now().to_rfc3339(),
+        };
+        record.write(&path)?;
+        assert_eq!(SubmissionRecord::from_path(&path)?, Some(record));
+
+        // Records of other API keys aren't found.
+        let other =
+            SubmissionRecord::path_for(td.path(), &CacheScope::new("issuer", "KEY2"), "abcd");
+        assert!(SubmissionRecord::from_path(&other)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_compression() {
+        let random = (0..ENTROPY_SAMPLE_SIZE as u64)
+            .map(|i| {
+                (i.wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407)
+                    >> 56) as u8
+            })
+            .collect::<Vec<_>>();
+        let text = b"<key>CFBundleIdentifier</key>\n".repeat(1000);
+
+        let auto = ZipCompression::Auto;
+        assert_eq!(
+            auto.method_for("MyApp.app/Contents/Resources/icon.PNG", &text),
+            zip::CompressionMethod::Stored
+        );
+        assert_eq!(
+            auto.method_for("MyApp.app/Contents/Info.plist", &text),
+            zip::CompressionMethod::Deflated
+        );
+        assert_eq!(
+            auto.method_for("MyApp.app/Contents/Resources/data.bin", &random),
+            zip::CompressionMethod::Stored
+        );
+        assert_eq!(
+            auto.method_for("MyApp.app/Contents/Resources/small.bin", &random[..100]),
+            zip::CompressionMethod::Deflated
+        );
+        assert_eq!(
+            ZipCompression::Deflate.method_for("icon.png", &random),
+            zip::CompressionMethod::Deflated
+        );
+        assert_eq!(
+            ZipCompression::Store.method_for("Info.plist", &text),
+            zip::CompressionMethod::Stored
+        );
+
+        assert_eq!(
+            ZipCompression::from_str("store").unwrap(),
+            ZipCompression::Store
+        );
+        assert!(ZipCompression::from_str("fast").is_err());
+    }
+
+    #[test]
+    fn reproducible_zip() -> Result<(), AppleCodesignError> {
+        let td = tempfile::TempDir::new()?;
+        let app = td.path().join("MyApp.app");
+        std::fs::create_dir(&app)?;
+        std::fs::write(
+            app.join("Info.plist"),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <plist version=\"1.0\"><dict/></plist>\n",
+        )?;
+        std::fs::write(app.join("data.txt"), "data")?;
+        let bundle =
+            DirectoryBundle::new_from_path(&app).map_err(AppleCodesignError::DirectoryBundle)?;
+
+        let set_mtime = |seconds| {
+            filetime::set_file_mtime(
+                app.join("data.txt"),
+                filetime::FileTime::from_unix_time(seconds, 0),
+            )
+        };
+
+        set_mtime(1_600_000_000)?;
+        let reproducible = bundle_to_zip(&bundle, ZipCompression::Auto, true)?;
+        let default = bundle_to_zip(&bundle, ZipCompression::Auto, false)?;
+
+        // Neither kind of zip records modification times.
+        set_mtime(1_700_000_000)?;
+        assert_eq!(
+            bundle_to_zip(&bundle, ZipCompression::Auto, true)?,
+            reproducible
+        );
+        assert_eq!(
+            bundle_to_zip(&bundle, ZipCompression::Auto, false)?,
+            default
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Sources of secret data given on the command line.
+//!
+//! CI systems typically expose secrets as environment variables or pipes
+//! rather than files. [SecretInput] allows arguments naming a secret to refer
+//! to these directly so secrets don't need to be written to temporary files.
+//!
+//! The following forms are recognized:
+//!
+//! * `-` reads standard input.
+//! * `base64:<data>` decodes the base64 encoded data following the prefix.
+//! * `env:<NAME>` reads the environment variable `NAME`.
+//! * `fd:<N>` reads the already open file descriptor `N` (UNIX only). `N` must
+//!   be 3 or greater; use `-` for standard input.
+//! * `file:<path>` or any other value reads a file.
+
+use {
+    crate::AppleCodesignError,
+    std::{
+        io::Read,
+        path::{Path, PathBuf},
+        str::FromStr,
+    },
+    zeroize::Zeroizing,
+};
+
+/// A source of secret data.
+#[derive(Clone, Eq, PartialEq)]
+pub enum SecretInput {
+    /// Standard input.
+    Stdin,
+
+    /// Data given inline, base64 encoded.
+    Base64(Zeroizing<Vec<u8>>),
+
+    /// An environment variable.
+    Env(String),
+
+    /// An open file descriptor, not a standard stream.
+    ///
+    /// The descriptor is read through a duplicate and left open.
+    FileDescriptor(i32),
+
+    /// A filesystem path.
+    Path(PathBuf),
+}
+
+impl FromStr for SecretInput {
+    type Err = AppleCodesignError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            Ok(Self::Stdin)
+        } else if let Some(data) = s.strip_prefix("base64:") {
+            Ok(Self::Base64(Zeroizing::new(
+                base64::decode(data.trim()).map_err(|e| {
+                    AppleCodesignError::SecretInput(format!("invalid base64: {}", e))
+                })?,
+            )))
+        } else if let Some(name) = s.strip_prefix("env:") {
+            if name.is_empty() {
+                Err(AppleCodesignError::SecretInput(
+                    "env: requires a variable name".into(),
+                ))
+            } else {
+                Ok(Self::Env(name.to_string()))
+            }
+        } else if let Some(fd) = s.strip_prefix("fd:") {
+            match fd.parse::<i32>() {
+                Ok(n) if n >= 3 => Ok(Self::FileDescriptor(n)),
+                Ok(n) if n >= 0 => Err(AppleCodesignError::SecretInput(format!(
+                    "file descriptor {} is a standard stream; use - for standard input",
+                    n
+                ))),
+                _ => Err(AppleCodesignError::SecretInput(format!(
+                    "invalid file descriptor: {}",
+                    fd
+                ))),
+            }
+        } else if let Some(path) = s.strip_prefix("file:") {
+            Ok(Self::Path(PathBuf::from(path)))
+        } else {
+            Ok(Self::Path(PathBuf::from(s)))
+        }
+    }
+}
+
+impl std::fmt::Debug for SecretInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stdin => f.write_str("Stdin"),
+            Self::Base64(_) => f
+                .debug_tuple("Base64")
+                .field(&crate::redact::REDACTED)
+                .finish(),
+            Self::Env(name) => f.debug_tuple("Env").field(name).finish(),
+            Self::FileDescriptor(fd) => f.debug_tuple("FileDescriptor").field(fd).finish(),
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+        }
+    }
+}
+
+impl std::fmt::Display for SecretInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stdin => f.write_str("standard input"),
+            Self::Base64(_) => f.write_str("base64 argument"),
+            Self::Env(name) => write!(f, "environment variable {}", name),
+            Self::FileDescriptor(fd) => write!(f, "file descriptor {}", fd),
+            Self::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl SecretInput {
+    /// The filesystem path of this input, if it is a file.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Read the secret data.
+    ///
+    /// The returned memory is zeroed on drop. Standard input and file descriptors
+    /// are consumed, so reading them again returns whatever data remains.
+    pub fn read(&self) -> Result<Zeroizing<Vec<u8>>, AppleCodesignError> {
+        let data = match self {
+            Self::Stdin => {
+                let mut data = vec![];
+                std::io::stdin().read_to_end(&mut data)?;
+                data
+            }
+            Self::Base64(data) => data.to_vec(),
+            Self::Env(name) => std::env::var(name).map(|v| v.into_bytes()).map_err(|_| {
+                AppleCodesignError::SecretInput(format!(
+                    "environment variable {} is not set or is not UTF-8",
+                    name
+                ))
+            })?,
+            Self::FileDescriptor(fd) => read_file_descriptor(*fd)?,
+            Self::Path(path) => std::fs::read(path)?,
+        };
+
+        Ok(Zeroizing::new(data))
+    }
+
+    /// Read the secret data as a string.
+    pub fn read_string(&self) -> Result<Zeroizing<String>, AppleCodesignError> {
+        let data = self.read()?;
+
+        std::str::from_utf8(&data)
+            .map(|s| Zeroizing::new(s.to_string()))
+            .map_err(|_| AppleCodesignError::SecretInput(format!("{} is not UTF-8", self)))
+    }
+}
+
+#[cfg(unix)]
+fn read_file_descriptor(fd: i32) -> Result<Vec<u8>, AppleCodesignError> {
+    use std::{mem::ManuallyDrop, os::unix::io::FromRawFd};
+
+    debug_assert!(fd >= 3, "standard streams are rejected when parsing");
+
+    // The descriptor isn't ours, so never close it. Reading happens through
+    // a duplicate, which fails cleanly if the descriptor isn't open, and is
+    // closed once read.
+    let borrowed = ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    let mut fh = borrowed.try_clone().map_err(|e| {
+        AppleCodesignError::SecretInput(format!("cannot read file descriptor {}: {}", fd, e))
+    })?;
+
+    let mut data = vec![];
+    fh.read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+#[cfg(not(unix))]
+fn read_file_descriptor(_: i32) -> Result<Vec<u8>, AppleCodesignError> {
+    Err(AppleCodesignError::SecretInput(
+        "file descriptors are only supported on UNIX".into(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() -> Result<(), AppleCodesignError> {
+        assert_eq!(SecretInput::from_str("-")?, SecretInput::Stdin);
+        assert_eq!(
+            SecretInput::from_str("base64:aGVsbG8=")?,
+            SecretInput::Base64(Zeroizing::new(b"hello".to_vec()))
+        );
+        assert_eq!(
+            SecretInput::from_str("env:API_KEY")?,
+            SecretInput::Env("API_KEY".into())
+        );
+        assert_eq!(
+            SecretInput::from_str("fd:3")?,
+            SecretInput::FileDescriptor(3)
+        );
+        assert_eq!(
+            SecretInput::from_str("file:-")?,
+            SecretInput::Path("-".into())
+        );
+        assert_eq!(
+            SecretInput::from_str("key.json")?,
+            SecretInput::Path("key.json".into())
+        );
+
+        assert!(SecretInput::from_str("base64:!!").is_err());
+        assert!(SecretInput::from_str("env:").is_err());
+        assert!(SecretInput::from_str("fd:three").is_err());
+        assert!(SecretInput::from_str("fd:-1").is_err());
+        assert!(SecretInput::from_str("fd:1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read() -> Result<(), AppleCodesignError> {
+        assert_eq!(*SecretInput::from_str("base64:aGVsbG8=")?.read()?, b"hello");
+        assert_eq!(
+            SecretInput::from_str("base64:aGVsbG8=")?.to_string(),
+            "base64 argument"
+        );
+
+        std::env::set_var("RCODESIGN_SECRET_INPUT_TEST", "secret");
+        assert_eq!(
+            *SecretInput::from_str("env:RCODESIGN_SECRET_INPUT_TEST")?.read_string()?,
+            "secret"
+        );
+        assert!(SecretInput::from_str("env:RCODESIGN_SECRET_INPUT_MISSING")?
+            .read()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_fd() -> Result<(), AppleCodesignError> {
+        use std::{io::Write, os::unix::io::AsRawFd};
+
+        let mut file = tempfile::tempfile()?;
+        file.write_all(b"secret")?;
+        std::io::Seek::rewind(&mut file)?;
+
+        let input = SecretInput::FileDescriptor(file.as_raw_fd());
+        assert_eq!(*input.clone().read_string()?, "secret");
+
+        // Data is consumed, but the descriptor stays open.
+        assert!(input.read()?.is_empty());
+        file.write_all(b"!")?;
+        drop(file);
+
+        Ok(())
+    }
+}
@@ -163,10 +163,19 @@ pub struct Field {
 }
 
 /// Obtain the default [Client] to use for HTTP requests.
+///
+/// The TLS implementation is chosen by crate features: the platform's native
+/// TLS library if only the `native-tls` feature is enabled, else rustls.
 pub fn default_client() -> Result<Client, AppleCodesignError> {
-    Ok(ClientBuilder::default()
-        .user_agent("apple-codesign crate (https://crates.io/crates/apple-codesign)")
-        .build()?)
+    let builder = ClientBuilder::default()
+        .user_agent("apple-codesign crate (https://crates.io/crates/apple-codesign)");
+
+    #[cfg(any(feature = "rustls-tls", not(feature = "native-tls")))]
+    let builder = builder.use_rustls_tls();
+    #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+    let builder = builder.use_native_tls();
+
+    Ok(builder.build()?)
 }
 
 /// Look up a notarization ticket given an HTTP client and an iterable of record names.
@@ -0,0 +1,108 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Apps and App Store versions.
+
+use {
+    crate::{builds::Platform, json_api::Resource},
+    serde::{Deserialize, Serialize},
+};
+
+/// Attributes of an app.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_locale: Option<String>,
+}
+
+pub type App = Resource<AppAttributes>;
+
+/// The state of an App Store version.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AppStoreVersionState {
+    Accepted,
+    DeveloperRemovedFromSale,
+    DeveloperRejected,
+    InReview,
+    InvalidBinary,
+    MetadataRejected,
+    PendingAppleRelease,
+    PendingContract,
+    PendingDeveloperRelease,
+    PrepareForSubmission,
+    PreorderReadyForSale,
+    ProcessingForAppStore,
+    ReadyForReview,
+    ReadyForSale,
+    Rejected,
+    RemovedFromSale,
+    ReplacedWithNewVersion,
+    WaitingForExportCompliance,
+    WaitingForReview,
+    NotApplicable,
+    #[serde(other)]
+    Unknown,
+}
+
+impl AppStoreVersionState {
+    /// Whether the version was released to the App Store.
+    ///
+    /// Later versions of the app can be created once a version is released.
+    pub fn is_released(&self) -> bool {
+        matches!(
+            self,
+            Self::ReadyForSale
+                | Self::PreorderReadyForSale
+                | Self::ReplacedWithNewVersion
+                | Self::RemovedFromSale
+                | Self::DeveloperRemovedFromSale
+        )
+    }
+
+    /// Whether App Review is considering the version.
+    pub fn is_in_review(&self) -> bool {
+        matches!(
+            self,
+            Self::WaitingForReview | Self::InReview | Self::WaitingForExportCompliance
+        )
+    }
+
+    /// Whether App Review rejected the version or the developer withdrew it.
+    pub fn is_rejected(&self) -> bool {
+        matches!(
+            self,
+            Self::Rejected | Self::MetadataRejected | Self::InvalidBinary | Self::DeveloperRejected
+        )
+    }
+}
+
+/// Attributes of an App Store version.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStoreVersionAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_string: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub app_store_state: Option<AppStoreVersionState>,
+    /// `MANUAL`, `AFTER_APPROVAL`, or `SCHEDULED`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_type: Option<String>,
+    /// Release time of versions with the `SCHEDULED` release type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub earliest_release_date: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub created_date: Option<String>,
+}
+
+pub type AppStoreVersion = Resource<AppStoreVersionAttributes>;
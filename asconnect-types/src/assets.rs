@@ -0,0 +1,46 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Uploads of assets such as builds and review attachments.
+
+use serde::{Deserialize, Serialize};
+
+/// An HTTP header that must be sent with an [UploadOperation].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadOperationHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Instructions for uploading a byte range of an asset.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadOperation {
+    pub method: String,
+    pub url: String,
+    pub length: u64,
+    pub offset: u64,
+    #[serde(default)]
+    pub request_headers: Vec<UploadOperationHeader>,
+}
+
+/// An error or warning reported while processing an uploaded asset.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppMediaStateError {
+    pub code: Option<String>,
+    pub description: Option<String>,
+}
+
+/// The processing state of an uploaded asset.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppMediaAssetState {
+    #[serde(default)]
+    pub errors: Vec<AppMediaStateError>,
+    #[serde(default)]
+    pub warnings: Vec<AppMediaStateError>,
+    pub state: Option<String>,
+}
@@ -0,0 +1,110 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Builds and build uploads.
+
+use {
+    crate::{
+        assets::{AppMediaAssetState, UploadOperation},
+        json_api::Resource,
+    },
+    serde::{Deserialize, Serialize},
+};
+
+/// The processing state of a build.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BuildProcessingState {
+    Processing,
+    Failed,
+    Invalid,
+    Valid,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of a build.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildAttributes {
+    /// The build number. e.g. `42`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_os_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processing_state: Option<BuildProcessingState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uses_non_exempt_encryption: Option<bool>,
+}
+
+pub type Build = Resource<BuildAttributes>;
+
+/// The platform of a build.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Platform {
+    Ios,
+    MacOs,
+    TvOs,
+    VisionOs,
+}
+
+/// The state of a build upload.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BuildUploadState {
+    AwaitingUpload,
+    Processing,
+    Failed,
+    Complete,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of a build upload.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildUploadAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cf_bundle_short_version_string: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cf_bundle_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    #[serde(default, skip_serializing)]
+    pub state: Option<BuildUploadState>,
+}
+
+pub type BuildUpload = Resource<BuildUploadAttributes>;
+
+/// Attributes of a build upload file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildUploadFileAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_file_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded: Option<bool>,
+    #[serde(default, skip_serializing)]
+    pub upload_operations: Option<Vec<UploadOperation>>,
+    #[serde(default, skip_serializing)]
+    pub asset_delivery_state: Option<AppMediaAssetState>,
+}
+
+pub type BuildUploadFile = Resource<BuildUploadFileAttributes>;
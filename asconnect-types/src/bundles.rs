@@ -0,0 +1,72 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Bundle IDs and their capabilities.
+
+use {
+    crate::json_api::Resource,
+    serde::{Deserialize, Serialize},
+};
+
+/// Attributes of a bundle ID.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleIdAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// The App ID prefix. This is typically the team ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed_id: Option<String>,
+}
+
+impl BundleIdAttributes {
+    /// Whether this is a wildcard bundle ID (e.g. `com.example.*`).
+    ///
+    /// Wildcard bundle IDs match every bundle identifier starting with the
+    /// identifier preceding the `*`.
+    pub fn is_wildcard(&self) -> bool {
+        self.identifier
+            .as_deref()
+            .is_some_and(|identifier| identifier.ends_with('*'))
+    }
+}
+
+pub type BundleId = Resource<BundleIdAttributes>;
+
+/// An option of a capability setting.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityOption {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+/// A setting of a capability.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilitySetting {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<CapabilityOption>,
+}
+
+/// Attributes of a capability enabled for a bundle ID.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleIdCapabilityAttributes {
+    /// The capability. e.g. `PUSH_NOTIFICATIONS`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capability_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub settings: Vec<CapabilitySetting>,
+}
+
+pub type BundleIdCapability = Resource<BundleIdCapabilityAttributes>;
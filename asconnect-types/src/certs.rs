@@ -0,0 +1,236 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Signing certificates, provisioning profiles, and devices.
+
+use {
+    crate::json_api::Resource,
+    serde::{Deserialize, Serialize},
+};
+
+/// The type of a signing certificate.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CertificateType {
+    Development,
+    Distribution,
+    IosDevelopment,
+    IosDistribution,
+    MacAppDevelopment,
+    MacAppDistribution,
+    MacInstallerDistribution,
+    DeveloperIdKext,
+    DeveloperIdApplication,
+    DeveloperIdInstaller,
+    #[serde(other)]
+    Unknown,
+}
+
+impl CertificateType {
+    /// Whether this is a Developer ID certificate for distribution outside the Mac App Store.
+    pub fn is_developer_id(&self) -> bool {
+        matches!(
+            self,
+            Self::DeveloperIdKext | Self::DeveloperIdApplication | Self::DeveloperIdInstaller
+        )
+    }
+}
+
+/// Attributes of a signing certificate.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate_type: Option<CertificateType>,
+    /// Base64 encoded DER of the certificate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
+    /// PEM encoded certificate signing request. Only used when creating.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csr_content: Option<String>,
+}
+
+pub type Certificate = Resource<CertificateAttributes>;
+
+/// The state of a provisioning profile.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ProfileState {
+    Active,
+    Invalid,
+    #[serde(other)]
+    Unknown,
+}
+
+/// The type of a provisioning profile.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ProfileType {
+    IosAppDevelopment,
+    IosAppStore,
+    IosAppAdhoc,
+    IosAppInhouse,
+    MacAppDevelopment,
+    MacAppStore,
+    MacAppDirect,
+    TvosAppDevelopment,
+    TvosAppStore,
+    TvosAppAdhoc,
+    TvosAppInhouse,
+    MacCatalystAppDevelopment,
+    MacCatalystAppStore,
+    MacCatalystAppDirect,
+    VisionOsAppDevelopment,
+    VisionOsAppStore,
+    DriverkitAppDevelopment,
+    DriverkitAppStore,
+    DriverkitAppDirect,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ProfileType {
+    /// Whether profiles of this type are restricted to registered devices.
+    pub fn requires_devices(&self) -> bool {
+        matches!(
+            self,
+            Self::IosAppDevelopment
+                | Self::IosAppAdhoc
+                | Self::MacAppDevelopment
+                | Self::TvosAppDevelopment
+                | Self::TvosAppAdhoc
+                | Self::MacCatalystAppDevelopment
+                | Self::VisionOsAppDevelopment
+                | Self::DriverkitAppDevelopment
+        )
+    }
+
+    /// Whether this is a development profile.
+    pub fn is_development(&self) -> bool {
+        matches!(
+            self,
+            Self::IosAppDevelopment
+                | Self::MacAppDevelopment
+                | Self::TvosAppDevelopment
+                | Self::MacCatalystAppDevelopment
+                | Self::VisionOsAppDevelopment
+                | Self::DriverkitAppDevelopment
+        )
+    }
+
+    /// Whether this is an App Store distribution profile.
+    pub fn is_app_store(&self) -> bool {
+        matches!(
+            self,
+            Self::IosAppStore
+                | Self::MacAppStore
+                | Self::TvosAppStore
+                | Self::MacCatalystAppStore
+                | Self::VisionOsAppStore
+                | Self::DriverkitAppStore
+        )
+    }
+
+    /// Whether this profile type is for macOS (including Mac Catalyst and DriverKit).
+    pub fn is_macos(&self) -> bool {
+        matches!(
+            self,
+            Self::MacAppDevelopment
+                | Self::MacAppStore
+                | Self::MacAppDirect
+                | Self::MacCatalystAppDevelopment
+                | Self::MacCatalystAppStore
+                | Self::MacCatalystAppDirect
+                | Self::DriverkitAppDevelopment
+                | Self::DriverkitAppStore
+                | Self::DriverkitAppDirect
+        )
+    }
+
+    /// Whether this profile type is for DriverKit driver extensions.
+    pub fn is_driverkit(&self) -> bool {
+        matches!(
+            self,
+            Self::DriverkitAppDevelopment | Self::DriverkitAppStore | Self::DriverkitAppDirect
+        )
+    }
+}
+
+/// Attributes of a provisioning profile.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_type: Option<ProfileType>,
+    #[serde(default, skip_serializing)]
+    pub profile_state: Option<ProfileState>,
+    /// Base64 encoded content of the `.mobileprovision` file.
+    #[serde(default, skip_serializing)]
+    pub profile_content: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub uuid: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub created_date: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub expiration_date: Option<String>,
+}
+
+pub type Profile = Resource<ProfileAttributes>;
+
+/// The status of a registered device.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeviceStatus {
+    Enabled,
+    Disabled,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of a registered device.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub udid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<DeviceStatus>,
+    #[serde(default, skip_serializing)]
+    pub device_class: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub added_date: Option<String>,
+}
+
+impl DeviceAttributes {
+    /// The marketing name of the model of the device, if it is known.
+    ///
+    /// See [crate::device_models].
+    pub fn marketing_name(&self) -> Option<&'static str> {
+        self.model
+            .as_deref()
+            .and_then(crate::device_models::marketing_name)
+    }
+}
+
+pub type Device = Resource<DeviceAttributes>;
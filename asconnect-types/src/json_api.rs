@@ -0,0 +1,405 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generic JSON:API document types used by the App Store Connect API.
+//!
+//! The App Store Connect API (as opposed to the Notary API) follows the
+//! JSON:API specification. Every response is a *document* holding a primary
+//! `data` member, which is a single resource or a list of resources. Resources
+//! have an `id`, a `type`, and resource specific `attributes`.
+//!
+//! See also <https://developer.apple.com/documentation/appstoreconnectapi>.
+
+use {
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::collections::BTreeMap,
+};
+
+/// A response document from the App Store Connect API.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document<T> {
+    /// The primary data of the document.
+    pub data: T,
+
+    /// Related resources requested via the `include` query parameter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub included: Vec<Value>,
+
+    /// Navigational links, including pagination links.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<DocumentLinks>,
+
+    /// Non-standard meta information, such as paging totals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+/// Links attached to a [Document].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLinks {
+    #[serde(rename = "self")]
+    pub self_: Option<String>,
+    pub first: Option<String>,
+    pub next: Option<String>,
+}
+
+/// A resource object.
+///
+/// `A` is the type of the resource's `attributes`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource<A> {
+    pub id: String,
+    pub r#type: String,
+    pub attributes: A,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub relationships: BTreeMap<String, Relationship>,
+}
+
+impl<A> Resource<A> {
+    /// Identifiers of the resources a relationship links to.
+    ///
+    /// Empty if the relationship is missing or its linkage data wasn't
+    /// requested (with the `include` query parameter).
+    pub fn related(&self, relationship: &str) -> Vec<&ResourceIdentifier> {
+        match self
+            .relationships
+            .get(relationship)
+            .and_then(|r| r.data.as_ref())
+        {
+            Some(RelationshipData::One(id)) => vec![id],
+            Some(RelationshipData::Many(ids)) => ids.iter().collect(),
+            None => vec![],
+        }
+    }
+}
+
+/// A relationship from one resource to other resources.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relationship {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<RelationshipData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<RelationshipLinks>,
+}
+
+/// Links attached to a [Relationship].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelationshipLinks {
+    #[serde(rename = "self")]
+    pub self_: Option<String>,
+    pub related: Option<String>,
+}
+
+/// The linkage data of a relationship.
+///
+/// To-one relationships have a single identifier. To-many relationships have a list.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum RelationshipData {
+    One(ResourceIdentifier),
+    Many(Vec<ResourceIdentifier>),
+}
+
+/// The `type` and `id` of a resource, without its attributes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceIdentifier {
+    pub r#type: String,
+    pub id: String,
+}
+
+impl ResourceIdentifier {
+    pub fn new(r#type: impl ToString, id: impl ToString) -> Self {
+        Self {
+            r#type: r#type.to_string(),
+            id: id.to_string(),
+        }
+    }
+}
+
+/// The body of a create (`POST`) or update (`PATCH`) request.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestDocument<A> {
+    pub data: RequestData<A>,
+
+    /// Related resources created along with the primary resource.
+    ///
+    /// They are referenced from relationships by a local ID like `${name}`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub included: Vec<Value>,
+}
+
+/// The primary data of a [RequestDocument].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestData<A> {
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub attributes: A,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub relationships: BTreeMap<String, RequestRelationship>,
+}
+
+/// A relationship in a [RequestData].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestRelationship {
+    pub data: RelationshipData,
+}
+
+impl<A> RequestDocument<A> {
+    /// Construct a request to create a resource of the given type.
+    pub fn create(r#type: impl ToString, attributes: A) -> Self {
+        Self {
+            data: RequestData {
+                r#type: r#type.to_string(),
+                id: None,
+                attributes,
+                relationships: BTreeMap::new(),
+            },
+            included: vec![],
+        }
+    }
+
+    /// Construct a request to update the resource having the given type and id.
+    pub fn update(r#type: impl ToString, id: impl ToString, attributes: A) -> Self {
+        Self {
+            data: RequestData {
+                r#type: r#type.to_string(),
+                id: Some(id.to_string()),
+                attributes,
+                relationships: BTreeMap::new(),
+            },
+            included: vec![],
+        }
+    }
+
+    /// Add a to-one relationship to the request.
+    pub fn with_relationship(
+        mut self,
+        name: impl ToString,
+        r#type: impl ToString,
+        id: impl ToString,
+    ) -> Self {
+        self.data.relationships.insert(
+            name.to_string(),
+            RequestRelationship {
+                data: RelationshipData::One(ResourceIdentifier::new(r#type, id)),
+            },
+        );
+
+        self
+    }
+
+    /// Add a related resource to create along with the primary resource.
+    pub fn with_included(mut self, resource: Value) -> Self {
+        self.included.push(resource);
+
+        self
+    }
+
+    /// Add a to-many relationship to the request.
+    pub fn with_relationships(
+        mut self,
+        name: impl ToString,
+        identifiers: Vec<ResourceIdentifier>,
+    ) -> Self {
+        self.data.relationships.insert(
+            name.to_string(),
+            RequestRelationship {
+                data: RelationshipData::Many(identifiers),
+            },
+        );
+
+        self
+    }
+}
+
+/// The body of a request adding or removing to-many relationship linkage.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelationshipRequest {
+    pub data: Vec<ResourceIdentifier>,
+}
+
+/// A change to a single attribute made by an update.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributeChange {
+    /// Name of the attribute, as it appears in the API.
+    pub attribute: String,
+    /// Value before the update. `null` if the attribute wasn't set.
+    pub before: Value,
+    /// Value after the update. `null` if the attribute isn't set.
+    pub after: Value,
+}
+
+/// The result of updating a resource.
+///
+/// Holds the resource as returned by the server and the attributes whose
+/// values differ from before the update.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Updated<A> {
+    pub resource: Resource<A>,
+    pub changes: Vec<AttributeChange>,
+}
+
+/// Compute the attributes that differ between two attributes instances.
+///
+/// Attributes are compared by their serialized form, so attributes that aren't
+/// serialized (such as read-only attributes) are ignored.
+pub fn diff_attributes<A: Serialize>(
+    before: &A,
+    after: &A,
+) -> Result<Vec<AttributeChange>, serde_json::Error> {
+    let before = serde_json::to_value(before)?;
+    let after = serde_json::to_value(after)?;
+
+    let empty = serde_json::Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let names = before
+        .keys()
+        .chain(after.keys())
+        .collect::<std::collections::BTreeSet<_>>();
+
+    Ok(names
+        .into_iter()
+        .filter_map(|name| {
+            let old = before.get(name).cloned().unwrap_or(Value::Null);
+            let new = after.get(name).cloned().unwrap_or(Value::Null);
+
+            if old == new {
+                None
+            } else {
+                Some(AttributeChange {
+                    attribute: name.clone(),
+                    before: old,
+                    after: new,
+                })
+            }
+        })
+        .collect())
+}
+
+/// An error response document from the App Store Connect API.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ErrorDocument {
+    pub errors: Vec<ErrorObject>,
+}
+
+/// A single error of an [ErrorDocument].
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ErrorObject {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The HTTP status code, as a string.
+    pub status: String,
+    /// A machine-readable code, e.g. `ENTITY_ERROR.ATTRIBUTE.INVALID`.
+    ///
+    /// Codes are hierarchical, with `.` separating increasingly specific
+    /// components.
+    pub code: String,
+    pub title: String,
+    pub detail: String,
+    /// The part of the request the error is about, e.g. a JSON pointer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Value>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_document_serialize() -> Result<(), serde_json::Error> {
+        let doc = RequestDocument::create("things", serde_json::json!({"name": "foo"}))
+            .with_relationship("parent", "parents", "42");
+
+        assert_eq!(
+            serde_json::to_value(&doc)?,
+            serde_json::json!({
+                "data": {
+                    "type": "things",
+                    "attributes": {"name": "foo"},
+                    "relationships": {
+                        "parent": {"data": {"type": "parents", "id": "42"}},
+                    },
+                }
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff() -> Result<(), serde_json::Error> {
+        let changes = diff_attributes(
+            &serde_json::json!({"name": "old", "status": "ENABLED", "udid": "abc"}),
+            &serde_json::json!({"name": "new", "status": "ENABLED", "model": "iPhone"}),
+        )?;
+
+        assert_eq!(
+            changes,
+            vec![
+                AttributeChange {
+                    attribute: "model".into(),
+                    before: Value::Null,
+                    after: "iPhone".into(),
+                },
+                AttributeChange {
+                    attribute: "name".into(),
+                    before: "old".into(),
+                    after: "new".into(),
+                },
+                AttributeChange {
+                    attribute: "udid".into(),
+                    before: "abc".into(),
+                    after: Value::Null,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_deserialize() -> Result<(), serde_json::Error> {
+        let doc: Document<Vec<Resource<Value>>> = serde_json::from_value(serde_json::json!({
+            "data": [{
+                "id": "1",
+                "type": "things",
+                "attributes": {"name": "foo"},
+                "relationships": {
+                    "children": {
+                        "links": {"related": "https://example.com/v1/things/1/children"},
+                    },
+                },
+            }],
+            "links": {"self": "https://example.com/v1/things", "next": "https://example.com/v1/things?cursor=a"},
+        }))?;
+
+        assert_eq!(doc.data.len(), 1);
+        assert_eq!(doc.data[0].id, "1");
+        assert!(doc.data[0].relationships.contains_key("children"));
+        assert!(doc.data[0].related("children").is_empty());
+        assert_eq!(
+            doc.links.unwrap().next.as_deref(),
+            Some("https://example.com/v1/things?cursor=a")
+        );
+
+        Ok(())
+    }
+}
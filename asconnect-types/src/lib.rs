@@ -0,0 +1,28 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App Store Connect API data models.
+//!
+//! This crate defines the request and response documents of the App Store
+//! Connect API without any HTTP client. [json_api] models the JSON:API
+//! envelope. [query] defines typed collection filters. The other modules define
+//! the attributes of resources.
+//!
+//! Most enumerations of values controlled by Apple have an `Unknown` variant,
+//! so documents containing values introduced after this crate was written
+//! still deserialize.
+
+pub mod apps;
+pub mod assets;
+pub mod builds;
+pub mod bundles;
+pub mod certs;
+pub mod device_models;
+pub mod json_api;
+pub mod pricing;
+pub mod query;
+pub mod review;
+pub mod subscriptions;
+pub mod testflight;
+pub mod users;
@@ -0,0 +1,60 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Territories, app price points, and price schedules.
+
+use {
+    crate::json_api::Resource,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+};
+
+/// Attributes of a territory.
+///
+/// Territories are identified by ISO 3166-1 alpha-3 codes like `USA`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerritoryAttributes {
+    #[serde(default, skip_serializing)]
+    pub currency: Option<String>,
+}
+
+pub type Territory = Resource<TerritoryAttributes>;
+
+/// Attributes of a price point of an app in a territory.
+///
+/// Prices are decimal strings in the currency of the territory.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppPricePointAttributes {
+    #[serde(default, skip_serializing)]
+    pub customer_price: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub proceeds: Option<String>,
+}
+
+pub type AppPricePoint = Resource<AppPricePointAttributes>;
+
+/// Attributes of a price of an app in a price schedule.
+///
+/// Dates are formatted as `YYYY-MM-DD`. A price without a start date is in
+/// effect until the end date.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppPriceAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub end_date: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub manual: Option<bool>,
+}
+
+pub type AppPrice = Resource<AppPriceAttributes>;
+
+/// An app price schedule.
+///
+/// Price schedules have no attributes, only relationships to the base
+/// territory and the prices.
+pub type AppPriceSchedule = Resource<Option<Value>>;
@@ -0,0 +1,283 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Typed filters of collection requests.
+//!
+//! Collections are filtered with `filter[<attribute>]` query parameters. The
+//! API ignores unknown filters, so a misspelled filter name yields unfiltered
+//! results instead of an error. The query types in this module only have
+//! methods for filters a collection supports, taking values of the type of
+//! the filtered attribute:
+//!
+//! ```
+//! use asconnect_types::{builds::Platform, certs::DeviceStatus, query::DeviceQuery};
+//!
+//! let query = DeviceQuery::new()
+//!     .platform(Platform::Ios)
+//!     .status(DeviceStatus::Enabled);
+//!
+//! assert_eq!(
+//!     query.to_query(),
+//!     vec![
+//!         ("filter[platform]", "IOS".to_string()),
+//!         ("filter[status]", "ENABLED".to_string()),
+//!     ]
+//! );
+//! ```
+//!
+//! Setting a filter multiple times matches resources having any of the values.
+
+use {
+    crate::{
+        apps::AppStoreVersionState,
+        builds::{BuildProcessingState, Platform},
+        certs::{CertificateType, DeviceStatus, ProfileState, ProfileType},
+        review::ReviewSubmissionState,
+    },
+    serde::Serialize,
+    std::collections::BTreeMap,
+};
+
+/// A value of a filter query parameter.
+pub trait FilterValue {
+    /// The value as it appears in the query string.
+    fn filter_value(&self) -> String;
+}
+
+impl FilterValue for str {
+    fn filter_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl FilterValue for bool {
+    fn filter_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Implement [FilterValue] for enumerations serializing to strings.
+macro_rules! enum_filter_values {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl FilterValue for $t {
+                fn filter_value(&self) -> String {
+                    serialized_str(self)
+                }
+            }
+        )*
+    };
+}
+
+enum_filter_values!(
+    AppStoreVersionState,
+    BuildProcessingState,
+    CertificateType,
+    DeviceStatus,
+    Platform,
+    ProfileState,
+    ProfileType,
+    ReviewSubmissionState,
+);
+
+fn serialized_str(value: &impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+/// Define a query type with a method per supported filter.
+macro_rules! resource_query {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $(
+                $(#[$filter_meta:meta])*
+                $method:ident($value:ty) => $filter:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, Default, PartialEq, Eq)]
+        pub struct $name {
+            filters: BTreeMap<&'static str, Vec<String>>,
+        }
+
+        impl $name {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            $(
+                $(#[$filter_meta])*
+                #[must_use]
+                pub fn $method(mut self, value: $value) -> Self {
+                    self.filters
+                        .entry($filter)
+                        .or_default()
+                        .push(value.filter_value());
+                    self
+                }
+            )*
+
+            /// Whether no filter is set.
+            pub fn is_empty(&self) -> bool {
+                self.filters.is_empty()
+            }
+
+            /// The query parameters of the filters, ordered by name.
+            pub fn to_query(&self) -> Vec<(&'static str, String)> {
+                self.filters
+                    .iter()
+                    .map(|(name, values)| (*name, values.join(",")))
+                    .collect()
+            }
+        }
+    };
+}
+
+resource_query!(
+    /// Filters of the `apps` collection.
+    AppQuery {
+        bundle_id(&str) => "filter[bundleId]",
+        id(&str) => "filter[id]",
+        name(&str) => "filter[name]",
+        sku(&str) => "filter[sku]",
+    }
+);
+
+resource_query!(
+    /// Filters of the price points of an app.
+    AppPricePointQuery {
+        territory(&str) => "filter[territory]",
+    }
+);
+
+resource_query!(
+    /// Filters of the App Store versions of an app.
+    AppStoreVersionQuery {
+        app_store_state(AppStoreVersionState) => "filter[appStoreState]",
+        id(&str) => "filter[id]",
+        platform(Platform) => "filter[platform]",
+        version_string(&str) => "filter[versionString]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `betaAppLocalizations` collection.
+    BetaAppLocalizationQuery {
+        app(&str) => "filter[app]",
+        locale(&str) => "filter[locale]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `betaGroups` collection.
+    BetaGroupQuery {
+        app(&str) => "filter[app]",
+        id(&str) => "filter[id]",
+        is_internal_group(bool) => "filter[isInternalGroup]",
+        name(&str) => "filter[name]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `betaTesters` collection.
+    BetaTesterQuery {
+        apps(&str) => "filter[apps]",
+        beta_groups(&str) => "filter[betaGroups]",
+        email(&str) => "filter[email]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `builds` collection.
+    BuildQuery {
+        app(&str) => "filter[app]",
+        expired(bool) => "filter[expired]",
+        id(&str) => "filter[id]",
+        processing_state(BuildProcessingState) => "filter[processingState]",
+        /// Filter by build number.
+        version(&str) => "filter[version]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `bundleIds` collection.
+    BundleIdQuery {
+        id(&str) => "filter[id]",
+        identifier(&str) => "filter[identifier]",
+        name(&str) => "filter[name]",
+        platform(Platform) => "filter[platform]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `certificates` collection.
+    CertificateQuery {
+        certificate_type(CertificateType) => "filter[certificateType]",
+        display_name(&str) => "filter[displayName]",
+        id(&str) => "filter[id]",
+        serial_number(&str) => "filter[serialNumber]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `devices` collection.
+    DeviceQuery {
+        id(&str) => "filter[id]",
+        name(&str) => "filter[name]",
+        platform(Platform) => "filter[platform]",
+        status(DeviceStatus) => "filter[status]",
+        udid(&str) => "filter[udid]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `profiles` collection.
+    ProfileQuery {
+        id(&str) => "filter[id]",
+        name(&str) => "filter[name]",
+        profile_state(ProfileState) => "filter[profileState]",
+        profile_type(ProfileType) => "filter[profileType]",
+    }
+);
+
+resource_query!(
+    /// Filters of the `reviewSubmissions` collection.
+    ///
+    /// The API requires the `app` filter.
+    ReviewSubmissionQuery {
+        app(&str) => "filter[app]",
+        platform(Platform) => "filter[platform]",
+        state(ReviewSubmissionState) => "filter[state]",
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn multiple_values() {
+        let query = BuildQuery::new()
+            .processing_state(BuildProcessingState::Processing)
+            .app("A1")
+            .processing_state(BuildProcessingState::Failed);
+
+        assert_eq!(
+            query.to_query(),
+            vec![
+                ("filter[app]", "A1".to_string()),
+                ("filter[processingState]", "PROCESSING,FAILED".to_string()),
+            ]
+        );
+        assert!(BuildQuery::new().is_empty());
+        assert_eq!(
+            BetaGroupQuery::new().is_internal_group(true).to_query(),
+            vec![("filter[isInternalGroup]", "true".to_string())]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! App Store review details and submissions.
+
+use {
+    crate::{
+        assets::{AppMediaAssetState, UploadOperation},
+        json_api::Resource,
+    },
+    serde::{Deserialize, Serialize},
+};
+
+/// Attributes of an App Store review detail.
+///
+/// The same type is used for reading, creating, and updating. Fields that are
+/// [None] are not sent to the server, so updates only touch the fields that are set.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStoreReviewDetailAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_first_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_last_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub demo_account_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub demo_account_password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub demo_account_required: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+pub type AppStoreReviewDetail = Resource<AppStoreReviewDetailAttributes>;
+
+/// Attributes of an App Store review attachment.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStoreReviewAttachmentAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_file_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded: Option<bool>,
+    #[serde(default, skip_serializing)]
+    pub upload_operations: Option<Vec<UploadOperation>>,
+    #[serde(default, skip_serializing)]
+    pub asset_delivery_state: Option<AppMediaAssetState>,
+}
+
+pub type AppStoreReviewAttachment = Resource<AppStoreReviewAttachmentAttributes>;
+
+/// The state of a review submission.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewSubmissionState {
+    ReadyForReview,
+    WaitingForReview,
+    InReview,
+    UnresolvedIssues,
+    Canceling,
+    Completing,
+    Complete,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ReviewSubmissionState {
+    /// Whether the submission is still awaiting a decision from App Review.
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            Self::WaitingForReview | Self::InReview | Self::UnresolvedIssues
+        )
+    }
+}
+
+/// Attributes of a review submission.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewSubmissionAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub submitted_date: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub state: Option<ReviewSubmissionState>,
+}
+
+pub type ReviewSubmission = Resource<ReviewSubmissionAttributes>;
+
+/// The state of an item of a review submission.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewSubmissionItemState {
+    ReadyForReview,
+    Accepted,
+    Approved,
+    Rejected,
+    Removed,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of a review submission item.
+///
+/// An item links a review submission to the resource being reviewed, such as
+/// an App Store version or an in-app event.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewSubmissionItemAttributes {
+    #[serde(default, skip_serializing)]
+    pub state: Option<ReviewSubmissionItemState>,
+}
+
+pub type ReviewSubmissionItem = Resource<ReviewSubmissionItemAttributes>;
@@ -0,0 +1,83 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Auto-renewable subscriptions and their prices.
+
+use {
+    crate::json_api::Resource,
+    serde::{Deserialize, Serialize},
+};
+
+/// Attributes of a subscription group.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionGroupAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_name: Option<String>,
+}
+
+pub type SubscriptionGroup = Resource<SubscriptionGroupAttributes>;
+
+/// The duration of a subscription period.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubscriptionPeriod {
+    OneWeek,
+    OneMonth,
+    TwoMonths,
+    ThreeMonths,
+    SixMonths,
+    OneYear,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of an auto-renewable subscription.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_period: Option<SubscriptionPeriod>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_level: Option<u32>,
+    #[serde(default, skip_serializing)]
+    pub state: Option<String>,
+}
+
+pub type Subscription = Resource<SubscriptionAttributes>;
+
+/// Attributes of a price of a subscription in a territory.
+///
+/// Dates are formatted as `YYYY-MM-DD`. A price without a start date is in
+/// effect until the start of the next price.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionPriceAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+
+    /// Whether existing subscribers keep paying their current price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preserved: Option<bool>,
+}
+
+pub type SubscriptionPrice = Resource<SubscriptionPriceAttributes>;
+
+/// Attributes of a price point of a subscription in a territory.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionPricePointAttributes {
+    #[serde(default, skip_serializing)]
+    pub customer_price: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub proceeds: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub proceeds_year2: Option<String>,
+}
+
+pub type SubscriptionPricePoint = Resource<SubscriptionPricePointAttributes>;
@@ -0,0 +1,191 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! TestFlight beta testing.
+
+use {
+    crate::json_api::Resource,
+    serde::{Deserialize, Serialize},
+};
+
+/// The TestFlight state of a build for internal testers.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InternalBetaState {
+    Processing,
+    ProcessingException,
+    MissingExportCompliance,
+    ReadyForBetaTesting,
+    InBetaTesting,
+    Expired,
+    InExportComplianceReview,
+    #[serde(other)]
+    Unknown,
+}
+
+/// The TestFlight state of a build for external testers.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExternalBetaState {
+    Processing,
+    ProcessingException,
+    MissingExportCompliance,
+    ReadyForBetaTesting,
+    InBetaTesting,
+    Expired,
+    ReadyForBetaSubmission,
+    InExportComplianceReview,
+    WaitingForBetaReview,
+    InBetaReview,
+    BetaRejected,
+    BetaApproved,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of the beta details of a build.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildBetaDetailAttributes {
+    /// Whether testers are automatically notified when the build is available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_notify_enabled: Option<bool>,
+    #[serde(default, skip_serializing)]
+    pub internal_build_state: Option<InternalBetaState>,
+    #[serde(default, skip_serializing)]
+    pub external_build_state: Option<ExternalBetaState>,
+}
+
+pub type BuildBetaDetail = Resource<BuildBetaDetailAttributes>;
+
+/// Attributes of a beta group.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BetaGroupAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_internal_group: Option<bool>,
+    /// Whether internal testers of the group automatically get access to all builds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_access_to_all_builds: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_link_enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_link: Option<String>,
+}
+
+pub type BetaGroup = Resource<BetaGroupAttributes>;
+
+/// How a beta tester was invited.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BetaInviteType {
+    Email,
+    PublicLink,
+    #[serde(other)]
+    Unknown,
+}
+
+/// The state of a beta tester's invitation.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BetaTesterState {
+    NotInvited,
+    Invited,
+    Accepted,
+    Installed,
+    Revoked,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of a beta tester.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BetaTesterAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub invite_type: Option<BetaInviteType>,
+    #[serde(default, skip_serializing)]
+    pub state: Option<BetaTesterState>,
+}
+
+pub type BetaTester = Resource<BetaTesterAttributes>;
+
+/// Attributes of the TestFlight beta license agreement of an app.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BetaLicenseAgreementAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agreement_text: Option<String>,
+}
+
+pub type BetaLicenseAgreement = Resource<BetaLicenseAgreementAttributes>;
+
+/// Attributes of the localized TestFlight information of an app.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BetaAppLocalizationAttributes {
+    /// The locale of the localization. e.g. `en-US`.
+    ///
+    /// Can only be set when creating a localization.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feedback_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marketing_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy_policy_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tv_os_privacy_policy: Option<String>,
+}
+
+pub type BetaAppLocalization = Resource<BetaAppLocalizationAttributes>;
+
+/// Attributes of the localized TestFlight information of a build.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BetaBuildLocalizationAttributes {
+    /// Can only be set when creating a localization.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// The *What to Test* text shown to testers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub whats_new: Option<String>,
+}
+
+pub type BetaBuildLocalization = Resource<BetaBuildLocalizationAttributes>;
+
+/// The state of a submission for TestFlight beta app review.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BetaReviewState {
+    WaitingForReview,
+    InReview,
+    Rejected,
+    Approved,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of a submission for TestFlight beta app review.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BetaAppReviewSubmissionAttributes {
+    #[serde(default, skip_serializing)]
+    pub beta_review_state: Option<BetaReviewState>,
+    #[serde(default, skip_serializing)]
+    pub submitted_date: Option<String>,
+}
+
+pub type BetaAppReviewSubmission = Resource<BetaAppReviewSubmissionAttributes>;
@@ -0,0 +1,51 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Users of a team and their roles.
+
+use {
+    crate::json_api::Resource,
+    serde::{Deserialize, Serialize},
+};
+
+/// A role of a user or API key.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum UserRole {
+    AccountHolder,
+    Admin,
+    AppManager,
+    Developer,
+    Marketing,
+    Sales,
+    Finance,
+    AccessToReports,
+    CustomerSupport,
+    CreateApps,
+    CloudManagedDeveloperId,
+    CloudManagedAppDistribution,
+    GenerateIndividualKeys,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Attributes of a user.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<UserRole>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_apps_visible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provisioning_allowed: Option<bool>,
+}
+
+pub type User = Resource<UserAttributes>;